@@ -0,0 +1,271 @@
+use futures_util::StreamExt;
+use reqwest::{Method, RequestBuilder, Response};
+use serde_json::Value;
+use std::io::Write;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+
+/// How often the state of a followed deployment is polled.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Client of the Nephelios HTTP and WebSocket API.
+pub struct Client {
+    http: reqwest::Client,
+    server: String,
+    token: Option<String>,
+}
+
+impl Client {
+    /// Creates a client.
+    ///
+    /// # Arguments
+    /// * `server` - Base URL of the Nephelios API (e.g., "http://127.0.0.1:3030").
+    /// * `token` - API key or OIDC token sent as a bearer token, if any.
+    pub fn new(server: &str, token: Option<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            server: server.trim_end_matches('/').to_string(),
+            token,
+        }
+    }
+
+    /// Returns the URL of an API path (e.g., "/get-apps").
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/v1{}", self.server, path)
+    }
+
+    /// Builds an authenticated request.
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let builder = self.http.request(method, self.url(path));
+        match &self.token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
+    /// Sends a request, failing with the error message of the API on error statuses.
+    async fn send(&self, builder: RequestBuilder) -> Result<Response, String> {
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach {}: {}", self.server, e))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        Err(format!("{}: {}", status, error_message(&body)))
+    }
+
+    /// Sends a request and parses the JSON body of the response.
+    async fn send_json(&self, builder: RequestBuilder) -> Result<Value, String> {
+        self.send(builder)
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse the response: {}", e))
+    }
+
+    /// Lists the deployed applications.
+    pub async fn list_apps(&self) -> Result<Vec<Value>, String> {
+        let body = self
+            .send_json(self.request(Method::GET, "/get-apps"))
+            .await?;
+        Ok(body["apps"].as_array().cloned().unwrap_or_default())
+    }
+
+    /// Starts the deployment of an application.
+    ///
+    /// # Arguments
+    /// * `body` - The body of `POST /create`.
+    ///
+    /// # Returns
+    /// * `Ok(String)` with the deployment ID.
+    /// * `Err(String)` if the request is refused.
+    pub async fn deploy(&self, body: &Value) -> Result<String, String> {
+        let response = self
+            .send_json(self.request(Method::POST, "/create").json(body))
+            .await?;
+        response["deployment_id"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "The response has no deployment_id".to_string())
+    }
+
+    /// Scales an application to a number of replicas.
+    pub async fn scale(&self, app_name: &str, replicas: u32) -> Result<(), String> {
+        let path = format!("/apps/{}/scale", app_name);
+        self.send(
+            self.request(Method::POST, &path)
+                .json(&serde_json::json!({ "replicas": replicas })),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Returns a deployment, as answered by `GET /deployments/{id}`.
+    pub async fn deployment(&self, deployment_id: &str) -> Result<Value, String> {
+        let path = format!("/deployments/{}", deployment_id);
+        self.send_json(self.request(Method::GET, &path)).await
+    }
+
+    /// Prints the logs of an application to stdout.
+    ///
+    /// # Arguments
+    /// * `app_name` - The name of the application.
+    /// * `tail` - Number of lines to print from the end of the logs, all lines if `None`.
+    /// * `follow` - Whether to keep printing new lines until interrupted.
+    pub async fn logs(
+        &self,
+        app_name: &str,
+        tail: Option<u32>,
+        follow: bool,
+    ) -> Result<(), String> {
+        let path = format!("/apps/{}/logs", app_name);
+        let mut query = vec![("follow", follow.to_string())];
+        if let Some(tail) = tail {
+            query.push(("tail", tail.to_string()));
+        }
+
+        let mut response = self
+            .send(self.request(Method::GET, &path).query(&query))
+            .await?;
+
+        let mut stdout = std::io::stdout();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| format!("Failed to read the logs: {}", e))?
+        {
+            stdout
+                .write_all(&chunk)
+                .and_then(|_| stdout.flush())
+                .map_err(|e| format!("Failed to write the logs: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Prints the progress of a deployment until it finishes.
+    ///
+    /// Steps are read from the `/ws` status stream, the outcome from `GET /deployments/{id}`.
+    ///
+    /// # Arguments
+    /// * `app_name` - The name of the deployed application.
+    /// * `deployment_id` - The deployment to follow.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the deployment succeeded.
+    /// * `Err(String)` with the deployment error if it failed.
+    pub async fn follow_deployment(
+        &self,
+        app_name: &str,
+        deployment_id: &str,
+    ) -> Result<(), String> {
+        let mut status_stream = match self.connect_status_stream(app_name).await {
+            Ok(stream) => Some(stream),
+            Err(e) => {
+                eprintln!("Warning: {}, only the outcome will be shown", e);
+                None
+            }
+        };
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            tokio::select! {
+                message = async { status_stream.as_mut()?.next().await }, if status_stream.is_some() => {
+                    match message {
+                        Some(Ok(Message::Text(text))) => print_status(&text, app_name),
+                        Some(Ok(_)) => {}
+                        Some(Err(_)) | None => status_stream = None,
+                    }
+                }
+                _ = poll.tick() => {
+                    let deployment = self.deployment(deployment_id).await?;
+                    match deployment["state"].as_str() {
+                        Some("succeeded") => return Ok(()),
+                        Some("failed") => {
+                            return Err(deployment["error"]
+                                .as_str()
+                                .unwrap_or("Deployment failed")
+                                .to_string())
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Opens the `/ws` deployment status stream, subscribed to the updates of an app.
+    async fn connect_status_stream(
+        &self,
+        app_name: &str,
+    ) -> Result<
+        tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+        String,
+    > {
+        let url = self
+            .url(&format!("/ws?app={}", app_name))
+            .replacen("http://", "ws://", 1)
+            .replacen("https://", "wss://", 1);
+
+        let mut request = url
+            .into_client_request()
+            .map_err(|e| format!("Invalid WebSocket URL: {}", e))?;
+        if let Some(token) = &self.token {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|e| format!("Invalid token: {}", e))?;
+            request.headers_mut().insert("authorization", value);
+        }
+
+        let (stream, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(|e| format!("Failed to open the status stream: {}", e))?;
+        Ok(stream)
+    }
+}
+
+/// Prints a status update of the `/ws` stream if it concerns the given app.
+fn print_status(text: &str, app_name: &str) {
+    let Ok(status) = serde_json::from_str::<Value>(text) else {
+        return;
+    };
+    if status["app_name"].as_str() != Some(app_name) {
+        return;
+    }
+
+    let marker = match status["status"].as_str() {
+        Some("success") => "✅",
+        Some("error") => "❌",
+        Some("deployed") => return,
+        _ => "⏳",
+    };
+    println!("{} {}", marker, status["step"].as_str().unwrap_or_default());
+}
+
+/// Extracts a readable message from an error body of the API.
+fn error_message(body: &str) -> String {
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return body.trim().to_string();
+    };
+
+    if let Some(errors) = value["errors"].as_array() {
+        return errors
+            .iter()
+            .filter_map(|error| error["message"].as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+    }
+
+    value["error"]
+        .as_str()
+        .or_else(|| value["message"].as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| body.trim().to_string())
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Server used when none is configured.
+pub const DEFAULT_SERVER: &str = "http://127.0.0.1:3030";
+
+/// Settings of the CLI, stored in `~/.config/nephelios/cli.toml`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Base URL of the Nephelios API (e.g., "https://paas.example.com").
+    #[serde(default)]
+    pub server: Option<String>,
+    /// API key or OIDC token sent as a bearer token.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Resolves the path of the CLI configuration file.
+///
+/// # Returns
+/// * `Ok(PathBuf)` - `~/.config/nephelios/cli.toml`.
+/// * `Err(String)` if the home directory cannot be determined.
+pub fn config_path() -> Result<PathBuf, String> {
+    dirs::home_dir()
+        .map(|home| home.join(".config/nephelios/cli.toml"))
+        .ok_or_else(|| "Failed to determine the home directory".to_string())
+}
+
+impl Config {
+    /// Loads the configuration file, or an empty configuration if it does not exist.
+    pub fn load() -> Result<Self, String> {
+        let path = config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    /// Writes the configuration file, readable by the current user only.
+    pub fn save(&self) -> Result<(), String> {
+        let path = config_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize the configuration: {}", e))?;
+        fs::write(&path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+                .map_err(|e| format!("Failed to restrict {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+}
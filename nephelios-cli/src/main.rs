@@ -0,0 +1,191 @@
+//! `neph`, the command-line client of the Nephelios API.
+//!
+//! The server URL and token are read from `--server` / `--token`, the `NEPHELIOS_URL` /
+//! `NEPHELIOS_TOKEN` environment variables, or `~/.config/nephelios/cli.toml`, written with
+//! `neph config`.
+
+mod client;
+mod config;
+
+use crate::client::Client;
+use crate::config::{config_path, Config, DEFAULT_SERVER};
+use clap::{Parser, Subcommand};
+use serde_json::json;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "neph", version, about = "Deploy and manage apps on Nephelios")]
+struct Cli {
+    /// Base URL of the Nephelios API.
+    #[arg(long, env = "NEPHELIOS_URL", global = true)]
+    server: Option<String>,
+    /// API key or OIDC token.
+    #[arg(long, env = "NEPHELIOS_TOKEN", global = true, hide_env_values = true)]
+    token: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Saves the server URL and token used by default.
+    Config {
+        /// Base URL of the Nephelios API to save.
+        #[arg(long = "set-server")]
+        set_server: Option<String>,
+        /// API key or OIDC token to save.
+        #[arg(long = "set-token")]
+        set_token: Option<String>,
+    },
+    /// Deploys an application from a Git repository and follows the deployment.
+    Deploy {
+        /// Name of the application.
+        app_name: String,
+        /// URL of the Git repository.
+        #[arg(long)]
+        repo: String,
+        /// Type of the application.
+        #[arg(long = "type", default_value = "nodejs")]
+        app_type: String,
+        /// Branch, tag or commit to deploy.
+        #[arg(long = "ref")]
+        git_ref: Option<String>,
+        /// Returns once the deployment is queued instead of following it.
+        #[arg(long)]
+        detach: bool,
+    },
+    /// Manages applications.
+    Apps {
+        #[command(subcommand)]
+        command: AppsCommand,
+    },
+    /// Prints the logs of an application.
+    Logs {
+        /// Name of the application.
+        app_name: String,
+        /// Keeps printing new lines until interrupted.
+        #[arg(short, long)]
+        follow: bool,
+        /// Number of lines to print from the end of the logs.
+        #[arg(long)]
+        tail: Option<u32>,
+    },
+    /// Scales an application to a number of replicas.
+    Scale {
+        /// Name of the application.
+        app_name: String,
+        /// Number of replicas.
+        replicas: u32,
+    },
+}
+
+#[derive(Subcommand)]
+enum AppsCommand {
+    /// Lists the deployed applications.
+    List,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs a command.
+async fn run(cli: Cli) -> Result<(), String> {
+    let mut config = Config::load()?;
+
+    if let Command::Config {
+        set_server,
+        set_token,
+    } = cli.command
+    {
+        if set_server.is_some() {
+            config.server = set_server;
+        }
+        if set_token.is_some() {
+            config.token = set_token;
+        }
+        config.save()?;
+        println!("Configuration saved to {}", config_path()?.display());
+        println!(
+            "server: {}",
+            config.server.as_deref().unwrap_or(DEFAULT_SERVER)
+        );
+        println!(
+            "token:  {}",
+            if config.token.is_some() {
+                "set"
+            } else {
+                "not set"
+            }
+        );
+        return Ok(());
+    }
+
+    let server = cli
+        .server
+        .or(config.server)
+        .unwrap_or_else(|| DEFAULT_SERVER.to_string());
+    let client = Client::new(&server, cli.token.or(config.token));
+
+    match cli.command {
+        Command::Config { .. } => Ok(()),
+        Command::Deploy {
+            app_name,
+            repo,
+            app_type,
+            git_ref,
+            detach,
+        } => {
+            let deployment_id = client
+                .deploy(&json!({
+                    "app_name": app_name,
+                    "app_type": app_type,
+                    "github_url": repo,
+                    "git_ref": git_ref,
+                }))
+                .await?;
+            println!("🚀 Deployment {} of {} queued", deployment_id, app_name);
+
+            if detach {
+                return Ok(());
+            }
+            client.follow_deployment(&app_name, &deployment_id).await?;
+            println!("✅ {} deployed", app_name);
+            Ok(())
+        }
+        Command::Apps {
+            command: AppsCommand::List,
+        } => {
+            let apps = client.list_apps().await?;
+            println!("{:<24} {:<10} {:<12} DOMAIN", "NAME", "TYPE", "STATUS");
+            for app in apps {
+                println!(
+                    "{:<24} {:<10} {:<12} {}",
+                    app["app_name"].as_str().unwrap_or_default(),
+                    app["app_type"].as_str().unwrap_or_default(),
+                    app["status"].as_str().unwrap_or_default(),
+                    app["domain"].as_str().unwrap_or_default(),
+                );
+            }
+            Ok(())
+        }
+        Command::Logs {
+            app_name,
+            follow,
+            tail,
+        } => client.logs(&app_name, tail, follow).await,
+        Command::Scale { app_name, replicas } => {
+            client.scale(&app_name, replicas).await?;
+            println!("✅ {} scaled to {} replicas", app_name, replicas);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use crate::services::helpers::oidc_helper::{
+    oidc_config_from_env, token_subject, verify_token, OidcConfig,
+};
+use dirs::home_dir;
+use openssl::memcmp;
+use openssl::rand::rand_bytes;
+use openssl::sha::sha256;
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::{error, info};
+use warp::{reject, Filter, Rejection};
+
+/// The API keys accepted by the management routes, loaded once at startup.
+static API_KEYS: OnceLock<Vec<(Role, String)>> = OnceLock::new();
+
+/// The OIDC provider whose tokens are accepted, if configured.
+static OIDC_CONFIG: OnceLock<Option<OidcConfig>> = OnceLock::new();
+
+/// The access levels of the management routes, from the least to the most privileged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Can list apps and follow deployments.
+    Viewer,
+    /// Can also deploy, start, stop and configure apps.
+    Deployer,
+    /// Can also remove apps.
+    Admin,
+}
+
+impl Role {
+    /// Parses a role name.
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "viewer" => Some(Role::Viewer),
+            "deployer" => Some(Role::Deployer),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// The authenticated caller of a request.
+#[derive(Debug, Clone, Serialize)]
+pub struct Principal {
+    /// `api-key:<fingerprint>` for API keys, the token subject for OIDC tokens.
+    pub name: String,
+    pub role: Role,
+}
+
+/// A request without a valid API key or token, answered with a 401.
+#[derive(Debug)]
+pub struct Unauthorized;
+
+impl reject::Reject for Unauthorized {}
+
+/// A request whose credentials lack the role required by the route, answered with a 403.
+#[derive(Debug)]
+pub struct Forbidden;
+
+impl reject::Reject for Forbidden {}
+
+/// Why a request was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthError {
+    /// No valid API key or token was presented.
+    Unauthorized,
+    /// The presented credentials lack the required role.
+    Forbidden,
+}
+
+/// Resolves the path of the file holding the API keys.
+///
+/// Keys are stored in `NEPHELIOS_API_KEYS_FILE` (default: `~/.config/nephelios/api_keys`),
+/// one per line.
+fn api_keys_path() -> Result<PathBuf, String> {
+    match env::var("NEPHELIOS_API_KEYS_FILE") {
+        Ok(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+        _ => Ok(home_dir()
+            .ok_or("Failed to find home directory")?
+            .join(".config/nephelios/api_keys")),
+    }
+}
+
+/// Parses a list of keys separated by commas or newlines, ignoring blanks and comments.
+///
+/// A key may be prefixed with its role (`viewer:<key>`, `deployer:<key>`), keys without a
+/// role prefix are admin keys.
+fn parse_keys(raw: &str) -> Vec<(Role, String)> {
+    raw.split([',', '\n'])
+        .map(str::trim)
+        .filter(|key| !key.is_empty() && !key.starts_with('#'))
+        .map(|entry| {
+            match entry
+                .split_once(':')
+                .and_then(|(role, key)| Some((Role::parse(role)?, key)))
+            {
+                Some((role, key)) => (role, key.to_string()),
+                None => (Role::Admin, entry.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Generates a random API key.
+fn generate_key() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    rand_bytes(&mut bytes).map_err(|e| format!("Failed to generate API key: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Loads the API keys and the OIDC settings used by the management routes.
+///
+/// Keys are read from `NEPHELIOS_API_KEYS` (comma-separated), or else from the API keys file.
+/// When neither holds a key, a random key is generated and written to the file, readable by
+/// the current user only.
+///
+/// Tokens issued by an OIDC provider are also accepted when `OIDC_ISSUER` and
+/// `OIDC_AUDIENCE` are set.
+///
+/// # Returns
+/// * `Ok(())` if the keys were loaded.
+/// * `Err(String)` if the keys file could not be read or written.
+pub fn init_auth() -> Result<(), String> {
+    let mut keys = parse_keys(&env::var("NEPHELIOS_API_KEYS").unwrap_or_default());
+
+    if keys.is_empty() {
+        let path = api_keys_path()?;
+        if path.exists() {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read API keys file: {}", e))?;
+            keys = parse_keys(&content);
+        }
+
+        if keys.is_empty() {
+            let key = generate_key()?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create API keys directory: {}", e))?;
+            }
+            let mut file = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(&path)
+                .map_err(|e| format!("Failed to create API keys file: {}", e))?;
+            writeln!(file, "{}", key)
+                .map_err(|e| format!("Failed to write API keys file: {}", e))?;
+
+            info!("🔑 Generated an API key in {}", path.display());
+            keys.push((Role::Admin, key));
+        }
+    }
+
+    info!("🔑 {} API key(s) loaded", keys.len());
+    let _ = API_KEYS.set(keys);
+
+    let oidc = oidc_config_from_env();
+    if let Some(config) = &oidc {
+        info!("🔑 Accepting tokens issued by {}", config.issuer);
+    }
+    let _ = OIDC_CONFIG.set(oidc);
+    Ok(())
+}
+
+/// Identifies a presented key, comparing it to the configured keys in constant time.
+///
+/// Keys are named after the first bytes of their SHA-256 digest, so they can be told apart
+/// in logs without being disclosed.
+fn api_key_principal(candidate: &str) -> Option<Principal> {
+    let role = API_KEYS.get()?.iter().find_map(|(role, key)| {
+        (key.len() == candidate.len() && memcmp::eq(key.as_bytes(), candidate.as_bytes()))
+            .then_some(*role)
+    })?;
+    let fingerprint: String = sha256(candidate.as_bytes())[..4]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    Some(Principal {
+        name: format!("api-key:{}", fingerprint),
+        role,
+    })
+}
+
+/// Identifies the caller of a JWT, if OIDC is configured and the token grants a known role.
+async fn token_principal(token: &str) -> Option<Principal> {
+    let config = OIDC_CONFIG.get()?.as_ref()?;
+    match verify_token(config, token).await {
+        Ok(verified) => Some(Principal {
+            role: verified
+                .roles
+                .iter()
+                .filter_map(|role| Role::parse(role))
+                .max()?,
+            name: verified.subject,
+        }),
+        Err(e) => {
+            error!("❌ Rejected OIDC token: {}", e);
+            None
+        }
+    }
+}
+
+/// Checks whether a bearer credential looks like a JWT rather than an API key.
+fn is_jwt(credential: &str) -> bool {
+    credential.matches('.').count() == 2
+}
+
+/// Extracts the key from an `Authorization: Bearer <key>` header value.
+fn bearer_token(header: &str) -> Option<&str> {
+    header
+        .strip_prefix("Bearer ")
+        .or_else(|| header.strip_prefix("bearer "))
+        .map(str::trim)
+}
+
+/// Extracts a parameter from a raw query string.
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&').find_map(|pair| {
+        pair.split_once('=')
+            .filter(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    })
+}
+
+/// Identifies the caller of a request and checks its role against the role required by
+/// the route.
+///
+/// # Arguments
+/// * `required` - The role required by the route.
+/// * `api_keys` - The API keys presented by the request.
+/// * `tokens` - The bearer credentials presented by the request, either JWTs or API keys.
+///
+/// # Returns
+/// * `Ok(Principal)` with the most privileged identity presented, if the request is allowed.
+/// * `Err(AuthError)` when no credential is valid, or when the granted role is too low.
+async fn check_credentials(
+    required: Role,
+    api_keys: Vec<String>,
+    tokens: Vec<String>,
+) -> Result<Principal, AuthError> {
+    let mut principals: Vec<Principal> = api_keys
+        .iter()
+        .filter_map(|key| api_key_principal(key))
+        .collect();
+    for token in &tokens {
+        let principal = if is_jwt(token) {
+            token_principal(token).await
+        } else {
+            api_key_principal(token)
+        };
+        principals.extend(principal);
+    }
+
+    match principals
+        .into_iter()
+        .max_by_key(|principal| principal.role)
+    {
+        Some(principal) if principal.role >= required => Ok(principal),
+        Some(_) => Err(AuthError::Forbidden),
+        None => Err(AuthError::Unauthorized),
+    }
+}
+
+/// Checks the credentials of a request, rejecting it with `Unauthorized` or `Forbidden`.
+async fn authorize(
+    required: Role,
+    api_keys: Vec<String>,
+    tokens: Vec<String>,
+) -> Result<Principal, Rejection> {
+    check_credentials(required, api_keys, tokens)
+        .await
+        .map_err(|e| match e {
+            AuthError::Unauthorized => reject::custom(Unauthorized),
+            AuthError::Forbidden => reject::custom(Forbidden),
+        })
+}
+
+/// Names the caller of a request from its credentials, without checking them against the
+/// OIDC provider.
+///
+/// API keys are named as in `Principal`, unknown keys are not named. JWTs are named
+/// `unverified:<subject>`, as their signature is not checked here and a forged token could
+/// otherwise be attributed to the identity it claims.
+///
+/// # Arguments
+/// * `authorization` - The `authorization` header (`Bearer <key or JWT>`), if any.
+/// * `api_key` - The `x-api-key` header, if any.
+pub fn identify_caller(authorization: Option<&str>, api_key: Option<&str>) -> Option<String> {
+    if let Some(principal) = api_key.and_then(api_key_principal) {
+        return Some(principal.name);
+    }
+
+    let token = authorization.and_then(bearer_token)?;
+    if is_jwt(token) {
+        token_subject(token).map(|subject| format!("unverified:{}", subject))
+    } else {
+        api_key_principal(token).map(|principal| principal.name)
+    }
+}
+
+/// Checks the credentials of a request received outside of Warp (e.g., gRPC metadata).
+///
+/// # Arguments
+/// * `required` - The role required by the operation.
+/// * `authorization` - The `authorization` value (`Bearer <key or JWT>`), if any.
+/// * `api_key` - The `x-api-key` value, if any.
+///
+/// # Returns
+/// * `Ok(Principal)` with the authenticated caller, if the request is allowed.
+/// * `Err(AuthError)` otherwise.
+pub async fn authenticate(
+    required: Role,
+    authorization: Option<&str>,
+    api_key: Option<&str>,
+) -> Result<Principal, AuthError> {
+    let tokens = authorization
+        .and_then(bearer_token)
+        .map(str::to_string)
+        .into_iter()
+        .collect();
+    let api_keys = api_key.map(str::to_string).into_iter().collect();
+    check_credentials(required, api_keys, tokens).await
+}
+
+/// Checks a bearer credential (an API key or a JWT) received outside of a request header,
+/// such as in the first message of a WebSocket connection.
+///
+/// # Arguments
+/// * `required` - The role required by the operation.
+/// * `token` - The API key or JWT.
+///
+/// # Returns
+/// * `Ok(Principal)` with the authenticated caller, if the credential is allowed.
+/// * `Err(AuthError)` otherwise.
+pub async fn authenticate_token(required: Role, token: &str) -> Result<Principal, AuthError> {
+    check_credentials(required, Vec::new(), vec![token.trim().to_string()]).await
+}
+
+/// Requires credentials granting at least the given role on the request, and extracts the
+/// authenticated caller.
+///
+/// Credentials are read from the `Authorization: Bearer <key or JWT>` header or the
+/// `X-API-Key` header.
+///
+/// # Arguments
+/// * `required` - The role required by the route.
+pub fn require_principal(
+    required: Role,
+) -> impl Filter<Extract = (Principal,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and(warp::header::optional::<String>("x-api-key"))
+        .and_then(
+            move |authorization: Option<String>, api_key: Option<String>| async move {
+                let tokens = authorization
+                    .as_deref()
+                    .and_then(bearer_token)
+                    .map(str::to_string)
+                    .into_iter()
+                    .collect();
+                authorize(required, api_key.into_iter().collect(), tokens).await
+            },
+        )
+}
+
+/// Requires credentials granting at least the given role on the request.
+///
+/// # Arguments
+/// * `required` - The role required by the route.
+pub fn require_role(required: Role) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    require_principal(required)
+        .map(|_: Principal| ())
+        .untuple_one()
+}
+
+/// Checks the credentials presented on a WebSocket upgrade, and extracts the authenticated
+/// caller.
+///
+/// Browsers cannot set headers on a WebSocket handshake, so an API key is also accepted in
+/// the `api_key` query parameter and a JWT in the `access_token` query parameter. An upgrade
+/// without credentials is let through with `None`, the connection must then authenticate
+/// with its first message; invalid credentials are rejected.
+///
+/// # Arguments
+/// * `required` - The role required by the route.
+pub fn ws_principal(
+    required: Role,
+) -> impl Filter<Extract = (Option<Principal>,), Error = Rejection> + Clone {
+    warp::query::raw()
+        .or(warp::any().map(String::new))
+        .unify()
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::header::optional::<String>("x-api-key"))
+        .and_then(
+            move |query: String, authorization: Option<String>, api_key: Option<String>| async move {
+                let api_keys = api_key
+                    .into_iter()
+                    .chain(query_param(&query, "api_key").map(str::to_string))
+                    .collect::<Vec<_>>();
+                let tokens = authorization
+                    .as_deref()
+                    .and_then(bearer_token)
+                    .map(str::to_string)
+                    .into_iter()
+                    .chain(query_param(&query, "access_token").map(str::to_string))
+                    .collect::<Vec<_>>();
+                if api_keys.is_empty() && tokens.is_empty() {
+                    return Ok(None);
+                }
+                authorize(required, api_keys, tokens).await.map(Some)
+            },
+        )
+}
+
+/// Requires credentials granting at least the given role on a WebSocket upgrade, read as
+/// in `ws_principal`, for connections that cannot authenticate with a message.
+///
+/// # Arguments
+/// * `required` - The role required by the route.
+pub fn require_ws_role(required: Role) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    ws_principal(required)
+        .and_then(|principal: Option<Principal>| async move {
+            principal
+                .map(|_| ())
+                .ok_or_else(|| reject::custom(Unauthorized))
+        })
+        .untuple_one()
+}
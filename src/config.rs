@@ -0,0 +1,887 @@
+use crate::services::deployment::parse_memory;
+use crate::services::helpers::crypto_helper::parse_master_key;
+use crate::services::helpers::traefik_helper::HttpPolicy;
+use lettre::message::Mailbox;
+use lettre::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+/// Configuration file read when `NEPHELIOS_CONFIG` is not set.
+const DEFAULT_CONFIG_FILE: &str = "./nephelios.toml";
+
+/// The settings of the server, loaded once at startup.
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// The settings of Nephelios, read from `nephelios.toml` then overridden by the
+/// environment.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub domain: DomainConfig,
+    pub registry: RegistryConfig,
+    pub build: BuildConfig,
+    pub github: GithubConfig,
+    pub features: FeaturesConfig,
+    pub backup: BackupConfig,
+    pub smtp: SmtpConfig,
+    pub api_tls: ApiTlsConfig,
+    pub encryption: EncryptionConfig,
+    pub signing: SigningConfig,
+    pub quotas: QuotasConfig,
+    pub acme: AcmeConfig,
+}
+
+/// The `[server]` section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Port of the HTTP API (`NEPHELIOS_PORT`).
+    pub port: u16,
+    /// Port of the gRPC control API (`NEPHELIOS_GRPC_PORT`).
+    pub grpc_port: u16,
+    /// Port apps listen on in their containers (`NEPHELIOS_APPS_PORT`).
+    pub apps_port: u16,
+    /// Address advertised when initializing the Swarm, the listening address if unset
+    /// (`ADVERTISE_ADDR`).
+    pub advertise_addr: Option<String>,
+    /// Whether to leave the Swarm when shutting down (`LEAVE_SWARM`).
+    pub leave_swarm: bool,
+    /// Seconds to wait at startup for the registry, Traefik and the overlay network, before
+    /// accepting deployments (`NEPHELIOS_STARTUP_TIMEOUT`, 0 to skip the wait).
+    pub startup_timeout: u64,
+    /// SQLite database holding the app registry and deployment history,
+    /// `~/.config/nephelios/nephelios.db` if unset (`NEPHELIOS_DB_PATH`).
+    pub database_path: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: 3030,
+            grpc_port: 50051,
+            apps_port: 3000,
+            advertise_addr: None,
+            leave_swarm: false,
+            startup_timeout: 120,
+            database_path: None,
+        }
+    }
+}
+
+/// The `[domain]` section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DomainConfig {
+    /// Apps are served at `<app>.<base>` (`NEPHELIOS_BASE_DOMAIN`).
+    pub base: String,
+    /// How apps handle plain HTTP requests, unless set per app: `serve`, `redirect` or
+    /// `block` (`HTTP_POLICY`). Apps at `localhost` cannot get certificates, keep `serve` for
+    /// them.
+    pub http_policy: HttpPolicy,
+}
+
+impl Default for DomainConfig {
+    fn default() -> Self {
+        Self {
+            base: "localhost".to_string(),
+            http_policy: HttpPolicy::Serve,
+        }
+    }
+}
+
+/// The `[registry]` section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegistryConfig {
+    /// Host of the registry app images are pushed to and pulled from (`NEPHELIOS_REGISTRY`).
+    pub host: String,
+    /// URL of the registry API probed by `/ready`, `http://<host>` if unset
+    /// (`NEPHELIOS_REGISTRY_URL`).
+    pub url: Option<String>,
+}
+
+impl Default for RegistryConfig {
+    fn default() -> Self {
+        Self {
+            host: "registry:5000".to_string(),
+            url: None,
+        }
+    }
+}
+
+impl RegistryConfig {
+    /// Returns the URL of the registry API.
+    pub fn api_url(&self) -> String {
+        self.url
+            .clone()
+            .unwrap_or_else(|| format!("http://{}", self.host))
+    }
+
+    /// Returns the registry repository of an app (e.g., `registry:5000/my-app`).
+    pub fn repository(&self, app_name: &str) -> String {
+        format!("{}/{}", self.host, app_name.to_lowercase())
+    }
+}
+
+/// The `[build]` section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildConfig {
+    /// History depth of repository clones, `0` for full clones (`GIT_CLONE_DEPTH`).
+    pub clone_depth: u32,
+    /// Memory available to an image build, in MB, unlimited if unset
+    /// (`NEPHELIOS_BUILD_MEMORY_MB`).
+    pub memory_mb: Option<u64>,
+    /// CPUs available to an image build, unlimited if unset (`NEPHELIOS_BUILD_CPUS`).
+    pub cpus: Option<f64>,
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            clone_depth: 1,
+            memory_mb: None,
+            cpus: None,
+        }
+    }
+}
+
+/// The `[github]` section. Credentials are read from the environment, see
+/// `credentials_helper`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GithubConfig {
+    /// Base URL of the GitHub API, for commit statuses and App installation tokens, e.g. of a
+    /// GitHub Enterprise server (`GITHUB_API_URL`).
+    pub api_url: String,
+}
+
+impl Default for GithubConfig {
+    fn default() -> Self {
+        Self {
+            api_url: "https://api.github.com".to_string(),
+        }
+    }
+}
+
+/// The `[features]` section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeaturesConfig {
+    /// Whether deploy results are reported as GitHub commit statuses
+    /// (`GITHUB_COMMIT_STATUS`).
+    pub github_commit_status: bool,
+    /// Minutes between two polls of the repositories of apps deployed with `auto_redeploy`,
+    /// `0` to disable polling (`AUTO_REDEPLOY_INTERVAL`).
+    pub auto_redeploy_interval: u64,
+    /// Seconds between two container metrics collections (`METRICS_INTERVAL`).
+    pub metrics_interval: u64,
+    /// Whether an app is rolled back to its previous image when the tasks of a new release
+    /// do not start (`AUTO_ROLLBACK`).
+    pub auto_rollback: bool,
+    /// Seconds the tasks of a new release have to start before it is rolled back
+    /// (`ROLLOUT_TIMEOUT`).
+    pub rollout_timeout: u64,
+    /// Seconds between two evaluations of the apps with autoscaling, `0` to disable the
+    /// autoscaler (`AUTOSCALE_INTERVAL`).
+    pub autoscale_interval: u64,
+    /// Seconds an app is not scaled down after the autoscaler changed its replicas
+    /// (`AUTOSCALE_COOLDOWN`).
+    pub autoscale_cooldown: u64,
+    /// Deployments running at the same time, the others wait in the queue by priority, `0`
+    /// for no limit (`MAX_CONCURRENT_DEPLOYMENTS`).
+    pub max_concurrent_deployments: usize,
+    /// Minutes between two collections of orphaned resources, `0` to disable the collector
+    /// (`GC_INTERVAL`).
+    pub gc_interval: u64,
+    /// Whether the scheduled collections only report the orphaned resources, without
+    /// removing them, on by default (`GC_DRY_RUN`).
+    pub gc_dry_run: bool,
+    /// Days apps removed with `"soft": true` can be restored before they are purged
+    /// (`SOFT_DELETE_RETENTION_DAYS`).
+    pub soft_delete_retention_days: u32,
+    /// Maximum number of replicas an app can be scaled to (`MAX_REPLICAS`).
+    pub max_replicas: u32,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        Self {
+            github_commit_status: true,
+            auto_redeploy_interval: 0,
+            metrics_interval: 15,
+            auto_rollback: true,
+            rollout_timeout: 180,
+            autoscale_interval: 30,
+            autoscale_cooldown: 300,
+            max_concurrent_deployments: 0,
+            gc_interval: 1440,
+            gc_dry_run: true,
+            soft_delete_retention_days: 7,
+            max_replicas: 10,
+        }
+    }
+}
+
+/// The `[backup]` section.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupConfig {
+    /// Directory volume backups are written to, `~/.config/nephelios/volume-backups` if unset
+    /// (`VOLUME_BACKUP_DIR`).
+    pub volume_dir: Option<String>,
+}
+
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Upgraded to TLS with `STARTTLS`, usually on port 587.
+    #[default]
+    Starttls,
+    /// TLS from the start, usually on port 465.
+    Tls,
+    /// Plain text, for a relay on a trusted network.
+    None,
+}
+
+impl FromStr for SmtpSecurity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "starttls" => Ok(SmtpSecurity::Starttls),
+            "tls" => Ok(SmtpSecurity::Tls),
+            "none" => Ok(SmtpSecurity::None),
+            _ => Err(format!("Unknown SMTP security: {}", value)),
+        }
+    }
+}
+
+/// The `[smtp]` section, email notifications are sent when `host` is set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmtpConfig {
+    /// SMTP server notifications are sent through (`SMTP_HOST`).
+    pub host: Option<String>,
+    /// Port of the SMTP server (`SMTP_PORT`).
+    pub port: u16,
+    /// `starttls`, `tls` or `none` (`SMTP_SECURITY`).
+    pub security: SmtpSecurity,
+    /// Credentials, if the server requires them (`SMTP_USERNAME`, `SMTP_PASSWORD`).
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Sender of the notifications, e.g. "Nephelios <nephelios@example.com>" (`SMTP_FROM`).
+    pub from: String,
+    /// Addresses notified about every app, on top of the recipients of each app
+    /// (`SMTP_RECIPIENTS`, comma-separated).
+    pub recipients: Vec<String>,
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        Self {
+            host: None,
+            port: 587,
+            security: SmtpSecurity::default(),
+            username: None,
+            password: None,
+            from: "nephelios@localhost".to_string(),
+            recipients: Vec::new(),
+        }
+    }
+}
+
+/// How the API answers plain HTTP requests when it is served over TLS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlainHttp {
+    /// Redirected to the same path over HTTPS.
+    #[default]
+    Redirect,
+    /// Served over plain HTTP too.
+    Serve,
+    /// Not answered.
+    Disable,
+}
+
+impl FromStr for PlainHttp {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "redirect" => Ok(PlainHttp::Redirect),
+            "serve" => Ok(PlainHttp::Serve),
+            "disable" => Ok(PlainHttp::Disable),
+            _ => Err(format!("Unknown plain HTTP handling: {}", value)),
+        }
+    }
+}
+
+/// The `[api_tls]` section, how the Nephelios API itself is served over TLS.
+///
+/// The API serves TLS itself with `cert_path` and `key_path`, or is exposed by Traefik at
+/// `domain` with a certificate of its ACME resolver. It is served over plain HTTP otherwise.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiTlsConfig {
+    /// PEM certificate chain and private key of the API (`NEPHELIOS_TLS_CERT`,
+    /// `NEPHELIOS_TLS_KEY`).
+    pub cert_path: Option<String>,
+    pub key_path: Option<String>,
+    /// Domain Traefik serves the API at over HTTPS (`NEPHELIOS_API_DOMAIN`).
+    pub domain: Option<String>,
+    /// `redirect`, `serve` or `disable` plain HTTP requests (`NEPHELIOS_PLAIN_HTTP`).
+    pub plain_http: PlainHttp,
+    /// Port plain HTTP requests are answered on when the API serves TLS itself
+    /// (`NEPHELIOS_HTTP_PORT`).
+    pub http_port: u16,
+}
+
+impl Default for ApiTlsConfig {
+    fn default() -> Self {
+        Self {
+            cert_path: None,
+            key_path: None,
+            domain: None,
+            plain_http: PlainHttp::default(),
+            http_port: 3080,
+        }
+    }
+}
+
+impl ApiTlsConfig {
+    /// Returns the certificate and key paths when the API serves TLS itself.
+    pub fn native(&self) -> Option<(&str, &str)> {
+        self.cert_path.as_deref().zip(self.key_path.as_deref())
+    }
+}
+
+/// The `[encryption]` section, the master key encrypting the saved deploy requests and the
+/// webhook secrets.
+///
+/// Only these stores are encrypted: the environment of the apps and the passwords of their
+/// addons are still written in clear into the stack file `nephelios.yml`, which backups
+/// include, as Docker Swarm reads them from there.
+///
+/// Keys are 32 random bytes encoded in base64 (e.g., `openssl rand -base64 32`). To rotate the
+/// master key, set the new key and move the old one to `previous_keys`: values are decrypted
+/// with any of the keys and encrypted again with the new one at startup.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EncryptionConfig {
+    /// The master key (`NEPHELIOS_MASTER_KEY`), secrets are stored in clear if unset.
+    pub master_key: Option<String>,
+    /// File holding the master key, e.g. a Docker secret (`NEPHELIOS_MASTER_KEY_FILE`).
+    pub master_key_file: Option<String>,
+    /// Former master keys, only used to decrypt (`NEPHELIOS_PREVIOUS_MASTER_KEYS`,
+    /// comma-separated).
+    pub previous_keys: Vec<String>,
+}
+
+impl fmt::Debug for EncryptionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |key: &Option<String>| key.as_ref().map(|_| "<redacted>");
+        f.debug_struct("EncryptionConfig")
+            .field("master_key", &redacted(&self.master_key))
+            .field("master_key_file", &self.master_key_file)
+            .field("previous_keys", &self.previous_keys.len())
+            .finish()
+    }
+}
+
+impl EncryptionConfig {
+    /// Returns the master key, read from `master_key_file` if `master_key` is unset.
+    ///
+    /// # Returns
+    /// * `Ok(Some(String))` containing the encoded key.
+    /// * `Ok(None)` if no master key is configured.
+    /// * `Err(String)` if the key file could not be read.
+    pub fn current_key(&self) -> Result<Option<String>, String> {
+        if let Some(key) = &self.master_key {
+            return Ok(Some(key.clone()));
+        }
+        match &self.master_key_file {
+            Some(path) => fs::read_to_string(path)
+                .map(|key| Some(key.trim().to_string()))
+                .map_err(|e| format!("Failed to read master key file {}: {}", path, e)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Whether app images are signed with cosign, and their signatures verified before deploys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SigningPolicy {
+    /// Images are neither signed nor verified.
+    #[default]
+    Disabled,
+    /// Images built by Nephelios are signed after they are pushed.
+    Sign,
+    /// Images are signed, and only images signed by a trusted key are deployed.
+    Enforce,
+}
+
+impl FromStr for SigningPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "disabled" => Ok(SigningPolicy::Disabled),
+            "sign" => Ok(SigningPolicy::Sign),
+            "enforce" => Ok(SigningPolicy::Enforce),
+            _ => Err(format!("Unknown signing policy: {}", value)),
+        }
+    }
+}
+
+/// The `[signing]` section, how app images are signed and verified with cosign.
+///
+/// The password of the signing key is read by cosign from `COSIGN_PASSWORD`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SigningConfig {
+    /// `disabled`, `sign` or `enforce` (`NEPHELIOS_SIGNING_POLICY`).
+    pub policy: SigningPolicy,
+    /// Cosign private key signing the images built by Nephelios (`COSIGN_KEY`).
+    pub key_path: Option<String>,
+    /// Public keys of the signatures accepted when the policy is `enforce`, including the
+    /// public key of `key_path` (`COSIGN_TRUSTED_KEYS`, comma-separated).
+    pub trusted_keys: Vec<String>,
+}
+
+/// Limits of what a user or a team may use, unlimited when unset.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuotaLimits {
+    /// Number of apps.
+    pub max_apps: Option<u32>,
+    /// Total CPU reservations of the apps, in cores, every replica included.
+    pub max_cpus: Option<f64>,
+    /// Total memory reservations of the apps, every replica included (e.g., "8G").
+    pub max_memory: Option<String>,
+    /// Minutes spent deploying the apps in the current month (UTC).
+    pub build_minutes: Option<u64>,
+}
+
+/// A team, whose members share one quota.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TeamConfig {
+    /// The callers in the team, named as in the audit log (`api-key:<fingerprint>` for API
+    /// keys, the token subject for OIDC tokens).
+    pub members: Vec<String>,
+    #[serde(flatten)]
+    pub limits: QuotaLimits,
+}
+
+/// The `[quotas]` section, the limits of each user and team. Admins are not limited.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuotasConfig {
+    /// Limits of each caller outside of a team (`NEPHELIOS_QUOTA_MAX_APPS`,
+    /// `NEPHELIOS_QUOTA_MAX_CPUS`, `NEPHELIOS_QUOTA_MAX_MEMORY`,
+    /// `NEPHELIOS_QUOTA_BUILD_MINUTES`).
+    pub default: QuotaLimits,
+    /// Teams, by name.
+    pub teams: BTreeMap<String, TeamConfig>,
+}
+
+impl QuotasConfig {
+    /// Returns the account a caller's usage is counted against: `team:<name>` for members of
+    /// a team, the caller itself otherwise.
+    pub fn account(&self, caller: &str) -> String {
+        self.teams
+            .iter()
+            .find(|(_, team)| team.members.iter().any(|member| member == caller))
+            .map(|(name, _)| format!("team:{}", name))
+            .unwrap_or_else(|| caller.to_string())
+    }
+
+    /// Returns the limits of an account, as named by `account`.
+    pub fn limits(&self, account: &str) -> &QuotaLimits {
+        account
+            .strip_prefix("team:")
+            .and_then(|name| self.teams.get(name))
+            .map(|team| &team.limits)
+            .unwrap_or(&self.default)
+    }
+}
+
+/// ACME challenge Traefik uses to prove it owns the domains of the apps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AcmeChallengeType {
+    /// TLS-ALPN-01 on the `websecure` entrypoint.
+    #[default]
+    Tls,
+    /// HTTP-01 on the `web` entrypoint.
+    Http,
+    /// DNS-01 through `dns_provider`, required for wildcard certificates.
+    Dns,
+}
+
+impl FromStr for AcmeChallengeType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "tls" => Ok(AcmeChallengeType::Tls),
+            "http" => Ok(AcmeChallengeType::Http),
+            "dns" => Ok(AcmeChallengeType::Dns),
+            _ => Err(format!("Unknown ACME challenge: {}", value)),
+        }
+    }
+}
+
+/// The `[acme]` section, how Traefik gets the certificates of the apps.
+///
+/// The credentials of the DNS provider (e.g., `CF_DNS_API_TOKEN`) are read from the
+/// environment and stored as Docker secrets.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AcmeConfig {
+    /// `tls`, `http` or `dns` (`ACME_CHALLENGE`).
+    pub challenge: AcmeChallengeType,
+    /// The Let's Encrypt account email (`ACME_EMAIL`).
+    pub email: Option<String>,
+    /// The ACME directory, Let's Encrypt staging by default (`ACME_CA_SERVER`).
+    pub ca_server: String,
+    /// The lego provider code of the DNS challenge, e.g. `cloudflare` or `route53`
+    /// (`ACME_DNS_PROVIDER`).
+    pub dns_provider: Option<String>,
+    /// Extra credential variables to forward, for providers without known variables
+    /// (`ACME_DNS_CREDENTIALS`, comma-separated).
+    pub dns_credentials: Vec<String>,
+    /// DNS servers used to check propagation, e.g. `1.1.1.1:53` (`ACME_DNS_RESOLVERS`,
+    /// comma-separated).
+    pub dns_resolvers: Vec<String>,
+    /// Domains to request a `*.domain` certificate for (`ACME_WILDCARD_DOMAINS`,
+    /// comma-separated).
+    pub wildcard_domains: Vec<String>,
+}
+
+impl Default for AcmeConfig {
+    fn default() -> Self {
+        Self {
+            challenge: AcmeChallengeType::default(),
+            email: None,
+            ca_server: "https://acme-staging-v02.api.letsencrypt.org/directory".to_string(),
+            dns_provider: None,
+            dns_credentials: Vec::new(),
+            dns_resolvers: Vec::new(),
+            wildcard_domains: Vec::new(),
+        }
+    }
+}
+
+/// Reads an environment variable, ignoring it when empty.
+fn env_value(var: &str) -> Option<String> {
+    env::var(var).ok().filter(|value| !value.is_empty())
+}
+
+/// Overrides a setting with an environment variable, if it is set and valid.
+fn override_from_env<T: FromStr>(target: &mut T, var: &str) {
+    if let Some(value) = env_value(var) {
+        match value.parse() {
+            Ok(value) => *target = value,
+            Err(_) => warn!("Ignoring invalid {}: {}", var, value),
+        }
+    }
+}
+
+/// Overrides an optional setting with an environment variable, if it is set and valid.
+fn override_option_from_env<T: FromStr>(target: &mut Option<T>, var: &str) {
+    if let Some(value) = env_value(var) {
+        match value.parse() {
+            Ok(value) => *target = Some(value),
+            Err(_) => warn!("Ignoring invalid {}: {}", var, value),
+        }
+    }
+}
+
+/// Overrides a list setting with a comma-separated environment variable, if it is set.
+fn override_list_from_env(target: &mut Vec<String>, var: &str) {
+    if let Some(value) = env_value(var) {
+        *target = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect();
+    }
+}
+
+impl Config {
+    /// Applies the environment variables to the settings.
+    fn apply_env(&mut self) {
+        override_from_env(&mut self.server.port, "NEPHELIOS_PORT");
+        override_from_env(&mut self.server.grpc_port, "NEPHELIOS_GRPC_PORT");
+        override_from_env(&mut self.server.apps_port, "NEPHELIOS_APPS_PORT");
+        override_option_from_env(&mut self.server.advertise_addr, "ADVERTISE_ADDR");
+        override_from_env(&mut self.server.leave_swarm, "LEAVE_SWARM");
+        override_from_env(
+            &mut self.server.startup_timeout,
+            "NEPHELIOS_STARTUP_TIMEOUT",
+        );
+        override_option_from_env(&mut self.server.database_path, "NEPHELIOS_DB_PATH");
+        override_from_env(&mut self.domain.base, "NEPHELIOS_BASE_DOMAIN");
+        override_from_env(&mut self.domain.http_policy, "HTTP_POLICY");
+        override_from_env(&mut self.registry.host, "NEPHELIOS_REGISTRY");
+        override_option_from_env(&mut self.registry.url, "NEPHELIOS_REGISTRY_URL");
+        override_from_env(&mut self.build.clone_depth, "GIT_CLONE_DEPTH");
+        override_option_from_env(&mut self.build.memory_mb, "NEPHELIOS_BUILD_MEMORY_MB");
+        override_option_from_env(&mut self.build.cpus, "NEPHELIOS_BUILD_CPUS");
+        override_from_env(&mut self.github.api_url, "GITHUB_API_URL");
+        override_from_env(
+            &mut self.features.github_commit_status,
+            "GITHUB_COMMIT_STATUS",
+        );
+        override_from_env(
+            &mut self.features.auto_redeploy_interval,
+            "AUTO_REDEPLOY_INTERVAL",
+        );
+        override_from_env(&mut self.features.metrics_interval, "METRICS_INTERVAL");
+        override_from_env(&mut self.features.auto_rollback, "AUTO_ROLLBACK");
+        override_from_env(&mut self.features.rollout_timeout, "ROLLOUT_TIMEOUT");
+        override_from_env(&mut self.features.autoscale_interval, "AUTOSCALE_INTERVAL");
+        override_from_env(&mut self.features.autoscale_cooldown, "AUTOSCALE_COOLDOWN");
+        override_from_env(
+            &mut self.features.max_concurrent_deployments,
+            "MAX_CONCURRENT_DEPLOYMENTS",
+        );
+        override_from_env(&mut self.features.gc_interval, "GC_INTERVAL");
+        override_from_env(&mut self.features.gc_dry_run, "GC_DRY_RUN");
+        override_from_env(
+            &mut self.features.soft_delete_retention_days,
+            "SOFT_DELETE_RETENTION_DAYS",
+        );
+        override_from_env(&mut self.features.max_replicas, "MAX_REPLICAS");
+        override_option_from_env(&mut self.backup.volume_dir, "VOLUME_BACKUP_DIR");
+        override_option_from_env(&mut self.smtp.host, "SMTP_HOST");
+        override_from_env(&mut self.smtp.port, "SMTP_PORT");
+        override_from_env(&mut self.smtp.security, "SMTP_SECURITY");
+        override_option_from_env(&mut self.smtp.username, "SMTP_USERNAME");
+        override_option_from_env(&mut self.smtp.password, "SMTP_PASSWORD");
+        override_from_env(&mut self.smtp.from, "SMTP_FROM");
+        override_option_from_env(&mut self.api_tls.cert_path, "NEPHELIOS_TLS_CERT");
+        override_option_from_env(&mut self.api_tls.key_path, "NEPHELIOS_TLS_KEY");
+        override_option_from_env(&mut self.api_tls.domain, "NEPHELIOS_API_DOMAIN");
+        override_from_env(&mut self.api_tls.plain_http, "NEPHELIOS_PLAIN_HTTP");
+        override_from_env(&mut self.api_tls.http_port, "NEPHELIOS_HTTP_PORT");
+        override_option_from_env(&mut self.encryption.master_key, "NEPHELIOS_MASTER_KEY");
+        override_option_from_env(
+            &mut self.encryption.master_key_file,
+            "NEPHELIOS_MASTER_KEY_FILE",
+        );
+        override_list_from_env(
+            &mut self.encryption.previous_keys,
+            "NEPHELIOS_PREVIOUS_MASTER_KEYS",
+        );
+        override_option_from_env(
+            &mut self.quotas.default.max_apps,
+            "NEPHELIOS_QUOTA_MAX_APPS",
+        );
+        override_option_from_env(
+            &mut self.quotas.default.max_cpus,
+            "NEPHELIOS_QUOTA_MAX_CPUS",
+        );
+        override_option_from_env(
+            &mut self.quotas.default.max_memory,
+            "NEPHELIOS_QUOTA_MAX_MEMORY",
+        );
+        override_option_from_env(
+            &mut self.quotas.default.build_minutes,
+            "NEPHELIOS_QUOTA_BUILD_MINUTES",
+        );
+        override_from_env(&mut self.signing.policy, "NEPHELIOS_SIGNING_POLICY");
+        override_option_from_env(&mut self.signing.key_path, "COSIGN_KEY");
+        override_list_from_env(&mut self.signing.trusted_keys, "COSIGN_TRUSTED_KEYS");
+        override_list_from_env(&mut self.smtp.recipients, "SMTP_RECIPIENTS");
+        override_from_env(&mut self.acme.challenge, "ACME_CHALLENGE");
+        override_option_from_env(&mut self.acme.email, "ACME_EMAIL");
+        override_from_env(&mut self.acme.ca_server, "ACME_CA_SERVER");
+        override_option_from_env(&mut self.acme.dns_provider, "ACME_DNS_PROVIDER");
+        override_list_from_env(&mut self.acme.dns_credentials, "ACME_DNS_CREDENTIALS");
+        override_list_from_env(&mut self.acme.dns_resolvers, "ACME_DNS_RESOLVERS");
+        override_list_from_env(&mut self.acme.wildcard_domains, "ACME_WILDCARD_DOMAINS");
+    }
+
+    /// Checks the settings that cannot be used as-is.
+    fn validate(&self) -> Result<(), String> {
+        let base = &self.domain.base;
+        if base.is_empty() || base.starts_with('.') || base.ends_with('.') {
+            return Err(format!("Invalid base domain: {:?}", base));
+        }
+        if self.registry.host.is_empty() || self.registry.host.contains('/') {
+            return Err(format!("Invalid registry host: {:?}", self.registry.host));
+        }
+        if self.build.cpus.is_some_and(|cpus| cpus <= 0.0) {
+            return Err("build.cpus must be positive".to_string());
+        }
+        if self.features.metrics_interval == 0 {
+            return Err("features.metrics_interval must be positive".to_string());
+        }
+        if self.features.rollout_timeout == 0 {
+            return Err("features.rollout_timeout must be positive".to_string());
+        }
+        if self.features.max_replicas == 0 {
+            return Err("features.max_replicas must be positive".to_string());
+        }
+        let acme = &self.acme;
+        if acme.challenge == AcmeChallengeType::Dns && acme.dns_provider.is_none() {
+            return Err("acme.dns_provider is required for the DNS challenge".to_string());
+        }
+        if !acme.wildcard_domains.is_empty() && acme.challenge != AcmeChallengeType::Dns {
+            return Err("acme.wildcard_domains requires the DNS challenge".to_string());
+        }
+        let encryption = &self.encryption;
+        if encryption.master_key.is_some() && encryption.master_key_file.is_some() {
+            return Err(
+                "encryption.master_key and encryption.master_key_file are exclusive".to_string(),
+            );
+        }
+        let current_key = encryption.current_key()?;
+        if current_key.is_none() && !encryption.previous_keys.is_empty() {
+            return Err("encryption.previous_keys requires a master key".to_string());
+        }
+        for key in current_key.iter().chain(&encryption.previous_keys) {
+            parse_master_key(key)?;
+        }
+        let signing = &self.signing;
+        if signing.policy != SigningPolicy::Disabled && signing.key_path.is_none() {
+            return Err("signing.key_path is required to sign images".to_string());
+        }
+        if signing.policy == SigningPolicy::Enforce && signing.trusted_keys.is_empty() {
+            return Err("signing.trusted_keys is required to verify images".to_string());
+        }
+        for path in signing.key_path.iter().chain(&signing.trusted_keys) {
+            if !Path::new(path).is_file() {
+                return Err(format!("Cosign key {} does not exist", path));
+            }
+        }
+        let mut members = HashSet::new();
+        for (name, team) in &self.quotas.teams {
+            if name.is_empty() || team.members.is_empty() {
+                return Err(format!("quotas.teams.{} must have members", name));
+            }
+            if let Some(member) = team.members.iter().find(|member| !members.insert(*member)) {
+                return Err(format!("{} is a member of several teams", member));
+            }
+        }
+        let team_limits = self
+            .quotas
+            .teams
+            .iter()
+            .map(|(name, team)| (name.as_str(), &team.limits));
+        for (name, limits) in std::iter::once(("default", &self.quotas.default)).chain(team_limits)
+        {
+            if limits.max_cpus.is_some_and(|cpus| cpus <= 0.0) {
+                return Err(format!("quotas {}: max_cpus must be positive", name));
+            }
+            if let Some(memory) = &limits.max_memory {
+                parse_memory(memory).map_err(|e| format!("quotas {}: {}", name, e))?;
+            }
+        }
+        let api_tls = &self.api_tls;
+        if api_tls.cert_path.is_some() != api_tls.key_path.is_some() {
+            return Err("api_tls.cert_path and api_tls.key_path must be set together".to_string());
+        }
+        for path in [&api_tls.cert_path, &api_tls.key_path]
+            .into_iter()
+            .flatten()
+        {
+            if !Path::new(path).is_file() {
+                return Err(format!("TLS file {} does not exist", path));
+            }
+        }
+        if let Some(domain) = &api_tls.domain {
+            if api_tls.native().is_some() {
+                return Err("api_tls.domain cannot be used with api_tls.cert_path".to_string());
+            }
+            if domain.is_empty() || domain.contains(['/', ':', ' ', '`']) {
+                return Err(format!("Invalid API domain: {:?}", domain));
+            }
+        }
+        if api_tls.native().is_some()
+            && api_tls.plain_http != PlainHttp::Disable
+            && api_tls.http_port == self.server.port
+        {
+            return Err("api_tls.http_port must differ from server.port".to_string());
+        }
+        if self.smtp.host.is_some() {
+            self.smtp
+                .from
+                .parse::<Mailbox>()
+                .map_err(|e| format!("Invalid smtp.from {:?}: {}", self.smtp.from, e))?;
+            for recipient in &self.smtp.recipients {
+                recipient
+                    .parse::<Address>()
+                    .map_err(|e| format!("Invalid smtp.recipients {:?}: {}", recipient, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the path of the configuration file, from `NEPHELIOS_CONFIG`.
+fn config_path() -> PathBuf {
+    PathBuf::from(env_value("NEPHELIOS_CONFIG").unwrap_or_else(|| DEFAULT_CONFIG_FILE.to_string()))
+}
+
+/// Reads the configuration file, if any, and applies the environment overrides.
+fn load_config() -> Result<Config, String> {
+    let path = config_path();
+    let mut config = match fs::read_to_string(&path) {
+        Ok(content) => {
+            info!("⚙️ Loading configuration from {}", path.display());
+            toml::from_str(&content)
+                .map_err(|e| format!("Invalid configuration file {}: {}", path.display(), e))?
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Config::default(),
+        Err(e) => {
+            return Err(format!(
+                "Failed to read configuration file {}: {}",
+                path.display(),
+                e
+            ))
+        }
+    };
+    config.apply_env();
+    config.validate()?;
+    Ok(config)
+}
+
+/// Loads the settings at startup, so an invalid configuration is reported before serving.
+///
+/// Settings are read from `nephelios.toml` (or the file named by `NEPHELIOS_CONFIG`), then
+/// overridden by the environment variables named in the documentation of each setting.
+///
+/// # Returns
+/// * `Ok(())` if the settings were loaded.
+/// * `Err(String)` if the configuration file could not be read or is invalid.
+pub fn init_config() -> Result<(), String> {
+    let config = load_config()?;
+    CONFIG
+        .set(config)
+        .map_err(|_| "Configuration already loaded".to_string())
+}
+
+/// Returns the settings of the server.
+///
+/// Falls back to the defaults and the environment if `init_config` was not called.
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        let mut config = Config::default();
+        config.apply_env();
+        config
+    })
+}
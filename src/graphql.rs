@@ -0,0 +1,378 @@
+use crate::auth::{require_role, require_ws_role, Role};
+use crate::metrics::REGISTRY;
+use crate::services::addons::{list_addons_with_status, Addon};
+use crate::services::app_registry::{find_registered_app, list_registered_apps};
+use crate::services::deployment_tracker::{get_deployment, list_app_deployments, Deployment};
+use crate::services::helpers::docker_helper::AppInfo;
+use crate::services::metrics_collector::metric_label;
+use crate::services::websocket::{DeploymentStatus, StatusSender};
+use async_graphql::{Context, EmptyMutation, Json, Object, Schema, SimpleObject, Subscription};
+use async_graphql_warp::{graphql, graphql_subscription, GraphQLResponse};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+use warp::Filter;
+
+/// The GraphQL schema of the dashboard.
+pub type NepheliosSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+/// Builds the GraphQL schema.
+///
+/// # Arguments
+/// * `status_tx` - Sender of the deployment status updates, streamed to subscriptions.
+pub fn build_schema(status_tx: StatusSender) -> NepheliosSchema {
+    Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+        .data(status_tx)
+        .finish()
+}
+
+/// Creates the GraphQL routes.
+///
+/// - `/graphql` (GET, POST): Runs queries, requires the `viewer` role.
+/// - `/graphql/ws` (WebSocket): Runs subscriptions with the `graphql-transport-ws` or
+///   `graphql-ws` protocol. Credentials may be passed as query parameters, see
+///   `auth::require_ws_role`.
+///
+/// Returns a boxed Warp filter that handles GraphQL requests.
+pub fn graphql_route(schema: NepheliosSchema) -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let subscription = warp::path!("graphql" / "ws")
+        .and(require_ws_role(Role::Viewer))
+        .and(graphql_subscription(schema.clone()));
+
+    let query = warp::path!("graphql")
+        .and(require_role(Role::Viewer))
+        .and(graphql(schema))
+        .and_then(
+            |(schema, request): (NepheliosSchema, async_graphql::Request)| async move {
+                Ok::<_, Infallible>(GraphQLResponse::from(schema.execute(request).await))
+            },
+        );
+
+    subscription.or(query).boxed()
+}
+
+/// Formats an optional timestamp as RFC 3339.
+fn rfc3339(time: Option<DateTime<Utc>>) -> Option<String> {
+    time.map(|time| time.to_rfc3339())
+}
+
+/// Returns the serialized name of a unit enum variant (e.g., "in_progress").
+fn variant_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+/// A sample of a container metric.
+#[derive(SimpleObject)]
+pub struct MetricSample {
+    /// The metric name (e.g., "container_cpu_usage").
+    name: String,
+    /// The app of the container.
+    app: String,
+    /// The Swarm task of the container the sample was taken from.
+    container: String,
+    value: f64,
+}
+
+/// Reads the latest collected container metrics, optionally for the containers of a single app.
+async fn container_metrics(app_name: Option<&str>) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for family in REGISTRY.gather() {
+        for metric in family.get_metric() {
+            let (Some(app), Some(task)) =
+                (metric_label(metric, "app"), metric_label(metric, "task"))
+            else {
+                continue;
+            };
+            if app_name.is_some_and(|app_name| app_name != app) {
+                continue;
+            }
+
+            samples.push(MetricSample {
+                name: family.get_name().to_string(),
+                app: app.to_string(),
+                container: task.to_string(),
+                value: metric.get_gauge().get_value(),
+            });
+        }
+    }
+    samples
+}
+
+/// A deployed app.
+pub struct App(AppInfo);
+
+#[Object]
+impl App {
+    async fn name(&self) -> &str {
+        &self.0.app_name
+    }
+
+    async fn app_type(&self) -> &str {
+        &self.0.app_type
+    }
+
+    async fn github_url(&self) -> &str {
+        &self.0.github_url
+    }
+
+    /// The default domain of the app.
+    async fn domain(&self) -> &str {
+        &self.0.domain
+    }
+
+    /// Custom domains served in addition to the default domain.
+    async fn domains(&self) -> &[String] {
+        &self.0.domains
+    }
+
+    async fn created_at(&self) -> &str {
+        &self.0.created_at
+    }
+
+    async fn status(&self) -> &str {
+        &self.0.status
+    }
+
+    async fn git_ref(&self) -> Option<&str> {
+        self.0.git_ref.as_deref()
+    }
+
+    async fn commit_sha(&self) -> Option<&str> {
+        self.0.commit_sha.as_deref()
+    }
+
+    async fn commit_message(&self) -> Option<&str> {
+        self.0.commit_message.as_deref()
+    }
+
+    /// The deployments of the app: running ones first, then the history, most recent first.
+    async fn deployments(
+        &self,
+        #[graphql(default = 20)] limit: usize,
+    ) -> async_graphql::Result<Vec<DeploymentNode>> {
+        Ok(list_app_deployments(&self.0.app_name)?
+            .into_iter()
+            .take(limit)
+            .map(DeploymentNode)
+            .collect())
+    }
+
+    /// The latest metrics of the containers of the app.
+    async fn metrics(&self) -> Vec<MetricSample> {
+        container_metrics(Some(&self.0.app_name)).await
+    }
+
+    /// The addons of the app, with the status of their service.
+    async fn addons(&self) -> async_graphql::Result<Vec<AddonNode>> {
+        Ok(list_addons_with_status(&self.0.app_name)
+            .await?
+            .into_iter()
+            .map(AddonNode)
+            .collect())
+    }
+}
+
+/// A database or cache deployed next to an app.
+pub struct AddonNode(Addon);
+
+#[Object(name = "Addon")]
+impl AddonNode {
+    /// `postgres`, `redis`, `mysql` or `mariadb`.
+    async fn addon_type(&self) -> &str {
+        self.0.addon_type.as_str()
+    }
+
+    /// The service of the addon, also its hostname on the overlay network.
+    async fn service(&self) -> &str {
+        &self.0.service
+    }
+
+    async fn image(&self) -> Option<&str> {
+        self.0.image.as_deref()
+    }
+
+    /// The volume holding the data of the addon, if it is persisted.
+    async fn volume(&self) -> Option<&str> {
+        self.0.volume.as_deref()
+    }
+
+    /// The environment variable of the app holding the connection URL.
+    async fn env_var(&self) -> &str {
+        &self.0.env_var
+    }
+
+    /// `running`, `starting`, `stopped`, `missing` or `unknown`.
+    async fn status(&self) -> Option<&str> {
+        self.0.status.as_deref()
+    }
+}
+
+/// A deployment or rollback job.
+pub struct DeploymentNode(Deployment);
+
+#[Object(name = "Deployment")]
+impl DeploymentNode {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn app_name(&self) -> &str {
+        &self.0.app_name
+    }
+
+    /// Who or what triggered the deployment.
+    async fn initiator(&self) -> &str {
+        &self.0.initiator
+    }
+
+    /// `deploy` or `rollback`.
+    async fn kind(&self) -> String {
+        variant_name(&self.0.kind)
+    }
+
+    /// `queued`, `in_progress`, `succeeded`, `failed` or `cancelled`.
+    async fn state(&self) -> String {
+        variant_name(&self.0.state)
+    }
+
+    /// The last step reported for the deployment.
+    async fn stage(&self) -> Option<&str> {
+        self.0.stage.as_deref()
+    }
+
+    async fn created_at(&self) -> String {
+        self.0.created_at.to_rfc3339()
+    }
+
+    async fn started_at(&self) -> Option<String> {
+        rfc3339(self.0.started_at)
+    }
+
+    async fn finished_at(&self) -> Option<String> {
+        rfc3339(self.0.finished_at)
+    }
+
+    async fn duration_ms(&self) -> Option<i64> {
+        self.0.duration_ms
+    }
+
+    async fn git_ref(&self) -> Option<&str> {
+        self.0.git_ref.as_deref()
+    }
+
+    async fn commit_sha(&self) -> Option<&str> {
+        self.0.commit_sha.as_deref()
+    }
+
+    async fn image(&self) -> Option<&str> {
+        self.0.image.as_deref()
+    }
+
+    async fn error(&self) -> Option<&str> {
+        self.0.error.as_deref()
+    }
+
+    /// The deployed application details, once the deployment succeeded.
+    async fn result(&self) -> Option<Json<Value>> {
+        self.0.result.clone().map(Json)
+    }
+}
+
+/// A deployment status update.
+pub struct DeploymentStatusEvent(DeploymentStatus);
+
+#[Object(name = "DeploymentStatus")]
+impl DeploymentStatusEvent {
+    async fn app_name(&self) -> &str {
+        &self.0.app_name
+    }
+
+    /// `in_progress`, `success`, `deployed` or `error`.
+    async fn status(&self) -> &str {
+        &self.0.status
+    }
+
+    /// The reported step, or the error message for errors.
+    async fn step(&self) -> &str {
+        &self.0.step
+    }
+
+    /// The typed event, identified by its `type` (e.g., "build_started").
+    async fn event(&self) -> Json<Value> {
+        Json(serde_json::to_value(&self.0.event).unwrap_or_default())
+    }
+
+    async fn timestamp(&self) -> String {
+        self.0.timestamp.to_rfc3339()
+    }
+
+    async fn deployment_id(&self) -> Option<&str> {
+        self.0.deployment_id.as_deref()
+    }
+
+    /// The deployed application details, sent with the final update.
+    async fn app_deployed(&self) -> Option<Json<Value>> {
+        self.0.app_deployed.clone().map(Json)
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The deployed apps.
+    async fn apps(&self) -> async_graphql::Result<Vec<App>> {
+        Ok(list_registered_apps()?.into_iter().map(App).collect())
+    }
+
+    /// A deployed app by name.
+    async fn app(&self, name: String) -> async_graphql::Result<Option<App>> {
+        Ok(find_registered_app(&name)?.map(App))
+    }
+
+    /// A deployment by ID.
+    async fn deployment(&self, id: String) -> Option<DeploymentNode> {
+        get_deployment(&id).map(DeploymentNode)
+    }
+
+    /// The latest metrics of every container of the stack.
+    async fn metrics(&self) -> Vec<MetricSample> {
+        container_metrics(None).await
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// Deployment status updates, of every app or of a single app.
+    async fn deployment_status(
+        &self,
+        ctx: &Context<'_>,
+        app_name: Option<String>,
+    ) -> impl Stream<Item = DeploymentStatusEvent> {
+        let status_rx = ctx.data_unchecked::<StatusSender>().subscribe();
+        futures::stream::unfold(status_rx, |mut status_rx| async move {
+            loop {
+                match status_rx.recv().await {
+                    Ok(status) => return Some((status, status_rx)),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+        .filter(move |status| {
+            let matches = app_name
+                .as_ref()
+                .is_none_or(|app_name| app_name == &status.app_name);
+            async move { matches }
+        })
+        .map(DeploymentStatusEvent)
+    }
+}
@@ -0,0 +1,516 @@
+//! gRPC control API, mirroring the main REST operations.
+//!
+//! The service is described in `proto/nephelios.proto`. Its messages are declared here
+//! with `prost` derives so the build does not depend on `protoc`, keep both in sync.
+
+// `tonic::Status` is the error type of every gRPC handler.
+#![allow(clippy::result_large_err)]
+
+use crate::auth::{authenticate, AuthError, Principal, Role};
+use crate::requests::{
+    AppActionRequest, CreateAppRequest, EnvRequest, ScaleRequest, Validate, ValidationErrors,
+};
+use crate::services::app_registry::list_registered_apps;
+use crate::services::deployment::{
+    check_app_name_available, load_app_request, load_deploy_request, ResourceOverrides,
+};
+use crate::services::deployment_tracker::{get_deployment, spawn_deployment};
+use crate::services::events::{publish, Event};
+use crate::services::helpers::docker_helper::scale_service;
+use crate::services::helpers::traefik_helper::update_app_replicas;
+use crate::services::quotas::{enforce_quota, AppPlan, QuotaError};
+use crate::services::soft_delete::delete_app;
+use crate::services::websocket::StatusSender;
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::broadcast::error::RecvError;
+use tonic::body::BoxBody;
+use tonic::codec::ProstCodec;
+use tonic::codegen::{http, BoxFuture, Service};
+use tonic::server::{Grpc, NamedService};
+use tonic::{Request, Response, Status};
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListAppsRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListAppsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub apps: Vec<App>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct App {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub app_type: String,
+    #[prost(string, tag = "3")]
+    pub github_url: String,
+    #[prost(string, tag = "4")]
+    pub domain: String,
+    #[prost(string, tag = "5")]
+    pub status: String,
+    #[prost(string, tag = "6")]
+    pub created_at: String,
+    #[prost(string, optional, tag = "7")]
+    pub git_ref: Option<String>,
+    #[prost(string, optional, tag = "8")]
+    pub commit_sha: Option<String>,
+    #[prost(string, repeated, tag = "9")]
+    pub domains: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CreateAppRequestMessage {
+    #[prost(string, tag = "1")]
+    pub app_name: String,
+    #[prost(string, tag = "2")]
+    pub app_type: String,
+    #[prost(string, tag = "3")]
+    pub github_url: String,
+    #[prost(string, optional, tag = "4")]
+    pub git_ref: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub install_command: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub build_command: Option<String>,
+    #[prost(string, optional, tag = "7")]
+    pub run_command: Option<String>,
+    #[prost(string, optional, tag = "8")]
+    pub app_workdir: Option<String>,
+    #[prost(map = "string, string", tag = "9")]
+    pub env: HashMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CreateAppResponse {
+    #[prost(string, tag = "1")]
+    pub deployment_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RemoveAppRequest {
+    #[prost(string, tag = "1")]
+    pub app_name: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RemoveAppResponse {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ScaleAppRequest {
+    #[prost(string, tag = "1")]
+    pub app_name: String,
+    #[prost(uint32, tag = "2")]
+    pub replicas: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ScaleAppResponse {
+    #[prost(string, tag = "1")]
+    pub app_name: String,
+    #[prost(uint32, tag = "2")]
+    pub replicas: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetDeploymentRequest {
+    #[prost(string, tag = "1")]
+    pub deployment_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Deployment {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub app_name: String,
+    #[prost(string, tag = "3")]
+    pub initiator: String,
+    #[prost(string, tag = "4")]
+    pub kind: String,
+    #[prost(string, tag = "5")]
+    pub state: String,
+    #[prost(string, optional, tag = "6")]
+    pub stage: Option<String>,
+    #[prost(string, tag = "7")]
+    pub created_at: String,
+    #[prost(string, optional, tag = "8")]
+    pub started_at: Option<String>,
+    #[prost(string, optional, tag = "9")]
+    pub finished_at: Option<String>,
+    #[prost(int64, optional, tag = "10")]
+    pub duration_ms: Option<i64>,
+    #[prost(string, optional, tag = "11")]
+    pub git_ref: Option<String>,
+    #[prost(string, optional, tag = "12")]
+    pub commit_sha: Option<String>,
+    #[prost(string, optional, tag = "13")]
+    pub image: Option<String>,
+    #[prost(string, optional, tag = "14")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WatchDeploymentsRequest {
+    #[prost(string, optional, tag = "1")]
+    pub app_name: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DeploymentStatus {
+    #[prost(string, tag = "1")]
+    pub app_name: String,
+    #[prost(string, tag = "2")]
+    pub status: String,
+    #[prost(string, tag = "3")]
+    pub step: String,
+    #[prost(int64, tag = "4")]
+    pub timestamp_ms: i64,
+    #[prost(string, optional, tag = "5")]
+    pub deployment_id: Option<String>,
+}
+
+/// Stream of deployment status updates answered by `WatchDeployments`.
+type StatusStream = Pin<Box<dyn Stream<Item = Result<DeploymentStatus, Status>> + Send>>;
+
+/// Adapts an async function into the service expected by `tonic::server::Grpc`.
+struct Method<F>(F);
+
+impl<F, Fut, Req, Res> Service<Request<Req>> for Method<F>
+where
+    F: FnMut(Request<Req>) -> Fut,
+    Fut: Future<Output = Result<Response<Res>, Status>>,
+{
+    type Response = Response<Res>;
+    type Error = Status;
+    type Future = Fut;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Status>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Req>) -> Fut {
+        (self.0)(request)
+    }
+}
+
+/// The `nephelios.v1.Control` gRPC service.
+#[derive(Clone)]
+pub struct ControlServer {
+    status_tx: StatusSender,
+}
+
+impl ControlServer {
+    /// Creates the service.
+    ///
+    /// # Arguments
+    /// * `status_tx` - Sender of the deployment status updates, streamed to watchers.
+    pub fn new(status_tx: StatusSender) -> Self {
+        Self { status_tx }
+    }
+}
+
+impl NamedService for ControlServer {
+    const NAME: &'static str = "nephelios.v1.Control";
+}
+
+impl Service<http::Request<BoxBody>> for ControlServer {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+        let status_tx = self.status_tx.clone();
+        Box::pin(async move {
+            let response = match req.uri().path() {
+                "/nephelios.v1.Control/ListApps" => {
+                    Grpc::new(ProstCodec::default())
+                        .unary(Method(list_apps), req)
+                        .await
+                }
+                "/nephelios.v1.Control/CreateApp" => {
+                    Grpc::new(ProstCodec::default())
+                        .unary(Method(create_app), req)
+                        .await
+                }
+                "/nephelios.v1.Control/RemoveApp" => {
+                    Grpc::new(ProstCodec::default())
+                        .unary(Method(remove_app), req)
+                        .await
+                }
+                "/nephelios.v1.Control/ScaleApp" => {
+                    Grpc::new(ProstCodec::default())
+                        .unary(Method(scale_app), req)
+                        .await
+                }
+                "/nephelios.v1.Control/GetDeployment" => {
+                    Grpc::new(ProstCodec::default())
+                        .unary(Method(get_deployment_status), req)
+                        .await
+                }
+                "/nephelios.v1.Control/WatchDeployments" => {
+                    Grpc::new(ProstCodec::default())
+                        .server_streaming(
+                            Method(move |request| watch_deployments(request, status_tx.clone())),
+                            req,
+                        )
+                        .await
+                }
+                _ => Status::unimplemented("Unknown method").into_http(),
+            };
+            Ok(response)
+        })
+    }
+}
+
+/// Authenticates a call from its `authorization` or `x-api-key` metadata.
+///
+/// # Arguments
+/// * `request` - The incoming call.
+/// * `required` - The role required by the method.
+///
+/// # Returns
+/// * `Ok(Principal)` with the authenticated caller.
+/// * `Err(Status)` with `UNAUTHENTICATED` or `PERMISSION_DENIED`.
+async fn authorize_call<T>(request: &Request<T>, required: Role) -> Result<Principal, Status> {
+    let metadata = request.metadata();
+    let value = |key: &str| metadata.get(key).and_then(|value| value.to_str().ok());
+
+    authenticate(required, value("authorization"), value("x-api-key"))
+        .await
+        .map_err(|e| match e {
+            AuthError::Unauthorized => Status::unauthenticated("Missing or invalid API key"),
+            AuthError::Forbidden => Status::permission_denied(format!(
+                "This operation requires the {:?} role",
+                required
+            )),
+        })
+}
+
+/// Validates a request, answering `INVALID_ARGUMENT` with the field errors.
+fn validate(request: &impl Validate) -> Result<(), Status> {
+    request.validate().map_err(|ValidationErrors { errors }| {
+        let message = errors
+            .iter()
+            .map(|error| error.message.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Status::invalid_argument(message)
+    })
+}
+
+/// Checks a change to an app against the quota of its owner, answering `RESOURCE_EXHAUSTED`
+/// if the change would exceed it.
+fn check_quota(principal: &Principal, plan: &AppPlan) -> Result<(), Status> {
+    enforce_quota(Some(principal), plan).map_err(|e| match e {
+        QuotaError::Exceeded(e) => Status::resource_exhausted(e),
+        QuotaError::Failed(e) => Status::internal(format!("Failed to check quota: {}", e)),
+    })
+}
+
+/// Returns the serialized name of a unit enum variant (e.g., "in_progress").
+fn variant_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+async fn list_apps(
+    request: Request<ListAppsRequest>,
+) -> Result<Response<ListAppsResponse>, Status> {
+    authorize_call(&request, Role::Viewer).await?;
+
+    let apps = list_registered_apps()
+        .map_err(|e| Status::internal(format!("Failed to list apps: {}", e)))?
+        .into_iter()
+        .map(|app| App {
+            name: app.app_name,
+            app_type: app.app_type,
+            github_url: app.github_url,
+            domain: app.domain,
+            status: app.status,
+            created_at: app.created_at,
+            git_ref: app.git_ref,
+            commit_sha: app.commit_sha,
+            domains: app.domains,
+        })
+        .collect();
+
+    Ok(Response::new(ListAppsResponse { apps }))
+}
+
+async fn create_app(
+    request: Request<CreateAppRequestMessage>,
+) -> Result<Response<CreateAppResponse>, Status> {
+    let principal = authorize_call(&request, Role::Deployer).await?;
+    let message = request.into_inner();
+
+    let env = EnvRequest {
+        env: message.env.into_iter().collect::<BTreeMap<_, _>>(),
+    };
+    validate(&env)?;
+
+    let body = CreateAppRequest {
+        app_name: message.app_name,
+        app_type: if message.app_type.is_empty() {
+            "nodejs".to_string()
+        } else {
+            message.app_type
+        },
+        github_url: message.github_url,
+        git_ref: message.git_ref,
+        git_token: None,
+        deploy_key: None,
+        clone_depth: None,
+        recurse_submodules: false,
+        auto_redeploy: false,
+        install_command: message.install_command,
+        run_command: message.run_command,
+        build_command: message.build_command,
+        app_workdir: message.app_workdir,
+        additional_inputs: Vec::new(),
+        protocol: None,
+        resources: ResourceOverrides::default(),
+        placement: None,
+        update_config: None,
+        security: None,
+        volumes: None,
+        priority: None,
+    };
+    validate(&body)?;
+    check_app_name_available(&body.app_name, &body.github_url)
+        .await
+        .map_err(Status::invalid_argument)?;
+
+    let previous = load_deploy_request(&body.app_name);
+    let mut deploy_request = body.into_deploy_request(previous);
+    deploy_request.env.extend(env.env);
+    check_quota(&principal, &AppPlan::deploy(&deploy_request))?;
+    let deployment_id = spawn_deployment(deploy_request, &principal.name);
+
+    Ok(Response::new(CreateAppResponse { deployment_id }))
+}
+
+async fn remove_app(
+    request: Request<RemoveAppRequest>,
+) -> Result<Response<RemoveAppResponse>, Status> {
+    authorize_call(&request, Role::Admin).await?;
+    let body = AppActionRequest {
+        app_name: request.into_inner().app_name,
+    };
+    validate(&body)?;
+    delete_app(&body.app_name).await.map_err(Status::internal)?;
+
+    Ok(Response::new(RemoveAppResponse {}))
+}
+
+async fn scale_app(
+    request: Request<ScaleAppRequest>,
+) -> Result<Response<ScaleAppResponse>, Status> {
+    let principal = authorize_call(&request, Role::Deployer).await?;
+    let ScaleAppRequest { app_name, replicas } = request.into_inner();
+    validate(&ScaleRequest { replicas })?;
+
+    let deploy_request = load_app_request(&app_name)
+        .await
+        .map_err(Status::not_found)?;
+    check_quota(
+        &principal,
+        &AppPlan {
+            builds: false,
+            replicas,
+            ..AppPlan::deploy(&deploy_request)
+        },
+    )?;
+
+    update_app_replicas(&app_name, replicas).map_err(|e| {
+        Status::internal(format!(
+            "Failed to update replicas for app {}: {}",
+            app_name, e
+        ))
+    })?;
+    scale_service(&app_name, replicas)
+        .await
+        .map_err(|e| Status::internal(format!("Failed to scale app {}: {}", app_name, e)))?;
+
+    publish(Event::AppScaled {
+        app_name: app_name.clone(),
+        replicas,
+    });
+    Ok(Response::new(ScaleAppResponse { app_name, replicas }))
+}
+
+async fn get_deployment_status(
+    request: Request<GetDeploymentRequest>,
+) -> Result<Response<Deployment>, Status> {
+    authorize_call(&request, Role::Viewer).await?;
+    let deployment_id = request.into_inner().deployment_id;
+
+    let deployment = get_deployment(&deployment_id)
+        .ok_or_else(|| Status::not_found(format!("Deployment {} not found", deployment_id)))?;
+
+    Ok(Response::new(Deployment {
+        kind: variant_name(&deployment.kind),
+        state: variant_name(&deployment.state),
+        id: deployment.id,
+        app_name: deployment.app_name,
+        initiator: deployment.initiator,
+        stage: deployment.stage,
+        created_at: deployment.created_at.to_rfc3339(),
+        started_at: deployment.started_at.map(|time| time.to_rfc3339()),
+        finished_at: deployment.finished_at.map(|time| time.to_rfc3339()),
+        duration_ms: deployment.duration_ms,
+        git_ref: deployment.git_ref,
+        commit_sha: deployment.commit_sha,
+        image: deployment.image,
+        error: deployment.error,
+    }))
+}
+
+async fn watch_deployments(
+    request: Request<WatchDeploymentsRequest>,
+    status_tx: StatusSender,
+) -> Result<Response<StatusStream>, Status> {
+    authorize_call(&request, Role::Viewer).await?;
+    let app_name = request.into_inner().app_name;
+
+    let stream = futures::stream::unfold(status_tx.subscribe(), |mut status_rx| async move {
+        loop {
+            match status_rx.recv().await {
+                Ok(status) => return Some((status, status_rx)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .filter(move |status| {
+        let matches = app_name
+            .as_ref()
+            .is_none_or(|app_name| app_name == &status.app_name);
+        async move { matches }
+    })
+    .map(|status| {
+        Ok(DeploymentStatus {
+            app_name: status.app_name,
+            status: status.status,
+            step: status.step,
+            timestamp_ms: status.timestamp.timestamp_millis(),
+            deployment_id: status.deployment_id,
+        })
+    });
+
+    Ok(Response::new(Box::pin(stream) as StatusStream))
+}
@@ -1,29 +1,213 @@
+// The OpenAPI document is built with large `json!` literals
+#![recursion_limit = "512"]
+
+mod auth;
+mod config;
+mod graphql;
+mod grpc;
+mod openapi;
+mod requests;
 mod routes;
 mod services;
 
+use crate::auth::init_auth;
+use crate::config::{config, init_config, PlainHttp};
+use crate::graphql::{build_schema, graphql_route};
+use crate::grpc::ControlServer;
 use crate::routes::{
-    create_app_route, create_metrics_route, get_apps_route, health_check_route, remove_app_route,
-    start_app_route, stop_app_route,
+    alert_rules_route, alerts_route, app_addons_route, app_autoscaling_route, app_bulk_route,
+    app_cron_jobs_route, app_deployments_route, app_domains_route, app_env_route, app_exec_route,
+    app_http_policy_route, app_ip_allowlist_route, app_jobs_route, app_logs_route,
+    app_maintenance_route, app_metrics_route, app_middlewares_route, app_notifications_route,
+    app_placement_route, app_ports_route, app_protocol_route, app_redeploy_route,
+    app_resources_route, app_restart_route, app_restore_route, app_rollback_route, app_scale_route,
+    app_scaling_schedule_route, app_secrets_route, app_security_route, app_sticky_sessions_route,
+    app_update_config_route, app_update_route, app_volumes_route, app_webhooks_route, audit_route,
+    backup_route, create_app_route, create_metrics_route, deployment_cancel_route,
+    deployment_priority_route, deployment_queue_route, deployment_status_route, docs_route,
+    get_apps_route, github_webhook_route, handle_rejection, health_check_route,
+    https_redirect_route, node_activate_route, node_drain_route, node_join_token_route,
+    node_labels_route, nodes_route, openapi_route, quota_route, readiness_route, remove_app_route,
+    restore_route, start_app_route, stop_app_route, system_gc_route, system_reconcile_route,
+    templates_route, webhooks_route,
+};
+use crate::services::alerting::run_alert_notifier;
+use crate::services::app_registry::run_app_registry_sync;
+use crate::services::audit_log::{record_request, run_audit_recorder};
+use crate::services::auto_redeploy::run_auto_redeploy;
+use crate::services::autoscaler::run_autoscaler;
+use crate::services::cron_jobs::{fail_interrupted_cron_runs, run_cron_scheduler};
+use crate::services::database::init_database;
+use crate::services::deployment::reencrypt_deploy_requests;
+use crate::services::deployment_history::{fail_interrupted_deployments, import_history_files};
+use crate::services::email_notifications::run_email_notifier;
+use crate::services::garbage_collection::run_garbage_collector;
+use crate::services::helpers::acme_helper::{configure_api_route, configure_certificate_resolver};
+use crate::services::helpers::crypto_helper::encryption_enabled;
+use crate::services::helpers::readiness_helper::wait_for_readiness;
+use crate::services::jobs::fail_interrupted_jobs;
+use crate::services::metrics_collector::{run_disk_metrics_collector, run_metrics_collector};
+use crate::services::reconciliation::reconcile;
+use crate::services::scheduled_scaling::run_scaling_scheduler;
+use crate::services::soft_delete::run_soft_delete_purge;
+use crate::services::webhooks::{reencrypt_webhook_secrets, run_webhook_dispatcher};
+use crate::services::websocket::{
+    run_status_broadcaster, ws_logs_route, ws_metrics_route, ws_nodes_route, ws_route,
 };
-use crate::services::websocket::ws_route;
 
 use crate::services::helpers::docker_helper::{
     check_swarm, connect_to_overlay_network, deploy_nephelios_stack,
     disconnect_from_overlay_network, init_swarm, leave_swarm, prune_images, stop_nephelios_stack,
 };
-use std::env;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
 use tokio::sync::broadcast;
-use warp::http::Method;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use warp::http::{Method, StatusCode};
 use warp::Filter;
 mod metrics;
-use crate::metrics::{CONTAINER_CPU, CONTAINER_MEM, CONTAINER_NET_IN, CONTAINER_NET_OUT, REGISTRY};
+use crate::metrics::{
+    APP_IMAGE_SIZE, APP_REPLICAS_DESIRED, APP_REPLICAS_RUNNING, APP_UP, BUILD_CACHE_SIZE,
+    CONTAINER_CPU, CONTAINER_MEM, CONTAINER_NET_IN, CONTAINER_NET_OUT, DEPLOYMENTS,
+    DEPLOYMENTS_QUEUED, DEPLOY_STAGE_DURATION, HOST_DISK_FREE, HOST_DISK_TOTAL, HTTP_REQUESTS,
+    HTTP_REQUEST_DURATION, REGISTRY, SWARM_NODES, SWARM_NODE_CPUS, SWARM_NODE_CPUS_RESERVED,
+    SWARM_NODE_MEMORY, SWARM_NODE_MEMORY_RESERVED, SWARM_NODE_READY, SWARM_NODE_TASKS,
+    VOLUME_USAGE, WEBSOCKET_CLIENTS,
+};
+
+/// Default log filter when `RUST_LOG` is not set.
+const DEFAULT_LOG_FILTER: &str = "info,warp=warn";
+
+/// Installs the log subscriber, filtered with `RUST_LOG` (e.g., `debug`, `nephelios=debug,warp=info`).
+///
+/// Lines are written as text, or as one JSON object per line when `NEPHELIOS_LOG_FORMAT` is
+/// `json`. JSON lines carry the fields of their innermost span (e.g., the `deployment_id` of
+/// the deployment they belong to, as in the WebSocket events).
+fn init_tracing() {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    let format = std::env::var("NEPHELIOS_LOG_FORMAT").unwrap_or_default();
+    match format.as_str() {
+        "json" => subscriber
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .init(),
+        _ => subscriber.init(),
+    }
+    if !matches!(format.as_str(), "" | "text" | "json") {
+        warn!("Unknown log format {}, using text", format);
+    }
+}
+
+/// Logs the status and latency of a served request, inside its request span, and records
+/// it in the audit log.
+fn log_request(info: warp::log::Info<'_>) {
+    info!(
+        status = info.status().as_u16(),
+        latency_ms = info.elapsed().as_millis() as u64,
+        "request served"
+    );
+
+    let route = route_label(info.path(), info.status());
+    record_request(&info, &route);
+    HTTP_REQUESTS
+        .with_label_values(&[info.method().as_str(), &route, info.status().as_str()])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[info.method().as_str(), &route])
+        .observe(info.elapsed().as_secs_f64());
+}
+
+/// Builds the route label of a request path for the HTTP metrics.
+///
+/// The `/api/v1` prefix is dropped and path parameters are replaced (e.g.,
+/// `/api/v1/apps/my-app/scale` becomes `/apps/{name}/scale`), keeping the number of label
+/// values bounded. Unknown paths are labeled `unmatched`.
+fn route_label(path: &str, status: StatusCode) -> String {
+    let path = path.strip_prefix("/api/v1").unwrap_or(path);
+    let mut segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if segments.len() > 1 {
+        segments[1] = match segments[0] {
+            "apps" => "{name}",
+            "deployments" => "{id}",
+            _ => segments[1],
+        };
+    }
+    if segments.len() > 2 && segments[..2] == ["ws", "logs"] {
+        segments[2] = "{name}";
+    }
+
+    let route = format!("/{}", segments.join("/"));
+    if status == StatusCode::NOT_FOUND && !route.contains('{') {
+        return "unmatched".to_string();
+    }
+    route
+}
 
 /// Entry point for the application.
 ///
 /// Initializes and starts the Warp server. The server listens on `127.0.0.1:3030`
-/// and provides the following routes:
+/// and provides the following routes under the `/api/v1` prefix, also served without the
+/// prefix for existing clients:
 /// - `/create` (POST): Handles app creation requests. Expects a JSON body with app details.
+/// - `/templates` (GET), `/templates/{id}/deploy` (POST): Catalog of predefined apps (e.g.,
+///   Ghost, Uptime Kuma), deployed in one call.
+/// - `/quota` (GET): The limits and usage of the caller, or of its team.
 /// - `/health` (GET): Provides a simple health check endpoint to verify the server's status.
+/// - `/ready` (GET): Checks Docker, Swarm, the registry, Traefik and the overlay network, 503
+///   if one is down.
+/// - `/ws/logs/{name}` (WebSocket): Streams the logs of an app live.
+/// - `/ws/metrics` (WebSocket): Pushes the container metrics on every collection.
+/// - `/openapi.json` (GET): The OpenAPI document of every route, rendered at `/docs`.
+/// - `/graphql` (GET, POST): GraphQL queries for the dashboard, subscriptions at `/graphql/ws`.
+/// - `/audit` (GET): The requests that changed something, with their caller.
+/// - `/backup` (GET), `/restore` (POST): Export the state of the node, and rebuild it.
+/// - `/nodes` (GET): The nodes of the Swarm; `/nodes/join-token` (GET) to add nodes.
+/// - `/nodes/{id}/drain`, `/nodes/{id}/activate` (POST): Take a node out for maintenance, and back.
+/// - `/nodes/{id}/labels` (PUT, DELETE): Label nodes to pin or spread apps with their placement.
+/// - `/apps/{name}/volumes` (GET, PUT): Named volumes mounted into an app, kept across
+///   redeploys.
+/// - `/apps/{name}/volumes/{volume}/backup`, `/backups`, `/restore` (POST, GET): Archive the
+///   volumes of an app or its addons, download the archives, and restore them.
+/// - `/apps/{name}/secrets` (GET, PUT, DELETE): Sensitive values stored as Docker secrets,
+///   mounted into an app as files instead of being baked into its image.
+/// - `/apps/{name}/security` (PUT): Hardening of the containers of an app (read-only root
+///   filesystem, dropped capabilities, no-new-privileges, non-root user).
+/// - `/apps/{name}/autoscaling` (PUT, DELETE): Replicas of an app scaled between bounds to
+///   keep its CPU usage near a target.
+/// - `/apps/{name}/scaling-schedule` (PUT, DELETE): Replicas of an app set by time of day and
+///   day of week (e.g., 4 on weekdays from 9h to 18h, 1 otherwise).
+/// - `/apps/{name}/addons` (GET, POST, DELETE): Databases and caches deployed next to an app,
+///   their connection URL injected into its environment.
+/// - `/apps/{name}/cron-jobs` (GET, POST, PUT, DELETE): Commands run on a schedule for an app,
+///   with the history of their runs.
+/// - `/apps/{name}/jobs` (GET, POST): Commands run to completion in the image of an app (e.g.,
+///   migrations), their output streamed and recorded.
+/// - `/apps/{name}/notifications` (GET, PUT): Addresses emailed when a deployment of an app
+///   fails, the app crashes or one of its alerts fires, see the `[smtp]` settings.
+/// - `/webhooks`, `/apps/{name}/webhooks` (GET, POST, DELETE): URLs receiving the lifecycle
+///   events of every app or of one app (deployed, removed, scaled, crashed...), signed with
+///   HMAC-SHA256.
+/// - `/ws/nodes` (WebSocket): Pushes the node availability changes and drain progress.
+/// - `/system/reconcile` (GET, POST): The stack file, the app registry and the Swarm services
+///   compared at startup, missing services created again; POST runs it again.
+/// - `/system/gc` (GET, POST): Removes orphaned services, images of removed apps, stale build
+///   files and unused volumes and networks, or only lists them with `dry_run=true`.
+///
+/// Management routes require an API key or an OIDC token, see `auth::init_auth`.
+///
+/// A gRPC control API (`proto/nephelios.proto`) is also served on `NEPHELIOS_GRPC_PORT`
+/// (default 50051), authenticated with the same credentials.
+///
+/// The API is served over HTTPS when `[api_tls]` holds a certificate and key, plain HTTP
+/// requests being redirected, served or refused on `api_tls.http_port`. With `api_tls.domain`,
+/// Traefik serves it at that domain with a certificate of its ACME resolver instead.
 ///
 /// Combines the routes using Warp's `or` filter and serves them.
 ///
@@ -32,38 +216,164 @@ use crate::metrics::{CONTAINER_CPU, CONTAINER_MEM, CONTAINER_NET_IN, CONTAINER_N
 /// To start the server, run the application and use the following curl commands:
 /// ```sh
 /// # Health check
-/// curl http://127.0.0.1:3030/health
+/// curl http://127.0.0.1:3030/api/v1/health
 ///
 /// # App creation (example)
-/// curl -X POST http://127.0.0.1:3030/create \
+/// curl -X POST http://127.0.0.1:3030/api/v1/create \
+///      -H "Authorization: Bearer $NEPHELIOS_API_KEY" \
 ///      -H "Content-Type: application/json" \
 ///      -d '{"app_name": "my-app", "app_type": "nodejs", "github_url": "https://github.com/user/repo"}'
 /// ```
 #[tokio::main]
 async fn main() {
-    println!("🚀 Starting Nephelios...");
     dotenv::dotenv().ok();
+    init_tracing();
+    info!("🚀 Starting Nephelios...");
+
+    if let Err(e) = init_config() {
+        error!("❌ Failed to load the configuration: {}", e);
+        return;
+    }
+
+    if let Err(e) = init_auth() {
+        error!("❌ Failed to load authentication settings: {}", e);
+        return;
+    }
 
-    let app_port: u16 = env::var("NEPHELIOS_PORT")
-        .unwrap_or_else(|_| "3030".to_string())
-        .parse()
-        .unwrap_or(3030);
+    if let Err(e) = init_database() {
+        error!("❌ Failed to open the database: {}", e);
+        return;
+    }
+    if let Err(e) = import_history_files() {
+        warn!("Failed to import deployment history files: {}", e);
+    }
+    if let Err(e) = fail_interrupted_deployments() {
+        warn!("Failed to close interrupted deployments: {}", e);
+    }
+    if let Err(e) = fail_interrupted_cron_runs() {
+        warn!("Failed to close interrupted cron runs: {}", e);
+    }
+    if let Err(e) = fail_interrupted_jobs() {
+        warn!("Failed to close interrupted jobs: {}", e);
+    }
+    if encryption_enabled() {
+        match reencrypt_deploy_requests() {
+            Ok(0) => {}
+            Ok(count) => info!("🔐 Encrypted {} deploy requests with the master key", count),
+            Err(e) => warn!("Failed to encrypt the deploy requests: {}", e),
+        }
+        match reencrypt_webhook_secrets() {
+            Ok(0) => {}
+            Ok(count) => info!("🔐 Encrypted {} webhook secrets with the master key", count),
+            Err(e) => warn!("Failed to encrypt the webhook secrets: {}", e),
+        }
+    } else {
+        warn!("No master key configured: stored secrets are not encrypted");
+    }
+
+    let app_port = config().server.port;
+    let grpc_port = config().server.grpc_port;
 
     let cors = warp::cors()
         .allow_any_origin()
-        .allow_methods(&[Method::GET, Method::POST, Method::OPTIONS])
-        .allow_headers(vec!["Content-Type"]);
+        .allow_methods(&[
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers(vec!["Content-Type", "Authorization", "X-API-Key"]);
 
     let (status_tx, status_rx) = broadcast::channel(32);
-    let api_routes = create_app_route(status_tx.clone())
+    let (metrics_tx, _) = broadcast::channel(8);
+    // Event consumers, started before the routes and jobs publishing to them
+    tokio::spawn(run_status_broadcaster(status_tx.clone()));
+    tokio::spawn(run_alert_notifier());
+    tokio::spawn(run_email_notifier());
+    tokio::spawn(run_webhook_dispatcher());
+    tokio::spawn(run_audit_recorder());
+    // Routes under /apps/{name}, boxed separately to keep the filter type shallow
+    let app_routes = app_bulk_route()
+        .or(app_domains_route())
+        .or(app_ip_allowlist_route())
+        .or(app_http_policy_route())
+        .or(app_resources_route())
+        .or(app_placement_route())
+        .or(app_update_config_route())
+        .or(app_security_route())
+        .or(app_autoscaling_route())
+        .or(app_scaling_schedule_route())
+        .or(app_volumes_route())
+        .or(app_addons_route())
+        .or(app_cron_jobs_route())
+        .or(app_jobs_route())
+        .or(app_notifications_route())
+        .or(app_webhooks_route())
+        .or(app_sticky_sessions_route())
+        .or(app_middlewares_route())
+        .or(app_protocol_route())
+        .or(app_ports_route())
+        .or(app_maintenance_route())
+        .or(app_deployments_route())
+        .or(app_update_route())
+        .or(app_redeploy_route())
+        .or(app_rollback_route())
+        .or(app_restart_route())
+        .or(app_restore_route())
+        .or(app_scale_route())
+        .or(app_logs_route())
+        .or(app_metrics_route())
+        .or(alerts_route())
+        .or(alert_rules_route())
+        .or(app_exec_route())
+        .or(app_env_route())
+        .or(app_secrets_route())
+        .boxed();
+    let routes = create_app_route()
+        .or(templates_route())
+        .or(quota_route())
         .or(health_check_route())
+        .or(readiness_route())
         .or(get_apps_route())
         .or(ws_route(status_rx))
+        .or(ws_logs_route())
+        .or(ws_metrics_route(metrics_tx.clone()))
+        .or(ws_nodes_route())
         .or(remove_app_route())
         .or(stop_app_route())
         .or(start_app_route())
         .or(create_metrics_route())
-        .with(cors);
+        .or(github_webhook_route())
+        .or(app_routes)
+        .or(deployment_status_route())
+        .or(deployment_queue_route())
+        .or(deployment_priority_route())
+        .or(deployment_cancel_route())
+        .or(audit_route())
+        .or(webhooks_route())
+        .or(backup_route())
+        .or(restore_route())
+        .or(node_join_token_route())
+        .or(node_drain_route())
+        .or(node_activate_route())
+        .or(node_labels_route())
+        .or(nodes_route())
+        .or(system_reconcile_route())
+        .or(system_gc_route())
+        .or(openapi_route())
+        .or(docs_route())
+        .or(graphql_route(build_schema(status_tx.clone())))
+        .boxed();
+    // Routes are versioned under /api/v1, unprefixed paths are kept as aliases
+    let api_routes = warp::path!("api" / "v1" / ..)
+        .and(routes.clone())
+        .or(routes)
+        .recover(handle_rejection)
+        .with(cors)
+        .with(warp::log::custom(log_request))
+        .with(warp::trace::request());
 
     REGISTRY.register(Box::new(CONTAINER_CPU.clone())).unwrap();
     REGISTRY.register(Box::new(CONTAINER_MEM.clone())).unwrap();
@@ -73,67 +383,230 @@ async fn main() {
     REGISTRY
         .register(Box::new(CONTAINER_NET_OUT.clone()))
         .unwrap();
+    REGISTRY
+        .register(Box::new(WEBSOCKET_CLIENTS.clone()))
+        .unwrap();
+    REGISTRY.register(Box::new(APP_IMAGE_SIZE.clone())).unwrap();
+    REGISTRY
+        .register(Box::new(BUILD_CACHE_SIZE.clone()))
+        .unwrap();
+    REGISTRY.register(Box::new(VOLUME_USAGE.clone())).unwrap();
+    REGISTRY.register(Box::new(HOST_DISK_FREE.clone())).unwrap();
+    REGISTRY
+        .register(Box::new(HOST_DISK_TOTAL.clone()))
+        .unwrap();
+    REGISTRY.register(Box::new(HTTP_REQUESTS.clone())).unwrap();
+    REGISTRY
+        .register(Box::new(HTTP_REQUEST_DURATION.clone()))
+        .unwrap();
+    REGISTRY.register(Box::new(DEPLOYMENTS.clone())).unwrap();
+    REGISTRY
+        .register(Box::new(DEPLOYMENTS_QUEUED.clone()))
+        .unwrap();
+    REGISTRY
+        .register(Box::new(DEPLOY_STAGE_DURATION.clone()))
+        .unwrap();
+    REGISTRY
+        .register(Box::new(APP_REPLICAS_DESIRED.clone()))
+        .unwrap();
+    REGISTRY
+        .register(Box::new(APP_REPLICAS_RUNNING.clone()))
+        .unwrap();
+    REGISTRY.register(Box::new(APP_UP.clone())).unwrap();
+    REGISTRY.register(Box::new(SWARM_NODES.clone())).unwrap();
+    REGISTRY
+        .register(Box::new(SWARM_NODE_READY.clone()))
+        .unwrap();
+    REGISTRY
+        .register(Box::new(SWARM_NODE_CPUS.clone()))
+        .unwrap();
+    REGISTRY
+        .register(Box::new(SWARM_NODE_MEMORY.clone()))
+        .unwrap();
+    REGISTRY
+        .register(Box::new(SWARM_NODE_CPUS_RESERVED.clone()))
+        .unwrap();
+    REGISTRY
+        .register(Box::new(SWARM_NODE_MEMORY_RESERVED.clone()))
+        .unwrap();
+    REGISTRY
+        .register(Box::new(SWARM_NODE_TASKS.clone()))
+        .unwrap();
 
     // Source : https://stackoverflow.com/a/71279547
-    let (_addr, server) =
-        warp::serve(api_routes).bind_with_graceful_shutdown(([0, 0, 0, 0], app_port), async {
-            tokio::signal::ctrl_c().await.ok();
-        });
+    let api_tls = &config().api_tls;
+    let shutdown_signal = async {
+        tokio::signal::ctrl_c().await.ok();
+    };
+    let (_addr, server): (SocketAddr, Pin<Box<dyn Future<Output = ()> + Send>>) =
+        match api_tls.native() {
+            Some((cert_path, key_path)) => {
+                let bound = warp::serve(api_routes.clone())
+                    .tls()
+                    .cert_path(cert_path)
+                    .key_path(key_path)
+                    .try_bind_with_graceful_shutdown(([0, 0, 0, 0], app_port), shutdown_signal);
+                match bound {
+                    Ok((addr, server)) => (addr, Box::pin(server)),
+                    Err(e) => {
+                        error!("❌ Failed to serve the API over TLS: {}", e);
+                        return;
+                    }
+                }
+            }
+            None => {
+                let (addr, server) = warp::serve(api_routes.clone())
+                    .bind_with_graceful_shutdown(([0, 0, 0, 0], app_port), shutdown_signal);
+                (addr, Box::pin(server))
+            }
+        };
 
     let ip_addr = _addr.ip();
+    let scheme = if api_tls.native().is_some() {
+        "https"
+    } else {
+        "http"
+    };
+
+    // Plain HTTP requests are answered on a port of their own when the API serves TLS
+    let http_addr = ([0, 0, 0, 0], api_tls.http_port);
+    let http_shutdown = async {
+        tokio::signal::ctrl_c().await.ok();
+    };
+    let http_server: Option<Pin<Box<dyn Future<Output = ()> + Send>>> =
+        match (api_tls.native(), api_tls.plain_http) {
+            (None, _) | (_, PlainHttp::Disable) => None,
+            (Some(_), PlainHttp::Redirect) => Some(Box::pin(
+                warp::serve(https_redirect_route(app_port))
+                    .bind_with_graceful_shutdown(http_addr, http_shutdown)
+                    .1,
+            )),
+            (Some(_), PlainHttp::Serve) => Some(Box::pin(
+                warp::serve(api_routes)
+                    .bind_with_graceful_shutdown(http_addr, http_shutdown)
+                    .1,
+            )),
+        };
 
-    println!("🚀 Pruning Docker images...");
+    info!("🚀 Pruning Docker images...");
     let res_prune_images = prune_images().await;
     match res_prune_images {
-        Ok(_) => println!("✅ Docker images pruned successfully"),
-        Err(e) => eprintln!("❌ Failed to prune Docker images: {}", e),
+        Ok(_) => info!("✅ Docker images pruned successfully"),
+        Err(e) => error!("❌ Failed to prune Docker images: {}", e),
     }
 
-    println!("🚀 Check if Docker Swarm is initialized...");
+    info!("🚀 Check if Docker Swarm is initialized...");
     let is_alive = check_swarm();
     match is_alive {
         Ok(res) => {
             if res {
-                println!("✅ Docker Swarm is already initialized")
+                info!("✅ Docker Swarm is already initialized")
             } else {
-                println!("❌ Docker Swarm is not initialized");
-                println!("🚀 Init Docker Swarm...");
+                info!("❌ Docker Swarm is not initialized");
+                info!("🚀 Init Docker Swarm...");
                 let result_init_swarm = init_swarm(ip_addr);
                 match result_init_swarm {
-                    Ok(_) => println!("✅ Docker Swarm initialized successfully"),
+                    Ok(_) => info!("✅ Docker Swarm initialized successfully"),
                     Err(e) => {
-                        eprintln!("❌ Failed to initialize Docker Swarm: {}", e);
+                        error!("❌ Failed to initialize Docker Swarm: {}", e);
                         return;
                     }
                 }
             }
         }
         Err(e) => {
-            println!("❌ Failed to check Docker Swarm: {}", e);
+            error!("❌ Failed to check Docker Swarm: {}", e);
+            return;
+        }
+    }
+
+    info!("🚀 Configuring TLS certificate resolver...");
+    match configure_certificate_resolver() {
+        Ok(_) => info!("✅ TLS certificate resolver configured successfully"),
+        Err(e) => {
+            error!("❌ Failed to configure TLS certificate resolver: {}", e);
             return;
         }
     }
 
-    println!("🚀 Starting Nephelios Stack...");
+    info!("🚀 Configuring the API route...");
+    match configure_api_route() {
+        Ok(_) => info!("✅ API route configured successfully"),
+        Err(e) => {
+            error!("❌ Failed to configure the API route: {}", e);
+            return;
+        }
+    }
+
+    info!("🚀 Starting Nephelios Stack...");
     let result_start_stack = deploy_nephelios_stack();
     match result_start_stack {
         Ok(_) => {
-            println!("✅ Nephelios Stack started successfully");
-            println!("🔗 Connecting Nephelios to overlay network...");
+            info!("✅ Nephelios Stack started successfully");
+            info!("🔗 Connecting Nephelios to overlay network...");
             match connect_to_overlay_network().await {
-                Ok(_) => println!("✅ Connected to overlay network successfully"),
-                Err(e) => eprintln!("❌ Failed to connect to overlay network: {}", e),
+                Ok(_) => info!("✅ Connected to overlay network successfully"),
+                Err(e) => error!("❌ Failed to connect to overlay network: {}", e),
             }
         }
         Err(e) => {
-            eprintln!("❌ Failed to start Nephelios Stack: {}", e);
+            error!("❌ Failed to start Nephelios Stack: {}", e);
             return;
         }
     }
 
-    println!("🚀 Server running on http://{}:{}", ip_addr, app_port);
+    let startup_timeout = config().server.startup_timeout;
+    if startup_timeout > 0 {
+        info!("🚀 Waiting for the registry, Traefik and the overlay network...");
+        match wait_for_readiness(Duration::from_secs(startup_timeout)).await {
+            Ok(()) => info!("✅ Dependencies are ready"),
+            Err(e) => warn!(
+                "Dependencies not ready after {} seconds, deployments may fail: {}",
+                startup_timeout, e
+            ),
+        }
+    }
+
+    info!("🚀 Reconciling the stack file, the app registry and the Swarm services...");
+    if let Err(e) = reconcile().await {
+        error!("❌ Failed to reconcile the stack: {}", e);
+    }
+
+    tokio::spawn(run_auto_redeploy());
+    tokio::spawn(run_autoscaler());
+    tokio::spawn(run_scaling_scheduler());
+    tokio::spawn(run_soft_delete_purge());
+    tokio::spawn(run_app_registry_sync());
+    tokio::spawn(run_metrics_collector(metrics_tx));
+    tokio::spawn(run_disk_metrics_collector());
+    tokio::spawn(run_cron_scheduler());
+    tokio::spawn(run_garbage_collector());
+
+    let grpc_server = tonic::transport::Server::builder()
+        .add_service(ControlServer::new(status_tx.clone()))
+        .serve_with_shutdown(([0, 0, 0, 0], grpc_port).into(), async {
+            tokio::signal::ctrl_c().await.ok();
+        });
+    tokio::spawn(async move {
+        if let Err(e) = grpc_server.await {
+            error!("❌ gRPC server error: {}", e);
+        }
+    });
+
+    info!("🚀 Server running on {}://{}:{}", scheme, ip_addr, app_port);
+    if let Some(http_server) = http_server {
+        info!(
+            "🚀 Plain HTTP requests answered on port {} ({:?})",
+            api_tls.http_port, api_tls.plain_http
+        );
+        tokio::spawn(http_server);
+    }
+    if let Some(domain) = &api_tls.domain {
+        info!("🚀 Server exposed by Traefik on https://{}", domain);
+    }
+    info!("🚀 gRPC API running on {}:{}", ip_addr, grpc_port);
 
-    println!("🚀 Front running on http://{}:4173", ip_addr);
+    info!("🚀 Front running on http://{}:4173", ip_addr);
 
     // Créer un canal pour la notification de shutdown
     let (shutdown_tx, mut shutdown_rx) = broadcast::channel(1);
@@ -145,29 +618,29 @@ async fn main() {
         let mut sigterm = match signal(SignalKind::terminate()) {
             Ok(signal) => signal,
             Err(e) => {
-                eprintln!("❌ Failed to register SIGTERM handler: {}", e);
+                error!("❌ Failed to register SIGTERM handler: {}", e);
                 return;
             }
         };
         let mut sigint = match signal(SignalKind::interrupt()) {
             Ok(signal) => signal,
             Err(e) => {
-                eprintln!("❌ Failed to register SIGINT handler: {}", e);
+                error!("❌ Failed to register SIGINT handler: {}", e);
                 return;
             }
         };
         let mut sighup = match signal(SignalKind::hangup()) {
             Ok(signal) => signal,
             Err(e) => {
-                eprintln!("❌ Failed to register SIGHUP handler: {}", e);
+                error!("❌ Failed to register SIGHUP handler: {}", e);
                 return;
             }
         };
 
         tokio::select! {
-            _ = sigterm.recv() => println!("\n🛑 Received SIGTERM signal"),
-            _ = sigint.recv() => println!("\n🛑 Received SIGINT signal"),
-            _ = sighup.recv() => println!("\n🛑 Received SIGHUP signal"),
+            _ = sigterm.recv() => info!("🛑 Received SIGTERM signal"),
+            _ = sigint.recv() => info!("🛑 Received SIGINT signal"),
+            _ = sighup.recv() => info!("🛑 Received SIGHUP signal"),
         }
         shutdown_tx_clone.send(()).ok();
     });
@@ -178,56 +651,56 @@ async fn main() {
     // Attendre soit le signal de shutdown, soit une erreur du serveur
     tokio::select! {
         _ = shutdown_rx.recv() => {
-            println!("🛑 Starting cleanup process...");
+            info!("🛑 Starting cleanup process...");
         }
         result = server_handle => {
             if let Err(e) = result {
-                println!("ERROR: Server error {}", e);
+                error!("Server error {}", e);
             }
         }
     }
 
     // Cleanup process avec timeout
-    println!("🛑 Terminating Nephelios Stack...");
+    info!("🛑 Terminating Nephelios Stack...");
     let cleanup_timeout = tokio::time::Duration::from_secs(10);
 
     match tokio::time::timeout(cleanup_timeout, async {
-        println!("🔗 Disconnecting from overlay network...");
+        info!("🔗 Disconnecting from overlay network...");
         match disconnect_from_overlay_network().await {
-            Ok(_) => println!("✅ Disconnected from overlay network"),
-            Err(e) => eprintln!("❌ Failed to disconnect from overlay network: {}", e),
+            Ok(_) => info!("✅ Disconnected from overlay network"),
+            Err(e) => error!("❌ Failed to disconnect from overlay network: {}", e),
         }
 
-        println!("💥 Stopping Nephelios Stack...");
+        info!("💥 Stopping Nephelios Stack...");
         match stop_nephelios_stack() {
-            Ok(_) => println!("✅ Nephelios Stack terminated successfully"),
-            Err(e) => eprintln!("❌ Failed to terminate Nephelios Stack: {}", e),
+            Ok(_) => info!("✅ Nephelios Stack terminated successfully"),
+            Err(e) => error!("❌ Failed to terminate Nephelios Stack: {}", e),
         }
     })
     .await
     {
-        Ok(_) => println!("✅ Cleanup completed within timeout"),
-        Err(_) => eprintln!(
+        Ok(_) => info!("✅ Cleanup completed within timeout"),
+        Err(_) => error!(
             "❌ Cleanup timed out after {} seconds",
             cleanup_timeout.as_secs()
         ),
     };
 
-    if env::var("LEAVE_SWARM").unwrap_or_else(|_| "false".to_string()) == "true" {
-        println!("🛑 Leaving Docker Swarm...");
+    if config().server.leave_swarm {
+        info!("🛑 Leaving Docker Swarm...");
         if let Err(e) = leave_swarm() {
-            eprintln!("❌ Failed to leave Docker Swarm: {}", e);
+            error!("❌ Failed to leave Docker Swarm: {}", e);
         } else {
-            println!("✅ Left Docker Swarm successfully");
+            info!("✅ Left Docker Swarm successfully");
         }
     }
 
-    println!("🛑 Pruning Docker images...");
+    info!("🛑 Pruning Docker images...");
     if let Err(e) = prune_images().await {
-        eprintln!("❌ Failed to prune Docker images: {}", e);
+        error!("❌ Failed to prune Docker images: {}", e);
     } else {
-        println!("✅ Docker images pruned successfully");
+        info!("✅ Docker images pruned successfully");
     }
 
-    println!("👋 Goodbye!");
+    info!("👋 Goodbye!");
 }
@@ -1,63 +1,291 @@
 use lazy_static::lazy_static;
-use prometheus::{GaugeVec, Opts, Registry};
+use prometheus::{
+    Gauge, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+};
 
-/// Prometheus metrics and registry definitions for Docker container monitoring.
-/// This block initializes the custom Prometheus metrics used to track per-container
-/// CPU usage, memory usage, and network I/O, as well as the main metrics registry.
+// Prometheus metrics and registry definitions for Docker container monitoring.
+// This block initializes the custom Prometheus metrics used to track per-container
+// CPU usage, memory usage, and network I/O, as well as the main metrics registry.
+// Container metrics are labeled with the app (e.g., `myapp`), its Swarm service
+// (e.g., `nephelios_myapp`) and the task (e.g., `nephelios_myapp.1.xyz`), so queries on
+// `app` keep working across redeploys.
 lazy_static! {
     /// Global Prometheus registry used to register all custom metrics.
     pub static ref REGISTRY: Registry = Registry::new();
     /// Gauge vector tracking CPU usage per container.
     ///
     /// Metric name: `container_cpu_usage`  
-    /// Labels: `container`
+    /// Labels: `app`, `service`, `task`
     ///
     /// Represents the current CPU usage of each container as a floating-point value.
     pub static ref CONTAINER_CPU: GaugeVec = GaugeVec::new(
         Opts::new("container_cpu_usage", "CPU usage per container"),
-        &["container"]
+        &["app", "service", "task"]
     )
     .unwrap();
     /// Gauge vector tracking memory usage per container.
     ///
-    /// Metric name: `container_memory_usage`  
-    /// Labels: `container`
+    /// Metric name: `container_memory_usage_bytes`  
+    /// Labels: `app`, `service`, `task`
     ///
-    /// Represents the memory usage of each container, typically in megabytes (MB).
+    /// Represents the memory usage of each container, in bytes, without the page cache.
     pub static ref CONTAINER_MEM: GaugeVec = GaugeVec::new(
         Opts::new(
-            "container_memory_usage",
-            "Memory usage per container (in MB)"
+            "container_memory_usage_bytes",
+            "Memory usage per container (in bytes)"
         ),
-        &["container"]
+        &["app", "service", "task"]
     )
     .unwrap();
     /// Gauge vector tracking network input per container.
     ///
-    /// Metric name: `container_network_in`  
-    /// Labels: `container`
+    /// Metric name: `container_network_in_bytes`  
+    /// Labels: `app`, `service`, `task`
     ///
-    /// Represents the total inbound network traffic for each container, in kilobytes (KB).
+    /// Represents the total inbound network traffic for each container, in bytes.
     pub static ref CONTAINER_NET_IN: GaugeVec = GaugeVec::new(
         Opts::new(
-            "container_network_in",
-            "Network input per container (in KB)"
+            "container_network_in_bytes",
+            "Network input per container (in bytes)"
         ),
-        &["container"]
+        &["app", "service", "task"]
     )
     .unwrap();
     /// Gauge vector tracking network output per container.
     ///
-    /// Metric name: `container_network_out`  
-    /// Labels: `container`
+    /// Metric name: `container_network_out_bytes`  
+    /// Labels: `app`, `service`, `task`
     ///
-    /// Represents the total outbound network traffic for each container, in kilobytes (KB).
+    /// Represents the total outbound network traffic for each container, in bytes.
     pub static ref CONTAINER_NET_OUT: GaugeVec = GaugeVec::new(
         Opts::new(
-            "container_network_out",
-            "Network output per container (in KB)"
+            "container_network_out_bytes",
+            "Network output per container (in bytes)"
         ),
-        &["container"]
+        &["app", "service", "task"]
+    )
+    .unwrap();
+    /// Gauge tracking the open WebSocket status connections.
+    ///
+    /// Metric name: `websocket_clients`
+    ///
+    /// Represents the number of authenticated clients connected to `/ws`.
+    pub static ref WEBSOCKET_CLIENTS: IntGauge = IntGauge::new(
+        "websocket_clients",
+        "Open WebSocket status connections"
+    )
+    .unwrap();
+    /// Gauge vector tracking the size of the images of each app.
+    ///
+    /// Metric name: `app_image_size_bytes`  
+    /// Labels: `app`
+    ///
+    /// Represents the total size of the release images of each app kept on the node, in bytes.
+    /// Layers shared between releases are counted once per image.
+    pub static ref APP_IMAGE_SIZE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "app_image_size_bytes",
+            "Size of the release images per app (in bytes)"
+        ),
+        &["app"]
+    )
+    .unwrap();
+    /// Gauge tracking the size of the Docker build cache.
+    ///
+    /// Metric name: `docker_build_cache_bytes`
+    ///
+    /// Represents the disk space used by the build cache, in bytes.
+    pub static ref BUILD_CACHE_SIZE: Gauge = Gauge::new(
+        "docker_build_cache_bytes",
+        "Size of the Docker build cache (in bytes)"
+    )
+    .unwrap();
+    /// Gauge vector tracking the disk usage of the Docker volumes.
+    ///
+    /// Metric name: `docker_volume_usage_bytes`  
+    /// Labels: `volume`
+    ///
+    /// Represents the disk space used by each volume (e.g., `registry_data`), in bytes.
+    pub static ref VOLUME_USAGE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "docker_volume_usage_bytes",
+            "Disk usage per Docker volume (in bytes)"
+        ),
+        &["volume"]
+    )
+    .unwrap();
+    /// Gauge tracking the free disk space of the host.
+    ///
+    /// Metric name: `host_disk_free_bytes`
+    ///
+    /// Represents the space available on the filesystem holding the Docker data, in bytes.
+    pub static ref HOST_DISK_FREE: Gauge = Gauge::new(
+        "host_disk_free_bytes",
+        "Free space on the Docker data filesystem (in bytes)"
+    )
+    .unwrap();
+    /// Gauge tracking the size of the disk of the host.
+    ///
+    /// Metric name: `host_disk_total_bytes`
+    ///
+    /// Represents the size of the filesystem holding the Docker data, in bytes.
+    pub static ref HOST_DISK_TOTAL: Gauge = Gauge::new(
+        "host_disk_total_bytes",
+        "Size of the Docker data filesystem (in bytes)"
+    )
+    .unwrap();
+    /// Gauge vector tracking the number of replicas requested for each app.
+    ///
+    /// Metric name: `app_replicas_desired`  
+    /// Labels: `app`
+    pub static ref APP_REPLICAS_DESIRED: GaugeVec = GaugeVec::new(
+        Opts::new("app_replicas_desired", "Number of replicas requested for the app"),
+        &["app"]
+    )
+    .unwrap();
+    /// Gauge vector tracking the number of running replicas of each app.
+    ///
+    /// Metric name: `app_replicas_running`  
+    /// Labels: `app`
+    pub static ref APP_REPLICAS_RUNNING: GaugeVec = GaugeVec::new(
+        Opts::new("app_replicas_running", "Number of running replicas of the app"),
+        &["app"]
+    )
+    .unwrap();
+    /// Gauge vector tracking whether each app has a running replica (1) or not (0).
+    ///
+    /// Metric name: `app_up`  
+    /// Labels: `app`
+    ///
+    /// Stopped apps are down too; `app_up == 0 and app_replicas_desired > 0` matches the
+    /// apps that should run.
+    pub static ref APP_UP: GaugeVec = GaugeVec::new(
+        Opts::new("app_up", "Whether the app has a running replica"),
+        &["app"]
+    )
+    .unwrap();
+    /// Gauge tracking the number of nodes in the Swarm.
+    ///
+    /// Metric name: `swarm_nodes`
+    pub static ref SWARM_NODES: IntGauge = IntGauge::new(
+        "swarm_nodes",
+        "Number of nodes in the Swarm"
+    )
+    .unwrap();
+    /// Gauge vector tracking whether the Swarm nodes are ready (1) or not (0).
+    ///
+    /// Metric name: `swarm_node_ready`  
+    /// Labels: `node`, `role` (`manager` or `worker`), `availability` (`active`, `pause` or `drain`), `state`
+    pub static ref SWARM_NODE_READY: GaugeVec = GaugeVec::new(
+        Opts::new("swarm_node_ready", "Whether the Swarm node is ready"),
+        &["node", "role", "availability", "state"]
+    )
+    .unwrap();
+    /// Gauge vector tracking the CPUs of the Swarm nodes.
+    ///
+    /// Metric name: `swarm_node_cpus`  
+    /// Labels: `node`
+    pub static ref SWARM_NODE_CPUS: GaugeVec = GaugeVec::new(
+        Opts::new("swarm_node_cpus", "CPUs of the Swarm node"),
+        &["node"]
+    )
+    .unwrap();
+    /// Gauge vector tracking the memory of the Swarm nodes.
+    ///
+    /// Metric name: `swarm_node_memory_bytes`  
+    /// Labels: `node`
+    pub static ref SWARM_NODE_MEMORY: GaugeVec = GaugeVec::new(
+        Opts::new("swarm_node_memory_bytes", "Memory of the Swarm node (in bytes)"),
+        &["node"]
+    )
+    .unwrap();
+    /// Gauge vector tracking the CPUs reserved by the running tasks of the Swarm nodes.
+    ///
+    /// Metric name: `swarm_node_cpus_reserved`  
+    /// Labels: `node`
+    pub static ref SWARM_NODE_CPUS_RESERVED: GaugeVec = GaugeVec::new(
+        Opts::new("swarm_node_cpus_reserved", "CPUs reserved by the tasks of the Swarm node"),
+        &["node"]
+    )
+    .unwrap();
+    /// Gauge vector tracking the memory reserved by the running tasks of the Swarm nodes.
+    ///
+    /// Metric name: `swarm_node_memory_reserved_bytes`  
+    /// Labels: `node`
+    pub static ref SWARM_NODE_MEMORY_RESERVED: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "swarm_node_memory_reserved_bytes",
+            "Memory reserved by the tasks of the Swarm node (in bytes)"
+        ),
+        &["node"]
+    )
+    .unwrap();
+    /// Gauge vector tracking the running tasks of the Swarm nodes.
+    ///
+    /// Metric name: `swarm_node_tasks`  
+    /// Labels: `node`
+    pub static ref SWARM_NODE_TASKS: GaugeVec = GaugeVec::new(
+        Opts::new("swarm_node_tasks", "Running tasks of the Swarm node"),
+        &["node"]
+    )
+    .unwrap();
+    /// Counter vector tracking the HTTP requests served by Nephelios.
+    ///
+    /// Metric name: `nephelios_http_requests_total`  
+    /// Labels: `method`, `route`, `status`
+    ///
+    /// The route is the request path with its parameters replaced (e.g., `/apps/{name}/scale`).
+    pub static ref HTTP_REQUESTS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "nephelios_http_requests_total",
+            "HTTP requests served per route"
+        ),
+        &["method", "route", "status"]
+    )
+    .unwrap();
+    /// Histogram vector tracking the latency of the HTTP requests served by Nephelios.
+    ///
+    /// Metric name: `nephelios_http_request_duration_seconds`  
+    /// Labels: `method`, `route`
+    pub static ref HTTP_REQUEST_DURATION: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "nephelios_http_request_duration_seconds",
+            "Latency of the HTTP requests per route (in seconds)"
+        ),
+        &["method", "route"]
+    )
+    .unwrap();
+    /// Counter vector tracking the deployment jobs.
+    ///
+    /// Metric name: `nephelios_deployments_total`  
+    /// Labels: `kind` (`deploy` or `rollback`), `event` (`started`, `succeeded` or `failed`)
+    pub static ref DEPLOYMENTS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "nephelios_deployments_total",
+            "Deployment jobs started, succeeded and failed"
+        ),
+        &["kind", "event"]
+    )
+    .unwrap();
+    /// Histogram vector tracking the duration of the successful deploy pipeline stages.
+    ///
+    /// Metric name: `nephelios_deploy_stage_duration_seconds`  
+    /// Labels: `stage` (`clone`, `build`, `push` or `deploy`), `app_type`
+    pub static ref DEPLOY_STAGE_DURATION: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "nephelios_deploy_stage_duration_seconds",
+            "Duration of the deploy pipeline stages (in seconds)"
+        )
+        .buckets(vec![1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 1800.0]),
+        &["stage", "app_type"]
+    )
+    .unwrap();
+    /// Gauge tracking the deployment jobs waiting for another job of their app.
+    ///
+    /// Metric name: `nephelios_deployments_queued`
+    pub static ref DEPLOYMENTS_QUEUED: IntGauge = IntGauge::new(
+        "nephelios_deployments_queued",
+        "Deployment jobs waiting to start"
     )
     .unwrap();
 }
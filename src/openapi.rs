@@ -0,0 +1,2796 @@
+use crate::services::webhooks::WEBHOOK_EVENTS;
+use serde_json::{json, Map, Value};
+
+/// Swagger UI page rendering `/api/v1/openapi.json`, served at `/api/v1/docs`.
+pub const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8" />
+  <title>Nephelios API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js" crossorigin></script>
+  <script>
+    window.onload = () => {
+      window.ui = SwaggerUIBundle({ url: "/api/v1/openapi.json", dom_id: "#swagger-ui" });
+    };
+  </script>
+</body>
+</html>
+"##;
+
+/// Reference to a schema of the document.
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+/// A JSON request body using a schema of the document.
+fn json_body(schema: &str) -> Value {
+    json!({
+        "required": true,
+        "content": { "application/json": { "schema": schema_ref(schema) } }
+    })
+}
+
+/// A JSON response using the given schema.
+fn json_response(description: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } }
+    })
+}
+
+/// A plain text response.
+fn text_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "text/plain": { "schema": { "type": "string" } } }
+    })
+}
+
+/// The error responses shared by the authenticated routes.
+fn error_responses(with_body: bool) -> Map<String, Value> {
+    let mut responses = Map::new();
+    if with_body {
+        responses.insert(
+            "400".to_string(),
+            json_response("Invalid request body", schema_ref("ValidationErrors")),
+        );
+    }
+    responses.insert(
+        "401".to_string(),
+        json_response("Missing or invalid API key or token", schema_ref("Error")),
+    );
+    responses.insert(
+        "403".to_string(),
+        json_response("Insufficient role for this operation", schema_ref("Error")),
+    );
+    responses.insert(
+        "500".to_string(),
+        json_response("Operation failed", schema_ref("Error")),
+    );
+    responses
+}
+
+/// An operation requiring authentication with at least the given role.
+fn secured_operation(
+    summary: &str,
+    role: &str,
+    body: Option<&str>,
+    responses: Vec<(&str, Value)>,
+) -> Value {
+    let mut all_responses = error_responses(body.is_some());
+    for (status, response) in responses {
+        all_responses.insert(status.to_string(), response);
+    }
+
+    let mut operation = json!({
+        "summary": summary,
+        "description": format!("Requires the `{}` role.", role),
+        "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+        "responses": all_responses,
+    });
+    if let Some(schema) = body {
+        operation["requestBody"] = json_body(schema);
+    }
+    operation
+}
+
+/// An operation on `/apps/{app_name}/...`, requiring authentication with at least the given role.
+fn app_operation(
+    summary: &str,
+    role: &str,
+    body: Option<&str>,
+    responses: Vec<(&str, Value)>,
+) -> Value {
+    let mut operation = secured_operation(summary, role, body, responses);
+    operation["parameters"] = json!([{
+        "name": "app_name",
+        "in": "path",
+        "required": true,
+        "schema": { "type": "string" }
+    }]);
+    operation
+}
+
+/// An operation on `/apps/{app_name}/...`, updating a setting of the app.
+fn app_setting_operation(summary: &str, body: &str, result: Value) -> Value {
+    app_operation(
+        summary,
+        "deployer",
+        Some(body),
+        vec![
+            ("200", json_response("Setting updated", result)),
+            (
+                "404",
+                json_response("The app does not exist", schema_ref("Error")),
+            ),
+        ],
+    )
+}
+
+/// The response of an app setting update: the app name and the updated field.
+fn setting_result(field: &str, schema: Value) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "app_name": { "type": "string" },
+            field: schema,
+        }
+    })
+}
+
+/// The paths of the document.
+fn paths() -> Value {
+    let string_list = json!({ "type": "array", "items": { "type": "string" } });
+
+    let mut bulk_operation = secured_operation(
+        "Run an action on many apps",
+        "deployer",
+        Some("BulkRequest"),
+        vec![(
+            "200",
+            json_response("The result of each app", schema_ref("BulkResult")),
+        )],
+    );
+    bulk_operation["description"] = json!(
+        "Requires the `deployer` role, or the `admin` role for `remove`. \
+         The action runs concurrently on every app."
+    );
+
+    let addon_result = setting_result("addon", schema_ref("Addon"));
+    let mut remove_addon_operation = app_operation(
+        "Remove an addon",
+        "deployer",
+        None,
+        vec![
+            (
+                "200",
+                json_response("The removed addon", addon_result.clone()),
+            ),
+            (
+                "404",
+                json_response("The app or the addon does not exist", schema_ref("Error")),
+            ),
+        ],
+    );
+    remove_addon_operation["description"] = json!(
+        "Requires the `deployer` role. The connection URL is removed from the environment of \
+         the app. The data volume of the addon is kept."
+    );
+    remove_addon_operation["parameters"]
+        .as_array_mut()
+        .expect("app operations have parameters")
+        .push(json!({ "name": "type", "in": "path", "required": true, "schema": schema_ref("AddonType") }));
+
+    let cron_job_result = setting_result("cron_job", schema_ref("CronJob"));
+    let cron_job_not_found = (
+        "404",
+        json_response(
+            "The app or the cron job does not exist",
+            schema_ref("Error"),
+        ),
+    );
+    let mut update_cron_job_operation = app_operation(
+        "Update a cron job",
+        "deployer",
+        Some("CronJobRequest"),
+        vec![
+            (
+                "200",
+                json_response("The updated cron job", cron_job_result.clone()),
+            ),
+            cron_job_not_found.clone(),
+        ],
+    );
+    let mut delete_cron_job_operation = app_operation(
+        "Delete a cron job and its run history",
+        "deployer",
+        None,
+        vec![
+            (
+                "200",
+                json_response(
+                    "The cron job was deleted",
+                    json!({ "type": "object", "properties": { "message": { "type": "string" } } }),
+                ),
+            ),
+            cron_job_not_found.clone(),
+        ],
+    );
+    let mut cron_runs_operation = app_operation(
+        "List the runs of a cron job",
+        "viewer",
+        None,
+        vec![
+            (
+                "200",
+                json_response(
+                    "The last 50 runs, most recent first",
+                    json!({
+                        "type": "object",
+                        "properties": {
+                            "app_name": { "type": "string" },
+                            "cron_job": schema_ref("CronJob"),
+                            "runs": { "type": "array", "items": schema_ref("CronRun") }
+                        }
+                    }),
+                ),
+            ),
+            cron_job_not_found.clone(),
+        ],
+    );
+    let mut run_cron_job_operation = app_operation(
+        "Run a cron job now",
+        "deployer",
+        None,
+        vec![
+            (
+                "202",
+                json_response(
+                    "The run was started",
+                    setting_result("run", schema_ref("CronRun")),
+                ),
+            ),
+            cron_job_not_found.clone(),
+            (
+                "409",
+                json_response("A run of the cron job is in progress", schema_ref("Error")),
+            ),
+        ],
+    );
+    run_cron_job_operation["description"] = json!(
+        "Requires the `deployer` role. The run is started in the background, its outcome is \
+         listed with the runs of the cron job."
+    );
+    for operation in [
+        &mut update_cron_job_operation,
+        &mut delete_cron_job_operation,
+        &mut cron_runs_operation,
+        &mut run_cron_job_operation,
+    ] {
+        operation["parameters"]
+            .as_array_mut()
+            .expect("app operations have parameters")
+            .push(json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } }));
+    }
+
+    let mut template_deploy_operation = secured_operation(
+        "Deploy an app from a template",
+        "deployer",
+        Some("TemplateDeployRequest"),
+        vec![
+            (
+                "201",
+                json_response(
+                    "The deployment job was created",
+                    json!({
+                        "type": "object",
+                        "properties": {
+                            "message": { "type": "string" },
+                            "app_name": { "type": "string" },
+                            "template": { "type": "string" },
+                            "deployment_id": { "type": "string", "format": "uuid" }
+                        }
+                    }),
+                ),
+            ),
+            (
+                "404",
+                json_response("Unknown template", schema_ref("Error")),
+            ),
+            (
+                "422",
+                json_response(
+                    "Invalid or existing app name, or a required variable is missing",
+                    schema_ref("ValidationErrors"),
+                ),
+            ),
+        ],
+    );
+    template_deploy_operation["parameters"] = json!([
+        { "name": "id", "in": "path", "required": true, "schema": { "type": "string", "example": "ghost" } }
+    ]);
+
+    let mut job_operation = app_operation(
+        "Get a one-off job",
+        "viewer",
+        None,
+        vec![
+            (
+                "200",
+                json_response(
+                    "The job, with the end of its output",
+                    setting_result("job", schema_ref("Job")),
+                ),
+            ),
+            (
+                "404",
+                json_response("The app or the job does not exist", schema_ref("Error")),
+            ),
+        ],
+    );
+    job_operation["parameters"]
+        .as_array_mut()
+        .expect("app operations have parameters")
+        .push(
+            json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } }),
+        );
+
+    let webhooks_result = json_response(
+        "The webhooks, without their secret",
+        json!({
+            "type": "object",
+            "properties": {
+                "webhooks": { "type": "array", "items": schema_ref("Webhook") },
+                "total": { "type": "integer" }
+            }
+        }),
+    );
+    let webhook_created_result = json_response(
+        "The registered webhook, with the secret signing its deliveries",
+        json!({
+            "type": "object",
+            "properties": {
+                "webhook": schema_ref("Webhook"),
+                "secret": { "type": "string" }
+            }
+        }),
+    );
+    let webhook_deleted_result = json_response(
+        "The webhook was deleted",
+        json!({ "type": "object", "properties": { "message": { "type": "string" } } }),
+    );
+    let webhook_id_parameter = json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "schema": { "type": "string", "format": "uuid" }
+    });
+    let mut delete_app_webhook_operation = app_operation(
+        "Delete a webhook of the app",
+        "deployer",
+        None,
+        vec![
+            ("200", webhook_deleted_result.clone()),
+            (
+                "404",
+                json_response("The webhook does not exist", schema_ref("Error")),
+            ),
+        ],
+    );
+    delete_app_webhook_operation["parameters"]
+        .as_array_mut()
+        .expect("app operations have parameters")
+        .push(webhook_id_parameter.clone());
+    let mut delete_webhook_operation = secured_operation(
+        "Delete a global webhook",
+        "admin",
+        None,
+        vec![
+            ("200", webhook_deleted_result),
+            (
+                "404",
+                json_response("The webhook does not exist", schema_ref("Error")),
+            ),
+        ],
+    );
+    delete_webhook_operation["parameters"] = json!([webhook_id_parameter]);
+    let mut gc_operation = secured_operation(
+        "Remove orphaned services, images, build files, volumes and networks",
+        "admin",
+        None,
+        vec![
+            ("200", json_response("The report", schema_ref("GcReport"))),
+            (
+                "500",
+                json_response("The resources could not be listed", schema_ref("Error")),
+            ),
+        ],
+    );
+    gc_operation["parameters"] = json!([{
+        "name": "dry_run",
+        "in": "query",
+        "description": "Only list the orphaned resources, without removing them",
+        "schema": { "type": "boolean", "default": false }
+    }]);
+    let queue_change_responses = || {
+        vec![
+            (
+                "200",
+                json_response(
+                    "The queued deployment was changed",
+                    json!({
+                        "type": "object",
+                        "properties": {
+                            "message": { "type": "string" },
+                            "deployment": schema_ref("Deployment")
+                        }
+                    }),
+                ),
+            ),
+            (
+                "404",
+                json_response("Unknown deployment", schema_ref("Error")),
+            ),
+            (
+                "409",
+                json_response("The deployment is no longer queued", schema_ref("Error")),
+            ),
+        ]
+    };
+    let deployment_id_parameter = json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "schema": { "type": "string", "format": "uuid" }
+    });
+    let mut deployment_priority_operation = secured_operation(
+        "Change the priority of a queued deployment",
+        "deployer",
+        Some("DeploymentPriorityRequest"),
+        queue_change_responses(),
+    );
+    deployment_priority_operation["parameters"] = json!([deployment_id_parameter.clone()]);
+    let mut deployment_cancel_operation = secured_operation(
+        "Cancel a queued deployment",
+        "deployer",
+        None,
+        queue_change_responses(),
+    );
+    deployment_cancel_operation["parameters"] = json!([deployment_id_parameter]);
+
+    let volume_not_found = (
+        "404",
+        json_response("The app or the volume does not exist", schema_ref("Error")),
+    );
+    let mut volume_backup_operation = app_operation(
+        "Back up a volume",
+        "deployer",
+        None,
+        vec![
+            (
+                "201",
+                json_response(
+                    "The new backup",
+                    setting_result("backup", schema_ref("VolumeBackup")),
+                ),
+            ),
+            volume_not_found.clone(),
+            (
+                "409",
+                json_response(
+                    "The volume is on another node than Nephelios",
+                    schema_ref("Error"),
+                ),
+            ),
+        ],
+    );
+    volume_backup_operation["description"] = json!(
+        "Requires the `deployer` role. The volume is archived as a gzipped tar into the backup \
+         directory while the app keeps running."
+    );
+    let mut volume_backups_operation = app_operation(
+        "List the backups of a volume",
+        "viewer",
+        None,
+        vec![
+            (
+                "200",
+                json_response(
+                    "The backups, most recent first",
+                    setting_result(
+                        "backups",
+                        json!({ "type": "array", "items": schema_ref("VolumeBackup") }),
+                    ),
+                ),
+            ),
+            volume_not_found.clone(),
+        ],
+    );
+    let mut volume_download_operation = app_operation(
+        "Download a backup of a volume",
+        "admin",
+        None,
+        vec![
+            (
+                "200",
+                json!({
+                    "description": "The backup archive",
+                    "content": { "application/gzip": { "schema": { "type": "string", "format": "binary" } } }
+                }),
+            ),
+            (
+                "404",
+                json_response(
+                    "The app, the volume or the backup does not exist",
+                    schema_ref("Error"),
+                ),
+            ),
+        ],
+    );
+    volume_download_operation["parameters"]
+        .as_array_mut()
+        .expect("app operations have parameters")
+        .push(json!({ "name": "backup_id", "in": "path", "required": true, "schema": { "type": "string" } }));
+    let mut volume_restore_operation = app_operation(
+        "Restore a backup of a volume",
+        "admin",
+        Some("VolumeRestoreRequest"),
+        vec![
+            (
+                "200",
+                json_response(
+                    "The volume was restored",
+                    json!({
+                        "type": "object",
+                        "properties": {
+                            "message": { "type": "string" },
+                            "app_name": { "type": "string" },
+                            "volume": { "type": "string" },
+                            "backup": { "type": "string" }
+                        }
+                    }),
+                ),
+            ),
+            (
+                "404",
+                json_response(
+                    "The app, the volume or the backup does not exist",
+                    schema_ref("Error"),
+                ),
+            ),
+            (
+                "409",
+                json_response(
+                    "The volume is on another node than Nephelios",
+                    schema_ref("Error"),
+                ),
+            ),
+        ],
+    );
+    volume_restore_operation["description"] = json!(
+        "Requires the `admin` role. The service mounting the volume is stopped while the content \
+         of the volume is replaced with the backup, then started again."
+    );
+    for operation in [
+        &mut volume_backup_operation,
+        &mut volume_backups_operation,
+        &mut volume_download_operation,
+        &mut volume_restore_operation,
+    ] {
+        operation["parameters"]
+            .as_array_mut()
+            .expect("app operations have parameters")
+            .insert(1, json!({ "name": "volume", "in": "path", "required": true, "schema": { "type": "string" }, "description": "An app volume, or the type of an addon" }));
+    }
+
+    let app_secrets = setting_result(
+        "secrets",
+        json!({ "type": "array", "items": schema_ref("AppSecret") }),
+    );
+    let app_secrets_operation = app_operation(
+        "List the secrets, without their values",
+        "deployer",
+        None,
+        vec![
+            ("200", json_response("The secrets", app_secrets.clone())),
+            (
+                "404",
+                json_response("The app does not exist", schema_ref("Error")),
+            ),
+        ],
+    );
+    let set_app_secrets_operation = app_setting_operation(
+        "Store secrets as Docker secrets mounted into the app",
+        "SecretsRequest",
+        app_secrets.clone(),
+    );
+    let delete_app_secrets_operation =
+        app_setting_operation("Remove secrets", "EnvKeysRequest", app_secrets);
+
+    json!({
+        "/health": {
+            "get": {
+                "summary": "Health check",
+                "responses": {
+                    "200": json_response("The server is running", json!({ "type": "string", "example": "OK" }))
+                }
+            }
+        },
+        "/ready": {
+            "get": {
+                "summary": "Readiness check of Docker, Swarm, the registry, Traefik and the overlay network",
+                "responses": {
+                    "200": json_response("Every dependency is available", schema_ref("Readiness")),
+                    "503": json_response("A dependency is unavailable", schema_ref("Readiness"))
+                }
+            }
+        },
+        "/metrics": {
+            "get": {
+                "summary": "Prometheus metrics of the deployed containers",
+                "responses": { "200": text_response("Metrics in the Prometheus text format") }
+            }
+        },
+        "/get-apps": {
+            "get": secured_operation(
+                "List deployed apps",
+                "viewer",
+                None,
+                vec![("200", json_response("The deployed apps", schema_ref("AppList")))],
+            )
+        },
+        "/create": {
+            "post": secured_operation(
+                "Deploy or redeploy an app",
+                "deployer",
+                Some("CreateAppRequest"),
+                vec![
+                    (
+                        "201",
+                        json_response("The deployment job was created", schema_ref("DeploymentCreated")),
+                    ),
+                    (
+                        "409",
+                        json_response("The app is soft-deleted, restore it first", schema_ref("Error")),
+                    ),
+                    (
+                        "422",
+                        json_response(
+                            "Invalid app name, or the name is used by a Nephelios service or by \
+                             an app deployed from another repository",
+                            schema_ref("ValidationErrors"),
+                        ),
+                    ),
+                ],
+            )
+        },
+        "/templates": {
+            "get": secured_operation(
+                "List the app templates",
+                "viewer",
+                None,
+                vec![(
+                    "200",
+                    json_response(
+                        "The templates of the catalog",
+                        json!({
+                            "type": "object",
+                            "properties": {
+                                "templates": { "type": "array", "items": schema_ref("AppTemplate") }
+                            }
+                        }),
+                    ),
+                )],
+            )
+        },
+        "/templates/{id}/deploy": { "post": template_deploy_operation },
+        "/quota": {
+            "get": {
+                "summary": "Read the quota of the caller",
+                "description": "Requires the `viewer` role. Usage is counted against the team of the caller, or the caller alone. Deploys, scaling and resource changes that would exceed a limit are refused with 403, admins are not limited.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The quota of the caller", json!({
+                        "type": "object",
+                        "properties": {
+                            "account": { "type": "string", "example": "team:backend", "description": "The caller, or `team:<name>` for the members of a team" },
+                            "limits": schema_ref("QuotaLimits"),
+                            "usage": schema_ref("QuotaUsage")
+                        }
+                    })),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role for this operation", schema_ref("Error")),
+                    "500": json_response("The usage could not be read", schema_ref("Error"))
+                }
+            }
+        },
+        "/alerts": {
+            "get": {
+                "summary": "List the firing alerts",
+                "description": "Requires the `viewer` role. Alerts whose rule condition held for longer than its `for` duration, oldest first.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The firing alerts", json!({
+                        "type": "object",
+                        "properties": {
+                            "alerts": { "type": "array", "items": schema_ref("Alert") },
+                            "total": { "type": "integer" }
+                        }
+                    })),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error"))
+                }
+            }
+        },
+        "/audit": {
+            "get": {
+                "parameters": [
+                    { "name": "actor", "in": "query", "schema": { "type": "string", "example": "api-key:1a2b3c4d" } },
+                    { "name": "app_name", "in": "query", "schema": { "type": "string" } },
+                    { "name": "method", "in": "query", "schema": { "type": "string", "example": "POST" } },
+                    { "name": "since", "in": "query", "schema": { "type": "string", "format": "date-time" } },
+                    { "name": "until", "in": "query", "schema": { "type": "string", "format": "date-time" } },
+                    { "name": "limit", "in": "query", "schema": { "type": "integer", "default": 100, "minimum": 1, "maximum": 1000 } }
+                ],
+                "summary": "Read the audit log",
+                "description": "Requires the `admin` role. Every request other than GET, HEAD and OPTIONS is recorded with its caller and response status, most recent first.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The audit log entries", json!({
+                        "type": "object",
+                        "properties": {
+                            "entries": { "type": "array", "items": schema_ref("AuditEntry") },
+                            "total": { "type": "integer" }
+                        }
+                    })),
+                    "400": json_response("Invalid query parameters", schema_ref("ValidationErrors")),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role for this operation", schema_ref("Error"))
+                }
+            }
+        },
+        "/backup": {
+            "get": {
+                "summary": "Download a backup of the node",
+                "description": "Requires the `admin` role. A tar archive of the app registry, `nephelios.yml`, the settings and deployment history of each app and the alert rules. Docker secrets are listed by name in `manifest.json`, their values are not included.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": {
+                        "description": "The backup archive",
+                        "content": { "application/x-tar": { "schema": { "type": "string", "format": "binary" } } }
+                    },
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role for this operation", schema_ref("Error")),
+                    "500": json_response("The state could not be read", schema_ref("Error"))
+                }
+            }
+        },
+        "/restore": {
+            "post": {
+                "summary": "Restore a backup on the node",
+                "description": "Requires the `admin` role. Overwrites the stored state with a `/backup` archive (at most 64 MiB) and deploys the stack, unless secrets it refers to are missing.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/x-tar": { "schema": { "type": "string", "format": "binary" } } }
+                },
+                "responses": {
+                    "200": json_response("What was restored", json!({
+                        "type": "object",
+                        "properties": {
+                            "message": { "type": "string" },
+                            "restored": {
+                                "type": "object",
+                                "properties": {
+                                    "apps": { "type": "array", "items": { "type": "string" } },
+                                    "deployments": { "type": "integer" },
+                                    "alert_rules": { "type": "integer" },
+                                    "missing_secrets": { "type": "array", "items": { "type": "string" } },
+                                    "stack_deployed": { "type": "boolean" }
+                                }
+                            }
+                        }
+                    })),
+                    "400": json_response("Invalid archive, or the state could not be restored", schema_ref("Error")),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role for this operation", schema_ref("Error"))
+                }
+            }
+        },
+        "/system/reconcile": {
+            "get": secured_operation(
+                "Get the report of the last reconciliation of the stack",
+                "viewer",
+                None,
+                vec![
+                    ("200", json_response("The last report", schema_ref("ReconcileReport"))),
+                    ("404", json_response("No reconciliation ran yet", schema_ref("Error"))),
+                ],
+            ),
+            "post": secured_operation(
+                "Reconcile the stack file, the app registry and the Swarm services",
+                "admin",
+                None,
+                vec![
+                    ("200", json_response("The report", schema_ref("ReconcileReport"))),
+                    ("500", json_response("The stack could not be read", schema_ref("Error"))),
+                ],
+            )
+        },
+        "/system/gc": {
+            "get": secured_operation(
+                "Get the report of the last collection of orphaned resources",
+                "viewer",
+                None,
+                vec![
+                    ("200", json_response("The last report", schema_ref("GcReport"))),
+                    ("404", json_response("No collection ran yet", schema_ref("Error"))),
+                ],
+            ),
+            "post": gc_operation
+        },
+        "/nodes": {
+            "get": {
+                "summary": "List the nodes of the Swarm",
+                "description": "Requires the `viewer` role. Managers come first, then workers, by hostname.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The nodes", json!({
+                        "type": "object",
+                        "properties": {
+                            "nodes": { "type": "array", "items": schema_ref("NodeInfo") },
+                            "total": { "type": "integer" }
+                        }
+                    })),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "500": json_response("The Swarm could not be inspected", schema_ref("Error"))
+                }
+            }
+        },
+        "/nodes/join-token": {
+            "get": {
+                "parameters": [
+                    { "name": "role", "in": "query", "schema": { "type": "string", "enum": ["worker", "manager"], "default": "worker" } }
+                ],
+                "summary": "Get the token to join the Swarm",
+                "description": "Requires the `admin` role. The token lets any host join the Swarm with the requested role, keep it secret.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The join token", json!({
+                        "type": "object",
+                        "properties": {
+                            "role": { "type": "string", "enum": ["worker", "manager"] },
+                            "token": { "type": "string" },
+                            "manager_address": { "type": "string", "nullable": true },
+                            "command": { "type": "string", "nullable": true, "example": "docker swarm join --token SWMTKN-1-... 10.0.0.1:2377" }
+                        }
+                    })),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role for this operation", schema_ref("Error")),
+                    "500": json_response("The token could not be read", schema_ref("Error"))
+                }
+            }
+        },
+        "/nodes/{id}/drain": {
+            "post": {
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "description": "ID or hostname of the node", "schema": { "type": "string" } }
+                ],
+                "summary": "Drain a node",
+                "description": "Requires the `admin` role. Swarm stops the tasks of the node and schedules them on the other nodes. The progress is pushed over `/ws/nodes` until no task is left.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The node after the update", schema_ref("NodeInfo")),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role for this operation", schema_ref("Error")),
+                    "404": json_response("Unknown node", schema_ref("Error")),
+                    "409": json_response("The node is the last active node", schema_ref("Error")),
+                    "500": json_response("The node could not be updated", schema_ref("Error"))
+                }
+            }
+        },
+        "/nodes/{id}/activate": {
+            "post": {
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "description": "ID or hostname of the node", "schema": { "type": "string" } }
+                ],
+                "summary": "Make a node available again",
+                "description": "Requires the `admin` role. New tasks may be scheduled on the node, running tasks are not moved back.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The node after the update", schema_ref("NodeInfo")),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role for this operation", schema_ref("Error")),
+                    "404": json_response("Unknown node", schema_ref("Error")),
+                    "500": json_response("The node could not be updated", schema_ref("Error"))
+                }
+            }
+        },
+        "/nodes/{id}/labels": {
+            "put": {
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "description": "ID or hostname of the node", "schema": { "type": "string" } }
+                ],
+                "summary": "Set labels of a node",
+                "description": "Requires the `admin` role. Labels that are not listed are kept. Apps are pinned to or spread across labeled nodes with their placement (e.g., `node.labels.disk==ssd`).",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "required": ["labels"],
+                        "properties": {
+                            "labels": { "type": "object", "additionalProperties": { "type": "string" }, "example": { "disk": "ssd", "zone": "eu-west-1a" } }
+                        }
+                    } } }
+                },
+                "responses": {
+                    "200": json_response("The node after the update", schema_ref("NodeInfo")),
+                    "400": json_response("Invalid label names or values", schema_ref("ValidationErrors")),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role for this operation", schema_ref("Error")),
+                    "404": json_response("Unknown node", schema_ref("Error")),
+                    "500": json_response("The node could not be updated", schema_ref("Error"))
+                }
+            },
+            "delete": {
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "description": "ID or hostname of the node", "schema": { "type": "string" } }
+                ],
+                "summary": "Remove labels of a node",
+                "description": "Requires the `admin` role.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "required": ["keys"],
+                        "properties": { "keys": { "type": "array", "items": { "type": "string" } } }
+                    } } }
+                },
+                "responses": {
+                    "200": json_response("The node after the update", schema_ref("NodeInfo")),
+                    "400": json_response("Invalid label names", schema_ref("ValidationErrors")),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role for this operation", schema_ref("Error")),
+                    "404": json_response("Unknown node, or labels not set on it", schema_ref("Error")),
+                    "500": json_response("The node could not be updated", schema_ref("Error"))
+                }
+            }
+        },
+        "/alerts/rules": {
+            "get": {
+                "summary": "List the alert rules",
+                "description": "Requires the `viewer` role.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The alert rules", json!({
+                        "type": "object",
+                        "properties": {
+                            "rules": { "type": "array", "items": schema_ref("AlertRule") },
+                            "total": { "type": "integer" }
+                        }
+                    })),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error"))
+                }
+            },
+            "post": secured_operation(
+                "Add an alert rule",
+                "admin",
+                Some("AlertRuleRequest"),
+                vec![("201", json_response("The stored rule", schema_ref("AlertRule")))],
+            )
+        },
+        "/alerts/rules/{id}": {
+            "delete": {
+                "parameters": [{
+                    "name": "id",
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string", "format": "uuid" }
+                }],
+                "summary": "Delete an alert rule",
+                "description": "Requires the `admin` role.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The rule was deleted", json!({
+                        "type": "object",
+                        "properties": { "message": { "type": "string" } }
+                    })),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role for this operation", schema_ref("Error")),
+                    "404": json_response("Unknown alert rule", schema_ref("Error"))
+                }
+            }
+        },
+        "/webhooks": {
+            "get": secured_operation(
+                "List the global webhooks",
+                "admin",
+                None,
+                vec![("200", webhooks_result.clone())],
+            ),
+            "post": secured_operation(
+                "Register a webhook receiving the events of every app",
+                "admin",
+                Some("WebhookRequest"),
+                vec![("201", webhook_created_result.clone())],
+            )
+        },
+        "/webhooks/{id}": {
+            "delete": delete_webhook_operation
+        },
+        "/deployments/{id}": {
+            "get": {
+                "parameters": [{
+                    "name": "id",
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string", "format": "uuid" }
+                }],
+                "summary": "Get the status of a deployment",
+                "description": "Requires the `viewer` role.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The deployment", schema_ref("Deployment")),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "404": json_response("Unknown deployment", schema_ref("Error"))
+                }
+            }
+        ,
+            "patch": deployment_priority_operation
+        },
+        "/deployments/{id}/cancel": {
+            "post": deployment_cancel_operation
+        },
+        "/deployment-queue": {
+            "get": secured_operation(
+                "List the queued deployments, in the order they start",
+                "viewer",
+                None,
+                vec![("200", json_response("The deployment queue", json!({
+                    "type": "object",
+                    "properties": {
+                        "deployments": { "type": "array", "items": schema_ref("Deployment") },
+                        "total": { "type": "integer" },
+                        "max_concurrent_deployments": {
+                            "type": "integer",
+                            "description": "Deployments running at once across apps, 0 for no limit"
+                        }
+                    }
+                })))],
+            )
+        },
+        "/apps/{app_name}/deployments": {
+            "get": {
+                "parameters": [{
+                    "name": "app_name",
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" }
+                }],
+                "summary": "List the deployments of an app",
+                "description": "Requires the `viewer` role. Running deployments come first, then the history, most recent first.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The deployments", json!({
+                        "type": "object",
+                        "properties": {
+                            "app_name": { "type": "string" },
+                            "deployments": { "type": "array", "items": schema_ref("Deployment") },
+                            "total": { "type": "integer" }
+                        }
+                    })),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error"))
+                }
+            }
+        },
+        "/apps/{app_name}": {
+            "patch": app_operation(
+                "Update the build settings",
+                "deployer",
+                Some("UpdateAppRequest"),
+                vec![
+                    ("200", json_response("Settings updated", schema_ref("AppBuildSettings"))),
+                    ("202", json_response("Settings updated and a deployment job was created", schema_ref("AppBuildSettings"))),
+                    ("404", json_response("The app does not exist", schema_ref("Error"))),
+                ],
+            )
+        },
+        "/apps/{app_name}/redeploy": {
+            "post": app_operation(
+                "Rebuild and redeploy from the tracked repository",
+                "deployer",
+                None,
+                vec![
+                    ("202", json_response("The deployment job was created", schema_ref("DeploymentCreated"))),
+                    ("404", json_response("The app does not exist", schema_ref("Error"))),
+                ],
+            )
+        },
+        "/apps/{app_name}/rollback": {
+            "post": app_operation(
+                "Roll back to a previous release",
+                "deployer",
+                Some("RollbackRequest"),
+                vec![
+                    ("202", json_response("The rollback job was created", schema_ref("RollbackCreated"))),
+                    ("404", json_response("The app or the release does not exist", schema_ref("Error"))),
+                ],
+            )
+        },
+        "/apps/{app_name}/restart": {
+            "post": app_operation(
+                "Force a rolling restart without rebuilding",
+                "deployer",
+                None,
+                vec![
+                    ("200", json_response("The restart was started", json!({
+                        "type": "object",
+                        "properties": {
+                            "message": { "type": "string" },
+                            "app_name": { "type": "string" }
+                        }
+                    }))),
+                    ("404", json_response("The app does not exist", schema_ref("Error"))),
+                ],
+            )
+        },
+        "/apps/{app_name}/scale": {
+            "post": app_setting_operation(
+                "Set the number of replicas",
+                "ScaleRequest",
+                setting_result("replicas", json!({ "type": "integer" })),
+            )
+        },
+        "/apps/{app_name}/logs": {
+            "get": {
+                "parameters": [
+                    { "name": "app_name", "in": "path", "required": true, "schema": { "type": "string" } },
+                    { "name": "tail", "in": "query", "schema": { "type": "integer", "minimum": 0 } },
+                    { "name": "follow", "in": "query", "schema": { "type": "boolean", "default": false } }
+                ],
+                "summary": "Stream the logs of an app",
+                "description": "Requires the `deployer` role. Lines of every replica are streamed as chunked plain text.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": text_response("The log lines"),
+                    "400": json_response("Invalid query parameters", schema_ref("Error")),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role for this operation", schema_ref("Error")),
+                    "404": json_response("The app does not exist", schema_ref("Error"))
+                }
+            }
+        },
+        "/apps/{app_name}/metrics": {
+            "get": {
+                "parameters": [
+                    { "name": "app_name", "in": "path", "required": true, "schema": { "type": "string" } },
+                    { "name": "range", "in": "query", "schema": { "type": "string", "default": "1h", "example": "30m" } }
+                ],
+                "summary": "Get the metrics history of an app",
+                "description": "Requires the `viewer` role. CPU and memory usage summed over the replicas, oldest first, kept for 24 hours.",
+                "security": [{ "bearerAuth": [] }, { "apiKeyAuth": [] }],
+                "responses": {
+                    "200": json_response("The samples", json!({
+                        "type": "object",
+                        "properties": {
+                            "app_name": { "type": "string" },
+                            "range": { "type": "string" },
+                            "samples": { "type": "array", "items": schema_ref("MetricsSample") }
+                        }
+                    })),
+                    "400": json_response("Invalid range", schema_ref("ValidationErrors")),
+                    "401": json_response("Missing or invalid API key or token", schema_ref("Error")),
+                    "404": json_response("The app does not exist", schema_ref("Error"))
+                }
+            }
+        },
+        "/apps/{app_name}/exec": {
+            "post": app_operation(
+                "Run a command in an app container",
+                "deployer",
+                Some("ExecRequest"),
+                vec![
+                    (
+                        "200",
+                        json!({
+                            "description": "The command output, then its exit code, as newline-delimited JSON events",
+                            "content": { "application/x-ndjson": { "schema": schema_ref("ExecEvent") } }
+                        }),
+                    ),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                ],
+            )
+        },
+        "/apps/bulk": { "post": bulk_operation },
+        "/start": {
+            "post": secured_operation(
+                "Start an app",
+                "deployer",
+                Some("AppActionRequest"),
+                vec![
+                    ("200", text_response("The app was started")),
+                    (
+                        "403",
+                        json_response("The replica would exceed the quota", schema_ref("Error")),
+                    ),
+                    ("404", json_response("Unknown app", schema_ref("Error"))),
+                ],
+            )
+        },
+        "/stop": {
+            "post": secured_operation(
+                "Stop an app",
+                "deployer",
+                Some("AppActionRequest"),
+                vec![("200", text_response("The app was stopped"))],
+            )
+        },
+        "/remove": {
+            "post": secured_operation(
+                "Remove an app",
+                "admin",
+                Some("RemoveAppRequest"),
+                vec![
+                    ("201", text_response("The app was removed")),
+                    ("200", json_response("The app was soft-deleted", json!({
+                        "type": "object",
+                        "properties": {
+                            "message": { "type": "string" },
+                            "app_name": { "type": "string" },
+                            "deleted_at": { "type": "string", "format": "date-time" },
+                            "purge_at": { "type": "string", "format": "date-time" }
+                        }
+                    }))),
+                    ("404", json_response("The app does not exist", schema_ref("Error"))),
+                    ("409", json_response("The app is already deleted", schema_ref("Error"))),
+                ],
+            )
+        },
+        "/apps/{app_name}/restore": {
+            "post": app_operation(
+                "Restore a soft-deleted app",
+                "admin",
+                None,
+                vec![
+                    ("200", json_response("The app was restored", json!({
+                        "type": "object",
+                        "properties": {
+                            "message": { "type": "string" },
+                            "app_name": { "type": "string" },
+                            "replicas": { "type": "integer" }
+                        }
+                    }))),
+                    ("404", json_response("The app is not deleted", schema_ref("Error"))),
+                ],
+            )
+        },
+        "/apps/{app_name}/domains": {
+            "post": app_setting_operation(
+                "Attach a custom domain",
+                "DomainRequest",
+                setting_result("domains", string_list.clone()),
+            ),
+            "delete": app_setting_operation(
+                "Detach a custom domain",
+                "DomainRequest",
+                setting_result("domains", string_list.clone()),
+            )
+        },
+        "/apps/{app_name}/env": {
+            "get": app_operation(
+                "Get the environment variables",
+                "deployer",
+                None,
+                vec![
+                    (
+                        "200",
+                        json_response(
+                            "The environment variables, sensitive values redacted",
+                            setting_result("env", schema_ref("Environment")),
+                        ),
+                    ),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                ],
+            ),
+            "put": app_setting_operation(
+                "Set environment variables",
+                "EnvRequest",
+                setting_result("env", schema_ref("Environment")),
+            ),
+            "delete": app_setting_operation(
+                "Remove environment variables",
+                "EnvKeysRequest",
+                setting_result("env", schema_ref("Environment")),
+            )
+        },
+        "/apps/{app_name}/secrets": {
+            "get": app_secrets_operation,
+            "put": set_app_secrets_operation,
+            "delete": delete_app_secrets_operation
+        },
+        "/apps/{app_name}/ip-allowlist": {
+            "put": app_setting_operation(
+                "Replace the IP allowlist",
+                "IpAllowlistRequest",
+                setting_result("ip_allowlist", string_list),
+            )
+        },
+        "/apps/{app_name}/http-policy": {
+            "put": app_setting_operation(
+                "Set the plain HTTP policy",
+                "HttpPolicyRequest",
+                setting_result("http_policy", json!({ "type": "string", "nullable": true })),
+            )
+        },
+        "/apps/{app_name}/resources": {
+            "put": app_setting_operation(
+                "Update the CPU and memory limits",
+                "ResourceLimits",
+                setting_result("resources", schema_ref("ResourceLimits")),
+            )
+        },
+        "/apps/{app_name}/placement": {
+            "put": app_setting_operation(
+                "Set the nodes the app runs on",
+                "Placement",
+                setting_result("placement", schema_ref("Placement")),
+            )
+        },
+        "/apps/{app_name}/update-config": {
+            "put": app_setting_operation(
+                "Configure the rolling updates",
+                "UpdateConfig",
+                setting_result("update_config", schema_ref("UpdateConfig")),
+            )
+        },
+        "/apps/{app_name}/security": {
+            "put": app_setting_operation(
+                "Harden the containers",
+                "SecurityOptions",
+                setting_result("security", schema_ref("SecurityOptions")),
+            )
+        },
+        "/apps/{app_name}/autoscaling": {
+            "put": app_operation(
+                "Scale the replicas with their CPU usage",
+                "deployer",
+                Some("AutoscalingConfig"),
+                vec![
+                    ("200", json_response("Setting updated", setting_result("autoscaling", schema_ref("AutoscalingConfig")))),
+                    ("404", json_response("The app does not exist", schema_ref("Error"))),
+                    ("409", json_response("The app has a scaling schedule", schema_ref("Error"))),
+                ],
+            ),
+            "delete": app_operation(
+                "Disable autoscaling",
+                "deployer",
+                None,
+                vec![
+                    (
+                        "200",
+                        json_response(
+                            "Autoscaling disabled, the replicas are kept",
+                            setting_result("autoscaling", json!({ "type": "object", "nullable": true })),
+                        ),
+                    ),
+                    ("404", json_response("The app does not exist", schema_ref("Error"))),
+                ],
+            )
+        },
+        "/apps/{app_name}/scaling-schedule": {
+            "put": app_operation(
+                "Scale the replicas by time of day and day of week",
+                "deployer",
+                Some("ScalingSchedule"),
+                vec![
+                    ("200", json_response("Setting updated", setting_result("scaling_schedule", schema_ref("ScalingSchedule")))),
+                    ("404", json_response("The app does not exist", schema_ref("Error"))),
+                    ("409", json_response("The app has autoscaling", schema_ref("Error"))),
+                ],
+            ),
+            "delete": app_operation(
+                "Remove the scaling schedule",
+                "deployer",
+                None,
+                vec![
+                    (
+                        "200",
+                        json_response(
+                            "Schedule removed, the replicas are kept",
+                            setting_result("scaling_schedule", json!({ "type": "object", "nullable": true })),
+                        ),
+                    ),
+                    ("404", json_response("The app does not exist", schema_ref("Error"))),
+                ],
+            )
+        },
+        "/apps/{app_name}/volumes": {
+            "get": app_operation(
+                "List the volumes",
+                "viewer",
+                None,
+                vec![
+                    (
+                        "200",
+                        json_response(
+                            "The volumes",
+                            setting_result("volumes", json!({ "type": "array", "items": schema_ref("AppVolume") })),
+                        ),
+                    ),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                ],
+            ),
+            "put": app_setting_operation(
+                "Replace the volumes",
+                "VolumesRequest",
+                setting_result("volumes", json!({ "type": "array", "items": schema_ref("AppVolume") })),
+            )
+        },
+        "/apps/{app_name}/volumes/{volume}/backup": {
+            "post": volume_backup_operation
+        },
+        "/apps/{app_name}/volumes/{volume}/backups": {
+            "get": volume_backups_operation
+        },
+        "/apps/{app_name}/volumes/{volume}/backups/{backup_id}": {
+            "get": volume_download_operation
+        },
+        "/apps/{app_name}/volumes/{volume}/restore": {
+            "post": volume_restore_operation
+        },
+        "/apps/{app_name}/addons": {
+            "get": app_operation(
+                "List the addons",
+                "viewer",
+                None,
+                vec![
+                    (
+                        "200",
+                        json_response(
+                            "The addons, with the status of their service",
+                            setting_result("addons", json!({ "type": "array", "items": schema_ref("Addon") })),
+                        ),
+                    ),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                ],
+            ),
+            "post": app_operation(
+                "Provision an addon",
+                "deployer",
+                Some("AddonRequest"),
+                vec![
+                    (
+                        "201",
+                        json_response("The addon, whose connection URL was added to the environment of the app", addon_result),
+                    ),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                    (
+                        "409",
+                        json_response("The app already has this addon, or its environment variable is set", schema_ref("Error")),
+                    ),
+                ],
+            )
+        },
+        "/apps/{app_name}/addons/{type}": {
+            "delete": remove_addon_operation
+        },
+        "/apps/{app_name}/cron-jobs": {
+            "get": app_operation(
+                "List the cron jobs",
+                "viewer",
+                None,
+                vec![
+                    (
+                        "200",
+                        json_response(
+                            "The cron jobs, with when they run next",
+                            setting_result("cron_jobs", json!({ "type": "array", "items": schema_ref("CronJob") })),
+                        ),
+                    ),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                ],
+            ),
+            "post": app_operation(
+                "Add a cron job",
+                "deployer",
+                Some("CronJobRequest"),
+                vec![
+                    ("201", json_response("The new cron job", cron_job_result)),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                ],
+            )
+        },
+        "/apps/{app_name}/cron-jobs/{id}": {
+            "put": update_cron_job_operation,
+            "delete": delete_cron_job_operation
+        },
+        "/apps/{app_name}/cron-jobs/{id}/runs": {
+            "get": cron_runs_operation
+        },
+        "/apps/{app_name}/cron-jobs/{id}/run": {
+            "post": run_cron_job_operation
+        },
+        "/apps/{app_name}/jobs": {
+            "get": app_operation(
+                "List the one-off jobs",
+                "viewer",
+                None,
+                vec![
+                    (
+                        "200",
+                        json_response(
+                            "The last 100 jobs, most recent first, without their output",
+                            setting_result("jobs", json!({ "type": "array", "items": schema_ref("Job") })),
+                        ),
+                    ),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                ],
+            ),
+            "post": app_operation(
+                "Run a one-off job",
+                "deployer",
+                Some("JobRequest"),
+                vec![
+                    (
+                        "200",
+                        json!({
+                            "description": "The job, its output, then its exit code, as newline-delimited JSON events",
+                            "content": { "application/x-ndjson": { "schema": schema_ref("JobEvent") } }
+                        }),
+                    ),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                ],
+            )
+        },
+        "/apps/{app_name}/jobs/{id}": {
+            "get": job_operation
+        },
+        "/apps/{app_name}/notifications": {
+            "get": app_operation(
+                "Get the email notification recipients",
+                "viewer",
+                None,
+                vec![
+                    (
+                        "200",
+                        json_response("The addresses emailed about the app", schema_ref("AppNotifications")),
+                    ),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                ],
+            ),
+            "put": app_setting_operation(
+                "Replace the email notification recipients",
+                "NotificationsRequest",
+                schema_ref("AppNotifications"),
+            )
+        },
+        "/apps/{app_name}/webhooks": {
+            "get": app_operation(
+                "List the webhooks of the app",
+                "viewer",
+                None,
+                vec![
+                    ("200", webhooks_result),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                ],
+            ),
+            "post": app_operation(
+                "Register a webhook receiving the events of the app",
+                "deployer",
+                Some("WebhookRequest"),
+                vec![
+                    ("201", webhook_created_result),
+                    (
+                        "404",
+                        json_response("The app does not exist", schema_ref("Error")),
+                    ),
+                ],
+            )
+        },
+        "/apps/{app_name}/webhooks/{id}": {
+            "delete": delete_app_webhook_operation
+        },
+        "/apps/{app_name}/sticky-sessions": {
+            "put": app_setting_operation(
+                "Configure sticky sessions",
+                "StickySessionsRequest",
+                setting_result("sticky", schema_ref("StickySessions")),
+            )
+        },
+        "/apps/{app_name}/middlewares": {
+            "put": app_setting_operation(
+                "Replace the custom Traefik middlewares",
+                "MiddlewaresRequest",
+                setting_result("middlewares", json!({ "type": "object" })),
+            )
+        },
+        "/apps/{app_name}/protocol": {
+            "put": app_setting_operation(
+                "Set the backend protocol",
+                "ProtocolRequest",
+                setting_result("protocol", schema_ref("Protocol")),
+            )
+        },
+        "/apps/{app_name}/ports": {
+            "put": app_setting_operation(
+                "Replace the additional exposed ports",
+                "PortsRequest",
+                setting_result("ports", json!({ "type": "array", "items": schema_ref("ExposedPort") })),
+            )
+        },
+        "/apps/{app_name}/maintenance": {
+            "post": app_setting_operation(
+                "Toggle the maintenance page",
+                "MaintenanceRequest",
+                setting_result("maintenance", json!({ "type": "boolean" })),
+            )
+        },
+        "/webhooks/github": {
+            "post": {
+                "summary": "GitHub push webhook",
+                "description": "Authenticated with the `X-Hub-Signature-256` HMAC signature.",
+                "parameters": [
+                    { "name": "X-GitHub-Event", "in": "header", "required": true, "schema": { "type": "string" } },
+                    { "name": "X-Hub-Signature-256", "in": "header", "required": true, "schema": { "type": "string" } }
+                ],
+                "requestBody": { "required": true, "content": { "application/json": { "schema": { "type": "object" } } } },
+                "responses": {
+                    "200": json_response("The event was processed or ignored", json!({ "type": "object" })),
+                    "401": json_response("Invalid signature", schema_ref("Error"))
+                }
+            }
+        },
+        "/ws": {
+            "get": {
+                "summary": "Deployment status stream (WebSocket)",
+                "description": "Requires the `viewer` role. Browsers can pass an API key in the `api_key` query parameter or a JWT in `access_token`. A connection opened without credentials must send `{\"action\": \"auth\", \"token\": \"<key or JWT>\"}` as its first message within 10 seconds, or it is closed with code 4401 (4403 when the role is insufficient). On connection, the recent updates of the subscribed apps are replayed with `\"replayed\": true`. Without `app`, updates of every app are sent. Clients change their subscriptions by sending `{\"action\": \"subscribe\", \"app\": \"<name>\"}` or `{\"action\": \"unsubscribe\", \"app\": \"<name>\"}`, acknowledged with the list of subscribed apps. Connections that answer no ping for 75 seconds are closed. Updates are `DeploymentStatus` messages.",
+                "parameters": [
+                    { "name": "app", "in": "query", "description": "Comma-separated apps to receive updates for", "schema": { "type": "string" } },
+                    { "name": "api_key", "in": "query", "schema": { "type": "string" } },
+                    { "name": "access_token", "in": "query", "schema": { "type": "string" } }
+                ],
+                "responses": {
+                    "101": { "description": "Switching to the WebSocket protocol" },
+                    "401": json_response("Invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role", schema_ref("Error"))
+                }
+            }
+        },
+        "/ws/metrics": {
+            "get": {
+                "summary": "Live container metrics (WebSocket)",
+                "description": "Requires the `viewer` role, credentials are passed as for `/ws`. Pushes a `MetricsSnapshot` on every collection, every `METRICS_INTERVAL` seconds (default 15).",
+                "parameters": [
+                    { "name": "app", "in": "query", "description": "Comma-separated apps to receive metrics for", "schema": { "type": "string" } },
+                    { "name": "api_key", "in": "query", "schema": { "type": "string" } },
+                    { "name": "access_token", "in": "query", "schema": { "type": "string" } }
+                ],
+                "responses": {
+                    "101": { "description": "Switching to the WebSocket protocol" },
+                    "401": json_response("Invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role", schema_ref("Error"))
+                }
+            }
+        },
+        "/ws/nodes": {
+            "get": {
+                "summary": "Node changes (WebSocket)",
+                "description": "Requires the `viewer` role, credentials are passed as for `/ws`. Pushes the node events tagged by `type`: `node_availability_changed` (`node_id`, `hostname`, `availability`), `node_labels_changed` (`node_id`, `hostname`, `labels`), `node_drain_progress` (`node_id`, `hostname`, `tasks_remaining`) while a drained node still runs tasks, and `node_drained` (`node_id`, `hostname`) once none is left.",
+                "parameters": [
+                    { "name": "api_key", "in": "query", "schema": { "type": "string" } },
+                    { "name": "access_token", "in": "query", "schema": { "type": "string" } }
+                ],
+                "responses": {
+                    "101": { "description": "Switching to the WebSocket protocol" },
+                    "401": json_response("Invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role", schema_ref("Error"))
+                }
+            }
+        },
+        "/ws/logs/{name}": {
+            "get": {
+                "summary": "Live logs of an app (WebSocket)",
+                "description": "Requires the `deployer` role, credentials are passed as for `/ws`. Streams the logs of the app containers running on this node, one JSON message per line, and closes when they stop.",
+                "parameters": [
+                    { "name": "name", "in": "path", "required": true, "schema": { "type": "string" } },
+                    { "name": "tail", "in": "query", "description": "Past lines of each container sent before following", "schema": { "type": "integer", "minimum": 0, "default": 100 } },
+                    { "name": "api_key", "in": "query", "schema": { "type": "string" } },
+                    { "name": "access_token", "in": "query", "schema": { "type": "string" } }
+                ],
+                "responses": {
+                    "101": { "description": "Switching to the WebSocket protocol" },
+                    "401": json_response("Invalid API key or token", schema_ref("Error")),
+                    "403": json_response("Insufficient role", schema_ref("Error")),
+                    "404": json_response("Unknown application", schema_ref("Error"))
+                }
+            }
+        }
+    })
+}
+
+/// Builds the schema of a `DeploymentEvent` variant, tagged by `type`.
+fn event_schema(event_type: &str, mut properties: Value) -> Value {
+    properties["type"] = json!({ "type": "string", "enum": [event_type] });
+    json!({
+        "type": "object",
+        "required": ["type"],
+        "properties": properties
+    })
+}
+
+/// The schemas of the request and response bodies.
+fn schemas() -> Value {
+    let app_name = json!({
+        "type": "string",
+        "maxLength": 53,
+        "pattern": "^[a-z0-9]([a-z0-9-]*[a-z0-9])?$"
+    });
+    // Existing apps may predate the pattern of new app names
+    let existing_app_name = json!({ "type": "string", "minLength": 1, "maxLength": 63 });
+    let string_or_number = json!({ "oneOf": [{ "type": "string" }, { "type": "number" }] });
+
+    json!({
+        "Error": {
+            "type": "object",
+            "properties": { "error": { "type": "string" } }
+        },
+        "ValidationErrors": {
+            "type": "object",
+            "properties": {
+                "errors": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "field": { "type": "string" },
+                            "message": { "type": "string" }
+                        }
+                    }
+                }
+            }
+        },
+        "DeploymentCreated": {
+            "type": "object",
+            "properties": {
+                "message": { "type": "string" },
+                "deployment_id": { "type": "string", "format": "uuid" }
+            }
+        },
+        "DeploymentEvent": {
+            "description": "What happened during a deployment, identified by `type`",
+            "oneOf": [
+                event_schema("redeploy_triggered", json!({ "reason": { "type": "string" } })),
+                event_schema("clone_started", json!({})),
+                event_schema("clone_progress", json!({
+                    "received_objects": { "type": "integer" },
+                    "total_objects": { "type": "integer" },
+                    "percent": { "type": "integer" }
+                })),
+                event_schema("clone_succeeded", json!({
+                    "commit_sha": { "type": "string", "nullable": true },
+                    "commit_message": { "type": "string", "nullable": true }
+                })),
+                event_schema("build_started", json!({})),
+                event_schema("build_succeeded", json!({})),
+                event_schema("deploy_started", json!({})),
+                event_schema("deploy_succeeded", json!({})),
+                event_schema("rollback_started", json!({ "image": { "type": "string" } })),
+                event_schema("rollback_succeeded", json!({
+                    "image": { "type": "string" },
+                    "details": { "type": "object" }
+                })),
+                event_schema("rolled_back", json!({
+                    "image": { "type": "string" },
+                    "reason": { "type": "string" }
+                })),
+                event_schema("app_deployed", json!({ "app": { "type": "object" } })),
+                event_schema("autoscaled", json!({
+                    "from": { "type": "integer" },
+                    "to": { "type": "integer" },
+                    "cpu_percent": { "type": "number", "description": "Average CPU usage of the replicas, in percent of their CPU reservation" }
+                })),
+                event_schema("failed", json!({ "message": { "type": "string" } }))
+            ],
+            "discriminator": { "propertyName": "type" }
+        },
+        "DeploymentStatus": {
+            "type": "object",
+            "description": "A message of the `/ws` stream. `status`, `step` and `app_deployed` are derived from `event` for older clients.",
+            "properties": {
+                "version": { "type": "integer", "description": "Schema version of the message, currently 1" },
+                "app_name": { "type": "string" },
+                "event": schema_ref("DeploymentEvent"),
+                "status": { "type": "string", "enum": ["in_progress", "success", "deployed", "error"] },
+                "step": { "type": "string" },
+                "timestamp": { "type": "integer", "description": "Unix time in milliseconds" },
+                "app_deployed": { "type": "object", "nullable": true },
+                "deployment_id": { "type": "string", "nullable": true },
+                "replayed": { "type": "boolean", "description": "Set on the updates replayed on connection" }
+            }
+        },
+        "Deployment": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "format": "uuid" },
+                "app_name": { "type": "string" },
+                "initiator": { "type": "string" },
+                "kind": { "type": "string", "enum": ["deploy", "rollback"] },
+                "state": { "type": "string", "enum": ["queued", "in_progress", "succeeded", "failed", "cancelled"] },
+                "priority": schema_ref("DeploymentPriority"),
+                "stage": { "type": "string", "nullable": true },
+                "created_at": { "type": "string", "format": "date-time" },
+                "started_at": { "type": "string", "format": "date-time", "nullable": true },
+                "updated_at": { "type": "string", "format": "date-time" },
+                "finished_at": { "type": "string", "format": "date-time", "nullable": true },
+                "duration_ms": { "type": "integer", "nullable": true },
+                "git_ref": { "type": "string", "nullable": true },
+                "commit_sha": { "type": "string", "nullable": true },
+                "image": { "type": "string", "nullable": true },
+                "error": { "type": "string", "nullable": true },
+                "result": { "type": "object", "nullable": true },
+                "log_path": { "type": "string", "nullable": true, "description": "File on the Nephelios host the status updates of the deployment are written to" }
+            }
+        },
+        "Protocol": { "type": "string", "enum": ["http", "h2c", "grpc"] },
+        "DeploymentPriority": {
+            "type": "string",
+            "enum": ["low", "normal", "high"],
+            "description": "Queued deployments start by priority, then oldest first"
+        },
+        "AppInfo": {
+            "type": "object",
+            "properties": {
+                "app_name": { "type": "string" },
+                "app_type": { "type": "string" },
+                "github_url": { "type": "string" },
+                "domain": { "type": "string" },
+                "created_at": { "type": "string" },
+                "status": { "type": "string" },
+                "swarm_task_name": { "type": "string", "nullable": true },
+                "git_ref": { "type": "string", "nullable": true },
+                "commit_sha": { "type": "string", "nullable": true },
+                "commit_message": { "type": "string", "nullable": true },
+                "domains": { "type": "array", "items": { "type": "string" } }
+            }
+        },
+        "AppList": {
+            "type": "object",
+            "properties": {
+                "status": { "type": "string" },
+                "apps": { "type": "array", "items": schema_ref("AppInfo") },
+                "total": { "type": "integer" }
+            }
+        },
+        "ResourceLimits": {
+            "type": "object",
+            "properties": {
+                "cpu_limit": string_or_number.clone(),
+                "memory_limit": string_or_number.clone(),
+                "cpu_reservation": string_or_number.clone(),
+                "memory_reservation": string_or_number
+            }
+        },
+        "QuotaLimits": {
+            "type": "object",
+            "description": "Limits of an account, unset limits are not enforced",
+            "properties": {
+                "max_apps": { "type": "integer", "nullable": true, "description": "Maximum number of apps, soft-deleted ones included" },
+                "max_cpus": { "type": "number", "nullable": true, "description": "Maximum CPU reservations of every replica of the apps, in cores" },
+                "max_memory": { "type": "string", "nullable": true, "example": "4G", "description": "Maximum memory reservations of every replica of the apps" },
+                "build_minutes": { "type": "integer", "nullable": true, "description": "Maximum minutes spent deploying the apps per month (UTC)" }
+            }
+        },
+        "QuotaUsage": {
+            "type": "object",
+            "properties": {
+                "apps": { "type": "integer" },
+                "cpus": { "type": "number", "description": "CPU reservations of every replica of the apps, in cores" },
+                "memory_bytes": { "type": "integer", "description": "Memory reservations of every replica of the apps, in bytes" },
+                "build_minutes": { "type": "integer", "description": "Minutes spent deploying the apps this month (UTC)" }
+            }
+        },
+        "AppTemplate": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "example": "ghost" },
+                "name": { "type": "string", "example": "Ghost" },
+                "description": { "type": "string" },
+                "source": {
+                    "type": "object",
+                    "description": "A published `image` deployed without a build, or a `repository` built like `/create` apps",
+                    "properties": {
+                        "type": { "type": "string", "enum": ["image", "repository"] },
+                        "image": { "type": "string", "example": "ghost:5-alpine" },
+                        "github_url": { "type": "string" },
+                        "app_type": { "type": "string", "enum": ["nodejs", "python"] },
+                        "install_command": { "type": "string" },
+                        "build_command": { "type": "string" },
+                        "run_command": { "type": "string" }
+                    }
+                },
+                "port": { "type": "string", "example": "2368" },
+                "cpu_limit": { "type": "string", "example": "1" },
+                "memory_limit": { "type": "string", "example": "1G" },
+                "volumes": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "path": { "type": "string" }
+                        }
+                    }
+                },
+                "variables": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string", "example": "url" },
+                            "description": { "type": "string" },
+                            "default": { "type": "string", "nullable": true, "description": "`{app_name}` and `{domain}` are replaced" },
+                            "required": { "type": "boolean", "description": "Whether a value must be given when there is no default" }
+                        }
+                    }
+                }
+            }
+        },
+        "TemplateDeployRequest": {
+            "type": "object",
+            "required": ["app_name"],
+            "properties": {
+                "app_name": app_name.clone(),
+                "env": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Values of the variables of the template, defaults are used for the omitted ones",
+                    "example": { "url": "https://blog.example.com" }
+                }
+            }
+        },
+        "CreateAppRequest": {
+            "type": "object",
+            "required": ["app_name", "github_url"],
+            "allOf": [schema_ref("ResourceLimits")],
+            "properties": {
+                "app_name": app_name.clone(),
+                "app_type": { "type": "string", "enum": ["nodejs", "python"], "default": "nodejs" },
+                "github_url": { "type": "string" },
+                "git_ref": { "type": "string" },
+                "git_token": { "type": "string" },
+                "deploy_key": { "type": "string" },
+                "clone_depth": { "type": "integer", "minimum": 0 },
+                "recurse_submodules": { "type": "boolean", "default": false },
+                "auto_redeploy": { "type": "boolean", "default": false },
+                "priority": schema_ref("DeploymentPriority"),
+                "install_command": { "type": "string" },
+                "run_command": { "type": "string" },
+                "build_command": { "type": "string" },
+                "app_workdir": { "type": "string" },
+                "additionalInputs": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "key": { "type": "string" },
+                            "value": { "type": "string" },
+                            "secret": {
+                                "type": "boolean",
+                                "default": false,
+                                "description": "Store the value as a Docker secret mounted at /run/secrets/<key> instead of an ENV line of the image"
+                            }
+                        }
+                    }
+                },
+                "protocol": schema_ref("Protocol"),
+                "placement": schema_ref("Placement"),
+                "update_config": schema_ref("UpdateConfig"),
+                "security": schema_ref("SecurityOptions"),
+                "volumes": {
+                    "type": "array",
+                    "maxItems": 16,
+                    "description": "Named volumes mounted into the app, the previous volumes are kept if unset",
+                    "items": schema_ref("VolumeMount")
+                }
+            }
+        },
+        "VolumeMount": {
+            "type": "object",
+            "required": ["name", "path"],
+            "properties": {
+                "name": { "type": "string", "pattern": "^[a-z0-9][a-z0-9_-]{0,63}$", "example": "uploads" },
+                "path": { "type": "string", "description": "Absolute path in the containers", "example": "/app/uploads" }
+            }
+        },
+        "VolumesRequest": {
+            "type": "object",
+            "required": ["volumes"],
+            "properties": {
+                "volumes": { "type": "array", "maxItems": 16, "items": schema_ref("VolumeMount") }
+            }
+        },
+        "AppVolume": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "description": "The name of the volume, the addon type for addon volumes", "example": "uploads" },
+                "path": { "type": "string", "nullable": true, "description": "The mount path in the app containers, null for addon volumes", "example": "/app/uploads" },
+                "docker_volume": { "type": "string", "description": "The Docker volume holding the data", "example": "nephelios_my-app-uploads" },
+                "service": { "type": "string", "description": "The stack service the volume is mounted into", "example": "my-app" },
+                "addon": { "allOf": [schema_ref("AddonType")], "nullable": true, "description": "The addon the volume belongs to, null for app volumes" }
+            }
+        },
+        "VolumeBackup": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "example": "20260101-120000-000" },
+                "app_name": { "type": "string" },
+                "volume": { "type": "string", "example": "uploads" },
+                "size": { "type": "integer", "description": "Size of the gzipped tar archive, in bytes" },
+                "created_at": { "type": "string", "format": "date-time" }
+            }
+        },
+        "VolumeRestoreRequest": {
+            "type": "object",
+            "required": ["backup"],
+            "properties": {
+                "backup": { "type": "string", "description": "The ID of the backup to restore", "example": "20260101-120000-000" }
+            }
+        },
+        "UpdateConfig": {
+            "type": "object",
+            "description": "How the replicas are replaced on deploys, Swarm's `update_config`",
+            "properties": {
+                "parallelism": { "type": "integer", "minimum": 0, "default": 1, "description": "Replicas replaced at once, 0 for all of them" },
+                "delay": { "type": "string", "default": "0s", "example": "10s", "description": "Pause between two batches, a number followed by s, m or h, at most 1h" },
+                "failure_action": { "type": "string", "enum": ["pause", "continue", "rollback"], "default": "rollback" },
+                "order": { "type": "string", "enum": ["stop-first", "start-first"], "default": "stop-first" }
+            }
+        },
+        "AutoscalingConfig": {
+            "type": "object",
+            "required": ["min_replicas", "max_replicas", "target_cpu"],
+            "properties": {
+                "min_replicas": { "type": "integer", "minimum": 1 },
+                "max_replicas": { "type": "integer", "description": "At least min_replicas, at most features.max_replicas (default: 10)" },
+                "target_cpu": { "type": "integer", "minimum": 1, "maximum": 100, "description": "Average CPU usage of the replicas aimed at, in percent of the CPU reservation of a replica (of one core if nothing is reserved)" }
+            }
+        },
+        "ScalingSchedule": {
+            "type": "object",
+            "description": "Replicas set by time of day and day of week, in UTC. Applied when the schedule sets new replicas, an app scaled by hand keeps its replicas until then.",
+            "required": ["default_replicas", "windows"],
+            "properties": {
+                "default_replicas": { "type": "integer", "description": "Replicas outside of every window" },
+                "windows": {
+                    "type": "array",
+                    "minItems": 1,
+                    "maxItems": 16,
+                    "description": "The first window containing the current time wins",
+                    "items": {
+                        "type": "object",
+                        "required": ["start", "end", "replicas"],
+                        "properties": {
+                            "days": { "type": "array", "items": { "type": "string" }, "example": ["mon", "tue", "wed", "thu", "fri"], "description": "Days the window starts on, every day if empty" },
+                            "start": { "type": "string", "example": "09:00" },
+                            "end": { "type": "string", "example": "18:00", "description": "A window ending before it starts runs over midnight" },
+                            "replicas": { "type": "integer", "description": "At most features.max_replicas (default: 10)" }
+                        }
+                    }
+                }
+            }
+        },
+        "SecurityOptions": {
+            "type": "object",
+            "description": "Hardening of the containers, missing settings take the value of the default profile",
+            "properties": {
+                "read_only": { "type": "boolean", "default": false, "description": "Whether the root filesystem is read-only, volumes and /tmp stay writable" },
+                "cap_drop": { "type": "array", "items": { "type": "string" }, "default": ["ALL"], "description": "Linux capabilities dropped, ALL for every one of them" },
+                "cap_add": { "type": "array", "items": { "type": "string" }, "default": [], "example": ["NET_BIND_SERVICE"], "description": "Capabilities added back" },
+                "no_new_privileges": { "type": "boolean", "default": true, "description": "Whether processes are prevented from gaining privileges" },
+                "user": { "type": "string", "nullable": true, "default": "1000:1000", "description": "User the processes run as, null for the user of the image" }
+            }
+        },
+        "AddonType": {
+            "type": "string",
+            "enum": ["postgres", "redis", "mysql", "mariadb"],
+            "description": "`postgres`, `mysql` and `mariadb` set `DATABASE_URL` in the environment of the app, `redis` sets `REDIS_URL`"
+        },
+        "AddonRequest": {
+            "type": "object",
+            "required": ["type"],
+            "properties": {
+                "type": schema_ref("AddonType"),
+                "persistent": { "type": "boolean", "default": true, "description": "Whether the data is kept in a volume, only `redis` may run without one" }
+            }
+        },
+        "Addon": {
+            "type": "object",
+            "properties": {
+                "app_name": { "type": "string" },
+                "type": schema_ref("AddonType"),
+                "service": { "type": "string", "description": "Service of the addon, also its hostname on the overlay network", "example": "my-app-postgres" },
+                "image": { "type": "string", "example": "postgres:16-alpine" },
+                "volume": { "type": "string", "nullable": true, "description": "Volume holding the data of the addon, null if it is not persisted" },
+                "env_var": { "type": "string", "description": "Environment variable of the app holding the connection URL", "example": "DATABASE_URL" },
+                "status": { "type": "string", "enum": ["running", "starting", "stopped", "missing", "unknown"], "description": "Status of the service, only in listings" }
+            }
+        },
+        "CronJobRequest": {
+            "type": "object",
+            "required": ["schedule", "command"],
+            "properties": {
+                "schedule": { "type": "string", "example": "0 3 * * *", "description": "A five-field cron expression or a nickname such as `@daily`, evaluated in UTC" },
+                "command": { "type": "string", "maxLength": 4096, "example": "node scripts/cleanup.js", "description": "Run with `sh -c`" },
+                "mode": { "type": "string", "enum": ["job", "exec"], "default": "job", "description": "`job` runs the command in a one-shot Swarm task of the app image with the app environment, `exec` in a running container of the app" },
+                "timeout": { "type": "integer", "minimum": 1, "maximum": 86400, "default": 3600, "description": "How long the command may run, in seconds" },
+                "enabled": { "type": "boolean", "default": true }
+            }
+        },
+        "CronJob": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "app_name": { "type": "string" },
+                "schedule": { "type": "string", "example": "0 3 * * *" },
+                "command": { "type": "string", "example": "node scripts/cleanup.js" },
+                "mode": { "type": "string", "enum": ["job", "exec"] },
+                "timeout_seconds": { "type": "integer" },
+                "enabled": { "type": "boolean" },
+                "created_at": { "type": "string", "format": "date-time" },
+                "updated_at": { "type": "string", "format": "date-time" },
+                "next_run_at": { "type": "string", "format": "date-time", "nullable": true, "description": "Null if the cron job is disabled" }
+            }
+        },
+        "CronRun": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "job_id": { "type": "string" },
+                "app_name": { "type": "string" },
+                "trigger": { "type": "string", "enum": ["schedule", "manual"] },
+                "status": { "type": "string", "enum": ["running", "succeeded", "failed", "timed_out"] },
+                "started_at": { "type": "string", "format": "date-time" },
+                "finished_at": { "type": "string", "format": "date-time", "nullable": true },
+                "exit_code": { "type": "integer", "nullable": true },
+                "output": { "type": "string", "nullable": true, "description": "The end of the stdout and stderr of the command, at most 64 KiB" },
+                "error": { "type": "string", "nullable": true, "description": "Why the command could not be run" }
+            }
+        },
+        "JobRequest": {
+            "type": "object",
+            "required": ["command"],
+            "properties": {
+                "command": { "type": "string", "maxLength": 4096, "example": "npm run migrate", "description": "Run with `sh -c` in a one-shot Swarm task of the app image, with the app environment" },
+                "timeout": { "type": "integer", "minimum": 1, "maximum": 86400, "default": 3600, "description": "How long the command may run, in seconds" }
+            }
+        },
+        "Job": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "app_name": { "type": "string" },
+                "command": { "type": "string", "example": "npm run migrate" },
+                "initiator": { "type": "string", "example": "api-key:1a2b3c4d" },
+                "status": { "type": "string", "enum": ["running", "succeeded", "failed", "timed_out"] },
+                "started_at": { "type": "string", "format": "date-time" },
+                "finished_at": { "type": "string", "format": "date-time", "nullable": true },
+                "exit_code": { "type": "integer", "nullable": true },
+                "output": { "type": "string", "nullable": true, "description": "The end of the logs of the command, at most 64 KiB" },
+                "error": { "type": "string", "nullable": true, "description": "Why the command could not be run" }
+            }
+        },
+        "JobEvent": {
+            "type": "object",
+            "required": ["type"],
+            "properties": {
+                "type": { "type": "string", "enum": ["started", "output", "exit"] },
+                "job": schema_ref("Job"),
+                "data": { "type": "string", "description": "A line of the logs of the command" },
+                "exit_code": { "type": "integer", "nullable": true },
+                "timed_out": { "type": "boolean" },
+                "error": { "type": "string", "nullable": true }
+            }
+        },
+        "Placement": {
+            "type": "object",
+            "properties": {
+                "constraints": {
+                    "type": "array",
+                    "maxItems": 32,
+                    "description": "`<attribute>==<value>` or `<attribute>!=<value>`, on node.id, node.hostname, node.role, node.platform.os, node.platform.arch, node.labels.<label> or engine.labels.<label>",
+                    "items": { "type": "string", "example": "node.labels.tier==gpu" }
+                },
+                "preferences": {
+                    "type": "array",
+                    "maxItems": 32,
+                    "description": "Labels the replicas are spread across the values of",
+                    "items": { "type": "string", "example": "node.labels.zone" }
+                }
+            }
+        },
+        "UpdateAppRequest": {
+            "type": "object",
+            "properties": {
+                "install_command": { "type": "string" },
+                "build_command": { "type": "string" },
+                "run_command": { "type": "string" },
+                "app_workdir": { "type": "string" },
+                "priority": schema_ref("DeploymentPriority"),
+                "redeploy": { "type": "boolean", "default": false }
+            }
+        },
+        "AppBuildSettings": {
+            "type": "object",
+            "properties": {
+                "app_name": { "type": "string" },
+                "install_command": { "type": "string" },
+                "build_command": { "type": "string" },
+                "run_command": { "type": "string" },
+                "app_workdir": { "type": "string" },
+                "priority": schema_ref("DeploymentPriority"),
+                "needs_rebuild": {
+                    "type": "boolean",
+                    "description": "Whether the settings changed since the running release was built"
+                },
+                "deployment_id": { "type": "string", "format": "uuid" }
+            }
+        },
+        "AppActionRequest": {
+            "type": "object",
+            "required": ["app_name"],
+            "properties": { "app_name": existing_app_name.clone() }
+        },
+        "MetricsSnapshot": {
+            "type": "object",
+            "properties": {
+                "timestamp": { "type": "integer", "description": "Unix time in milliseconds" },
+                "containers": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "app_name": { "type": "string" },
+                            "service": { "type": "string" },
+                            "container": { "type": "string", "description": "The Swarm task of the container" },
+                            "cpu_percent": { "type": "number" },
+                            "memory_mb": { "type": "number" },
+                            "network_in_kb": { "type": "number" },
+                            "network_out_kb": { "type": "number" }
+                        }
+                    }
+                }
+            }
+        },
+        "AlertCondition": {
+            "type": "object",
+            "required": ["type"],
+            "properties": {
+                "type": { "type": "string", "enum": ["cpu_above", "memory_above", "app_down"] },
+                "percent": { "type": "number", "description": "Threshold of `cpu_above`, summed over the replicas" },
+                "mb": { "type": "number", "description": "Threshold of `memory_above` in MiB, summed over the replicas" }
+            }
+        },
+        "AlertRuleRequest": {
+            "type": "object",
+            "required": ["name", "condition", "webhooks"],
+            "properties": {
+                "name": { "type": "string" },
+                "app_name": { "type": "string", "description": "Every app if omitted" },
+                "condition": schema_ref("AlertCondition"),
+                "for": { "type": "string", "example": "5m", "description": "How long the condition must hold before the alert fires" },
+                "webhooks": { "type": "array", "items": { "type": "string", "format": "uri" } }
+            }
+        },
+        "AlertRule": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "format": "uuid" },
+                "name": { "type": "string" },
+                "app_name": { "type": "string", "nullable": true },
+                "condition": schema_ref("AlertCondition"),
+                "for_seconds": { "type": "integer" },
+                "webhooks": { "type": "array", "items": { "type": "string", "format": "uri" } }
+            }
+        },
+        "Webhook": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "format": "uuid" },
+                "app_name": {
+                    "type": "string",
+                    "nullable": true,
+                    "description": "The app whose events are delivered, every app if null"
+                },
+                "url": { "type": "string", "format": "uri" },
+                "events": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "The types of the events delivered, every type if empty"
+                },
+                "created_at": { "type": "string", "format": "date-time" }
+            },
+            "description": "Events are posted as {\"timestamp\", \"event\"}, signed with the X-Nephelios-Signature header: sha256=<hex HMAC-SHA256 of the body>"
+        },
+        "WebhookRequest": {
+            "type": "object",
+            "required": ["url"],
+            "properties": {
+                "url": { "type": "string", "format": "uri" },
+                "secret": {
+                    "type": "string",
+                    "minLength": 16,
+                    "description": "Secret signing the deliveries, generated if omitted"
+                },
+                "events": {
+                    "type": "array",
+                    "items": { "type": "string", "enum": WEBHOOK_EVENTS },
+                    "description": "The types of the events delivered, every type if omitted"
+                }
+            }
+        },
+        "NodeInfo": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "hostname": { "type": "string" },
+                "role": { "type": "string", "enum": ["manager", "worker"] },
+                "availability": { "type": "string", "enum": ["active", "pause", "drain"] },
+                "state": { "type": "string", "enum": ["ready", "down", "disconnected", "unknown"] },
+                "address": { "type": "string", "nullable": true },
+                "leader": { "type": "boolean" },
+                "manager_address": { "type": "string", "nullable": true },
+                "engine_version": { "type": "string", "nullable": true },
+                "labels": { "type": "object", "additionalProperties": { "type": "string" } },
+                "cpus": { "type": "number" },
+                "memory_bytes": { "type": "integer" },
+                "cpus_reserved": { "type": "number", "description": "CPUs reserved by the running tasks" },
+                "memory_reserved_bytes": { "type": "integer", "description": "Memory reserved by the running tasks" },
+                "tasks": { "type": "integer", "description": "Number of running tasks" }
+            }
+        },
+        "AuditEntry": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "integer" },
+                "timestamp": { "type": "string", "format": "date-time" },
+                "actor": { "type": "string", "nullable": true, "description": "`api-key:<fingerprint>` or `unverified:<subject>` of the OIDC token, null without credentials" },
+                "method": { "type": "string", "description": "`EVENT` for changes Nephelios made on its own (crashes, auto-redeploys)" },
+                "route": { "type": "string", "example": "/apps/{name}/scale", "description": "The event type (e.g., `app_crashed`) for `EVENT` entries" },
+                "path": { "type": "string" },
+                "app_name": { "type": "string", "nullable": true, "description": "Set when the app is named in the path" },
+                "status": { "type": "integer", "description": "`0` for `EVENT` entries" },
+                "remote_addr": { "type": "string", "nullable": true },
+                "user_agent": { "type": "string", "nullable": true },
+                "duration_ms": { "type": "integer" }
+            }
+        },
+        "Alert": {
+            "type": "object",
+            "description": "Also the `alert` of the webhook payloads, next to a `status` of `firing` or `resolved`",
+            "properties": {
+                "rule_id": { "type": "string", "format": "uuid" },
+                "rule_name": { "type": "string" },
+                "app_name": { "type": "string" },
+                "message": { "type": "string" },
+                "since": { "type": "string", "format": "date-time" },
+                "fired_at": { "type": "string", "format": "date-time" }
+            }
+        },
+        "MetricsSample": {
+            "type": "object",
+            "properties": {
+                "timestamp": { "type": "integer", "description": "Unix time in milliseconds" },
+                "cpu_percent": { "type": "number" },
+                "memory_mb": { "type": "number" },
+                "containers": { "type": "integer" }
+            }
+        },
+        "GcReport": {
+            "type": "object",
+            "description": "Every list holds the removed resources, or those that would be in a dry run",
+            "properties": {
+                "started_at": { "type": "string", "format": "date-time" },
+                "finished_at": { "type": "string", "format": "date-time" },
+                "dry_run": { "type": "boolean" },
+                "services": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Swarm services of the stack missing from the stack file"
+                },
+                "images": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Release images of apps missing from the stack file"
+                },
+                "cache_entries": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Clones and build archives left by interrupted deployments"
+                },
+                "volumes": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Unused volumes of the stack, except addon data and volumes of existing apps"
+                },
+                "networks": { "type": "array", "items": { "type": "string" } },
+                "failed": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Resources that could not be removed, with the error"
+                }
+            }
+        },
+        "ReconcileReport": {
+            "type": "object",
+            "properties": {
+                "started_at": { "type": "string", "format": "date-time" },
+                "finished_at": { "type": "string", "format": "date-time" },
+                "in_sync": { "type": "boolean", "description": "Whether nothing was missing or orphaned" },
+                "recreated": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Services of the stack file created again in the Swarm"
+                },
+                "failed": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Services that could not be created again, with the error"
+                },
+                "orphaned_services": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Swarm services of the stack missing from the stack file"
+                },
+                "orphaned_apps": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Registered apps missing from the stack file"
+                }
+            }
+        },
+        "Readiness": {
+            "type": "object",
+            "properties": {
+                "status": { "type": "string", "enum": ["ready", "not_ready"] },
+                "checks": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "properties": {
+                            "ok": { "type": "boolean" },
+                            "detail": { "type": "string" }
+                        }
+                    }
+                }
+            }
+        },
+        "BulkRequest": {
+            "type": "object",
+            "required": ["app_names", "action"],
+            "properties": {
+                "app_names": {
+                    "type": "array",
+                    "items": existing_app_name.clone(),
+                    "maxItems": 100
+                },
+                "action": { "type": "string", "enum": ["stop", "start", "remove", "redeploy"] }
+            }
+        },
+        "BulkResult": {
+            "type": "object",
+            "properties": {
+                "action": { "type": "string" },
+                "succeeded": { "type": "integer" },
+                "failed": { "type": "integer" },
+                "results": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "app_name": { "type": "string" },
+                            "success": { "type": "boolean" },
+                            "error": { "type": "string" },
+                            "deployment_id": { "type": "string", "description": "Set for `redeploy`" }
+                        }
+                    }
+                }
+            }
+        },
+        "RemoveAppRequest": {
+            "type": "object",
+            "required": ["app_name"],
+            "properties": {
+                "app_name": existing_app_name,
+                "soft": {
+                    "type": "boolean",
+                    "default": false,
+                    "description": "Scale the app to zero and remove its routing, keeping it restorable for features.soft_delete_retention_days days"
+                }
+            }
+        },
+        "DomainRequest": {
+            "type": "object",
+            "required": ["domain"],
+            "properties": { "domain": { "type": "string" } }
+        },
+        "IpAllowlistRequest": {
+            "type": "object",
+            "required": ["ip_allowlist"],
+            "properties": {
+                "ip_allowlist": {
+                    "type": "array",
+                    "items": { "type": "string", "description": "An IP address or CIDR range" }
+                }
+            }
+        },
+        "NotificationsRequest": {
+            "type": "object",
+            "required": ["emails"],
+            "properties": {
+                "emails": {
+                    "type": "array",
+                    "items": { "type": "string", "format": "email" },
+                    "description": "Addresses emailed about the app, besides the global smtp.recipients"
+                }
+            }
+        },
+        "AppNotifications": {
+            "type": "object",
+            "properties": {
+                "app_name": { "type": "string" },
+                "emails": { "type": "array", "items": { "type": "string" } },
+                "global_emails": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Addresses emailed about every app (smtp.recipients)"
+                },
+                "enabled": {
+                    "type": "boolean",
+                    "description": "Whether an SMTP server is configured (smtp.host)"
+                }
+            },
+            "description": "Failed deployments, crashed apps and fired alerts are emailed to these addresses"
+        },
+        "HttpPolicyRequest": {
+            "type": "object",
+            "properties": {
+                "http_policy": {
+                    "type": "string",
+                    "enum": ["redirect", "serve", "block"],
+                    "nullable": true,
+                    "description": "Omit to use the global domain.http_policy (HTTP_POLICY)"
+                }
+            }
+        },
+        "StickySessions": {
+            "type": "object",
+            "properties": {
+                "cookie_name": { "type": "string" },
+                "secure": { "type": "boolean" },
+                "http_only": { "type": "boolean" },
+                "same_site": { "type": "string", "enum": ["none", "lax", "strict"] }
+            }
+        },
+        "StickySessionsRequest": {
+            "type": "object",
+            "required": ["enabled"],
+            "allOf": [schema_ref("StickySessions")],
+            "properties": { "enabled": { "type": "boolean" } }
+        },
+        "MiddlewaresRequest": {
+            "type": "object",
+            "required": ["middlewares"],
+            "properties": {
+                "middlewares": {
+                    "type": "object",
+                    "description": "Traefik middlewares by name, each with a single middleware type key",
+                    "additionalProperties": { "type": "object" }
+                }
+            }
+        },
+        "ProtocolRequest": {
+            "type": "object",
+            "required": ["protocol"],
+            "properties": { "protocol": schema_ref("Protocol") }
+        },
+        "ExposedPort": {
+            "type": "object",
+            "required": ["name", "port"],
+            "properties": {
+                "name": { "type": "string" },
+                "port": { "type": "integer", "minimum": 1, "maximum": 65535 },
+                "subdomain": { "type": "string" },
+                "path_prefix": { "type": "string" },
+                "strip_prefix": { "type": "boolean" },
+                "protocol": schema_ref("Protocol")
+            }
+        },
+        "PortsRequest": {
+            "type": "object",
+            "required": ["ports"],
+            "properties": {
+                "ports": { "type": "array", "items": schema_ref("ExposedPort") }
+            }
+        },
+        "RollbackRequest": {
+            "type": "object",
+            "properties": {
+                "release": {
+                    "type": "string",
+                    "description": "A deployment ID, release tag or image, the previous release if omitted"
+                }
+            }
+        },
+        "RollbackCreated": {
+            "type": "object",
+            "properties": {
+                "message": { "type": "string" },
+                "deployment_id": { "type": "string", "format": "uuid" },
+                "image": { "type": "string" }
+            }
+        },
+        "Environment": {
+            "type": "object",
+            "additionalProperties": { "type": "string" },
+            "example": { "DATABASE_URL": "postgres://db:5432/app" }
+        },
+        "EnvRequest": {
+            "type": "object",
+            "required": ["env"],
+            "properties": {
+                "env": schema_ref("Environment")
+            }
+        },
+        "SecretsRequest": {
+            "type": "object",
+            "required": ["secrets"],
+            "properties": {
+                "secrets": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string", "maxLength": 512000 },
+                    "example": { "DATABASE_PASSWORD": "s3cr3t" }
+                }
+            }
+        },
+        "AppSecret": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "example": "DATABASE_PASSWORD" },
+                "secret": { "type": "string", "description": "The Docker secret holding the value" },
+                "path": {
+                    "type": "string",
+                    "description": "The file holding the value in the app containers, also set in the <name>_FILE variable",
+                    "example": "/run/secrets/DATABASE_PASSWORD"
+                }
+            }
+        },
+        "EnvKeysRequest": {
+            "type": "object",
+            "required": ["keys"],
+            "properties": {
+                "keys": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "example": ["DATABASE_URL"]
+                }
+            }
+        },
+        "ExecRequest": {
+            "type": "object",
+            "required": ["command"],
+            "properties": {
+                "command": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "example": ["rails", "db:migrate"]
+                },
+                "timeout": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": 3600,
+                    "default": 300,
+                    "description": "Seconds to wait for the command, after which it is killed"
+                }
+            }
+        },
+        "ExecEvent": {
+            "type": "object",
+            "required": ["type"],
+            "properties": {
+                "type": { "type": "string", "enum": ["output", "exit"] },
+                "stream": { "type": "string", "enum": ["stdout", "stderr"] },
+                "data": { "type": "string" },
+                "exit_code": { "type": "integer", "nullable": true },
+                "timed_out": { "type": "boolean" },
+                "error": { "type": "string", "nullable": true }
+            }
+        },
+        "DeploymentPriorityRequest": {
+            "type": "object",
+            "required": ["priority"],
+            "properties": { "priority": schema_ref("DeploymentPriority") }
+        },
+        "ScaleRequest": {
+            "type": "object",
+            "required": ["replicas"],
+            "properties": {
+                "replicas": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "At most features.max_replicas (default: 10)"
+                }
+            }
+        },
+        "MaintenanceRequest": {
+            "type": "object",
+            "required": ["enabled"],
+            "properties": { "enabled": { "type": "boolean" } }
+        }
+    })
+}
+
+/// Builds the OpenAPI 3 document of the Nephelios API.
+///
+/// # Returns
+/// The document, served at `/api/v1/openapi.json`.
+pub fn openapi_document() -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Nephelios API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Deploy and manage apps on a Docker Swarm cluster."
+        },
+        "servers": [{ "url": "/api/v1" }],
+        "paths": paths(),
+        "components": {
+            "securitySchemes": {
+                "bearerAuth": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "An API key or an OIDC JWT"
+                },
+                "apiKeyAuth": { "type": "apiKey", "in": "header", "name": "X-API-Key" }
+            },
+            "schemas": schemas()
+        }
+    })
+}
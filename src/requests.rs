@@ -0,0 +1,1608 @@
+use crate::config::config;
+use crate::services::addons::AddonType;
+use crate::services::alerting::AlertCondition;
+use crate::services::audit_log::AuditFilter;
+use crate::services::cron_jobs::{parse_schedule, CronJobSettings, CronMode};
+use crate::services::deployment::{
+    validate_volumes, AutoscalingConfig, DeployRequest, PlacementConfig, ResourceLimits,
+    ResourceOverrides, RolloutConfig, RolloutOverrides, ScalingSchedule, SecurityOptions,
+    VolumeMount,
+};
+use crate::services::deployment_tracker::DeploymentPriority;
+use crate::services::email_notifications::check_email;
+use crate::services::helpers::traefik_helper::{
+    normalize_cidr, normalize_domain, render_middlewares, validate_ports, AppProtocol, ExposedPort,
+    HttpPolicy, RoutingConfig, StickySessions,
+};
+use crate::services::metrics_history::HISTORY_RETENTION;
+use crate::services::webhooks::WEBHOOK_EVENTS;
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use warp::{reject, Filter, Rejection};
+
+/// Maximum size of a JSON request body, in bytes.
+const MAX_BODY_SIZE: u64 = 1024 * 1024;
+
+/// Maximum length of an application name, so its service name (`nephelios_<app>`) fits in
+/// the 63 characters allowed by Swarm.
+const MAX_APP_NAME_LENGTH: usize = 53;
+
+/// Maximum length of the name of an existing application, which may predate the DNS label
+/// rule of `app_name_error`.
+const MAX_EXISTING_APP_NAME_LENGTH: usize = 63;
+
+/// Maximum length of free-form text fields (commands, URLs).
+const MAX_TEXT_LENGTH: usize = 4096;
+
+/// Maximum length of an environment variable name.
+const MAX_ENV_NAME_LENGTH: usize = 128;
+
+/// Maximum size of a secret value, the limit of Docker Swarm.
+const MAX_SECRET_SIZE: usize = 500 * 1024;
+
+/// Default time a command run in an app container may take, in seconds.
+const DEFAULT_EXEC_TIMEOUT: u64 = 300;
+
+/// Default number of past lines sent by `/ws/logs/{name}` for each container.
+const DEFAULT_LOGS_STREAM_TAIL: u32 = 100;
+
+/// Maximum time a command run in an app container may take, in seconds.
+const MAX_EXEC_TIMEOUT: u64 = 3600;
+
+/// Default time the command of a cron job may take, in seconds.
+const DEFAULT_CRON_TIMEOUT: u64 = 3600;
+
+/// Maximum time the command of a cron job may take, in seconds.
+const MAX_CRON_TIMEOUT: u64 = 24 * 3600;
+
+/// Default time a one-off job may take, in seconds.
+const DEFAULT_JOB_TIMEOUT: u64 = 3600;
+
+/// Maximum time a one-off job may take, in seconds.
+const MAX_JOB_TIMEOUT: u64 = 24 * 3600;
+
+/// App types a Dockerfile can be generated for.
+const APP_TYPES: &[&str] = &["nodejs", "python"];
+
+/// Maximum number of apps of a bulk operation.
+const MAX_BULK_APPS: usize = 100;
+
+/// Minimum length of a webhook secret given by the user.
+const MIN_WEBHOOK_SECRET_LENGTH: usize = 16;
+
+/// Actions of `POST /apps/bulk`.
+const BULK_ACTIONS: &[&str] = &["stop", "start", "remove", "redeploy"];
+
+/// Number of entries returned by `GET /audit` when no limit is given.
+const DEFAULT_AUDIT_LIMIT: u32 = 100;
+
+/// Maximum number of entries returned by `GET /audit`.
+const MAX_AUDIT_LIMIT: u32 = 1000;
+
+/// An error on a single field of a request body.
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// The validation errors of a request body, answered with a 400, or a 422 when an app name
+/// is refused.
+#[derive(Debug, Default, Serialize)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl reject::Reject for ValidationErrors {}
+
+impl ValidationErrors {
+    /// Records an error on a field.
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Returns `Ok(())` if no error was recorded.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Checks whether an app name was refused.
+    pub fn has_invalid_app_name(&self) -> bool {
+        self.errors
+            .iter()
+            .any(|error| error.field == "app_name" || error.field == "app_names")
+    }
+
+    /// Builds the errors of a body that could not be deserialized.
+    ///
+    /// serde only names the field for missing and unknown fields, other errors are
+    /// reported on `body`.
+    fn from_serde(error: &serde_json::Error) -> Self {
+        let message = error.to_string();
+        let field = ["missing field `", "unknown field `"]
+            .iter()
+            .find_map(|prefix| {
+                let rest = message.split(prefix).nth(1)?;
+                rest.split('`').next()
+            })
+            .unwrap_or("body");
+
+        let mut errors = Self::default();
+        errors.add(field, message.clone());
+        errors
+    }
+}
+
+/// A request body that can check its own fields.
+pub trait Validate {
+    /// Checks every field of the body.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the body is valid.
+    /// * `Err(ValidationErrors)` listing every invalid field.
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+/// Extracts and validates a JSON request body.
+///
+/// An empty body is read as `{}`.
+///
+/// Malformed or invalid bodies are rejected with `ValidationErrors`, answered with a 400
+/// listing the invalid fields.
+pub fn json_body<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Validate + Send,
+{
+    warp::body::content_length_limit(MAX_BODY_SIZE)
+        .and(warp::body::bytes())
+        .and_then(|body: bytes::Bytes| async move {
+            // An empty body stands for an empty object, for routes whose fields are all optional
+            let body: &[u8] = if body.is_empty() { b"{}" } else { &body };
+            let request: T = serde_json::from_slice(body)
+                .map_err(|e| reject::custom(ValidationErrors::from_serde(&e)))?;
+            request.validate().map_err(reject::custom)?;
+            Ok::<T, Rejection>(request)
+        })
+}
+
+/// Returns why an application name is invalid, if it is.
+///
+/// App names become hostnames, image names and service names, so they must be DNS labels:
+/// lowercase letters, digits and dashes, starting and ending with a letter or a digit.
+///
+/// # Arguments
+/// * `app_name` - The name to check.
+///
+/// # Returns
+/// * `Some(String)` with the reason the name is refused.
+/// * `None` if the name is valid.
+pub fn app_name_error(app_name: &str) -> Option<String> {
+    if app_name.is_empty() {
+        return Some("app_name is required".to_string());
+    }
+    if app_name.len() > MAX_APP_NAME_LENGTH {
+        return Some(format!(
+            "app_name must be at most {} characters",
+            MAX_APP_NAME_LENGTH
+        ));
+    }
+    if !app_name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Some(format!(
+            "app_name {:?} may only contain lowercase letters, digits and dashes",
+            app_name
+        ));
+    }
+    if app_name.starts_with('-') || app_name.ends_with('-') {
+        return Some(format!(
+            "app_name {:?} must start and end with a letter or a digit",
+            app_name
+        ));
+    }
+    None
+}
+
+/// Checks that an application name is a valid DNS label.
+fn check_app_name(errors: &mut ValidationErrors, app_name: &str) {
+    if let Some(message) = app_name_error(app_name) {
+        errors.add("app_name", message);
+    }
+}
+
+/// Returns why the name of an existing application is invalid, if it is.
+///
+/// Only the presence and the length are checked, so apps created before names had to be DNS
+/// labels (e.g., `MyApp` or `my_app`) can still be managed.
+pub fn existing_app_name_error(app_name: &str) -> Option<String> {
+    if app_name.trim().is_empty() {
+        Some("app_name is required".to_string())
+    } else if app_name.len() > MAX_EXISTING_APP_NAME_LENGTH {
+        Some(format!(
+            "app_name must be at most {} characters",
+            MAX_EXISTING_APP_NAME_LENGTH
+        ))
+    } else {
+        None
+    }
+}
+
+/// Checks the name of an existing application.
+fn check_existing_app_name(errors: &mut ValidationErrors, app_name: &str) {
+    if let Some(message) = existing_app_name_error(app_name) {
+        errors.add("app_name", message);
+    }
+}
+
+/// Checks that an optional text field is not too long.
+fn check_length(errors: &mut ValidationErrors, field: &str, value: Option<&str>, max: usize) {
+    if value.map(|value| value.len() > max).unwrap_or(false) {
+        errors.add(
+            field,
+            format!("{} must be at most {} characters", field, max),
+        );
+    }
+}
+
+/// A key/value pair of `additionalInputs`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdditionalInput {
+    pub key: String,
+    pub value: String,
+    /// Stores the value as a Docker secret mounted into the service, instead of an `ENV`
+    /// line of the Dockerfile that would leak it into the image layers.
+    #[serde(default)]
+    pub secret: bool,
+}
+
+/// Body of `POST /remove`.
+#[derive(Debug, Deserialize)]
+pub struct RemoveAppRequest {
+    #[serde(default)]
+    pub app_name: String,
+    /// Scales the app to zero and keeps it restorable instead of removing it.
+    #[serde(default)]
+    pub soft: bool,
+}
+
+impl Validate for RemoveAppRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        check_existing_app_name(&mut errors, &self.app_name);
+        errors.into_result()
+    }
+}
+
+/// Body of `POST /apps/bulk`.
+#[derive(Debug, Deserialize)]
+pub struct BulkRequest {
+    #[serde(default)]
+    pub app_names: Vec<String>,
+    /// One of `BULK_ACTIONS`.
+    #[serde(default)]
+    pub action: String,
+}
+
+impl Validate for BulkRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+
+        if !BULK_ACTIONS.contains(&self.action.as_str()) {
+            errors.add(
+                "action",
+                format!("action must be one of: {}", BULK_ACTIONS.join(", ")),
+            );
+        }
+
+        if self.app_names.is_empty() {
+            errors.add("app_names", "app_names must not be empty");
+        } else if self.app_names.len() > MAX_BULK_APPS {
+            errors.add(
+                "app_names",
+                format!("app_names must contain at most {} apps", MAX_BULK_APPS),
+            );
+        }
+        for (index, app_name) in self.app_names.iter().enumerate() {
+            if let Some(message) = existing_app_name_error(app_name) {
+                errors.add("app_names", message);
+            } else if self.app_names[..index].contains(app_name) {
+                errors.add("app_names", format!("{} is listed twice", app_name));
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+/// Body of `POST /create`.
+#[derive(Debug, Deserialize)]
+pub struct CreateAppRequest {
+    #[serde(default)]
+    pub app_name: String,
+    #[serde(default = "default_app_type")]
+    pub app_type: String,
+    #[serde(default)]
+    pub github_url: String,
+    #[serde(default)]
+    pub git_ref: Option<String>,
+    #[serde(default)]
+    pub git_token: Option<String>,
+    #[serde(default)]
+    pub deploy_key: Option<String>,
+    #[serde(default)]
+    pub clone_depth: Option<u32>,
+    #[serde(default)]
+    pub recurse_submodules: bool,
+    #[serde(default)]
+    pub auto_redeploy: bool,
+    #[serde(default)]
+    pub install_command: Option<String>,
+    #[serde(default)]
+    pub run_command: Option<String>,
+    #[serde(default)]
+    pub build_command: Option<String>,
+    #[serde(default)]
+    pub app_workdir: Option<String>,
+    #[serde(default, rename = "additionalInputs")]
+    pub additional_inputs: Vec<AdditionalInput>,
+    #[serde(default)]
+    pub protocol: Option<String>,
+    #[serde(flatten)]
+    pub resources: ResourceOverrides,
+    /// The nodes the app may run on, the previous placement of the app is kept if unset.
+    #[serde(default)]
+    pub placement: Option<PlacementConfig>,
+    /// How replicas are replaced on deploys, missing settings keep their previous value.
+    #[serde(default)]
+    pub update_config: Option<RolloutOverrides>,
+    /// Hardening settings of the containers, the previous settings of the app are kept if
+    /// unset, the default profile for a new app.
+    #[serde(default)]
+    pub security: Option<SecurityOptions>,
+    /// Named volumes mounted into the app, the previous volumes of the app are kept if unset.
+    #[serde(default)]
+    pub volumes: Option<Vec<VolumeMount>>,
+    /// Priority of the deployments of the app in the deployment queue, the previous priority
+    /// of the app is kept if unset.
+    #[serde(default)]
+    pub priority: Option<DeploymentPriority>,
+}
+
+fn default_app_type() -> String {
+    "nodejs".to_string()
+}
+
+impl Validate for CreateAppRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+
+        check_app_name(&mut errors, &self.app_name);
+
+        if !APP_TYPES.contains(&self.app_type.as_str()) {
+            errors.add(
+                "app_type",
+                format!("app_type must be one of: {}", APP_TYPES.join(", ")),
+            );
+        }
+
+        if self.github_url.trim().is_empty() {
+            errors.add("github_url", "github_url is required");
+        }
+        check_length(
+            &mut errors,
+            "github_url",
+            Some(&self.github_url),
+            MAX_TEXT_LENGTH,
+        );
+        check_length(&mut errors, "git_ref", self.git_ref.as_deref(), 255);
+        check_length(&mut errors, "deploy_key", self.deploy_key.as_deref(), 255);
+
+        for (field, value) in [
+            ("install_command", &self.install_command),
+            ("run_command", &self.run_command),
+            ("build_command", &self.build_command),
+        ] {
+            check_length(&mut errors, field, value.as_deref(), MAX_TEXT_LENGTH);
+        }
+
+        if let Some(workdir) = &self.app_workdir {
+            if !workdir.starts_with('/') {
+                errors.add("app_workdir", "app_workdir must be an absolute path");
+            }
+            check_length(&mut errors, "app_workdir", Some(workdir), 255);
+        }
+
+        for input in &self.additional_inputs {
+            if input.key.is_empty() || input.key.len() > 128 {
+                errors.add(
+                    "additionalInputs",
+                    "keys must be between 1 and 128 characters",
+                );
+            } else if input.secret {
+                check_env_name(&mut errors, "additionalInputs", &input.key);
+                check_secret_value(&mut errors, "additionalInputs", &input.value);
+            }
+        }
+
+        if let Some(protocol) = &self.protocol {
+            if let Err(e) = AppProtocol::parse(protocol) {
+                errors.add("protocol", e);
+            }
+        }
+
+        if let Err((field, message)) = ResourceLimits::default().with_overrides(&self.resources) {
+            errors.add(field, message);
+        }
+
+        if let Some(placement) = &self.placement {
+            if let Err((field, message)) = placement.validate() {
+                errors.add(field, message);
+            }
+        }
+
+        if let Some(update_config) = &self.update_config {
+            if let Err((field, message)) = RolloutConfig::default().with_overrides(update_config) {
+                errors.add(field, message);
+            }
+        }
+
+        if let Some(security) = &self.security {
+            if let Err((field, message)) = security.validate() {
+                errors.add(field, message);
+            }
+        }
+
+        if let Some(volumes) = &self.volumes {
+            if let Err(e) = validate_volumes(volumes) {
+                errors.add("volumes", e);
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+impl CreateAppRequest {
+    /// Converts the validated body into a deploy request.
+    ///
+    /// # Arguments
+    /// * `previous` - The last deploy request of the app, if it was deployed before.
+    ///   Its routing, resources, placement, update config, security options, autoscaling,
+    ///   scaling schedule, priority, volumes and environment variables are kept, settings
+    ///   sent in the body override them.
+    pub fn into_deploy_request(self, previous: Option<DeployRequest>) -> DeployRequest {
+        let non_empty = |value: Option<String>| value.filter(|value| !value.is_empty());
+
+        let (
+            mut routing,
+            base_resources,
+            previous_placement,
+            base_update_config,
+            previous_security,
+            autoscaling,
+            scaling_schedule,
+            previous_priority,
+            previous_volumes,
+            env,
+            secrets,
+        ) = match previous {
+            Some(previous) => (
+                previous.routing,
+                previous.resources,
+                previous.placement,
+                previous.update_config,
+                previous.security,
+                previous.autoscaling,
+                previous.scaling_schedule,
+                previous.priority,
+                previous.volumes,
+                previous.env,
+                previous.secrets,
+            ),
+            None => (
+                RoutingConfig::default(),
+                ResourceLimits::default(),
+                PlacementConfig::default(),
+                RolloutConfig::default(),
+                SecurityOptions::default(),
+                None,
+                None,
+                DeploymentPriority::default(),
+                Vec::new(),
+                BTreeMap::new(),
+                BTreeMap::new(),
+            ),
+        };
+        if let Some(protocol) = self.protocol.as_deref() {
+            routing.protocol = AppProtocol::parse(protocol).unwrap_or_default();
+        }
+        let resources = base_resources
+            .with_overrides(&self.resources)
+            .unwrap_or(base_resources);
+        let update_config = match &self.update_config {
+            Some(overrides) => base_update_config
+                .with_overrides(overrides)
+                .unwrap_or(base_update_config),
+            None => base_update_config,
+        };
+        let (secret_inputs, additional_inputs): (Vec<_>, Vec<_>) = self
+            .additional_inputs
+            .into_iter()
+            .partition(|input| input.secret);
+
+        DeployRequest {
+            app_name: self.app_name,
+            app_type: self.app_type,
+            github_url: self.github_url,
+            git_ref: non_empty(self.git_ref),
+            git_token: non_empty(self.git_token),
+            deploy_key: non_empty(self.deploy_key),
+            clone_depth: self.clone_depth,
+            recurse_submodules: self.recurse_submodules,
+            auto_redeploy: self.auto_redeploy,
+            install_command: self.install_command.unwrap_or_default(),
+            run_command: self.run_command.unwrap_or_default(),
+            build_command: self.build_command.unwrap_or_default(),
+            app_workdir: self.app_workdir.unwrap_or_else(|| "/app".to_string()),
+            additional_inputs: additional_inputs
+                .into_iter()
+                .map(|input| (input.key, input.value))
+                .collect::<HashMap<String, String>>(),
+            routing,
+            resources,
+            placement: self.placement.unwrap_or(previous_placement),
+            update_config,
+            security: self.security.unwrap_or(previous_security),
+            autoscaling,
+            scaling_schedule,
+            priority: self.priority.unwrap_or(previous_priority),
+            volumes: self.volumes.unwrap_or(previous_volumes),
+            env,
+            secrets,
+            pending_secrets: secret_inputs
+                .into_iter()
+                .map(|input| (input.key, input.value))
+                .collect(),
+            needs_rebuild: false,
+            compose: None,
+            image: None,
+        }
+    }
+}
+
+/// Body of `PATCH /apps/{name}`.
+#[derive(Debug, Deserialize)]
+pub struct UpdateAppRequest {
+    #[serde(default)]
+    pub install_command: Option<String>,
+    #[serde(default)]
+    pub build_command: Option<String>,
+    #[serde(default)]
+    pub run_command: Option<String>,
+    #[serde(default)]
+    pub app_workdir: Option<String>,
+    /// Priority of the deployments of the app in the deployment queue.
+    #[serde(default)]
+    pub priority: Option<DeploymentPriority>,
+    /// Whether a new release is built right away with the new settings.
+    #[serde(default)]
+    pub redeploy: bool,
+}
+
+impl Validate for UpdateAppRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+
+        for (field, value) in [
+            ("install_command", &self.install_command),
+            ("build_command", &self.build_command),
+            ("run_command", &self.run_command),
+        ] {
+            check_length(&mut errors, field, value.as_deref(), MAX_TEXT_LENGTH);
+        }
+
+        if let Some(workdir) = &self.app_workdir {
+            if !workdir.starts_with('/') {
+                errors.add("app_workdir", "app_workdir must be an absolute path");
+            }
+            check_length(&mut errors, "app_workdir", Some(workdir), 255);
+        }
+
+        if !self.redeploy
+            && self.install_command.is_none()
+            && self.build_command.is_none()
+            && self.run_command.is_none()
+            && self.app_workdir.is_none()
+            && self.priority.is_none()
+        {
+            errors.add("body", "at least one setting must be provided");
+        }
+
+        errors.into_result()
+    }
+}
+
+/// Body of the `/start`, `/stop` and `/remove` routes.
+#[derive(Debug, Deserialize)]
+pub struct AppActionRequest {
+    #[serde(default)]
+    pub app_name: String,
+}
+
+impl Validate for AppActionRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        check_existing_app_name(&mut errors, &self.app_name);
+        errors.into_result()
+    }
+}
+
+/// Body of `POST/DELETE /apps/{name}/domains`.
+#[derive(Debug, Deserialize)]
+pub struct DomainRequest {
+    #[serde(default)]
+    pub domain: String,
+}
+
+impl Validate for DomainRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if let Err(e) = normalize_domain(&self.domain) {
+            errors.add("domain", e);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/ip-allowlist`.
+#[derive(Debug, Deserialize)]
+pub struct IpAllowlistRequest {
+    pub ip_allowlist: Vec<String>,
+}
+
+impl Validate for IpAllowlistRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        for range in &self.ip_allowlist {
+            if let Err(e) = normalize_cidr(range) {
+                errors.add("ip_allowlist", e);
+            }
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/http-policy`.
+#[derive(Debug, Deserialize)]
+pub struct HttpPolicyRequest {
+    #[serde(default)]
+    pub http_policy: Option<String>,
+}
+
+impl Validate for HttpPolicyRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if let Some(Err(e)) = self.http_policy.as_deref().map(HttpPolicy::parse) {
+            errors.add("http_policy", e);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/resources`.
+#[derive(Debug, Deserialize)]
+pub struct ResourcesRequest {
+    #[serde(flatten)]
+    pub resources: ResourceOverrides,
+}
+
+impl Validate for ResourcesRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        // Limits are checked against the current values of the app by the handler
+        Ok(())
+    }
+}
+
+/// Body of `PUT /apps/{name}/placement`.
+#[derive(Debug, Deserialize)]
+pub struct PlacementRequest {
+    #[serde(flatten)]
+    pub placement: PlacementConfig,
+}
+
+impl Validate for PlacementRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if let Err((field, message)) = self.placement.validate() {
+            errors.add(field, message);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/security`, missing settings take the value of the default
+/// profile.
+#[derive(Debug, Deserialize)]
+pub struct SecurityRequest {
+    #[serde(flatten)]
+    pub security: SecurityOptions,
+}
+
+impl Validate for SecurityRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if let Err((field, message)) = self.security.validate() {
+            errors.add(field, message);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/autoscaling`.
+#[derive(Debug, Deserialize)]
+pub struct AutoscalingRequest {
+    #[serde(flatten)]
+    pub autoscaling: AutoscalingConfig,
+}
+
+impl Validate for AutoscalingRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if let Err((field, message)) = self.autoscaling.validate() {
+            errors.add(field, message);
+        }
+        let max = max_replicas();
+        if self.autoscaling.max_replicas > max {
+            errors.add(
+                "max_replicas",
+                format!("max_replicas must be at most {}", max),
+            );
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/scaling-schedule`.
+#[derive(Debug, Deserialize)]
+pub struct ScalingScheduleRequest {
+    #[serde(flatten)]
+    pub schedule: ScalingSchedule,
+}
+
+impl Validate for ScalingScheduleRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if let Err((field, message)) = self.schedule.validate() {
+            errors.add(field, message);
+        }
+        let max = max_replicas();
+        if self.schedule.max_replicas() > max {
+            errors.add("windows", format!("replicas must be at most {}", max));
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `POST /apps/{name}/addons`.
+#[derive(Debug, Deserialize)]
+pub struct AddonRequest {
+    #[serde(rename = "type", default)]
+    pub addon_type: String,
+    /// Whether the data of the addon is kept in a volume (default: true).
+    #[serde(default)]
+    pub persistent: Option<bool>,
+}
+
+impl Validate for AddonRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        match AddonType::parse(&self.addon_type) {
+            Ok(addon_type) => {
+                if self.persistent == Some(false) && !addon_type.supports_ephemeral() {
+                    errors.add(
+                        "persistent",
+                        format!("{} addons always keep their data", addon_type.as_str()),
+                    );
+                }
+            }
+            Err(e) => errors.add("type", e),
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/volumes`.
+#[derive(Debug, Deserialize)]
+pub struct VolumesRequest {
+    pub volumes: Vec<VolumeMount>,
+}
+
+impl Validate for VolumesRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if let Err(e) = validate_volumes(&self.volumes) {
+            errors.add("volumes", e);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `POST /apps/{name}/volumes/{volume}/restore`.
+#[derive(Debug, Deserialize)]
+pub struct VolumeRestoreRequest {
+    /// The ID of the backup to restore.
+    #[serde(default)]
+    pub backup: String,
+}
+
+impl Validate for VolumeRestoreRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.backup.trim().is_empty() {
+            errors.add("backup", "backup is required");
+        }
+        check_length(&mut errors, "backup", Some(&self.backup), 64);
+        errors.into_result()
+    }
+}
+
+/// Body of `POST /apps/{name}/cron-jobs` and `PUT /apps/{name}/cron-jobs/{id}`.
+#[derive(Debug, Deserialize)]
+pub struct CronJobRequest {
+    /// A five-field cron expression (e.g., `0 3 * * *`) or a nickname such as `@daily`, in
+    /// UTC.
+    #[serde(default)]
+    pub schedule: String,
+    /// The command, run with `sh -c` (e.g., `node scripts/cleanup.js`).
+    #[serde(default)]
+    pub command: String,
+    #[serde(default)]
+    pub mode: CronMode,
+    /// How long the command may run, in seconds.
+    #[serde(default = "default_cron_timeout")]
+    pub timeout: u64,
+    /// Whether the job runs on its schedule, `true` if omitted.
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+fn default_cron_timeout() -> u64 {
+    DEFAULT_CRON_TIMEOUT
+}
+
+impl CronJobRequest {
+    /// Returns the settings of the cron job.
+    pub fn settings(&self) -> CronJobSettings {
+        CronJobSettings {
+            schedule: self.schedule.trim().to_string(),
+            command: self.command.clone(),
+            mode: self.mode,
+            timeout_seconds: self.timeout,
+            enabled: self.enabled.unwrap_or(true),
+        }
+    }
+}
+
+impl Validate for CronJobRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.schedule.trim().is_empty() {
+            errors.add("schedule", "schedule is required");
+        } else if let Err(e) = parse_schedule(self.schedule.trim()) {
+            errors.add("schedule", e);
+        }
+        if self.command.trim().is_empty() {
+            errors.add("command", "command is required");
+        }
+        check_length(&mut errors, "command", Some(&self.command), MAX_TEXT_LENGTH);
+        if self.timeout == 0 || self.timeout > MAX_CRON_TIMEOUT {
+            errors.add(
+                "timeout",
+                format!("timeout must be between 1 and {} seconds", MAX_CRON_TIMEOUT),
+            );
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `POST /apps/{name}/jobs`.
+#[derive(Debug, Deserialize)]
+pub struct JobRequest {
+    /// The command, run with `sh -c` in the image of the app (e.g., `npm run migrate`).
+    #[serde(default)]
+    pub command: String,
+    /// How long the command may run, in seconds.
+    #[serde(default = "default_job_timeout")]
+    pub timeout: u64,
+}
+
+fn default_job_timeout() -> u64 {
+    DEFAULT_JOB_TIMEOUT
+}
+
+impl Validate for JobRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.command.trim().is_empty() {
+            errors.add("command", "command is required");
+        }
+        check_length(&mut errors, "command", Some(&self.command), MAX_TEXT_LENGTH);
+        if self.timeout == 0 || self.timeout > MAX_JOB_TIMEOUT {
+            errors.add(
+                "timeout",
+                format!("timeout must be between 1 and {} seconds", MAX_JOB_TIMEOUT),
+            );
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/update-config`.
+#[derive(Debug, Deserialize)]
+pub struct UpdateConfigRequest {
+    #[serde(flatten)]
+    pub update_config: RolloutOverrides,
+}
+
+impl Validate for UpdateConfigRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        // The delay is checked against the current settings of the app by the handler
+        Ok(())
+    }
+}
+
+/// Body of `PUT /apps/{name}/sticky-sessions`.
+#[derive(Debug, Deserialize)]
+pub struct StickySessionsRequest {
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub sticky: StickySessions,
+}
+
+impl Validate for StickySessionsRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if let Err(e) = self.sticky.validate() {
+            errors.add("sticky", e);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/middlewares`.
+#[derive(Debug, Deserialize)]
+pub struct MiddlewaresRequest {
+    pub middlewares: IndexMap<String, Value>,
+}
+
+impl Validate for MiddlewaresRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if let Err(e) = render_middlewares(&self.middlewares) {
+            errors.add("middlewares", e);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/protocol`.
+#[derive(Debug, Deserialize)]
+pub struct ProtocolRequest {
+    #[serde(default)]
+    pub protocol: String,
+}
+
+impl Validate for ProtocolRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if let Err(e) = AppProtocol::parse(&self.protocol) {
+            errors.add("protocol", e);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/ports`.
+#[derive(Debug, Deserialize)]
+pub struct PortsRequest {
+    pub ports: Vec<ExposedPort>,
+}
+
+impl Validate for PortsRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if let Err(e) = validate_ports(&self.ports) {
+            errors.add("ports", e);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `POST /apps/{name}/maintenance`.
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceRequest {
+    pub enabled: bool,
+}
+
+impl Validate for MaintenanceRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        Ok(())
+    }
+}
+
+/// Body of `POST /apps/{name}/rollback`.
+#[derive(Debug, Deserialize)]
+pub struct RollbackRequest {
+    /// A deployment ID, release tag or image to roll back to, the previous release if omitted.
+    #[serde(default)]
+    pub release: Option<String>,
+}
+
+impl Validate for RollbackRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self
+            .release
+            .as_deref()
+            .is_some_and(|release| release.trim().is_empty())
+        {
+            errors.add("release", "release must not be empty");
+        }
+        check_length(&mut errors, "release", self.release.as_deref(), 255);
+        errors.into_result()
+    }
+}
+
+/// Body of `PATCH /deployments/{id}`.
+#[derive(Debug, Deserialize)]
+pub struct DeploymentPriorityRequest {
+    pub priority: DeploymentPriority,
+}
+
+impl Validate for DeploymentPriorityRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        Ok(())
+    }
+}
+
+/// Body of `POST /apps/{name}/scale`.
+#[derive(Debug, Deserialize)]
+pub struct ScaleRequest {
+    pub replicas: u32,
+}
+
+/// Returns the maximum number of replicas an app can be scaled to, see
+/// `features.max_replicas`.
+fn max_replicas() -> u32 {
+    config().features.max_replicas
+}
+
+impl Validate for ScaleRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let max = max_replicas();
+        if self.replicas > max {
+            errors.add("replicas", format!("replicas must be at most {}", max));
+        }
+        errors.into_result()
+    }
+}
+
+/// Query parameters of `GET /apps/{name}/logs`.
+#[derive(Debug, Deserialize)]
+pub struct LogsQuery {
+    /// Number of lines to return from the end of the logs, all lines if omitted.
+    #[serde(default)]
+    pub tail: Option<u32>,
+    /// Whether to keep streaming new lines.
+    #[serde(default)]
+    pub follow: bool,
+}
+
+/// Query parameters of `GET /ws/logs/{name}`.
+#[derive(Debug, Deserialize)]
+pub struct LogsStreamQuery {
+    /// Number of past lines of each container sent before following.
+    #[serde(default = "default_logs_stream_tail")]
+    pub tail: u32,
+}
+
+fn default_logs_stream_tail() -> u32 {
+    DEFAULT_LOGS_STREAM_TAIL
+}
+
+/// Parses a duration written as a number followed by `s`, `m` or `h` (e.g., `90s`, `5m`).
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    [('s', 1), ('m', 60), ('h', 60 * 60)]
+        .into_iter()
+        .find_map(|(suffix, unit)| {
+            let value = value.strip_suffix(suffix)?.parse::<u64>().ok()?;
+            Some(Duration::from_secs(value.saturating_mul(unit)))
+        })
+}
+
+/// Query parameters of `GET /apps/{name}/metrics`.
+#[derive(Debug, Deserialize)]
+pub struct MetricsQuery {
+    /// How far back to look, e.g. `30m`, `1h` or `24h`.
+    #[serde(default = "default_metrics_range")]
+    pub range: String,
+}
+
+fn default_metrics_range() -> String {
+    "1h".to_string()
+}
+
+impl MetricsQuery {
+    /// Parses the range, a number followed by `s`, `m` or `h`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Duration)` if the range is valid and within the metrics retention.
+    /// * `Err(ValidationErrors)` otherwise.
+    pub fn range(&self) -> Result<Duration, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let Some(range) = parse_duration(&self.range).filter(|range| !range.is_zero()) else {
+            errors.add("range", "range must be a number followed by s, m or h");
+            return Err(errors);
+        };
+        if range > HISTORY_RETENTION {
+            errors.add("range", "range must be at most 24h");
+            return Err(errors);
+        }
+        Ok(range)
+    }
+}
+
+/// The role of the nodes joining the Swarm.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JoinRole {
+    #[default]
+    Worker,
+    Manager,
+}
+
+impl JoinRole {
+    /// The name of the role, as accepted by `docker swarm join-token`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JoinRole::Worker => "worker",
+            JoinRole::Manager => "manager",
+        }
+    }
+}
+
+/// Query parameters of `GET /nodes/join-token`.
+#[derive(Debug, Deserialize)]
+pub struct JoinTokenQuery {
+    /// The role of the joining nodes, `worker` by default.
+    #[serde(default)]
+    pub role: JoinRole,
+}
+
+/// Query parameters of `POST /system/gc`.
+#[derive(Debug, Deserialize)]
+pub struct GcQuery {
+    /// Whether to only list the orphaned resources, without removing them.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Maximum length of a node label name.
+const MAX_NODE_LABEL_LENGTH: usize = 128;
+
+/// Checks the name of a node label (e.g., `tier`, `topology.zone`).
+fn check_node_label(errors: &mut ValidationErrors, field: &str, name: &str) {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    if name.is_empty() || !valid_chars || name.len() > MAX_NODE_LABEL_LENGTH {
+        errors.add(
+            field,
+            format!(
+                "{} is not a valid label name (letters, digits, dots, dashes and underscores, at most {} characters)",
+                name, MAX_NODE_LABEL_LENGTH
+            ),
+        );
+    }
+}
+
+/// Body of `PUT /nodes/{id}/labels`.
+#[derive(Debug, Deserialize)]
+pub struct NodeLabelsRequest {
+    /// The labels to set, existing labels with the same name are overwritten.
+    pub labels: BTreeMap<String, String>,
+}
+
+impl Validate for NodeLabelsRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.labels.is_empty() {
+            errors.add("labels", "labels must not be empty");
+        }
+        for (name, value) in &self.labels {
+            check_node_label(&mut errors, "labels", name);
+            check_length(&mut errors, "labels", Some(value), 255);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `DELETE /nodes/{id}/labels`.
+#[derive(Debug, Deserialize)]
+pub struct NodeLabelKeysRequest {
+    /// The names of the labels to remove.
+    pub keys: Vec<String>,
+}
+
+impl Validate for NodeLabelKeysRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.keys.is_empty() {
+            errors.add("keys", "keys must not be empty");
+        }
+        for name in &self.keys {
+            check_node_label(&mut errors, "keys", name);
+        }
+        errors.into_result()
+    }
+}
+
+/// Query parameters of `GET /audit`.
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    /// Only entries of this caller (e.g., `api-key:1a2b3c4d`).
+    #[serde(default)]
+    pub actor: Option<String>,
+    /// Only entries targeting this app.
+    #[serde(default)]
+    pub app_name: Option<String>,
+    /// Only entries with this HTTP method.
+    #[serde(default)]
+    pub method: Option<String>,
+    /// Only entries recorded at or after this RFC 3339 date.
+    #[serde(default)]
+    pub since: Option<String>,
+    /// Only entries recorded before this RFC 3339 date.
+    #[serde(default)]
+    pub until: Option<String>,
+    /// Maximum number of entries to return.
+    #[serde(default = "default_audit_limit")]
+    pub limit: u32,
+}
+
+fn default_audit_limit() -> u32 {
+    DEFAULT_AUDIT_LIMIT
+}
+
+impl AuditQuery {
+    /// Validates the parameters and builds the audit log filter.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AuditFilter)` if the dates are valid and the limit within bounds.
+    /// * `Err(ValidationErrors)` otherwise.
+    pub fn filter(&self) -> Result<AuditFilter, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let mut parse_date = |field: &str, value: &Option<String>| {
+            let value = value.as_deref()?;
+            match DateTime::parse_from_rfc3339(value) {
+                Ok(date) => Some(date.with_timezone(&Utc)),
+                Err(_) => {
+                    errors.add(field, format!("{} must be an RFC 3339 date", field));
+                    None
+                }
+            }
+        };
+        let since = parse_date("since", &self.since);
+        let until = parse_date("until", &self.until);
+        if self.limit == 0 || self.limit > MAX_AUDIT_LIMIT {
+            errors.add(
+                "limit",
+                format!("limit must be between 1 and {}", MAX_AUDIT_LIMIT),
+            );
+        }
+        errors.into_result()?;
+
+        Ok(AuditFilter {
+            actor: self.actor.clone(),
+            app_name: self.app_name.clone(),
+            method: self.method.clone(),
+            since,
+            until,
+            limit: self.limit,
+        })
+    }
+}
+
+/// Body of `POST /alerts/rules`.
+#[derive(Debug, Deserialize)]
+pub struct AlertRuleRequest {
+    pub name: String,
+    /// The app the rule applies to, every app if omitted.
+    #[serde(default)]
+    pub app_name: Option<String>,
+    pub condition: AlertCondition,
+    /// How long the condition must hold before the alert fires, e.g. `5m`.
+    #[serde(default, rename = "for")]
+    pub for_duration: Option<String>,
+    /// URLs notified when the alert fires and resolves.
+    pub webhooks: Vec<String>,
+}
+
+impl AlertRuleRequest {
+    /// Returns how long the condition must hold, zero if omitted.
+    pub fn for_duration(&self) -> Duration {
+        self.for_duration
+            .as_deref()
+            .and_then(parse_duration)
+            .unwrap_or_default()
+    }
+}
+
+impl Validate for AlertRuleRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.name.trim().is_empty() {
+            errors.add("name", "name is required");
+        }
+        check_length(&mut errors, "name", Some(&self.name), 255);
+        match self.condition {
+            AlertCondition::CpuAbove { percent } if percent <= 0.0 => {
+                errors.add("condition", "percent must be positive")
+            }
+            AlertCondition::MemoryAbove { mb } if mb <= 0.0 => {
+                errors.add("condition", "mb must be positive")
+            }
+            _ => {}
+        }
+        if self
+            .for_duration
+            .as_deref()
+            .is_some_and(|value| parse_duration(value).is_none())
+        {
+            errors.add("for", "for must be a number followed by s, m or h");
+        }
+        if self.webhooks.is_empty() {
+            errors.add("webhooks", "at least one webhook is required");
+        }
+        for url in &self.webhooks {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                errors.add("webhooks", format!("{} is not an HTTP(S) URL", url));
+            }
+            check_length(&mut errors, "webhooks", Some(url), MAX_TEXT_LENGTH);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/notifications`.
+#[derive(Debug, Deserialize)]
+pub struct NotificationsRequest {
+    /// Addresses emailed about the failures of the app, besides the global recipients.
+    pub emails: Vec<String>,
+}
+
+impl Validate for NotificationsRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        for email in &self.emails {
+            if let Err(e) = check_email(email) {
+                errors.add("emails", e);
+            }
+            check_length(&mut errors, "emails", Some(email), 254);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `POST /webhooks` and `POST /apps/{name}/webhooks`.
+#[derive(Debug, Deserialize)]
+pub struct WebhookRequest {
+    pub url: String,
+    /// The secret signing the deliveries, generated if omitted.
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// The types of the events delivered, every type if omitted.
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl Validate for WebhookRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            errors.add("url", format!("{} is not an HTTP(S) URL", self.url));
+        }
+        check_length(&mut errors, "url", Some(&self.url), MAX_TEXT_LENGTH);
+        if let Some(secret) = &self.secret {
+            if secret.len() < MIN_WEBHOOK_SECRET_LENGTH {
+                errors.add(
+                    "secret",
+                    format!(
+                        "secret must be at least {} characters long",
+                        MIN_WEBHOOK_SECRET_LENGTH
+                    ),
+                );
+            }
+            check_length(&mut errors, "secret", Some(secret), 255);
+        }
+        for event in &self.events {
+            if !WEBHOOK_EVENTS.contains(&event.as_str()) {
+                errors.add(
+                    "events",
+                    format!("{} is not one of {}", event, WEBHOOK_EVENTS.join(", ")),
+                );
+            }
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `POST /apps/{name}/exec`.
+#[derive(Debug, Deserialize)]
+pub struct ExecRequest {
+    /// The command and its arguments, e.g. `["rails", "db:migrate"]`.
+    pub command: Vec<String>,
+    /// How long to wait for the command, in seconds.
+    #[serde(default = "default_exec_timeout")]
+    pub timeout: u64,
+}
+
+fn default_exec_timeout() -> u64 {
+    DEFAULT_EXEC_TIMEOUT
+}
+
+impl Validate for ExecRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self
+            .command
+            .first()
+            .is_none_or(|program| program.is_empty())
+        {
+            errors.add("command", "command is required");
+        }
+        let length: usize = self.command.iter().map(String::len).sum();
+        if length > MAX_TEXT_LENGTH {
+            errors.add(
+                "command",
+                format!("command must be at most {} characters", MAX_TEXT_LENGTH),
+            );
+        }
+        if self.timeout == 0 || self.timeout > MAX_EXEC_TIMEOUT {
+            errors.add(
+                "timeout",
+                format!("timeout must be between 1 and {} seconds", MAX_EXEC_TIMEOUT),
+            );
+        }
+        errors.into_result()
+    }
+}
+
+/// Checks that an environment variable name is a valid shell identifier.
+fn check_env_name(errors: &mut ValidationErrors, field: &str, name: &str) {
+    let valid_start = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    let valid_chars = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_start || !valid_chars || name.len() > MAX_ENV_NAME_LENGTH {
+        errors.add(
+            field,
+            format!(
+                "{} is not a valid variable name (letters, digits and underscores, not starting with a digit, at most {} characters)",
+                name, MAX_ENV_NAME_LENGTH
+            ),
+        );
+    }
+}
+
+/// Records an error if a secret value is empty or larger than `MAX_SECRET_SIZE`.
+fn check_secret_value(errors: &mut ValidationErrors, field: &str, value: &str) {
+    if value.is_empty() || value.len() > MAX_SECRET_SIZE {
+        errors.add(
+            field,
+            format!(
+                "secret values must be between 1 and {} bytes",
+                MAX_SECRET_SIZE
+            ),
+        );
+    }
+}
+
+/// Body of `PUT /apps/{name}/env`.
+#[derive(Debug, Deserialize)]
+pub struct EnvRequest {
+    /// The variables to set, existing variables with the same name are overwritten.
+    pub env: BTreeMap<String, String>,
+}
+
+impl Validate for EnvRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        for (name, value) in &self.env {
+            check_env_name(&mut errors, "env", name);
+            check_length(&mut errors, "env", Some(value), MAX_TEXT_LENGTH);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `POST /templates/{id}/deploy`.
+#[derive(Debug, Deserialize)]
+pub struct TemplateDeployRequest {
+    #[serde(default)]
+    pub app_name: String,
+    /// Values of the variables of the template, and any other variable to set.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+impl Validate for TemplateDeployRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        check_app_name(&mut errors, &self.app_name);
+        for (name, value) in &self.env {
+            check_env_name(&mut errors, "env", name);
+            check_length(&mut errors, "env", Some(value), MAX_TEXT_LENGTH);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `PUT /apps/{name}/secrets`.
+#[derive(Debug, Deserialize)]
+pub struct SecretsRequest {
+    /// The values to store, by variable name. Existing secrets with the same name are
+    /// replaced.
+    pub secrets: BTreeMap<String, String>,
+}
+
+impl Validate for SecretsRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.secrets.is_empty() {
+            errors.add("secrets", "secrets must not be empty");
+        }
+        for (name, value) in &self.secrets {
+            check_env_name(&mut errors, "secrets", name);
+            check_secret_value(&mut errors, "secrets", value);
+        }
+        errors.into_result()
+    }
+}
+
+/// Body of `DELETE /apps/{name}/env` and `DELETE /apps/{name}/secrets`.
+#[derive(Debug, Deserialize)]
+pub struct EnvKeysRequest {
+    /// The names of the variables or secrets to remove.
+    pub keys: Vec<String>,
+}
+
+impl Validate for EnvKeysRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.keys.is_empty() {
+            errors.add("keys", "keys must not be empty");
+        }
+        for name in &self.keys {
+            check_env_name(&mut errors, "keys", name);
+        }
+        errors.into_result()
+    }
+}
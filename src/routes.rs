@@ -1,16 +1,86 @@
+use crate::auth::{require_principal, require_role, Forbidden, Principal, Role, Unauthorized};
+use crate::config::config;
 use crate::metrics::REGISTRY;
+use crate::openapi::{openapi_document, SWAGGER_UI};
+use crate::requests::{
+    json_body, AddonRequest, AlertRuleRequest, AppActionRequest, AuditQuery, AutoscalingRequest,
+    BulkRequest, CreateAppRequest, CronJobRequest, DeploymentPriorityRequest, DomainRequest,
+    EnvKeysRequest, EnvRequest, ExecRequest, GcQuery, HttpPolicyRequest, IpAllowlistRequest,
+    JobRequest, JoinTokenQuery, LogsQuery, MaintenanceRequest, MetricsQuery, MiddlewaresRequest,
+    NodeLabelKeysRequest, NodeLabelsRequest, NotificationsRequest, PlacementRequest, PortsRequest,
+    ProtocolRequest, RemoveAppRequest, ResourcesRequest, RollbackRequest, ScaleRequest,
+    ScalingScheduleRequest, SecretsRequest, SecurityRequest, StickySessionsRequest,
+    TemplateDeployRequest, UpdateAppRequest, UpdateConfigRequest, ValidationErrors,
+    VolumeRestoreRequest, VolumesRequest, WebhookRequest,
+};
+use crate::services::addons::{
+    list_addons, list_addons_with_status, provision_addon, remove_addon, AddonType,
+};
+use crate::services::alerting::{
+    active_alerts, add_alert_rule, delete_alert_rule, load_alert_rules,
+};
+use crate::services::app_registry::{is_deployed, list_registered_apps};
+use crate::services::app_secrets::secret_path;
+use crate::services::audit_log::query_audit_log;
+use crate::services::backup::{create_backup, restore_backup};
+use crate::services::cron_jobs::{
+    create_cron_job, delete_cron_job, find_cron_job, list_cron_jobs, list_cron_runs,
+    spawn_cron_run, update_cron_job, CronJob, CronTrigger,
+};
+use crate::services::deployment::{
+    apply_environment, apply_routing, apply_secrets, check_app_name_available, deploy_app_services,
+    find_rollback_target, load_app_request, load_deploy_request, save_deploy_request,
+    AutoscalingConfig, DeployRequest, ScalingSchedule,
+};
+use crate::services::deployment_tracker::{
+    cancel_deployment, get_deployment, list_app_deployments, list_queued_deployments,
+    set_deployment_priority, spawn_deployment, spawn_rollback, Deployment, QueueError,
+};
+use crate::services::email_notifications::{list_app_recipients, set_app_recipients};
+use crate::services::events::{publish, Event};
+use crate::services::garbage_collection::{collect_garbage, last_gc_report};
+use crate::services::helpers::crypto_helper::redact_env;
 use crate::services::helpers::docker_helper::{
-    build_image, deploy_nephelios_stack, generate_and_write_dockerfile, get_app_details,
-    list_deployed_apps, prune_images, push_image, remove_service, update_metrics, AppMetadata,
+    deploy_stack_service, exec_in_app, find_swarm_node, force_update_service, list_swarm_nodes,
+    scale_service, stream_service_logs, swarm_join_token, NodeInfo,
+};
+use crate::services::helpers::github_helper::{normalize_repo_url, verify_webhook_signature};
+use crate::services::helpers::readiness_helper::check_readiness;
+use crate::services::helpers::traefik_helper::{
+    ensure_maintenance_service, normalize_cidr, normalize_domain, update_app_placement,
+    update_app_replicas, update_app_resources, update_app_security, update_app_update_config,
+    update_app_volumes, AppProtocol, HttpPolicy, MAINTENANCE_SERVICE,
+};
+use crate::services::jobs::{find_job, list_jobs, start_job};
+use crate::services::metrics_history::app_history;
+use crate::services::node_maintenance::{change_node_availability, change_node_labels};
+use crate::services::quotas::{caller_quota, enforce_quota, AppPlan, QuotaError};
+use crate::services::reconciliation::{last_reconcile_report, reconcile};
+use crate::services::soft_delete::{delete_app, load_deleted_app, restore_app, soft_delete_app};
+use crate::services::templates::{find_template, TEMPLATES};
+use crate::services::volume_backup::{
+    app_volumes, backup_volume, find_app_volume, list_volume_backups, restore_volume,
+    volume_backup_path, volume_location_error, AppVolume,
 };
-use crate::services::helpers::github_helper::{clone_repo, create_temp_dir, remove_temp_dir};
-use crate::services::helpers::traefik_helper::{add_to_deploy, remove_app_compose, update_app_replicas, verif_app};
-use crate::services::websocket::{send_deployment_status, StatusSender};
+use crate::services::webhooks::{create_webhook, delete_webhook, list_webhooks};
+use crate::services::websocket::{send_deployment_status, DeploymentEvent};
+use async_graphql_warp::GraphQLBadRequest;
+use futures::StreamExt;
 use prometheus::{Encoder, TextEncoder};
 use serde_json::json;
 use serde_json::Value;
-use std::collections::HashMap;
-use warp::{reject, Filter};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::env;
+use tokio::io::AsyncReadExt;
+use tracing::{error, info, warn};
+use warp::{reject, Filter, Reply};
+
+/// Maximum size of a backup archive accepted by `/restore`, in bytes.
+const MAX_BACKUP_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Size of the chunks volume backups are downloaded in, in bytes.
+const DOWNLOAD_CHUNK_SIZE: usize = 64 * 1024;
 
 #[derive(Debug)]
 struct CustomError(String);
@@ -23,52 +93,741 @@ impl std::fmt::Display for CustomError {
 
 impl reject::Reject for CustomError {}
 
+/// Turns rejections into JSON error replies.
+///
+/// Invalid request bodies are answered with a 400 listing the invalid fields, requests
+/// without valid credentials with a 401, requests with a too low role with a 403, and
+/// handler failures with a 500 carrying the error message.
+///
+/// # Arguments
+///
+/// * `err` - The rejection produced by the route filters.
+///
+/// # Returns
+///
+/// A JSON reply with the matching status code.
+pub async fn handle_rejection(err: warp::Rejection) -> Result<impl Reply, Infallible> {
+    let (status, body) = if let Some(errors) = err.find::<ValidationErrors>() {
+        let status = if errors.has_invalid_app_name() {
+            warp::http::StatusCode::UNPROCESSABLE_ENTITY
+        } else {
+            warp::http::StatusCode::BAD_REQUEST
+        };
+        (status, json!(errors))
+    } else if err.find::<Unauthorized>().is_some() {
+        (
+            warp::http::StatusCode::UNAUTHORIZED,
+            json!({ "error": "Missing or invalid API key or token" }),
+        )
+    } else if err.find::<Forbidden>().is_some() {
+        (
+            warp::http::StatusCode::FORBIDDEN,
+            json!({ "error": "Insufficient role for this operation" }),
+        )
+    } else if let Some(CustomError(message)) = err.find::<CustomError>() {
+        (
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": message }),
+        )
+    } else if err.is_not_found() {
+        (
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": "Not found" }),
+        )
+    } else if let Some(e) = err.find::<GraphQLBadRequest>() {
+        (e.status(), json!({ "error": e.to_string() }))
+    } else if let Some(e) = err.find::<reject::InvalidQuery>() {
+        (
+            warp::http::StatusCode::BAD_REQUEST,
+            json!({ "error": e.to_string() }),
+        )
+    } else if err.find::<reject::PayloadTooLarge>().is_some() {
+        (
+            warp::http::StatusCode::PAYLOAD_TOO_LARGE,
+            json!({ "error": "Request body is too large" }),
+        )
+    } else if err.find::<reject::MethodNotAllowed>().is_some() {
+        (
+            warp::http::StatusCode::METHOD_NOT_ALLOWED,
+            json!({ "error": "Method not allowed" }),
+        )
+    } else {
+        error!("❌ Unhandled rejection: {:?}", err);
+        (
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": "Internal server error" }),
+        )
+    };
+
+    Ok(json_reply(status, body))
+}
+
 /// Creates the route for app creation.
 ///
 /// This route listens for POST requests at the `/create` path and expects a JSON body.
 /// The JSON body should contain the following keys:
-/// - `app_name`: The name of the application (default: "default-app").
+/// - `app_name`: The name of the application (required).
 /// - `app_type`: The type of the application (e.g., "nodejs", default: "nodejs").
 /// - `github_url`: The GitHub URL for the application repository (required).
+/// - `git_ref`: The branch, tag or commit SHA to deploy (optional, default branch if omitted).
+/// - `clone_depth`: The history depth to clone (optional, `GIT_CLONE_DEPTH` or 1 by default, 0 for a full clone).
+/// - `recurse_submodules`: Whether to clone git submodules (optional, default: false).
+/// - `auto_redeploy`: Whether to redeploy when the tracked branch receives new commits (optional, default: false).
+/// - `git_token`: A token used to clone a private repository (optional).
+/// - `deploy_key`: The name of a server-side SSH deploy key used to clone a private repository (optional).
+/// - `protocol`: The protocol spoken by the app, `http`, `h2c` or `grpc` (optional, default: "http").
+/// - `cpu_limit`, `memory_limit`: The CPU (cores) and memory (e.g., "512M") limits of the app (optional, default: "1.5" and "1G").
+/// - `cpu_reservation`, `memory_reservation`: The CPU and memory reserved for the app (optional, default: "0.5" and "256M").
 ///
 /// Returns a boxed Warp filter that handles app creation requests.
-pub fn create_app_route(
-    status_tx: StatusSender,
-) -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+pub fn create_app_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
     warp::post()
         .and(warp::path("create"))
-        .and(warp::body::json())
-        .and(warp::any().map(move || status_tx.clone()))
+        .and(require_principal(Role::Deployer))
+        .and(json_body::<CreateAppRequest>())
         .and_then(handle_create_app)
         .boxed()
 }
 
+/// Creates the route for the app template catalog.
+///
+/// This route listens for requests at the `/templates` path:
+/// - GET lists the templates, with the variables they ask for and their resources.
+/// - POST `/templates/{id}/deploy` creates an app from a template and expects a JSON body with
+///   the `app_name` and the `env` values of its variables. Variables with a default may be
+///   omitted.
+///
+/// Image templates (e.g., Ghost) are deployed without a build, repository templates are
+/// built like the apps created with `/create`. The deployment runs in the background.
+///
+/// Returns a boxed Warp filter that handles template requests.
+pub fn templates_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let list = warp::get()
+        .and(warp::path!("templates"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_templates);
+    let deploy = warp::post()
+        .and(warp::path!("templates" / String / "deploy"))
+        .and(require_principal(Role::Deployer))
+        .and(json_body::<TemplateDeployRequest>())
+        .and_then(handle_template_deploy);
+
+    list.or(deploy).boxed()
+}
+
+/// Creates the route for reading the quota of the caller.
+///
+/// This route listens for GET requests at the `/quota` path and returns the account the
+/// caller's usage is counted against (the caller, or its team), its limits and its usage.
+///
+/// Returns a boxed Warp filter that handles quota requests.
+pub fn quota_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path!("quota"))
+        .and(require_principal(Role::Viewer))
+        .and_then(handle_quota)
+        .boxed()
+}
+
+/// Creates the route for GitHub webhooks.
+///
+/// This route listens for POST requests at the `/webhooks/github` path. Deliveries must be
+/// signed with the secret configured in `GITHUB_WEBHOOK_SECRET` (`X-Hub-Signature-256` header).
+/// A `push` event on the tracked branch of a deployed app triggers a rebuild and redeploy of
+/// that app, reported on the WebSocket channel.
+///
+/// Returns a boxed Warp filter that handles GitHub webhook deliveries.
+pub fn github_webhook_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("webhooks" / "github"))
+        .and(warp::header::optional::<String>("x-github-event"))
+        .and(warp::header::optional::<String>("x-hub-signature-256"))
+        .and(warp::body::bytes())
+        .and_then(handle_github_webhook)
+        .boxed()
+}
+
+/// Creates the route for managing the custom domains of an app.
+///
+/// This route listens for POST (attach) and DELETE (detach) requests at the
+/// `/apps/{name}/domains` path and expects a JSON body.
+/// The JSON body should contain the following key:
+/// - `domain`: The domain name to attach or detach (e.g., "app.example.com").
+///
+/// The Traefik router of the app is regenerated to match every attached domain in addition
+/// to `<name>.localhost`, and the stack is redeployed.
+///
+/// Returns a boxed Warp filter that handles app domain requests.
+pub fn app_domains_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let attach = warp::post().map(|| true);
+    let detach = warp::delete().map(|| false);
+
+    attach
+        .or(detach)
+        .unify()
+        .and(warp::path!("apps" / String / "domains"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<DomainRequest>())
+        .and_then(handle_app_domains)
+        .boxed()
+}
+
+/// Creates the route for managing the environment variables of an app.
+///
+/// This route listens for requests at the `/apps/{name}/env` path:
+/// - GET returns the environment variables of the app.
+/// - PUT sets variables and expects a JSON body with an `env` object of names to values.
+///   Variables that are not listed are kept.
+/// - DELETE removes variables and expects a JSON body with a `keys` list of names.
+///
+/// Variables are set on the app service rather than baked into the image, so changes are
+/// applied with a rolling update of the service, without a rebuild. Responses redact the values
+/// of the variables whose name suggests a secret (e.g., `DB_PASSWORD`, `API_KEY`).
+///
+/// Returns a boxed Warp filter that handles app environment requests.
+pub fn app_env_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let get = warp::get()
+        .and(warp::path!("apps" / String / "env"))
+        .and(require_role(Role::Deployer))
+        .and_then(handle_app_env);
+    let set = warp::put()
+        .and(warp::path!("apps" / String / "env"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<EnvRequest>())
+        .and_then(handle_app_env_set);
+    let unset = warp::delete()
+        .and(warp::path!("apps" / String / "env"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<EnvKeysRequest>())
+        .and_then(handle_app_env_unset);
+
+    get.or(set).or(unset).boxed()
+}
+
+/// Creates the route for managing the secrets of an app.
+///
+/// This route listens for requests at the `/apps/{name}/secrets` path:
+/// - GET lists the secrets of the app, by name, never their values.
+/// - PUT stores secrets and expects a JSON body with a `secrets` object of names to values.
+///   Secrets that are not listed are kept.
+/// - DELETE removes secrets and expects a JSON body with a `keys` list of names.
+///
+/// Values are stored as Docker Swarm secrets, mounted into the app containers as
+/// `/run/secrets/<NAME>` files and pointed to by `<NAME>_FILE` variables, instead of being
+/// baked into the image or its environment. Changes are applied with a rolling update.
+///
+/// Returns a boxed Warp filter that handles app secrets requests.
+pub fn app_secrets_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let get = warp::get()
+        .and(warp::path!("apps" / String / "secrets"))
+        .and(require_role(Role::Deployer))
+        .and_then(handle_app_secrets);
+    let set = warp::put()
+        .and(warp::path!("apps" / String / "secrets"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<SecretsRequest>())
+        .and_then(handle_app_secrets_set);
+    let unset = warp::delete()
+        .and(warp::path!("apps" / String / "secrets"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<EnvKeysRequest>())
+        .and_then(handle_app_secrets_unset);
+
+    get.or(set).or(unset).boxed()
+}
+
+/// Creates the route for restricting an app to specific source IPs.
+///
+/// This route listens for PUT requests at the `/apps/{name}/ip-allowlist` path and expects a
+/// JSON body. The JSON body should contain the following key:
+/// - `ip_allowlist`: The IP addresses or CIDR ranges allowed to reach the app
+///   (e.g., `["10.0.0.0/8", "203.0.113.7"]`). An empty list lifts the restriction.
+///
+/// Requests from other sources are rejected by Traefik with a 403.
+///
+/// Returns a boxed Warp filter that handles app IP allowlist requests.
+pub fn app_ip_allowlist_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::put()
+        .and(warp::path!("apps" / String / "ip-allowlist"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<IpAllowlistRequest>())
+        .and_then(handle_app_ip_allowlist)
+        .boxed()
+}
+
+/// Creates the route for setting how an app handles plain HTTP requests.
+///
+/// This route listens for PUT requests at the `/apps/{name}/http-policy` path and expects a
+/// JSON body. The JSON body should contain the following key:
+/// - `http_policy`: `redirect` (redirect to HTTPS), `serve` (serve over HTTP too) or `block`
+///   (HTTPS only). `null` falls back to the global `domain.http_policy` setting.
+///
+/// Returns a boxed Warp filter that handles app HTTP policy requests.
+pub fn app_http_policy_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::put()
+        .and(warp::path!("apps" / String / "http-policy"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<HttpPolicyRequest>())
+        .and_then(handle_app_http_policy)
+        .boxed()
+}
+
+/// Creates the route for updating the CPU and memory of an app.
+///
+/// This route listens for PUT requests at the `/apps/{name}/resources` path and expects a JSON
+/// body. The JSON body may contain the following keys, missing keys keep their current value:
+/// - `cpu_limit`: The maximum number of CPUs (e.g., "1.5").
+/// - `memory_limit`: The maximum memory (e.g., "1G", "1GiB" or "512MB", units are binary).
+/// - `cpu_reservation`: The number of CPUs reserved for the app (e.g., "0.5").
+/// - `memory_reservation`: The memory reserved for the app (e.g., "256M").
+///
+/// Returns a boxed Warp filter that handles app resources requests.
+pub fn app_resources_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::put()
+        .and(warp::path!("apps" / String / "resources"))
+        .and(require_principal(Role::Deployer))
+        .and(json_body::<ResourcesRequest>())
+        .and_then(handle_app_resources)
+        .boxed()
+}
+
+/// Creates the route for choosing the Swarm nodes an app runs on.
+///
+/// This route listens for PUT requests at the `/apps/{name}/placement` path and expects a JSON
+/// body. The JSON body may contain the following keys, missing keys are emptied:
+/// - `constraints`: Conditions a node must meet to run the app (e.g., "node.labels.tier==gpu",
+///   "node.role!=manager").
+/// - `preferences`: Node labels the replicas are spread across (e.g., "node.labels.zone").
+///
+/// Returns a boxed Warp filter that handles app placement requests.
+pub fn app_placement_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::put()
+        .and(warp::path!("apps" / String / "placement"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<PlacementRequest>())
+        .and_then(handle_app_placement)
+        .boxed()
+}
+
+/// Creates the route for configuring the rolling updates of an app.
+///
+/// This route listens for PUT requests at the `/apps/{name}/update-config` path and expects a
+/// JSON body. The JSON body may contain the following keys, missing keys keep their current
+/// value:
+/// - `parallelism`: The number of replicas replaced at once, `0` for all of them.
+/// - `delay`: The pause between two batches (e.g., "10s", "1m").
+/// - `failure_action`: `pause`, `continue` or `rollback`, when a new replica fails to start.
+/// - `order`: `stop-first` or `start-first`.
+///
+/// Returns a boxed Warp filter that handles app update config requests.
+pub fn app_update_config_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::put()
+        .and(warp::path!("apps" / String / "update-config"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<UpdateConfigRequest>())
+        .and_then(handle_app_update_config)
+        .boxed()
+}
+
+/// Creates the route for scaling an app with its CPU usage.
+///
+/// This route listens for requests at the `/apps/{name}/autoscaling` path:
+/// - PUT enables autoscaling and expects a JSON body with the following keys:
+///   - `min_replicas`: The fewest replicas the app is scaled down to, at least 1.
+///   - `max_replicas`: The most replicas the app is scaled up to, at most
+///     `features.max_replicas`.
+///   - `target_cpu`: The average CPU usage of the replicas aimed at, in percent of the CPU
+///     reservation of a replica.
+/// - DELETE disables autoscaling, the app keeps its current replicas.
+///
+/// The replicas are changed by the autoscaler on its next evaluation, see `run_autoscaler`.
+/// An app cannot have both autoscaling and a scaling schedule.
+///
+/// Returns a boxed Warp filter that handles app autoscaling requests.
+pub fn app_autoscaling_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let enable = warp::put()
+        .and(warp::path!("apps" / String / "autoscaling"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<AutoscalingRequest>())
+        .map(|app_name, body: AutoscalingRequest| (app_name, Some(body.autoscaling)))
+        .untuple_one();
+    let disable = warp::delete()
+        .and(warp::path!("apps" / String / "autoscaling"))
+        .and(require_role(Role::Deployer))
+        .map(|app_name| (app_name, None))
+        .untuple_one();
+
+    enable
+        .or(disable)
+        .unify()
+        .and_then(handle_app_autoscaling)
+        .boxed()
+}
+
+/// Creates the route for scaling an app by time of day and day of week.
+///
+/// This route listens for requests at the `/apps/{name}/scaling-schedule` path:
+/// - PUT sets the schedule and expects a JSON body with the following keys:
+///   - `default_replicas`: The replicas outside of every window.
+///   - `windows`: The windows, the first one containing the current time wins. Each has the
+///     `days` it starts on (e.g., `["mon", "tue"]`, every day if empty), its `start` and `end`
+///     times as "HH:MM" in UTC, and its `replicas`.
+/// - DELETE removes the schedule, the app keeps its current replicas.
+///
+/// The replicas are changed by the scaling scheduler when the schedule sets new ones, see
+/// `run_scaling_scheduler`. An app cannot have both autoscaling and a scaling schedule.
+///
+/// Returns a boxed Warp filter that handles app scaling schedule requests.
+pub fn app_scaling_schedule_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let set = warp::put()
+        .and(warp::path!("apps" / String / "scaling-schedule"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<ScalingScheduleRequest>())
+        .map(|app_name, body: ScalingScheduleRequest| (app_name, Some(body.schedule)))
+        .untuple_one();
+    let remove = warp::delete()
+        .and(warp::path!("apps" / String / "scaling-schedule"))
+        .and(require_role(Role::Deployer))
+        .map(|app_name| (app_name, None))
+        .untuple_one();
+
+    set.or(remove)
+        .unify()
+        .and_then(handle_app_scaling_schedule)
+        .boxed()
+}
+
+/// Creates the route for hardening the containers of an app.
+///
+/// This route listens for PUT requests at the `/apps/{name}/security` path and expects a JSON
+/// body. The JSON body may contain the following keys, missing keys take the value of the
+/// default profile:
+/// - `read_only`: Whether the root filesystem is read-only (default: false).
+/// - `cap_drop`: Linux capabilities dropped, `ALL` for every one of them (default: `["ALL"]`).
+/// - `cap_add`: Capabilities added back (e.g., `["NET_BIND_SERVICE"]`, default: none).
+/// - `no_new_privileges`: Whether privilege escalation is forbidden (default: true).
+/// - `user`: The user the processes run as (default: "1000:1000"), `null` for the user of the
+///   image.
+///
+/// Returns a boxed Warp filter that handles app security requests.
+pub fn app_security_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::put()
+        .and(warp::path!("apps" / String / "security"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<SecurityRequest>())
+        .and_then(handle_app_security)
+        .boxed()
+}
+
+/// Creates the route for configuring session affinity of an app.
+///
+/// This route listens for PUT requests at the `/apps/{name}/sticky-sessions` path and expects
+/// a JSON body. The JSON body should contain the following keys:
+/// - `enabled`: Whether requests of a client stick to the same replica.
+/// - `cookie_name`: The name of the affinity cookie (optional, generated by Traefik).
+/// - `secure`: Whether the cookie is only sent over HTTPS (optional, default: false).
+/// - `http_only`: Whether the cookie is hidden from scripts (optional, default: false).
+/// - `same_site`: The SameSite attribute, `none`, `lax` or `strict` (optional).
+///
+/// Returns a boxed Warp filter that handles app sticky sessions requests.
+pub fn app_sticky_sessions_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::put()
+        .and(warp::path!("apps" / String / "sticky-sessions"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<StickySessionsRequest>())
+        .and_then(handle_app_sticky_sessions)
+        .boxed()
+}
+
+/// Creates the route for attaching custom Traefik middlewares to an app.
+///
+/// This route listens for PUT requests at the `/apps/{name}/middlewares` path and expects a
+/// JSON body. The JSON body should contain the following key:
+/// - `middlewares`: An object mapping each middleware name to a single
+///   `{ "<type>": { options } }` Traefik definition, e.g.
+///   `{ "frame-deny": { "headers": { "frameDeny": true } } }`. An empty object removes them.
+///
+/// Middlewares are chained in the given order, after the ones generated by Nephelios.
+///
+/// Returns a boxed Warp filter that handles app middlewares requests.
+pub fn app_middlewares_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::put()
+        .and(warp::path!("apps" / String / "middlewares"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<MiddlewaresRequest>())
+        .and_then(handle_app_middlewares)
+        .boxed()
+}
+
+/// Creates the route for setting the protocol of an app.
+///
+/// This route listens for PUT requests at the `/apps/{name}/protocol` path and expects a JSON
+/// body. The JSON body should contain the following key:
+/// - `protocol`: `http`, `h2c` (HTTP/2 cleartext) or `grpc`.
+///
+/// Returns a boxed Warp filter that handles app protocol requests.
+pub fn app_protocol_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::put()
+        .and(warp::path!("apps" / String / "protocol"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<ProtocolRequest>())
+        .and_then(handle_app_protocol)
+        .boxed()
+}
+
+/// Creates the route for exposing additional container ports of an app.
+///
+/// This route listens for PUT requests at the `/apps/{name}/ports` path and expects a JSON
+/// body. The JSON body should contain the following key:
+/// - `ports`: The additional ports, each an object with:
+///   - `name`: A short name (lowercase letters and digits, e.g., "admin").
+///   - `port`: The container port.
+///   - `subdomain`: A subdomain of the app hosts (e.g., "admin" for `admin.<app>.localhost`).
+///   - `path_prefix`: A path prefix on the app hosts (e.g., "/metrics").
+///   - `strip_prefix`: Whether the path prefix is removed before forwarding (default: false).
+///   - `protocol`: `http`, `h2c` or `grpc` (default: "http").
+///
+///   At least one of `subdomain` and `path_prefix` is required. An empty list removes them.
+///
+/// Returns a boxed Warp filter that handles app ports requests.
+pub fn app_ports_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::put()
+        .and(warp::path!("apps" / String / "ports"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<PortsRequest>())
+        .and_then(handle_app_ports)
+        .boxed()
+}
+
+/// Creates the route for updating the build settings of an app.
+///
+/// This route listens for PATCH requests at the `/apps/{name}` path and expects a JSON body.
+/// The JSON body may contain the following keys, missing keys keep their current value:
+/// - `install_command`: The command to install dependencies.
+/// - `build_command`: The command to build the application.
+/// - `run_command`: The command to start the application.
+/// - `app_workdir`: The working directory of the application in the image.
+/// - `redeploy`: Whether a new release is built right away (optional, default: false).
+///
+/// Changed settings only take effect with the next build, the app is marked as needing a
+/// rebuild until then.
+///
+/// Returns a boxed Warp filter that handles app update requests.
+pub fn app_update_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::patch()
+        .and(warp::path!("apps" / String))
+        .and(require_principal(Role::Deployer))
+        .and(json_body::<UpdateAppRequest>())
+        .and_then(handle_app_update)
+        .boxed()
+}
+
+/// Creates the route for redeploying an app from its repository.
+///
+/// This route listens for POST requests at the `/apps/{name}/redeploy` path. No body is
+/// needed: the app is cloned, built, pushed and rolled out again with the parameters it was
+/// created with.
+///
+/// The deployment runs in the background and reports its progress over WebSocket.
+///
+/// Returns a boxed Warp filter that handles app redeploy requests.
+pub fn app_redeploy_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("apps" / String / "redeploy"))
+        .and(require_principal(Role::Deployer))
+        .and_then(handle_app_redeploy)
+        .boxed()
+}
+
+/// Creates the route for rolling an app back to a previous release.
+///
+/// This route listens for POST requests at the `/apps/{name}/rollback` path and accepts an
+/// optional JSON body. The JSON body may contain the following key:
+/// - `release`: The deployment ID, release tag or image to roll back to (optional, the
+///   previous release if omitted).
+///
+/// The rollback runs in the background and reports its progress over WebSocket.
+///
+/// Returns a boxed Warp filter that handles app rollback requests.
+pub fn app_rollback_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("apps" / String / "rollback"))
+        .and(require_principal(Role::Deployer))
+        .and(json_body::<RollbackRequest>())
+        .and_then(handle_app_rollback)
+        .boxed()
+}
+
+/// Creates the route for restarting an app.
+///
+/// This route listens for POST requests at the `/apps/{name}/restart` path. It forces a rolling
+/// restart of the app service without rebuilding its image, e.g. after a configuration change.
+///
+/// Returns a boxed Warp filter that handles app restart requests.
+pub fn app_restart_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("apps" / String / "restart"))
+        .and(require_role(Role::Deployer))
+        .and_then(handle_app_restart)
+        .boxed()
+}
+
+/// Creates the route for scaling an app.
+///
+/// This route listens for POST requests at the `/apps/{name}/scale` path and expects a JSON
+/// body. The JSON body should contain the following key:
+/// - `replicas`: The number of replicas, from 0 to `features.max_replicas` (default: 10).
+///
+/// Returns a boxed Warp filter that handles app scaling requests.
+pub fn app_scale_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("apps" / String / "scale"))
+        .and(require_principal(Role::Deployer))
+        .and(json_body::<ScaleRequest>())
+        .and_then(handle_app_scale)
+        .boxed()
+}
+
+/// Creates the route for reading the logs of an app.
+///
+/// This route listens for GET requests at the `/apps/{name}/logs` path and accepts the
+/// following query parameters:
+/// - `tail`: The number of lines to return from the end of the logs (optional, all lines if omitted).
+/// - `follow`: Whether to keep streaming new lines (optional, default: false).
+///
+/// The logs of every replica are streamed as chunked plain text, one line per log entry.
+///
+/// Returns a boxed Warp filter that handles app logs requests.
+pub fn app_logs_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path!("apps" / String / "logs"))
+        .and(require_role(Role::Deployer))
+        .and(warp::query::<LogsQuery>())
+        .and_then(handle_app_logs)
+        .boxed()
+}
+
+/// Creates the route for reading the metrics history of an app.
+///
+/// This route listens for GET requests at the `/apps/{name}/metrics` path and accepts the
+/// following query parameter:
+/// - `range`: How far back to look, e.g. `30m` or `6h` (optional, default: "1h", at most "24h").
+///
+/// The CPU and memory usage of the app, summed over its replicas, is returned oldest first,
+/// one sample per metrics collection.
+///
+/// Returns a boxed Warp filter that handles app metrics requests.
+pub fn app_metrics_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path!("apps" / String / "metrics"))
+        .and(require_role(Role::Viewer))
+        .and(warp::query::<MetricsQuery>())
+        .and_then(handle_app_metrics)
+        .boxed()
+}
+
+/// Creates the route for running a command in an app container.
+///
+/// This route listens for POST requests at the `/apps/{name}/exec` path and expects a JSON
+/// body. The JSON body should contain the following keys:
+/// - `command`: The command and its arguments, e.g. `["rails", "db:migrate"]`.
+/// - `timeout`: How long to wait for the command, in seconds (optional, default: 300).
+///
+/// The output is streamed as newline-delimited JSON events, the last one holding the exit code.
+/// A command still running at the timeout is killed and reported as timed out.
+///
+/// Returns a boxed Warp filter that handles app exec requests.
+pub fn app_exec_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("apps" / String / "exec"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<ExecRequest>())
+        .and_then(handle_app_exec)
+        .boxed()
+}
+
+/// Creates the route for toggling maintenance mode of an app.
+///
+/// This route listens for POST requests at the `/apps/{name}/maintenance` path and expects a
+/// JSON body. The JSON body should contain the following key:
+/// - `enabled`: Whether requests are answered by the maintenance page (503) instead of the app.
+///
+/// The app keeps running, so migrations can be done before traffic is routed back to it.
+///
+/// Returns a boxed Warp filter that handles app maintenance requests.
+pub fn app_maintenance_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("apps" / String / "maintenance"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<MaintenanceRequest>())
+        .and_then(handle_app_maintenance)
+        .boxed()
+}
+
 /// Creates the route for app removal.
 ///
 /// This route listens for POST requests at the `/remove` path and expects a JSON body.
-/// The JSON body should contain the following key:
-/// - `app_name`: The name of the application (default: "default-app").
+/// The JSON body should contain the following keys:
+/// - `app_name`: The name of the application (required).
+/// - `soft`: Scales the app to zero and removes its routing instead, keeping its image and
+///   settings for `features.soft_delete_retention_days` (default: 7) days (optional).
 ///
 /// Returns a boxed Warp filter that handles app removal requests.
 pub fn remove_app_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
     warp::post()
         .and(warp::path("remove"))
-        .and(warp::body::json()) // Expect a JSON body
+        .and(require_role(Role::Admin))
+        .and(json_body::<RemoveAppRequest>())
         .and_then(handle_remove_app)
         .boxed()
 }
 
+/// Creates the route for bulk app operations.
+///
+/// This route listens for POST requests at the `/apps/bulk` path and expects a JSON body.
+/// The JSON body should contain the following keys:
+/// - `app_names`: The names of the applications, at most 100 (required).
+/// - `action`: `stop`, `start`, `remove` or `redeploy` (required). `remove` requires the
+///   `admin` role, other actions the `deployer` role.
+///
+/// The action runs concurrently on every app, and the response lists the result of each app.
+///
+/// Returns a boxed Warp filter that handles bulk operation requests.
+pub fn app_bulk_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("apps" / "bulk"))
+        .and(require_principal(Role::Deployer))
+        .and(json_body::<BulkRequest>())
+        .and_then(handle_app_bulk)
+        .boxed()
+}
+
+/// Creates the route for restoring a soft-deleted app.
+///
+/// This route listens for POST requests at the `/apps/{name}/restore` path. The app gets its
+/// routing and replicas back, if its retention period did not expire.
+///
+/// Returns a boxed Warp filter that handles app restore requests.
+pub fn app_restore_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("apps" / String / "restore"))
+        .and(require_role(Role::Admin))
+        .and_then(handle_app_restore)
+        .boxed()
+}
+
 /// Creates the route for stopping an app.
 ///
 /// This route listens for POST requests at the `/stop` path and expects a JSON body.
 /// The JSON body should contain the following key:
-/// - `app_name`: The name of the application (default: "default-app").
+/// - `app_name`: The name of the application (required).
 ///
 /// Returns a boxed Warp filter that handles app stop requests.
 pub fn stop_app_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
     warp::post()
         .and(warp::path("stop"))
-        .and(warp::body::json()) // Expect a JSON body
+        .and(require_role(Role::Deployer))
+        .and(json_body::<AppActionRequest>())
         .and_then(handle_stop_app)
         .boxed()
 }
@@ -77,13 +836,14 @@ pub fn stop_app_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
 ///
 /// This route listens for POST requests at the `/start` path and expects a JSON body.
 /// The JSON body should contain the following key:
-/// - `app_name`: The name of the application (default: "default-app").
+/// - `app_name`: The name of the application (required).
 ///
 /// Returns a boxed Warp filter that handles app start requests.
 pub fn start_app_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
     warp::post()
         .and(warp::path("start"))
-        .and(warp::body::json()) // Expect a JSON body
+        .and(require_principal(Role::Deployer))
+        .and(json_body::<AppActionRequest>())
         .and_then(handle_start_app)
         .boxed()
 }
@@ -101,6 +861,108 @@ pub fn health_check_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
         .boxed()
 }
 
+/// Creates the route for readiness checks.
+///
+/// This route listens for GET requests at the `/ready` path. Unlike `/health`, it verifies
+/// the Docker connection, the Swarm status, the registry, the Traefik service and the overlay
+/// network, and answers a 503 when one of them is unavailable. The response details each dependency.
+///
+/// Returns a boxed Warp filter that handles readiness check requests.
+pub fn readiness_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path("ready"))
+        .and(warp::path::end())
+        .and_then(handle_readiness)
+        .boxed()
+}
+
+/// Handles the readiness check.
+///
+/// # Returns
+///
+/// A result containing a Warp reply with a 200 if every dependency is available, a 503
+/// otherwise.
+async fn handle_readiness() -> Result<impl warp::Reply, warp::Rejection> {
+    let readiness = check_readiness().await;
+    let (status, state) = if readiness.is_ready() {
+        (warp::http::StatusCode::OK, "ready")
+    } else {
+        (warp::http::StatusCode::SERVICE_UNAVAILABLE, "not_ready")
+    };
+
+    Ok(json_reply(
+        status,
+        json!({
+            "status": state,
+            "checks": readiness,
+        }),
+    ))
+}
+
+/// Creates the route serving the OpenAPI document.
+///
+/// This route listens for GET requests at the `/openapi.json` path and returns the OpenAPI 3
+/// document describing every route of the API.
+///
+/// Returns a boxed Warp filter that handles OpenAPI document requests.
+pub fn openapi_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let document = openapi_document();
+    warp::get()
+        .and(warp::path("openapi.json"))
+        .and(warp::path::end())
+        .map(move || warp::reply::json(&document))
+        .boxed()
+}
+
+/// Creates the route for the interactive API documentation.
+///
+/// This route listens for GET requests at the `/docs` path and returns a Swagger UI page
+/// rendering the OpenAPI document.
+///
+/// Returns a boxed Warp filter that handles documentation requests.
+pub fn docs_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path("docs"))
+        .and(warp::path::end())
+        .map(|| warp::reply::html(SWAGGER_UI))
+        .boxed()
+}
+
+/// Creates the route redirecting plain HTTP requests to the API served over TLS.
+///
+/// Every request is answered with a permanent redirect (308, so the method and body are
+/// kept) to the same host, path and query on the HTTPS port.
+///
+/// # Arguments
+/// * `https_port` - The port the API is served on over TLS.
+///
+/// Returns a boxed Warp filter that handles plain HTTP requests.
+pub fn https_redirect_route(https_port: u16) -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::header::optional::<String>("host")
+        .and(warp::path::full())
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .map(
+            move |host: Option<String>, path: warp::path::FullPath, query: String| {
+                let host = host.unwrap_or_else(|| "localhost".to_string());
+                // Drop the port of the host, keeping IPv6 literals (e.g., "[::1]:3080")
+                let host = match host.rsplit_once(':') {
+                    Some((name, port)) if !port.contains(']') => name,
+                    _ => host.as_str(),
+                };
+                let mut location = format!("https://{}:{}{}", host, https_port, path.as_str());
+                if !query.is_empty() {
+                    location.push('?');
+                    location.push_str(&query);
+                }
+                match location.parse::<warp::http::Uri>() {
+                    Ok(uri) => warp::redirect::permanent(uri).into_response(),
+                    Err(_) => warp::http::StatusCode::BAD_REQUEST.into_response(),
+                }
+            },
+        )
+        .boxed()
+}
+
 /// Creates the route for metrics.
 ///
 /// This route listens for GET requests at the `/metrics` path.
@@ -116,20 +978,13 @@ pub fn create_metrics_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)>
 
 /// Handles the metrics request.
 ///
-/// This function updates the metrics and returns a text response containing the metrics.
+/// This function returns a text response containing the metrics, refreshed in the
+/// background by `run_metrics_collector`.
 ///
 /// # Returns
 ///
 /// A result containing a Warp reply or a Warp rejection.
-///
-/// # Errors
-///
-/// This function returns a Warp rejection if the metrics update fails.
 async fn handle_metrics() -> Result<impl warp::Reply, warp::Rejection> {
-    if let Err(e) = update_metrics().await {
-        eprintln!("Failed to update metrics: {}", e);
-    }
-
     let encoder = TextEncoder::new();
     let metric_families = REGISTRY.gather();
     let mut buffer = Vec::new();
@@ -145,21 +1000,37 @@ async fn handle_metrics() -> Result<impl warp::Reply, warp::Rejection> {
 
 /// Handles the app start logic.
 ///
-/// Extracts `app_name` from the JSON body and performs the necessary steps to start the app:
-/// adding the app to the deployment list and scaling the service to 1.
+/// Takes `app_name` from the request body and performs the necessary steps to start the app:
+/// adding the app to the deployment list and scaling the service to 1. The replica must fit
+/// in the quota of the owner of the app.
 ///
 /// # Arguments
 ///
-/// * `body` - The JSON body received in the request, expected to contain `app_name`.
+/// * `principal` - The authenticated caller, whose quota bounds the reservations.
+/// * `body` - The validated request body.
 ///
 /// # Returns
 ///
 /// A result containing a Warp reply or a Warp rejection.
-async fn handle_start_app(body: Value) -> Result<impl warp::Reply, warp::Rejection> {
-    let app_name = body
-        .get("app_name")
-        .and_then(Value::as_str)
-        .unwrap_or("default-app");
+async fn handle_start_app(
+    principal: Principal,
+    body: AppActionRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let app_name = body.app_name.as_str();
+
+    // The reservations freed by stopping the app may have been spent since
+    let request = match find_app_request(app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply.into_response()),
+    };
+    let plan = AppPlan {
+        builds: false,
+        replicas: 1,
+        ..AppPlan::deploy(&request)
+    };
+    if let Err(reply) = check_quota(&principal, &plan) {
+        return Ok(reply.into_response());
+    }
 
     if let Err(e) = update_app_replicas(app_name, 1) {
         return Err(warp::reject::custom(CustomError(format!(
@@ -168,36 +1039,37 @@ async fn handle_start_app(body: Value) -> Result<impl warp::Reply, warp::Rejecti
         ))));
     }
 
-    if let Err(e) = deploy_nephelios_stack() {
+    if let Err(e) = deploy_app_services(app_name).await {
         return Err(warp::reject::custom(CustomError(format!(
-            "Failed to deploy stack for app {}: {}",
+            "Failed to deploy app {}: {}",
             app_name, e
         ))));
     }
 
+    publish(Event::AppStarted {
+        app_name: app_name.to_string(),
+    });
     Ok(warp::reply::with_status(
         format!("start app: {}.", app_name),
         warp::http::StatusCode::CREATED,
-    ))
+    )
+    .into_response())
 }
 
 /// Handles the app stop logic.
 ///
-/// Extracts `app_name` from the JSON body and performs the necessary steps to stop the app:
+/// Takes `app_name` from the request body and performs the necessary steps to stop the app:
 /// stopping the running service and scaling this serving to 0.
 ///
 /// # Arguments
 ///
-/// * `body` - The JSON body received in the request, expected to contain `app_name`.
+/// * `body` - The validated request body.
 ///
 /// # Returns
 ///
 /// A result containing a Warp reply or a Warp rejection.
-async fn handle_stop_app(body: Value) -> Result<impl warp::Reply, warp::Rejection> {
-    let app_name = body
-        .get("app_name")
-        .and_then(Value::as_str)
-        .unwrap_or("default-app");
+async fn handle_stop_app(body: AppActionRequest) -> Result<impl warp::Reply, warp::Rejection> {
+    let app_name = body.app_name.as_str();
     if let Err(e) = update_app_replicas(app_name, 0) {
         return Err(warp::reject::custom(CustomError(format!(
             "Failed to update replicas for app {}: {}",
@@ -205,54 +1077,3527 @@ async fn handle_stop_app(body: Value) -> Result<impl warp::Reply, warp::Rejectio
         ))));
     }
 
-    if let Err(e) = deploy_nephelios_stack() {
+    if let Err(e) = deploy_app_services(app_name).await {
         return Err(warp::reject::custom(CustomError(format!(
-            "Failed to deploy stack for app {}: {}",
+            "Failed to deploy app {}: {}",
             app_name, e
         ))));
     }
 
+    publish(Event::AppStopped {
+        app_name: app_name.to_string(),
+    });
     Ok(warp::reply::with_status(
         format!("stop app: {}.", app_name),
         warp::http::StatusCode::CREATED,
     ))
 }
 
-/// Handles the app removal logic.
+/// Creates the route for managing the persistent volumes of an app.
+///
+/// This route listens for requests at the `/apps/{name}/volumes` path:
+/// - GET lists the volumes of the app, with the Docker volume backing each of them.
+/// - PUT replaces the volumes and expects a JSON body with a `volumes` list of objects with
+///   the `name` of the volume and the absolute `path` it is mounted at.
+///
+/// - POST `/apps/{name}/volumes/{volume}/backup` archives a volume into the backup directory.
+/// - GET `/apps/{name}/volumes/{volume}/backups` lists the backups of a volume.
+/// - GET `/apps/{name}/volumes/{volume}/backups/{id}` downloads a backup as a gzipped tar.
+/// - POST `/apps/{name}/volumes/{volume}/restore` replaces the content of a volume with a
+///   backup and expects a JSON body with the `backup` ID.
+///
+/// Volumes are named Docker volumes, kept across redeploys. A volume that is no longer listed
+/// is unmounted but not deleted, and is found again when mounted back under the same name.
+/// The data volumes of the addons are listed too, named after the addon type, and can be
+/// backed up and restored the same way.
+///
+/// Returns a boxed Warp filter that handles app volume requests.
+pub fn app_volumes_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let list = warp::get()
+        .and(warp::path!("apps" / String / "volumes"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_app_volumes);
+    let set = warp::put()
+        .and(warp::path!("apps" / String / "volumes"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<VolumesRequest>())
+        .and_then(handle_app_volumes_set);
+    let backup = warp::post()
+        .and(warp::path!("apps" / String / "volumes" / String / "backup"))
+        .and(require_role(Role::Deployer))
+        .and_then(handle_volume_backup);
+    let backups = warp::get()
+        .and(warp::path!(
+            "apps" / String / "volumes" / String / "backups"
+        ))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_volume_backups);
+    let download = warp::get()
+        .and(warp::path!(
+            "apps" / String / "volumes" / String / "backups" / String
+        ))
+        .and(require_role(Role::Admin))
+        .and_then(handle_volume_backup_download);
+    let restore = warp::post()
+        .and(warp::path!(
+            "apps" / String / "volumes" / String / "restore"
+        ))
+        .and(require_role(Role::Admin))
+        .and(json_body::<VolumeRestoreRequest>())
+        .and_then(handle_volume_restore);
+
+    list.or(set)
+        .or(backup)
+        .or(backups)
+        .or(download)
+        .or(restore)
+        .boxed()
+}
+
+/// Creates the route for managing the addons of an app (e.g., a PostgreSQL database).
+///
+/// This route listens for requests at the `/apps/{name}/addons` path:
+/// - GET lists the addons of the app, with the status of their service.
+/// - POST provisions an addon and expects a JSON body with its `type` (`postgres`, `redis`,
+///   `mysql` or `mariadb`) and, for Redis, whether its data is `persistent` (default: true).
+/// - DELETE `/apps/{name}/addons/{type}` removes an addon, keeping its data volume.
+///
+/// An addon runs as its own service on the overlay network, with generated credentials. Its
+/// connection URL is injected into the environment of the app (e.g., `DATABASE_URL`,
+/// `REDIS_URL`).
+///
+/// Returns a boxed Warp filter that handles app addon requests.
+pub fn app_addons_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let list = warp::get()
+        .and(warp::path!("apps" / String / "addons"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_app_addons);
+    let add = warp::post()
+        .and(warp::path!("apps" / String / "addons"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<AddonRequest>())
+        .and_then(handle_app_addon_add);
+    let remove = warp::delete()
+        .and(warp::path!("apps" / String / "addons" / String))
+        .and(require_role(Role::Deployer))
+        .and_then(handle_app_addon_remove);
+
+    list.or(add).or(remove).boxed()
+}
+
+/// Creates the route for managing the cron jobs of an app.
+///
+/// This route listens for requests at the `/apps/{name}/cron-jobs` path:
+/// - GET lists the cron jobs of the app, with when they run next.
+/// - POST adds a cron job and expects a JSON body with its `schedule` (a five-field cron
+///   expression in UTC, e.g. `0 3 * * *`), the `command` run with `sh -c`, and optionally its
+///   `mode` (`job` or `exec`, default: `job`), `timeout` in seconds (default: 3600) and
+///   whether it is `enabled` (default: true).
+/// - PUT `/apps/{name}/cron-jobs/{id}` replaces the settings of a cron job.
+/// - DELETE `/apps/{name}/cron-jobs/{id}` deletes a cron job and its run history.
+/// - GET `/apps/{name}/cron-jobs/{id}/runs` lists the last runs, with their exit code.
+/// - POST `/apps/{name}/cron-jobs/{id}/run` runs a cron job now.
+///
+/// In `job` mode the command runs in a one-shot Swarm task of the app image with the app
+/// environment; in `exec` mode it runs in a running container of the app.
+///
+/// Returns a boxed Warp filter that handles app cron job requests.
+pub fn app_cron_jobs_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let list = warp::get()
+        .and(warp::path!("apps" / String / "cron-jobs"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_cron_jobs);
+    let create = warp::post()
+        .and(warp::path!("apps" / String / "cron-jobs"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<CronJobRequest>())
+        .and_then(handle_cron_job_create);
+    let update = warp::put()
+        .and(warp::path!("apps" / String / "cron-jobs" / String))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<CronJobRequest>())
+        .and_then(handle_cron_job_update);
+    let delete = warp::delete()
+        .and(warp::path!("apps" / String / "cron-jobs" / String))
+        .and(require_role(Role::Deployer))
+        .and_then(handle_cron_job_delete);
+    let runs = warp::get()
+        .and(warp::path!("apps" / String / "cron-jobs" / String / "runs"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_cron_job_runs);
+    let run = warp::post()
+        .and(warp::path!("apps" / String / "cron-jobs" / String / "run"))
+        .and(require_role(Role::Deployer))
+        .and_then(handle_cron_job_run);
+
+    list.or(create)
+        .or(update)
+        .or(delete)
+        .or(runs)
+        .or(run)
+        .boxed()
+}
+
+/// Creates the route for running one-off jobs of an app.
+///
+/// This route listens for requests at the `/apps/{name}/jobs` path:
+/// - POST runs a command to completion and expects a JSON body with the `command` run with
+///   `sh -c` and optionally a `timeout` in seconds (default: 3600). The response streams
+///   newline-delimited JSON events: `started` with the job, `output` for each log line, then
+///   `exit` with the exit code.
+/// - GET lists the last jobs of the app, without their output.
+/// - GET `/apps/{name}/jobs/{id}` returns a job with the end of its output.
+///
+/// The command runs in a one-shot Swarm task of the app image with the app environment,
+/// which is not restarted when the command fails (e.g., migrations, batch scripts). The job
+/// keeps running if the client disconnects.
+///
+/// Returns a boxed Warp filter that handles app job requests.
+pub fn app_jobs_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let run = warp::post()
+        .and(warp::path!("apps" / String / "jobs"))
+        .and(require_principal(Role::Deployer))
+        .and(json_body::<JobRequest>())
+        .and_then(handle_job_run);
+    let list = warp::get()
+        .and(warp::path!("apps" / String / "jobs"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_jobs);
+    let get = warp::get()
+        .and(warp::path!("apps" / String / "jobs" / String))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_job);
+
+    run.or(list).or(get).boxed()
+}
+
+/// Creates the route for the email notifications of an app.
+///
+/// This route listens for requests at the `/apps/{name}/notifications` path:
+/// - GET returns the addresses emailed about the app.
+/// - PUT replaces them and expects a JSON body with the `emails` list, empty to only notify
+///   the global `smtp.recipients`.
+///
+/// Failed deployments, crashed apps and fired alerts are emailed when `smtp.host` is set.
+///
+/// Returns a boxed Warp filter that handles app notification requests.
+pub fn app_notifications_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let get = warp::get()
+        .and(warp::path!("apps" / String / "notifications"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_app_notifications);
+    let update = warp::put()
+        .and(warp::path!("apps" / String / "notifications"))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<NotificationsRequest>())
+        .and_then(handle_app_notifications_update);
+
+    get.or(update).boxed()
+}
+
+/// Creates the route for the webhooks of an app.
+///
+/// This route listens for requests at the `/apps/{name}/webhooks` path:
+/// - GET lists the webhooks of the app, without their secret.
+/// - POST registers a webhook and expects a JSON body with the following keys:
+///   - `url`: The URL the events are posted to (required).
+///   - `secret`: The secret signing the deliveries (optional, generated if omitted).
+///   - `events`: The types of the events delivered, e.g. `["deployment_finished",
+///     "app_crashed"]` (optional, every type if omitted).
+///
+///   The secret is only returned in the response.
+/// - DELETE `/apps/{name}/webhooks/{id}` removes a webhook.
+///
+/// Each event is posted as `{"timestamp": ..., "event": {"type": ..., ...}}`, with its
+/// HMAC-SHA256 in the `X-Nephelios-Signature: sha256=<hex digest>` header.
+///
+/// Returns a boxed Warp filter that handles app webhook requests.
+pub fn app_webhooks_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let list = warp::get()
+        .and(warp::path!("apps" / String / "webhooks").map(Some))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_webhooks);
+    let create = warp::post()
+        .and(warp::path!("apps" / String / "webhooks").map(Some))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<WebhookRequest>())
+        .and_then(handle_webhook_create);
+    let delete = warp::delete()
+        .and(
+            warp::path!("apps" / String / "webhooks" / String)
+                .map(|app_name, id| (Some(app_name), id)),
+        )
+        .untuple_one()
+        .and(require_role(Role::Deployer))
+        .and_then(handle_webhook_delete);
+
+    list.or(create).or(delete).boxed()
+}
+
+/// Builds a JSON reply with the given status code.
+fn json_reply(
+    status: warp::http::StatusCode,
+    body: Value,
+) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(warp::reply::json(&body), status)
+}
+
+/// Loads the deploy request of an app, or builds the 404 reply if the app is unknown.
+async fn find_app_request(
+    app_name: &str,
+) -> Result<DeployRequest, warp::reply::WithStatus<warp::reply::Json>> {
+    load_app_request(app_name)
+        .await
+        .map_err(|e| json_reply(warp::http::StatusCode::NOT_FOUND, json!({ "error": e })))
+}
+
+/// Checks a change to an app against the quota of its owner, or builds the 403 reply if the
+/// change would exceed it.
+fn check_quota(
+    principal: &Principal,
+    plan: &AppPlan,
+) -> Result<(), warp::reply::WithStatus<warp::reply::Json>> {
+    enforce_quota(Some(principal), plan).map_err(|e| match e {
+        QuotaError::Exceeded(e) => {
+            json_reply(warp::http::StatusCode::FORBIDDEN, json!({ "error": e }))
+        }
+        QuotaError::Failed(e) => json_reply(
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": format!("Failed to check quota: {}", e) }),
+        ),
+    })
+}
+
+/// Handles the custom domain attach and detach logic.
+///
+/// Updates the domains stored for the app, regenerates its Traefik labels in the stack file
+/// and redeploys the stack.
+///
+/// # Arguments
+///
+/// * `attach` - Whether the domain is attached (`true`) or detached (`false`).
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_domains(
+    attach: bool,
+    app_name: String,
+    body: DomainRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // The domain was validated by the body filter
+    let domain = normalize_domain(&body.domain).unwrap_or(body.domain);
+
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    let domains = &mut request.routing.domains;
+    if attach {
+        if !domains.contains(&domain) {
+            domains.push(domain);
+        }
+    } else {
+        let before = domains.len();
+        domains.retain(|attached| attached != &domain);
+        if domains.len() == before {
+            return Ok(json_reply(
+                warp::http::StatusCode::NOT_FOUND,
+                json!({ "error": format!("Domain {} is not attached to {}", domain, app_name) }),
+            ));
+        }
+    }
+
+    apply_routing(&request)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "domains": request.routing.domains,
+        }),
+    ))
+}
+
+/// Handles the app environment request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_env(app_name: String) -> Result<impl warp::Reply, warp::Rejection> {
+    let request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "env": redact_env(&request.env),
+        }),
+    ))
+}
+
+/// Handles the environment variables update logic.
+///
+/// Merges the variables into the ones stored for the app, sets them on the app service in the
+/// stack file and redeploys the stack.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_env_set(
+    app_name: String,
+    body: EnvRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.env.extend(body.env);
+    apply_environment(&request)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "env": redact_env(&request.env),
+        }),
+    ))
+}
+
+/// Handles the environment variables removal logic.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_env_unset(
+    app_name: String,
+    body: EnvKeysRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    let missing: Vec<&String> = body
+        .keys
+        .iter()
+        .filter(|key| !request.env.contains_key(*key))
+        .collect();
+    if !missing.is_empty() {
+        return Ok(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Variables not set on {}: {:?}", app_name, missing) }),
+        ));
+    }
+
+    for key in &body.keys {
+        request.env.remove(key);
+    }
+    apply_environment(&request)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "env": redact_env(&request.env),
+        }),
+    ))
+}
+
+/// Builds the reply listing the secrets of an app, without their values.
+fn app_secrets_reply(request: &DeployRequest) -> warp::reply::WithStatus<warp::reply::Json> {
+    let secrets: Vec<Value> = request
+        .secrets
+        .iter()
+        .map(|(name, secret)| {
+            json!({
+                "name": name,
+                "secret": secret,
+                "path": secret_path(name),
+            })
+        })
+        .collect();
+
+    json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": request.app_name,
+            "secrets": secrets,
+        }),
+    )
+}
+
+/// Handles the secrets listing logic.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_secrets(app_name: String) -> Result<impl warp::Reply, warp::Rejection> {
+    match find_app_request(&app_name).await {
+        Ok(request) => Ok(app_secrets_reply(&request)),
+        Err(reply) => Ok(reply),
+    }
+}
+
+/// Handles the secrets update logic.
+///
+/// Stores the values as Docker secrets, mounts them into the app service in the stack file
+/// and redeploys the stack.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_secrets_set(
+    app_name: String,
+    body: SecretsRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.pending_secrets = body.secrets;
+    apply_secrets(&mut request, Vec::new())
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(app_secrets_reply(&request))
+}
+
+/// Handles the secrets removal logic.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_secrets_unset(
+    app_name: String,
+    body: EnvKeysRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    let missing: Vec<&String> = body
+        .keys
+        .iter()
+        .filter(|key| !request.secrets.contains_key(*key))
+        .collect();
+    if !missing.is_empty() {
+        return Ok(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Secrets not set on {}: {:?}", app_name, missing) }),
+        ));
+    }
+
+    let removed = body
+        .keys
+        .iter()
+        .filter_map(|key| request.secrets.remove(key))
+        .collect();
+    apply_secrets(&mut request, removed)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(app_secrets_reply(&request))
+}
+
+/// Handles the IP allowlist update logic.
+///
+/// Replaces the allowlist stored for the app, regenerates its Traefik labels in the stack
+/// file and redeploys the stack.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_ip_allowlist(
+    app_name: String,
+    body: IpAllowlistRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    // Ranges were validated by the body filter
+    request.routing.ip_allowlist = body
+        .ip_allowlist
+        .iter()
+        .filter_map(|range| normalize_cidr(range).ok())
+        .collect();
+    apply_routing(&request)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "ip_allowlist": request.routing.ip_allowlist,
+        }),
+    ))
+}
+
+/// Handles the HTTP policy update logic.
+///
+/// Stores the policy for the app, regenerates its Traefik labels in the stack file and
+/// redeploys the stack.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_http_policy(
+    app_name: String,
+    body: HttpPolicyRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.routing.http_policy = body
+        .http_policy
+        .as_deref()
+        .and_then(|policy| HttpPolicy::parse(policy).ok());
+    apply_routing(&request)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "http_policy": request.routing.http_policy,
+            "effective_http_policy": request.routing.effective_http_policy(),
+        }),
+    ))
+}
+
+/// Handles the app resources update logic.
+///
+/// Stores the new limits for the app, writes them into its service in the stack file and
+/// redeploys the stack.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `principal` - The authenticated caller, whose quota bounds the reservations.
+/// * `body` - The validated request body, containing the limits to change.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_resources(
+    app_name: String,
+    principal: Principal,
+    body: ResourcesRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.resources = match request.resources.with_overrides(&body.resources) {
+        Ok(resources) => resources,
+        Err((field, message)) => {
+            let mut errors = ValidationErrors::default();
+            errors.add(field, message);
+            return Ok(json_reply(
+                warp::http::StatusCode::BAD_REQUEST,
+                json!(errors),
+            ));
+        }
+    };
+
+    let plan = AppPlan {
+        builds: false,
+        reservations: request.resources.reservations(),
+        ..AppPlan::deploy(&request)
+    };
+    if let Err(reply) = check_quota(&principal, &plan) {
+        return Ok(reply);
+    }
+
+    update_app_resources(&app_name, request.resources.to_stack_resources()).map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to update resources for app {}: {}",
+            app_name, e
+        )))
+    })?;
+
+    save_deploy_request(&request).map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    deploy_app_services(&app_name).await.map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to deploy app {}: {}",
+            app_name, e
+        )))
+    })?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "resources": request.resources,
+        }),
+    ))
+}
+
+/// Handles the app placement update logic.
+///
+/// Stores the new placement of the app, writes it into its service in the stack file and
+/// redeploys the stack, which moves the replicas running on nodes that no longer match.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body, containing the constraints and preferences.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_placement(
+    app_name: String,
+    body: PlacementRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.placement = body.placement;
+    update_app_placement(&app_name, request.placement.to_stack_placement()).map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to update placement for app {}: {}",
+            app_name, e
+        )))
+    })?;
+
+    save_deploy_request(&request).map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    deploy_app_services(&app_name).await.map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to deploy app {}: {}",
+            app_name, e
+        )))
+    })?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "placement": request.placement,
+        }),
+    ))
+}
+
+/// Handles the app security logic.
+///
+/// Stores the new hardening settings of the app, writes them into its service in the stack
+/// file and redeploys the stack. The `USER` of the generated Dockerfile follows on the next
+/// build, the service runs as the new user right away.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body, containing the new settings.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_security(
+    app_name: String,
+    body: SecurityRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.security = body.security;
+    update_app_security(&app_name, request.security.to_stack_security()).map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to update security options for app {}: {}",
+            app_name, e
+        )))
+    })?;
+
+    save_deploy_request(&request).map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    deploy_app_services(&app_name).await.map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to deploy app {}: {}",
+            app_name, e
+        )))
+    })?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "security": request.security,
+        }),
+    ))
+}
+
+/// Handles the app autoscaling logic.
+///
+/// Stores the autoscaling settings of the app, applied by the autoscaler on its next
+/// evaluation.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `autoscaling` - The new settings, `None` to disable autoscaling.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_autoscaling(
+    app_name: String,
+    autoscaling: Option<AutoscalingConfig>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    if autoscaling.is_some() && request.scaling_schedule.is_some() {
+        return Ok(json_reply(
+            warp::http::StatusCode::CONFLICT,
+            json!({ "error": format!("Application {} has a scaling schedule, remove it first", app_name) }),
+        ));
+    }
+
+    request.autoscaling = autoscaling;
+    save_deploy_request(&request).map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "autoscaling": request.autoscaling,
+        }),
+    ))
+}
+
+/// Handles the app scaling schedule logic.
+///
+/// Stores the scaling schedule of the app, applied by the scaling scheduler on its next
+/// evaluation.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `schedule` - The new schedule, `None` to remove it.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_scaling_schedule(
+    app_name: String,
+    schedule: Option<ScalingSchedule>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    if schedule.is_some() && request.autoscaling.is_some() {
+        return Ok(json_reply(
+            warp::http::StatusCode::CONFLICT,
+            json!({ "error": format!("Application {} has autoscaling, disable it first", app_name) }),
+        ));
+    }
+
+    request.scaling_schedule = schedule;
+    save_deploy_request(&request).map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "scaling_schedule": request.scaling_schedule,
+        }),
+    ))
+}
+
+/// Handles the app update config logic.
+///
+/// Stores the new rolling update settings of the app, writes them into its service in the
+/// stack file and redeploys the stack. They apply from the next update of the service.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body, containing the settings to change.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_update_config(
+    app_name: String,
+    body: UpdateConfigRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.update_config = match request.update_config.with_overrides(&body.update_config) {
+        Ok(update_config) => update_config,
+        Err((field, message)) => {
+            let mut errors = ValidationErrors::default();
+            errors.add(field, message);
+            return Ok(json_reply(
+                warp::http::StatusCode::BAD_REQUEST,
+                json!(errors),
+            ));
+        }
+    };
+
+    update_app_update_config(&app_name, request.update_config.to_stack_update_config()).map_err(
+        |e| {
+            warp::reject::custom(CustomError(format!(
+                "Failed to update the update config of app {}: {}",
+                app_name, e
+            )))
+        },
+    )?;
+
+    save_deploy_request(&request).map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    deploy_app_services(&app_name).await.map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to deploy app {}: {}",
+            app_name, e
+        )))
+    })?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "update_config": request.update_config,
+        }),
+    ))
+}
+
+/// Handles the app volumes listing request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_volumes(app_name: String) -> Result<impl warp::Reply, warp::Rejection> {
+    let request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    let volumes = app_volumes(&request).map_err(|e| warp::reject::custom(CustomError(e)))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "volumes": volumes,
+        }),
+    ))
+}
+
+/// Handles the app volumes update logic.
+///
+/// Stores the new volumes of the app, mounts them into its service in the stack file and
+/// redeploys the stack, which replaces the app tasks.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_volumes_set(
+    app_name: String,
+    body: VolumesRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.volumes = body.volumes;
+    update_app_volumes(&app_name, &request.volume_mounts()).map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to update volumes for app {}: {}",
+            app_name, e
+        )))
+    })?;
+
+    save_deploy_request(&request).map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    deploy_app_services(&app_name).await.map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to deploy app {}: {}",
+            app_name, e
+        )))
+    })?;
+
+    let volumes = app_volumes(&request).map_err(|e| warp::reject::custom(CustomError(e)))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "volumes": volumes,
+        }),
+    ))
+}
+
+/// Finds a volume of an app or of one of its addons, or builds the 404 reply if the app or
+/// the volume is unknown.
+async fn find_volume(
+    app_name: &str,
+    volume: &str,
+) -> Result<AppVolume, warp::reply::WithStatus<warp::reply::Json>> {
+    let request = find_app_request(app_name).await?;
+    match find_app_volume(&request, volume) {
+        Ok(Some(volume)) => Ok(volume),
+        Ok(None) => Err(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Volume {} not found for app {}", volume, app_name) }),
+        )),
+        Err(e) => Err(json_reply(
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": e }),
+        )),
+    }
+}
+
+/// Builds the 409 reply if a volume is not on the node running Nephelios.
+async fn check_volume_location(
+    volume: &AppVolume,
+) -> Result<(), warp::reply::WithStatus<warp::reply::Json>> {
+    match volume_location_error(volume).await {
+        Ok(None) => Ok(()),
+        Ok(Some(e)) => Err(json_reply(
+            warp::http::StatusCode::CONFLICT,
+            json!({ "error": e }),
+        )),
+        Err(e) => Err(json_reply(
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": e }),
+        )),
+    }
+}
+
+/// Handles the volume backup request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `volume` - The name of the volume, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_volume_backup(
+    app_name: String,
+    volume: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let volume = match find_volume(&app_name, &volume).await {
+        Ok(volume) => volume,
+        Err(reply) => return Ok(reply),
+    };
+    if let Err(reply) = check_volume_location(&volume).await {
+        return Ok(reply);
+    }
+
+    let backup = backup_volume(&app_name, &volume)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::CREATED,
+        json!({
+            "app_name": app_name,
+            "backup": backup,
+        }),
+    ))
+}
+
+/// Handles the volume backups listing request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `volume` - The name of the volume, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_volume_backups(
+    app_name: String,
+    volume: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let volume = match find_volume(&app_name, &volume).await {
+        Ok(volume) => volume,
+        Err(reply) => return Ok(reply),
+    };
+
+    let backups = list_volume_backups(&app_name, &volume.name)
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "volume": volume.name,
+            "backups": backups,
+        }),
+    ))
+}
+
+/// Handles the volume backup download request.
+///
+/// The archive is streamed from the backup directory as a gzipped tar.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `volume` - The name of the volume, taken from the path.
+/// * `id` - The ID of the backup, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_volume_backup_download(
+    app_name: String,
+    volume: String,
+    id: String,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let volume = match find_volume(&app_name, &volume).await {
+        Ok(volume) => volume,
+        Err(reply) => return Ok(reply.into_response()),
+    };
+    let path = match volume_backup_path(&app_name, &volume.name, &id) {
+        Ok(Some(path)) => path,
+        Ok(None) => {
+            let error = format!("Backup {} not found for volume {}", id, volume.name);
+            return Ok(
+                json_reply(warp::http::StatusCode::NOT_FOUND, json!({ "error": error }))
+                    .into_response(),
+            );
+        }
+        Err(e) => return Err(warp::reject::custom(CustomError(e))),
+    };
+
+    let file = tokio::fs::File::open(&path).await.map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to open {}: {}",
+            path.display(),
+            e
+        )))
+    })?;
+    let chunks = futures::stream::unfold(file, |mut file| async move {
+        let mut buffer = vec![0; DOWNLOAD_CHUNK_SIZE];
+        match file.read(&mut buffer).await {
+            Ok(0) => None,
+            Ok(read) => {
+                buffer.truncate(read);
+                Some((Ok(bytes::Bytes::from(buffer)), file))
+            }
+            Err(e) => Some((Err::<bytes::Bytes, _>(e), file)),
+        }
+    });
+
+    let mut response = warp::reply::Response::new(warp::hyper::Body::wrap_stream(chunks));
+    let headers = response.headers_mut();
+    headers.insert(
+        warp::http::header::CONTENT_TYPE,
+        warp::http::HeaderValue::from_static("application/gzip"),
+    );
+    if let Ok(value) = warp::http::HeaderValue::from_str(&format!(
+        "attachment; filename=\"{}-{}-{}.tar.gz\"",
+        app_name, volume.name, id
+    )) {
+        headers.insert(warp::http::header::CONTENT_DISPOSITION, value);
+    }
+    Ok(response)
+}
+
+/// Handles the volume restore request.
+///
+/// Stops the service mounting the volume, replaces the content of the volume with the backup
+/// and starts the service again.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `volume` - The name of the volume, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_volume_restore(
+    app_name: String,
+    volume: String,
+    body: VolumeRestoreRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let volume = match find_volume(&app_name, &volume).await {
+        Ok(volume) => volume,
+        Err(reply) => return Ok(reply),
+    };
+    let path = match volume_backup_path(&app_name, &volume.name, &body.backup) {
+        Ok(Some(path)) => path,
+        Ok(None) => {
+            return Ok(json_reply(
+                warp::http::StatusCode::NOT_FOUND,
+                json!({
+                    "error": format!("Backup {} not found for volume {}", body.backup, volume.name)
+                }),
+            ))
+        }
+        Err(e) => return Err(warp::reject::custom(CustomError(e))),
+    };
+    if let Err(reply) = check_volume_location(&volume).await {
+        return Ok(reply);
+    }
+
+    restore_volume(&app_name, &volume, &path)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "message": format!("Volume {} restored from backup {}", volume.name, body.backup),
+            "app_name": app_name,
+            "volume": volume.name,
+            "backup": body.backup,
+        }),
+    ))
+}
+
+/// Handles the app addons listing request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_addons(app_name: String) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply);
+    }
+
+    let addons = list_addons_with_status(&app_name)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "addons": addons,
+        }),
+    ))
+}
+
+/// Handles the addon provisioning logic.
+///
+/// Adds the addon service to the stack file, sets its connection URL in the environment of
+/// the app and redeploys the stack.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_addon_add(
+    app_name: String,
+    body: AddonRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // The type was validated by the body filter
+    let addon_type =
+        AddonType::parse(&body.addon_type).map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    let existing = list_addons(&app_name).map_err(|e| warp::reject::custom(CustomError(e)))?;
+    if existing.iter().any(|addon| addon.addon_type == addon_type) {
+        return Ok(json_reply(
+            warp::http::StatusCode::CONFLICT,
+            json!({
+                "error": format!("{} already has a {} addon", app_name, addon_type.as_str())
+            }),
+        ));
+    }
+    if request.env.contains_key(addon_type.env_var()) {
+        return Ok(json_reply(
+            warp::http::StatusCode::CONFLICT,
+            json!({
+                "error": format!("{} is already set on {}", addon_type.env_var(), app_name)
+            }),
+        ));
+    }
+
+    let (addon, url) = provision_addon(&app_name, addon_type, body.persistent.unwrap_or(true))
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+    request.env.insert(addon_type.env_var().to_string(), url);
+    apply_environment(&request)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::CREATED,
+        json!({
+            "app_name": app_name,
+            "addon": addon,
+        }),
+    ))
+}
+
+/// Handles the addon removal logic.
+///
+/// Removes the addon service, unsets its connection URL from the environment of the app and
+/// redeploys the stack. The data volume of the addon is kept.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `addon_type` - The type of the addon, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_addon_remove(
+    app_name: String,
+    addon_type: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let addon_type = match AddonType::parse(&addon_type) {
+        Ok(addon_type) => addon_type,
+        Err(e) => {
+            return Ok(json_reply(
+                warp::http::StatusCode::NOT_FOUND,
+                json!({ "error": e }),
+            ))
+        }
+    };
+
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    let removed = remove_addon(&app_name, addon_type)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+    let Some(addon) = removed else {
+        return Ok(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("{} has no {} addon", app_name, addon_type.as_str()) }),
+        ));
+    };
+
+    if request.env.remove(&addon.env_var).is_some() {
+        apply_environment(&request)
+            .await
+            .map_err(|e| warp::reject::custom(CustomError(e)))?;
+    }
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "addon": addon,
+        }),
+    ))
+}
+
+/// Finds a cron job of an app, or builds the 404 reply if the app or the cron job is unknown.
+async fn find_app_cron_job(
+    app_name: &str,
+    id: &str,
+) -> Result<CronJob, warp::reply::WithStatus<warp::reply::Json>> {
+    find_app_request(app_name).await?;
+    match find_cron_job(app_name, id) {
+        Ok(Some(job)) => Ok(job),
+        Ok(None) => Err(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Cron job {} not found for app {}", id, app_name) }),
+        )),
+        Err(e) => Err(json_reply(
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": e }),
+        )),
+    }
+}
+
+/// Handles the app cron jobs listing request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_cron_jobs(app_name: String) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply);
+    }
+
+    let jobs = list_cron_jobs(Some(&app_name)).map_err(|e| warp::reject::custom(CustomError(e)))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "cron_jobs": jobs,
+        }),
+    ))
+}
+
+/// Handles the cron job creation logic.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_cron_job_create(
+    app_name: String,
+    body: CronJobRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply);
+    }
+
+    let job = create_cron_job(&app_name, body.settings())
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+    info!(
+        "⏰ Added cron job {} to {}: {} {}",
+        job.id, app_name, job.schedule, job.command
+    );
+    Ok(json_reply(
+        warp::http::StatusCode::CREATED,
+        json!({
+            "app_name": app_name,
+            "cron_job": job,
+        }),
+    ))
+}
+
+/// Handles the cron job update logic.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `id` - The ID of the cron job, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_cron_job_update(
+    app_name: String,
+    id: String,
+    body: CronJobRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(reply) = find_app_cron_job(&app_name, &id).await {
+        return Ok(reply);
+    }
+
+    let job = update_cron_job(&app_name, &id, body.settings())
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "cron_job": job,
+        }),
+    ))
+}
+
+/// Handles the cron job deletion logic.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `id` - The ID of the cron job, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_cron_job_delete(
+    app_name: String,
+    id: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(reply) = find_app_cron_job(&app_name, &id).await {
+        return Ok(reply);
+    }
+
+    delete_cron_job(&app_name, &id).map_err(|e| warp::reject::custom(CustomError(e)))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({ "message": format!("Cron job {} deleted", id) }),
+    ))
+}
+
+/// Handles the cron job runs listing request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `id` - The ID of the cron job, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_cron_job_runs(
+    app_name: String,
+    id: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let job = match find_app_cron_job(&app_name, &id).await {
+        Ok(job) => job,
+        Err(reply) => return Ok(reply),
+    };
+
+    let runs = list_cron_runs(&job.id).map_err(|e| warp::reject::custom(CustomError(e)))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "cron_job": job,
+            "runs": runs,
+        }),
+    ))
+}
+
+/// Handles the request to run a cron job now.
+///
+/// The run is started in the background; its outcome is listed with the runs of the job.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `id` - The ID of the cron job, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_cron_job_run(
+    app_name: String,
+    id: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let job = match find_app_cron_job(&app_name, &id).await {
+        Ok(job) => job,
+        Err(reply) => return Ok(reply),
+    };
+
+    match spawn_cron_run(job, CronTrigger::Manual) {
+        Ok(Some(run)) => Ok(json_reply(
+            warp::http::StatusCode::ACCEPTED,
+            json!({
+                "app_name": app_name,
+                "run": run,
+            }),
+        )),
+        Ok(None) => Ok(json_reply(
+            warp::http::StatusCode::CONFLICT,
+            json!({ "error": format!("Cron job {} is already running", id) }),
+        )),
+        Err(e) => Err(warp::reject::custom(CustomError(e))),
+    }
+}
+
+/// Handles the one-off job run request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `principal` - The authenticated caller, recorded as the initiator of the job.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_job_run(
+    app_name: String,
+    principal: Principal,
+    body: JobRequest,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply.into_response());
+    }
+
+    let events = start_job(
+        &app_name,
+        &body.command,
+        std::time::Duration::from_secs(body.timeout),
+        &principal.name,
+    )
+    .await
+    .map_err(|e| warp::reject::custom(CustomError(e)))?;
+    let body = warp::hyper::Body::wrap_stream(
+        events.map(|event| serde_json::to_string(&event).map(|line| line + "\n")),
+    );
+
+    let mut response = warp::reply::Response::new(body);
+    response.headers_mut().insert(
+        warp::http::header::CONTENT_TYPE,
+        warp::http::HeaderValue::from_static("application/x-ndjson"),
+    );
+    Ok(response)
+}
+
+/// Handles the app one-off jobs listing request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_jobs(app_name: String) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply);
+    }
+
+    let mut jobs = list_jobs(&app_name).map_err(|e| warp::reject::custom(CustomError(e)))?;
+    // Outputs are returned by the job endpoint only, as they may be large
+    for job in &mut jobs {
+        job.output = None;
+    }
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "jobs": jobs,
+        }),
+    ))
+}
+
+/// Handles the one-off job request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `id` - The ID of the job, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_job(app_name: String, id: String) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply);
+    }
+
+    match find_job(&app_name, &id).map_err(|e| warp::reject::custom(CustomError(e)))? {
+        Some(job) => Ok(json_reply(
+            warp::http::StatusCode::OK,
+            json!({
+                "app_name": app_name,
+                "job": job,
+            }),
+        )),
+        None => Ok(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Job {} not found for app {}", id, app_name) }),
+        )),
+    }
+}
+
+/// Builds the reply listing the addresses emailed about an app.
+fn notifications_reply(
+    app_name: &str,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
+    let emails = list_app_recipients(app_name).map_err(|e| warp::reject::custom(CustomError(e)))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "emails": emails,
+            "global_emails": config().smtp.recipients,
+            "enabled": config().smtp.host.is_some(),
+        }),
+    ))
+}
+
+/// Handles the app email notifications request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_notifications(app_name: String) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply);
+    }
+
+    notifications_reply(&app_name)
+}
+
+/// Handles the app email notifications update logic.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_notifications_update(
+    app_name: String,
+    body: NotificationsRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply);
+    }
+
+    set_app_recipients(&app_name, &body.emails)
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+    notifications_reply(&app_name)
+}
+
+/// Handles the sticky sessions update logic.
+///
+/// Stores the affinity settings of the app, regenerates its Traefik labels in the stack file
+/// and redeploys the stack.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_sticky_sessions(
+    app_name: String,
+    body: StickySessionsRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.routing.sticky = body.enabled.then_some(body.sticky);
+    apply_routing(&request)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "sticky": request.routing.sticky,
+        }),
+    ))
+}
+
+/// Handles the custom middlewares update logic.
+///
+/// Stores the middleware definitions for the app, regenerates its Traefik labels in the
+/// stack file and redeploys the stack.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_middlewares(
+    app_name: String,
+    body: MiddlewaresRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.routing.middlewares = body.middlewares;
+    apply_routing(&request)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "middlewares": request.routing.middlewares,
+        }),
+    ))
+}
+
+/// Handles the protocol update logic.
+///
+/// Stores the protocol of the app, regenerates its Traefik labels in the stack file and
+/// redeploys the stack.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_protocol(
+    app_name: String,
+    body: ProtocolRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.routing.protocol = AppProtocol::parse(&body.protocol).unwrap_or_default();
+    apply_routing(&request)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "protocol": request.routing.protocol,
+        }),
+    ))
+}
+
+/// Handles the additional ports update logic.
+///
+/// Stores the ports of the app, regenerates its Traefik labels in the stack file and
+/// redeploys the stack.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_ports(
+    app_name: String,
+    body: PortsRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    request.routing.ports = body.ports;
+    apply_routing(&request)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "ports": request.routing.ports,
+        }),
+    ))
+}
+
+/// Handles the maintenance mode toggle logic.
+///
+/// Makes sure the maintenance page service exists, swaps the app routers to it (or back) in
+/// the stack file and redeploys the stack.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_maintenance(
+    app_name: String,
+    body: MaintenanceRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    if body.enabled {
+        ensure_maintenance_service().map_err(|e| {
+            warp::reject::custom(CustomError(format!(
+                "Failed to add the maintenance page service: {}",
+                e
+            )))
+        })?;
+        deploy_stack_service(MAINTENANCE_SERVICE)
+            .await
+            .map_err(|e| {
+                warp::reject::custom(CustomError(format!(
+                    "Failed to deploy the maintenance page service: {}",
+                    e
+                )))
+            })?;
+    }
+
+    request.routing.maintenance = body.enabled;
+    apply_routing(&request)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "maintenance": request.routing.maintenance,
+        }),
+    ))
+}
+
+/// Handles the app update logic.
+///
+/// Stores the new build settings of the app, and starts a new deployment if requested.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `principal` - The authenticated caller.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_update(
+    app_name: String,
+    principal: Principal,
+    body: UpdateAppRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    for (setting, value) in [
+        (&mut request.install_command, body.install_command),
+        (&mut request.build_command, body.build_command),
+        (&mut request.run_command, body.run_command),
+        (&mut request.app_workdir, body.app_workdir),
+    ] {
+        if let Some(value) = value {
+            if *setting != value {
+                *setting = value;
+                request.needs_rebuild = true;
+            }
+        }
+    }
+
+    if let Some(priority) = body.priority {
+        request.priority = priority;
+    }
+
+    save_deploy_request(&request).map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    let mut response = json!({
+        "app_name": app_name,
+        "install_command": request.install_command,
+        "build_command": request.build_command,
+        "run_command": request.run_command,
+        "app_workdir": request.app_workdir,
+        "priority": request.priority,
+        "needs_rebuild": request.needs_rebuild,
+    });
+
+    if !body.redeploy {
+        return Ok(json_reply(warp::http::StatusCode::OK, response));
+    }
+    if let Err(reply) = check_quota(&principal, &AppPlan::deploy(&request)) {
+        return Ok(reply);
+    }
+
+    response["deployment_id"] = json!(spawn_deployment(request, &principal.name));
+    Ok(json_reply(warp::http::StatusCode::ACCEPTED, response))
+}
+
+/// Handles the app redeploy logic.
+///
+/// Loads the stored deploy request of the app and runs it again in the background, building
+/// a new release from the tracked repository and ref.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `principal` - The authenticated caller.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_redeploy(
+    app_name: String,
+    principal: Principal,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    if let Err(reply) = check_quota(&principal, &AppPlan::deploy(&request)) {
+        return Ok(reply);
+    }
+
+    let git_ref = request.git_ref.clone();
+    let deployment_id = spawn_deployment(request, &principal.name);
+
+    Ok(json_reply(
+        warp::http::StatusCode::ACCEPTED,
+        json!({
+            "message": "Deployment Job has been created !",
+            "deployment_id": deployment_id,
+            "git_ref": git_ref,
+        }),
+    ))
+}
+
+/// Handles the app rollback logic.
+///
+/// Resolves the release to roll back to, then re-points the app service to its image in the
+/// background.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `principal` - The authenticated caller.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_rollback(
+    app_name: String,
+    principal: Principal,
+    body: RollbackRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let target = match find_rollback_target(&app_name, body.release.as_deref()) {
+        Ok(target) => target,
+        Err(e) => {
+            return Ok(json_reply(
+                warp::http::StatusCode::NOT_FOUND,
+                json!({ "error": e }),
+            ))
+        }
+    };
+
+    let image = target.image.clone();
+    let deployment_id = spawn_rollback(&app_name, target, &principal.name);
+
+    Ok(json_reply(
+        warp::http::StatusCode::ACCEPTED,
+        json!({
+            "message": "Rollback Job has been created !",
+            "deployment_id": deployment_id,
+            "image": image,
+        }),
+    ))
+}
+
+/// Handles the app restart logic.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_restart(app_name: String) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply);
+    }
+
+    force_update_service(&app_name).await.map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to restart app {}: {}",
+            app_name, e
+        )))
+    })?;
+    publish(Event::AppRestarted {
+        app_name: app_name.clone(),
+    });
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "message": format!("Restarting app: {}.", app_name),
+            "app_name": app_name,
+        }),
+    ))
+}
+
+/// Handles the app scaling logic.
+///
+/// Updates the replicas of the app service through the Docker API and keeps the stack file
+/// in sync, so later stack deployments keep the new scale.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `principal` - The authenticated caller, whose quota bounds the reservations.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_scale(
+    app_name: String,
+    principal: Principal,
+    body: ScaleRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let request = match find_app_request(&app_name).await {
+        Ok(request) => request,
+        Err(reply) => return Ok(reply),
+    };
+
+    let plan = AppPlan {
+        builds: false,
+        replicas: body.replicas,
+        ..AppPlan::deploy(&request)
+    };
+    if let Err(reply) = check_quota(&principal, &plan) {
+        return Ok(reply);
+    }
+
+    update_app_replicas(&app_name, body.replicas).map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to update replicas for app {}: {}",
+            app_name, e
+        )))
+    })?;
+
+    scale_service(&app_name, body.replicas).await.map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to scale app {}: {}",
+            app_name, e
+        )))
+    })?;
+    publish(Event::AppScaled {
+        app_name: app_name.clone(),
+        replicas: body.replicas,
+    });
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "replicas": body.replicas,
+        }),
+    ))
+}
+
+/// Handles the app logs request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `query` - The query parameters.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_logs(
+    app_name: String,
+    query: LogsQuery,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply.into_response());
+    }
+
+    let lines = stream_service_logs(&app_name, query.tail, query.follow)
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+    let body = warp::hyper::Body::wrap_stream(lines.map(|line| line.map(|line| line + "\n")));
+
+    let mut response = warp::reply::Response::new(body);
+    response.headers_mut().insert(
+        warp::http::header::CONTENT_TYPE,
+        warp::http::HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    Ok(response)
+}
+
+/// Handles the app metrics history request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `query` - The query parameters.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_metrics(
+    app_name: String,
+    query: MetricsQuery,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let range = match query.range() {
+        Ok(range) => range,
+        Err(errors) => {
+            return Ok(json_reply(
+                warp::http::StatusCode::BAD_REQUEST,
+                json!(errors),
+            ))
+        }
+    };
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply);
+    }
+
+    let samples = app_history(&app_name, range);
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "range": query.range,
+            "samples": samples,
+        }),
+    ))
+}
+
+/// Handles the app exec request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_exec(
+    app_name: String,
+    body: ExecRequest,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    if let Err(reply) = find_app_request(&app_name).await {
+        return Ok(reply.into_response());
+    }
+
+    info!("🔧 Running {:?} in {}", body.command, app_name);
+    let events = exec_in_app(
+        &app_name,
+        body.command,
+        std::time::Duration::from_secs(body.timeout),
+    )
+    .await
+    .map_err(|e| warp::reject::custom(CustomError(e)))?;
+    let body = warp::hyper::Body::wrap_stream(
+        events.map(|event| serde_json::to_string(&event).map(|line| line + "\n")),
+    );
+
+    let mut response = warp::reply::Response::new(body);
+    response.headers_mut().insert(
+        warp::http::header::CONTENT_TYPE,
+        warp::http::HeaderValue::from_static("application/x-ndjson"),
+    );
+    Ok(response)
+}
+
+/// Handles the app removal logic.
+///
+/// Takes `app_name` from the request body and soft-deletes the app, or removes it with its
+/// addons, compose services, cron jobs and secrets, see `delete_app`.
+///
+/// # Arguments
+///
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_remove_app(body: RemoveAppRequest) -> Result<impl warp::Reply, warp::Rejection> {
+    let app_name = body.app_name.as_str();
+
+    if body.soft {
+        return handle_soft_delete(app_name).await.map(Reply::into_response);
+    }
+
+    delete_app(app_name)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(warp::reply::with_status(
+        format!("Remove app: {}.", app_name),
+        warp::http::StatusCode::CREATED,
+    )
+    .into_response())
+}
+
+/// Runs a bulk action on a single app.
+///
+/// `start` and `stop` only update the stack file, which the caller deploys once for every app.
+///
+/// # Arguments
+///
+/// * `action` - `stop`, `start`, `remove` or `redeploy`.
+/// * `app_name` - The name of the application.
+/// * `principal` - The authenticated caller.
+///
+/// # Returns
+///
+/// * `Ok(Value)` with extra result fields (e.g., the `deployment_id` of a redeploy).
+/// * `Err(String)` if the action failed on this app.
+async fn run_bulk_action(
+    action: &str,
+    app_name: &str,
+    principal: &Principal,
+) -> Result<Value, String> {
+    if action != "remove" && load_deleted_app(app_name).is_some() {
+        return Err(format!(
+            "Application {} is deleted, restore it first",
+            app_name
+        ));
+    }
+
+    match action {
+        "start" | "stop" => {
+            let replicas = if action == "start" { 1 } else { 0 };
+            if action == "start" {
+                let request = load_app_request(app_name).await?;
+                let plan = AppPlan {
+                    builds: false,
+                    replicas,
+                    ..AppPlan::deploy(&request)
+                };
+                enforce_quota(Some(principal), &plan).map_err(|e| match e {
+                    QuotaError::Exceeded(e) | QuotaError::Failed(e) => e,
+                })?;
+            }
+            update_app_replicas(app_name, replicas)
+                .map_err(|e| format!("Failed to update replicas for app {}: {}", app_name, e))?;
+            Ok(json!({}))
+        }
+        "remove" => {
+            delete_app(app_name).await?;
+            Ok(json!({}))
+        }
+        _ => {
+            let request = load_app_request(app_name).await?;
+            enforce_quota(Some(principal), &AppPlan::deploy(&request)).map_err(|e| match e {
+                QuotaError::Exceeded(e) | QuotaError::Failed(e) => e,
+            })?;
+            let deployment_id = spawn_deployment(request, &principal.name);
+            Ok(json!({ "deployment_id": deployment_id }))
+        }
+    }
+}
+
+/// Handles the bulk app operations.
+///
+/// Runs the action concurrently on every app. For `start` and `stop`, the services of the
+/// updated apps are deployed once every app was updated.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_bulk(
+    principal: Principal,
+    body: BulkRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if body.action == "remove" && principal.role < Role::Admin {
+        return Err(warp::reject::custom(Forbidden));
+    }
+
+    let action = body.action.as_str();
+    let mut results = futures::future::join_all(
+        body.app_names
+            .iter()
+            .map(|app_name| run_bulk_action(action, app_name, &principal)),
+    )
+    .await;
+
+    if matches!(action, "start" | "stop") {
+        for (app_name, result) in body.app_names.iter().zip(results.iter_mut()) {
+            if result.is_err() {
+                continue;
+            }
+            match deploy_app_services(app_name).await {
+                Ok(()) => {
+                    let app_name = app_name.clone();
+                    publish(if action == "start" {
+                        Event::AppStarted { app_name }
+                    } else {
+                        Event::AppStopped { app_name }
+                    });
+                }
+                Err(e) => *result = Err(format!("Failed to deploy app: {}", e)),
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|result| result.is_err()).count();
+    let results = body
+        .app_names
+        .iter()
+        .zip(results)
+        .map(|(app_name, result)| {
+            let mut entry = json!({ "app_name": app_name, "success": result.is_ok() });
+            match result {
+                Ok(Value::Object(fields)) => {
+                    for (key, value) in fields {
+                        entry[key] = value;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => entry["error"] = json!(e),
+            }
+            entry
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "action": action,
+            "succeeded": results.len() - failed,
+            "failed": failed,
+            "results": results,
+        }),
+    ))
+}
+
+/// Handles the soft deletion of an app.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_soft_delete(app_name: &str) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(reply) = find_app_request(app_name).await {
+        return Ok(reply);
+    }
+    if load_deleted_app(app_name).is_some() {
+        return Ok(json_reply(
+            warp::http::StatusCode::CONFLICT,
+            json!({ "error": format!("Application {} is already deleted", app_name) }),
+        ));
+    }
+
+    let deleted = soft_delete_app(app_name)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "message": format!("App {} deleted, it can be restored until {}", app_name, deleted.purge_at),
+            "app_name": app_name,
+            "deleted_at": deleted.deleted_at,
+            "purge_at": deleted.purge_at,
+        }),
+    ))
+}
+
+/// Handles the restore of a soft-deleted app.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_restore(app_name: String) -> Result<impl warp::Reply, warp::Rejection> {
+    let Some(deleted) = load_deleted_app(&app_name) else {
+        return Ok(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Application {} is not deleted", app_name) }),
+        ));
+    };
+
+    let deleted = restore_app(deleted)
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "message": format!("App {} restored", app_name),
+            "app_name": app_name,
+            "replicas": deleted.replicas,
+        }),
+    ))
+}
+
+/// Creates the route for listing the firing alerts.
+///
+/// This route listens for GET requests at the `/alerts` path. It returns the alerts whose
+/// rule condition currently holds for longer than the `for` duration of the rule.
+///
+/// Returns a boxed Warp filter that handles alert listing requests.
+pub fn alerts_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path!("alerts"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_alerts)
+        .boxed()
+}
+
+/// Creates the route for reading the audit log.
+///
+/// This route listens for GET requests at the `/audit` path and accepts the following query
+/// parameters, all optional:
+/// - `actor`: Only entries of this caller (e.g., `api-key:1a2b3c4d` or `unverified:<subject>`
+///   of an OIDC token).
+/// - `app_name`: Only entries targeting this app.
+/// - `method`: Only entries with this HTTP method.
+/// - `since`, `until`: Only entries recorded within these RFC 3339 dates.
+/// - `limit`: The maximum number of entries (default: 100, at most 1000).
+///
+/// Every request that could change something (any method but GET, HEAD and OPTIONS) is
+/// recorded, with its caller and response status, most recent first.
+///
+/// Returns a boxed Warp filter that handles audit log requests.
+pub fn audit_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path!("audit"))
+        .and(require_role(Role::Admin))
+        .and(warp::query::<AuditQuery>())
+        .and_then(handle_audit)
+        .boxed()
+}
+
+/// Creates the route for downloading a backup of this node.
+///
+/// This route listens for GET requests at the `/backup` path and returns a tar archive of
+/// the app registry, the stack file (`nephelios.yml`), the settings and deployment history
+/// of each app and the alert rules. Docker secrets are only listed by name.
+///
+/// Returns a boxed Warp filter that handles backup requests.
+pub fn backup_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path!("backup"))
+        .and(require_role(Role::Admin))
+        .and_then(handle_backup)
+        .boxed()
+}
+
+/// Creates the route for restoring a backup on this node.
+///
+/// This route listens for POST requests at the `/restore` path and expects a tar archive
+/// downloaded from `/backup` as the body (at most 64 MiB). The stored state is overwritten
+/// and the stack deployed, unless secrets it refers to are missing on this node.
+///
+/// Returns a boxed Warp filter that handles restore requests.
+pub fn restore_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("restore"))
+        .and(require_role(Role::Admin))
+        .and(warp::body::content_length_limit(MAX_BACKUP_SIZE))
+        .and(warp::body::bytes())
+        .and_then(handle_restore)
+        .boxed()
+}
+
+/// Creates the route for listing the nodes of the Swarm.
+///
+/// This route listens for GET requests at the `/nodes` path and returns every node with its
+/// role, availability, state, labels, capacity and the resources reserved by its tasks.
+///
+/// Returns a boxed Warp filter that handles node listing requests.
+pub fn nodes_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path!("nodes"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_nodes)
+        .boxed()
+}
+
+/// Creates the route for the reconciliation of the stack file, the app registry and the Swarm
+/// services.
+///
+/// This route listens for requests at the `/system/reconcile` path:
+/// - GET returns the report of the last reconciliation, run at startup.
+/// - POST runs a reconciliation and returns its report: services missing from the Swarm are
+///   created again from the stack file, orphaned services and registered apps are flagged.
+///
+/// Returns a boxed Warp filter that handles reconciliation requests.
+pub fn system_reconcile_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let report = warp::get()
+        .and(warp::path!("system" / "reconcile"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_reconcile_report);
+    let run = warp::post()
+        .and(warp::path!("system" / "reconcile"))
+        .and(require_role(Role::Admin))
+        .and_then(handle_reconcile);
+
+    report.or(run).boxed()
+}
+
+/// Creates the route for the collection of orphaned resources.
+///
+/// This route listens for requests at the `/system/gc` path:
+/// - GET returns the report of the last collection.
+/// - POST runs a collection and returns its report: Swarm services missing from the stack
+///   file, images of removed apps, stale clones and build archives, and unused volumes and
+///   networks of the stack are removed. With the `dry_run=true` query parameter, they are
+///   only listed.
+///
+/// Addon data volumes and the volumes of apps that are registered or soft-deleted are kept.
+/// Scheduled collections leave the orphaned services alone, only this route removes them.
+///
+/// Returns a boxed Warp filter that handles garbage collection requests.
+pub fn system_gc_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let report = warp::get()
+        .and(warp::path!("system" / "gc"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_gc_report);
+    let run = warp::post()
+        .and(warp::path!("system" / "gc"))
+        .and(require_role(Role::Admin))
+        .and(warp::query::<GcQuery>())
+        .and_then(handle_gc);
+
+    report.or(run).boxed()
+}
+
+/// Creates the route for reading the token nodes join the Swarm with.
+///
+/// This route listens for GET requests at the `/nodes/join-token` path and accepts the
+/// `role` query parameter: `worker` (default) or `manager`. The response holds the token,
+/// the address of the leader and the `docker swarm join` command to run on the new node.
+///
+/// Returns a boxed Warp filter that handles join token requests.
+pub fn node_join_token_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path!("nodes" / "join-token"))
+        .and(require_role(Role::Admin))
+        .and(warp::query::<JoinTokenQuery>())
+        .and_then(handle_node_join_token)
+        .boxed()
+}
+
+/// Creates the route for draining a node of the Swarm.
+///
+/// This route listens for POST requests at the `/nodes/{id}/drain` path, `{id}` being the ID
+/// or hostname of the node. Swarm stops the tasks of the node and schedules them on the other
+/// nodes; the progress is pushed over `/ws/nodes` until no task is left.
+///
+/// Returns a boxed Warp filter that handles node drain requests.
+pub fn node_drain_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("nodes" / String / "drain"))
+        .and(require_role(Role::Admin))
+        .and_then(|node: String| handle_node_availability(node, "drain"))
+        .boxed()
+}
+
+/// Creates the route for making a drained node available again.
+///
+/// This route listens for POST requests at the `/nodes/{id}/activate` path, `{id}` being the
+/// ID or hostname of the node. Running tasks are not moved back, new tasks may be scheduled
+/// on the node.
+///
+/// Returns a boxed Warp filter that handles node activation requests.
+pub fn node_activate_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("nodes" / String / "activate"))
+        .and(require_role(Role::Admin))
+        .and_then(|node: String| handle_node_availability(node, "active"))
+        .boxed()
+}
+
+/// Creates the route for managing the labels of a node of the Swarm.
+///
+/// This route listens for requests at the `/nodes/{id}/labels` path, `{id}` being the ID or
+/// hostname of the node:
+/// - PUT sets labels and expects a JSON body with a `labels` object of names to values.
+///   Labels that are not listed are kept.
+/// - DELETE removes labels and expects a JSON body with a `keys` list of names.
+///
+/// Apps are pinned to or spread across labeled nodes with their placement, e.g.
+/// `node.labels.disk==ssd` or a `node.labels.zone` preference.
+///
+/// Returns a boxed Warp filter that handles node labels requests.
+pub fn node_labels_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let set = warp::put()
+        .and(warp::path!("nodes" / String / "labels"))
+        .and(require_role(Role::Admin))
+        .and(json_body::<NodeLabelsRequest>())
+        .and_then(handle_node_labels_set);
+    let unset = warp::delete()
+        .and(warp::path!("nodes" / String / "labels"))
+        .and(require_role(Role::Admin))
+        .and(json_body::<NodeLabelKeysRequest>())
+        .and_then(handle_node_labels_unset);
+
+    set.or(unset).boxed()
+}
+
+/// Creates the route for managing the alert rules.
+///
+/// This route listens for requests at the `/alerts/rules` path:
+/// - GET lists the rules.
+/// - POST adds a rule and expects a JSON body with the following keys:
+///   - `name`: The name of the rule (required).
+///   - `app_name`: The app the rule applies to (optional, every app if omitted).
+///   - `condition`: `{"type": "cpu_above", "percent": 90}`, `{"type": "memory_above", "mb": 512}`
+///     or `{"type": "app_down"}` (required).
+///   - `for`: How long the condition must hold before the alert fires, e.g. `5m` (optional).
+///   - `webhooks`: The URLs notified with a JSON POST when the alert fires and resolves (required).
+/// - DELETE `/alerts/rules/{id}` removes a rule.
+///
+/// Returns a boxed Warp filter that handles alert rule requests.
+pub fn alert_rules_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let list = warp::get()
+        .and(warp::path!("alerts" / "rules"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_alert_rules);
+    let add = warp::post()
+        .and(warp::path!("alerts" / "rules"))
+        .and(require_role(Role::Admin))
+        .and(json_body::<AlertRuleRequest>())
+        .and_then(handle_alert_rule_add);
+    let delete = warp::delete()
+        .and(warp::path!("alerts" / "rules" / String))
+        .and(require_role(Role::Admin))
+        .and_then(handle_alert_rule_delete);
+
+    list.or(add).or(delete).boxed()
+}
+
+/// Creates the route for the global webhooks, receiving the events of every app.
+///
+/// This route listens for requests at the `/webhooks` path, like `/apps/{name}/webhooks`:
+/// - GET lists the global webhooks, without their secret.
+/// - POST registers a webhook and expects a JSON body with the `url`, and optionally the
+///   `secret` and the `events` delivered.
+/// - DELETE `/webhooks/{id}` removes a webhook.
+///
+/// Returns a boxed Warp filter that handles global webhook requests.
+pub fn webhooks_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let list = warp::get()
+        .and(warp::path!("webhooks").map(|| None))
+        .and(require_role(Role::Admin))
+        .and_then(handle_webhooks);
+    let create = warp::post()
+        .and(warp::path!("webhooks").map(|| None))
+        .and(require_role(Role::Admin))
+        .and(json_body::<WebhookRequest>())
+        .and_then(handle_webhook_create);
+    let delete = warp::delete()
+        .and(warp::path!("webhooks" / String).map(|id| (None, id)))
+        .untuple_one()
+        .and(require_role(Role::Admin))
+        .and_then(handle_webhook_delete);
+
+    list.or(create).or(delete).boxed()
+}
+
+/// Handles the firing alerts request.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_alerts() -> Result<impl warp::Reply, warp::Rejection> {
+    let alerts = active_alerts();
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "alerts": alerts,
+            "total": alerts.len(),
+        }),
+    ))
+}
+
+/// Handles the audit log request.
+///
+/// # Arguments
+///
+/// * `query` - The query parameters.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_audit(query: AuditQuery) -> Result<impl warp::Reply, warp::Rejection> {
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(errors) => {
+            return Ok(json_reply(
+                warp::http::StatusCode::BAD_REQUEST,
+                json!(errors),
+            ))
+        }
+    };
+
+    let entries = query_audit_log(&filter).map_err(|e| warp::reject::custom(CustomError(e)))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "entries": entries,
+            "total": entries.len(),
+        }),
+    ))
+}
+
+/// Handles the backup request.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_backup() -> Result<impl warp::Reply, warp::Rejection> {
+    let archive = create_backup().map_err(|e| warp::reject::custom(CustomError(e)))?;
+    let file_name = format!(
+        "nephelios-backup-{}.tar",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S")
+    );
+
+    let mut response = warp::reply::Response::new(archive.into());
+    let headers = response.headers_mut();
+    headers.insert(
+        warp::http::header::CONTENT_TYPE,
+        warp::http::HeaderValue::from_static("application/x-tar"),
+    );
+    if let Ok(value) =
+        warp::http::HeaderValue::from_str(&format!("attachment; filename=\"{}\"", file_name))
+    {
+        headers.insert(warp::http::header::CONTENT_DISPOSITION, value);
+    }
+    Ok(response)
+}
+
+/// Handles the restore request.
+///
+/// # Arguments
+///
+/// * `body` - The backup archive.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_restore(body: bytes::Bytes) -> Result<impl warp::Reply, warp::Rejection> {
+    let result = tokio::task::spawn_blocking(move || restore_backup(&body))
+        .await
+        .map_err(|e| format!("Restore task failed: {}", e))
+        .and_then(|result| result);
+
+    match result {
+        Ok(report) => {
+            info!("✅ Backup restored: {} app(s)", report.apps.len());
+            Ok(json_reply(
+                warp::http::StatusCode::OK,
+                json!({
+                    "message": "Backup restored successfully",
+                    "restored": report,
+                }),
+            ))
+        }
+        Err(e) => {
+            error!("❌ Failed to restore backup: {}", e);
+            Ok(json_reply(
+                warp::http::StatusCode::BAD_REQUEST,
+                json!({ "error": e }),
+            ))
+        }
+    }
+}
+
+/// Handles the Swarm nodes listing request.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_nodes() -> Result<impl warp::Reply, warp::Rejection> {
+    let nodes = list_swarm_nodes()
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(format!("Failed to list nodes: {}", e))))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "nodes": nodes,
+            "total": nodes.len(),
+        }),
+    ))
+}
+
+/// Handles the request for the last reconciliation report.
+///
+/// # Returns
+///
+/// A result containing a Warp reply with the report, or a 404 if no reconciliation ran yet.
+async fn handle_reconcile_report() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(match last_reconcile_report() {
+        Some(report) => json_reply(warp::http::StatusCode::OK, json!(report)),
+        None => json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": "No reconciliation ran yet" }),
+        ),
+    })
+}
+
+/// Handles the reconciliation request.
+///
+/// # Returns
+///
+/// A result containing a Warp reply with the report, or a Warp rejection.
+async fn handle_reconcile() -> Result<impl warp::Reply, warp::Rejection> {
+    let report = reconcile()
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(format!("Failed to reconcile: {}", e))))?;
+    Ok(json_reply(warp::http::StatusCode::OK, json!(report)))
+}
+
+/// Handles the request for the last garbage collection report.
+///
+/// # Returns
+///
+/// A result containing a Warp reply with the report, or a 404 if no collection ran yet.
+async fn handle_gc_report() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(match last_gc_report() {
+        Some(report) => json_reply(warp::http::StatusCode::OK, json!(report)),
+        None => json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": "No garbage collection ran yet" }),
+        ),
+    })
+}
+
+/// Handles the garbage collection request.
+///
+/// # Arguments
+///
+/// * `query` - Whether to only list the orphaned resources.
+///
+/// # Returns
+///
+/// A result containing a Warp reply with the report, or a Warp rejection.
+async fn handle_gc(query: GcQuery) -> Result<impl warp::Reply, warp::Rejection> {
+    let report = collect_garbage(query.dry_run, true).await.map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to collect orphaned resources: {}",
+            e
+        )))
+    })?;
+    Ok(json_reply(warp::http::StatusCode::OK, json!(report)))
+}
+
+/// Handles the node drain and activation requests.
+///
+/// Refuses to drain the last active node, whose tasks could not be scheduled anywhere.
+///
+/// # Arguments
+///
+/// * `node` - The ID or hostname of the node, taken from the path.
+/// * `availability` - `drain` or `active`.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_node_availability(
+    node: String,
+    availability: &'static str,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let nodes = list_swarm_nodes()
+        .await
+        .map_err(|e| warp::reject::custom(CustomError(format!("Failed to list nodes: {}", e))))?;
+    let Some(target) = nodes
+        .iter()
+        .find(|candidate| candidate.id == node || candidate.hostname == node)
+    else {
+        return Ok(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Node {} not found", node) }),
+        ));
+    };
+
+    if availability == "drain"
+        && target.availability == "active"
+        && !nodes
+            .iter()
+            .any(|other| other.id != target.id && other.availability == "active")
+    {
+        return Ok(json_reply(
+            warp::http::StatusCode::CONFLICT,
+            json!({ "error": format!("Node {} is the last active node", target.hostname) }),
+        ));
+    }
+
+    let updated = change_node_availability(target, availability)
+        .await
+        .map_err(|e| {
+            warp::reject::custom(CustomError(format!(
+                "Failed to update node {}: {}",
+                target.hostname, e
+            )))
+        })?;
+    Ok(json_reply(warp::http::StatusCode::OK, json!(updated)))
+}
+
+/// Finds a node of the Swarm by ID or hostname, or builds the error reply if it is unknown.
+async fn find_node(node: &str) -> Result<NodeInfo, warp::reply::WithStatus<warp::reply::Json>> {
+    match find_swarm_node(node).await {
+        Ok(Some(found)) => Ok(found),
+        Ok(None) => Err(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Node {} not found", node) }),
+        )),
+        Err(e) => Err(json_reply(
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": format!("Failed to list nodes: {}", e) }),
+        )),
+    }
+}
+
+/// Handles the node label update request.
+///
+/// # Arguments
+///
+/// * `node` - The ID or hostname of the node, taken from the path.
+/// * `body` - The validated request body, containing the labels to set.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_node_labels_set(
+    node: String,
+    body: NodeLabelsRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let target = match find_node(&node).await {
+        Ok(target) => target,
+        Err(reply) => return Ok(reply),
+    };
+
+    let updated = change_node_labels(&target, &body.labels, &[])
+        .await
+        .map_err(|e| {
+            warp::reject::custom(CustomError(format!(
+                "Failed to update the labels of node {}: {}",
+                target.hostname, e
+            )))
+        })?;
+    Ok(json_reply(warp::http::StatusCode::OK, json!(updated)))
+}
+
+/// Handles the node label removal request.
+///
+/// # Arguments
+///
+/// * `node` - The ID or hostname of the node, taken from the path.
+/// * `body` - The validated request body, containing the names of the labels to remove.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_node_labels_unset(
+    node: String,
+    body: NodeLabelKeysRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let target = match find_node(&node).await {
+        Ok(target) => target,
+        Err(reply) => return Ok(reply),
+    };
+
+    let missing: Vec<&String> = body
+        .keys
+        .iter()
+        .filter(|key| !target.labels.contains_key(*key))
+        .collect();
+    if !missing.is_empty() {
+        return Ok(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Labels not set on {}: {:?}", target.hostname, missing) }),
+        ));
+    }
+
+    let updated = change_node_labels(&target, &BTreeMap::new(), &body.keys)
+        .await
+        .map_err(|e| {
+            warp::reject::custom(CustomError(format!(
+                "Failed to update the labels of node {}: {}",
+                target.hostname, e
+            )))
+        })?;
+    Ok(json_reply(warp::http::StatusCode::OK, json!(updated)))
+}
+
+/// Handles the Swarm join token request.
+///
+/// # Arguments
+///
+/// * `query` - The query parameters.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_node_join_token(
+    query: JoinTokenQuery,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let role = query.role.as_str();
+    let (token, manager_address) = swarm_join_token(role).await.map_err(|e| {
+        warp::reject::custom(CustomError(format!(
+            "Failed to read the {} join token: {}",
+            role, e
+        )))
+    })?;
+
+    let command = manager_address
+        .as_ref()
+        .map(|address| format!("docker swarm join --token {} {}", token, address));
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "role": role,
+            "token": token,
+            "manager_address": manager_address,
+            "command": command,
+        }),
+    ))
+}
+
+/// Handles the alert rules listing request.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_alert_rules() -> Result<impl warp::Reply, warp::Rejection> {
+    let rules = load_alert_rules().map_err(|e| warp::reject::custom(CustomError(e)))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "rules": rules,
+            "total": rules.len(),
+        }),
+    ))
+}
+
+/// Handles the alert rule creation request.
+///
+/// # Arguments
+///
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_alert_rule_add(
+    body: AlertRuleRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let for_duration = body.for_duration();
+    let rule = add_alert_rule(
+        body.name,
+        body.app_name,
+        body.condition,
+        for_duration,
+        body.webhooks,
+    )
+    .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    info!("🔔 Alert rule {} added", rule.name);
+    Ok(json_reply(warp::http::StatusCode::CREATED, json!(rule)))
+}
+
+/// Handles the alert rule deletion request.
+///
+/// # Arguments
+///
+/// * `id` - The ID of the rule, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_alert_rule_delete(id: String) -> Result<impl warp::Reply, warp::Rejection> {
+    let deleted = delete_alert_rule(&id).map_err(|e| warp::reject::custom(CustomError(e)))?;
+    if !deleted {
+        return Ok(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Alert rule {} not found", id) }),
+        ));
+    }
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({ "message": format!("Alert rule {} deleted", id) }),
+    ))
+}
+
+/// Handles the webhooks listing request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path, `None` for the global
+///   webhooks.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_webhooks(app_name: Option<String>) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Some(app_name) = &app_name {
+        if let Err(reply) = find_app_request(app_name).await {
+            return Ok(reply);
+        }
+    }
+
+    let webhooks =
+        list_webhooks(app_name.as_deref()).map_err(|e| warp::reject::custom(CustomError(e)))?;
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "webhooks": webhooks,
+            "total": webhooks.len(),
+        }),
+    ))
+}
+
+/// Handles the webhook registration request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path, `None` for a global
+///   webhook.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_webhook_create(
+    app_name: Option<String>,
+    body: WebhookRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Some(app_name) = &app_name {
+        if let Err(reply) = find_app_request(app_name).await {
+            return Ok(reply);
+        }
+    }
+
+    let webhook = create_webhook(app_name.as_deref(), body.url, body.secret, body.events)
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    info!("🪝 Webhook {} registered", webhook.url);
+    Ok(json_reply(
+        warp::http::StatusCode::CREATED,
+        json!({
+            "webhook": webhook,
+            "secret": webhook.secret,
+        }),
+    ))
+}
+
+/// Handles the webhook deletion request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path, `None` for a global
+///   webhook.
+/// * `id` - The ID of the webhook, taken from the path.
 ///
-/// Extracts `app_name` from the JSON body and performs the necessary steps to remove the app:
-/// stopping the running container, removing the container, and deleting the associated compose file.
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_webhook_delete(
+    app_name: Option<String>,
+    id: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let deleted = delete_webhook(app_name.as_deref(), &id)
+        .map_err(|e| warp::reject::custom(CustomError(e)))?;
+    if !deleted {
+        return Ok(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Webhook {} not found", id) }),
+        ));
+    }
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({ "message": format!("Webhook {} deleted", id) }),
+    ))
+}
+
+/// Creates the route for querying a deployment.
+///
+/// This route listens for GET requests at the `/deployments/{id}` path, where `id` is the
+/// deployment ID returned by `/create`. It returns the current stage, timestamps and outcome
+/// of the deployment.
+///
+/// Returns a boxed Warp filter that handles deployment status requests.
+pub fn deployment_status_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path!("deployments" / String))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_deployment_status)
+        .boxed()
+}
+
+/// Handles the deployment status request.
 ///
 /// # Arguments
 ///
-/// * `body` - The JSON body received in the request, expected to contain `app_name`.
+/// * `deployment_id` - The deployment ID, taken from the path.
 ///
 /// # Returns
 ///
 /// A result containing a Warp reply or a Warp rejection.
-async fn handle_remove_app(body: Value) -> Result<impl warp::Reply, warp::Rejection> {
-    let app_name = body
-        .get("app_name")
-        .and_then(Value::as_str)
-        .unwrap_or("default-app");
+async fn handle_deployment_status(
+    deployment_id: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_deployment(&deployment_id) {
+        Some(deployment) => Ok(json_reply(warp::http::StatusCode::OK, json!(deployment))),
+        None => Ok(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Deployment {} not found", deployment_id) }),
+        )),
+    }
+}
 
-    remove_service(app_name).await.map_err(|e| {
-        warp::reject::custom(CustomError(format!(
-            "Failed to remove container for app {}: {}",
-            app_name, e
-        )))
-    })?;
+/// Creates the route for listing the deployment queue.
+///
+/// This route listens for GET requests at the `/deployment-queue` path and returns the queued
+/// deployments of every app, in the order they start: higher priorities first, then oldest
+/// first.
+///
+/// Returns a boxed Warp filter that handles deployment queue requests.
+pub fn deployment_queue_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path!("deployment-queue"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_deployment_queue)
+        .boxed()
+}
 
-    remove_app_compose(app_name).map_err(|e| {
-        warp::reject::custom(CustomError(format!(
-            "Failed to remove app compose file for app {}: {}",
-            app_name, e
-        )))
-    })?;
+/// Handles the deployment queue request.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_deployment_queue() -> Result<impl warp::Reply, warp::Rejection> {
+    let deployments = list_queued_deployments();
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "deployments": deployments,
+            "total": deployments.len(),
+            "max_concurrent_deployments": config().features.max_concurrent_deployments,
+        }),
+    ))
+}
 
-    Ok(warp::reply::with_status(
-        format!("Remove app: {}.", app_name),
-        warp::http::StatusCode::CREATED,
+/// Answers a change to a queued deployment.
+///
+/// # Arguments
+///
+/// * `deployment_id` - The deployment ID.
+/// * `result` - The outcome of the change.
+/// * `message` - What the change did, on success.
+fn queue_change_reply(
+    deployment_id: &str,
+    result: Result<Deployment, QueueError>,
+    message: &str,
+) -> warp::reply::WithStatus<warp::reply::Json> {
+    match result {
+        Ok(deployment) => json_reply(
+            warp::http::StatusCode::OK,
+            json!({ "message": message, "deployment": deployment }),
+        ),
+        Err(QueueError::NotFound) => json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Deployment {} not found", deployment_id) }),
+        ),
+        Err(QueueError::NotQueued(state)) => json_reply(
+            warp::http::StatusCode::CONFLICT,
+            json!({
+                "error": format!(
+                    "Deployment {} is {}, only queued deployments can be changed",
+                    deployment_id,
+                    state.as_str()
+                )
+            }),
+        ),
+    }
+}
+
+/// Creates the route for reordering the deployment queue.
+///
+/// This route listens for PATCH requests at the `/deployments/{id}` path, with the new
+/// `priority` of a queued deployment (`low`, `normal` or `high`) in the body.
+///
+/// Returns a boxed Warp filter that handles deployment priority requests.
+pub fn deployment_priority_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::patch()
+        .and(warp::path!("deployments" / String))
+        .and(require_role(Role::Deployer))
+        .and(json_body::<DeploymentPriorityRequest>())
+        .and_then(handle_deployment_priority)
+        .boxed()
+}
+
+/// Handles the deployment priority request.
+///
+/// # Arguments
+///
+/// * `deployment_id` - The deployment ID, taken from the path.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_deployment_priority(
+    deployment_id: String,
+    body: DeploymentPriorityRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let result = set_deployment_priority(&deployment_id, body.priority);
+    Ok(queue_change_reply(
+        &deployment_id,
+        result,
+        "Deployment priority updated",
+    ))
+}
+
+/// Creates the route for cancelling a queued deployment.
+///
+/// This route listens for POST requests at the `/deployments/{id}/cancel` path. Only queued
+/// deployments can be cancelled, running ones are left to finish.
+///
+/// Returns a boxed Warp filter that handles deployment cancel requests.
+pub fn deployment_cancel_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path!("deployments" / String / "cancel"))
+        .and(require_role(Role::Deployer))
+        .and_then(handle_deployment_cancel)
+        .boxed()
+}
+
+/// Handles the deployment cancel request.
+///
+/// # Arguments
+///
+/// * `deployment_id` - The deployment ID, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_deployment_cancel(
+    deployment_id: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let result = cancel_deployment(&deployment_id);
+    Ok(queue_change_reply(
+        &deployment_id,
+        result,
+        "Deployment cancelled",
+    ))
+}
+
+/// Creates the route for the deployment history of an app.
+///
+/// This route listens for GET requests at the `/apps/{app_name}/deployments` path and returns
+/// the running and past deployments of the app, most recent first, with who triggered them,
+/// when, the deployed commit and image, the outcome and the duration.
+///
+/// Returns a boxed Warp filter that handles deployment history requests.
+pub fn app_deployments_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path!("apps" / String / "deployments"))
+        .and(require_role(Role::Viewer))
+        .and_then(handle_app_deployments)
+        .boxed()
+}
+
+/// Handles the deployment history request.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application, taken from the path.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_app_deployments(app_name: String) -> Result<impl warp::Reply, warp::Rejection> {
+    let deployments =
+        list_app_deployments(&app_name).map_err(|e| warp::reject::custom(CustomError(e)))?;
+
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "app_name": app_name,
+            "deployments": deployments,
+            "total": deployments.len(),
+        }),
     ))
 }
 
@@ -265,6 +4610,7 @@ async fn handle_remove_app(body: Value) -> Result<impl warp::Reply, warp::Reject
 pub fn get_apps_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
     warp::get()
         .and(warp::path("get-apps"))
+        .and(require_role(Role::Viewer))
         .and_then(handle_get_apps)
         .boxed()
 }
@@ -281,7 +4627,7 @@ pub fn get_apps_route() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
 ///
 /// This function returns a Warp rejection if the app listing fails.
 pub async fn handle_get_apps() -> Result<impl warp::Reply, warp::Rejection> {
-    match list_deployed_apps().await {
+    match list_registered_apps() {
         Ok(apps) => {
             let response = json!({
                 "status": "success",
@@ -319,307 +4665,275 @@ pub async fn handle_get_apps() -> Result<impl warp::Reply, warp::Rejection> {
 ///
 /// A result containing a Warp reply or a Warp rejection.
 async fn handle_create_app(
-    body: Value,
-    status_tx: StatusSender,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    tokio::spawn(async move {
-        let app_name = body
-            .get("app_name")
-            .and_then(Value::as_str)
-            .unwrap_or("default-app");
-        let app_type = body
-            .get("app_type")
-            .and_then(Value::as_str)
-            .unwrap_or("nodejs");
-        let github_url = body.get("github_url").and_then(Value::as_str);
-
-        let install_command = body
-            .get("install_command")
-            .and_then(Value::as_str)
-            .unwrap_or("");
-        let run_command = body
-            .get("run_command")
-            .and_then(Value::as_str)
-            .unwrap_or("");
-        let build_command = body
-            .get("build_command")
-            .and_then(Value::as_str)
-            .unwrap_or("");
-        let app_workdir = body
-            .get("app_workdir")
-            .and_then(Value::as_str)
-            .unwrap_or("/app");
-        let additional_inputs = body
-            .get("additionalInputs")
-            .and_then(Value::as_array)
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|item| {
-                        let key = item.get("key").and_then(Value::as_str)?;
-                        let value = item.get("value").and_then(Value::as_str)?;
-                        Some((key.to_string(), value.to_string()))
-                    })
-                    .collect::<HashMap<String, String>>()
-            })
-            .unwrap_or_default();
-
-        if github_url.is_none() || github_url.unwrap().is_empty() {
-            send_deployment_status(
-                &status_tx,
-                app_name,
-                "error",
-                "GitHub URL is required",
-                None,
-            )
-            .await;
-            return Ok(warp::reply::with_status(
-                warp::reply::json(&json!({
-                    "error": "GitHub URL is required"
-                })),
-                warp::http::StatusCode::BAD_REQUEST,
-            ));
-        }
+    principal: Principal,
+    body: CreateAppRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if load_deleted_app(&body.app_name).is_some() {
+        return Ok(json_reply(
+            warp::http::StatusCode::CONFLICT,
+            json!({
+                "error": format!("Application {} is deleted, restore it before deploying", body.app_name),
+            }),
+        ));
+    }
+
+    if let Err(e) = check_app_name_available(&body.app_name, &body.github_url).await {
+        let mut errors = ValidationErrors::default();
+        errors.add("app_name", e);
+        return Ok(json_reply(
+            warp::http::StatusCode::UNPROCESSABLE_ENTITY,
+            json!(errors),
+        ));
+    }
+
+    // Settings managed through their own endpoints are kept across redeployments
+    let previous = load_deploy_request(&body.app_name);
+    let request = body.into_deploy_request(previous);
+    if let Err(reply) = check_quota(&principal, &AppPlan::deploy(&request)) {
+        return Ok(reply);
+    }
+    let deployment_id = spawn_deployment(request, &principal.name);
+
+    Ok(json_reply(
+        warp::http::StatusCode::CREATED,
+        json!({
+            "message": "Deployment Job has been created !",
+            "deployment_id": deployment_id,
+        }),
+    ))
+}
+
+/// Handles the template listing request.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_templates() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({ "templates": TEMPLATES }),
+    ))
+}
 
-        let github_url = github_url.unwrap();
+/// Handles the deployment of an app from a template.
+///
+/// # Arguments
+///
+/// * `id` - The ID of the template, taken from the path.
+/// * `principal` - The authenticated caller, recorded as the initiator of the deployment.
+/// * `body` - The validated request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_template_deploy(
+    id: String,
+    principal: Principal,
+    body: TemplateDeployRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let Some(template) = find_template(&id) else {
+        return Ok(json_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            json!({ "error": format!("Template {} not found", id) }),
+        ));
+    };
 
-        let metadata = AppMetadata::new(
-            app_name.to_string(),
-            app_type.to_string(),
-            github_url.to_string(),
+    let mut errors = ValidationErrors::default();
+    if load_deleted_app(&body.app_name).is_some() || load_app_request(&body.app_name).await.is_ok()
+    {
+        errors.add(
+            "app_name",
+            format!("Application {} already exists", body.app_name),
         );
+    } else if let Err(e) = check_app_name_available(&body.app_name, template.github_url()).await {
+        errors.add("app_name", e);
+    }
+    for name in template.missing_variables(&body.env) {
+        errors.add("env", format!("{} is required", name));
+    }
+    if let Err(errors) = errors.into_result() {
+        return Ok(json_reply(
+            warp::http::StatusCode::UNPROCESSABLE_ENTITY,
+            json!(errors),
+        ));
+    }
 
-        // Clone repository
-        send_deployment_status(
-            &status_tx,
-            app_name,
-            "in_progress",
-            "Cloning repository",
-            None,
-        )
-        .await;
-        let temp_dir = match create_temp_dir(app_name) {
-            Ok(dir) => dir,
-            Err(e) => {
-                send_deployment_status(
-                    &status_tx,
-                    app_name,
-                    "error",
-                    &format!("Failed to create temp directory: {}", e),
-                    None,
-                )
-                .await;
-                return Err(reject::custom(CustomError(format!(
-                    "Failed to create temp directory: {}",
-                    e
-                ))));
-            }
-        };
+    let request = template.deploy_request(&body.app_name, body.env);
+    if let Err(reply) = check_quota(&principal, &AppPlan::deploy(&request)) {
+        return Ok(reply);
+    }
+    let deployment_id = spawn_deployment(request, &principal.name);
 
-        let temp_dir_path = match temp_dir.to_str() {
-            Some(path) => path,
-            None => {
-                send_deployment_status(
-                    &status_tx,
-                    app_name,
-                    "error",
-                    "Invalid temp directory path",
-                    None,
-                )
-                .await;
-                return Err(reject::custom(CustomError(
-                    "Temp directory path is invalid".to_string(),
-                )));
-            }
-        };
+    Ok(json_reply(
+        warp::http::StatusCode::CREATED,
+        json!({
+            "message": "Deployment Job has been created !",
+            "app_name": body.app_name,
+            "template": template.id,
+            "deployment_id": deployment_id,
+        }),
+    ))
+}
 
-        if let Err(e) = clone_repo(github_url, temp_dir_path) {
-            let _ = remove_temp_dir(&temp_dir);
-            send_deployment_status(
-                &status_tx,
-                app_name,
-                "error",
-                &format!("Failed to clone repository: {}", e),
-                None,
-            )
-            .await;
-            return Err(reject::custom(CustomError(format!(
-                "Failed to clone repository: {}",
-                e
-            ))));
-        }
+/// Handles the quota request.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_quota(principal: Principal) -> Result<impl warp::Reply, warp::Rejection> {
+    let (account, limits, usage) =
+        caller_quota(&principal.name).map_err(|e| warp::reject::custom(CustomError(e)))?;
 
-        // Generate Dockerfile
-        if let Err(e) = generate_and_write_dockerfile(
-            app_type,
-            temp_dir_path,
-            &metadata,
-            install_command,
-            run_command,
-            build_command,
-            app_workdir,
-            Some(&additional_inputs),
-        ) {
-            let _ = remove_temp_dir(&temp_dir);
-            send_deployment_status(
-                &status_tx,
-                app_name,
-                "error",
-                &format!("Failed to generate Dockerfile: {}", e),
-                None,
-            )
-            .await;
-            return Err(reject::custom(CustomError(format!(
-                "Failed to generate Dockerfile: {}",
-                e
-            ))));
-        }
+    Ok(json_reply(
+        warp::http::StatusCode::OK,
+        json!({
+            "account": account,
+            "limits": limits,
+            "usage": usage,
+        }),
+    ))
+}
 
-        send_deployment_status(&status_tx, app_name, "success", "Cloning repository", None).await;
+/// Handles a GitHub webhook delivery.
+///
+/// Validates the HMAC signature, then for `push` events finds the deployed apps tracking the
+/// pushed repository and branch, and redeploys each of them in the background.
+///
+/// # Arguments
+///
+/// * `event` - The `X-GitHub-Event` header.
+/// * `signature` - The `X-Hub-Signature-256` header.
+/// * `payload` - The raw request body.
+///
+/// # Returns
+///
+/// A result containing a Warp reply or a Warp rejection.
+async fn handle_github_webhook(
+    event: Option<String>,
+    signature: Option<String>,
+    payload: bytes::Bytes,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let reply = |status: warp::http::StatusCode, body: Value| {
+        warp::reply::with_status(warp::reply::json(&body), status)
+    };
 
-        // Build Docker image
-        send_deployment_status(
-            &status_tx,
-            app_name,
-            "in_progress",
-            "Building Docker image",
-            None,
-        )
-        .await;
-        if let Err(e) = build_image(app_name, temp_dir_path, &metadata).await {
-            let _ = remove_temp_dir(&temp_dir);
-            send_deployment_status(
-                &status_tx,
-                app_name,
-                "error",
-                &format!("Failed to build Docker image: {}", e),
-                None,
-            )
-            .await;
-            return Err(reject::custom(CustomError(format!(
-                "Failed to build Docker image: {}",
-                e
-            ))));
+    let secret = match env::var("GITHUB_WEBHOOK_SECRET") {
+        Ok(secret) if !secret.is_empty() => secret,
+        _ => {
+            return Ok(reply(
+                warp::http::StatusCode::UNAUTHORIZED,
+                json!({ "error": "Webhook secret is not configured" }),
+            ))
         }
+    };
 
-        send_deployment_status(
-            &status_tx,
-            app_name,
-            "success",
-            "Building Docker image",
-            None,
-        )
-        .await;
+    if !signature
+        .map(|signature| verify_webhook_signature(&secret, &payload, &signature))
+        .unwrap_or(false)
+    {
+        return Ok(reply(
+            warp::http::StatusCode::UNAUTHORIZED,
+            json!({ "error": "Invalid webhook signature" }),
+        ));
+    }
 
-        if let Err(e) = push_image(app_name).await {
-            return Err(reject::custom(CustomError(format!(
-                "Failed to push Docker image: {}",
-                e
-            ))));
+    match event.as_deref() {
+        Some("ping") => return Ok(reply(warp::http::StatusCode::OK, json!("pong"))),
+        Some("push") => {}
+        _ => {
+            return Ok(reply(
+                warp::http::StatusCode::ACCEPTED,
+                json!({ "message": "Event ignored" }),
+            ))
         }
+    }
 
-        send_deployment_status(
-            &status_tx,
-            app_name,
-            "in_progress",
-            "Starting deployment",
-            None,
-        )
-        .await;
-        if let Ok(1) = verif_app(app_name) {
-            if let Err(e) = deploy_nephelios_stack() {
-                let _ = remove_temp_dir(&temp_dir);
-                send_deployment_status(
-                    &status_tx,
-                    app_name,
-                    "error",
-                    &format!("Failed to update deployment: {}", e),
-                    None,
-                )
-                .await;
-                return Err(reject::custom(CustomError(format!(
-                    "Failed to execute docker compose: {}",
-                    e
-                ))));
-            }
-        } else {
-            if let Err(e) = add_to_deploy(app_name, "3000", &metadata) {
-                let _ = remove_temp_dir(&temp_dir);
-                send_deployment_status(
-                    &status_tx,
-                    app_name,
-                    "error",
-                    &format!("Failed to add app to deploy file: {}", e),
-                    None,
-                )
-                .await;
-                return Err(reject::custom(CustomError(format!(
-                    "Failed to add app to deploy file: {}",
-                    e
-                ))));
-            }
-
-            if let Err(e) = deploy_nephelios_stack() {
-                let _ = remove_temp_dir(&temp_dir);
-                send_deployment_status(
-                    &status_tx,
-                    app_name,
-                    "error",
-                    &format!("Failed to start deployment: {}", e),
-                    None,
-                )
-                .await;
-                return Err(reject::custom(CustomError(format!(
-                    "Failed to execute docker compose: {}",
-                    e
-                ))));
-            }
+    let body: Value = match serde_json::from_slice(&payload) {
+        Ok(body) => body,
+        Err(e) => {
+            return Ok(reply(
+                warp::http::StatusCode::BAD_REQUEST,
+                json!({ "error": format!("Invalid payload: {}", e) }),
+            ))
         }
+    };
+
+    if body
+        .get("deleted")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+    {
+        return Ok(reply(
+            warp::http::StatusCode::ACCEPTED,
+            json!({ "message": "Branch deletion ignored" }),
+        ));
+    }
 
-        send_deployment_status(&status_tx, app_name, "success", "Starting deployment", None).await;
+    let pushed_branch = body
+        .get("ref")
+        .and_then(Value::as_str)
+        .and_then(|git_ref| git_ref.strip_prefix("refs/heads/"))
+        .unwrap_or_default()
+        .to_string();
+    let repository = body.get("repository").cloned().unwrap_or_default();
+    let default_branch = repository
+        .get("default_branch")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let repo_urls: Vec<String> = ["html_url", "clone_url", "ssh_url"]
+        .iter()
+        .filter_map(|key| repository.get(*key).and_then(Value::as_str))
+        .map(normalize_repo_url)
+        .collect();
 
-        if let Err(e) = remove_temp_dir(&temp_dir) {
-            eprintln!("Warning: Failed to clean up temp directory: {}", e);
+    let apps = match list_registered_apps() {
+        Ok(apps) => apps,
+        Err(e) => {
+            return Ok(reply(
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+                json!({ "error": format!("Failed to list apps: {}", e) }),
+            ))
         }
+    };
 
-        tokio::spawn(async move {
-            let res_prune_images = prune_images().await;
-            match res_prune_images {
-                Ok(_) => println!("✅ Docker images pruned successfully"),
-                Err(e) => eprintln!("❌ Failed to prune Docker images: {}", e),
-            }
-        });
+    let mut redeployed = Vec::new();
+    for app in apps.iter().filter(|app| is_deployed(app)) {
+        if !repo_urls.contains(&normalize_repo_url(&app.github_url)) {
+            continue;
+        }
 
-        // Get both the app status and swarm service name
-        let (status, swarm_name) = get_app_details(app_name.to_string()).await;
+        let request = load_deploy_request(&app.app_name)
+            .unwrap_or_else(|| DeployRequest::from_app_info(app));
+        let tracked_branch = request.git_ref.as_deref().unwrap_or(default_branch);
+        if pushed_branch.is_empty() || tracked_branch != pushed_branch {
+            continue;
+        }
 
-        let response = json!({
-        "message": "Application created successfully",
-        "app_name": app_name,
-        "app_type": app_type,
-        "github_url": github_url,
-        "status": status,
-        "swarm_task_name": swarm_name,
-        "domain": metadata.domain,
-        "created_at": metadata.created_at,
-        });
+        if let Err(QuotaError::Exceeded(e) | QuotaError::Failed(e)) =
+            enforce_quota(None, &AppPlan::deploy(&request))
+        {
+            warn!("Skipping redeploy of app {}: {}", request.app_name, e);
+            continue;
+        }
 
         send_deployment_status(
-            &status_tx,
-            app_name,
-            "deployed",
-            "deployed_info",
-            Some(response.clone()),
-        )
-        .await;
+            &request.app_name,
+            DeploymentEvent::RedeployTriggered {
+                reason: "Redeploy triggered by push".to_string(),
+            },
+        );
 
-        Ok(warp::reply::with_status(
-            warp::reply::json(&response),
-            warp::http::StatusCode::CREATED,
-        ))
-    });
+        let app_name = request.app_name.clone();
+        let deployment_id = spawn_deployment(request, "github-webhook");
+        redeployed.push(json!({ "app_name": app_name, "deployment_id": deployment_id }));
+    }
 
-    Ok(warp::reply::with_status(
-        "Deployment Job has been created !",
-        warp::http::StatusCode::CREATED,
+    Ok(reply(
+        warp::http::StatusCode::ACCEPTED,
+        json!({ "message": "Push processed", "redeployed": redeployed }),
     ))
 }
@@ -0,0 +1,444 @@
+use crate::services::helpers::docker_helper::{remove_service, service_replicas};
+use crate::services::helpers::stack_helper::{load_stack, update_stack, Deploy, Service};
+use openssl::rand::rand_bytes;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value as YamlValue};
+use std::io;
+use tracing::{info, warn};
+
+/// Label holding the type of an addon service.
+const ADDON_TYPE_LABEL: &str = "com.nephelios.addon.type";
+
+/// Label holding the app an addon service belongs to.
+const ADDON_APP_LABEL: &str = "com.nephelios.addon.app";
+
+/// Maximum length of a Swarm service name, which addon services get prefixed with `nephelios_`.
+const MAX_SERVICE_NAME_LENGTH: usize = 63;
+
+/// Maximum length of a MySQL user name.
+const MAX_MYSQL_USER_LENGTH: usize = 32;
+
+/// A backing service deployed next to an application (e.g., a database).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddonType {
+    /// PostgreSQL, exposed to the app as `DATABASE_URL`.
+    Postgres,
+    /// Redis, exposed to the app as `REDIS_URL`.
+    Redis,
+    /// MySQL, exposed to the app as `DATABASE_URL`.
+    Mysql,
+    /// MariaDB, exposed to the app as `DATABASE_URL` with the `mysql` scheme.
+    Mariadb,
+}
+
+impl AddonType {
+    /// Every kind of addon.
+    pub const ALL: [AddonType; 4] = [
+        AddonType::Postgres,
+        AddonType::Redis,
+        AddonType::Mysql,
+        AddonType::Mariadb,
+    ];
+
+    /// Parses an addon type (`postgres`, `redis`, `mysql` or `mariadb`).
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "postgres" | "postgresql" => Ok(AddonType::Postgres),
+            "redis" => Ok(AddonType::Redis),
+            "mysql" => Ok(AddonType::Mysql),
+            "mariadb" => Ok(AddonType::Mariadb),
+            other => Err(format!("Unknown addon type: {}", other)),
+        }
+    }
+
+    /// Returns the name of the type, as used in service names and labels.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AddonType::Postgres => "postgres",
+            AddonType::Redis => "redis",
+            AddonType::Mysql => "mysql",
+            AddonType::Mariadb => "mariadb",
+        }
+    }
+
+    /// Returns the image the addon runs.
+    fn image(self) -> &'static str {
+        match self {
+            AddonType::Postgres => "postgres:16-alpine",
+            AddonType::Redis => "redis:7-alpine",
+            AddonType::Mysql => "mysql:8.4",
+            AddonType::Mariadb => "mariadb:11",
+        }
+    }
+
+    /// Returns the directory the addon keeps its data in, mounted from a volume.
+    fn data_path(self) -> &'static str {
+        match self {
+            AddonType::Postgres => "/var/lib/postgresql/data",
+            AddonType::Redis => "/data",
+            AddonType::Mysql | AddonType::Mariadb => "/var/lib/mysql",
+        }
+    }
+
+    /// Whether the addon may run without a volume, losing its data when restarted.
+    pub fn supports_ephemeral(self) -> bool {
+        matches!(self, AddonType::Redis)
+    }
+
+    /// Returns the environment variable the connection URL is injected into.
+    pub fn env_var(self) -> &'static str {
+        match self {
+            AddonType::Postgres | AddonType::Mysql | AddonType::Mariadb => "DATABASE_URL",
+            AddonType::Redis => "REDIS_URL",
+        }
+    }
+}
+
+/// An addon of an application, as deployed in the stack file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Addon {
+    pub app_name: String,
+    #[serde(rename = "type")]
+    pub addon_type: AddonType,
+    /// The stack service of the addon, also its hostname on the overlay network.
+    pub service: String,
+    pub image: Option<String>,
+    /// The volume holding the data of the addon, `None` if the data is not persisted.
+    pub volume: Option<String>,
+    /// The app environment variable holding the connection URL.
+    pub env_var: String,
+    /// `running`, `starting`, `stopped`, `missing` or `unknown`, when read from the Swarm.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+/// Returns the stack service name of an addon of an app (e.g., `my-app-postgres`).
+pub fn addon_service_name(app_name: &str, addon_type: AddonType) -> String {
+    format!("{}-{}", app_name, addon_type.as_str())
+}
+
+/// Whether a volume holds the data of an addon, named `<app>-<type>-data-<id>` by
+/// `add_addon`, whether the addon still exists or not.
+///
+/// # Arguments
+/// * `volume` - The name of the volume, in the stack file or in Docker.
+pub fn is_addon_volume(volume: &str) -> bool {
+    AddonType::ALL
+        .iter()
+        .any(|addon_type| volume.contains(&format!("-{}-data-", addon_type.as_str())))
+}
+
+/// Returns the value of a `key=value` deploy label of a service.
+fn label_value<'a>(service: &'a Service, key: &str) -> Option<&'a str> {
+    service
+        .deploy
+        .as_ref()?
+        .labels
+        .iter()
+        .find_map(|label| label.strip_prefix(key)?.strip_prefix('='))
+}
+
+/// Reads the addon a stack service stands for, if it is one.
+fn addon_from_service(name: &str, service: &Service) -> Option<Addon> {
+    let app_name = label_value(service, ADDON_APP_LABEL)?;
+    let addon_type = AddonType::parse(label_value(service, ADDON_TYPE_LABEL)?).ok()?;
+    let volume = service
+        .extra
+        .get("volumes")
+        .and_then(|volumes| volumes.as_sequence())
+        .and_then(|volumes| volumes.first())
+        .and_then(|volume| volume.as_str())
+        .and_then(|volume| volume.split(':').next())
+        .map(str::to_string);
+
+    Some(Addon {
+        app_name: app_name.to_string(),
+        addon_type,
+        service: name.to_string(),
+        image: service.image.clone(),
+        volume,
+        env_var: addon_type.env_var().to_string(),
+        status: None,
+    })
+}
+
+/// Lists the addons of an application, from the stack file.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(Vec<Addon>)` - The addons of the app.
+/// * `Err(String)` - If the stack file could not be read.
+pub fn list_addons(app_name: &str) -> Result<Vec<Addon>, String> {
+    let stack = load_stack().map_err(|e| format!("Failed to read the stack file: {}", e))?;
+    Ok(stack
+        .services
+        .iter()
+        .filter_map(|(name, service)| addon_from_service(name, service))
+        .filter(|addon| addon.app_name == app_name)
+        .collect())
+}
+
+/// Lists the addons of an application with the status of their service.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(Vec<Addon>)` - The addons of the app, with their `status`.
+/// * `Err(String)` - If the stack file could not be read.
+pub async fn list_addons_with_status(app_name: &str) -> Result<Vec<Addon>, String> {
+    let mut addons = list_addons(app_name)?;
+    for addon in &mut addons {
+        let status = match service_replicas(&addon.service).await {
+            Ok(None) => "missing",
+            Ok(Some((_, 0))) => "stopped",
+            Ok(Some((running, desired))) if running >= desired => "running",
+            Ok(Some(_)) => "starting",
+            Err(e) => {
+                warn!(
+                    "Failed to read the status of addon {}: {}",
+                    addon.service, e
+                );
+                "unknown"
+            }
+        };
+        addon.status = Some(status.to_string());
+    }
+    Ok(addons)
+}
+
+/// Generates a random hexadecimal secret.
+fn generate_secret() -> Result<String, String> {
+    let mut bytes = [0u8; 24];
+    rand_bytes(&mut bytes).map_err(|e| format!("Failed to generate addon password: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Returns the database and user name of an addon, from the app name.
+fn database_name(app_name: &str) -> String {
+    app_name.replace('-', "_")
+}
+
+/// Builds the environment and command of an addon service, and the connection URL given to
+/// the app.
+fn addon_settings(
+    app_name: &str,
+    addon_type: AddonType,
+    service: &str,
+    password: &str,
+    persistent: bool,
+) -> (Mapping, Vec<String>, String) {
+    let mut environment = Mapping::new();
+    let mut command = Vec::new();
+    let mut set = |name: &str, value: &str| {
+        environment.insert(
+            YamlValue::String(name.to_string()),
+            YamlValue::String(value.to_string()),
+        );
+    };
+
+    let url = match addon_type {
+        AddonType::Postgres => {
+            let database = database_name(app_name);
+            set("POSTGRES_USER", &database);
+            set("POSTGRES_PASSWORD", password);
+            set("POSTGRES_DB", &database);
+            format!(
+                "postgres://{}:{}@{}:5432/{}",
+                database, password, service, database
+            )
+        }
+        AddonType::Redis => {
+            command.extend(["redis-server", "--requirepass", password]);
+            if persistent {
+                command.extend(["--appendonly", "yes"]);
+            } else {
+                command.extend(["--save", "", "--appendonly", "no"]);
+            }
+            format!("redis://:{}@{}:6379", password, service)
+        }
+        AddonType::Mysql | AddonType::Mariadb => {
+            let prefix = if addon_type == AddonType::Mysql {
+                "MYSQL"
+            } else {
+                "MARIADB"
+            };
+            let database = database_name(app_name);
+            let user = &database[..database.len().min(MAX_MYSQL_USER_LENGTH)];
+            set(&format!("{}_USER", prefix), user);
+            set(&format!("{}_PASSWORD", prefix), password);
+            set(&format!("{}_DATABASE", prefix), &database);
+            // The app only gets its own user, the root password is never needed
+            set(&format!("{}_RANDOM_ROOT_PASSWORD", prefix), "yes");
+            format!(
+                "mysql://{}:{}@{}:3306/{}",
+                user, password, service, database
+            )
+        }
+    };
+    let command = command.into_iter().map(str::to_string).collect();
+    (environment, command, url)
+}
+
+/// Adds an addon service to the stack file, with generated credentials and a volume for its
+/// data.
+///
+/// The service joins the overlay network, so the app reaches it by its service name. It is
+/// not routed by Traefik. The stack must be deployed for the addon to start.
+///
+/// # Arguments
+/// * `app_name` - The name of the application the addon belongs to.
+/// * `addon_type` - The kind of addon.
+/// * `persistent` - Whether the data is kept in a volume, always for addons that do not
+///   support running without one.
+///
+/// # Returns
+/// * `Ok((Addon, String))` - The addon and the connection URL to give the app.
+/// * `Err(String)` - If the app already has this addon or the stack file could not be updated.
+pub fn provision_addon(
+    app_name: &str,
+    addon_type: AddonType,
+    persistent: bool,
+) -> Result<(Addon, String), String> {
+    let name = addon_service_name(app_name, addon_type);
+    if "nephelios_".len() + name.len() > MAX_SERVICE_NAME_LENGTH {
+        return Err(format!(
+            "The name of {} is too long for a {} addon",
+            app_name,
+            addon_type.as_str()
+        ));
+    }
+    let persistent = persistent || !addon_type.supports_ephemeral();
+    let password = generate_secret()?;
+    // A new volume for every provisioning, as the data of a removed addon is kept with its
+    // former credentials
+    let volume = if persistent {
+        Some(format!("{}-data-{}", name, &generate_secret()?[..8]))
+    } else {
+        None
+    };
+    let (environment, command, url) =
+        addon_settings(app_name, addon_type, &name, &password, persistent);
+
+    let mut extra = Mapping::new();
+    if !environment.is_empty() {
+        extra.insert(
+            YamlValue::String("environment".to_string()),
+            YamlValue::Mapping(environment),
+        );
+    }
+    if !command.is_empty() {
+        extra.insert(
+            YamlValue::String("command".to_string()),
+            YamlValue::Sequence(command.into_iter().map(YamlValue::String).collect()),
+        );
+    }
+    if let Some(volume) = &volume {
+        extra.insert(
+            YamlValue::String("volumes".to_string()),
+            YamlValue::Sequence(vec![YamlValue::String(format!(
+                "{}:{}",
+                volume,
+                addon_type.data_path()
+            ))]),
+        );
+    }
+    let service = Service {
+        image: Some(addon_type.image().to_string()),
+        deploy: Some(Deploy {
+            mode: Some("replicated".to_string()),
+            replicas: Some(1),
+            labels: vec![
+                format!("{}={}", ADDON_APP_LABEL, app_name),
+                format!("{}={}", ADDON_TYPE_LABEL, addon_type.as_str()),
+                "traefik.enable=false".to_string(),
+            ],
+            ..Default::default()
+        }),
+        networks: vec!["nephelios_overlay".to_string()],
+        extra,
+    };
+    let addon = addon_from_service(&name, &service)
+        .ok_or_else(|| format!("Invalid addon service {}", name))?;
+
+    update_stack(|stack| {
+        if stack.services.contains_key(&name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Service {} already exists", name),
+            ));
+        }
+        if let Some(volume) = &volume {
+            stack.volumes.insert(
+                YamlValue::String(volume.clone()),
+                YamlValue::Mapping(Mapping::new()),
+            );
+        }
+        stack.services.insert(name.clone(), service);
+        Ok(())
+    })
+    .map_err(|e| format!("Failed to add {} to the stack file: {}", name, e))?;
+
+    info!(
+        "🧩 Added {} addon {} for {}",
+        addon_type.as_str(),
+        name,
+        app_name
+    );
+    Ok((addon, url))
+}
+
+/// Removes an addon service from the stack file and from the Swarm.
+///
+/// The volume of the addon is kept, so its data can still be recovered.
+///
+/// # Arguments
+/// * `app_name` - The name of the application the addon belongs to.
+/// * `addon_type` - The kind of addon.
+///
+/// # Returns
+/// * `Ok(Some(Addon))` - The removed addon.
+/// * `Ok(None)` - If the app has no such addon.
+/// * `Err(String)` - If the stack file or the service could not be updated.
+pub async fn remove_addon(app_name: &str, addon_type: AddonType) -> Result<Option<Addon>, String> {
+    let name = addon_service_name(app_name, addon_type);
+    let removed = update_stack(|stack| {
+        let is_addon = stack
+            .services
+            .get(&name)
+            .and_then(|service| addon_from_service(&name, service))
+            .is_some_and(|addon| addon.app_name == app_name);
+        if !is_addon {
+            return Ok(None);
+        }
+        Ok(stack
+            .services
+            .shift_remove(&name)
+            .and_then(|service| addon_from_service(&name, &service)))
+    })
+    .map_err(|e| format!("Failed to remove {} from the stack file: {}", name, e))?;
+
+    if removed.is_some() {
+        remove_service(&name).await?;
+        info!("🧩 Removed addon {} of {}", name, app_name);
+    }
+    Ok(removed)
+}
+
+/// Removes every addon of an application, e.g. when the app is removed.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(())` - If every addon was removed.
+/// * `Err(String)` - If an addon could not be removed.
+pub async fn remove_app_addons(app_name: &str) -> Result<(), String> {
+    for addon in list_addons(app_name)? {
+        remove_addon(app_name, addon.addon_type).await?;
+    }
+    Ok(())
+}
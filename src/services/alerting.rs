@@ -0,0 +1,394 @@
+use crate::services::events::{consume_events, publish, Event};
+use crate::services::helpers::docker_helper::list_deployed_apps;
+use crate::services::metrics_collector::MetricsSnapshot;
+use crate::services::metrics_history::app_samples;
+use chrono::{DateTime, Utc};
+use dirs::home_dir;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How long a webhook may take to answer a notification.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The condition of an alert rule.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// The CPU usage of the app, summed over its replicas, is above `percent`.
+    CpuAbove { percent: f64 },
+    /// The memory usage of the app, summed over its replicas, is above `mb` MiB.
+    MemoryAbove { mb: f64 },
+    /// The app should run but none of its replicas is running.
+    AppDown,
+}
+
+impl AlertCondition {
+    /// Describes the condition when it is met.
+    ///
+    /// # Arguments
+    ///
+    /// * `app_name` - The app the condition is met for
+    /// * `value` - The measured value, if the condition is a threshold
+    fn describe(&self, app_name: &str, value: f64) -> String {
+        match self {
+            AlertCondition::CpuAbove { percent } => format!(
+                "CPU usage of {} is {:.1}% (threshold {}%)",
+                app_name, value, percent
+            ),
+            AlertCondition::MemoryAbove { mb } => format!(
+                "Memory usage of {} is {:.1} MiB (threshold {} MiB)",
+                app_name, value, mb
+            ),
+            AlertCondition::AppDown => format!("{} has no running replica", app_name),
+        }
+    }
+}
+
+/// A user-defined alert rule.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub id: String,
+    pub name: String,
+    /// The app the rule applies to, every app if `None`.
+    pub app_name: Option<String>,
+    pub condition: AlertCondition,
+    /// How long the condition must hold before the alert fires, in seconds.
+    pub for_seconds: u64,
+    /// URLs notified with a JSON POST when the alert fires and resolves.
+    pub webhooks: Vec<String>,
+}
+
+/// An alert of a rule for an app, pending until its condition held for `for_seconds`.
+#[derive(Clone, Debug, Serialize)]
+pub struct Alert {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub app_name: String,
+    pub message: String,
+    /// When the condition started to hold.
+    pub since: DateTime<Utc>,
+    /// When the alert fired, `None` while pending.
+    pub fired_at: Option<DateTime<Utc>>,
+}
+
+lazy_static! {
+    /// Pending and firing alerts, by rule ID and app name.
+    static ref ALERTS: Mutex<HashMap<(String, String), Alert>> = Mutex::new(HashMap::new());
+    /// Serializes the updates of the rules file.
+    static ref RULES_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Returns the path of the file storing the alert rules.
+fn rules_path() -> Result<PathBuf, String> {
+    let home = home_dir().ok_or("Failed to find home directory")?;
+    Ok(home.join(".config/nephelios/alert_rules.json"))
+}
+
+/// Lists the alert rules.
+///
+/// # Returns
+/// * `Ok(Vec<AlertRule>)` - The rules, empty if none was defined.
+/// * `Err(String)` - If the rules file could not be read.
+pub fn load_alert_rules() -> Result<Vec<AlertRule>, String> {
+    let path = rules_path()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read alert rules: {}", e)),
+    };
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse alert rules: {}", e))
+}
+
+/// Writes the alert rules.
+fn save_alert_rules(rules: &[AlertRule]) -> Result<(), String> {
+    let path = rules_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(rules)
+        .map_err(|e| format!("Failed to serialize alert rules: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write alert rules: {}", e))
+}
+
+/// Adds an alert rule.
+///
+/// # Arguments
+/// * `name` - The name of the rule.
+/// * `app_name` - The app the rule applies to, every app if `None`.
+/// * `condition` - The condition firing the alert.
+/// * `for_duration` - How long the condition must hold before the alert fires.
+/// * `webhooks` - The URLs notified when the alert fires and resolves.
+///
+/// # Returns
+/// * `Ok(AlertRule)` - The stored rule, with its generated ID.
+/// * `Err(String)` - If the rules file could not be updated.
+pub fn add_alert_rule(
+    name: String,
+    app_name: Option<String>,
+    condition: AlertCondition,
+    for_duration: Duration,
+    webhooks: Vec<String>,
+) -> Result<AlertRule, String> {
+    let rule = AlertRule {
+        id: Uuid::new_v4().to_string(),
+        name,
+        app_name,
+        condition,
+        for_seconds: for_duration.as_secs(),
+        webhooks,
+    };
+
+    let _guard = RULES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut rules = load_alert_rules()?;
+    rules.push(rule.clone());
+    save_alert_rules(&rules)?;
+    Ok(rule)
+}
+
+/// Replaces every alert rule, e.g. when restoring a backup.
+///
+/// # Arguments
+/// * `rules` - The new rules.
+pub fn replace_alert_rules(rules: &[AlertRule]) -> Result<(), String> {
+    let _guard = RULES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    save_alert_rules(rules)?;
+    ALERTS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    Ok(())
+}
+
+/// Deletes an alert rule and drops its alerts.
+///
+/// # Arguments
+/// * `id` - The ID of the rule.
+///
+/// # Returns
+/// * `Ok(true)` if the rule was deleted, `Ok(false)` if it does not exist.
+/// * `Err(String)` if the rules file could not be updated.
+pub fn delete_alert_rule(id: &str) -> Result<bool, String> {
+    let _guard = RULES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut rules = load_alert_rules()?;
+    let count = rules.len();
+    rules.retain(|rule| rule.id != id);
+    if rules.len() == count {
+        return Ok(false);
+    }
+
+    save_alert_rules(&rules)?;
+    ALERTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|(rule_id, _), _| rule_id != id);
+    Ok(true)
+}
+
+/// Lists the firing alerts, oldest first.
+pub fn active_alerts() -> Vec<Alert> {
+    let mut alerts: Vec<Alert> = ALERTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .filter(|alert| alert.fired_at.is_some())
+        .cloned()
+        .collect();
+    alerts.sort_by_key(|alert| alert.fired_at);
+    alerts
+}
+
+/// Posts an alert to the webhooks of its rule.
+///
+/// # Arguments
+/// * `webhooks` - The URLs to notify.
+/// * `status` - `firing` or `resolved`.
+/// * `alert` - The alert.
+async fn notify(webhooks: Vec<String>, status: &'static str, alert: Alert) {
+    let client = match reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Failed to create webhook client: {}", e);
+            return;
+        }
+    };
+
+    let payload = json!({ "status": status, "alert": alert });
+    for url in webhooks {
+        let result = client
+            .post(&url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = result {
+            warn!("Failed to notify alert webhook {}: {}", url, e);
+        }
+    }
+}
+
+/// Posts the alerts fired and resolved on the event bus to the webhooks of their rule.
+pub async fn run_alert_notifier() {
+    consume_events("alert notifier", |event| async move {
+        let (status, alert) = match event {
+            Event::AlertFired { alert } => ("firing", alert),
+            Event::AlertResolved { alert } => ("resolved", alert),
+            _ => return,
+        };
+        let webhooks = match load_alert_rules() {
+            Ok(rules) => rules
+                .into_iter()
+                .find(|rule| rule.id == alert.rule_id)
+                .map(|rule| rule.webhooks)
+                .unwrap_or_default(),
+            Err(e) => {
+                warn!("Failed to notify alert {}: {}", alert.rule_name, e);
+                return;
+            }
+        };
+        if !webhooks.is_empty() {
+            tokio::spawn(notify(webhooks, status, alert));
+        }
+    })
+    .await
+}
+
+/// Evaluates the alert rules against the latest metrics.
+///
+/// Alerts whose condition held for the `for_seconds` of their rule fire, and alerts whose
+/// condition stopped holding resolve; both are published on the event bus.
+///
+/// # Arguments
+///
+/// * `snapshot` - The metrics collected from every container
+pub async fn evaluate_alerts(snapshot: &MetricsSnapshot) {
+    let rules = match load_alert_rules() {
+        Ok(rules) => rules,
+        Err(e) => {
+            warn!("Failed to evaluate alerts: {}", e);
+            return;
+        }
+    };
+    if rules.is_empty() {
+        return;
+    }
+
+    let samples = app_samples(snapshot);
+    // Apps scaled to zero are "stopping", apps whose replicas all failed are "stopped"
+    let mut statuses: Option<HashMap<String, String>> = None;
+    if rules
+        .iter()
+        .any(|rule| rule.condition == AlertCondition::AppDown)
+    {
+        match list_deployed_apps().await {
+            Ok(apps) => {
+                statuses = Some(
+                    apps.into_iter()
+                        .map(|app| (app.app_name, app.status))
+                        .collect(),
+                );
+            }
+            Err(e) => warn!("Failed to list apps for alerts: {}", e),
+        }
+    }
+
+    let now = snapshot.timestamp;
+    let mut events = Vec::new();
+    {
+        let mut alerts = ALERTS.lock().unwrap_or_else(|e| e.into_inner());
+        let mut evaluated = HashSet::new();
+        for rule in &rules {
+            let apps: Vec<&String> = match (&rule.condition, &rule.app_name, &statuses) {
+                // Keep the app down alerts as they are when the apps could not be listed
+                (AlertCondition::AppDown, _, None) => {
+                    evaluated.extend(
+                        alerts
+                            .keys()
+                            .filter(|(rule_id, _)| *rule_id == rule.id)
+                            .cloned(),
+                    );
+                    continue;
+                }
+                (_, Some(app_name), _) => vec![app_name],
+                (AlertCondition::AppDown, None, Some(statuses)) => statuses.keys().collect(),
+                (_, None, _) => samples.keys().collect(),
+            };
+
+            for app_name in apps {
+                let value = match &rule.condition {
+                    AlertCondition::CpuAbove { percent } => samples
+                        .get(app_name)
+                        .map(|sample| sample.cpu_percent)
+                        .filter(|cpu| cpu > percent),
+                    AlertCondition::MemoryAbove { mb } => samples
+                        .get(app_name)
+                        .map(|sample| sample.memory_mb)
+                        .filter(|memory| memory > mb),
+                    AlertCondition::AppDown => statuses
+                        .as_ref()
+                        .and_then(|statuses| statuses.get(app_name))
+                        .filter(|status| status.as_str() == "stopped")
+                        .map(|_| 0.0),
+                };
+
+                let key = (rule.id.clone(), app_name.clone());
+                evaluated.insert(key.clone());
+                let Some(value) = value else {
+                    if let Some(alert) = alerts.remove(&key) {
+                        if alert.fired_at.is_some() {
+                            info!("✅ Alert {} resolved for {}", rule.name, app_name);
+                            events.push(Event::AlertResolved { alert });
+                        }
+                    }
+                    continue;
+                };
+
+                let alert = alerts.entry(key).or_insert_with(|| Alert {
+                    rule_id: rule.id.clone(),
+                    rule_name: rule.name.clone(),
+                    app_name: app_name.clone(),
+                    message: String::new(),
+                    since: now,
+                    fired_at: None,
+                });
+                alert.message = rule.condition.describe(app_name, value);
+                let held = (now - alert.since).num_seconds().max(0) as u64;
+                if alert.fired_at.is_none() && held >= rule.for_seconds {
+                    alert.fired_at = Some(now);
+                    warn!("🚨 Alert {} firing: {}", rule.name, alert.message);
+                    events.push(Event::AlertFired {
+                        alert: alert.clone(),
+                    });
+                }
+            }
+        }
+
+        // Alerts of removed apps are resolved too
+        let rule_ids: HashSet<&str> = rules.iter().map(|rule| rule.id.as_str()).collect();
+        alerts.retain(|key, alert| {
+            if evaluated.contains(key) {
+                return true;
+            }
+            if alert.fired_at.is_some() && rule_ids.contains(key.0.as_str()) {
+                info!(
+                    "✅ Alert {} resolved for {}",
+                    alert.rule_name, alert.app_name
+                );
+                events.push(Event::AlertResolved {
+                    alert: alert.clone(),
+                });
+            }
+            false
+        });
+    }
+
+    for event in events {
+        publish(event);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::services::database::with_connection;
+use crate::services::events::{publish, Event};
+use crate::services::helpers::docker_helper::{list_deployed_apps, AppInfo};
+use crate::services::soft_delete::load_deleted_app;
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension, Row};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tracing::warn;
+
+/// How often the registry is synced with the Docker services.
+const SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Status of registered apps whose service no longer exists.
+pub const MISSING_STATUS: &str = "missing";
+
+/// Status of apps with running replicas.
+const RUNNING_STATUS: &str = "running";
+
+/// Status of apps that should run but whose replicas all failed.
+const STOPPED_STATUS: &str = "stopped";
+
+/// Status of soft-deleted apps, see `soft_delete`.
+pub const DELETED_STATUS: &str = "deleted";
+
+const APP_COLUMNS: &str = "app_name, app_type, github_url, domain, created_at, status, \
+     swarm_task_name, git_ref, commit_sha, commit_message, domains";
+
+/// Checks whether a registered app still has a service that is not soft-deleted.
+pub fn is_deployed(app: &AppInfo) -> bool {
+    app.status != MISSING_STATUS && app.status != DELETED_STATUS
+}
+
+/// Reads an app from a row selected with `APP_COLUMNS`.
+fn app_from_row(row: &Row) -> rusqlite::Result<AppInfo> {
+    let domains: String = row.get(10)?;
+    Ok(AppInfo {
+        app_name: row.get(0)?,
+        app_type: row.get(1)?,
+        github_url: row.get(2)?,
+        domain: row.get(3)?,
+        created_at: row.get(4)?,
+        status: row.get(5)?,
+        swarm_task_name: row.get(6)?,
+        git_ref: row.get(7)?,
+        commit_sha: row.get(8)?,
+        commit_message: row.get(9)?,
+        domains: serde_json::from_str(&domains).unwrap_or_default(),
+    })
+}
+
+/// Adds an app to the registry, or updates it.
+///
+/// The creation date of an app that is already registered is kept.
+///
+/// # Arguments
+/// * `app` - The app, as read from its service.
+///
+/// # Returns
+/// * `Ok(())` if the app is registered.
+/// * `Err(String)` if the database could not be updated.
+pub fn register_app(app: &AppInfo) -> Result<(), String> {
+    let domains = serde_json::to_string(&app.domains)
+        .map_err(|e| format!("Failed to serialize domains: {}", e))?;
+    with_connection(|connection| {
+        connection.execute(
+            "INSERT INTO apps (app_name, app_type, github_url, domain, created_at, status,
+                 swarm_task_name, git_ref, commit_sha, commit_message, domains, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+             ON CONFLICT (app_name) DO UPDATE SET
+                 app_type = excluded.app_type,
+                 github_url = excluded.github_url,
+                 domain = excluded.domain,
+                 status = excluded.status,
+                 swarm_task_name = excluded.swarm_task_name,
+                 git_ref = excluded.git_ref,
+                 commit_sha = excluded.commit_sha,
+                 commit_message = excluded.commit_message,
+                 domains = excluded.domains,
+                 updated_at = excluded.updated_at",
+            params![
+                app.app_name,
+                app.app_type,
+                app.github_url,
+                app.domain,
+                app.created_at,
+                app.status,
+                app.swarm_task_name,
+                app.git_ref,
+                app.commit_sha,
+                app.commit_message,
+                domains,
+                Utc::now().to_rfc3339(),
+            ],
+        )
+    })?;
+    Ok(())
+}
+
+/// Removes an app from the registry.
+///
+/// # Arguments
+/// * `app_name` - The name of the app.
+pub fn unregister_app(app_name: &str) -> Result<(), String> {
+    with_connection(|connection| {
+        connection.execute("DELETE FROM apps WHERE app_name = ?1", [app_name])
+    })?;
+    Ok(())
+}
+
+/// Updates the status of a registered app.
+///
+/// # Arguments
+/// * `app_name` - The name of the app.
+/// * `status` - The new status.
+pub fn set_app_status(app_name: &str, status: &str) -> Result<(), String> {
+    with_connection(|connection| {
+        connection.execute(
+            "UPDATE apps SET status = ?2, updated_at = ?3 WHERE app_name = ?1",
+            params![app_name, status, Utc::now().to_rfc3339()],
+        )
+    })?;
+    Ok(())
+}
+
+/// Lists the registered apps, most recently created first.
+///
+/// # Returns
+/// * `Ok(Vec<AppInfo>)` - The apps, with the status seen at the last sync.
+/// * `Err(String)` - If the database could not be read.
+pub fn list_registered_apps() -> Result<Vec<AppInfo>, String> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM apps ORDER BY created_at DESC",
+            APP_COLUMNS
+        ))?;
+        let apps = statement.query_map([], app_from_row)?;
+        apps.collect()
+    })
+}
+
+/// Finds a registered app by name.
+///
+/// # Arguments
+/// * `app_name` - The name of the app.
+///
+/// # Returns
+/// * `Ok(Some(AppInfo))` if the app is registered, `Ok(None)` otherwise.
+/// * `Err(String)` if the database could not be read.
+pub fn find_registered_app(app_name: &str) -> Result<Option<AppInfo>, String> {
+    with_connection(|connection| {
+        connection
+            .query_row(
+                &format!("SELECT {} FROM apps WHERE app_name = ?1", APP_COLUMNS),
+                [app_name],
+                app_from_row,
+            )
+            .optional()
+    })
+}
+
+/// Syncs the registry with the Docker services.
+///
+/// Apps found in the Nephelios stack are registered or updated with their current status,
+/// registered apps whose service is gone are kept with the `missing` status, until they
+/// are removed. Running apps whose replicas all failed since the last sync are published as
+/// crashed.
+///
+/// # Returns
+/// * `Ok(())` if the registry is in sync.
+/// * `Err(String)` if the services could not be listed or the database updated.
+pub async fn sync_app_registry() -> Result<(), String> {
+    let apps = list_deployed_apps().await?;
+    let deployed: HashSet<String> = apps.iter().map(|app| app.app_name.clone()).collect();
+    let previous: HashMap<String, String> = list_registered_apps()?
+        .into_iter()
+        .map(|app| (app.app_name, app.status))
+        .collect();
+
+    for mut app in apps {
+        if load_deleted_app(&app.app_name).is_some() {
+            app.status = DELETED_STATUS.to_string();
+        }
+        let was_running = previous.get(&app.app_name).map(String::as_str) == Some(RUNNING_STATUS);
+        if was_running && app.status == STOPPED_STATUS {
+            warn!("App {} has no running replica anymore", app.app_name);
+            publish(Event::AppCrashed {
+                app_name: app.app_name.clone(),
+            });
+        }
+        register_app(&app)?;
+    }
+
+    for app in list_registered_apps()? {
+        if !deployed.contains(&app.app_name) && app.status != MISSING_STATUS {
+            warn!("App {} has no service anymore", app.app_name);
+            set_app_status(&app.app_name, MISSING_STATUS)?;
+        }
+    }
+    Ok(())
+}
+
+/// Syncs a single app with its service, after it was deployed or changed.
+///
+/// # Arguments
+/// * `app_name` - The name of the app.
+pub async fn refresh_registered_app(app_name: &str) -> Result<(), String> {
+    let app = list_deployed_apps()
+        .await?
+        .into_iter()
+        .find(|app| app.app_name == app_name);
+    match app {
+        Some(app) => register_app(&app),
+        None => set_app_status(app_name, MISSING_STATUS),
+    }
+}
+
+/// Periodically syncs the registry with the Docker services.
+pub async fn run_app_registry_sync() {
+    let mut ticker = tokio::time::interval(SYNC_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        if let Err(e) = sync_app_registry().await {
+            warn!("Failed to sync app registry: {}", e);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::services::deployment::DeployRequest;
+use crate::services::helpers::docker_helper::{create_docker_secret, remove_docker_secret};
+use openssl::sha::sha256;
+use std::time::Duration;
+use tracing::warn;
+
+/// Prefix of the Docker secrets holding the secret values of apps.
+const SECRET_PREFIX: &str = "nephelios_secret_";
+
+/// Directory the secrets of an app are mounted in, one file per variable.
+pub const SECRETS_DIR: &str = "/run/secrets";
+
+/// Pause between two attempts to remove a replaced secret, while old tasks still mount it.
+const REMOVAL_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Number of attempts to remove a replaced secret before it is left behind.
+const REMOVAL_ATTEMPTS: u32 = 30;
+
+/// Returns the name of the Docker secret holding a secret value of an app.
+///
+/// Swarm secrets cannot be updated, so the name includes a hash of the app, the variable and
+/// the value: a new value gets a new secret, swapped in by a rolling update of the service.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `key` - The name of the variable.
+/// * `value` - The secret value.
+fn secret_name(app_name: &str, key: &str, value: &str) -> String {
+    let digest: String = sha256(format!("{}\0{}\0{}", app_name, key, value).as_bytes())
+        .iter()
+        .take(12)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}{}", SECRET_PREFIX, digest)
+}
+
+/// Returns the path a secret of an app is mounted at inside its containers.
+///
+/// # Arguments
+/// * `key` - The name of the variable.
+pub fn secret_path(key: &str) -> String {
+    format!("{}/{}", SECRETS_DIR, key)
+}
+
+/// Stores the pending secret values of a deploy request as Docker secrets, and references
+/// them by name in `secrets`.
+///
+/// # Arguments
+/// * `request` - The deploy request, its `pending_secrets` are emptied.
+///
+/// # Returns
+/// * `Ok(Vec<String>)` containing the secrets replaced by a new value, to remove once the
+///   service no longer mounts them.
+/// * `Err(String)` if a secret could not be created.
+pub fn store_pending_secrets(request: &mut DeployRequest) -> Result<Vec<String>, String> {
+    let mut replaced = Vec::new();
+    for (key, value) in std::mem::take(&mut request.pending_secrets) {
+        let name = secret_name(&request.app_name, &key, &value);
+        create_docker_secret(&name, &value)?;
+        if let Some(previous) = request.secrets.insert(key, name.clone()) {
+            if previous != name {
+                replaced.push(previous);
+            }
+        }
+    }
+    Ok(replaced)
+}
+
+/// Removes Docker secrets of apps once no task mounts them anymore.
+///
+/// Swarm refuses to remove a secret mounted by a running task, so removals are retried while
+/// a rolling update replaces the tasks.
+///
+/// # Arguments
+/// * `names` - The names of the secrets.
+pub async fn remove_unused_secrets(names: Vec<String>) {
+    for name in names {
+        for attempt in 1..=REMOVAL_ATTEMPTS {
+            match remove_docker_secret(&name) {
+                Ok(()) => break,
+                Err(e) if attempt == REMOVAL_ATTEMPTS => {
+                    warn!("Failed to remove secret {}: {}", name, e)
+                }
+                Err(_) => tokio::time::sleep(REMOVAL_RETRY_DELAY).await,
+            }
+        }
+    }
+}
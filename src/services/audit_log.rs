@@ -0,0 +1,224 @@
+use crate::auth::identify_caller;
+use crate::services::auto_redeploy::AUTO_REDEPLOY_INITIATOR;
+use crate::services::database::with_connection;
+use crate::services::events::{consume_events, Event};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, params_from_iter, Row, ToSql};
+use serde::Serialize;
+use tracing::warn;
+use warp::http::Method;
+
+/// Method of the entries recorded from the event bus rather than from a request.
+const EVENT_METHOD: &str = "EVENT";
+
+const AUDIT_COLUMNS: &str =
+    "id, timestamp, actor, method, route, path, app_name, status, remote_addr, user_agent, \
+     duration_ms";
+
+/// An API request that changed something, or a change Nephelios made on its own, as recorded
+/// in the audit log.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    /// The caller (e.g., "api-key:1a2b3c4d" or an OIDC subject), unknown without credentials.
+    pub actor: Option<String>,
+    /// The method of the request, `EVENT` for changes Nephelios made on its own.
+    pub method: String,
+    /// The route, with path parameters replaced (e.g., "/apps/{name}/scale"), or the type of
+    /// the event (e.g., "app_crashed").
+    pub route: String,
+    pub path: String,
+    /// The app the request targeted, when named in the path.
+    pub app_name: Option<String>,
+    /// The status code of the response, `0` for events.
+    pub status: u16,
+    pub remote_addr: Option<String>,
+    pub user_agent: Option<String>,
+    pub duration_ms: i64,
+}
+
+/// The filters of an audit log query, all optional.
+#[derive(Debug, Default)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub app_name: Option<String>,
+    pub method: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: u32,
+}
+
+/// Reads an entry from a row selected with `AUDIT_COLUMNS`.
+fn entry_from_row(row: &Row) -> rusqlite::Result<AuditEntry> {
+    Ok(AuditEntry {
+        id: row.get(0)?,
+        timestamp: row.get(1)?,
+        actor: row.get(2)?,
+        method: row.get(3)?,
+        route: row.get(4)?,
+        path: row.get(5)?,
+        app_name: row.get(6)?,
+        status: row.get(7)?,
+        remote_addr: row.get(8)?,
+        user_agent: row.get(9)?,
+        duration_ms: row.get(10)?,
+    })
+}
+
+/// Records a served request in the audit log, if it could have changed something.
+///
+/// Read-only requests (GET, HEAD, OPTIONS) are not recorded. Called for every request from
+/// the logging filter of the API, so no route needs to record its own entries.
+///
+/// # Arguments
+/// * `info` - The request and response details.
+/// * `route` - The route label of the request, see `route_label`.
+pub fn record_request(info: &warp::log::Info<'_>, route: &str) {
+    if [Method::GET, Method::HEAD, Method::OPTIONS].contains(info.method()) {
+        return;
+    }
+
+    let path = info.path().strip_prefix("/api/v1").unwrap_or(info.path());
+    let app_name = path
+        .strip_prefix("/apps/")
+        .and_then(|rest| rest.split('/').next())
+        .filter(|name| !name.is_empty() && *name != "bulk")
+        .map(str::to_string);
+    let header = |name: &str| {
+        info.request_headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+
+    let entry = AuditEntry {
+        id: 0,
+        timestamp: Utc::now(),
+        actor: identify_caller(header("authorization"), header("x-api-key")),
+        method: info.method().to_string(),
+        route: route.to_string(),
+        path: path.to_string(),
+        app_name,
+        status: info.status().as_u16(),
+        remote_addr: info.remote_addr().map(|addr| addr.ip().to_string()),
+        user_agent: info.user_agent().map(str::to_string),
+        duration_ms: info.elapsed().as_millis() as i64,
+    };
+    if let Err(e) = save_entry(&entry) {
+        warn!("Failed to write audit log entry: {}", e);
+    }
+}
+
+/// Builds the audit log entry of an event, if it is a change not requested through the API.
+///
+/// Changes requested through the API are recorded from their request, so only crashes and
+/// the deployments triggered by new commits are recorded from the event bus.
+fn event_entry(event: &Event) -> Option<AuditEntry> {
+    let actor = match event {
+        Event::AppCrashed { .. } => None,
+        Event::DeploymentStarted { initiator, .. } if initiator == AUTO_REDEPLOY_INITIATOR => {
+            Some(initiator.clone())
+        }
+        _ => return None,
+    };
+
+    let app_name = event.app_name()?.to_string();
+    Some(AuditEntry {
+        id: 0,
+        timestamp: Utc::now(),
+        actor,
+        method: EVENT_METHOD.to_string(),
+        route: event.kind().to_string(),
+        path: format!("/apps/{}", app_name),
+        app_name: Some(app_name),
+        status: 0,
+        remote_addr: None,
+        user_agent: None,
+        duration_ms: 0,
+    })
+}
+
+/// Records the changes published on the event bus that no API request made.
+pub async fn run_audit_recorder() {
+    consume_events("audit log", |event| {
+        if let Some(entry) = event_entry(&event) {
+            if let Err(e) = save_entry(&entry) {
+                warn!("Failed to write audit log entry: {}", e);
+            }
+        }
+        async {}
+    })
+    .await
+}
+
+/// Appends an entry to the audit log.
+///
+/// # Arguments
+/// * `entry` - The entry; its `id` is ignored and assigned by the database.
+pub fn save_entry(entry: &AuditEntry) -> Result<(), String> {
+    with_connection(|connection| {
+        connection.execute(
+            "INSERT INTO audit_log (timestamp, actor, method, route, path, app_name, status,
+                 remote_addr, user_agent, duration_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                entry.timestamp,
+                entry.actor,
+                entry.method,
+                entry.route,
+                entry.path,
+                entry.app_name,
+                entry.status,
+                entry.remote_addr,
+                entry.user_agent,
+                entry.duration_ms,
+            ],
+        )
+    })?;
+    Ok(())
+}
+
+/// Lists the audit log entries matching a filter, most recent first.
+///
+/// # Arguments
+/// * `filter` - The filters of the query.
+///
+/// # Returns
+/// * `Ok(Vec<AuditEntry>)` - The matching entries, at most `filter.limit`.
+/// * `Err(String)` - If the database could not be read.
+pub fn query_audit_log(filter: &AuditFilter) -> Result<Vec<AuditEntry>, String> {
+    let mut conditions = Vec::new();
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+    let mut add = |condition: &str, value: Box<dyn ToSql>| {
+        values.push(value);
+        conditions.push(format!("{} ?{}", condition, values.len()));
+    };
+
+    if let Some(actor) = &filter.actor {
+        add("actor =", Box::new(actor.clone()));
+    }
+    if let Some(app_name) = &filter.app_name {
+        add("app_name =", Box::new(app_name.clone()));
+    }
+    if let Some(method) = &filter.method {
+        add("method =", Box::new(method.to_ascii_uppercase()));
+    }
+    if let Some(since) = filter.since {
+        add("timestamp >=", Box::new(since));
+    }
+    if let Some(until) = filter.until {
+        add("timestamp <", Box::new(until));
+    }
+
+    let mut query = format!("SELECT {} FROM audit_log", AUDIT_COLUMNS);
+    if !conditions.is_empty() {
+        query.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+    }
+    query.push_str(&format!(" ORDER BY id DESC LIMIT {}", filter.limit));
+
+    with_connection(|connection| {
+        let mut statement = connection.prepare(&query)?;
+        let entries = statement.query_map(params_from_iter(values.iter()), entry_from_row)?;
+        entries.collect()
+    })
+}
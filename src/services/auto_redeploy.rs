@@ -0,0 +1,111 @@
+use crate::config::config;
+use crate::services::app_registry::{is_deployed, list_registered_apps};
+use crate::services::deployment::load_deploy_request;
+use crate::services::deployment_tracker::{create_deployment, run_deployment};
+use crate::services::helpers::credentials_helper::resolve_git_credentials;
+use crate::services::helpers::github_helper::remote_head;
+use crate::services::websocket::{send_deployment_status, DeploymentEvent};
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Initiator of the deployments triggered by new commits.
+pub const AUTO_REDEPLOY_INITIATOR: &str = "auto-redeploy";
+
+/// Returns the polling interval, from `features.auto_redeploy_interval` (in minutes).
+///
+/// # Returns
+/// * `Some(Duration)` if polling is enabled.
+/// * `None` if the interval is `0`.
+fn poll_interval() -> Option<Duration> {
+    Some(config().features.auto_redeploy_interval)
+        .filter(|minutes| *minutes > 0)
+        .map(|minutes| Duration::from_secs(minutes * 60))
+}
+
+/// Periodically redeploys apps whose tracked branch received new commits.
+///
+/// Only apps deployed with `auto_redeploy` enabled are polled. Does nothing unless
+/// `AUTO_REDEPLOY_INTERVAL` is set.
+pub async fn run_auto_redeploy() {
+    let Some(interval) = poll_interval() else {
+        return;
+    };
+
+    info!(
+        "🔁 Polling tracked repositories every {} minutes",
+        interval.as_secs() / 60
+    );
+
+    let mut ticker = tokio::time::interval(interval);
+    // The first tick completes immediately, skip it to let the stack start
+    ticker.tick().await;
+
+    loop {
+        ticker.tick().await;
+        if let Err(e) = poll_tracked_apps().await {
+            error!("❌ Auto-redeploy poll failed: {}", e);
+        }
+    }
+}
+
+/// Checks every auto-redeploy app once and redeploys those with a new remote commit.
+///
+/// # Returns
+/// * `Ok(())` if the apps could be listed.
+/// * `Err(String)` otherwise. Failures on a single app are logged and skipped.
+async fn poll_tracked_apps() -> Result<(), String> {
+    let apps = list_registered_apps()?;
+
+    for app in apps.iter().filter(|app| is_deployed(app)) {
+        let Some(request) = load_deploy_request(&app.app_name) else {
+            continue;
+        };
+        if !request.auto_redeploy {
+            continue;
+        }
+
+        let credentials = match resolve_git_credentials(None, request.deploy_key.as_deref()).await {
+            Ok(credentials) => credentials,
+            Err(e) => {
+                error!("❌ Auto-redeploy of {} skipped: {}", app.app_name, e);
+                continue;
+            }
+        };
+
+        let github_url = request.github_url.clone();
+        let git_ref = request.git_ref.clone();
+        let head = tokio::task::spawn_blocking(move || {
+            remote_head(&github_url, git_ref.as_deref(), &credentials)
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("Remote lookup task failed: {}", e)));
+
+        let head = match head {
+            Ok(head) => head,
+            Err(e) => {
+                error!("❌ Auto-redeploy of {} skipped: {}", app.app_name, e);
+                continue;
+            }
+        };
+
+        if app.commit_sha.as_deref() == Some(head.as_str()) {
+            continue;
+        }
+
+        info!("🔁 New commit {} detected for {}", head, app.app_name);
+        send_deployment_status(
+            &app.app_name,
+            DeploymentEvent::RedeployTriggered {
+                reason: "Redeploy triggered by new commit".to_string(),
+            },
+        );
+
+        let deployment_id =
+            create_deployment(&app.app_name, AUTO_REDEPLOY_INITIATOR, request.priority);
+        if let Err(e) = run_deployment(&deployment_id, request).await {
+            error!("❌ Redeployment of {} failed: {}", app.app_name, e);
+        }
+    }
+
+    Ok(())
+}
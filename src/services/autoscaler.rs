@@ -0,0 +1,231 @@
+use crate::config::config;
+use crate::services::app_registry::{is_deployed, list_registered_apps};
+use crate::services::deployment::{load_deploy_request, AutoscalingConfig, DeployRequest};
+use crate::services::deployment_tracker::active_deployment_id;
+use crate::services::events::{publish, Event};
+use crate::services::helpers::docker_helper::scale_service;
+use crate::services::helpers::lock_helper::try_lock_app;
+use crate::services::helpers::traefik_helper::{app_replicas, update_app_replicas};
+use crate::services::metrics_history::app_history;
+use crate::services::quotas::{enforce_quota, AppPlan, QuotaError};
+use crate::services::websocket::{send_deployment_status, DeploymentEvent};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Relative distance to the target CPU usage within which the replicas are left unchanged,
+/// so that small variations do not make an app flap.
+const TOLERANCE: f64 = 0.1;
+
+/// Returns the interval between two evaluations, from `features.autoscale_interval`.
+///
+/// # Returns
+/// * `Some(Duration)` if the autoscaler is enabled.
+/// * `None` if the interval is `0`.
+fn autoscale_interval() -> Option<Duration> {
+    Some(config().features.autoscale_interval)
+        .filter(|seconds| *seconds > 0)
+        .map(Duration::from_secs)
+}
+
+/// Returns the average CPU usage of the replicas of an app over a window, in percent of the
+/// CPU reservation of a replica.
+///
+/// # Arguments
+/// * `request` - The deploy request of the app.
+/// * `window` - How far back the metrics history is read.
+///
+/// # Returns
+/// * `Some(f64)` containing the usage.
+/// * `None` if no metrics of the app were collected over the window.
+fn cpu_utilization(request: &DeployRequest, window: Duration) -> Option<f64> {
+    let samples = app_history(&request.app_name, window);
+    let containers: usize = samples.iter().map(|sample| sample.containers).sum();
+    if containers == 0 {
+        return None;
+    }
+
+    let cpu_percent: f64 = samples.iter().map(|sample| sample.cpu_percent).sum();
+    let (reserved_cpus, _) = request.resources.reservations();
+    let replica_cpus = if reserved_cpus > 0.0 {
+        reserved_cpus
+    } else {
+        1.0
+    };
+    Some(cpu_percent / containers as f64 / replica_cpus)
+}
+
+/// Returns the replicas that bring the CPU usage of an app back to its target.
+///
+/// # Arguments
+/// * `autoscaling` - The autoscaling settings of the app.
+/// * `replicas` - The current replicas.
+/// * `utilization` - The CPU usage, `None` to only keep the replicas within the bounds.
+fn desired_replicas(
+    autoscaling: &AutoscalingConfig,
+    replicas: u32,
+    utilization: Option<f64>,
+) -> u32 {
+    let desired = match utilization {
+        Some(utilization) => {
+            let ratio = utilization / f64::from(autoscaling.target_cpu);
+            if (ratio - 1.0).abs() <= TOLERANCE {
+                replicas
+            } else {
+                (f64::from(replicas) * ratio).ceil() as u32
+            }
+        }
+        None => replicas,
+    };
+    desired.clamp(autoscaling.min_replicas, autoscaling.max_replicas)
+}
+
+/// Scales the apps with autoscaling in the background, following their CPU usage.
+///
+/// Every `AUTOSCALE_INTERVAL` seconds, the average CPU usage of each app over the interval is
+/// compared with its target, and its replicas are changed in proportion, within its bounds.
+/// An app is scaled up right away, but not scaled down during `AUTOSCALE_COOLDOWN` seconds
+/// after a change. Stopped apps and apps being deployed or restored are left alone.
+pub async fn run_autoscaler() {
+    let Some(interval) = autoscale_interval() else {
+        return;
+    };
+    let cooldown = Duration::from_secs(config().features.autoscale_cooldown);
+
+    info!(
+        "📈 Evaluating autoscaled apps every {} seconds",
+        interval.as_secs()
+    );
+
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last_scaled: HashMap<String, Instant> = HashMap::new();
+
+    loop {
+        ticker.tick().await;
+        if let Err(e) = autoscale_apps(interval, cooldown, &mut last_scaled).await {
+            error!("❌ Autoscaling failed: {}", e);
+        }
+    }
+}
+
+/// Evaluates every app with autoscaling once, and scales those away from their target.
+///
+/// # Arguments
+/// * `window` - How far back the CPU usage is averaged.
+/// * `cooldown` - How long an app is not scaled down after a change.
+/// * `last_scaled` - When each app was last scaled by the autoscaler.
+///
+/// # Returns
+/// * `Ok(())` if the apps could be listed.
+/// * `Err(String)` otherwise. Failures on a single app are logged and skipped.
+async fn autoscale_apps(
+    window: Duration,
+    cooldown: Duration,
+    last_scaled: &mut HashMap<String, Instant>,
+) -> Result<(), String> {
+    let apps = list_registered_apps()?;
+
+    for app in apps.iter().filter(|app| is_deployed(app)) {
+        let Some(request) = load_deploy_request(&app.app_name) else {
+            continue;
+        };
+        let Some(autoscaling) = &request.autoscaling else {
+            continue;
+        };
+        if active_deployment_id(&app.app_name).is_some() {
+            continue;
+        }
+        // Held until the replicas are written, so a deployment or a volume restore does not
+        // run in between.
+        let _lock = match try_lock_app(&app.app_name) {
+            Ok(Some(lock)) => lock,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Failed to lock {}: {}", app.app_name, e);
+                continue;
+            }
+        };
+
+        let replicas = match app_replicas(&app.app_name) {
+            Ok(replicas) => replicas.unwrap_or(1),
+            Err(e) => {
+                warn!("Failed to read replicas of {}: {}", app.app_name, e);
+                continue;
+            }
+        };
+        if replicas == 0 {
+            continue;
+        }
+
+        let utilization = cpu_utilization(&request, window);
+        let desired = desired_replicas(autoscaling, replicas, utilization);
+        if desired == replicas {
+            continue;
+        }
+        if desired < replicas
+            && last_scaled
+                .get(&app.app_name)
+                .is_some_and(|scaled| scaled.elapsed() < cooldown)
+        {
+            continue;
+        }
+
+        let plan = AppPlan {
+            builds: false,
+            replicas: desired,
+            ..AppPlan::deploy(&request)
+        };
+        if let Err(QuotaError::Exceeded(e) | QuotaError::Failed(e)) = enforce_quota(None, &plan) {
+            warn!(
+                "Not scaling {} to {} replicas: {}",
+                app.app_name, desired, e
+            );
+            continue;
+        }
+
+        if let Err(e) = scale_app(&app.app_name, desired).await {
+            error!("❌ Autoscaling of {} failed: {}", app.app_name, e);
+            continue;
+        }
+        last_scaled.insert(app.app_name.clone(), Instant::now());
+
+        let cpu_percent = utilization.unwrap_or_default();
+        info!(
+            "📈 Autoscaled {} from {} to {} replicas at {:.0}% CPU",
+            app.app_name, replicas, desired, cpu_percent
+        );
+        send_deployment_status(
+            &app.app_name,
+            DeploymentEvent::Autoscaled {
+                from: replicas,
+                to: desired,
+                cpu_percent,
+            },
+        );
+        publish(Event::AppScaled {
+            app_name: app.app_name.clone(),
+            replicas: desired,
+        });
+    }
+
+    last_scaled.retain(|_, scaled| scaled.elapsed() < cooldown);
+    Ok(())
+}
+
+/// Sets the replicas of an app in the stack file and on its Swarm service.
+///
+/// The caller holds the lock of the app.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `replicas` - The new number of replicas.
+///
+/// # Returns
+/// * `Ok(())` if the app was scaled.
+/// * `Err(String)` if the stack file or the service could not be updated.
+pub async fn scale_app(app_name: &str, replicas: u32) -> Result<(), String> {
+    update_app_replicas(app_name, replicas)
+        .map_err(|e| format!("Failed to update replicas in the stack file: {}", e))?;
+    scale_service(app_name, replicas).await
+}
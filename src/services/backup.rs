@@ -0,0 +1,262 @@
+use crate::requests::existing_app_name_error;
+use crate::services::alerting::{load_alert_rules, replace_alert_rules, AlertRule};
+use crate::services::app_registry::{list_registered_apps, register_app};
+use crate::services::deployment::{load_deploy_request, save_deploy_request, DeployRequest};
+use crate::services::deployment_history::{load_history, save_deployment};
+use crate::services::deployment_tracker::Deployment;
+use crate::services::helpers::docker_helper::{
+    deploy_nephelios_stack, docker_secret_exists, AppInfo,
+};
+use crate::services::helpers::stack_helper::{
+    load_stack, replace_stack, stack_secret_names, StackFile, STACK_FILE,
+};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use tar::{Archive, Builder, Header};
+use tracing::{info, warn};
+
+/// Version of the backup archive layout, bumped when it changes incompatibly.
+const BACKUP_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const STACK_ENTRY: &str = "nephelios.yml";
+const REGISTRY_ENTRY: &str = "registry.json";
+const ALERT_RULES_ENTRY: &str = "alert_rules.json";
+
+/// Describes a backup archive.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    /// The version of Nephelios that created the backup.
+    pub nephelios_version: String,
+    pub apps: Vec<String>,
+    /// The Docker secrets the stack refers to. Their values are not part of the backup and
+    /// must be recreated on the restored node.
+    pub secrets: Vec<String>,
+}
+
+/// The outcome of a restore.
+#[derive(Debug, Serialize)]
+pub struct RestoreReport {
+    pub apps: Vec<String>,
+    pub deployments: usize,
+    pub alert_rules: usize,
+    /// The secrets referenced by the stack that do not exist on this node.
+    pub missing_secrets: Vec<String>,
+    /// Whether the stack was deployed, which waits for the missing secrets.
+    pub stack_deployed: bool,
+}
+
+/// Appends a file to the archive being built.
+fn append_entry(builder: &mut Builder<Vec<u8>>, path: &str, data: &[u8]) -> Result<(), String> {
+    let mut header = Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, path, data)
+        .map_err(|e| format!("Failed to add {} to the backup: {}", path, e))
+}
+
+/// Serializes a value and appends it to the archive being built.
+fn append_json<T: Serialize>(
+    builder: &mut Builder<Vec<u8>>,
+    path: &str,
+    value: &T,
+) -> Result<(), String> {
+    let data = serde_json::to_vec_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", path, e))?;
+    append_entry(builder, path, &data)
+}
+
+/// Builds a backup archive of the state of this node.
+///
+/// The tar archive holds the stack file, the app registry, the stored deploy request and
+/// deployment history of each app, and the alert rules. Secrets are only listed by name in
+/// the manifest.
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The tar archive.
+/// * `Err(String)` - If part of the state could not be read.
+pub fn create_backup() -> Result<Vec<u8>, String> {
+    let stack = load_stack().map_err(|e| format!("Failed to read the stack file: {}", e))?;
+    let stack_content =
+        fs::read(STACK_FILE).map_err(|e| format!("Failed to read the stack file: {}", e))?;
+    let apps = list_registered_apps()?;
+
+    let manifest = BackupManifest {
+        version: BACKUP_VERSION,
+        created_at: Utc::now(),
+        nephelios_version: env!("CARGO_PKG_VERSION").to_string(),
+        apps: apps.iter().map(|app| app.app_name.clone()).collect(),
+        secrets: stack_secret_names(&stack),
+    };
+
+    let mut builder = Builder::new(Vec::new());
+    append_json(&mut builder, MANIFEST_ENTRY, &manifest)?;
+    append_entry(&mut builder, STACK_ENTRY, &stack_content)?;
+    append_json(&mut builder, REGISTRY_ENTRY, &apps)?;
+    append_json(&mut builder, ALERT_RULES_ENTRY, &load_alert_rules()?)?;
+    for app in &apps {
+        if let Some(request) = load_deploy_request(&app.app_name) {
+            append_json(
+                &mut builder,
+                &format!("apps/{}.json", app.app_name),
+                &request.encrypted()?,
+            )?;
+        }
+        append_json(
+            &mut builder,
+            &format!("history/{}.json", app.app_name),
+            &load_history(&app.app_name)?,
+        )?;
+    }
+
+    builder
+        .into_inner()
+        .map_err(|e| format!("Failed to write the backup: {}", e))
+}
+
+/// Reads the files of a backup archive, by path.
+fn read_archive(archive: &[u8]) -> Result<HashMap<String, Vec<u8>>, String> {
+    let mut files = HashMap::new();
+    let mut archive = Archive::new(Cursor::new(archive));
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Invalid backup archive: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Invalid backup archive: {}", e))?;
+        let path = entry
+            .path()
+            .map_err(|e| format!("Invalid backup archive: {}", e))?
+            .to_string_lossy()
+            .into_owned();
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read {} from the backup: {}", path, e))?;
+        files.insert(path, data);
+    }
+    Ok(files)
+}
+
+/// Parses a JSON file of a backup archive.
+fn parse_entry<T: DeserializeOwned>(path: &str, data: &[u8]) -> Result<T, String> {
+    serde_json::from_slice(data).map_err(|e| format!("Invalid {} in the backup: {}", path, e))
+}
+
+/// Rebuilds the state of this node from a backup archive, then deploys the stack.
+///
+/// The stack file, the app registry, the deploy requests, the deployment history and the
+/// alert rules are overwritten with the content of the backup. Apps are only restored from
+/// the stack file, their images must still be available in the registry, or the apps
+/// redeployed. The stack is not deployed while secrets it refers to are missing; it is
+/// deployed on the next start of Nephelios, or by restoring again, once they are created.
+///
+/// # Arguments
+/// * `archive` - A tar archive built by `create_backup`.
+///
+/// # Returns
+/// * `Ok(RestoreReport)` - What was restored.
+/// * `Err(String)` - If the archive is invalid or the state could not be written.
+pub fn restore_backup(archive: &[u8]) -> Result<RestoreReport, String> {
+    let files = read_archive(archive)?;
+    let entry = |path: &str| {
+        files
+            .get(path)
+            .ok_or_else(|| format!("The backup has no {}", path))
+    };
+
+    let manifest: BackupManifest = parse_entry(MANIFEST_ENTRY, entry(MANIFEST_ENTRY)?)?;
+    if manifest.version > BACKUP_VERSION {
+        return Err(format!(
+            "Backup version {} is not supported, at most {} is",
+            manifest.version, BACKUP_VERSION
+        ));
+    }
+
+    let stack: StackFile = serde_yaml::from_slice(entry(STACK_ENTRY)?)
+        .map_err(|e| format!("Invalid {} in the backup: {}", STACK_ENTRY, e))?;
+    let apps: Vec<AppInfo> = parse_entry(REGISTRY_ENTRY, entry(REGISTRY_ENTRY)?)?;
+    if let Some(error) = apps
+        .iter()
+        .find_map(|app| existing_app_name_error(&app.app_name))
+    {
+        return Err(format!(
+            "Invalid {} in the backup: {}",
+            REGISTRY_ENTRY, error
+        ));
+    }
+    let alert_rules: Vec<AlertRule> = match files.get(ALERT_RULES_ENTRY) {
+        Some(data) => parse_entry(ALERT_RULES_ENTRY, data)?,
+        None => Vec::new(),
+    };
+
+    replace_stack(&stack).map_err(|e| format!("Failed to write the stack file: {}", e))?;
+    replace_alert_rules(&alert_rules)?;
+
+    let mut deployments = 0;
+    for app in &apps {
+        register_app(app)?;
+
+        let request_path = format!("apps/{}.json", app.app_name);
+        if let Some(data) = files.get(&request_path) {
+            let request = parse_entry::<DeployRequest>(&request_path, data)?.decrypted()?;
+            if request.app_name == app.app_name {
+                save_deploy_request(&request)?;
+            } else {
+                warn!(
+                    "Skipping {}: it belongs to {}",
+                    request_path, request.app_name
+                );
+            }
+        }
+
+        let history_path = format!("history/{}.json", app.app_name);
+        if let Some(data) = files.get(&history_path) {
+            let history: Vec<Deployment> = parse_entry(&history_path, data)?;
+            for deployment in history
+                .iter()
+                .filter(|deployment| deployment.app_name == app.app_name)
+            {
+                save_deployment(deployment)?;
+                deployments += 1;
+            }
+        }
+    }
+
+    let mut missing_secrets = Vec::new();
+    for secret in stack_secret_names(&stack) {
+        if !docker_secret_exists(&secret)? {
+            missing_secrets.push(secret);
+        }
+    }
+    let stack_deployed = missing_secrets.is_empty();
+    if stack_deployed {
+        deploy_nephelios_stack()?;
+    } else {
+        warn!(
+            "Restored stack refers to missing secrets, not deploying it: {}",
+            missing_secrets.join(", ")
+        );
+    }
+    info!(
+        "♻️ Restored {} app(s) from a backup of {}",
+        apps.len(),
+        manifest.created_at
+    );
+
+    Ok(RestoreReport {
+        apps: apps.into_iter().map(|app| app.app_name).collect(),
+        deployments,
+        alert_rules: alert_rules.len(),
+        missing_secrets,
+        stack_deployed,
+    })
+}
@@ -0,0 +1,605 @@
+use crate::services::deployment::{release_image, DeployRequest};
+use crate::services::helpers::docker_helper::remove_service;
+use crate::services::helpers::stack_helper::{load_stack, update_stack, Deploy, Service};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value as YamlValue;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+use tracing::{info, warn};
+
+/// Compose files looked for at the root of a repository, in order of precedence.
+pub const COMPOSE_FILES: &[&str] = &[
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+
+/// Label holding the app a compose service belongs to.
+const COMPOSE_APP_LABEL: &str = "com.nephelios.compose.app";
+
+/// Label holding the name of a compose service in the compose file.
+const COMPOSE_SERVICE_LABEL: &str = "com.nephelios.compose.service";
+
+/// Maximum length of a Swarm service name, which compose services get prefixed with
+/// `nephelios_`.
+const MAX_SERVICE_NAME_LENGTH: usize = 63;
+
+/// Name of the service routed by Traefik, when the compose file has one.
+const WEB_SERVICE: &str = "web";
+
+/// The command of a compose service, in the shell or the exec form.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ComposeCommand {
+    Shell(String),
+    Exec(Vec<String>),
+}
+
+/// A service of an app deployed from a compose file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComposeService {
+    pub name: String,
+    /// The image the service runs, `None` if it is built from the repository.
+    #[serde(default)]
+    pub image: Option<String>,
+    /// The build context, relative to the root of the repository.
+    #[serde(default)]
+    pub context: Option<String>,
+    /// The Dockerfile, relative to the build context.
+    #[serde(default)]
+    pub dockerfile: Option<String>,
+    #[serde(default)]
+    pub command: Option<ComposeCommand>,
+    /// The variables set in the compose file, overridden by the environment of the app.
+    #[serde(default)]
+    pub environment: BTreeMap<String, String>,
+    /// The replicas of the service, ignored for the web service which is scaled with the app.
+    pub replicas: u32,
+}
+
+/// The services of an app deployed from a compose file.
+///
+/// The web service is deployed as the app service, with its routing, resources and
+/// volumes. The other services run next to it on the overlay network, without routing, and
+/// are reachable as `<app>-<service>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComposeApp {
+    /// The compose file, relative to the root of the repository.
+    pub file: String,
+    /// The service routed by Traefik.
+    pub web: String,
+    pub services: Vec<ComposeService>,
+}
+
+impl ComposeApp {
+    /// Returns the web service.
+    pub fn web_service(&self) -> Option<&ComposeService> {
+        self.services
+            .iter()
+            .find(|service| service.name == self.web)
+    }
+
+    /// Returns the services deployed next to the app service.
+    pub fn workers(&self) -> impl Iterator<Item = &ComposeService> {
+        self.services
+            .iter()
+            .filter(|service| service.name != self.web)
+    }
+
+    /// Returns the name of the image built for a service, the app name for the web service.
+    pub fn image_name(&self, app_name: &str, service: &ComposeService) -> String {
+        if service.name == self.web {
+            app_name.to_string()
+        } else {
+            compose_service_name(app_name, &service.name)
+        }
+    }
+}
+
+/// The parts of a compose file Nephelios deploys.
+#[derive(Deserialize)]
+struct ComposeFile {
+    #[serde(default)]
+    services: IndexMap<String, ComposeFileService>,
+}
+
+/// A service of a compose file.
+#[derive(Deserialize)]
+struct ComposeFileService {
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    build: Option<ComposeBuild>,
+    #[serde(default)]
+    command: Option<ComposeCommand>,
+    #[serde(default)]
+    environment: Option<ComposeEnvironment>,
+    #[serde(default)]
+    ports: Vec<YamlValue>,
+    #[serde(default)]
+    deploy: Option<ComposeDeploy>,
+}
+
+/// The `build` section of a compose service, a context or its long form.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ComposeBuild {
+    Context(String),
+    Options {
+        #[serde(default)]
+        context: Option<String>,
+        #[serde(default)]
+        dockerfile: Option<String>,
+    },
+}
+
+/// The `environment` section of a compose service, a mapping or a list of `KEY=value`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ComposeEnvironment {
+    Map(IndexMap<String, Option<YamlValue>>),
+    List(Vec<String>),
+}
+
+/// The `deploy` section of a compose service.
+#[derive(Deserialize)]
+struct ComposeDeploy {
+    #[serde(default)]
+    replicas: Option<u32>,
+}
+
+/// Returns the stack service name of a compose service of an app (e.g., `my-app-worker`).
+pub fn compose_service_name(app_name: &str, service: &str) -> String {
+    format!("{}-{}", app_name, service)
+}
+
+/// Checks that a path of a compose file stays inside the repository.
+fn check_relative_path(path: &str, field: &str, service: &str) -> Result<(), String> {
+    let inside = Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if inside {
+        Ok(())
+    } else {
+        Err(format!(
+            "The {} of service {} must be a relative path inside the repository",
+            field, service
+        ))
+    }
+}
+
+/// Reads the variables of a compose service, skipping the ones without a value.
+fn read_environment(environment: Option<ComposeEnvironment>) -> BTreeMap<String, String> {
+    match environment {
+        None => BTreeMap::new(),
+        Some(ComposeEnvironment::Map(variables)) => variables
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let value = match value? {
+                    YamlValue::String(value) => value,
+                    YamlValue::Bool(value) => value.to_string(),
+                    YamlValue::Number(value) => value.to_string(),
+                    _ => return None,
+                };
+                Some((name, value))
+            })
+            .collect(),
+        Some(ComposeEnvironment::List(variables)) => variables
+            .into_iter()
+            .filter_map(|variable| {
+                let (name, value) = variable.split_once('=')?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect(),
+    }
+}
+
+/// Reads and validates a service of a compose file.
+fn read_service(
+    repo_dir: &Path,
+    name: String,
+    service: ComposeFileService,
+) -> Result<ComposeService, String> {
+    if name.is_empty()
+        || name.starts_with('-')
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(format!(
+            "Service {:?} may only contain lowercase letters, digits and dashes",
+            name
+        ));
+    }
+
+    let (context, dockerfile) = match service.build {
+        Some(ComposeBuild::Context(context)) => (Some(context), None),
+        Some(ComposeBuild::Options {
+            context,
+            dockerfile,
+        }) => (Some(context.unwrap_or_else(|| ".".to_string())), dockerfile),
+        None => (None, None),
+    };
+    if let Some(context) = &context {
+        check_relative_path(context, "build context", &name)?;
+        if !repo_dir.join(context).is_dir() {
+            return Err(format!(
+                "The build context {} of service {} is not a directory",
+                context, name
+            ));
+        }
+    } else if service.image.is_none() {
+        return Err(format!("Service {} has neither an image nor a build", name));
+    }
+    if let Some(dockerfile) = &dockerfile {
+        check_relative_path(dockerfile, "Dockerfile", &name)?;
+    }
+
+    Ok(ComposeService {
+        // Built services run the image built from the repository
+        image: service.image.filter(|_| context.is_none()),
+        context,
+        dockerfile,
+        command: service.command,
+        environment: read_environment(service.environment),
+        replicas: service
+            .deploy
+            .and_then(|deploy| deploy.replicas)
+            .unwrap_or(1),
+        name,
+    })
+}
+
+/// Reads the compose file at the root of a cloned repository, if it has one.
+///
+/// The web service is the service named `web`, otherwise the first service publishing
+/// ports, otherwise the first service.
+///
+/// # Arguments
+/// * `repo_dir` - The directory of the cloned repository.
+///
+/// # Returns
+/// * `Ok(Some(ComposeApp))` - The services of the compose file.
+/// * `Ok(None)` - If the repository has no compose file.
+/// * `Err(String)` - If the compose file is invalid.
+pub fn load_compose(repo_dir: &Path) -> Result<Option<ComposeApp>, String> {
+    let Some(file) = COMPOSE_FILES
+        .iter()
+        .find(|file| repo_dir.join(file).is_file())
+    else {
+        return Ok(None);
+    };
+    let content = fs::read_to_string(repo_dir.join(file))
+        .map_err(|e| format!("Failed to read {}: {}", file, e))?;
+    let compose: ComposeFile =
+        serde_yaml::from_str(&content).map_err(|e| format!("Invalid {}: {}", file, e))?;
+    if compose.services.is_empty() {
+        return Err(format!("{} has no services", file));
+    }
+
+    let web = compose
+        .services
+        .get_key_value(WEB_SERVICE)
+        .or_else(|| {
+            compose
+                .services
+                .iter()
+                .find(|(_, service)| !service.ports.is_empty())
+        })
+        .or_else(|| compose.services.first())
+        .map(|(name, _)| name.clone())
+        .unwrap_or_default();
+
+    let services = compose
+        .services
+        .into_iter()
+        .map(|(name, service)| read_service(repo_dir, name, service))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Some(ComposeApp {
+        file: file.to_string(),
+        web,
+        services,
+    }))
+}
+
+/// Returns the value of a `key=value` deploy label of a service.
+fn label_value<'a>(service: &'a Service, key: &str) -> Option<&'a str> {
+    service
+        .deploy
+        .as_ref()?
+        .labels
+        .iter()
+        .find_map(|label| label.strip_prefix(key)?.strip_prefix('='))
+}
+
+/// Whether a stack service is a compose service of an app.
+fn is_app_service(service: &Service, app_name: &str) -> bool {
+    label_value(service, COMPOSE_APP_LABEL) == Some(app_name)
+}
+
+/// Escapes `$` in a value of the compose file, which `docker stack deploy` would otherwise
+/// interpolate from the environment of the server.
+fn escape_variables(value: &str) -> String {
+    value.replace('$', "$$")
+}
+
+/// Builds the stack file value of the command of a service, with its variables escaped.
+fn command_value(command: &ComposeCommand) -> Result<YamlValue, serde_yaml::Error> {
+    let escaped = match command {
+        ComposeCommand::Shell(command) => ComposeCommand::Shell(escape_variables(command)),
+        ComposeCommand::Exec(args) => {
+            ComposeCommand::Exec(args.iter().map(|arg| escape_variables(arg)).collect())
+        }
+    };
+    serde_yaml::to_value(escaped)
+}
+
+/// Builds the environment mapping of a service: its compose variables, overridden by the
+/// variables of the app.
+fn environment_mapping(
+    service: &ComposeService,
+    app_env: &BTreeMap<String, String>,
+) -> Option<YamlValue> {
+    let mut environment = service.environment.clone();
+    environment.extend(app_env.clone());
+    if environment.is_empty() {
+        return None;
+    }
+    Some(YamlValue::Mapping(
+        environment
+            .into_iter()
+            .map(|(name, value)| {
+                (
+                    YamlValue::String(name),
+                    YamlValue::String(escape_variables(&value)),
+                )
+            })
+            .collect(),
+    ))
+}
+
+/// Sets or removes a key of the extra settings of a stack service.
+fn set_extra(service: &mut Service, key: &str, value: Option<YamlValue>) {
+    let key = YamlValue::String(key.to_string());
+    match value {
+        Some(value) => {
+            service.extra.insert(key, value);
+        }
+        None => {
+            service.extra.remove(&key);
+        }
+    }
+}
+
+/// Returns the image of each compose service of an app besides the web service, by service
+/// name: the image of the compose file, or the image built for the release.
+///
+/// # Arguments
+/// * `request` - The deploy request of the app, with the compose services of the release.
+/// * `release` - The release tag of the images built for the services.
+pub fn compose_images(request: &DeployRequest, release: &str) -> BTreeMap<String, String> {
+    let Some(compose) = &request.compose else {
+        return BTreeMap::new();
+    };
+    compose
+        .workers()
+        .map(|worker| {
+            let image = worker.image.clone().unwrap_or_else(|| {
+                release_image(
+                    &compose_service_name(&request.app_name, &worker.name),
+                    release,
+                )
+            });
+            (worker.name.clone(), image)
+        })
+        .collect()
+}
+
+/// Writes the compose services of an app to the stack file.
+///
+/// The command of the web service is set on the app service, which must be in the stack
+/// file. The other services are added or replaced with their image, and the services removed
+/// from the compose file are removed from the stack file. Apps without a compose file lose
+/// their compose services.
+///
+/// # Arguments
+/// * `request` - The deploy request of the app, with the compose services of the release.
+/// * `images` - The images of the services, as listed by `compose_images` and verified.
+///
+/// # Returns
+/// * `Ok(Vec<String>)` - The stack services removed, to be removed from the Swarm.
+/// * `Err(String)` - If a service name is taken or the stack file could not be updated.
+pub fn update_compose_services(
+    request: &DeployRequest,
+    images: &BTreeMap<String, String>,
+) -> Result<Vec<String>, String> {
+    let app_name = request.app_name.as_str();
+    let mut services = IndexMap::new();
+    let mut web_command = None;
+    if let Some(compose) = &request.compose {
+        web_command = compose
+            .web_service()
+            .and_then(|service| service.command.as_ref())
+            .map(command_value)
+            .transpose()
+            .map_err(|e| format!("Invalid command: {}", e))?;
+
+        for worker in compose.workers() {
+            let name = compose_service_name(app_name, &worker.name);
+            if "nephelios_".len() + name.len() > MAX_SERVICE_NAME_LENGTH {
+                return Err(format!(
+                    "The name of {} is too long for service {}",
+                    app_name, worker.name
+                ));
+            }
+            let image = images
+                .get(&worker.name)
+                .cloned()
+                .ok_or_else(|| format!("No image for service {}", worker.name))?;
+            let mut service = Service {
+                image: Some(image),
+                deploy: Some(Deploy {
+                    mode: Some("replicated".to_string()),
+                    replicas: Some(worker.replicas),
+                    labels: vec![
+                        format!("{}={}", COMPOSE_APP_LABEL, app_name),
+                        format!("{}={}", COMPOSE_SERVICE_LABEL, worker.name),
+                        "traefik.enable=false".to_string(),
+                    ],
+                    ..Default::default()
+                }),
+                networks: vec!["nephelios_overlay".to_string()],
+                ..Default::default()
+            };
+            set_extra(
+                &mut service,
+                "environment",
+                environment_mapping(worker, &request.env),
+            );
+            let command = worker
+                .command
+                .as_ref()
+                .map(command_value)
+                .transpose()
+                .map_err(|e| format!("Invalid command of service {}: {}", worker.name, e))?;
+            set_extra(&mut service, "command", command);
+            services.insert(name, service);
+        }
+    }
+
+    let removed = update_stack(|stack| {
+        for name in services.keys() {
+            if let Some(existing) = stack.services.get(name) {
+                if !is_app_service(existing, app_name) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("Service {} already exists", name),
+                    ));
+                }
+            }
+        }
+
+        let app_service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Application {} not found in the stack file", app_name),
+            )
+        })?;
+        set_extra(app_service, "command", web_command);
+
+        let removed: Vec<String> = stack
+            .services
+            .iter()
+            .filter(|(name, service)| {
+                is_app_service(service, app_name) && !services.contains_key(*name)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &removed {
+            stack.services.shift_remove(name);
+        }
+        stack.services.extend(services);
+        Ok(removed)
+    })
+    .map_err(|e| {
+        format!(
+            "Failed to update the compose services of {}: {}",
+            app_name, e
+        )
+    })?;
+
+    for name in &removed {
+        info!("🧩 Removed compose service {} of {}", name, app_name);
+    }
+    Ok(removed)
+}
+
+/// Sets the environment of the compose services of an app, after its variables changed.
+///
+/// # Arguments
+/// * `request` - The deploy request of the app, with its new variables.
+pub fn update_compose_environment(request: &DeployRequest) -> Result<(), String> {
+    let Some(compose) = &request.compose else {
+        return Ok(());
+    };
+    update_stack(|stack| {
+        for worker in compose.workers() {
+            let name = compose_service_name(&request.app_name, &worker.name);
+            if let Some(service) = stack.services.get_mut(&name) {
+                set_extra(
+                    service,
+                    "environment",
+                    environment_mapping(worker, &request.env),
+                );
+            }
+        }
+        Ok(())
+    })
+    .map_err(|e| {
+        format!(
+            "Failed to update the compose services of {}: {}",
+            request.app_name, e
+        )
+    })
+}
+
+/// Lists the stack services deployed from the compose file of an app, besides the app service.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+pub fn list_compose_services(app_name: &str) -> Result<Vec<String>, String> {
+    let stack = load_stack().map_err(|e| format!("Failed to read the stack file: {}", e))?;
+    Ok(stack
+        .services
+        .iter()
+        .filter(|(_, service)| is_app_service(service, app_name))
+        .map(|(name, _)| name.clone())
+        .collect())
+}
+
+/// Removes stack services from the Swarm, once they were removed from the stack file.
+///
+/// `docker stack deploy` keeps the services that are no longer in the stack file.
+pub async fn remove_compose_services(names: &[String]) {
+    for name in names {
+        if let Err(e) = remove_service(name).await {
+            warn!("Failed to remove compose service {}: {}", name, e);
+        }
+    }
+}
+
+/// Removes the compose services of an application, e.g. when the app is removed.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(())` - If every compose service was removed.
+/// * `Err(String)` - If the stack file or a service could not be updated.
+pub async fn remove_app_compose_services(app_name: &str) -> Result<(), String> {
+    let names: HashSet<String> = list_compose_services(app_name)?.into_iter().collect();
+    if names.is_empty() {
+        return Ok(());
+    }
+    update_stack(|stack| {
+        stack.services.retain(|name, _| !names.contains(name));
+        Ok(())
+    })
+    .map_err(|e| {
+        format!(
+            "Failed to remove the compose services of {}: {}",
+            app_name, e
+        )
+    })?;
+
+    for name in &names {
+        remove_service(name).await?;
+    }
+    info!("🧩 Removed the compose services of {}", app_name);
+    Ok(())
+}
@@ -0,0 +1,645 @@
+use crate::services::database::{enum_column, with_connection};
+use crate::services::helpers::docker_helper::{
+    create_job_service, exec_in_app, remove_service, stream_service_logs, wait_for_job, ExecEvent,
+};
+use crate::services::helpers::stack_helper::load_stack;
+use crate::services::jobs::{job_image, job_service_name, truncate_output, MAX_OUTPUT_LENGTH};
+use chrono::{DateTime, Utc};
+use croner::Cron;
+use futures::StreamExt;
+use lazy_static::lazy_static;
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Time between two checks of the schedules.
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Number of runs kept in the history of each cron job; the oldest ones are deleted first.
+const MAX_RUNS_PER_JOB: usize = 50;
+
+/// Number of log lines read from the task of a one-shot job.
+const MAX_JOB_LOG_LINES: u32 = 1000;
+
+/// Error recorded on runs that were still running when Nephelios stopped.
+const INTERRUPTED_ERROR: &str = "Interrupted by a restart of Nephelios";
+
+const CRON_JOB_COLUMNS: &str =
+    "id, app_name, schedule, command, mode, timeout_seconds, enabled, created_at, updated_at";
+
+const CRON_RUN_COLUMNS: &str =
+    "id, job_id, app_name, trigger, status, started_at, finished_at, exit_code, output, error";
+
+/// How a cron job runs its command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CronMode {
+    /// In a one-shot Swarm task of the image of the app, with the environment of the app.
+    #[default]
+    Job,
+    /// In a running container of the app, like `POST /apps/{name}/exec`.
+    Exec,
+}
+
+impl CronMode {
+    /// The name of the mode, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CronMode::Job => "job",
+            CronMode::Exec => "exec",
+        }
+    }
+}
+
+/// What started a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CronTrigger {
+    Schedule,
+    Manual,
+}
+
+impl CronTrigger {
+    /// The name of the trigger, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CronTrigger::Schedule => "schedule",
+            CronTrigger::Manual => "manual",
+        }
+    }
+}
+
+/// The state of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CronRunStatus {
+    Running,
+    /// The command exited with code 0.
+    Succeeded,
+    /// The command exited with another code, or could not be started.
+    Failed,
+    /// The command was still running after the timeout of the job.
+    TimedOut,
+}
+
+impl CronRunStatus {
+    /// The name of the status, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CronRunStatus::Running => "running",
+            CronRunStatus::Succeeded => "succeeded",
+            CronRunStatus::Failed => "failed",
+            CronRunStatus::TimedOut => "timed_out",
+        }
+    }
+}
+
+/// The settings of a cron job, as given through the API.
+#[derive(Debug, Clone)]
+pub struct CronJobSettings {
+    /// A five-field cron expression or a nickname such as `@daily`, evaluated in UTC.
+    pub schedule: String,
+    /// The command, run with `sh -c`.
+    pub command: String,
+    pub mode: CronMode,
+    pub timeout_seconds: u64,
+    pub enabled: bool,
+}
+
+/// A command run on a schedule for an app.
+#[derive(Debug, Clone, Serialize)]
+pub struct CronJob {
+    pub id: String,
+    pub app_name: String,
+    pub schedule: String,
+    pub command: String,
+    pub mode: CronMode,
+    /// How long the command may run, in seconds.
+    pub timeout_seconds: u64,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// When the job runs next, `None` if it is disabled.
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+/// A run of a cron job.
+#[derive(Debug, Clone, Serialize)]
+pub struct CronRun {
+    pub id: String,
+    pub job_id: String,
+    pub app_name: String,
+    pub trigger: CronTrigger,
+    pub status: CronRunStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The exit code of the command, once it exited.
+    pub exit_code: Option<i64>,
+    /// The end of the stdout and stderr of the command, at most `MAX_OUTPUT_LENGTH` bytes.
+    pub output: Option<String>,
+    /// Why the command could not be run, if it could not.
+    pub error: Option<String>,
+}
+
+lazy_static! {
+    /// The IDs of the jobs with a run in progress, so runs of a job do not overlap.
+    static ref RUNNING_JOBS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Parses a schedule.
+///
+/// # Arguments
+/// * `schedule` - A five-field cron expression (e.g., `0 3 * * *`) or a nickname such as
+///   `@daily`.
+///
+/// # Returns
+/// * `Ok(Cron)` - The parsed schedule.
+/// * `Err(String)` - If the schedule is invalid.
+pub fn parse_schedule(schedule: &str) -> Result<Cron, String> {
+    Cron::new(schedule)
+        .parse()
+        .map_err(|e| format!("Invalid schedule {:?}: {}", schedule, e))
+}
+
+/// Returns when a schedule fires next after a time.
+fn next_run(schedule: &str, after: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+    parse_schedule(schedule)
+        .ok()?
+        .find_next_occurrence(after, false)
+        .ok()
+}
+
+/// Reads a cron job from a row selected with `CRON_JOB_COLUMNS`.
+fn job_from_row(row: &Row) -> rusqlite::Result<CronJob> {
+    let schedule: String = row.get(2)?;
+    let enabled: bool = row.get(6)?;
+    Ok(CronJob {
+        id: row.get(0)?,
+        app_name: row.get(1)?,
+        next_run_at: enabled.then(|| next_run(&schedule, &Utc::now())).flatten(),
+        schedule,
+        command: row.get(3)?,
+        mode: enum_column(row, 4)?,
+        timeout_seconds: row.get(5)?,
+        enabled,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
+    })
+}
+
+/// Reads a run from a row selected with `CRON_RUN_COLUMNS`.
+fn run_from_row(row: &Row) -> rusqlite::Result<CronRun> {
+    Ok(CronRun {
+        id: row.get(0)?,
+        job_id: row.get(1)?,
+        app_name: row.get(2)?,
+        trigger: enum_column(row, 3)?,
+        status: enum_column(row, 4)?,
+        started_at: row.get(5)?,
+        finished_at: row.get(6)?,
+        exit_code: row.get(7)?,
+        output: row.get(8)?,
+        error: row.get(9)?,
+    })
+}
+
+/// Lists the cron jobs of an app, oldest first.
+///
+/// # Arguments
+/// * `app_name` - The name of the application, every app if `None`.
+///
+/// # Returns
+/// * `Ok(Vec<CronJob>)` - The cron jobs.
+/// * `Err(String)` - If the database could not be read.
+pub fn list_cron_jobs(app_name: Option<&str>) -> Result<Vec<CronJob>, String> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM cron_jobs WHERE ?1 IS NULL OR app_name = ?1 ORDER BY created_at",
+            CRON_JOB_COLUMNS
+        ))?;
+        let jobs = statement.query_map([app_name], job_from_row)?;
+        jobs.collect()
+    })
+}
+
+/// Finds a cron job of an app by ID.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `id` - The ID of the cron job.
+///
+/// # Returns
+/// * `Ok(Some(CronJob))` if the app has this cron job, `Ok(None)` otherwise.
+/// * `Err(String)` if the database could not be read.
+pub fn find_cron_job(app_name: &str, id: &str) -> Result<Option<CronJob>, String> {
+    with_connection(|connection| {
+        connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM cron_jobs WHERE id = ?1 AND app_name = ?2",
+                    CRON_JOB_COLUMNS
+                ),
+                [id, app_name],
+                job_from_row,
+            )
+            .optional()
+    })
+}
+
+/// Adds a cron job to an app.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `settings` - The schedule and command of the job.
+///
+/// # Returns
+/// * `Ok(CronJob)` - The stored job, with its generated ID.
+/// * `Err(String)` - If the database could not be updated.
+pub fn create_cron_job(app_name: &str, settings: CronJobSettings) -> Result<CronJob, String> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+    with_connection(|connection| {
+        connection.execute(
+            &format!(
+                "INSERT INTO cron_jobs ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
+                CRON_JOB_COLUMNS
+            ),
+            params![
+                id,
+                app_name,
+                settings.schedule,
+                settings.command,
+                settings.mode.as_str(),
+                settings.timeout_seconds,
+                settings.enabled,
+                now,
+            ],
+        )
+    })?;
+
+    find_cron_job(app_name, &id)?.ok_or_else(|| format!("Cron job {} was not saved", id))
+}
+
+/// Replaces the settings of a cron job.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `id` - The ID of the cron job.
+/// * `settings` - The new schedule and command of the job.
+///
+/// # Returns
+/// * `Ok(Some(CronJob))` - The updated job.
+/// * `Ok(None)` - If the app has no such cron job.
+/// * `Err(String)` - If the database could not be updated.
+pub fn update_cron_job(
+    app_name: &str,
+    id: &str,
+    settings: CronJobSettings,
+) -> Result<Option<CronJob>, String> {
+    let updated = with_connection(|connection| {
+        connection.execute(
+            "UPDATE cron_jobs SET schedule = ?1, command = ?2, mode = ?3, timeout_seconds = ?4,
+                 enabled = ?5, updated_at = ?6
+             WHERE id = ?7 AND app_name = ?8",
+            params![
+                settings.schedule,
+                settings.command,
+                settings.mode.as_str(),
+                settings.timeout_seconds,
+                settings.enabled,
+                Utc::now(),
+                id,
+                app_name,
+            ],
+        )
+    })?;
+    if updated == 0 {
+        return Ok(None);
+    }
+    find_cron_job(app_name, id)
+}
+
+/// Deletes a cron job and its run history.
+///
+/// A run in progress is not stopped.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `id` - The ID of the cron job.
+///
+/// # Returns
+/// * `Ok(true)` if the job was deleted, `Ok(false)` if the app has no such cron job.
+/// * `Err(String)` if the database could not be updated.
+pub fn delete_cron_job(app_name: &str, id: &str) -> Result<bool, String> {
+    with_connection(|connection| {
+        let transaction = connection.transaction()?;
+        let deleted = transaction.execute(
+            "DELETE FROM cron_jobs WHERE id = ?1 AND app_name = ?2",
+            [id, app_name],
+        )?;
+        if deleted > 0 {
+            transaction.execute("DELETE FROM cron_runs WHERE job_id = ?1", [id])?;
+        }
+        transaction.commit()?;
+        Ok(deleted > 0)
+    })
+}
+
+/// Deletes the cron jobs of an app and their run history, when the app is removed.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+pub fn delete_app_cron_jobs(app_name: &str) -> Result<(), String> {
+    with_connection(|connection| {
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM cron_jobs WHERE app_name = ?1", [app_name])?;
+        transaction.execute("DELETE FROM cron_runs WHERE app_name = ?1", [app_name])?;
+        transaction.commit()
+    })
+}
+
+/// Lists the runs of a cron job, most recent first.
+///
+/// # Arguments
+/// * `job_id` - The ID of the cron job.
+///
+/// # Returns
+/// * `Ok(Vec<CronRun>)` - The last `MAX_RUNS_PER_JOB` runs of the job.
+/// * `Err(String)` - If the database could not be read.
+pub fn list_cron_runs(job_id: &str) -> Result<Vec<CronRun>, String> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM cron_runs WHERE job_id = ?1 ORDER BY started_at DESC",
+            CRON_RUN_COLUMNS
+        ))?;
+        let runs = statement.query_map([job_id], run_from_row)?;
+        runs.collect()
+    })
+}
+
+/// Saves a run, replacing its previous record, and deletes the oldest runs of its job.
+fn save_run(run: &CronRun) -> Result<(), String> {
+    with_connection(|connection| {
+        connection.execute(
+            &format!(
+                "INSERT OR REPLACE INTO cron_runs ({}) VALUES \
+                 (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                CRON_RUN_COLUMNS
+            ),
+            params![
+                run.id,
+                run.job_id,
+                run.app_name,
+                run.trigger.as_str(),
+                run.status.as_str(),
+                run.started_at,
+                run.finished_at,
+                run.exit_code,
+                run.output,
+                run.error,
+            ],
+        )?;
+        connection.execute(
+            "DELETE FROM cron_runs WHERE job_id = ?1 AND id NOT IN (
+                 SELECT id FROM cron_runs WHERE job_id = ?1 ORDER BY started_at DESC LIMIT ?2
+             )",
+            params![run.job_id, MAX_RUNS_PER_JOB],
+        )?;
+        Ok(())
+    })
+}
+
+/// Marks the runs left running by a previous run of Nephelios as failed.
+///
+/// Called at startup, before the scheduler starts.
+pub fn fail_interrupted_cron_runs() -> Result<(), String> {
+    let interrupted = with_connection(|connection| {
+        connection.execute(
+            "UPDATE cron_runs SET status = ?1, error = ?2, finished_at = ?3
+             WHERE finished_at IS NULL",
+            params![
+                CronRunStatus::Failed.as_str(),
+                INTERRUPTED_ERROR,
+                Utc::now()
+            ],
+        )
+    })?;
+    if interrupted > 0 {
+        warn!("{} cron run(s) were interrupted by a restart", interrupted);
+    }
+    Ok(())
+}
+
+/// The result of a command run for a cron job.
+#[derive(Default)]
+struct RunOutcome {
+    exit_code: Option<i64>,
+    timed_out: bool,
+    output: String,
+    error: Option<String>,
+}
+
+/// Runs the command of a cron job in a one-shot Swarm task of the image of its app.
+async fn run_in_job(job: &CronJob, run_id: &str) -> Result<RunOutcome, String> {
+    let (image, environment) = job_image(&job.app_name)?;
+    let name = job_service_name(run_id);
+    create_job_service(&name, &job.app_name, &image, &environment, &job.command).await?;
+    let result = wait_for_job(&name, Duration::from_secs(job.timeout_seconds)).await;
+
+    let mut outcome = RunOutcome::default();
+    match stream_service_logs(&name, Some(MAX_JOB_LOG_LINES), false) {
+        Ok(lines) => {
+            let lines: Vec<String> = lines.filter_map(|line| async { line.ok() }).collect().await;
+            outcome.output = lines.join("\n");
+        }
+        Err(e) => warn!("Failed to read the output of cron job {}: {}", job.id, e),
+    }
+    if let Err(e) = remove_service(&name).await {
+        warn!("Failed to remove job service {}: {}", name, e);
+    }
+
+    match result {
+        Ok(Some(exit_code)) => outcome.exit_code = Some(exit_code),
+        Ok(None) => outcome.timed_out = true,
+        Err(e) => outcome.error = Some(e),
+    }
+    Ok(outcome)
+}
+
+/// Runs the command of a cron job in a running container of its app.
+async fn run_in_exec(job: &CronJob) -> Result<RunOutcome, String> {
+    let command = vec!["sh".to_string(), "-c".to_string(), job.command.clone()];
+    let events = exec_in_app(
+        &job.app_name,
+        command,
+        Duration::from_secs(job.timeout_seconds),
+    )
+    .await?;
+    let mut events = Box::pin(events);
+
+    let mut outcome = RunOutcome::default();
+    while let Some(event) = events.next().await {
+        match event {
+            ExecEvent::Output { data, .. } => {
+                outcome.output.push_str(&data);
+                if outcome.output.len() > 2 * MAX_OUTPUT_LENGTH {
+                    outcome.output = truncate_output(std::mem::take(&mut outcome.output));
+                }
+            }
+            ExecEvent::Exit {
+                exit_code,
+                timed_out,
+                error,
+            } => {
+                outcome.exit_code = exit_code;
+                outcome.timed_out = timed_out;
+                outcome.error = error;
+            }
+        }
+    }
+    Ok(outcome)
+}
+
+/// Runs a cron job and records its run.
+async fn execute(job: CronJob, mut run: CronRun) {
+    info!(
+        "⏰ Running cron job {} of {}: {}",
+        job.id, job.app_name, job.command
+    );
+    let result = match job.mode {
+        CronMode::Job => run_in_job(&job, &run.id).await,
+        CronMode::Exec => run_in_exec(&job).await,
+    };
+    let outcome = result.unwrap_or_else(|e| RunOutcome {
+        error: Some(e),
+        ..Default::default()
+    });
+
+    run.status = if outcome.timed_out {
+        CronRunStatus::TimedOut
+    } else if outcome.error.is_none() && outcome.exit_code == Some(0) {
+        CronRunStatus::Succeeded
+    } else {
+        CronRunStatus::Failed
+    };
+    run.finished_at = Some(Utc::now());
+    run.exit_code = outcome.exit_code;
+    run.output = Some(truncate_output(outcome.output)).filter(|output| !output.is_empty());
+    run.error = outcome.error;
+
+    match run.status {
+        CronRunStatus::Succeeded => info!("✅ Cron job {} of {} succeeded", job.id, job.app_name),
+        _ => warn!(
+            "Cron job {} of {} {}: exit code {:?}, {}",
+            job.id,
+            job.app_name,
+            run.status.as_str(),
+            run.exit_code,
+            run.error.as_deref().unwrap_or("no error")
+        ),
+    }
+    if let Err(e) = save_run(&run) {
+        error!(
+            "❌ Failed to record run {} of cron job {}: {}",
+            run.id, job.id, e
+        );
+    }
+    RUNNING_JOBS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&job.id);
+}
+
+/// Starts a run of a cron job in the background.
+///
+/// # Arguments
+/// * `job` - The cron job.
+/// * `trigger` - What started the run.
+///
+/// # Returns
+/// * `Ok(Some(CronRun))` - The started run.
+/// * `Ok(None)` - If a run of the job is already in progress.
+/// * `Err(String)` - If the run could not be recorded.
+pub fn spawn_cron_run(job: CronJob, trigger: CronTrigger) -> Result<Option<CronRun>, String> {
+    if !RUNNING_JOBS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(job.id.clone())
+    {
+        return Ok(None);
+    }
+
+    let run = CronRun {
+        id: Uuid::new_v4().to_string(),
+        job_id: job.id.clone(),
+        app_name: job.app_name.clone(),
+        trigger,
+        status: CronRunStatus::Running,
+        started_at: Utc::now(),
+        finished_at: None,
+        exit_code: None,
+        output: None,
+        error: None,
+    };
+    if let Err(e) = save_run(&run) {
+        RUNNING_JOBS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&job.id);
+        return Err(e);
+    }
+
+    tokio::spawn(execute(job, run.clone()));
+    Ok(Some(run))
+}
+
+/// Starts the cron jobs whose schedule fired since the previous check.
+///
+/// Jobs of apps that are not in the stack (e.g., soft-deleted apps) are skipped.
+fn start_due_jobs(since: &DateTime<Utc>, now: &DateTime<Utc>) -> Result<(), String> {
+    let jobs = list_cron_jobs(None)?;
+    if jobs.is_empty() {
+        return Ok(());
+    }
+    let stack = load_stack().map_err(|e| format!("Failed to read the stack file: {}", e))?;
+
+    for job in jobs {
+        if !job.enabled || !stack.services.contains_key(&job.app_name) {
+            continue;
+        }
+        if next_run(&job.schedule, since).is_none_or(|next| next > *now) {
+            continue;
+        }
+        match spawn_cron_run(job.clone(), CronTrigger::Schedule) {
+            Ok(Some(_)) => {}
+            Ok(None) => warn!(
+                "Cron job {} of {} skipped: the previous run is still in progress",
+                job.id, job.app_name
+            ),
+            Err(e) => error!("❌ Failed to start cron job {}: {}", job.id, e),
+        }
+    }
+    Ok(())
+}
+
+/// Runs the cron jobs of the apps on their schedule.
+///
+/// Schedules are evaluated in UTC. Runs missed while Nephelios was stopped are not caught up.
+pub async fn run_cron_scheduler() {
+    let mut ticker = tokio::time::interval(SCHEDULER_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last_check = Utc::now();
+
+    loop {
+        ticker.tick().await;
+        let now = Utc::now();
+        if let Err(e) = start_due_jobs(&last_check, &now) {
+            error!("❌ Cron scheduler check failed: {}", e);
+        }
+        last_check = now;
+    }
+}
@@ -0,0 +1,211 @@
+use crate::config::config;
+use dirs::home_dir;
+use lazy_static::lazy_static;
+use rusqlite::types::Type;
+use rusqlite::{Connection, Row};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::info;
+
+/// How long a statement waits for a lock held by another connection.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Schema migrations, applied in order. The index of the last applied one is kept in
+/// `PRAGMA user_version`, so new migrations must be appended.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE apps (
+        app_name TEXT PRIMARY KEY,
+        app_type TEXT NOT NULL,
+        github_url TEXT NOT NULL,
+        domain TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        status TEXT NOT NULL,
+        swarm_task_name TEXT,
+        git_ref TEXT,
+        commit_sha TEXT,
+        commit_message TEXT,
+        domains TEXT NOT NULL DEFAULT '[]',
+        updated_at TEXT NOT NULL
+    );",
+    "CREATE TABLE deployments (
+        id TEXT PRIMARY KEY,
+        app_name TEXT NOT NULL,
+        initiator TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        state TEXT NOT NULL,
+        stage TEXT,
+        created_at TEXT NOT NULL,
+        started_at TEXT,
+        updated_at TEXT NOT NULL,
+        finished_at TEXT,
+        duration_ms INTEGER,
+        git_ref TEXT,
+        commit_sha TEXT,
+        image TEXT,
+        error TEXT,
+        result TEXT,
+        log_path TEXT
+    );
+    CREATE INDEX deployments_app_name ON deployments (app_name, created_at);",
+    "CREATE TABLE audit_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp TEXT NOT NULL,
+        actor TEXT,
+        method TEXT NOT NULL,
+        route TEXT NOT NULL,
+        path TEXT NOT NULL,
+        app_name TEXT,
+        status INTEGER NOT NULL,
+        remote_addr TEXT,
+        user_agent TEXT,
+        duration_ms INTEGER NOT NULL
+    );
+    CREATE INDEX audit_log_timestamp ON audit_log (timestamp);",
+    "CREATE TABLE cron_jobs (
+        id TEXT PRIMARY KEY,
+        app_name TEXT NOT NULL,
+        schedule TEXT NOT NULL,
+        command TEXT NOT NULL,
+        mode TEXT NOT NULL,
+        timeout_seconds INTEGER NOT NULL,
+        enabled INTEGER NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE INDEX cron_jobs_app_name ON cron_jobs (app_name);
+    CREATE TABLE cron_runs (
+        id TEXT PRIMARY KEY,
+        job_id TEXT NOT NULL,
+        app_name TEXT NOT NULL,
+        trigger TEXT NOT NULL,
+        status TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        finished_at TEXT,
+        exit_code INTEGER,
+        output TEXT,
+        error TEXT
+    );
+    CREATE INDEX cron_runs_job_id ON cron_runs (job_id, started_at);",
+    "CREATE TABLE jobs (
+        id TEXT PRIMARY KEY,
+        app_name TEXT NOT NULL,
+        command TEXT NOT NULL,
+        initiator TEXT NOT NULL,
+        status TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        finished_at TEXT,
+        exit_code INTEGER,
+        output TEXT,
+        error TEXT
+    );
+    CREATE INDEX jobs_app_name ON jobs (app_name, started_at);",
+    "CREATE TABLE email_recipients (
+        app_name TEXT NOT NULL,
+        email TEXT NOT NULL,
+        PRIMARY KEY (app_name, email)
+    );",
+    "CREATE TABLE webhooks (
+        id TEXT PRIMARY KEY,
+        app_name TEXT,
+        url TEXT NOT NULL,
+        secret TEXT NOT NULL,
+        events TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX webhooks_app_name ON webhooks (app_name);",
+    "CREATE TABLE app_owners (
+        app_name TEXT PRIMARY KEY,
+        account TEXT NOT NULL
+    );
+    CREATE INDEX app_owners_account ON app_owners (account);",
+    "ALTER TABLE deployments ADD COLUMN priority TEXT NOT NULL DEFAULT 'normal';",
+];
+
+lazy_static! {
+    /// The database connection, opened on first use.
+    static ref CONNECTION: Mutex<Option<Connection>> = Mutex::new(None);
+}
+
+/// Returns the path of the database, see `server.database_path`.
+fn database_path() -> Result<PathBuf, String> {
+    if let Some(path) = &config().server.database_path {
+        return Ok(PathBuf::from(path));
+    }
+    let home = home_dir().ok_or("Failed to find home directory")?;
+    Ok(home.join(".config/nephelios/nephelios.db"))
+}
+
+/// Applies the migrations that were not applied yet.
+fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+    let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", index + 1)?;
+        transaction.commit()?;
+        info!("🗄️ Applied database migration {}", index + 1);
+    }
+    Ok(())
+}
+
+/// Opens the database and brings its schema up to date.
+fn open_database() -> Result<Connection, String> {
+    let path = database_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create database directory: {}", e))?;
+    }
+
+    let mut connection = Connection::open(&path)
+        .map_err(|e| format!("Failed to open database {}: {}", path.display(), e))?;
+    connection
+        .busy_timeout(BUSY_TIMEOUT)
+        .and_then(|_| connection.pragma_update(None, "journal_mode", "WAL"))
+        .map_err(|e| format!("Failed to configure database: {}", e))?;
+    migrate(&mut connection).map_err(|e| format!("Failed to migrate database: {}", e))?;
+    Ok(connection)
+}
+
+/// Opens the database at startup, so a broken database is reported before serving.
+///
+/// # Returns
+/// * `Ok(())` if the database is ready.
+/// * `Err(String)` if it could not be opened or migrated.
+pub fn init_database() -> Result<(), String> {
+    with_connection(|_| Ok(()))
+}
+
+/// Runs a function with the database connection.
+///
+/// # Arguments
+/// * `f` - The function, given the connection.
+///
+/// # Returns
+/// * `Ok(T)` - The result of the function.
+/// * `Err(String)` - If the database could not be opened or the function failed.
+pub fn with_connection<T>(
+    f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>,
+) -> Result<T, String> {
+    let mut connection = CONNECTION.lock().unwrap_or_else(|e| e.into_inner());
+    if connection.is_none() {
+        *connection = Some(open_database()?);
+    }
+
+    let connection = connection.as_mut().ok_or("Database is not open")?;
+    f(connection).map_err(|e| format!("Database error: {}", e))
+}
+
+/// Reads an enum stored as its serialized name.
+///
+/// # Arguments
+/// * `row` - The row.
+/// * `index` - The index of the column.
+pub fn enum_column<T: DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let name: String = row.get(index)?;
+    serde_json::from_value(Value::String(name))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
@@ -0,0 +1,2024 @@
+use crate::config::config;
+use crate::metrics::DEPLOY_STAGE_DURATION;
+use crate::requests::parse_duration;
+use crate::services::addons::AddonType;
+use crate::services::app_registry::{find_registered_app, refresh_registered_app};
+use crate::services::app_secrets::{remove_unused_secrets, secret_path, store_pending_secrets};
+use crate::services::compose::{
+    compose_images, list_compose_services, load_compose, remove_compose_services,
+    update_compose_environment, update_compose_services, ComposeApp,
+};
+use crate::services::deployment_history::load_history;
+use crate::services::deployment_tracker::{Deployment, DeploymentPriority, DeploymentState};
+use crate::services::helpers::credentials_helper::{resolve_git_credentials, GitCredentials};
+use crate::services::helpers::crypto_helper::{decrypt_value, encrypt_value, needs_reencryption};
+use crate::services::helpers::docker_helper::{
+    build_image, deploy_stack_service, generate_and_write_dockerfile, get_app_details,
+    prune_images, push_image, start_docker_context, wait_for_rollout, AppInfo, AppMetadata,
+};
+use crate::services::helpers::github_helper::{
+    clone_repo, commit_status_enabled, create_temp_dir, default_clone_depth, head_commit,
+    normalize_repo_url, remove_temp_dir, report_commit_status, CloneOptions, CommitState,
+};
+use crate::services::helpers::signing_helper::{sign_image, verify_image};
+use crate::services::helpers::stack_helper::{
+    Placement, PlacementPreference, ResourceSpec, Resources, ServiceSecurity, UpdateConfig,
+};
+use crate::services::helpers::traefik_helper::{
+    add_to_deploy, app_image, app_replicas, reserved_service_names, update_app_environment,
+    update_app_image, update_app_placement, update_app_resources, update_app_secrets,
+    update_app_security, update_app_update_config, update_app_volumes, update_routing, verif_app,
+    RoutingConfig,
+};
+use crate::services::websocket::{send_deployment_status, DeploymentEvent};
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn, Instrument};
+
+/// Everything needed to build and deploy an application from its repository.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployRequest {
+    pub app_name: String,
+    pub app_type: String,
+    pub github_url: String,
+    #[serde(default)]
+    pub git_ref: Option<String>,
+    /// Per-request token, never persisted.
+    #[serde(skip)]
+    pub git_token: Option<String>,
+    #[serde(default)]
+    pub deploy_key: Option<String>,
+    /// Clone depth override, `GIT_CLONE_DEPTH` is used when `None`.
+    #[serde(default)]
+    pub clone_depth: Option<u32>,
+    #[serde(default)]
+    pub recurse_submodules: bool,
+    /// Whether the app is redeployed when its tracked branch receives new commits.
+    #[serde(default)]
+    pub auto_redeploy: bool,
+    #[serde(default)]
+    pub install_command: String,
+    #[serde(default)]
+    pub run_command: String,
+    #[serde(default)]
+    pub build_command: String,
+    pub app_workdir: String,
+    #[serde(default)]
+    pub additional_inputs: HashMap<String, String>,
+    #[serde(default)]
+    pub routing: RoutingConfig,
+    #[serde(default)]
+    pub resources: ResourceLimits,
+    #[serde(default)]
+    pub placement: PlacementConfig,
+    #[serde(default)]
+    pub update_config: RolloutConfig,
+    /// Hardening settings of the containers, none for apps deployed before they existed.
+    #[serde(default = "SecurityOptions::unrestricted")]
+    pub security: SecurityOptions,
+    /// CPU-based autoscaling of the replicas, disabled if unset.
+    #[serde(default)]
+    pub autoscaling: Option<AutoscalingConfig>,
+    /// Replicas set by time of day and day of week, disabled if unset.
+    #[serde(default)]
+    pub scaling_schedule: Option<ScalingSchedule>,
+    /// Priority of the deployments of the app in the deployment queue.
+    #[serde(default)]
+    pub priority: DeploymentPriority,
+    /// Named volumes mounted into the service.
+    #[serde(default)]
+    pub volumes: Vec<VolumeMount>,
+    /// Environment variables set on the service, applied without rebuilding the image.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Docker secrets mounted into the service, by variable name. Only the secret names are
+    /// stored, never their values.
+    #[serde(default)]
+    pub secrets: BTreeMap<String, String>,
+    /// Secret values to store as Docker secrets on the next deploy, by variable name, never
+    /// persisted.
+    #[serde(skip)]
+    pub pending_secrets: BTreeMap<String, String>,
+    /// Whether the build settings changed since the running release was built.
+    #[serde(default)]
+    pub needs_rebuild: bool,
+    /// The services of the compose file of the repository, read when the app is built.
+    #[serde(default)]
+    pub compose: Option<ComposeApp>,
+    /// A published image deployed instead of building the repository (e.g., `ghost:5`).
+    #[serde(default)]
+    pub image: Option<String>,
+}
+
+/// CPU and memory limits and reservations of an app service.
+///
+/// CPUs are a number of cores (e.g., "1.5"), memory a size with a `b`, `k`, `m` or `g`
+/// suffix (e.g., "512M").
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    pub cpu_limit: String,
+    pub memory_limit: String,
+    pub cpu_reservation: String,
+    pub memory_reservation: String,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            cpu_limit: "1.5".to_string(),
+            memory_limit: "1G".to_string(),
+            cpu_reservation: "0.5".to_string(),
+            memory_reservation: "256M".to_string(),
+        }
+    }
+}
+
+/// Parses a CPU amount, in cores.
+pub fn parse_cpus(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|cpus| cpus.is_finite() && *cpus > 0.0)
+        .ok_or_else(|| format!("Invalid CPU amount: {}", value))
+}
+
+/// Parses a memory size, in bytes.
+///
+/// Sizes are read as Docker does: a number with an optional `k`, `m`, `g` or `t` unit,
+/// optionally followed by `i` and `b` (e.g., `512m`, `512MB`, `1GiB`, `1 Gi`). Units are
+/// binary whatever their spelling, so `1GB` and `1GiB` both stand for 1024³ bytes.
+pub fn parse_memory(value: &str) -> Result<f64, String> {
+    let invalid = || format!("Invalid memory size: {}", value);
+    let lower = value.trim().to_ascii_lowercase();
+    let mut number = lower.as_str();
+    number = number.strip_suffix('b').unwrap_or(number);
+    number = number.strip_suffix('i').unwrap_or(number);
+    let mut multiplier = 1.0;
+    for (suffix, power) in [('k', 1), ('m', 2), ('g', 3), ('t', 4)] {
+        if let Some(stripped) = number.strip_suffix(suffix) {
+            number = stripped;
+            multiplier = 1024f64.powi(power);
+            break;
+        }
+    }
+    // `i` is only allowed after a unit, as in `Gi`
+    if multiplier == 1.0 && lower.trim_end_matches('b').ends_with('i') {
+        return Err(invalid());
+    }
+
+    number
+        .trim_end()
+        .parse::<f64>()
+        .ok()
+        .filter(|size| size.is_finite() && *size > 0.0)
+        .map(|size| size * multiplier)
+        .ok_or_else(invalid)
+}
+
+/// Resource values to change, missing ones keep their current value.
+///
+/// Values may be sent as strings or numbers.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ResourceOverrides {
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub cpu_limit: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub memory_limit: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub cpu_reservation: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub memory_reservation: Option<String>,
+}
+
+/// Deserializes an optional value sent either as a string or a number.
+fn string_or_number<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value.trim().to_string())),
+        Some(Value::Number(value)) => Ok(Some(value.to_string())),
+        Some(other) => Err(serde::de::Error::custom(format!(
+            "expected a string or a number, got {}",
+            other
+        ))),
+    }
+}
+
+impl ResourceLimits {
+    /// Returns a copy with the given overrides applied, then validates it.
+    ///
+    /// # Arguments
+    /// * `overrides` - The values to change.
+    ///
+    /// # Returns
+    /// * `Ok(ResourceLimits)` with the overrides applied.
+    /// * `Err((field, message))` if a value is invalid or a reservation exceeds its limit.
+    pub fn with_overrides(
+        &self,
+        overrides: &ResourceOverrides,
+    ) -> Result<Self, (&'static str, String)> {
+        let pick = |value: &Option<String>, current: &str| {
+            value.clone().unwrap_or_else(|| current.to_string())
+        };
+
+        let limits = Self {
+            cpu_limit: pick(&overrides.cpu_limit, &self.cpu_limit),
+            memory_limit: pick(&overrides.memory_limit, &self.memory_limit),
+            cpu_reservation: pick(&overrides.cpu_reservation, &self.cpu_reservation),
+            memory_reservation: pick(&overrides.memory_reservation, &self.memory_reservation),
+        };
+        limits.validate()?;
+        Ok(limits)
+    }
+
+    /// Checks that every value is valid and reservations do not exceed limits.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the limits are valid.
+    /// * `Err((field, message))` naming the first invalid field.
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        let cpu_limit = parse_cpus(&self.cpu_limit).map_err(|e| ("cpu_limit", e))?;
+        let cpu_reservation =
+            parse_cpus(&self.cpu_reservation).map_err(|e| ("cpu_reservation", e))?;
+        let memory_limit = parse_memory(&self.memory_limit).map_err(|e| ("memory_limit", e))?;
+        let memory_reservation =
+            parse_memory(&self.memory_reservation).map_err(|e| ("memory_reservation", e))?;
+
+        if cpu_reservation > cpu_limit {
+            return Err((
+                "cpu_reservation",
+                "cpu_reservation cannot exceed cpu_limit".to_string(),
+            ));
+        }
+        if memory_reservation > memory_limit {
+            return Err((
+                "memory_reservation",
+                "memory_reservation cannot exceed memory_limit".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the CPU and memory reservations of one replica, in cores and bytes.
+    pub fn reservations(&self) -> (f64, f64) {
+        (
+            parse_cpus(&self.cpu_reservation).unwrap_or(0.0),
+            parse_memory(&self.memory_reservation).unwrap_or(0.0),
+        )
+    }
+
+    /// Converts the limits to the `deploy.resources` section of the stack file.
+    pub fn to_stack_resources(&self) -> Resources {
+        Resources {
+            limits: Some(ResourceSpec {
+                cpus: Some(self.cpu_limit.clone()),
+                memory: Some(self.memory_limit.clone()),
+            }),
+            reservations: Some(ResourceSpec {
+                cpus: Some(self.cpu_reservation.clone()),
+                memory: Some(self.memory_reservation.clone()),
+            }),
+        }
+    }
+}
+
+/// Maximum number of placement constraints or preferences of an app.
+const MAX_PLACEMENT_RULES: usize = 32;
+
+/// Node attributes a placement constraint can test, besides node and engine labels.
+const CONSTRAINT_ATTRIBUTES: &[&str] = &[
+    "node.id",
+    "node.hostname",
+    "node.role",
+    "node.platform.os",
+    "node.platform.arch",
+];
+
+/// The Swarm nodes an app service may run on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlacementConfig {
+    /// Conditions a node must meet to run the app (e.g., "node.labels.tier==gpu").
+    #[serde(default)]
+    pub constraints: Vec<String>,
+    /// Labels the replicas are spread across the values of (e.g., "node.labels.zone").
+    #[serde(default)]
+    pub preferences: Vec<String>,
+}
+
+/// Checks that a label attribute (e.g., `node.labels.tier`) names a node or engine label.
+fn is_label_attribute(attribute: &str) -> bool {
+    ["node.labels.", "engine.labels."].iter().any(|prefix| {
+        attribute
+            .strip_prefix(prefix)
+            .is_some_and(|label| !label.is_empty())
+    })
+}
+
+/// Checks a placement constraint, `<attribute>==<value>` or `<attribute>!=<value>`.
+fn validate_constraint(constraint: &str) -> Result<(), String> {
+    let invalid = |reason: &str| format!("Invalid constraint {:?}: {}", constraint, reason);
+    let (attribute, value) = constraint
+        .split_once("==")
+        .or_else(|| constraint.split_once("!="))
+        .ok_or_else(|| invalid("expected <attribute>==<value> or <attribute>!=<value>"))?;
+    let (attribute, value) = (attribute.trim(), value.trim());
+    if !CONSTRAINT_ATTRIBUTES.contains(&attribute) && !is_label_attribute(attribute) {
+        return Err(invalid(&format!(
+            "the attribute must be one of {}, node.labels.<label> or engine.labels.<label>",
+            CONSTRAINT_ATTRIBUTES.join(", ")
+        )));
+    }
+    if value.is_empty() || value.contains("==") || value.contains("!=") {
+        return Err(invalid("invalid value"));
+    }
+    Ok(())
+}
+
+impl PlacementConfig {
+    /// Checks the constraints and preferences.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the placement is valid.
+    /// * `Err((field, message))` naming the first invalid field.
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        if self.constraints.len() > MAX_PLACEMENT_RULES {
+            return Err((
+                "constraints",
+                format!("At most {} constraints are allowed", MAX_PLACEMENT_RULES),
+            ));
+        }
+        for constraint in &self.constraints {
+            validate_constraint(constraint).map_err(|e| ("constraints", e))?;
+        }
+
+        if self.preferences.len() > MAX_PLACEMENT_RULES {
+            return Err((
+                "preferences",
+                format!("At most {} preferences are allowed", MAX_PLACEMENT_RULES),
+            ));
+        }
+        if let Some(preference) = self
+            .preferences
+            .iter()
+            .find(|preference| !is_label_attribute(preference.trim()))
+        {
+            return Err((
+                "preferences",
+                format!(
+                    "Invalid preference {:?}: expected node.labels.<label> or engine.labels.<label>",
+                    preference
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Converts the placement to the `deploy.placement` section of the stack file.
+    ///
+    /// # Returns
+    /// `None` when the app may run on any node.
+    pub fn to_stack_placement(&self) -> Option<Placement> {
+        if self.constraints.is_empty() && self.preferences.is_empty() {
+            return None;
+        }
+        Some(Placement {
+            constraints: self
+                .constraints
+                .iter()
+                .map(|constraint| constraint.trim().to_string())
+                .collect(),
+            preferences: self
+                .preferences
+                .iter()
+                .map(|preference| PlacementPreference {
+                    spread: preference.trim().to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Longest pause between two batches of a rolling update.
+const MAX_UPDATE_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// What Swarm does when a task fails to start during a rolling update.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureAction {
+    /// Stop the update, leaving the updated and old tasks running.
+    Pause,
+    /// Keep updating the other tasks.
+    Continue,
+    /// Go back to the previous version of the service.
+    #[default]
+    Rollback,
+}
+
+/// The order in which a task is replaced during a rolling update.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdateOrder {
+    /// Stop the old task before starting the new one.
+    #[default]
+    StopFirst,
+    /// Start the new task first, so both briefly run together.
+    StartFirst,
+}
+
+impl FailureAction {
+    /// The name of the action in the stack file.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailureAction::Pause => "pause",
+            FailureAction::Continue => "continue",
+            FailureAction::Rollback => "rollback",
+        }
+    }
+}
+
+impl UpdateOrder {
+    /// The name of the order in the stack file.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UpdateOrder::StopFirst => "stop-first",
+            UpdateOrder::StartFirst => "start-first",
+        }
+    }
+}
+
+/// How the replicas of an app service are replaced on deploys, Swarm's `update_config`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RolloutConfig {
+    /// Number of replicas replaced at once, `0` for all of them.
+    pub parallelism: u32,
+    /// Pause between two batches of replicas (e.g., "10s", "1m").
+    pub delay: String,
+    pub failure_action: FailureAction,
+    pub order: UpdateOrder,
+}
+
+impl Default for RolloutConfig {
+    fn default() -> Self {
+        Self {
+            parallelism: 1,
+            delay: "0s".to_string(),
+            failure_action: FailureAction::default(),
+            order: UpdateOrder::default(),
+        }
+    }
+}
+
+/// Rolling update settings to change, missing settings keep their current value.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RolloutOverrides {
+    #[serde(default)]
+    pub parallelism: Option<u32>,
+    #[serde(default)]
+    pub delay: Option<String>,
+    #[serde(default)]
+    pub failure_action: Option<FailureAction>,
+    #[serde(default)]
+    pub order: Option<UpdateOrder>,
+}
+
+impl RolloutConfig {
+    /// Applies overrides to the settings and checks the result.
+    ///
+    /// # Arguments
+    /// * `overrides` - The settings to change.
+    ///
+    /// # Returns
+    /// * `Ok(RolloutConfig)` with the overrides applied.
+    /// * `Err((field, message))` if the delay is invalid.
+    pub fn with_overrides(
+        &self,
+        overrides: &RolloutOverrides,
+    ) -> Result<Self, (&'static str, String)> {
+        let config = Self {
+            parallelism: overrides.parallelism.unwrap_or(self.parallelism),
+            delay: overrides
+                .delay
+                .as_deref()
+                .map(str::trim)
+                .unwrap_or(&self.delay)
+                .to_string(),
+            failure_action: overrides.failure_action.unwrap_or(self.failure_action),
+            order: overrides.order.unwrap_or(self.order),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that the delay is a duration of at most `MAX_UPDATE_DELAY`.
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        match parse_duration(&self.delay) {
+            Some(delay) if delay <= MAX_UPDATE_DELAY => Ok(()),
+            Some(_) => Err(("delay", "delay must be at most 1h".to_string())),
+            None => Err((
+                "delay",
+                "delay must be a number followed by s, m or h".to_string(),
+            )),
+        }
+    }
+
+    /// Converts the settings to the `deploy.update_config` section of the stack file.
+    pub fn to_stack_update_config(&self) -> UpdateConfig {
+        UpdateConfig {
+            parallelism: Some(self.parallelism),
+            delay: Some(self.delay.clone()),
+            failure_action: Some(self.failure_action.as_str().to_string()),
+            order: Some(self.order.as_str().to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Maximum number of capabilities dropped or added back.
+const MAX_CAPABILITIES: usize = 64;
+
+/// Hardening settings of the containers of an app.
+///
+/// The default profile drops every capability, forbids privilege escalation and runs the
+/// processes as a non-root user. The root filesystem stays writable unless `read_only` is set,
+/// since many apps write next to their code.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityOptions {
+    /// Whether the root filesystem is mounted read-only, volumes and `/tmp` stay writable.
+    pub read_only: bool,
+    /// Linux capabilities dropped (e.g., "NET_RAW"), `ALL` for every one of them.
+    pub cap_drop: Vec<String>,
+    /// Capabilities added back after the dropped ones (e.g., "NET_BIND_SERVICE").
+    pub cap_add: Vec<String>,
+    /// Whether processes are prevented from gaining privileges (e.g., with setuid binaries).
+    pub no_new_privileges: bool,
+    /// User the processes run as (e.g., "1000:1000"), the user of the image if unset.
+    pub user: Option<String>,
+}
+
+impl Default for SecurityOptions {
+    fn default() -> Self {
+        Self {
+            read_only: false,
+            cap_drop: vec!["ALL".to_string()],
+            cap_add: Vec::new(),
+            no_new_privileges: true,
+            user: Some("1000:1000".to_string()),
+        }
+    }
+}
+
+/// Checks a capability name, `ALL` or a name such as `NET_ADMIN` with an optional `CAP_`
+/// prefix.
+fn validate_capability(capability: &str) -> Result<(), String> {
+    let name = capability.strip_prefix("CAP_").unwrap_or(capability);
+    if name.is_empty()
+        || name.len() > 32
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err(format!("Invalid capability: {:?}", capability));
+    }
+    Ok(())
+}
+
+impl SecurityOptions {
+    /// Settings of the apps deployed before hardening settings existed, and of published
+    /// images: the containers run as the image defines them.
+    pub fn unrestricted() -> Self {
+        Self {
+            read_only: false,
+            cap_drop: Vec::new(),
+            cap_add: Vec::new(),
+            no_new_privileges: false,
+            user: None,
+        }
+    }
+
+    /// Checks the capabilities and the user.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the settings are valid.
+    /// * `Err((field, message))` naming the first invalid field.
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        for (field, capabilities) in [("cap_drop", &self.cap_drop), ("cap_add", &self.cap_add)] {
+            if capabilities.len() > MAX_CAPABILITIES {
+                return Err((
+                    field,
+                    format!("At most {} capabilities are allowed", MAX_CAPABILITIES),
+                ));
+            }
+            for capability in capabilities {
+                validate_capability(capability).map_err(|e| (field, e))?;
+            }
+        }
+        if let Some(user) = &self.user {
+            let valid_part = |part: &str| {
+                !part.is_empty()
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            };
+            let parts: Vec<&str> = user.split(':').collect();
+            if user.len() > 64 || parts.len() > 2 || !parts.into_iter().all(valid_part) {
+                return Err((
+                    "user",
+                    "user must be a user name or ID, optionally followed by :<group>".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts the settings to the hardening keys of the service in the stack file.
+    pub fn to_stack_security(&self) -> ServiceSecurity {
+        ServiceSecurity {
+            read_only: self.read_only,
+            tmpfs: if self.read_only {
+                vec!["/tmp".to_string()]
+            } else {
+                Vec::new()
+            },
+            cap_drop: self.cap_drop.clone(),
+            cap_add: self.cap_add.clone(),
+            security_opt: if self.no_new_privileges {
+                vec!["no-new-privileges:true".to_string()]
+            } else {
+                Vec::new()
+            },
+            user: self.user.clone(),
+        }
+    }
+}
+
+/// Bounds and target of the CPU-based autoscaling of an app, see `run_autoscaler`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoscalingConfig {
+    pub min_replicas: u32,
+    pub max_replicas: u32,
+    /// Average CPU usage of the replicas aimed at, in percent of the CPU reservation of a
+    /// replica (of one core if nothing is reserved).
+    pub target_cpu: u32,
+}
+
+impl AutoscalingConfig {
+    /// Checks that the bounds are ordered and the target is a percentage.
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        if self.min_replicas == 0 {
+            return Err((
+                "min_replicas",
+                "min_replicas must be at least 1".to_string(),
+            ));
+        }
+        if self.max_replicas < self.min_replicas {
+            return Err((
+                "max_replicas",
+                "max_replicas must be at least min_replicas".to_string(),
+            ));
+        }
+        if !(1..=100).contains(&self.target_cpu) {
+            return Err((
+                "target_cpu",
+                "target_cpu must be between 1 and 100".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Maximum number of windows of a scaling schedule.
+const MAX_SCALING_WINDOWS: usize = 16;
+
+/// Replicas of an app set by time of day and day of week, see `run_scaling_scheduler`.
+///
+/// Times are in UTC, like the schedules of cron jobs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScalingSchedule {
+    /// Replicas outside of every window.
+    pub default_replicas: u32,
+    /// The windows, the first one containing a time wins.
+    pub windows: Vec<ScalingWindow>,
+}
+
+/// A period of the week during which an app runs a given number of replicas.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScalingWindow {
+    /// Days the window starts on (e.g., "mon", "friday"), every day if empty.
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    /// Start of the window, as "HH:MM".
+    pub start: String,
+    /// End of the window, as "HH:MM". A window ending before it starts runs over midnight.
+    pub end: String,
+    pub replicas: u32,
+}
+
+/// Parses a time of day written as "HH:MM".
+fn parse_time_of_day(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+impl ScalingWindow {
+    /// Whether the window starts on a day.
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    /// Whether a time falls within the window.
+    ///
+    /// # Arguments
+    /// * `time` - The time, in UTC.
+    pub fn contains(&self, time: &DateTime<Utc>) -> bool {
+        let (Some(start), Some(end)) =
+            (parse_time_of_day(&self.start), parse_time_of_day(&self.end))
+        else {
+            return false;
+        };
+        let (day, time_of_day) = (time.weekday(), time.time());
+        if start < end {
+            self.starts_on(day) && start <= time_of_day && time_of_day < end
+        } else {
+            (self.starts_on(day) && time_of_day >= start)
+                || (self.starts_on(day.pred()) && time_of_day < end)
+        }
+    }
+}
+
+impl ScalingSchedule {
+    /// Returns the replicas the app runs at a time.
+    ///
+    /// # Arguments
+    /// * `time` - The time, in UTC.
+    pub fn replicas_at(&self, time: &DateTime<Utc>) -> u32 {
+        self.windows
+            .iter()
+            .find(|window| window.contains(time))
+            .map_or(self.default_replicas, |window| window.replicas)
+    }
+
+    /// Checks that there are windows and that their times are valid.
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        if self.windows.is_empty() || self.windows.len() > MAX_SCALING_WINDOWS {
+            return Err((
+                "windows",
+                format!("windows must have 1 to {} entries", MAX_SCALING_WINDOWS),
+            ));
+        }
+        for window in &self.windows {
+            for time in [&window.start, &window.end] {
+                if parse_time_of_day(time).is_none() {
+                    return Err((
+                        "windows",
+                        format!("Invalid time {:?}, expected HH:MM", time),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the most replicas the schedule sets.
+    pub fn max_replicas(&self) -> u32 {
+        self.windows
+            .iter()
+            .map(|window| window.replicas)
+            .fold(self.default_replicas, u32::max)
+    }
+}
+
+/// Maximum number of volumes mounted into an app.
+const MAX_APP_VOLUMES: usize = 16;
+
+/// Maximum length of the name of an app volume.
+const MAX_VOLUME_NAME_LENGTH: usize = 64;
+
+/// A named volume mounted into the containers of an app, kept across redeploys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VolumeMount {
+    /// The name of the volume, unique for the app (e.g., "uploads").
+    pub name: String,
+    /// The absolute path the volume is mounted at in the containers (e.g., "/app/uploads").
+    pub path: String,
+}
+
+impl VolumeMount {
+    /// Returns the name of the volume in the stack file, prefixed with the app name so apps do
+    /// not share volumes (e.g., `my-app-uploads`).
+    pub fn stack_volume(&self, app_name: &str) -> String {
+        format!("{}-{}", app_name, self.name)
+    }
+}
+
+/// Checks the volumes of an app: valid names and absolute paths, each used once.
+///
+/// # Arguments
+/// * `volumes` - The volumes to check.
+///
+/// # Returns
+/// * `Ok(())` if the volumes are valid.
+/// * `Err(String)` describing the first invalid volume.
+pub fn validate_volumes(volumes: &[VolumeMount]) -> Result<(), String> {
+    if volumes.len() > MAX_APP_VOLUMES {
+        return Err(format!("At most {} volumes are allowed", MAX_APP_VOLUMES));
+    }
+
+    let mut names = HashSet::new();
+    let mut paths = HashSet::new();
+    for volume in volumes {
+        let name = &volume.name;
+        if name.is_empty()
+            || name.len() > MAX_VOLUME_NAME_LENGTH
+            || !name.starts_with(|c: char| c.is_ascii_alphanumeric())
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Invalid volume name {:?}: expected at most {} lowercase letters, digits, dashes \
+                 and underscores",
+                name, MAX_VOLUME_NAME_LENGTH
+            ));
+        }
+
+        if AddonType::parse(name).is_ok() {
+            return Err(format!(
+                "Invalid volume name {:?}: reserved for the volume of the addon",
+                name
+            ));
+        }
+
+        let path = volume.path.trim_end_matches('/');
+        if !path.starts_with('/')
+            || path.contains(':')
+            || path.split('/').any(|segment| segment == "..")
+        {
+            return Err(format!(
+                "Invalid path {:?} for volume {}: expected an absolute path",
+                volume.path, name
+            ));
+        }
+
+        if !names.insert(name.as_str()) {
+            return Err(format!("Volume {} is listed twice", name));
+        }
+        if !paths.insert(path) {
+            return Err(format!("Path {} is mounted twice", path));
+        }
+    }
+    Ok(())
+}
+
+impl DeployRequest {
+    /// Returns the request as it is stored, the values of `env` and `additional_inputs`
+    /// encrypted with the master key.
+    pub fn encrypted(&self) -> Result<DeployRequest, String> {
+        let mut request = self.clone();
+        for value in request
+            .env
+            .values_mut()
+            .chain(request.additional_inputs.values_mut())
+        {
+            *value = encrypt_value(value)?;
+        }
+        Ok(request)
+    }
+
+    /// Decrypts the values of a stored request, the reverse of `encrypted`.
+    pub fn decrypted(mut self) -> Result<DeployRequest, String> {
+        for value in self
+            .env
+            .values_mut()
+            .chain(self.additional_inputs.values_mut())
+        {
+            *value = decrypt_value(value)?;
+        }
+        Ok(self)
+    }
+
+    /// Whether a stored request holds values in clear or encrypted with a previous master
+    /// key.
+    fn needs_reencryption(&self) -> bool {
+        self.env
+            .values()
+            .chain(self.additional_inputs.values())
+            .any(|value| needs_reencryption(value))
+    }
+
+    /// Returns the volumes of the app as pairs of a stack volume name and a mount path.
+    pub fn volume_mounts(&self) -> Vec<(String, String)> {
+        self.volumes
+            .iter()
+            .map(|volume| (volume.stack_volume(&self.app_name), volume.path.clone()))
+            .collect()
+    }
+
+    /// Returns the secrets of the app as pairs of a Docker secret name and the file name it
+    /// is mounted as.
+    pub fn secret_mounts(&self) -> Vec<(String, String)> {
+        self.secrets
+            .iter()
+            .map(|(key, secret)| (secret.clone(), key.clone()))
+            .collect()
+    }
+
+    /// Returns the environment of the app service: the variables of the compose web service,
+    /// overridden by the variables of the app.
+    ///
+    /// Each secret is pointed to by a `<NAME>_FILE` variable holding the path of its file,
+    /// unless the app sets that variable itself.
+    pub fn app_environment(&self) -> BTreeMap<String, String> {
+        let mut env = self
+            .compose
+            .as_ref()
+            .and_then(ComposeApp::web_service)
+            .map(|service| service.environment.clone())
+            .unwrap_or_default();
+        for key in self.secrets.keys() {
+            env.insert(format!("{}_FILE", key), secret_path(key));
+        }
+        env.extend(self.env.clone());
+        env
+    }
+
+    /// Builds a deploy request for an existing app from its labels, using default commands.
+    pub fn from_app_info(app: &AppInfo) -> Self {
+        Self {
+            app_name: app.app_name.clone(),
+            app_type: app.app_type.clone(),
+            github_url: app.github_url.clone(),
+            git_ref: app.git_ref.clone(),
+            git_token: None,
+            deploy_key: None,
+            clone_depth: None,
+            recurse_submodules: false,
+            auto_redeploy: false,
+            install_command: String::new(),
+            run_command: String::new(),
+            build_command: String::new(),
+            app_workdir: "/app".to_string(),
+            additional_inputs: HashMap::new(),
+            routing: RoutingConfig {
+                domains: app.domains.clone(),
+                ..Default::default()
+            },
+            resources: ResourceLimits::default(),
+            placement: PlacementConfig::default(),
+            update_config: RolloutConfig::default(),
+            security: SecurityOptions::unrestricted(),
+            autoscaling: None,
+            scaling_schedule: None,
+            priority: DeploymentPriority::default(),
+            volumes: Vec::new(),
+            env: BTreeMap::new(),
+            secrets: BTreeMap::new(),
+            pending_secrets: BTreeMap::new(),
+            needs_rebuild: false,
+            compose: None,
+            image: None,
+        }
+    }
+}
+
+/// Returns the directory storing the last deploy request of each app.
+fn deploy_requests_dir() -> Result<PathBuf, String> {
+    let home = home_dir().ok_or("Failed to find home directory")?;
+    Ok(home.join(".config/nephelios/apps"))
+}
+
+/// Returns the path of the file storing the last deploy request of an app.
+fn deploy_request_path(app_name: &str) -> Result<PathBuf, String> {
+    Ok(deploy_requests_dir()?.join(format!("{}.json", app_name)))
+}
+
+/// Persists the deploy request of an app so it can be redeployed later.
+///
+/// The values of its variables are encrypted with the master key, if one is configured.
+///
+/// # Arguments
+/// * `request` - The deploy request to store.
+///
+/// # Returns
+/// * `Ok(())` if the request was written.
+/// * `Err(String)` if the file could not be written.
+pub fn save_deploy_request(request: &DeployRequest) -> Result<(), String> {
+    let path = deploy_request_path(&request.app_name)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create apps directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(&request.encrypted()?)
+        .map_err(|e| format!("Failed to serialize deploy request: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write deploy request: {}", e))
+}
+
+/// Loads the last deploy request stored for an app.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Some(DeployRequest)` if a request was stored for this app.
+/// * `None` otherwise.
+pub fn load_deploy_request(app_name: &str) -> Option<DeployRequest> {
+    let path = deploy_request_path(app_name).ok()?;
+    let content = fs::read_to_string(path).ok()?;
+    let stored: DeployRequest = serde_json::from_str(&content).ok()?;
+    match stored.decrypted() {
+        Ok(request) => Some(request),
+        Err(e) => {
+            error!(
+                "❌ Failed to decrypt the deploy request of {}: {}",
+                app_name, e
+            );
+            None
+        }
+    }
+}
+
+/// Encrypts the stored deploy requests again with the current master key, when they hold
+/// values in clear or encrypted with a previous master key.
+///
+/// # Returns
+/// * `Ok(usize)` containing the number of requests encrypted again.
+/// * `Err(String)` if a request could not be decrypted or written.
+pub fn reencrypt_deploy_requests() -> Result<usize, String> {
+    let dir = deploy_requests_dir()?;
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let entries =
+        fs::read_dir(&dir).map_err(|e| format!("Failed to read apps directory: {}", e))?;
+    let mut reencrypted = 0;
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        let Some(stored) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<DeployRequest>(&content).ok())
+        else {
+            continue;
+        };
+        if stored.needs_reencryption() {
+            save_deploy_request(&stored.decrypted()?)?;
+            reencrypted += 1;
+        }
+    }
+    Ok(reencrypted)
+}
+
+/// Deletes the deploy request stored for an app, if any.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(())` if no request is stored anymore.
+/// * `Err(String)` if the file could not be removed.
+pub fn delete_deploy_request(app_name: &str) -> Result<(), String> {
+    let path = deploy_request_path(app_name)?;
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to delete deploy request: {}", e)),
+    }
+}
+
+/// Loads the deploy request of a deployed app.
+///
+/// Falls back to the app registry when no request was stored for it.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(DeployRequest)` for the app.
+/// * `Err(String)` if the app is not deployed.
+pub async fn load_app_request(app_name: &str) -> Result<DeployRequest, String> {
+    if let Some(request) = load_deploy_request(app_name) {
+        return Ok(request);
+    }
+
+    find_registered_app(app_name)?
+        .as_ref()
+        .map(DeployRequest::from_app_info)
+        .ok_or_else(|| format!("Application {} not found", app_name))
+}
+
+/// Checks that an app name can be used to deploy a repository.
+///
+/// The name must not be used by a Nephelios service (e.g., "traefik"), nor by an app
+/// deployed from another repository. Redeploying an app from its repository is allowed.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `github_url` - The repository to deploy.
+///
+/// # Returns
+/// * `Ok(())` if the name is available.
+/// * `Err(String)` with the reason the name is refused.
+pub async fn check_app_name_available(app_name: &str, github_url: &str) -> Result<(), String> {
+    let reserved =
+        reserved_service_names().map_err(|e| format!("Failed to read the stack file: {}", e))?;
+    if reserved.iter().any(|name| name == app_name) {
+        return Err(format!(
+            "app_name {} is reserved by a Nephelios service",
+            app_name
+        ));
+    }
+
+    if let Ok(existing) = load_app_request(app_name).await {
+        if normalize_repo_url(&existing.github_url) != normalize_repo_url(github_url) {
+            return Err(format!(
+                "app_name {} is already used by an app deployed from {}",
+                app_name, existing.github_url
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Deploys the services of an application from the stack file: the app service and the
+/// services of its compose file.
+///
+/// The other services of the stack are left untouched, see `deploy_stack_service`.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(())` if every service was deployed.
+/// * `Err(String)` if the stack file could not be read, or a service could not be deployed.
+pub async fn deploy_app_services(app_name: &str) -> Result<(), String> {
+    deploy_stack_service(app_name).await?;
+    for service in list_compose_services(app_name)? {
+        deploy_stack_service(&service).await?;
+    }
+    Ok(())
+}
+
+/// Applies the routing configuration of a deploy request to the running app.
+///
+/// Regenerates the Traefik labels of the app, stores the request and redeploys the app.
+///
+/// # Arguments
+/// * `request` - The deploy request holding the new routing configuration.
+///
+/// # Returns
+/// * `Ok(())` if the routing was applied.
+/// * `Err(String)` if the stack file could not be updated or deployed.
+pub async fn apply_routing(request: &DeployRequest) -> Result<(), String> {
+    update_routing(&request.app_name, &request.routing).map_err(|e| {
+        format!(
+            "Failed to update routing for app {}: {}",
+            request.app_name, e
+        )
+    })?;
+
+    save_deploy_request(request)?;
+
+    deploy_app_services(&request.app_name)
+        .await
+        .map_err(|e| format!("Failed to deploy app {}: {}", request.app_name, e))
+}
+
+/// Applies the environment variables of a deploy request to the running app.
+///
+/// Sets the variables on the app service in the stack file, stores the request and redeploys
+/// the app, which replaces its tasks following the update policy of the service.
+///
+/// # Arguments
+/// * `request` - The deploy request holding the new environment variables.
+///
+/// # Returns
+/// * `Ok(())` if the variables were applied.
+/// * `Err(String)` if the stack file could not be updated or deployed.
+pub async fn apply_environment(request: &DeployRequest) -> Result<(), String> {
+    update_app_environment(&request.app_name, &request.app_environment()).map_err(|e| {
+        format!(
+            "Failed to update environment for app {}: {}",
+            request.app_name, e
+        )
+    })?;
+    update_compose_environment(request)?;
+
+    save_deploy_request(request)?;
+
+    deploy_app_services(&request.app_name)
+        .await
+        .map_err(|e| format!("Failed to deploy app {}: {}", request.app_name, e))
+}
+
+/// Applies the secrets of a deploy request to the running app.
+///
+/// Stores the pending secret values as Docker secrets, mounts the secrets into the app service
+/// in the stack file, stores the request and redeploys the app. The secrets replaced by a
+/// new value are removed in the background once the rolling update no longer needs them.
+///
+/// # Arguments
+/// * `request` - The deploy request holding the new secrets.
+/// * `removed` - The secrets the request no longer references, removed in the background too.
+///
+/// # Returns
+/// * `Ok(())` if the secrets were applied.
+/// * `Err(String)` if a secret could not be created, or the stack file could not be updated
+///   or deployed.
+pub async fn apply_secrets(
+    request: &mut DeployRequest,
+    removed: Vec<String>,
+) -> Result<(), String> {
+    let mut unused = store_pending_secrets(request)?;
+    unused.extend(removed);
+
+    update_app_secrets(&request.app_name, &request.secret_mounts()).map_err(|e| {
+        format!(
+            "Failed to update secrets for app {}: {}",
+            request.app_name, e
+        )
+    })?;
+    apply_environment(request).await?;
+
+    tokio::spawn(remove_unused_secrets(unused).in_current_span());
+    Ok(())
+}
+
+/// Generates the release tag of a new build, from the current time.
+fn release_tag() -> String {
+    format!("r{}", Utc::now().format("%Y%m%d-%H%M%S"))
+}
+
+/// Returns the registry image of an application release.
+pub fn release_image(app_name: &str, release: &str) -> String {
+    format!("{}:{}", config().registry.repository(app_name), release)
+}
+
+/// Sends an error status for the app and returns the error message.
+fn report_error(app_name: &str, message: String) -> String {
+    send_deployment_status(
+        app_name,
+        DeploymentEvent::Failed {
+            message: message.clone(),
+        },
+    );
+    message
+}
+
+/// Waits for the tasks of a new release, and rolls the app back if they do not start.
+///
+/// A `RolledBack` event is published when the previous image is restored.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `image` - The image of the new release.
+/// * `previous_image` - The image the app ran before the deploy.
+///
+/// # Returns
+/// * `Ok(())` if the new release is running.
+/// * `Err(String)` describing why the release was rolled back.
+async fn check_rollout(app_name: &str, image: &str, previous_image: &str) -> Result<(), String> {
+    let replicas = app_replicas(app_name).ok().flatten().unwrap_or(1);
+    let timeout = Duration::from_secs(config().features.rollout_timeout);
+    let Err(reason) = wait_for_rollout(app_name, image, replicas, timeout).await else {
+        return Ok(());
+    };
+
+    warn!(
+        "↩️ Rolling {} back to {}: {}",
+        app_name, previous_image, reason
+    );
+    if let Err(e) = update_app_image(app_name, previous_image) {
+        return Err(report_error(
+            app_name,
+            format!("{}, and the rollback failed: {}", reason, e),
+        ));
+    }
+    if let Err(e) = deploy_app_services(app_name).await {
+        return Err(report_error(
+            app_name,
+            format!("{}, and the rollback failed: {}", reason, e),
+        ));
+    }
+
+    send_deployment_status(
+        app_name,
+        DeploymentEvent::RolledBack {
+            image: previous_image.to_string(),
+            reason: reason.clone(),
+        },
+    );
+    Err(report_error(
+        app_name,
+        format!(
+            "The new release did not start ({}), rolled back to {}",
+            reason, previous_image
+        ),
+    ))
+}
+
+/// Clones, builds, pushes and deploys an application.
+///
+/// Progress is reported step by step on the event bus. The temporary clone directory is
+/// removed whatever the outcome.
+///
+/// # Arguments
+/// * `request` - The deploy request describing the application.
+///
+/// # Returns
+/// * `Ok(Value)` containing the deployed application details.
+/// * `Err(String)` if any step of the deployment fails.
+pub async fn deploy_app(mut request: DeployRequest) -> Result<Value, String> {
+    // The new release is built with the current settings, the request is saved once it succeeded
+    request.needs_rebuild = false;
+    let app_name = request.app_name.clone();
+    let app_name = app_name.as_str();
+
+    if request.github_url.is_empty() && request.image.is_none() {
+        return Err(report_error(
+            app_name,
+            "GitHub URL or image is required".to_string(),
+        ));
+    }
+
+    let replaced_secrets = store_pending_secrets(&mut request)
+        .map_err(|e| report_error(app_name, format!("Failed to store secrets: {}", e)))?;
+
+    let mut metadata = AppMetadata::new(
+        request.app_name.clone(),
+        request.app_type.clone(),
+        request.github_url.clone(),
+        request.git_ref.clone(),
+    );
+
+    let release = release_tag();
+    if let Some(image) = request.image.clone() {
+        // Published images are deployed as they are, without a clone nor a build
+        request.compose = None;
+        release_app(&request, &metadata, &image, &release).await?;
+    } else {
+        // Clone repository
+        send_deployment_status(app_name, DeploymentEvent::CloneStarted);
+        let temp_dir = match create_temp_dir(app_name) {
+            Ok(dir) => dir,
+            Err(e) => {
+                return Err(report_error(
+                    app_name,
+                    format!("Failed to create temp directory: {}", e),
+                ))
+            }
+        };
+
+        let result = build_and_deploy(&mut request, &mut metadata, &release, &temp_dir).await;
+
+        if let Err(e) = remove_temp_dir(&temp_dir) {
+            warn!("Failed to clean up temp directory: {}", e);
+        }
+
+        result?;
+    }
+
+    if let Err(e) = save_deploy_request(&request) {
+        warn!("Failed to save deploy request: {}", e);
+    }
+    tokio::spawn(remove_unused_secrets(replaced_secrets).in_current_span());
+
+    tokio::spawn(
+        async move {
+            let res_prune_images = prune_images().await;
+            match res_prune_images {
+                Ok(_) => info!("✅ Docker images pruned successfully"),
+                Err(e) => error!("❌ Failed to prune Docker images: {}", e),
+            }
+        }
+        .in_current_span(),
+    );
+
+    // Get both the app status and swarm service name
+    let (status, swarm_name) = get_app_details(app_name.to_string()).await;
+    if let Err(e) = refresh_registered_app(app_name).await {
+        warn!("Failed to register app {}: {}", app_name, e);
+    }
+
+    let response = json!({
+        "message": "Application created successfully",
+        "app_name": app_name,
+        "app_type": request.app_type,
+        "github_url": request.github_url,
+        "git_ref": metadata.git_ref,
+        "commit_sha": metadata.commit_sha,
+        "commit_message": metadata.commit_message,
+        "image": app_image(app_name).ok().flatten(),
+        "release": release,
+        "status": status,
+        "swarm_task_name": swarm_name,
+        "domain": metadata.domain,
+        "created_at": metadata.created_at,
+    });
+
+    send_deployment_status(
+        app_name,
+        DeploymentEvent::AppDeployed {
+            app: response.clone(),
+        },
+    );
+
+    Ok(response)
+}
+
+/// Records the duration of a successful deploy pipeline stage.
+///
+/// # Arguments
+/// * `stage` - The stage: `clone`, `build`, `push` or `deploy`.
+/// * `app_type` - The type of the deployed app.
+/// * `started` - When the stage started.
+fn observe_stage(stage: &str, app_type: &str, started: Instant) {
+    DEPLOY_STAGE_DURATION
+        .with_label_values(&[stage, app_type])
+        .observe(started.elapsed().as_secs_f64());
+}
+
+/// Runs the clone, build, push and deploy steps inside the given temporary directory.
+async fn build_and_deploy(
+    request: &mut DeployRequest,
+    metadata: &mut AppMetadata,
+    release: &str,
+    temp_dir: &std::path::Path,
+) -> Result<(), String> {
+    let app_name = request.app_name.as_str();
+
+    let temp_dir_path = match temp_dir.to_str() {
+        Some(path) => path,
+        None => {
+            send_deployment_status(
+                app_name,
+                DeploymentEvent::Failed {
+                    message: "Invalid temp directory path".to_string(),
+                },
+            );
+            return Err("Temp directory path is invalid".to_string());
+        }
+    };
+
+    let credentials =
+        match resolve_git_credentials(request.git_token.as_deref(), request.deploy_key.as_deref())
+            .await
+        {
+            Ok(credentials) => credentials,
+            Err(e) => {
+                return Err(report_error(
+                    app_name,
+                    format!("Failed to resolve git credentials: {}", e),
+                ))
+            }
+        };
+
+    let clone_options = CloneOptions {
+        git_ref: request.git_ref.clone(),
+        depth: request.clone_depth.unwrap_or_else(default_clone_depth),
+        recurse_submodules: request.recurse_submodules,
+    };
+
+    // Clone in a blocking task, publishing the transfer progress
+    let github_url = request.github_url.clone();
+    let target_dir = temp_dir_path.to_string();
+    let clone_credentials = credentials.clone();
+    let progress_app_name = app_name.to_string();
+    let clone_started = Instant::now();
+    let clone_result = tokio::task::spawn_blocking(move || {
+        let mut last_percent = None;
+        let mut progress = |received: usize, total: usize| {
+            let percent = (received * 100).checked_div(total).unwrap_or(0);
+            if last_percent == Some(percent) {
+                return;
+            }
+            last_percent = Some(percent);
+            send_deployment_status(
+                &progress_app_name,
+                DeploymentEvent::CloneProgress {
+                    received_objects: received,
+                    total_objects: total,
+                    percent,
+                },
+            );
+        };
+
+        clone_repo(
+            &github_url,
+            &target_dir,
+            &clone_options,
+            &clone_credentials,
+            &mut progress,
+        )
+    })
+    .await
+    .unwrap_or_else(|e| Err(format!("Clone task failed: {}", e)));
+
+    if let Err(e) = clone_result {
+        return Err(report_error(
+            app_name,
+            format!("Failed to clone repository: {}", e),
+        ));
+    }
+    observe_stage("clone", &request.app_type, clone_started);
+
+    match head_commit(temp_dir_path) {
+        Ok((commit_sha, commit_message)) => {
+            metadata.commit_sha = Some(commit_sha);
+            metadata.commit_message = Some(commit_message);
+        }
+        Err(e) => warn!("Failed to read deployed commit: {}", e),
+    }
+
+    let commit_status = match (&credentials, &metadata.commit_sha) {
+        (GitCredentials::Token { token, .. }, Some(commit_sha)) if commit_status_enabled() => {
+            Some((token.clone(), commit_sha.clone()))
+        }
+        _ => None,
+    };
+
+    // The pending status is reported while the app builds
+    let github_url = request.github_url.clone();
+    let pending_description = format!("Deploying {}", metadata.domain);
+    let pending = async {
+        if let Some((token, commit_sha)) = &commit_status {
+            report_commit_status(
+                &github_url,
+                token,
+                commit_sha,
+                CommitState::Pending,
+                &pending_description,
+            )
+            .await;
+        }
+    };
+    let (_, result) = tokio::join!(
+        pending,
+        build_and_release(request, metadata, release, temp_dir_path)
+    );
+
+    if let Some((token, commit_sha)) = &commit_status {
+        let (state, description) = match &result {
+            Ok(_) => (
+                CommitState::Success,
+                format!("Deployed to {}", metadata.domain),
+            ),
+            Err(e) => (CommitState::Failure, e.clone()),
+        };
+        report_commit_status(&request.github_url, token, commit_sha, state, &description).await;
+    }
+
+    result
+}
+
+/// Generates the Dockerfile, then builds, pushes and deploys the cloned application.
+///
+/// When the repository has a compose file, its buildable services are built from their own
+/// Dockerfile instead, and its other services are deployed next to the app. The services of
+/// the compose file are recorded in the request.
+async fn build_and_release(
+    request: &mut DeployRequest,
+    metadata: &AppMetadata,
+    release: &str,
+    temp_dir_path: &str,
+) -> Result<(), String> {
+    let app_name = request.app_name.clone();
+    let app_name = app_name.as_str();
+
+    request.compose = match load_compose(Path::new(temp_dir_path)) {
+        Ok(compose) => compose,
+        Err(e) => {
+            return Err(report_error(
+                app_name,
+                format!("Failed to read the compose file: {}", e),
+            ))
+        }
+    };
+    let request = &*request;
+
+    // The image of each service is built from its context, the app one from the repository
+    let builds: Vec<_> = match &request.compose {
+        Some(compose) => {
+            info!(
+                "🧩 Deploying {} from {}, routing {}",
+                app_name, compose.file, compose.web
+            );
+            compose
+                .services
+                .iter()
+                .filter_map(|service| {
+                    let context = service.context.as_deref()?;
+                    Some((
+                        compose.image_name(app_name, service),
+                        Path::new(temp_dir_path).join(context),
+                        service.dockerfile.as_deref().unwrap_or("Dockerfile"),
+                    ))
+                })
+                .collect()
+        }
+        None => vec![(
+            app_name.to_string(),
+            Path::new(temp_dir_path).to_path_buf(),
+            "Dockerfile",
+        )],
+    };
+
+    // The contexts are archived while the Dockerfile is generated, the generated one is added
+    // to the archive last
+    let deferred: &[&str] = if request.compose.is_none() {
+        &["Dockerfile"]
+    } else {
+        &[]
+    };
+    let mut contexts = Vec::new();
+    for (name, context, _) in &builds {
+        match start_docker_context(&context.to_string_lossy(), deferred) {
+            Ok(context) => contexts.push(context),
+            Err(e) => {
+                return Err(report_error(
+                    app_name,
+                    format!("Failed to archive the context of {}: {}", name, e),
+                ))
+            }
+        }
+    }
+    if request.compose.is_none() {
+        if let Err(e) = generate_and_write_dockerfile(
+            &request.app_type,
+            temp_dir_path,
+            metadata,
+            &request.install_command,
+            &request.run_command,
+            &request.build_command,
+            &request.app_workdir,
+            Some(&request.additional_inputs),
+            request.security.user.as_deref(),
+        ) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to generate Dockerfile: {}", e),
+            ));
+        }
+    }
+    // A web service running a published image is not built
+    let image = request
+        .compose
+        .as_ref()
+        .and_then(ComposeApp::web_service)
+        .and_then(|service| service.image.clone())
+        .unwrap_or_else(|| release_image(app_name, release));
+
+    send_deployment_status(
+        app_name,
+        DeploymentEvent::CloneSucceeded {
+            commit_sha: metadata.commit_sha.clone(),
+            commit_message: metadata.commit_message.clone(),
+        },
+    );
+
+    // Build Docker images, each one is pushed and signed while the next ones build
+    send_deployment_status(app_name, DeploymentEvent::BuildStarted);
+    let build_started = Instant::now();
+    let builds = &builds;
+    let (built, mut to_push) = mpsc::unbounded_channel::<&str>();
+    let build_all = async move {
+        for ((name, _, dockerfile), context) in builds.iter().zip(contexts) {
+            build_image(name, context, dockerfile, metadata)
+                .await
+                .map_err(|e| format!("Failed to build Docker image {}: {}", name, e))?;
+            let _ = built.send(name);
+        }
+        observe_stage("build", &request.app_type, build_started);
+        send_deployment_status(app_name, DeploymentEvent::BuildSucceeded);
+        Ok::<_, String>(())
+    };
+    let push_all = async {
+        let mut push_started = None;
+        while let Some(name) = to_push.recv().await {
+            push_started.get_or_insert_with(Instant::now);
+            push_image(name, release)
+                .await
+                .map_err(|e| format!("Failed to push Docker image {}: {}", name, e))?;
+            sign_image(&release_image(name, release))?;
+        }
+        if let Some(push_started) = push_started {
+            observe_stage("push", &request.app_type, push_started);
+        }
+        Ok::<_, String>(())
+    };
+    if let Err(e) = tokio::try_join!(build_all, push_all) {
+        return Err(report_error(app_name, e));
+    }
+
+    release_app(request, metadata, &image, release).await
+}
+
+/// Points the app service to the image of a release, with the settings of the app, and
+/// deploys the stack.
+///
+/// The app is added to the stack file on its first deploy. Otherwise the release is rolled
+/// back if its tasks do not start, when `AUTO_ROLLBACK` is enabled. The images of the app and
+/// of its compose services must be signed by a trusted key when the signing policy is
+/// `enforce`, and are then deployed by digest.
+async fn release_app(
+    request: &DeployRequest,
+    metadata: &AppMetadata,
+    image: &str,
+    release: &str,
+) -> Result<(), String> {
+    let app_name = request.app_name.as_str();
+
+    let image = match verify_image(image).await {
+        Ok(image) => image,
+        Err(e) => return Err(report_error(app_name, e)),
+    };
+    let image = image.as_str();
+    let mut service_images = compose_images(request, release);
+    for service_image in service_images.values_mut() {
+        match verify_image(service_image).await {
+            Ok(verified) => *service_image = verified,
+            Err(e) => return Err(report_error(app_name, e)),
+        }
+    }
+
+    send_deployment_status(app_name, DeploymentEvent::DeployStarted);
+    let deploy_started = Instant::now();
+    if let Ok(1) = verif_app(app_name) {
+        if let Err(e) = update_routing(app_name, &request.routing) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to update app routing: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_resources(app_name, request.resources.to_stack_resources()) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to update app resources: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_placement(app_name, request.placement.to_stack_placement()) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to update app placement: {}", e),
+            ));
+        }
+
+        if let Err(e) =
+            update_app_update_config(app_name, request.update_config.to_stack_update_config())
+        {
+            return Err(report_error(
+                app_name,
+                format!("Failed to update app update config: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_security(app_name, request.security.to_stack_security()) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to update app security options: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_volumes(app_name, &request.volume_mounts()) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to update app volumes: {}", e),
+            ));
+        }
+
+        let previous_image = app_image(app_name).ok().flatten();
+        if let Err(e) = update_app_image(app_name, image) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to update app image: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_environment(app_name, &request.app_environment()) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to update app environment: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_secrets(app_name, &request.secret_mounts()) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to update app secrets: {}", e),
+            ));
+        }
+
+        let removed = match update_compose_services(request, &service_images) {
+            Ok(removed) => removed,
+            Err(e) => return Err(report_error(app_name, e)),
+        };
+
+        if let Err(e) = deploy_app_services(app_name).await {
+            return Err(report_error(
+                app_name,
+                format!("Failed to update deployment: {}", e),
+            ));
+        }
+        remove_compose_services(&removed).await;
+
+        if let Some(previous_image) = previous_image.filter(|_| config().features.auto_rollback) {
+            check_rollout(app_name, image, &previous_image).await?;
+        }
+    } else {
+        if let Err(e) = add_to_deploy(
+            app_name,
+            metadata,
+            &request.routing,
+            request.resources.to_stack_resources(),
+        ) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to add app to deploy file: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_placement(app_name, request.placement.to_stack_placement()) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to set app placement: {}", e),
+            ));
+        }
+
+        if let Err(e) =
+            update_app_update_config(app_name, request.update_config.to_stack_update_config())
+        {
+            return Err(report_error(
+                app_name,
+                format!("Failed to set app update config: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_security(app_name, request.security.to_stack_security()) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to set app security options: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_volumes(app_name, &request.volume_mounts()) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to set app volumes: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_image(app_name, image) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to set app image: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_environment(app_name, &request.app_environment()) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to set app environment: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_app_secrets(app_name, &request.secret_mounts()) {
+            return Err(report_error(
+                app_name,
+                format!("Failed to set app secrets: {}", e),
+            ));
+        }
+
+        if let Err(e) = update_compose_services(request, &service_images) {
+            return Err(report_error(app_name, e));
+        }
+
+        if let Err(e) = deploy_app_services(app_name).await {
+            return Err(report_error(
+                app_name,
+                format!("Failed to start deployment: {}", e),
+            ));
+        }
+    }
+
+    observe_stage("deploy", &request.app_type, deploy_started);
+    send_deployment_status(app_name, DeploymentEvent::DeploySucceeded);
+
+    Ok(())
+}
+
+/// Finds the release an app should be rolled back to.
+///
+/// Only successful deployments with a versioned image can be rolled back to. Without a
+/// target, the most recent one whose image differs from the running image is picked.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `target` - A deployment ID, release tag or image reference to roll back to.
+///
+/// # Returns
+/// * `Ok(Deployment)` containing the deployment of the target release.
+/// * `Err(String)` if no matching release was found.
+pub fn find_rollback_target(app_name: &str, target: Option<&str>) -> Result<Deployment, String> {
+    let current = app_image(app_name)
+        .map_err(|e| format!("Failed to read the image of app {}: {}", app_name, e))?
+        .ok_or_else(|| format!("App {} is not deployed", app_name))?;
+
+    let mut releases = load_history(app_name)?.into_iter().filter(|deployment| {
+        deployment.state == DeploymentState::Succeeded
+            && deployment
+                .image
+                .as_deref()
+                .is_some_and(|image| !image.ends_with(":latest"))
+    });
+
+    match target {
+        Some(target) => releases
+            .find(|deployment| {
+                let image = deployment.image.as_deref().unwrap_or_default();
+                deployment.id == target
+                    || image == target
+                    || image.rsplit_once(':').map(|(_, tag)| tag) == Some(target)
+            })
+            .ok_or_else(|| format!("Release {} not found for app {}", target, app_name)),
+        None => releases
+            .find(|deployment| deployment.image.as_deref() != Some(current.as_str()))
+            .ok_or_else(|| format!("No previous release to roll back to for app {}", app_name)),
+    }
+}
+
+/// Rolls an app back to the image of a previous release.
+///
+/// The service image is updated in the stack file and the stack is redeployed, once its
+/// signature is verified when the signing policy is `enforce`. Progress is reported on the
+/// event bus.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `target` - The deployment of the release to roll back to.
+///
+/// # Returns
+/// * `Ok(Value)` containing the rolled back release details.
+/// * `Err(String)` if the stack could not be updated.
+pub async fn rollback_app(app_name: &str, target: Deployment) -> Result<Value, String> {
+    let image = target.image.clone().unwrap_or_default();
+    send_deployment_status(
+        app_name,
+        DeploymentEvent::RollbackStarted {
+            image: image.clone(),
+        },
+    );
+
+    let verified_image = match verify_image(&image).await {
+        Ok(verified_image) => verified_image,
+        Err(e) => return Err(report_error(app_name, e)),
+    };
+
+    let previous_image = app_image(app_name).ok().flatten();
+
+    if let Err(e) = update_app_image(app_name, &verified_image) {
+        return Err(report_error(
+            app_name,
+            format!("Failed to update app image: {}", e),
+        ));
+    }
+
+    if let Err(e) = deploy_app_services(app_name).await {
+        return Err(report_error(
+            app_name,
+            format!("Failed to deploy app {}: {}", app_name, e),
+        ));
+    }
+
+    let response = json!({
+        "message": "Application rolled back successfully",
+        "app_name": app_name,
+        "image": image,
+        "git_ref": target.git_ref,
+        "commit_sha": target.commit_sha,
+        "rolled_back_from": previous_image,
+        "target_deployment_id": target.id,
+    });
+
+    send_deployment_status(
+        app_name,
+        DeploymentEvent::RollbackSucceeded {
+            image,
+            details: response.clone(),
+        },
+    );
+
+    Ok(response)
+}
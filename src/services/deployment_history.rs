@@ -0,0 +1,188 @@
+use crate::services::database::{enum_column, with_connection};
+use crate::services::deployment_tracker::{Deployment, DeploymentState};
+use chrono::Utc;
+use dirs::home_dir;
+use rusqlite::{params, OptionalExtension, Row};
+use std::fs;
+use tracing::{info, warn};
+
+/// Error recorded on deployments that were still running when Nephelios stopped.
+const INTERRUPTED_ERROR: &str = "Interrupted by a restart of Nephelios";
+
+const DEPLOYMENT_COLUMNS: &str = "id, app_name, initiator, kind, state, stage, created_at, \
+     started_at, updated_at, finished_at, duration_ms, git_ref, commit_sha, image, error, \
+     result, log_path, priority";
+
+/// Reads a deployment from a row selected with `DEPLOYMENT_COLUMNS`.
+fn deployment_from_row(row: &Row) -> rusqlite::Result<Deployment> {
+    Ok(Deployment {
+        id: row.get(0)?,
+        app_name: row.get(1)?,
+        initiator: row.get(2)?,
+        kind: enum_column(row, 3)?,
+        priority: enum_column(row, 17)?,
+        state: enum_column(row, 4)?,
+        stage: row.get(5)?,
+        created_at: row.get(6)?,
+        started_at: row.get(7)?,
+        updated_at: row.get(8)?,
+        finished_at: row.get(9)?,
+        duration_ms: row.get(10)?,
+        git_ref: row.get(11)?,
+        commit_sha: row.get(12)?,
+        image: row.get(13)?,
+        error: row.get(14)?,
+        result: row.get(15)?,
+        log_path: row.get(16)?,
+    })
+}
+
+/// Saves a deployment in the history, replacing its previous record.
+///
+/// # Arguments
+/// * `deployment` - The deployment, in its current state.
+///
+/// # Returns
+/// * `Ok(())` if the deployment is saved.
+/// * `Err(String)` if the database could not be updated.
+pub fn save_deployment(deployment: &Deployment) -> Result<(), String> {
+    with_connection(|connection| {
+        connection.execute(
+            &format!(
+                "INSERT OR REPLACE INTO deployments ({}) VALUES \
+                 (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+                DEPLOYMENT_COLUMNS
+            ),
+            params![
+                deployment.id,
+                deployment.app_name,
+                deployment.initiator,
+                deployment.kind.as_str(),
+                deployment.state.as_str(),
+                deployment.stage,
+                deployment.created_at,
+                deployment.started_at,
+                deployment.updated_at,
+                deployment.finished_at,
+                deployment.duration_ms,
+                deployment.git_ref,
+                deployment.commit_sha,
+                deployment.image,
+                deployment.error,
+                deployment.result,
+                deployment.log_path,
+                deployment.priority.as_str(),
+            ],
+        )
+    })?;
+    Ok(())
+}
+
+/// Finds a deployment of the history by ID.
+///
+/// # Arguments
+/// * `id` - The deployment ID.
+///
+/// # Returns
+/// * `Ok(Some(Deployment))` if the deployment was recorded, `Ok(None)` otherwise.
+/// * `Err(String)` if the database could not be read.
+pub fn find_deployment(id: &str) -> Result<Option<Deployment>, String> {
+    with_connection(|connection| {
+        connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM deployments WHERE id = ?1",
+                    DEPLOYMENT_COLUMNS
+                ),
+                [id],
+                deployment_from_row,
+            )
+            .optional()
+    })
+}
+
+/// Loads the finished deployments of an app, most recent first.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(Vec<Deployment>)` containing the finished deployments, empty if none was recorded.
+/// * `Err(String)` if the database could not be read.
+pub fn load_history(app_name: &str) -> Result<Vec<Deployment>, String> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM deployments
+             WHERE app_name = ?1 AND finished_at IS NOT NULL
+             ORDER BY created_at DESC",
+            DEPLOYMENT_COLUMNS
+        ))?;
+        let deployments = statement.query_map([app_name], deployment_from_row)?;
+        deployments.collect()
+    })
+}
+
+/// Marks the deployments left queued or running by a previous run as failed.
+///
+/// Called at startup, before any deployment is created.
+pub fn fail_interrupted_deployments() -> Result<(), String> {
+    let now = Utc::now();
+    let interrupted = with_connection(|connection| {
+        connection.execute(
+            "UPDATE deployments SET state = ?1, error = ?2, updated_at = ?3, finished_at = ?3
+             WHERE finished_at IS NULL",
+            params![DeploymentState::Failed.as_str(), INTERRUPTED_ERROR, now],
+        )
+    })?;
+    if interrupted > 0 {
+        warn!(
+            "{} deployment(s) were interrupted by a restart",
+            interrupted
+        );
+    }
+    Ok(())
+}
+
+/// Imports the deployment history files written by earlier versions into the database.
+///
+/// Each file is removed once its deployments are saved, so the import runs once.
+pub fn import_history_files() -> Result<(), String> {
+    let home = home_dir().ok_or("Failed to find home directory")?;
+    let dir = home.join(".config/nephelios/history");
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    let entries =
+        fs::read_dir(&dir).map_err(|e| format!("Failed to read history directory: {}", e))?;
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        let history: Vec<Deployment> = match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        {
+            Ok(history) => history,
+            Err(e) => {
+                warn!("Skipping history file {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        for deployment in &history {
+            if find_deployment(&deployment.id)?.is_none() {
+                save_deployment(deployment)?;
+            }
+        }
+        fs::remove_file(&path)
+            .map_err(|e| format!("Failed to remove history file {}: {}", path.display(), e))?;
+        info!(
+            "🗄️ Imported {} deployment(s) from {}",
+            history.len(),
+            path.display()
+        );
+    }
+    let _ = fs::remove_dir(&dir);
+    Ok(())
+}
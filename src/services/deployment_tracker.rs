@@ -0,0 +1,643 @@
+use crate::config::config;
+use crate::metrics::{DEPLOYMENTS, DEPLOYMENTS_QUEUED};
+use crate::services::deployment::{deploy_app, load_deploy_request, rollback_app, DeployRequest};
+use crate::services::deployment_history::{find_deployment, load_history, save_deployment};
+use crate::services::events::{publish, Event};
+use crate::services::helpers::lock_helper::lock_app;
+use chrono::{DateTime, Utc};
+use dirs::home_dir;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tokio::sync::Notify;
+use tracing::{error, info, info_span, warn, Instrument};
+use uuid::Uuid;
+
+/// Number of deployments kept in memory; the oldest finished ones are dropped first.
+const MAX_TRACKED_DEPLOYMENTS: usize = 1000;
+
+/// The lifecycle state of a deployment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentState {
+    Queued,
+    InProgress,
+    Succeeded,
+    Failed,
+    /// Removed from the queue before it started.
+    Cancelled,
+}
+
+impl DeploymentState {
+    /// The name of the state, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeploymentState::Queued => "queued",
+            DeploymentState::InProgress => "in_progress",
+            DeploymentState::Succeeded => "succeeded",
+            DeploymentState::Failed => "failed",
+            DeploymentState::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// What a deployment does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentKind {
+    /// Builds and releases the app from its repository.
+    #[default]
+    Deploy,
+    /// Re-points the app to the image of a previous release.
+    Rollback,
+}
+
+impl DeploymentKind {
+    /// The name of the kind, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeploymentKind::Deploy => "deploy",
+            DeploymentKind::Rollback => "rollback",
+        }
+    }
+}
+
+/// How early a queued deployment starts: higher priorities first, then in order of creation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentPriority {
+    /// E.g., preview environments.
+    Low,
+    #[default]
+    Normal,
+    /// E.g., production apps.
+    High,
+}
+
+impl DeploymentPriority {
+    /// The name of the priority, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeploymentPriority::Low => "low",
+            DeploymentPriority::Normal => "normal",
+            DeploymentPriority::High => "high",
+        }
+    }
+}
+
+/// Why a queued deployment could not be changed.
+#[derive(Debug)]
+pub enum QueueError {
+    /// No tracked deployment has the ID.
+    NotFound,
+    /// The deployment already left the queue, in the given state.
+    NotQueued(DeploymentState),
+}
+
+/// A deployment job and its progress.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deployment {
+    pub id: String,
+    pub app_name: String,
+    /// Who or what triggered the deployment (e.g., "api-key:1a2b3c4d", "github-webhook").
+    pub initiator: String,
+    #[serde(default)]
+    pub kind: DeploymentKind,
+    #[serde(default)]
+    pub priority: DeploymentPriority,
+    pub state: DeploymentState,
+    /// The last step reported for the deployment (e.g., "Building Docker image").
+    pub stage: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+    pub git_ref: Option<String>,
+    pub commit_sha: Option<String>,
+    pub image: Option<String>,
+    pub error: Option<String>,
+    /// The deployed application details, once the deployment succeeded.
+    pub result: Option<Value>,
+    /// The file the status updates of the deployment are written to.
+    #[serde(default)]
+    pub log_path: Option<String>,
+}
+
+#[derive(Default)]
+struct Tracker {
+    deployments: HashMap<String, Deployment>,
+    /// The running deployment of each app, used to attribute status updates.
+    active: HashMap<String, String>,
+}
+
+lazy_static! {
+    static ref TRACKER: RwLock<Tracker> = RwLock::new(Tracker::default());
+    /// Wakes the queued deployments when one may start: a deployment finished, or the queue
+    /// changed.
+    static ref QUEUE_CHANGED: Notify = Notify::new();
+}
+
+/// Whether a queued deployment starts, see `Tracker::try_start`.
+enum Turn {
+    Wait,
+    Started(Box<Deployment>),
+    Cancelled,
+}
+
+impl Tracker {
+    /// Updates the `nephelios_deployments_queued` gauge.
+    fn update_queue_depth(&self) {
+        let queued = self
+            .deployments
+            .values()
+            .filter(|deployment| deployment.state == DeploymentState::Queued)
+            .count();
+        DEPLOYMENTS_QUEUED.set(queued as i64);
+    }
+
+    /// Returns the queued deployments, in the order they start.
+    fn queue(&self) -> Vec<&Deployment> {
+        let mut queue: Vec<&Deployment> = self
+            .deployments
+            .values()
+            .filter(|deployment| deployment.state == DeploymentState::Queued)
+            .collect();
+        queue.sort_by(|a, b| {
+            b.priority
+                .cmp(&a.priority)
+                .then(a.created_at.cmp(&b.created_at))
+                .then(a.id.cmp(&b.id))
+        });
+        queue
+    }
+
+    /// Starts a queued deployment if it is its turn: it is the first queued deployment whose
+    /// app has no running deployment, and fewer than `max_concurrent_deployments` run.
+    fn try_start(&mut self, id: &str) -> Turn {
+        match self.deployments.get(id).map(|deployment| deployment.state) {
+            Some(DeploymentState::Queued) => {}
+            Some(DeploymentState::Cancelled) | None => return Turn::Cancelled,
+            Some(_) => return Turn::Wait,
+        }
+        let limit = config().features.max_concurrent_deployments;
+        if limit > 0 && self.active.len() >= limit {
+            return Turn::Wait;
+        }
+        let next = self
+            .queue()
+            .into_iter()
+            .find(|deployment| !self.active.contains_key(&deployment.app_name))
+            .map(|deployment| deployment.id.clone());
+        if next.as_deref() != Some(id) {
+            return Turn::Wait;
+        }
+
+        let Some(deployment) = self.deployments.get_mut(id) else {
+            return Turn::Cancelled;
+        };
+        let now = Utc::now();
+        deployment.state = DeploymentState::InProgress;
+        deployment.started_at = Some(now);
+        deployment.updated_at = now;
+        let record = deployment.clone();
+        self.active.insert(record.app_name.clone(), id.to_string());
+        self.update_queue_depth();
+        Turn::Started(Box::new(record))
+    }
+
+    /// Drops the oldest finished deployments beyond `MAX_TRACKED_DEPLOYMENTS`.
+    fn evict(&mut self) {
+        while self.deployments.len() > MAX_TRACKED_DEPLOYMENTS {
+            let oldest = self
+                .deployments
+                .values()
+                .filter(|deployment| deployment.finished_at.is_some())
+                .min_by_key(|deployment| deployment.created_at)
+                .map(|deployment| deployment.id.clone());
+            match oldest {
+                Some(id) => self.deployments.remove(&id),
+                None => break,
+            };
+        }
+    }
+}
+
+/// Registers a new queued deployment for an app.
+///
+/// # Arguments
+/// * `app_name` - The name of the application being deployed.
+/// * `initiator` - Who or what triggered the deployment.
+/// * `priority` - How early the deployment leaves the queue.
+///
+/// # Returns
+/// The generated deployment ID.
+pub fn create_deployment(app_name: &str, initiator: &str, priority: DeploymentPriority) -> String {
+    create_job(app_name, initiator, DeploymentKind::Deploy, priority)
+}
+
+/// Registers a new queued job of the given kind for an app.
+fn create_job(
+    app_name: &str,
+    initiator: &str,
+    kind: DeploymentKind,
+    priority: DeploymentPriority,
+) -> String {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let deployment = Deployment {
+        id: id.clone(),
+        app_name: app_name.to_string(),
+        initiator: initiator.to_string(),
+        kind,
+        priority,
+        state: DeploymentState::Queued,
+        stage: None,
+        created_at: now,
+        started_at: None,
+        updated_at: now,
+        finished_at: None,
+        duration_ms: None,
+        git_ref: None,
+        commit_sha: None,
+        image: None,
+        error: None,
+        result: None,
+        log_path: log_path(&id).ok().map(|path| path.display().to_string()),
+    };
+    persist(&deployment);
+
+    let mut tracker = TRACKER.write().unwrap_or_else(|e| e.into_inner());
+    tracker.deployments.insert(id.clone(), deployment);
+    tracker.evict();
+    tracker.update_queue_depth();
+    id
+}
+
+/// Saves a deployment in the history, logging failures.
+fn persist(deployment: &Deployment) {
+    if let Err(e) = save_deployment(deployment) {
+        warn!("Failed to save deployment {}: {}", deployment.id, e);
+    }
+}
+
+/// Returns a deployment by ID, from the tracked deployments or the history.
+pub fn get_deployment(id: &str) -> Option<Deployment> {
+    let tracked = {
+        let tracker = TRACKER.read().unwrap_or_else(|e| e.into_inner());
+        tracker.deployments.get(id).cloned()
+    };
+    tracked.or_else(|| {
+        find_deployment(id).unwrap_or_else(|e| {
+            warn!("Failed to read deployment {}: {}", id, e);
+            None
+        })
+    })
+}
+
+/// Resolves the path of the log file of a deployment.
+fn log_path(id: &str) -> Result<PathBuf, String> {
+    let home = home_dir().ok_or("Failed to find home directory")?;
+    Ok(home.join(format!(".config/nephelios/logs/deployments/{}.log", id)))
+}
+
+/// Appends a line to the log file of a deployment.
+fn append_log(deployment: &Deployment, line: &str) -> Result<(), String> {
+    let Some(path) = deployment.log_path.as_deref().map(PathBuf::from) else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create log directory: {}", e))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open deployment log: {}", e))?;
+    writeln!(file, "{} {}", Utc::now().to_rfc3339(), line)
+        .map_err(|e| format!("Failed to write deployment log: {}", e))
+}
+
+/// Returns the ID of the running deployment of an app.
+pub fn active_deployment_id(app_name: &str) -> Option<String> {
+    let tracker = TRACKER.read().unwrap_or_else(|e| e.into_inner());
+    tracker.active.get(app_name).cloned()
+}
+
+/// Records a status update on the running deployment of an app, if any.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `status` - The reported status (e.g., "in_progress", "error").
+/// * `step` - The reported step, or the error message for errors.
+pub fn record_status(app_name: &str, status: &str, step: &str) {
+    let mut tracker = TRACKER.write().unwrap_or_else(|e| e.into_inner());
+    let Some(id) = tracker.active.get(app_name).cloned() else {
+        return;
+    };
+
+    if let Some(deployment) = tracker.deployments.get_mut(&id) {
+        deployment.updated_at = Utc::now();
+        if status == "error" {
+            deployment.error = Some(step.to_string());
+        } else {
+            deployment.stage = Some(step.to_string());
+        }
+        if let Err(e) = append_log(deployment, &format!("[{}] {}", status, step)) {
+            warn!("Failed to log deployment {}: {}", id, e);
+        }
+    }
+}
+
+/// Waits until a queued deployment may start, then marks it as started and makes it the
+/// running deployment of its app.
+///
+/// # Returns
+/// * `Ok(())` once the deployment started.
+/// * `Err(String)` if the deployment was cancelled while queued.
+async fn wait_for_turn(id: &str) -> Result<(), String> {
+    let record = loop {
+        // Registered before the check, so a change made in between still wakes the loop
+        let changed = QUEUE_CHANGED.notified();
+        tokio::pin!(changed);
+        changed.as_mut().enable();
+
+        let turn = {
+            let mut tracker = TRACKER.write().unwrap_or_else(|e| e.into_inner());
+            tracker.try_start(id)
+        };
+        match turn {
+            Turn::Started(record) => break *record,
+            Turn::Cancelled => return Err(format!("Deployment {} was cancelled", id)),
+            Turn::Wait => changed.await,
+        }
+    };
+
+    DEPLOYMENTS
+        .with_label_values(&[record.kind.as_str(), "started"])
+        .inc();
+    persist(&record);
+    publish(Event::DeploymentStarted {
+        app_name: record.app_name,
+        deployment_id: record.id,
+        kind: record.kind,
+        initiator: record.initiator,
+    });
+    Ok(())
+}
+
+/// Lists the queued deployments of every app, in the order they start.
+pub fn list_queued_deployments() -> Vec<Deployment> {
+    let tracker = TRACKER.read().unwrap_or_else(|e| e.into_inner());
+    tracker.queue().into_iter().cloned().collect()
+}
+
+/// Changes the priority of a queued deployment, moving it in the queue.
+///
+/// # Arguments
+/// * `id` - The deployment ID.
+/// * `priority` - The new priority.
+///
+/// # Returns
+/// * `Ok(Deployment)` containing the updated deployment.
+/// * `Err(QueueError)` if the deployment is unknown or not queued anymore.
+pub fn set_deployment_priority(
+    id: &str,
+    priority: DeploymentPriority,
+) -> Result<Deployment, QueueError> {
+    let mut tracker = TRACKER.write().unwrap_or_else(|e| e.into_inner());
+    let deployment = tracker
+        .deployments
+        .get_mut(id)
+        .ok_or(QueueError::NotFound)?;
+    if deployment.state != DeploymentState::Queued {
+        return Err(QueueError::NotQueued(deployment.state));
+    }
+
+    deployment.priority = priority;
+    deployment.updated_at = Utc::now();
+    let record = deployment.clone();
+    drop(tracker);
+
+    persist(&record);
+    QUEUE_CHANGED.notify_waiters();
+    Ok(record)
+}
+
+/// Cancels a queued deployment, which then never starts.
+///
+/// # Arguments
+/// * `id` - The deployment ID.
+///
+/// # Returns
+/// * `Ok(Deployment)` containing the cancelled deployment.
+/// * `Err(QueueError)` if the deployment is unknown or not queued anymore.
+pub fn cancel_deployment(id: &str) -> Result<Deployment, QueueError> {
+    let mut tracker = TRACKER.write().unwrap_or_else(|e| e.into_inner());
+    let deployment = tracker
+        .deployments
+        .get_mut(id)
+        .ok_or(QueueError::NotFound)?;
+    if deployment.state != DeploymentState::Queued {
+        return Err(QueueError::NotQueued(deployment.state));
+    }
+
+    let now = Utc::now();
+    deployment.state = DeploymentState::Cancelled;
+    deployment.updated_at = now;
+    deployment.finished_at = Some(now);
+    let record = deployment.clone();
+    tracker.update_queue_depth();
+    drop(tracker);
+
+    info!(
+        "🚫 Deployment {} of {} cancelled",
+        record.id, record.app_name
+    );
+    persist(&record);
+    QUEUE_CHANGED.notify_waiters();
+    publish(Event::DeploymentFinished {
+        app_name: record.app_name.clone(),
+        deployment_id: record.id.clone(),
+        kind: record.kind,
+        state: record.state,
+        error: None,
+    });
+    Ok(record)
+}
+
+/// Records the outcome of a deployment in the history.
+fn finish(id: &str, result: &Result<Value, String>) {
+    let mut tracker = TRACKER.write().unwrap_or_else(|e| e.into_inner());
+    let Some(deployment) = tracker.deployments.get_mut(id) else {
+        return;
+    };
+
+    let now = Utc::now();
+    deployment.updated_at = now;
+    deployment.finished_at = Some(now);
+    deployment.duration_ms = deployment
+        .started_at
+        .map(|started_at| (now - started_at).num_milliseconds());
+    match result {
+        Ok(value) => {
+            let field = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+            deployment.state = DeploymentState::Succeeded;
+            DEPLOYMENTS
+                .with_label_values(&[deployment.kind.as_str(), "succeeded"])
+                .inc();
+            deployment.git_ref = field("git_ref");
+            deployment.commit_sha = field("commit_sha");
+            deployment.image = field("image");
+            deployment.result = Some(value.clone());
+        }
+        Err(e) => {
+            deployment.state = DeploymentState::Failed;
+            DEPLOYMENTS
+                .with_label_values(&[deployment.kind.as_str(), "failed"])
+                .inc();
+            deployment.error = Some(e.clone());
+        }
+    }
+
+    let record = deployment.clone();
+    if tracker.active.get(&record.app_name).map(String::as_str) == Some(id) {
+        tracker.active.remove(&record.app_name);
+    }
+    drop(tracker);
+
+    QUEUE_CHANGED.notify_waiters();
+    persist(&record);
+    publish(Event::DeploymentFinished {
+        app_name: record.app_name,
+        deployment_id: record.id,
+        kind: record.kind,
+        state: record.state,
+        error: record.error,
+    });
+}
+
+/// Lists the deployments of an app: running ones first, then the history.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(Vec<Deployment>)` containing the deployments, most recent first.
+/// * `Err(String)` if the history could not be read.
+pub fn list_app_deployments(app_name: &str) -> Result<Vec<Deployment>, String> {
+    let mut running: Vec<Deployment> = {
+        let tracker = TRACKER.read().unwrap_or_else(|e| e.into_inner());
+        tracker
+            .deployments
+            .values()
+            .filter(|deployment| {
+                deployment.app_name == app_name && deployment.finished_at.is_none()
+            })
+            .cloned()
+            .collect()
+    };
+    running.sort_by_key(|deployment| std::cmp::Reverse(deployment.created_at));
+
+    running.extend(load_history(app_name)?);
+    Ok(running)
+}
+
+/// Runs a registered job, tracking its progress and outcome.
+///
+/// Jobs of the same app run one at a time: a job stays queued until the running job of its
+/// app finished, and until the queued jobs before it started. A job cancelled while queued
+/// is not run.
+async fn track<F>(id: &str, app_name: &str, job: F) -> Result<Value, String>
+where
+    F: Future<Output = Result<Value, String>>,
+{
+    let span = info_span!("deployment", deployment_id = %id, app_name = %app_name);
+    async {
+        wait_for_turn(id).await?;
+        let _lock = match lock_app(app_name).await {
+            Ok(lock) => lock,
+            Err(e) => {
+                let result = Err(e);
+                finish(id, &result);
+                return result;
+            }
+        };
+
+        info!("deployment started");
+        let result = job.await;
+        finish(id, &result);
+        result
+    }
+    .instrument(span)
+    .await
+}
+
+/// Runs a registered deployment, tracking its progress and outcome.
+///
+/// # Arguments
+/// * `id` - The deployment ID returned by `create_deployment`.
+/// * `request` - The deploy request describing the application.
+///
+/// # Returns
+/// The result of `deploy_app`.
+pub async fn run_deployment(id: &str, request: DeployRequest) -> Result<Value, String> {
+    let app_name = request.app_name.clone();
+    track(id, &app_name, deploy_app(request)).await
+}
+
+/// Registers a deployment and runs it in the background.
+///
+/// The deployment stays queued while another deployment of the app is running, or until
+/// its turn comes when `max_concurrent_deployments` deployments are running. Its priority is
+/// the priority of the app.
+///
+/// # Arguments
+/// * `request` - The deploy request describing the application.
+/// * `initiator` - Who or what triggered the deployment.
+///
+/// # Returns
+/// The generated deployment ID.
+pub fn spawn_deployment(request: DeployRequest, initiator: &str) -> String {
+    let id = create_deployment(&request.app_name, initiator, request.priority);
+    let deployment_id = id.clone();
+    tokio::spawn(async move {
+        let app_name = request.app_name.clone();
+        if let Err(e) = run_deployment(&deployment_id, request).await {
+            error!(deployment_id = %deployment_id, "❌ Deployment of {} failed: {}", app_name, e);
+        }
+    });
+    id
+}
+
+/// Registers a rollback and runs it in the background, with the priority of the app.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `target` - The deployment of the release to roll back to.
+/// * `initiator` - Who or what triggered the rollback.
+///
+/// # Returns
+/// The generated deployment ID.
+pub fn spawn_rollback(app_name: &str, target: Deployment, initiator: &str) -> String {
+    let priority = load_deploy_request(app_name)
+        .map(|request| request.priority)
+        .unwrap_or_default();
+    let id = create_job(app_name, initiator, DeploymentKind::Rollback, priority);
+    let deployment_id = id.clone();
+    let app_name = app_name.to_string();
+    tokio::spawn(async move {
+        let job = rollback_app(&app_name, target);
+        if let Err(e) = track(&deployment_id, &app_name, job).await {
+            error!(deployment_id = %deployment_id, "❌ Rollback of {} failed: {}", app_name, e);
+        }
+    });
+    id
+}
@@ -0,0 +1,271 @@
+use crate::config::{config, SmtpConfig, SmtpSecurity};
+use crate::services::database::with_connection;
+use crate::services::deployment_tracker::{DeploymentKind, DeploymentState};
+use crate::services::events::{consume_events, Event};
+use lazy_static::lazy_static;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Address, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rusqlite::params;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How long the SMTP server may take to accept a notification.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Minimum time between two notifications of the same kind for an app, so an app crashing
+/// in a loop does not flood the inboxes.
+const NOTIFICATION_COOLDOWN: Duration = Duration::from_secs(15 * 60);
+
+lazy_static! {
+    /// When the last notification was sent, by app name and notification kind.
+    static ref LAST_SENT: Mutex<HashMap<(String, String), Instant>> = Mutex::new(HashMap::new());
+}
+
+/// An email about an app.
+#[derive(Debug)]
+struct Notification {
+    app_name: String,
+    /// Notifications of the same kind and app are sent once per `NOTIFICATION_COOLDOWN`.
+    kind: String,
+    subject: String,
+    body: String,
+}
+
+/// Checks an email address.
+///
+/// # Arguments
+/// * `email` - The address, without a display name (e.g., "ops@example.com").
+///
+/// # Returns
+/// * `Ok(())` if the address is valid.
+/// * `Err(String)` with the reason otherwise.
+pub fn check_email(email: &str) -> Result<(), String> {
+    email
+        .parse::<Address>()
+        .map(|_| ())
+        .map_err(|e| format!("{} is not a valid email address: {}", email, e))
+}
+
+/// Lists the addresses notified about an app, besides the global `smtp.recipients`.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+pub fn list_app_recipients(app_name: &str) -> Result<Vec<String>, String> {
+    with_connection(|connection| {
+        let mut statement = connection
+            .prepare("SELECT email FROM email_recipients WHERE app_name = ?1 ORDER BY email")?;
+        let emails = statement
+            .query_map([app_name], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(emails)
+    })
+}
+
+/// Replaces the addresses notified about an app.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `emails` - The validated addresses, empty to only notify the global recipients.
+pub fn set_app_recipients(app_name: &str, emails: &[String]) -> Result<(), String> {
+    with_connection(|connection| {
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "DELETE FROM email_recipients WHERE app_name = ?1",
+            [app_name],
+        )?;
+        for email in emails {
+            transaction.execute(
+                "INSERT OR IGNORE INTO email_recipients (app_name, email) VALUES (?1, ?2)",
+                params![app_name, email],
+            )?;
+        }
+        transaction.commit()
+    })
+}
+
+/// Deletes the addresses notified about an app, when the app is removed.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+pub fn delete_app_recipients(app_name: &str) -> Result<(), String> {
+    set_app_recipients(app_name, &[])
+}
+
+/// Builds the notification of an event, if operators should hear about it.
+///
+/// Failed deployments, crashed apps and fired alerts are notified.
+fn notification(event: Event) -> Option<Notification> {
+    match event {
+        Event::DeploymentFinished {
+            app_name,
+            deployment_id,
+            kind,
+            state: DeploymentState::Failed,
+            error,
+        } => {
+            let action = match kind {
+                DeploymentKind::Deploy => "Deployment",
+                DeploymentKind::Rollback => "Rollback",
+            };
+            Some(Notification {
+                subject: format!("[Nephelios] {} of {} failed", action, app_name),
+                body: format!(
+                    "{} {} of {} failed:\n\n{}\n\nDetails are available at /deployments/{}.",
+                    action,
+                    deployment_id,
+                    app_name,
+                    error.as_deref().unwrap_or("unknown error"),
+                    deployment_id
+                ),
+                kind: "deployment_failed".to_string(),
+                app_name,
+            })
+        }
+        Event::AppCrashed { app_name } => Some(Notification {
+            subject: format!("[Nephelios] {} crashed", app_name),
+            body: format!(
+                "Every replica of {} failed, the app is not serving requests.\n\nSwarm keeps \
+                 restarting its tasks; check the logs of the app for the cause.",
+                app_name
+            ),
+            kind: "app_crashed".to_string(),
+            app_name,
+        }),
+        Event::AlertFired { alert } => Some(Notification {
+            subject: format!(
+                "[Nephelios] Alert {} firing for {}",
+                alert.rule_name, alert.app_name
+            ),
+            body: format!(
+                "{}\n\nThe condition of alert rule {} holds since {}.",
+                alert.message,
+                alert.rule_name,
+                alert.since.to_rfc3339()
+            ),
+            kind: format!("alert:{}", alert.rule_id),
+            app_name: alert.app_name,
+        }),
+        _ => None,
+    }
+}
+
+/// Records that a notification is sent, unless one of the same kind was sent for the app
+/// during the last `NOTIFICATION_COOLDOWN`.
+///
+/// # Returns
+/// * `true` if the notification should be sent.
+fn claim_cooldown(notification: &Notification) -> bool {
+    let now = Instant::now();
+    let mut last_sent = LAST_SENT.lock().unwrap_or_else(|e| e.into_inner());
+    last_sent.retain(|_, sent| now.duration_since(*sent) < NOTIFICATION_COOLDOWN);
+
+    let key = (notification.app_name.clone(), notification.kind.clone());
+    if last_sent.contains_key(&key) {
+        return false;
+    }
+    last_sent.insert(key, now);
+    true
+}
+
+/// Builds the SMTP transport of the `[smtp]` settings.
+fn smtp_transport(
+    smtp: &SmtpConfig,
+    host: &str,
+) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
+    let builder = match smtp.security {
+        SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+        SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+            host,
+        )),
+    }
+    .map_err(|e| format!("Failed to configure SMTP server {}: {}", host, e))?;
+
+    let mut builder = builder.port(smtp.port).timeout(Some(SMTP_TIMEOUT));
+    if let Some(username) = &smtp.username {
+        builder = builder.credentials(Credentials::new(
+            username.clone(),
+            smtp.password.clone().unwrap_or_default(),
+        ));
+    }
+    Ok(builder.build())
+}
+
+/// Sends a notification to the given addresses.
+///
+/// # Arguments
+/// * `recipients` - The addresses, validated when they were configured.
+/// * `notification` - The notification.
+async fn send_notification(
+    recipients: &[String],
+    notification: &Notification,
+) -> Result<(), String> {
+    let smtp = &config().smtp;
+    let host = smtp.host.as_deref().ok_or("SMTP is not configured")?;
+
+    let from: Mailbox = smtp
+        .from
+        .parse()
+        .map_err(|e| format!("Invalid sender {}: {}", smtp.from, e))?;
+    let mut message = Message::builder()
+        .from(from)
+        .subject(notification.subject.as_str())
+        .header(ContentType::TEXT_PLAIN);
+    for recipient in recipients {
+        let mailbox: Mailbox = recipient
+            .parse()
+            .map_err(|e| format!("Invalid recipient {}: {}", recipient, e))?;
+        message = message.to(mailbox);
+    }
+    let message = message
+        .body(notification.body.clone())
+        .map_err(|e| format!("Failed to build email: {}", e))?;
+
+    smtp_transport(smtp, host)?
+        .send(message)
+        .await
+        .map_err(|e| format!("Failed to send email through {}: {}", host, e))?;
+    Ok(())
+}
+
+/// Emails the failed deployments, crashed apps and fired alerts published on the event bus
+/// to the global `smtp.recipients` and the recipients of the app.
+///
+/// Does nothing unless `smtp.host` is set.
+pub async fn run_email_notifier() {
+    if config().smtp.host.is_none() {
+        return;
+    }
+    info!("📧 Email notifications enabled");
+
+    consume_events("email notifier", |event| async move {
+        let Some(notification) = notification(event) else {
+            return;
+        };
+
+        let mut recipients = config().smtp.recipients.clone();
+        match list_app_recipients(&notification.app_name) {
+            Ok(emails) => recipients.extend(emails),
+            Err(e) => warn!(
+                "Failed to list email recipients of {}: {}",
+                notification.app_name, e
+            ),
+        }
+        recipients.sort();
+        recipients.dedup();
+        if recipients.is_empty() || !claim_cooldown(&notification) {
+            return;
+        }
+
+        tokio::spawn(async move {
+            if let Err(e) = send_notification(&recipients, &notification).await {
+                warn!("Failed to email {}: {}", notification.subject, e);
+            }
+        });
+    })
+    .await
+}
@@ -0,0 +1,189 @@
+use crate::services::alerting::Alert;
+use crate::services::deployment_tracker::{DeploymentKind, DeploymentState};
+use crate::services::websocket::DeploymentStatus;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+/// Number of events buffered for each consumer; slower consumers skip the oldest ones.
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+/// Something that happened to an app or a node, serialized with its `type` (e.g.,
+/// `app_crashed`).
+///
+/// Events are published by the routes, the deployment jobs and the Docker watchers, and
+/// consumed by the features reacting to them (WebSocket broadcaster, alert notifier, audit
+/// log), so the publishers do not need to know about their consumers.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A deployment reported its progress, see `send_deployment_status`.
+    DeploymentStatus(DeploymentStatus),
+    DeploymentStarted {
+        app_name: String,
+        deployment_id: String,
+        kind: DeploymentKind,
+        /// Who or what triggered the deployment (e.g., "api-key:1a2b3c4d", "auto-redeploy").
+        initiator: String,
+    },
+    DeploymentFinished {
+        app_name: String,
+        deployment_id: String,
+        kind: DeploymentKind,
+        state: DeploymentState,
+        error: Option<String>,
+    },
+    AppStarted {
+        app_name: String,
+    },
+    AppStopped {
+        app_name: String,
+    },
+    AppScaled {
+        app_name: String,
+        replicas: u32,
+    },
+    AppRestarted {
+        app_name: String,
+    },
+    /// The app was removed, or soft-deleted when `soft` is set.
+    AppRemoved {
+        app_name: String,
+        soft: bool,
+    },
+    AppRestored {
+        app_name: String,
+    },
+    /// Every replica of a running app failed.
+    AppCrashed {
+        app_name: String,
+    },
+    AlertFired {
+        alert: Alert,
+    },
+    AlertResolved {
+        alert: Alert,
+    },
+    /// A node was drained, paused or made active again.
+    NodeAvailabilityChanged {
+        node_id: String,
+        hostname: String,
+        availability: String,
+    },
+    /// Labels of a node were set or removed.
+    NodeLabelsChanged {
+        node_id: String,
+        hostname: String,
+        labels: HashMap<String, String>,
+    },
+    /// Tasks are still running on a node being drained.
+    NodeDrainProgress {
+        node_id: String,
+        hostname: String,
+        tasks_remaining: usize,
+    },
+    /// Every task of a drained node was stopped, the node can be taken down.
+    NodeDrained {
+        node_id: String,
+        hostname: String,
+    },
+}
+
+impl Event {
+    /// The type of the event, as serialized.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::DeploymentStatus(_) => "deployment_status",
+            Event::DeploymentStarted { .. } => "deployment_started",
+            Event::DeploymentFinished { .. } => "deployment_finished",
+            Event::AppStarted { .. } => "app_started",
+            Event::AppStopped { .. } => "app_stopped",
+            Event::AppScaled { .. } => "app_scaled",
+            Event::AppRestarted { .. } => "app_restarted",
+            Event::AppRemoved { .. } => "app_removed",
+            Event::AppRestored { .. } => "app_restored",
+            Event::AppCrashed { .. } => "app_crashed",
+            Event::AlertFired { .. } => "alert_fired",
+            Event::AlertResolved { .. } => "alert_resolved",
+            Event::NodeAvailabilityChanged { .. } => "node_availability_changed",
+            Event::NodeLabelsChanged { .. } => "node_labels_changed",
+            Event::NodeDrainProgress { .. } => "node_drain_progress",
+            Event::NodeDrained { .. } => "node_drained",
+        }
+    }
+
+    /// The app the event is about, `None` for node events.
+    pub fn app_name(&self) -> Option<&str> {
+        match self {
+            Event::DeploymentStatus(status) => Some(&status.app_name),
+            Event::AlertFired { alert } | Event::AlertResolved { alert } => Some(&alert.app_name),
+            Event::DeploymentStarted { app_name, .. }
+            | Event::DeploymentFinished { app_name, .. }
+            | Event::AppStarted { app_name }
+            | Event::AppStopped { app_name }
+            | Event::AppScaled { app_name, .. }
+            | Event::AppRestarted { app_name }
+            | Event::AppRemoved { app_name, .. }
+            | Event::AppRestored { app_name }
+            | Event::AppCrashed { app_name } => Some(app_name),
+            Event::NodeAvailabilityChanged { .. }
+            | Event::NodeLabelsChanged { .. }
+            | Event::NodeDrainProgress { .. }
+            | Event::NodeDrained { .. } => None,
+        }
+    }
+
+    /// Whether the event is about a node of the Swarm.
+    pub fn is_node_event(&self) -> bool {
+        self.app_name().is_none()
+    }
+}
+
+lazy_static! {
+    /// The event bus, every consumer holding a receiver of it.
+    static ref EVENT_BUS: broadcast::Sender<Event> = broadcast::channel(EVENT_BUS_CAPACITY).0;
+}
+
+/// Publishes an event to every consumer.
+///
+/// Events published while no consumer is running are dropped.
+///
+/// # Arguments
+/// * `event` - What happened.
+pub fn publish(event: Event) {
+    let _ = EVENT_BUS.send(event);
+}
+
+/// Subscribes to the events published from now on.
+pub fn subscribe() -> broadcast::Receiver<Event> {
+    EVENT_BUS.subscribe()
+}
+
+/// Runs a consumer of the event bus, handling every event in order.
+///
+/// A consumer that falls behind by more than `EVENT_BUS_CAPACITY` events skips the oldest
+/// ones, with a warning.
+///
+/// # Arguments
+/// * `name` - The name of the consumer, for the logs.
+/// * `handler` - Called with each event.
+pub async fn consume_events<F, Fut>(name: &str, mut handler: F)
+where
+    F: FnMut(Event) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut events = subscribe();
+    loop {
+        match events.recv().await {
+            Ok(event) => handler(event).await,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Event consumer {} skipped {} events", name, skipped)
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use crate::config::config;
+use crate::services::addons::is_addon_volume;
+use crate::services::app_registry::list_registered_apps;
+use crate::services::deployment_tracker::active_deployment_id;
+use crate::services::helpers::docker_helper::{
+    list_registry_images, list_stack_services, remove_image, remove_network, remove_service,
+    remove_volume, unused_stack_resources,
+};
+use crate::services::helpers::stack_helper::{load_stack, StackFile};
+use crate::services::soft_delete::list_deleted_apps;
+use chrono::{DateTime, Utc};
+use dirs::home_dir;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+/// Age under which build files and images are left alone, as a deployment may still use them.
+const MIN_AGE: Duration = Duration::from_secs(24 * 3600);
+
+lazy_static! {
+    /// The report of the last collection.
+    static ref LAST_REPORT: RwLock<Option<GcReport>> = RwLock::new(None);
+    /// Held while a collection runs.
+    static ref COLLECTING: Mutex<()> = Mutex::new(());
+}
+
+/// The outcome of a collection of orphaned resources.
+///
+/// Every list holds what was removed, or what would be in a dry run.
+#[derive(Debug, Clone, Serialize)]
+pub struct GcReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Whether the resources were only listed.
+    pub dry_run: bool,
+    /// Swarm services of the stack that are not in the stack file anymore, only collected
+    /// when asked for.
+    pub services: Vec<String>,
+    /// Local release images of apps and services that are not in the stack file anymore.
+    pub images: Vec<String>,
+    /// Clones and build archives left in `~/.cache/nephelios` by interrupted deployments.
+    pub cache_entries: Vec<String>,
+    /// Volumes of the stack no service mounts anymore, of apps that are gone.
+    pub volumes: Vec<String>,
+    /// Networks of the stack no service is attached to anymore.
+    pub networks: Vec<String>,
+    /// Resources that could not be removed, with the error.
+    pub failed: BTreeMap<String, String>,
+}
+
+/// Returns the report of the last collection, if one ran.
+pub fn last_gc_report() -> Option<GcReport> {
+    LAST_REPORT
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Returns the directory repositories are cloned and build contexts archived in.
+fn cache_dir() -> Result<PathBuf, String> {
+    let home = home_dir().ok_or("Failed to find home directory")?;
+    Ok(home.join(".cache/nephelios"))
+}
+
+/// Lists the entries of the cache directory left by deployments that are over.
+///
+/// Clones are named `.<app>-tmp` and kept while a deployment of the app runs, archives end
+/// with `.tar`. Entries younger than `MIN_AGE` are kept.
+fn stale_cache_entries() -> Result<Vec<PathBuf>, String> {
+    let dir = cache_dir()?;
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut stale = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let clone_of = name
+            .strip_prefix('.')
+            .and_then(|name| name.strip_suffix("-tmp"));
+        if clone_of.is_none() && !name.ends_with(".tar") {
+            continue;
+        }
+        if clone_of.is_some_and(|app_name| active_deployment_id(app_name).is_some()) {
+            continue;
+        }
+        let age = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+        if age.is_some_and(|age| age >= MIN_AGE) {
+            stale.push(entry.path());
+        }
+    }
+    stale.sort();
+    Ok(stale)
+}
+
+/// Removes a file or directory of the cache.
+fn remove_cache_entry(path: &Path) -> Result<(), String> {
+    let result = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    result.map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+}
+
+/// Whether an unused volume may still be wanted: the data of an addon, kept when it is
+/// removed, or a volume of an app that is still registered, in the stack file or
+/// soft-deleted, kept when the app stops listing it.
+///
+/// # Arguments
+/// * `volume` - The Docker name of the volume.
+/// * `apps` - The names of the apps that are not gone.
+fn is_kept_volume(volume: &str, apps: &HashSet<String>) -> bool {
+    let name = volume.strip_prefix("nephelios_").unwrap_or(volume);
+    is_addon_volume(name)
+        || apps
+            .iter()
+            .any(|app_name| name.starts_with(&format!("{}-", app_name)))
+}
+
+/// Lists the apps whose volumes are kept: registered, in the stack file or soft-deleted.
+fn kept_apps(stack: &StackFile) -> Result<HashSet<String>, String> {
+    let mut apps: HashSet<String> = stack.services.keys().cloned().collect();
+    apps.extend(list_registered_apps()?.into_iter().map(|app| app.app_name));
+    apps.extend(list_deleted_apps()?.into_iter().map(|app| app.app_name));
+    Ok(apps)
+}
+
+/// Records the outcome of a removal in a report.
+///
+/// # Arguments
+/// * `removed` - The list the resource is added to once removed.
+/// * `failed` - The failures of the report.
+/// * `kind` - The kind of the resource, prefixed to its name in `failed`.
+/// * `name` - The name of the resource.
+/// * `result` - The outcome of the removal, `None` in a dry run.
+fn record_removal(
+    removed: &mut Vec<String>,
+    failed: &mut BTreeMap<String, String>,
+    kind: &str,
+    name: String,
+    result: Option<Result<(), String>>,
+) {
+    match result {
+        Some(Err(e)) => {
+            failed.insert(format!("{} {}", kind, name), e);
+        }
+        Some(Ok(())) | None => removed.push(name),
+    }
+}
+
+/// Finds the resources Nephelios left behind, and removes them unless it is a dry run.
+///
+/// The stack file is the reference: release images of repositories none of its services is
+/// named after, and volumes and networks of the stack it does not use are orphaned. Stale
+/// clones and build archives of the cache are removed too. Addon data volumes and the volumes
+/// of apps that are not gone are always kept. Swarm services the stack file does not declare
+/// may hold data, they are only removed when asked for. Docker refuses to remove volumes,
+/// networks and images still in use, which are reported as failed.
+///
+/// # Arguments
+/// * `dry_run` - Whether to only list the orphaned resources.
+/// * `include_services` - Whether to collect the Swarm services missing from the stack file.
+///
+/// # Returns
+/// * `Ok(GcReport)` describing what was found and removed.
+/// * `Err(String)` if the stack file or the Docker resources could not be read.
+pub async fn collect_garbage(dry_run: bool, include_services: bool) -> Result<GcReport, String> {
+    let _running = COLLECTING.lock().await;
+    let started_at = Utc::now();
+
+    let stack = load_stack().map_err(|e| format!("Failed to read the stack file: {}", e))?;
+    let declared: HashSet<String> = stack
+        .services
+        .keys()
+        .map(|name| name.to_lowercase())
+        .collect();
+    let mut failed = BTreeMap::new();
+
+    let mut services = Vec::new();
+    let mut orphaned_services: Vec<String> = if include_services {
+        list_stack_services()
+            .await?
+            .into_iter()
+            .filter(|name| !stack.services.contains_key(name))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    orphaned_services.sort();
+    for name in orphaned_services {
+        let result = if dry_run {
+            None
+        } else {
+            Some(remove_service(&name).await)
+        };
+        record_removal(&mut services, &mut failed, "service", name, result);
+    }
+
+    let mut images = Vec::new();
+    let min_created = Utc::now().timestamp() - MIN_AGE.as_secs() as i64;
+    for image in list_registry_images().await? {
+        if declared.contains(&image.repository) || image.created > min_created {
+            continue;
+        }
+        let result = if dry_run {
+            None
+        } else {
+            Some(remove_image(&image.reference).await)
+        };
+        record_removal(&mut images, &mut failed, "image", image.reference, result);
+    }
+
+    let mut cache_entries = Vec::new();
+    for path in stale_cache_entries()? {
+        let result = (!dry_run).then(|| remove_cache_entry(&path));
+        let name = path.display().to_string();
+        record_removal(&mut cache_entries, &mut failed, "cache", name, result);
+    }
+
+    let (unused_volumes, unused_networks) = unused_stack_resources(&stack).await?;
+    let apps = kept_apps(&stack)?;
+    let mut volumes = Vec::new();
+    for name in unused_volumes {
+        if is_kept_volume(&name, &apps) {
+            continue;
+        }
+        let result = if dry_run {
+            None
+        } else {
+            Some(remove_volume(&name).await)
+        };
+        record_removal(&mut volumes, &mut failed, "volume", name, result);
+    }
+    let mut networks = Vec::new();
+    for name in unused_networks {
+        let result = if dry_run {
+            None
+        } else {
+            Some(remove_network(&name).await)
+        };
+        record_removal(&mut networks, &mut failed, "network", name, result);
+    }
+
+    let report = GcReport {
+        started_at,
+        finished_at: Utc::now(),
+        dry_run,
+        services,
+        images,
+        cache_entries,
+        volumes,
+        networks,
+        failed,
+    };
+    log_report(&report);
+    *LAST_REPORT.write().unwrap_or_else(|e| e.into_inner()) = Some(report.clone());
+    Ok(report)
+}
+
+/// Logs what a collection found and removed.
+fn log_report(report: &GcReport) {
+    let found = [
+        ("services", &report.services),
+        ("images", &report.images),
+        ("cache entries", &report.cache_entries),
+        ("volumes", &report.volumes),
+        ("networks", &report.networks),
+    ];
+    let verb = if report.dry_run {
+        "Found orphaned"
+    } else {
+        "Removed orphaned"
+    };
+    for (kind, names) in found {
+        if !names.is_empty() {
+            info!("🧹 {} {}: {}", verb, kind, names.join(", "));
+        }
+    }
+    for (resource, error) in &report.failed {
+        warn!("Failed to remove orphaned {}: {}", resource, error);
+    }
+}
+
+/// Periodically reports the orphaned resources, or removes them when `features.gc_dry_run`
+/// is off. Orphaned Swarm services are left to `POST /system/gc`. Does nothing if
+/// `features.gc_interval` is `0`.
+pub async fn run_garbage_collector() {
+    let features = &config().features;
+    if features.gc_interval == 0 {
+        return;
+    }
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(features.gc_interval * 60));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick completes immediately, skip it to let the stack start
+    ticker.tick().await;
+
+    loop {
+        ticker.tick().await;
+        if let Err(e) = collect_garbage(features.gc_dry_run, false).await {
+            error!("❌ Garbage collection failed: {}", e);
+        }
+    }
+}
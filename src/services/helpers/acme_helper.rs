@@ -0,0 +1,485 @@
+use crate::config::{config, AcmeChallengeType, PlainHttp};
+use crate::services::helpers::docker_helper::create_docker_secret;
+use crate::services::helpers::stack_helper::update_stack;
+use openssl::sha::sha256;
+use serde_json::json;
+use serde_yaml::{Mapping, Value};
+use std::env;
+use std::io;
+
+/// Name of the Traefik certificate resolver referenced by the app routers.
+const RESOLVER: &str = "myresolver";
+
+/// Prefix of the Docker secrets holding DNS provider credentials.
+const SECRET_PREFIX: &str = "nephelios_acme_";
+
+/// Prefix of the Docker secrets holding the Traefik route of the Nephelios API.
+const API_ROUTE_PREFIX: &str = "nephelios_api_route_";
+
+/// Name of the Nephelios container, reached by Traefik on the overlay network.
+const API_HOST: &str = "nephelios";
+
+/// ACME challenge used by Traefik to prove domain ownership.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AcmeChallenge {
+    /// TLS-ALPN-01 on the `websecure` entrypoint.
+    Tls,
+    /// HTTP-01 on the `web` entrypoint.
+    Http,
+    /// DNS-01 through the given provider, required for wildcard certificates.
+    Dns {
+        provider: String,
+        credentials: Vec<String>,
+    },
+}
+
+/// Certificate resolver settings, resolved from the `[acme]` section.
+#[derive(Debug, Clone)]
+pub struct ResolverConfig {
+    pub challenge: AcmeChallenge,
+    pub email: Option<String>,
+    pub ca_server: String,
+    pub dns_resolvers: Vec<String>,
+    pub wildcard_domains: Vec<String>,
+}
+
+/// Returns the environment variables holding the credentials of a known DNS provider.
+///
+/// Names follow the lego provider documentation used by Traefik.
+fn provider_credential_vars(provider: &str) -> &'static [&'static str] {
+    match provider {
+        "cloudflare" => &[
+            "CF_DNS_API_TOKEN",
+            "CF_ZONE_API_TOKEN",
+            "CF_API_EMAIL",
+            "CF_API_KEY",
+        ],
+        "route53" => &[
+            "AWS_ACCESS_KEY_ID",
+            "AWS_SECRET_ACCESS_KEY",
+            "AWS_REGION",
+            "AWS_HOSTED_ZONE_ID",
+        ],
+        "digitalocean" => &["DO_AUTH_TOKEN"],
+        "gandiv5" => &["GANDIV5_PERSONAL_ACCESS_TOKEN", "GANDIV5_API_KEY"],
+        "ovh" => &[
+            "OVH_ENDPOINT",
+            "OVH_APPLICATION_KEY",
+            "OVH_APPLICATION_SECRET",
+            "OVH_CONSUMER_KEY",
+        ],
+        "gcloud" => &["GCE_PROJECT", "GCE_SERVICE_ACCOUNT"],
+        "azuredns" => &[
+            "AZURE_CLIENT_ID",
+            "AZURE_CLIENT_SECRET",
+            "AZURE_TENANT_ID",
+            "AZURE_SUBSCRIPTION_ID",
+            "AZURE_RESOURCE_GROUP",
+        ],
+        _ => &[],
+    }
+}
+
+/// Resolves the certificate resolver settings from the `[acme]` section.
+///
+/// With the DNS challenge, the credential variables of the provider and those of
+/// `acme.dns_credentials` that are set in the environment are forwarded.
+///
+/// # Returns
+/// * `Ok(ResolverConfig)` containing the settings.
+/// * `Err(String)` if no credentials of the DNS provider are set.
+pub fn resolver_config() -> Result<ResolverConfig, String> {
+    let acme = &config().acme;
+    let challenge = match acme.challenge {
+        AcmeChallengeType::Tls => AcmeChallenge::Tls,
+        AcmeChallengeType::Http => AcmeChallenge::Http,
+        AcmeChallengeType::Dns => {
+            let provider = acme
+                .dns_provider
+                .clone()
+                .ok_or("acme.dns_provider is required for the DNS challenge")?;
+
+            let mut credentials: Vec<String> = provider_credential_vars(&provider)
+                .iter()
+                .map(|var| var.to_string())
+                .collect();
+            for var in &acme.dns_credentials {
+                if !credentials.contains(var) {
+                    credentials.push(var.clone());
+                }
+            }
+            credentials.retain(|var| env::var(var).map(|v| !v.is_empty()).unwrap_or(false));
+
+            if credentials.is_empty() {
+                return Err(format!(
+                    "No credentials found in the environment for DNS provider {}",
+                    provider
+                ));
+            }
+
+            AcmeChallenge::Dns {
+                provider,
+                credentials,
+            }
+        }
+    };
+
+    Ok(ResolverConfig {
+        challenge,
+        email: acme.email.clone(),
+        ca_server: acme.ca_server.clone(),
+        dns_resolvers: acme.dns_resolvers.clone(),
+        wildcard_domains: acme.wildcard_domains.clone(),
+    })
+}
+
+/// Builds the Traefik static configuration arguments of the certificate resolver.
+///
+/// # Arguments
+/// * `config` - The certificate resolver settings.
+///
+/// # Returns
+/// The list of command-line arguments.
+fn resolver_args(config: &ResolverConfig) -> Vec<String> {
+    let acme = format!("--certificatesresolvers.{}.acme", RESOLVER);
+    let mut args = Vec::new();
+
+    match &config.challenge {
+        AcmeChallenge::Tls => args.push(format!("{}.tlschallenge=true", acme)),
+        AcmeChallenge::Http => args.push(format!("{}.httpchallenge.entrypoint=web", acme)),
+        AcmeChallenge::Dns { provider, .. } => {
+            args.push(format!("{}.dnschallenge=true", acme));
+            args.push(format!("{}.dnschallenge.provider={}", acme, provider));
+            if !config.dns_resolvers.is_empty() {
+                args.push(format!(
+                    "{}.dnschallenge.resolvers={}",
+                    acme,
+                    config.dns_resolvers.join(",")
+                ));
+            }
+        }
+    }
+
+    if let Some(email) = &config.email {
+        args.push(format!("{}.email={}", acme, email));
+    }
+    args.push(format!("{}.caserver={}", acme, config.ca_server));
+    args.push(format!("{}.storage=/letsencrypt/acme.json", acme));
+
+    if !config.wildcard_domains.is_empty() {
+        args.push(format!(
+            "--entrypoints.websecure.http.tls.certresolver={}",
+            RESOLVER
+        ));
+        for (i, domain) in config.wildcard_domains.iter().enumerate() {
+            args.push(format!(
+                "--entrypoints.websecure.http.tls.domains[{}].main={}",
+                i, domain
+            ));
+            args.push(format!(
+                "--entrypoints.websecure.http.tls.domains[{}].sans=*.{}",
+                i, domain
+            ));
+        }
+    }
+
+    args
+}
+
+/// Checks whether a Traefik argument is generated from the certificate resolver settings.
+fn is_resolver_arg(arg: &str) -> bool {
+    arg.starts_with(&format!("--certificatesresolvers.{}.", RESOLVER))
+        || arg.starts_with("--entrypoints.websecure.http.tls")
+}
+
+/// Stores each DNS provider credential as a Docker secret.
+///
+/// Secret names include a hash of the value, so rotated credentials get a new secret
+/// instead of conflicting with the one in use.
+///
+/// # Arguments
+/// * `credentials` - The environment variables holding the credentials.
+///
+/// # Returns
+/// * `Ok(Vec<(String, String)>)` containing the variable and secret names.
+/// * `Err(String)` if a secret could not be created.
+fn create_credential_secrets(credentials: &[String]) -> Result<Vec<(String, String)>, String> {
+    credentials
+        .iter()
+        .map(|var| {
+            let value = env::var(var).unwrap_or_default();
+            let digest: String = sha256(value.as_bytes())
+                .iter()
+                .take(6)
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            let secret = format!("{}{}_{}", SECRET_PREFIX, var.to_ascii_lowercase(), digest);
+            create_docker_secret(&secret, &value)?;
+            Ok((var.clone(), secret))
+        })
+        .collect()
+}
+
+/// Returns the sequence stored under `key` in a mapping, creating it if needed.
+fn sequence_mut<'a>(mapping: &'a mut Mapping, key: &str) -> &'a mut Vec<Value> {
+    let entry = mapping
+        .entry(Value::from(key))
+        .or_insert_with(|| Value::Sequence(Vec::new()));
+    if !entry.is_sequence() {
+        *entry = Value::Sequence(Vec::new());
+    }
+    entry.as_sequence_mut().expect("entry is a sequence")
+}
+
+/// Writes the certificate resolver settings into the Traefik service of the stack file.
+///
+/// With the DNS challenge, provider credentials are stored as Docker secrets and passed to
+/// Traefik through `<VAR>_FILE` environment variables.
+///
+/// # Returns
+/// * `Ok(())` if the stack file was updated.
+/// * `Err(String)` if the settings are invalid or the stack file could not be updated.
+pub fn configure_certificate_resolver() -> Result<(), String> {
+    let config = resolver_config()?;
+
+    let secrets = match &config.challenge {
+        AcmeChallenge::Dns { credentials, .. } => create_credential_secrets(credentials)?,
+        _ => Vec::new(),
+    };
+    let args = resolver_args(&config);
+
+    update_stack(|stack| {
+        let traefik = stack.services.get_mut("traefik").ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "Service traefik not found in the file nephelios.yml",
+            )
+        })?;
+
+        let command = sequence_mut(&mut traefik.extra, "command");
+        command.retain(|arg| !arg.as_str().map(is_resolver_arg).unwrap_or(false));
+        command.extend(args.into_iter().map(Value::from));
+
+        let is_acme_secret = |value: &Value| {
+            value
+                .as_str()
+                .map(|name| name.starts_with(SECRET_PREFIX))
+                .unwrap_or(false)
+        };
+
+        let service_secrets = sequence_mut(&mut traefik.extra, "secrets");
+        service_secrets.retain(|secret| !is_acme_secret(secret));
+        service_secrets.extend(
+            secrets
+                .iter()
+                .map(|(_, secret)| Value::from(secret.as_str())),
+        );
+
+        let environment = sequence_mut(&mut traefik.extra, "environment");
+        environment.retain(|var| {
+            !var.as_str()
+                .map(|var| var.contains(&format!("=/run/secrets/{}", SECRET_PREFIX)))
+                .unwrap_or(false)
+        });
+        environment.extend(
+            secrets
+                .iter()
+                .map(|(var, secret)| Value::from(format!("{}_FILE=/run/secrets/{}", var, secret))),
+        );
+
+        for key in ["secrets", "environment"] {
+            let empty = traefik
+                .extra
+                .get(key)
+                .and_then(Value::as_sequence)
+                .map(Vec::is_empty)
+                .unwrap_or(false);
+            if empty {
+                traefik.extra.remove(key);
+            }
+        }
+
+        let stack_secrets = stack
+            .extra
+            .entry(Value::from("secrets"))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        if let Value::Mapping(stack_secrets) = stack_secrets {
+            stack_secrets.retain(|name, _| !is_acme_secret(name));
+            for (_, secret) in &secrets {
+                let mut definition = Mapping::new();
+                definition.insert(Value::from("name"), Value::from(secret.as_str()));
+                definition.insert(Value::from("external"), Value::from(true));
+                stack_secrets.insert(Value::from(secret.as_str()), Value::Mapping(definition));
+            }
+        }
+        if stack
+            .extra
+            .get("secrets")
+            .and_then(Value::as_mapping)
+            .map(Mapping::is_empty)
+            .unwrap_or(false)
+        {
+            stack.extra.remove("secrets");
+        }
+
+        Ok(())
+    })
+    .map_err(|e| format!("Failed to configure certificate resolver: {}", e))
+}
+
+/// Builds the Traefik dynamic configuration serving the Nephelios API at a domain.
+///
+/// # Arguments
+/// * `domain` - The domain the API is served at over HTTPS.
+/// * `port` - The port the API listens on inside the Nephelios container.
+/// * `plain_http` - How requests to the domain over plain HTTP are answered.
+///
+/// # Returns
+/// * `Ok(String)` containing the YAML configuration.
+/// * `Err(String)` if the configuration could not be serialized.
+fn api_route_config(domain: &str, port: u16, plain_http: PlainHttp) -> Result<String, String> {
+    let rule = format!("Host(`{}`)", domain);
+    let mut routers = json!({
+        "nephelios-api": {
+            "rule": rule,
+            "entryPoints": ["websecure"],
+            "service": "nephelios-api",
+            "tls": { "certResolver": RESOLVER },
+        },
+    });
+    match plain_http {
+        PlainHttp::Redirect => {
+            routers["nephelios-api-http"] = json!({
+                "rule": rule,
+                "entryPoints": ["web"],
+                "service": "nephelios-api",
+                "middlewares": ["nephelios-api-https"],
+            })
+        }
+        PlainHttp::Serve => {
+            routers["nephelios-api-http"] = json!({
+                "rule": rule,
+                "entryPoints": ["web"],
+                "service": "nephelios-api",
+            })
+        }
+        PlainHttp::Disable => {}
+    }
+
+    let route = json!({
+        "http": {
+            "routers": routers,
+            "middlewares": {
+                "nephelios-api-https": {
+                    "redirectScheme": { "scheme": "https", "permanent": true },
+                },
+            },
+            "services": {
+                "nephelios-api": {
+                    "loadBalancer": {
+                        "servers": [{ "url": format!("http://{}:{}", API_HOST, port) }],
+                    },
+                },
+            },
+        },
+    });
+    serde_yaml::to_string(&route).map_err(|e| format!("Failed to serialize API route: {}", e))
+}
+
+/// Writes the Traefik route of the Nephelios API into the stack file, from `api_tls.domain`.
+///
+/// The route is a dynamic configuration stored as a Docker secret, named with a hash of its
+/// content, and loaded by the file provider of Traefik. The route is removed when no domain
+/// is configured.
+///
+/// # Returns
+/// * `Ok(())` if the stack file was updated.
+/// * `Err(String)` if the secret could not be created or the stack file could not be updated.
+pub fn configure_api_route() -> Result<(), String> {
+    let settings = config();
+    let secret = match &settings.api_tls.domain {
+        Some(domain) => {
+            let route =
+                api_route_config(domain, settings.server.port, settings.api_tls.plain_http)?;
+            let digest: String = sha256(route.as_bytes())
+                .iter()
+                .take(6)
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            let secret = format!("{}{}", API_ROUTE_PREFIX, digest);
+            create_docker_secret(&secret, &route)?;
+            Some(secret)
+        }
+        None => None,
+    };
+
+    update_stack(|stack| {
+        let traefik = stack.services.get_mut("traefik").ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "Service traefik not found in the file nephelios.yml",
+            )
+        })?;
+
+        let is_route_secret = |value: &Value| {
+            value
+                .as_str()
+                .map(|name| name.starts_with(API_ROUTE_PREFIX))
+                .unwrap_or(false)
+        };
+        // The file provider reads the route from the secret mounted under /run/secrets
+        let provider_arg = "--providers.file.filename=/run/secrets/";
+
+        let command = sequence_mut(&mut traefik.extra, "command");
+        command.retain(|arg| {
+            !arg.as_str()
+                .and_then(|arg| arg.strip_prefix(provider_arg))
+                .map(|name| name.starts_with(API_ROUTE_PREFIX))
+                .unwrap_or(false)
+        });
+        if let Some(secret) = &secret {
+            command.push(Value::from(format!("{}{}", provider_arg, secret)));
+        }
+
+        let service_secrets = sequence_mut(&mut traefik.extra, "secrets");
+        service_secrets.retain(|secret| !is_route_secret(secret));
+        if let Some(secret) = &secret {
+            service_secrets.push(Value::from(secret.as_str()));
+        }
+        let no_secrets = traefik
+            .extra
+            .get("secrets")
+            .and_then(Value::as_sequence)
+            .map(Vec::is_empty)
+            .unwrap_or(false);
+        if no_secrets {
+            traefik.extra.remove("secrets");
+        }
+
+        let stack_secrets = stack
+            .extra
+            .entry(Value::from("secrets"))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        if let Value::Mapping(stack_secrets) = stack_secrets {
+            stack_secrets.retain(|name, _| !is_route_secret(name));
+            if let Some(secret) = &secret {
+                let mut definition = Mapping::new();
+                definition.insert(Value::from("name"), Value::from(secret.as_str()));
+                definition.insert(Value::from("external"), Value::from(true));
+                stack_secrets.insert(Value::from(secret.as_str()), Value::Mapping(definition));
+            }
+        }
+        if stack
+            .extra
+            .get("secrets")
+            .and_then(Value::as_mapping)
+            .map(Mapping::is_empty)
+            .unwrap_or(false)
+        {
+            stack.extra.remove("secrets");
+        }
+
+        Ok(())
+    })
+    .map_err(|e| format!("Failed to configure the API route: {}", e))
+}
@@ -0,0 +1,218 @@
+use crate::config::config;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use dirs::home_dir;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Credentials used to authenticate git operations against private repositories.
+#[derive(Debug, Clone)]
+pub enum GitCredentials {
+    /// No authentication, only public repositories can be cloned.
+    Anonymous,
+    /// HTTPS authentication with a username and a token (PAT or GitHub App installation token).
+    Token { username: String, token: String },
+    /// SSH authentication with the private key stored at the given path.
+    SshKey(PathBuf),
+}
+
+/// Resolves the credentials to use for cloning a repository.
+///
+/// Sources are checked in the following order:
+/// 1. `git_token` - a token sent with the request.
+/// 2. `deploy_key` - the name of an SSH deploy key stored in `NEPHELIOS_DEPLOY_KEYS_DIR`.
+/// 3. `GITHUB_TOKEN` - a server-level personal access token (`GITHUB_USERNAME` is optional).
+/// 4. `GITHUB_APP_ID`, `GITHUB_APP_INSTALLATION_ID` and `GITHUB_APP_PRIVATE_KEY_PATH` -
+///    a server-level GitHub App, exchanged for a short-lived installation token.
+/// 5. `GIT_SSH_KEY_PATH` - a server-level SSH key.
+///
+/// # Arguments
+/// * `git_token` - Optional token provided with the request.
+/// * `deploy_key` - Optional deploy key name provided with the request.
+///
+/// # Returns
+/// * `Ok(GitCredentials)` with the first credentials found, or `GitCredentials::Anonymous`.
+/// * `Err(String)` if a configured credential source could not be used.
+pub async fn resolve_git_credentials(
+    git_token: Option<&str>,
+    deploy_key: Option<&str>,
+) -> Result<GitCredentials, String> {
+    if let Some(token) = git_token.filter(|token| !token.is_empty()) {
+        return Ok(GitCredentials::Token {
+            username: "x-access-token".to_string(),
+            token: token.to_string(),
+        });
+    }
+
+    if let Some(key_name) = deploy_key.filter(|key| !key.is_empty()) {
+        return deploy_key_path(key_name).map(GitCredentials::SshKey);
+    }
+
+    if let Some(token) = non_empty_env("GITHUB_TOKEN") {
+        return Ok(GitCredentials::Token {
+            username: non_empty_env("GITHUB_USERNAME")
+                .unwrap_or_else(|| "x-access-token".to_string()),
+            token,
+        });
+    }
+
+    if let (Some(app_id), Some(installation_id), Some(key_path)) = (
+        non_empty_env("GITHUB_APP_ID"),
+        non_empty_env("GITHUB_APP_INSTALLATION_ID"),
+        non_empty_env("GITHUB_APP_PRIVATE_KEY_PATH"),
+    ) {
+        let token = fetch_installation_token(&app_id, &installation_id, &key_path).await?;
+        return Ok(GitCredentials::Token {
+            username: "x-access-token".to_string(),
+            token,
+        });
+    }
+
+    if let Some(key_path) = non_empty_env("GIT_SSH_KEY_PATH") {
+        let key_path = PathBuf::from(key_path);
+        if !key_path.is_file() {
+            return Err(format!("SSH key not found at {}", key_path.display()));
+        }
+        return Ok(GitCredentials::SshKey(key_path));
+    }
+
+    Ok(GitCredentials::Anonymous)
+}
+
+/// Reads an environment variable, ignoring it when empty.
+fn non_empty_env(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+/// Resolves the path of a named SSH deploy key.
+///
+/// Deploy keys are stored in `NEPHELIOS_DEPLOY_KEYS_DIR`
+/// (default: `~/.config/nephelios/deploy_keys`). Only plain file names are accepted.
+///
+/// # Arguments
+/// * `key_name` - The file name of the deploy key.
+///
+/// # Returns
+/// * `Ok(PathBuf)` containing the path to the key.
+/// * `Err(String)` if the name is invalid or the key does not exist.
+fn deploy_key_path(key_name: &str) -> Result<PathBuf, String> {
+    if !key_name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        || key_name.starts_with('.')
+    {
+        return Err(format!("Invalid deploy key name: {}", key_name));
+    }
+
+    let keys_dir = match non_empty_env("NEPHELIOS_DEPLOY_KEYS_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => home_dir()
+            .ok_or("Failed to find home directory")?
+            .join(".config/nephelios/deploy_keys"),
+    };
+
+    let key_path = keys_dir.join(key_name);
+    if !key_path.is_file() {
+        return Err(format!("Deploy key {} not found", key_name));
+    }
+
+    Ok(key_path)
+}
+
+/// Exchanges GitHub App credentials for an installation access token.
+///
+/// Signs a short-lived JWT with the App private key and requests an installation token
+/// from the GitHub API.
+///
+/// # Arguments
+/// * `app_id` - The GitHub App ID.
+/// * `installation_id` - The installation ID of the App on the repository owner.
+/// * `key_path` - The path to the App private key (PEM).
+///
+/// # Returns
+/// * `Ok(String)` containing the installation token.
+/// * `Err(String)` if signing or the API call fails.
+async fn fetch_installation_token(
+    app_id: &str,
+    installation_id: &str,
+    key_path: &str,
+) -> Result<String, String> {
+    let pem =
+        fs::read(key_path).map_err(|e| format!("Failed to read GitHub App private key: {}", e))?;
+    let jwt = sign_app_jwt(app_id, &pem)?;
+
+    let api_url = &config().github.api_url;
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/app/installations/{}/access_tokens",
+            api_url, installation_id
+        ))
+        .bearer_auth(jwt)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "nephelios")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to request GitHub App token: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "GitHub App token request failed with status {}",
+            response.status()
+        ));
+    }
+
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid GitHub App token response: {}", e))?;
+
+    body.get("token")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "GitHub App token missing from response".to_string())
+}
+
+/// Builds an RS256 JWT authenticating as the GitHub App.
+///
+/// # Arguments
+/// * `app_id` - The GitHub App ID, used as the issuer.
+/// * `pem` - The App private key in PEM format.
+///
+/// # Returns
+/// * `Ok(String)` containing the encoded JWT.
+/// * `Err(String)` if the key is invalid or signing fails.
+fn sign_app_jwt(app_id: &str, pem: &[u8]) -> Result<String, String> {
+    let now = Utc::now().timestamp();
+    let header = json!({ "alg": "RS256", "typ": "JWT" });
+    let claims = json!({ "iat": now - 60, "exp": now + 540, "iss": app_id });
+
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header.to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+
+    let key = PKey::private_key_from_pem(pem)
+        .map_err(|e| format!("Invalid GitHub App private key: {}", e))?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)
+        .map_err(|e| format!("Failed to create JWT signer: {}", e))?;
+    signer
+        .update(signing_input.as_bytes())
+        .map_err(|e| format!("Failed to sign JWT: {}", e))?;
+    let signature = signer
+        .sign_to_vec()
+        .map_err(|e| format!("Failed to sign JWT: {}", e))?;
+
+    Ok(format!(
+        "{}.{}",
+        signing_input,
+        URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
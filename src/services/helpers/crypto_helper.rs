@@ -0,0 +1,202 @@
+use crate::config::config;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use openssl::rand::rand_bytes;
+use openssl::sha::sha256;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// Prefix of the values encrypted with a master key, followed by the key ID and the payload.
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// Length of the AES-256-GCM nonce, prepended to the ciphertext.
+const NONCE_LENGTH: usize = 12;
+
+/// Length of the AES-256-GCM authentication tag, appended to the ciphertext.
+const TAG_LENGTH: usize = 16;
+
+/// What redacted values are replaced with.
+pub const REDACTED: &str = "********";
+
+/// Parts of variable names whose values are redacted from API responses.
+const SENSITIVE_NAME_PARTS: &[&str] = &[
+    "PASS",
+    "SECRET",
+    "TOKEN",
+    "KEY",
+    "CREDENTIAL",
+    "AUTH",
+    "PRIVATE",
+    "DSN",
+    "DATABASE_URL",
+    "REDIS_URL",
+];
+
+/// The master keys, the current one first, loaded once from the `[encryption]` settings.
+static MASTER_KEYS: OnceLock<Vec<MasterKey>> = OnceLock::new();
+
+/// A key encrypting the values stored by Nephelios.
+struct MasterKey {
+    /// Short hash of the key, stored with the values it encrypted.
+    id: String,
+    key: [u8; 32],
+}
+
+/// Decodes a master key.
+///
+/// # Arguments
+/// * `value` - 32 bytes encoded in base64.
+///
+/// # Returns
+/// * `Ok([u8; 32])` containing the key.
+/// * `Err(String)` if the value is not a base64 encoded 32 bytes key.
+pub fn parse_master_key(value: &str) -> Result<[u8; 32], String> {
+    let bytes = STANDARD
+        .decode(value.trim())
+        .map_err(|e| format!("Invalid master key, expected base64: {}", e))?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        format!("Invalid master key, expected 32 bytes, got {}", bytes.len())
+    })
+}
+
+/// Returns the master keys, the current one first, or none if encryption is disabled.
+///
+/// Keys were checked when the configuration was loaded.
+fn master_keys() -> &'static [MasterKey] {
+    MASTER_KEYS.get_or_init(|| {
+        let encryption = &config().encryption;
+        let current = encryption.current_key().ok().flatten();
+        current
+            .iter()
+            .chain(&encryption.previous_keys)
+            .filter_map(|value| parse_master_key(value).ok())
+            .map(|key| MasterKey {
+                id: sha256(&key)
+                    .iter()
+                    .take(4)
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect(),
+                key,
+            })
+            .collect()
+    })
+}
+
+/// Whether a master key is configured, so stored secrets are encrypted.
+pub fn encryption_enabled() -> bool {
+    !master_keys().is_empty()
+}
+
+/// Encrypts a value with the current master key, using AES-256-GCM.
+///
+/// # Arguments
+/// * `value` - The value to encrypt.
+///
+/// # Returns
+/// * `Ok(String)` containing `enc:v1:<key ID>:<base64 nonce, ciphertext and tag>`, or the
+///   value itself if no master key is configured.
+/// * `Err(String)` if the value could not be encrypted.
+pub fn encrypt_value(value: &str) -> Result<String, String> {
+    let Some(master_key) = master_keys().first() else {
+        return Ok(value.to_string());
+    };
+
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rand_bytes(&mut nonce).map_err(|e| format!("Failed to generate nonce: {}", e))?;
+    let mut tag = [0u8; TAG_LENGTH];
+    let ciphertext = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        &master_key.key,
+        Some(&nonce),
+        &[],
+        value.as_bytes(),
+        &mut tag,
+    )
+    .map_err(|e| format!("Failed to encrypt value: {}", e))?;
+
+    let payload = [nonce.as_slice(), &ciphertext, &tag].concat();
+    Ok(format!(
+        "{}{}:{}",
+        ENCRYPTED_PREFIX,
+        master_key.id,
+        STANDARD.encode(payload)
+    ))
+}
+
+/// Decrypts a value encrypted by `encrypt_value`, with the master key it was encrypted with.
+///
+/// Values stored before encryption was enabled are returned as they are.
+///
+/// # Arguments
+/// * `value` - The stored value.
+///
+/// # Returns
+/// * `Ok(String)` containing the value in clear.
+/// * `Err(String)` if the key is unknown, or the value is corrupted.
+pub fn decrypt_value(value: &str) -> Result<String, String> {
+    let Some(encrypted) = value.strip_prefix(ENCRYPTED_PREFIX) else {
+        return Ok(value.to_string());
+    };
+    let (key_id, payload) = encrypted.split_once(':').ok_or("Invalid encrypted value")?;
+    let master_key = master_keys()
+        .iter()
+        .find(|master_key| master_key.id == key_id)
+        .ok_or_else(|| format!("Value encrypted with unknown master key {}", key_id))?;
+
+    let payload = STANDARD
+        .decode(payload)
+        .map_err(|e| format!("Invalid encrypted value: {}", e))?;
+    if payload.len() < NONCE_LENGTH + TAG_LENGTH {
+        return Err("Invalid encrypted value".to_string());
+    }
+    let (nonce, rest) = payload.split_at(NONCE_LENGTH);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LENGTH);
+    let plaintext = decrypt_aead(
+        Cipher::aes_256_gcm(),
+        &master_key.key,
+        Some(nonce),
+        &[],
+        ciphertext,
+        tag,
+    )
+    .map_err(|_| format!("Failed to decrypt value with master key {}", key_id))?;
+    String::from_utf8(plaintext).map_err(|e| format!("Invalid decrypted value: {}", e))
+}
+
+/// Whether a stored value must be encrypted again: it is in clear while a master key is
+/// configured, or it was encrypted with a previous master key.
+///
+/// # Arguments
+/// * `value` - The stored value.
+pub fn needs_reencryption(value: &str) -> bool {
+    let Some(current) = master_keys().first() else {
+        return false;
+    };
+    !value
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .is_some_and(|encrypted| encrypted.starts_with(&format!("{}:", current.id)))
+}
+
+/// Whether a variable name suggests a sensitive value (e.g., `DB_PASSWORD`, `API_KEY`).
+pub fn is_sensitive_name(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SENSITIVE_NAME_PARTS.iter().any(|part| name.contains(part))
+}
+
+/// Replaces the values of the sensitive variables with `REDACTED`, for API responses.
+///
+/// # Arguments
+/// * `env` - The variables, by name.
+pub fn redact_env(env: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    env.iter()
+        .map(|(name, value)| {
+            let value = if is_sensitive_name(name) {
+                REDACTED.to_string()
+            } else {
+                value.clone()
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
@@ -1,24 +1,83 @@
-use crate::metrics::{CONTAINER_CPU, CONTAINER_MEM, CONTAINER_NET_IN, CONTAINER_NET_OUT};
+use crate::config::config;
+use crate::metrics::{
+    APP_IMAGE_SIZE, APP_REPLICAS_DESIRED, APP_REPLICAS_RUNNING, APP_UP, BUILD_CACHE_SIZE,
+    CONTAINER_CPU, CONTAINER_MEM, CONTAINER_NET_IN, CONTAINER_NET_OUT, HOST_DISK_FREE,
+    HOST_DISK_TOTAL, SWARM_NODES, SWARM_NODE_CPUS, SWARM_NODE_CPUS_RESERVED, SWARM_NODE_MEMORY,
+    SWARM_NODE_MEMORY_RESERVED, SWARM_NODE_READY, SWARM_NODE_TASKS, VOLUME_USAGE,
+};
+use crate::requests::parse_duration;
+use crate::services::deployment::{parse_cpus, parse_memory};
+use crate::services::helpers::stack_helper::{
+    load_stack, ResourceSpec, ServiceSecurity, StackFile,
+};
 use bollard::auth::DockerCredentials;
-use bollard::container::ListContainersOptions;
-use bollard::image::{BuildImageOptions, PruneImagesOptions, PushImageOptions, TagImageOptions};
-// Removed unused service imports
+use bollard::container::{
+    ListContainersOptions, LogOutput, LogsOptions, MemoryStatsStats, Stats, StatsOptions,
+};
+use bollard::errors::Error::DockerResponseServerError;
+use bollard::exec::{CreateExecOptions, StartExecResults};
+use bollard::image::{
+    BuildImageOptions, ListImagesOptions, PruneImagesOptions, PushImageOptions, RemoveImageOptions,
+    TagImageOptions,
+};
+use bollard::models::{
+    Limit, Mount, MountTypeEnum, MountVolumeOptions, NetworkAttachmentConfig, Node, NodeState,
+    ResourceObject, ServiceSpecMode, ServiceSpecModeReplicated, ServiceSpecUpdateConfig, Task,
+    TaskSpec, TaskSpecContainerSpec, TaskSpecContainerSpecFile, TaskSpecContainerSpecPrivileges,
+    TaskSpecContainerSpecSecrets, TaskSpecPlacement, TaskSpecPlacementPreferences,
+    TaskSpecPlacementSpread, TaskSpecResources, TaskState,
+};
+use bollard::network::ListNetworksOptions;
+use bollard::service::{
+    InspectServiceOptions, ListServicesOptions, ServiceSpec, UpdateServiceOptions,
+};
+use bollard::volume::ListVolumesOptions;
 use bollard::Docker;
 use chrono::Utc;
-use dirs::home_dir;
-use futures_util::stream::StreamExt;
+use futures_util::stream::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::env;
+use serde_yaml::{Mapping, Value as YamlValue};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::Write;
 use std::net::IpAddr;
-use std::path::Path;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
 use tar::Builder;
+use tokio::io::{AsyncBufReadExt, AsyncRead};
+use tracing::{debug, error, info, warn};
 use walkdir::WalkDir;
 
+/// Node.js major version used when the repository does not specify one.
+const DEFAULT_NODE_VERSION: &str = "20";
+
+/// Node.js majors with an official `node:<major>-alpine` image, oldest first.
+const NODE_VERSIONS: &[u32] = &[10, 12, 14, 16, 18, 20, 22, 24];
+
+/// Node.js majors still maintained as LTS, oldest first, preferred when a range allows them.
+const LTS_NODE_VERSIONS: &[u32] = &[20, 22, 24];
+
+/// Time between two checks of the tasks of a new release.
+const ROLLOUT_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Number of failed tasks after which a new release is considered broken.
+const MAX_ROLLOUT_FAILURES: usize = 3;
+
+/// Image of the one-off containers reading and writing volumes.
+const VOLUME_HELPER_IMAGE: &str = "alpine:3";
+
+/// Time between two checks of the task of a one-shot job.
+const JOB_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Label holding the app a one-shot job runs for.
+pub const JOB_APP_LABEL: &str = "com.nephelios.job.app";
+
+/// CFS period of image builds, in microseconds, the CPU quota being a fraction of it.
+const BUILD_CPU_PERIOD: u64 = 100_000;
+
 #[derive(Debug, Clone, Serialize)]
 pub struct AppMetadata {
     pub app_name: String,
@@ -26,16 +85,27 @@ pub struct AppMetadata {
     pub github_url: String,
     pub domain: String,
     pub created_at: String,
+    pub git_ref: Option<String>,
+    pub commit_sha: Option<String>,
+    pub commit_message: Option<String>,
 }
 
 impl AppMetadata {
-    pub fn new(app_name: String, app_type: String, github_url: String) -> Self {
+    pub fn new(
+        app_name: String,
+        app_type: String,
+        github_url: String,
+        git_ref: Option<String>,
+    ) -> Self {
         Self {
             app_name: app_name.clone(),
             app_type,
             github_url,
-            domain: format!("{}.localhost", app_name),
+            domain: format!("{}.{}", app_name, config().domain.base),
             created_at: Utc::now().to_rfc3339(),
+            git_ref,
+            commit_sha: None,
+            commit_message: None,
         }
     }
 
@@ -50,10 +120,46 @@ impl AppMetadata {
         labels.insert("com.myapp.github_url".to_string(), self.github_url.clone());
         labels.insert("com.myapp.domain".to_string(), self.domain.clone());
         labels.insert("com.myapp.created_at".to_string(), self.created_at.clone());
+        if let Some(git_ref) = &self.git_ref {
+            labels.insert("com.myapp.git_ref".to_string(), git_ref.clone());
+        }
+        if let Some(commit_sha) = &self.commit_sha {
+            labels.insert("com.myapp.commit_sha".to_string(), commit_sha.clone());
+        }
+        if let Some(commit_message) = &self.commit_message {
+            labels.insert(
+                "com.myapp.commit_message".to_string(),
+                sanitize_label_value(commit_message),
+            );
+        }
         labels
     }
 }
 
+/// Makes a free-form text safe to embed in Dockerfile and stack file labels.
+///
+/// Keeps the first line only, replaces quotes, backslashes and `$` (compose interpolation)
+/// and truncates the result to 100 characters.
+///
+/// # Arguments
+/// * `value` - The text to sanitize.
+///
+/// # Returns
+/// The sanitized label value.
+pub fn sanitize_label_value(value: &str) -> String {
+    value
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' | '$' | '`' => '\'',
+            c => c,
+        })
+        .take(100)
+        .collect()
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppInfo {
     pub app_name: String,
@@ -64,6 +170,14 @@ pub struct AppInfo {
     pub status: String,
     #[serde(default)]
     pub swarm_task_name: Option<String>,
+    #[serde(default)]
+    pub git_ref: Option<String>,
+    #[serde(default)]
+    pub commit_sha: Option<String>,
+    #[serde(default)]
+    pub commit_message: Option<String>,
+    #[serde(default)]
+    pub domains: Vec<String>,
 }
 
 /// Lists all deployed applications in the Nephelios stack.
@@ -76,7 +190,6 @@ pub struct AppInfo {
 /// # Returns
 /// * `Ok(Vec<AppInfo>)` - A vector of `AppInfo` objects representing the deployed applications.
 /// * `Err(String)` - An error message if the operation fails.
-
 pub async fn list_deployed_apps() -> Result<Vec<AppInfo>, String> {
     let docker = Docker::connect_with_local_defaults()
         .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
@@ -86,7 +199,6 @@ pub async fn list_deployed_apps() -> Result<Vec<AppInfo>, String> {
         .await
         .map_err(|e| format!("Failed to list services: {}", e))?;
 
-    let mut apps = Vec::new();
     let mut app_map: HashMap<String, AppInfo> = HashMap::new();
 
     for service in services {
@@ -121,6 +233,15 @@ pub async fn list_deployed_apps() -> Result<Vec<AppInfo>, String> {
                                     created_at: created.clone(),
                                     status: app_status,
                                     swarm_task_name: Some(service_id), // Default to service_id, will be updated if container info is found
+                                    git_ref: labels.get("com.myapp.git_ref").cloned(),
+                                    commit_sha: labels.get("com.myapp.commit_sha").cloned(),
+                                    commit_message: labels.get("com.myapp.commit_message").cloned(),
+                                    domains: labels
+                                        .get("com.myapp.routing.domains")
+                                        .map(|domains| {
+                                            domains.split(',').map(str::to_string).collect()
+                                        })
+                                        .unwrap_or_default(),
                                 },
                             );
                         }
@@ -169,6 +290,12 @@ pub async fn list_deployed_apps() -> Result<Vec<AppInfo>, String> {
                                     {
                                         app_info.swarm_task_name = Some(task_name.clone());
                                     }
+                                    // Image labels reflect the commit actually running
+                                    if let Some(commit_sha) = labels.get("com.myapp.commit_sha") {
+                                        app_info.commit_sha = Some(commit_sha.clone());
+                                        app_info.commit_message =
+                                            labels.get("com.myapp.commit_message").cloned();
+                                    }
                                 }
                             }
                         }
@@ -179,7 +306,7 @@ pub async fn list_deployed_apps() -> Result<Vec<AppInfo>, String> {
     }
 
     // Convert map to vector
-    apps = app_map.into_values().collect();
+    let mut apps: Vec<AppInfo> = app_map.into_values().collect();
     apps.sort_by(|a, b| b.created_at.cmp(&a.created_at));
 
     Ok(apps)
@@ -233,7 +360,7 @@ pub async fn get_app_status(name: String) -> String {
 /// * `Err(String)` if there is an error executing the command or parsing the output.
 async fn is_app_stopping(name: String) -> Result<bool, String> {
     let output = Command::new("docker")
-        .args(&[
+        .args([
             "service",
             "ls",
             "--filter",
@@ -340,15 +467,25 @@ async fn is_app_running(name: String) -> Result<bool, String> {
     Ok(false)
 }
 
-/// Creates a Docker context tarball for the specified application path.
+/// A Docker build context being archived in the background, see `start_docker_context`.
+pub struct DockerContext {
+    app_dir: PathBuf,
+    deferred: Vec<String>,
+    archive: tokio::task::JoinHandle<Result<Builder<Vec<u8>>, String>>,
+}
+
+/// Starts archiving the build context of an application in a blocking task, so the tarball
+/// is created while the next steps of the deployment run.
 ///
 /// # Arguments
 /// * `app_path` - The path to the application directory.
+/// * `deferred` - Files of the context that are still being written (e.g., a generated
+///   Dockerfile), relative to the directory. They are added by `DockerContext::finish`.
 ///
 /// # Returns
-/// * `Ok(String)` containing the path to the created tarball.
-/// * `Err(String)` if there is an error.
-fn create_docker_context(app_name: &str, app_path: &str) -> Result<String, String> {
+/// * `Ok(DockerContext)` to pass to `build_image`.
+/// * `Err(String)` if the application path is invalid.
+pub fn start_docker_context(app_path: &str, deferred: &[&str]) -> Result<DockerContext, String> {
     let app_dir = Path::new(app_path)
         .canonicalize()
         .map_err(|e| format!("Invalid application path: {}", e))?;
@@ -357,39 +494,72 @@ fn create_docker_context(app_name: &str, app_path: &str) -> Result<String, Strin
         return Err(format!("Invalid application path: {}", app_path));
     }
 
-    let home = home_dir().ok_or("Failed to find home directory")?;
-    let tar_path = home.join(format!(".cache/nephelios/{}.tar", app_name));
+    let deferred: Vec<String> = deferred.iter().map(|file| file.to_string()).collect();
+    let walked_dir = app_dir.clone();
+    let skipped: Vec<PathBuf> = deferred.iter().map(|file| app_dir.join(file)).collect();
+    let archive = tokio::task::spawn_blocking(move || {
+        let mut tar_builder = Builder::new(Vec::new());
 
-    let tar_file =
-        fs::File::create(&tar_path).map_err(|e| format!("Failed to create tar file: {}", e))?;
-    let mut tar_builder = Builder::new(tar_file);
+        for entry in WalkDir::new(&walked_dir).into_iter().filter_map(Result::ok) {
+            let path = entry.path();
 
-    for entry in WalkDir::new(&app_dir).into_iter().filter_map(Result::ok) {
-        let path = entry.path();
-
-        if path.is_dir() {
-            if let Some(name) = path.file_name() {
-                if name == ".git" || name == "node_modules" {
-                    continue;
+            if path.is_dir() {
+                if let Some(name) = path.file_name() {
+                    if name == ".git" || name == "node_modules" {
+                        continue;
+                    }
                 }
             }
-        }
 
-        // Add files to the tarball
-        if path.is_file() && !path.is_symlink() {
-            let file_name = path.strip_prefix(&app_dir).unwrap(); // Use the relative path
-            tar_builder
-                .append_path_with_name(path, file_name)
-                .map_err(|e| format!("Failed to add file {}: {}", path.display(), e))?;
+            // Add files to the tarball
+            if path.is_file() && !path.is_symlink() && !skipped.iter().any(|file| file == path) {
+                let file_name = path.strip_prefix(&walked_dir).unwrap(); // Use the relative path
+                tar_builder
+                    .append_path_with_name(path, file_name)
+                    .map_err(|e| format!("Failed to add file {}: {}", path.display(), e))?;
+            }
         }
-    }
+        Ok(tar_builder)
+    });
 
-    tar_builder
-        .finish()
-        .map_err(|e| format!("Failed to finalize tarball: {}", e))?;
-    println!("Docker context created at {}", tar_path.display());
+    Ok(DockerContext {
+        app_dir,
+        deferred,
+        archive,
+    })
+}
+
+impl DockerContext {
+    /// Waits for the archive, then adds the deferred files that exist.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` containing the tarball of the context.
+    /// * `Err(String)` if a file could not be archived.
+    pub async fn finish(self) -> Result<Vec<u8>, String> {
+        let mut tar_builder = self
+            .archive
+            .await
+            .map_err(|e| format!("Context archive task failed: {}", e))??;
+
+        for file in &self.deferred {
+            let path = self.app_dir.join(file);
+            if path.is_file() {
+                tar_builder
+                    .append_path_with_name(&path, file)
+                    .map_err(|e| format!("Failed to add file {}: {}", path.display(), e))?;
+            }
+        }
 
-    Ok(tar_path.to_string_lossy().to_string())
+        let contents = tar_builder
+            .into_inner()
+            .map_err(|e| format!("Failed to finalize tarball: {}", e))?;
+        info!(
+            "Docker context of {} archived ({} bytes)",
+            self.app_dir.display(),
+            contents.len()
+        );
+        Ok(contents)
+    }
 }
 
 /// Generates and writes a Dockerfile for the given application type.
@@ -403,10 +573,12 @@ fn create_docker_context(app_name: &str, app_path: &str) -> Result<String, Strin
 /// * `build_command` - Custom build command from the frontend.
 /// * `app_workdir` - Working directory for the application in the container.
 /// * `additional_inputs` - Optional additional environment variables and settings.
+/// * `user` - The user the processes run as, the root user of the base image if `None`.
 ///
 /// # Returns
 /// * `Ok(())` if successful.
 /// * `Err(String)` if an error occurs.
+#[allow(clippy::too_many_arguments)]
 pub fn generate_and_write_dockerfile(
     app_type: &str,
     app_path: &str,
@@ -416,16 +588,16 @@ pub fn generate_and_write_dockerfile(
     build_command: &str,
     app_workdir: &str,
     additional_inputs: Option<&HashMap<String, String>>,
+    user: Option<&str>,
 ) -> Result<(), String> {
     let dockerfile_path = Path::new(app_path).join("Dockerfile");
 
     if dockerfile_path.exists() {
-        println!("Dockerfile already exists at {}", dockerfile_path.display());
+        info!("Dockerfile already exists at {}", dockerfile_path.display());
         return Ok(());
     }
 
-    let deploy_port: String =
-        env::var("NEPHELIOS_APPS_PORT").unwrap_or_else(|_| "3000".to_string());
+    let deploy_port = config().server.apps_port.to_string();
 
     let labels = metadata
         .to_labels()
@@ -445,6 +617,11 @@ pub fn generate_and_write_dockerfile(
         })
         .unwrap_or_default();
 
+    // Drop root once the image is built
+    let user_cmd = user
+        .map(|user| format!("USER {}", user))
+        .unwrap_or_default();
+
     let dockerfile_content = match app_type {
         "nodejs" => {
             // Detect which package manager is being used
@@ -468,16 +645,14 @@ pub fn generate_and_write_dockerfile(
             } else if uses_npm {
                 "npm"
             } else {
-                println!("Unknown package manager");
+                warn!("Unknown package manager");
                 "npm"
             };
 
-            // Choose the base image based on the package manager
-            let base_image = match package_manager {
-                "yarn" => "node:20-alpine".to_string(),
-                "pnpm" => "node:20-alpine".to_string(),
-                _ => "node:20-alpine".to_string(),
-            };
+            // Choose the base image matching the Node version requested by the repo
+            let node_version = detect_node_version(Path::new(app_path), app_workdir);
+            info!("Using Node.js {} base image", node_version);
+            let base_image = format!("node:{}-alpine", node_version);
 
             // Additional setup commands for package managers
             let setup_cmd = match package_manager {
@@ -537,6 +712,7 @@ RUN {install_cmd}
 COPY {app_workdir}/ ./
 {build_cmd}
 EXPOSE {deploy_port}
+{user_cmd}
 {run_cmd}"#,
                 base_image = base_image,
                 app_workdir = app_workdir,
@@ -547,6 +723,7 @@ EXPOSE {deploy_port}
                 install_cmd = install_cmd,
                 build_cmd = build_cmd,
                 deploy_port = deploy_port,
+                user_cmd = user_cmd,
                 run_cmd = run_cmd,
                 package_lock = package_lock
             )
@@ -581,14 +758,22 @@ RUN {}
 COPY . .
 {}
 EXPOSE {}
+{}
 {}"#,
-                app_workdir, labels, env_vars, install_cmd, build_cmd, deploy_port, run_cmd
+                app_workdir,
+                labels,
+                env_vars,
+                install_cmd,
+                build_cmd,
+                deploy_port,
+                user_cmd,
+                run_cmd
             )
         }
         _ => return Err(format!("Unsupported app type: {}", app_type)),
     };
 
-    println!("Writing Dockerfile to {}", dockerfile_path.display());
+    info!("Writing Dockerfile to {}", dockerfile_path.display());
     let mut file = File::create(&dockerfile_path)
         .map_err(|e| format!("Failed to create Dockerfile: {}", e))?;
     file.write_all(dockerfile_content.as_bytes())
@@ -596,37 +781,163 @@ EXPOSE {}
     Ok(())
 }
 
-/// Builds a Docker image using the tarball created from the application directory.
+/// Checks whether a comparator of a semver range (e.g. `>=18.2`, `^20`, `22.x`) allows some
+/// version of a Node.js major.
+///
+/// # Returns
+/// * `Some(bool)` - Whether a version of the major satisfies the comparator.
+/// * `None` - If the comparator could not be parsed.
+fn node_comparator_allows(comparator: &str, major: u32) -> Option<bool> {
+    let (operator, version) = [">=", "<=", ">", "<", "=", "^", "~"]
+        .iter()
+        .find_map(|operator| Some((*operator, comparator.strip_prefix(operator)?)))
+        .unwrap_or(("", comparator));
+    let mut parts = version.trim_start_matches('v').split('.');
+    let wildcard = |part: &str| matches!(part, "x" | "X" | "*");
+    let Some(first) = parts.next().filter(|part| !wildcard(part)) else {
+        return Some(true);
+    };
+    let bound: u32 = first.parse().ok()?;
+    // Whether the version is above `<bound>.0.0`, so a lower version of the major is below it
+    let above_major = parts.any(|part| !wildcard(part) && part.parse::<u32>().is_ok_and(|n| n > 0));
+
+    Some(match operator {
+        ">=" | ">" => major >= bound,
+        "<=" => major <= bound,
+        "<" => major < bound || (major == bound && above_major),
+        _ => major == bound,
+    })
+}
+
+/// Checks whether a Node.js version or semver range allows some version of a major.
+///
+/// Handles `||` alternatives, hyphen ranges, comparators, `x` wildcards and the `node`,
+/// `latest` and `lts/*` aliases, which allow any major.
+///
+/// # Returns
+/// * `Some(bool)` - Whether a version of the major satisfies the range.
+/// * `None` - If the range could not be parsed.
+fn node_range_allows(range: &str, major: u32) -> Option<bool> {
+    let operator_space = regex::Regex::new(r"(>=|<=|>|<|=|\^|~)\s+").unwrap();
+    let mut allowed = false;
+    for set in range.split("||") {
+        let set = operator_space.replace_all(set.trim(), "$1");
+        let set_allows = match set.as_ref() {
+            "" | "*" | "node" | "latest" | "lts/*" => true,
+            set => match set.split_once(" - ") {
+                Some((low, high)) => {
+                    node_comparator_allows(&format!(">={}", low.trim()), major)?
+                        && node_comparator_allows(&format!("<={}", high.trim()), major)?
+                }
+                None => {
+                    let mut set_allows = true;
+                    for comparator in set.split_whitespace() {
+                        set_allows &= node_comparator_allows(comparator, major)?;
+                    }
+                    set_allows
+                }
+            },
+        };
+        allowed |= set_allows;
+    }
+    Some(allowed)
+}
+
+/// Picks the Node.js major to build with for a version or semver range: the newest LTS major
+/// it allows, or else the newest major with an image.
+///
+/// # Returns
+/// * `Some(u32)` - The major.
+/// * `None` - If the range could not be parsed or allows no known major.
+fn resolve_node_version(range: &str) -> Option<u32> {
+    let range = match range.trim().to_ascii_lowercase().as_str() {
+        "lts/hydrogen" => "18".to_string(),
+        "lts/iron" => "20".to_string(),
+        "lts/jod" => "22".to_string(),
+        "lts/krypton" => "24".to_string(),
+        range => range.to_string(),
+    };
+    LTS_NODE_VERSIONS
+        .iter()
+        .rev()
+        .chain(NODE_VERSIONS.iter().rev())
+        .copied()
+        .find(|major| node_range_allows(&range, *major) == Some(true))
+}
+
+/// Detects the Node.js major version required by the application.
+///
+/// Looks for a `.nvmrc` file first, then for the `engines.node` field of `package.json`,
+/// both in the application working directory and at the repository root. The newest LTS
+/// major the first version or range found allows is used (e.g. `>=14` gives `24`, `v18.3.0`
+/// gives `18`), or else the newest major with an official image. Sources that allow no known
+/// major (e.g. `>=30`) are skipped.
+///
+/// # Arguments
+/// * `app_path` - The path to the cloned repository.
+/// * `app_workdir` - The application directory inside the repository.
+///
+/// # Returns
+/// The Node.js major version as a `String`, falling back to `DEFAULT_NODE_VERSION`.
+fn detect_node_version(app_path: &Path, app_workdir: &str) -> String {
+    let workdir = app_path.join(app_workdir.trim_start_matches('/'));
+
+    for dir in [workdir.as_path(), app_path] {
+        let nvmrc = fs::read_to_string(dir.join(".nvmrc")).ok();
+        let engines_node = fs::read_to_string(dir.join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|package| {
+                package
+                    .get("engines")
+                    .and_then(|engines| engines.get("node"))
+                    .and_then(serde_json::Value::as_str)
+                    .map(str::to_string)
+            });
+
+        for range in [nvmrc, engines_node].into_iter().flatten() {
+            match resolve_node_version(&range) {
+                Some(major) => return major.to_string(),
+                None => warn!("Ignoring unsupported Node.js version {:?}", range.trim()),
+            }
+        }
+    }
+
+    DEFAULT_NODE_VERSION.to_string()
+}
+
+/// Builds a Docker image from the archived build context of an application.
 ///
 /// # Arguments
 /// * `app_name` - The name of the Docker image.
-/// * `app_path` - The application directory.
+/// * `context` - The build context, archived by `start_docker_context`.
+/// * `dockerfile` - The path of the Dockerfile, relative to the build context.
 ///
 /// # Returns
 /// * `Ok(())` if successful.
 /// * `Err(String)` if there is an error.
 pub async fn build_image(
     app_name: &str,
-    app_path: &str,
+    context: DockerContext,
+    dockerfile: &str,
     metadata: &AppMetadata,
 ) -> Result<(), String> {
     let docker = Docker::connect_with_local_defaults()
         .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
 
-    let tar_path =
-        create_docker_context(app_name, app_path).map_err(|e| format!("Error: {}", e))?;
-    let mut tar_file =
-        File::open(&tar_path).map_err(|e| format!("Failed to open tar file: {}", e))?;
-
-    let mut contents = Vec::new();
-    tar_file
-        .read_to_end(&mut contents)
-        .map_err(|e| format!("Failed to read tar file: {}", e))?;
+    let contents = context.finish().await?;
 
+    let limits = &config().build;
     let options = BuildImageOptions {
+        dockerfile: dockerfile.to_string(),
         t: format!("{}:latest", app_name.to_lowercase()),
         rm: true,
         labels: metadata.to_labels(),
+        memory: limits.memory_mb.map(|mb| mb * 1024 * 1024),
+        cpuperiod: limits.cpus.map(|_| BUILD_CPU_PERIOD),
+        cpuquota: limits
+            .cpus
+            .map(|cpus| (cpus * BUILD_CPU_PERIOD as f64) as u64),
         ..Default::default()
     };
 
@@ -636,50 +947,56 @@ pub async fn build_image(
         match build_result {
             Ok(output) => {
                 if let Some(stream) = output.stream {
-                    println!("Build Info: {}", stream);
+                    debug!("Build Info: {}", stream);
                 }
                 if let Some(error) = output.error {
-                    eprintln!("Error: {}", error);
+                    error!("Error: {}", error);
                 }
             }
             Err(e) => {
-                eprintln!("Error during build: {}", e);
+                error!("Error during build: {}", e);
             }
         }
     }
 
-    if let Err(e) = std::fs::remove_file(&tar_path) {
-        eprintln!("Warning: Failed to clean up tar file: {}", e);
-    } else {
-        println!("Successfully cleaned up tar file: {}", tar_path);
-    }
-
     Ok(())
 }
 
 /// Pushes a Docker image to a remote registry.
 ///
+/// The image is pushed under the `latest` tag and under its release tag, so previous
+/// releases stay available for rollbacks.
+///
 /// # Arguments
 ///
 /// * `app_name` - The name of the Docker image to push.
+/// * `release` - The release tag of the image (e.g., "r20250101-120000").
 ///
 /// # Returns
 ///
 /// * `Ok(())` if the image was successfully pushed.
 /// * `Err(String)` if there was an error during the push process.
-pub async fn push_image(app_name: &str) -> Result<(), String> {
+pub async fn push_image(app_name: &str, release: &str) -> Result<(), String> {
+    for tag in ["latest", release] {
+        push_image_tag(app_name, tag).await?;
+    }
+    Ok(())
+}
+
+/// Tags the local image of an application for the registry and pushes it under the given tag.
+async fn push_image_tag(app_name: &str, tag: &str) -> Result<(), String> {
     let docker = Docker::connect_with_local_defaults()
         .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
 
     // Local image name (without registry)
     let local_image = format!("{}:latest", app_name.to_lowercase());
     // Remote image name (with registry)
-    let remote_image = format!("registry:5000/{}", app_name.to_lowercase());
+    let remote_image = config().registry.repository(app_name);
 
     // Taguer l'image pour le registre
     let tag_options = TagImageOptions {
         repo: remote_image.clone(),
-        tag: "latest".parse().unwrap(),
+        tag: tag.to_string(),
     };
     docker
         .tag_image(&local_image, Some(tag_options))
@@ -687,7 +1004,7 @@ pub async fn push_image(app_name: &str) -> Result<(), String> {
         .map_err(|e| format!("Failed to tag image: {}", e))?;
 
     // Pousser l'image vers le registre
-    let push_options = PushImageOptions { tag: "latest" };
+    let push_options = PushImageOptions { tag };
 
     // Si votre registre nécessite une authentification, fournissez les identifiants
     let credentials = Some(DockerCredentials {
@@ -703,20 +1020,20 @@ pub async fn push_image(app_name: &str) -> Result<(), String> {
                     match serde_json::from_str::<serde_json::Value>(&stream) {
                         Ok(value) => {
                             if let Some(status) = value.get("status") {
-                                println!("Push Image info: {}", status);
+                                debug!("Push Image info: {}", status);
                             }
                         }
                         Err(_) => {
-                            println!("Push Image info: {}", stream);
+                            debug!("Push Image info: {}", stream);
                         }
                     }
                 }
                 if let Some(error) = output.error {
-                    eprintln!("Error: {}", error);
+                    error!("Error: {}", error);
                 }
             }
             Err(e) => {
-                eprintln!("Error pushing image: {}", e);
+                error!("Error pushing image: {}", e);
             }
         }
     }
@@ -724,24 +1041,6 @@ pub async fn push_image(app_name: &str) -> Result<(), String> {
     Ok(())
 }
 
-/// Runs the Docker Compose command to deploy the application.
-/// Creates and runs a Docker container from the specified image.
-///
-/// # Arguments
-/// * `app_name` - The name of the Docker image.
-///
-/// # Returns
-/// * `Ok(())` if the Docker Compose command was successful.
-/// * `Err(String)` if there was an error during execution.
-/// Connects the Nephelios container to the overlay network after Swarm initialization
-///
-/// This function uses the Docker API to:
-/// 1. Find the Nephelios container
-/// 2. Connect it to the nephelios_overlay network
-///
-/// # Returns
-/// * `Ok(())` if the connection was successful
-/// * `Err(String)` if there was an error during the process
 /// Disconnects the Nephelios container from the overlay network during cleanup
 ///
 /// This function uses the Docker API to:
@@ -801,7 +1100,6 @@ pub async fn disconnect_from_overlay_network() -> Result<(), String> {
 /// # Returns
 /// * `Ok(())` if successful.
 /// * `Err(String)` if an error occurs during connection or container lookup.
-
 pub async fn connect_to_overlay_network() -> Result<(), String> {
     let docker = Docker::connect_with_local_defaults()
         .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
@@ -847,14 +1145,18 @@ pub async fn connect_to_overlay_network() -> Result<(), String> {
 /// Deploys the Nephelios stack using the `docker stack deploy` command.
 ///
 /// This function runs the `docker stack deploy` command with the `nephelios.yml` file
-/// to deploy the Nephelios stack.
+/// to deploy the Nephelios stack. The command only gets `PATH`, `HOME` and the `DOCKER_*`
+/// variables of the server, so the stack file cannot interpolate its secrets.
 ///
 /// # Returns
 /// * `Ok(())` if the deployment is successful.
 /// * `Err(String)` if the deployment command fails.
-
 pub fn deploy_nephelios_stack() -> Result<(), String> {
+    let environment = std::env::vars()
+        .filter(|(name, _)| name == "PATH" || name == "HOME" || name.starts_with("DOCKER_"));
     let status = Command::new("docker")
+        .env_clear()
+        .envs(environment)
         .current_dir("./")
         .arg("stack")
         .arg("deploy")
@@ -871,96 +1173,1305 @@ pub fn deploy_nephelios_stack() -> Result<(), String> {
     Ok(())
 }
 
-/// Removes the container for the given application.
+/// Label `docker stack deploy` puts on the services and containers of the stack.
+const STACK_NAMESPACE_LABEL: &str = "com.docker.stack.namespace";
+
+/// Name of the stack the services of Nephelios belong to.
+const STACK_NAMESPACE: &str = "nephelios";
+
+/// Keys of a stack service translated to the Docker API by `stack_service_spec`, besides the
+/// modeled ones.
+const TRANSLATED_SERVICE_KEYS: &[&str] = &[
+    "environment",
+    "command",
+    "secrets",
+    "volumes",
+    "read_only",
+    "tmpfs",
+    "cap_drop",
+    "cap_add",
+    "security_opt",
+    "user",
+];
+
+/// Reads a stack file value as a list of strings, undoing the `$$` escapes of the stack file
+/// interpolation.
+fn stack_strings(value: &YamlValue) -> Option<Vec<String>> {
+    value
+        .as_sequence()?
+        .iter()
+        .map(|item| item.as_str().map(|item| item.replace("$$", "$")))
+        .collect()
+}
+
+/// Returns the Docker name of a volume, secret or network declared in the stack file.
 ///
-/// Executes the `docker rm` command to remove the container with the given name.
+/// Declarations with a `name` keep it, external ones keep their key, the others are prefixed
+/// with the stack name as `docker stack deploy` does.
+fn stack_resource_name(declarations: Option<&Mapping>, key: &str) -> String {
+    let declaration = declarations.and_then(|declarations| declarations.get(key));
+    if let Some(name) = declaration.and_then(|declaration| declaration.get("name")?.as_str()) {
+        return name.to_string();
+    }
+    if declaration.is_some_and(|declaration| {
+        declaration.get("external").and_then(YamlValue::as_bool) == Some(true)
+    }) {
+        return key.to_string();
+    }
+    format!("{}_{}", STACK_NAMESPACE, key)
+}
+
+/// Parses a resource spec of the stack file into Docker API amounts.
 ///
-/// # Arguments
+/// # Returns
+/// * `Some((Option<i64>, Option<i64>))` containing the nano CPUs and memory bytes.
+/// * `None` if an amount is invalid.
+fn resource_amounts(spec: &ResourceSpec) -> Option<(Option<i64>, Option<i64>)> {
+    let nano_cpus = match &spec.cpus {
+        Some(cpus) => Some((parse_cpus(cpus).ok()? * 1e9) as i64),
+        None => None,
+    };
+    let memory_bytes = match &spec.memory {
+        Some(memory) => Some(parse_memory(memory).ok()? as i64),
+        None => None,
+    };
+    Some((nano_cpus, memory_bytes))
+}
+
+/// Converts a service of the stack file to the spec `docker stack deploy` would create.
 ///
-/// * `app_name` - The name of the container to remove.
+/// # Arguments
+/// * `stack` - The stack file.
+/// * `name` - The name of the service in the stack file.
 ///
 /// # Returns
+/// * `Ok(Some(ServiceSpec))` containing the spec of the service, secrets referenced by name.
+/// * `Ok(None)` if the service uses keys that are not translated.
+/// * `Err(String)` if the service is not in the stack file.
+fn stack_service_spec(stack: &StackFile, name: &str) -> Result<Option<ServiceSpec>, String> {
+    let service = stack
+        .services
+        .get(name)
+        .ok_or_else(|| format!("Service {} not found in the file nephelios.yml", name))?;
+    let deploy = service.deploy.clone().unwrap_or_default();
+    let untranslated = service.extra.keys().any(|key| {
+        key.as_str()
+            .is_none_or(|key| !TRANSLATED_SERVICE_KEYS.contains(&key))
+    }) || !deploy.extra.is_empty()
+        || deploy
+            .placement
+            .as_ref()
+            .is_some_and(|placement| !placement.extra.is_empty())
+        || deploy
+            .update_config
+            .as_ref()
+            .is_some_and(|update_config| !update_config.extra.is_empty());
+    if untranslated {
+        return Ok(None);
+    }
+    let security: ServiceSecurity =
+        match serde_yaml::from_value(YamlValue::Mapping(service.extra.clone())) {
+            Ok(security) => security,
+            Err(_) => return Ok(None),
+        };
+    if security
+        .security_opt
+        .iter()
+        .any(|option| option != "no-new-privileges:true")
+    {
+        return Ok(None);
+    }
+
+    let namespace = || {
+        (
+            STACK_NAMESPACE_LABEL.to_string(),
+            STACK_NAMESPACE.to_string(),
+        )
+    };
+    // Label values are escaped like the rest of the stack file, e.g. basic auth hashes
+    let mut labels: HashMap<String, String> = deploy
+        .labels
+        .iter()
+        .map(|label| match label.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.replace("$$", "$")),
+            None => (label.clone(), String::new()),
+        })
+        .collect();
+    labels.extend([namespace()]);
+
+    let env = match service.extra.get("environment") {
+        None => None,
+        Some(YamlValue::Mapping(variables)) => {
+            let mut env = Vec::new();
+            for (key, value) in variables {
+                let (Some(key), Some(value)) = (key.as_str(), value.as_str()) else {
+                    return Ok(None);
+                };
+                env.push(format!("{}={}", key, value.replace("$$", "$")));
+            }
+            Some(env)
+        }
+        Some(variables) => match stack_strings(variables) {
+            Some(env) => Some(env),
+            None => return Ok(None),
+        },
+    };
+    let command = match service.extra.get("command").map(stack_strings) {
+        None => None,
+        Some(Some(command)) => Some(command),
+        Some(None) => return Ok(None),
+    };
+
+    let mut mounts = Vec::new();
+    if let Some(volumes) = service.extra.get("volumes") {
+        let Some(volumes) = stack_strings(volumes) else {
+            return Ok(None);
+        };
+        for volume in volumes {
+            let Some((source, target)) = volume.split_once(':') else {
+                return Ok(None);
+            };
+            let bind = source.starts_with('/');
+            mounts.push(Mount {
+                target: Some(target.to_string()),
+                source: Some(if bind {
+                    source.to_string()
+                } else {
+                    stack_resource_name(Some(&stack.volumes), source)
+                }),
+                typ: Some(if bind {
+                    MountTypeEnum::BIND
+                } else {
+                    MountTypeEnum::VOLUME
+                }),
+                volume_options: (!bind).then(|| MountVolumeOptions {
+                    labels: Some(HashMap::from([namespace()])),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            });
+        }
+    }
+    for path in &security.tmpfs {
+        mounts.push(Mount {
+            target: Some(path.clone()),
+            typ: Some(MountTypeEnum::TMPFS),
+            ..Default::default()
+        });
+    }
+
+    let mut secrets = Vec::new();
+    if let Some(references) = service.extra.get("secrets") {
+        let Some(references) = references.as_sequence() else {
+            return Ok(None);
+        };
+        let declarations = stack.extra.get("secrets").and_then(YamlValue::as_mapping);
+        for reference in references {
+            let Some(source) = reference
+                .as_str()
+                .or_else(|| reference.get("source")?.as_str())
+            else {
+                return Ok(None);
+            };
+            let target = reference
+                .get("target")
+                .and_then(YamlValue::as_str)
+                .unwrap_or(source);
+            secrets.push(TaskSpecContainerSpecSecrets {
+                file: Some(TaskSpecContainerSpecFile {
+                    name: Some(target.to_string()),
+                    uid: Some("0".to_string()),
+                    gid: Some("0".to_string()),
+                    mode: Some(0o444),
+                }),
+                secret_name: Some(stack_resource_name(declarations, source)),
+                ..Default::default()
+            });
+        }
+    }
+
+    let (mut limits, mut reservations) = (None, None);
+    if let Some(resources) = &deploy.resources {
+        if let Some(spec) = &resources.limits {
+            let Some((nano_cpus, memory_bytes)) = resource_amounts(spec) else {
+                return Ok(None);
+            };
+            limits = Some(Limit {
+                nano_cpus,
+                memory_bytes,
+                ..Default::default()
+            });
+        }
+        if let Some(spec) = &resources.reservations {
+            let Some((nano_cpus, memory_bytes)) = resource_amounts(spec) else {
+                return Ok(None);
+            };
+            reservations = Some(ResourceObject {
+                nano_cpus,
+                memory_bytes,
+                ..Default::default()
+            });
+        }
+    }
+
+    let update_config = match &deploy.update_config {
+        None => None,
+        Some(update_config) => {
+            let delay = match update_config.delay.as_deref().map(parse_duration) {
+                None => None,
+                Some(Some(delay)) => Some(delay.as_nanos() as i64),
+                Some(None) => return Ok(None),
+            };
+            let failure_action = match update_config.failure_action.as_deref().map(str::parse) {
+                None => None,
+                Some(Ok(failure_action)) => Some(failure_action),
+                Some(Err(_)) => return Ok(None),
+            };
+            let order = match update_config.order.as_deref().map(str::parse) {
+                None => None,
+                Some(Ok(order)) => Some(order),
+                Some(Err(_)) => return Ok(None),
+            };
+            Some(ServiceSpecUpdateConfig {
+                parallelism: update_config.parallelism.map(i64::from),
+                delay,
+                failure_action,
+                order,
+                ..Default::default()
+            })
+        }
+    };
+
+    let mode = match deploy.mode.as_deref() {
+        None | Some("replicated") => ServiceSpecMode {
+            replicated: Some(ServiceSpecModeReplicated {
+                replicas: Some(i64::from(deploy.replicas.unwrap_or(1))),
+            }),
+            ..Default::default()
+        },
+        Some("global") => ServiceSpecMode {
+            global: Some(HashMap::new()),
+            ..Default::default()
+        },
+        Some(_) => return Ok(None),
+    };
+
+    let networks = service
+        .networks
+        .iter()
+        .map(|network| NetworkAttachmentConfig {
+            target: Some(stack_resource_name(Some(&stack.networks), network)),
+            aliases: Some(vec![name.to_string()]),
+            ..Default::default()
+        })
+        .collect();
+
+    Ok(Some(ServiceSpec {
+        name: Some(format!("{}_{}", STACK_NAMESPACE, name)),
+        labels: Some(labels),
+        mode: Some(mode),
+        update_config,
+        task_template: Some(TaskSpec {
+            container_spec: Some(TaskSpecContainerSpec {
+                image: service.image.clone(),
+                labels: Some(HashMap::from([namespace()])),
+                command,
+                env,
+                user: security.user.clone(),
+                privileges: (!security.security_opt.is_empty()).then(|| {
+                    TaskSpecContainerSpecPrivileges {
+                        no_new_privileges: Some(true),
+                        ..Default::default()
+                    }
+                }),
+                read_only: security.read_only.then_some(true),
+                mounts: (!mounts.is_empty()).then_some(mounts),
+                secrets: (!secrets.is_empty()).then_some(secrets),
+                capability_add: (!security.cap_add.is_empty()).then_some(security.cap_add),
+                capability_drop: (!security.cap_drop.is_empty()).then_some(security.cap_drop),
+                ..Default::default()
+            }),
+            resources: Some(TaskSpecResources {
+                limits,
+                reservations,
+            }),
+            placement: deploy.placement.map(|placement| TaskSpecPlacement {
+                constraints: Some(placement.constraints),
+                preferences: Some(
+                    placement
+                        .preferences
+                        .into_iter()
+                        .map(|preference| TaskSpecPlacementPreferences {
+                            spread: Some(TaskSpecPlacementSpread {
+                                spread_descriptor: Some(preference.spread),
+                            }),
+                        })
+                        .collect(),
+                ),
+                ..Default::default()
+            }),
+            networks: Some(networks),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }))
+}
+
+/// Deploys one service of the stack file through the Docker API, leaving the other services
+/// of the stack untouched.
 ///
-/// A `Result` indicating success or an error message in case of failure.
-pub async fn remove_service(app_name: &str) -> Result<(), String> {
+/// The service is created if it does not exist yet, and updated otherwise: only the tasks of
+/// this service are replaced, and only if their spec changed. Services using keys of the stack
+/// file that are not translated fall back to `deploy_nephelios_stack`.
+///
+/// # Arguments
+/// * `name` - The name of the service in the stack file (e.g., the name of an app).
+///
+/// # Returns
+/// * `Ok(())` if the service was created or updated.
+/// * `Err(String)` if the service is not in the stack file, or the Docker API refused it.
+pub async fn deploy_stack_service(name: &str) -> Result<(), String> {
+    let stack = load_stack().map_err(|e| format!("Failed to read the stack file: {}", e))?;
+    let Some(mut spec) = stack_service_spec(&stack, name)? else {
+        debug!("Deploying the whole stack for service {}", name);
+        return deploy_nephelios_stack();
+    };
+
     let docker = Docker::connect_with_local_defaults()
         .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+    let container_spec = spec
+        .task_template
+        .as_mut()
+        .and_then(|task_template| task_template.container_spec.as_mut());
+    if let Some(secrets) = container_spec.and_then(|container| container.secrets.as_mut()) {
+        for secret in secrets {
+            let secret_name = secret.secret_name.clone().unwrap_or_default();
+            let found = docker
+                .inspect_secret(&secret_name)
+                .await
+                .map_err(|e| format!("Failed to inspect secret {}: {}", secret_name, e))?;
+            secret.secret_id = found.id;
+        }
+    }
 
-    let service_name: &str = &format!("nephelios_{}", app_name);
+    let service_name = format!("{}_{}", STACK_NAMESPACE, name);
+    let current = match docker
+        .inspect_service(&service_name, None::<InspectServiceOptions>)
+        .await
+    {
+        Ok(current) => current,
+        Err(DockerResponseServerError {
+            status_code: 404, ..
+        }) => {
+            docker
+                .create_service(spec, None)
+                .await
+                .map_err(|e| format!("Failed to create service {}: {}", service_name, e))?;
+            info!("Created service {}", service_name);
+            return Ok(());
+        }
+        Err(e) => return Err(format!("Failed to inspect service {}: {}", service_name, e)),
+    };
 
-    println!("Removing service: {}", service_name);
+    let version = current
+        .version
+        .and_then(|version| version.index)
+        .ok_or_else(|| format!("Service {} has no version", service_name))?;
+    // Keep the restarts forced so far, and the image digest pinned when the service was
+    // created, so an unchanged spec does not replace the tasks
+    let current_task = current
+        .spec
+        .and_then(|spec| spec.task_template)
+        .unwrap_or_default();
+    if let Some(task_template) = spec.task_template.as_mut() {
+        task_template.force_update = current_task.force_update;
+        let current_image = current_task
+            .container_spec
+            .and_then(|container| container.image);
+        if let (Some(container), Some(current_image)) =
+            (task_template.container_spec.as_mut(), current_image)
+        {
+            if container
+                .image
+                .as_ref()
+                .is_some_and(|image| current_image.starts_with(&format!("{}@", image)))
+            {
+                container.image = Some(current_image);
+            }
+        }
+    }
 
     docker
-        .delete_service(service_name)
+        .update_service(
+            &service_name,
+            spec,
+            UpdateServiceOptions {
+                version,
+                ..Default::default()
+            },
+            None,
+        )
         .await
-        .map_err(|e| format!("Failed to start container: {}", e))?;
+        .map_err(|e| format!("Failed to update service {}: {}", service_name, e))?;
     Ok(())
 }
 
-/// Leaves the Docker Swarm.
+/// Lists the Swarm services of the Nephelios stack.
 ///
-/// Executes the `docker swarm leave -f` command to forcefully leave the Docker Swarm.
+/// One-shot job services are not part of the stack, and are left out.
 ///
 /// # Returns
-///
-/// * `Ok(())` if the command was successful.
-/// * `Err(String)` if there was an error during execution.
-pub fn leave_swarm() -> Result<(), String> {
-    let status = Command::new("docker")
-        .arg("swarm")
-        .arg("leave")
-        .arg("-f")
-        .status()
-        .map_err(|e| format!("Failed to execute leave swarm: {}", e))?;
-
-    if !status.success() {
-        return Err("Docker Compose command failed".to_string());
-    }
+/// * `Ok(Vec<String>)` containing the names of the services in the stack file (e.g.,
+///   "my-app" for the `nephelios_my-app` service).
+/// * `Err(String)` if the services could not be listed.
+pub async fn list_stack_services() -> Result<Vec<String>, String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+    let label = format!("{}={}", STACK_NAMESPACE_LABEL, STACK_NAMESPACE);
+    let options = ListServicesOptions {
+        filters: HashMap::from([("label", vec![label.as_str()])]),
+        ..Default::default()
+    };
+    let services = docker
+        .list_services(Some(options))
+        .await
+        .map_err(|e| format!("Failed to list services: {}", e))?;
 
-    Ok(())
+    let prefix = format!("{}_", STACK_NAMESPACE);
+    Ok(services
+        .into_iter()
+        .filter_map(|service| service.spec?.name)
+        .filter_map(|name| name.strip_prefix(&prefix).map(str::to_string))
+        .collect())
 }
 
-/// Stops the Nephelios stack by removing the Docker stack.
+/// Lists the volumes and networks of the Nephelios stack the stack file does not use anymore.
 ///
-/// # Returns
+/// Volumes are unused when no service of the stack file mounts them and no container uses
+/// them. Networks are unused when the stack file neither declares them nor attaches a service
+/// to them.
 ///
-/// * `Ok(())` if the stack was successfully stopped.
-/// * `Err(String)` if there was an error during the process.
-pub fn stop_nephelios_stack() -> Result<(), String> {
-    let status = Command::new("docker")
-        .arg("stack")
-        .arg("rm")
-        .arg("nephelios")
-        .status()
-        .map_err(|e| format!("Failed to execute remove Nephelios: {}", e))?;
+/// # Arguments
+/// * `stack` - The stack file.
+///
+/// # Returns
+/// * `Ok((Vec<String>, Vec<String>))` containing the Docker names of the unused volumes and
+///   networks.
+/// * `Err(String)` if the volumes or networks could not be listed.
+pub async fn unused_stack_resources(
+    stack: &StackFile,
+) -> Result<(Vec<String>, Vec<String>), String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
 
-    if !status.success() {
-        return Err("Docker Compose command failed".to_string());
+    let volume_key = YamlValue::String("volumes".to_string());
+    let mut used_volumes = HashSet::new();
+    let mut used_networks: HashSet<String> = stack
+        .networks
+        .keys()
+        .filter_map(YamlValue::as_str)
+        .map(|key| stack_resource_name(Some(&stack.networks), key))
+        .collect();
+    for service in stack.services.values() {
+        let mounts = service
+            .extra
+            .get(&volume_key)
+            .and_then(YamlValue::as_sequence);
+        for mount in mounts.into_iter().flatten() {
+            let source = match mount {
+                YamlValue::String(mount) => mount.split(':').next(),
+                mount => mount.get("source").and_then(YamlValue::as_str),
+            };
+            if let Some(source) = source.filter(|source| !source.starts_with('/')) {
+                used_volumes.insert(stack_resource_name(Some(&stack.volumes), source));
+            }
+        }
+        for network in &service.networks {
+            used_networks.insert(stack_resource_name(Some(&stack.networks), network));
+        }
     }
 
-    Ok(())
+    let label = format!("{}={}", STACK_NAMESPACE_LABEL, STACK_NAMESPACE);
+    let volumes = docker
+        .list_volumes(Some(ListVolumesOptions {
+            filters: HashMap::from([("label", vec![label.as_str()]), ("dangling", vec!["true"])]),
+        }))
+        .await
+        .map_err(|e| format!("Failed to list volumes: {}", e))?;
+    let unused_volumes = volumes
+        .volumes
+        .unwrap_or_default()
+        .into_iter()
+        .map(|volume| volume.name)
+        .filter(|name| !used_volumes.contains(name))
+        .collect();
+
+    let networks = docker
+        .list_networks(Some(ListNetworksOptions {
+            filters: HashMap::from([("label", vec![label.as_str()])]),
+        }))
+        .await
+        .map_err(|e| format!("Failed to list networks: {}", e))?;
+    let unused_networks = networks
+        .into_iter()
+        .filter_map(|network| network.name)
+        .filter(|name| !used_networks.contains(name))
+        .collect();
+
+    Ok((unused_volumes, unused_networks))
 }
 
-/// Initializes Docker Swarm with the given IP address.
+/// Removes a volume, refused by Docker while a container uses it.
 ///
 /// # Arguments
+/// * `name` - The Docker name of the volume.
+pub async fn remove_volume(name: &str) -> Result<(), String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+    docker
+        .remove_volume(name, None)
+        .await
+        .map_err(|e| format!("Failed to remove volume {}: {}", name, e))
+}
+
+/// Removes a network, refused by Docker while a service or container is attached to it.
+///
+/// # Arguments
+/// * `name` - The Docker name of the network.
+pub async fn remove_network(name: &str) -> Result<(), String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+    docker
+        .remove_network(name)
+        .await
+        .map_err(|e| format!("Failed to remove network {}: {}", name, e))
+}
+
+/// A local image tagged for the registry of Nephelios.
+pub struct RegistryImage {
+    /// The repository in the registry (e.g., "my-app").
+    pub repository: String,
+    /// The full reference of the image (e.g., "registry:5000/my-app:r20250101-120000").
+    pub reference: String,
+    /// When the image was built, as a Unix timestamp.
+    pub created: i64,
+}
+
+/// Lists the local images tagged for the registry of Nephelios, one per tag.
+///
+/// # Returns
+/// * `Ok(Vec<RegistryImage>)` containing the images.
+/// * `Err(String)` if the images could not be listed.
+pub async fn list_registry_images() -> Result<Vec<RegistryImage>, String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+    let images = docker
+        .list_images(None::<ListImagesOptions<String>>)
+        .await
+        .map_err(|e| format!("Failed to list images: {}", e))?;
+
+    // Release images are tagged `<registry>/<app>:<release>`
+    let registry_prefix = format!("{}/", config().registry.host);
+    let mut registry_images = Vec::new();
+    for image in images {
+        for reference in image.repo_tags {
+            let repository = reference
+                .strip_prefix(&registry_prefix)
+                .and_then(|tag| tag.rsplit_once(':'))
+                .map(|(repository, _)| repository.to_string());
+            if let Some(repository) = repository {
+                registry_images.push(RegistryImage {
+                    repository,
+                    reference,
+                    created: image.created,
+                });
+            }
+        }
+    }
+    Ok(registry_images)
+}
+
+/// Resolves an image to the digest it has in its registry, so the image that is deployed is the
+/// one that was checked, even if its tag is pushed again.
+///
+/// # Arguments
+/// * `image` - The image reference, with its tag.
+///
+/// # Returns
+/// * `Ok(String)` containing the reference by digest, e.g. `registry:5000/my-app@sha256:...`.
+/// * `Err(String)` if the registry could not be queried.
+pub async fn image_digest(image: &str) -> Result<String, String> {
+    if image.contains('@') {
+        return Ok(image.to_string());
+    }
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+    let digest = docker
+        .inspect_registry_image(image, None)
+        .await
+        .map_err(|e| format!("Failed to resolve the digest of image {}: {}", image, e))?
+        .descriptor
+        .digest
+        .ok_or_else(|| format!("The registry returned no digest for image {}", image))?;
+
+    // The tag follows the last `:` after the last `/`, which may be a registry port
+    let repository = match image.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => repository,
+        _ => image,
+    };
+    Ok(format!("{}@{}", repository, digest))
+}
+
+/// Removes the tag of a local image, and the image once it has no tag left.
+///
+/// # Arguments
+/// * `reference` - The reference of the image, with its tag.
+pub async fn remove_image(reference: &str) -> Result<(), String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+    docker
+        .remove_image(reference, None::<RemoveImageOptions>, None)
+        .await
+        .map_err(|e| format!("Failed to remove image {}: {}", reference, e))?;
+    Ok(())
+}
+
+/// Creates a Docker Swarm secret unless one with the same name already exists.
+///
+/// The value is passed through stdin so it never appears in the process list.
+///
+/// # Arguments
+///
+/// * `name` - The name of the secret.
+/// * `value` - The secret value.
+///
+/// # Returns
+///
+/// * `Ok(())` if the secret exists or was created.
+/// * `Err(String)` if there was an error during creation.
+pub fn create_docker_secret(name: &str, value: &str) -> Result<(), String> {
+    if docker_secret_exists(name)? {
+        return Ok(());
+    }
+
+    let mut child = Command::new("docker")
+        .args(["secret", "create", name, "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to create secret {}: {}", name, e))?;
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| format!("Failed to create secret {}: stdin unavailable", name))?
+        .write_all(value.as_bytes())
+        .map_err(|e| format!("Failed to create secret {}: {}", name, e))?;
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to create secret {}: {}", name, e))?;
+
+    if !status.success() {
+        return Err(format!("Docker secret create command failed for {}", name));
+    }
+
+    Ok(())
+}
+
+/// Checks whether a Docker Swarm secret exists.
+///
+/// # Arguments
+///
+/// * `name` - The name of the secret.
+///
+/// # Returns
+///
+/// * `Ok(bool)` telling whether the secret exists.
+/// * `Err(String)` if `docker secret inspect` could not be run.
+pub fn docker_secret_exists(name: &str) -> Result<bool, String> {
+    let status = Command::new("docker")
+        .args(["secret", "inspect", name])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to inspect secret {}: {}", name, e))?;
+    Ok(status.success())
+}
+
+/// Removes a Docker Swarm secret.
+///
+/// Swarm refuses to remove a secret still mounted by a service, including the tasks of a
+/// rolling update that are being replaced.
+///
+/// # Arguments
+///
+/// * `name` - The name of the secret.
+///
+/// # Returns
+///
+/// * `Ok(())` if the secret was removed or did not exist.
+/// * `Err(String)` if the secret could not be removed.
+pub fn remove_docker_secret(name: &str) -> Result<(), String> {
+    if !docker_secret_exists(name)? {
+        return Ok(());
+    }
+
+    let output = Command::new("docker")
+        .args(["secret", "rm", name])
+        .output()
+        .map_err(|e| format!("Failed to remove secret {}: {}", name, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to remove secret {}: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Removes the container for the given application.
+///
+/// Executes the `docker rm` command to remove the container with the given name.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the container to remove.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an error message in case of failure.
+pub async fn remove_service(app_name: &str) -> Result<(), String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+
+    let service_name: &str = &format!("nephelios_{}", app_name);
+
+    info!("Removing service: {}", service_name);
+
+    docker
+        .delete_service(service_name)
+        .await
+        .map_err(|e| format!("Failed to start container: {}", e))?;
+    Ok(())
+}
+
+/// Applies a change to the spec of the service of an application through the Docker API.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application.
+/// * `change` - The change to apply to the current spec.
+///
+/// # Returns
+///
+/// * `Ok(())` if the update was accepted.
+/// * `Err(String)` if the service does not exist or the update failed.
+async fn update_service_spec<F>(app_name: &str, change: F) -> Result<(), String>
+where
+    F: FnOnce(&mut ServiceSpec),
+{
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+
+    let service_name = format!("nephelios_{}", app_name);
+    let service = docker
+        .inspect_service(&service_name, None::<InspectServiceOptions>)
+        .await
+        .map_err(|e| format!("Failed to inspect service {}: {}", service_name, e))?;
+
+    let version = service
+        .version
+        .and_then(|version| version.index)
+        .ok_or_else(|| format!("Service {} has no version", service_name))?;
+    let mut spec = service
+        .spec
+        .ok_or_else(|| format!("Service {} has no spec", service_name))?;
+
+    change(&mut spec);
+
+    docker
+        .update_service(
+            &service_name,
+            spec,
+            UpdateServiceOptions {
+                version,
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .map_err(|e| format!("Failed to update service {}: {}", service_name, e))?;
+
+    Ok(())
+}
+
+/// Forces a rolling restart of the service of an application.
+///
+/// Bumps the `ForceUpdate` counter of the service task template, which makes Swarm replace
+/// every task following the update policy of the service, without changing its image.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+///
+/// * `Ok(())` if the update was accepted.
+/// * `Err(String)` if the service does not exist or the update failed.
+pub async fn force_update_service(app_name: &str) -> Result<(), String> {
+    update_service_spec(app_name, |spec| {
+        let task_template = spec.task_template.get_or_insert_with(Default::default);
+        task_template.force_update = Some(task_template.force_update.unwrap_or(0) + 1);
+    })
+    .await
+}
+
+/// Sets the number of replicas of the service of an application.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application.
+/// * `replicas` - The new number of replicas.
+///
+/// # Returns
+///
+/// * `Ok(())` if the update was accepted.
+/// * `Err(String)` if the service does not exist or the update failed.
+pub async fn scale_service(app_name: &str, replicas: u32) -> Result<(), String> {
+    update_service_spec(app_name, |spec| {
+        let mode = spec.mode.get_or_insert_with(Default::default);
+        mode.replicated
+            .get_or_insert_with(Default::default)
+            .replicas = Some(i64::from(replicas));
+    })
+    .await
+}
+
+/// Reads the lines of a child process output as a stream, ending after the first error.
+///
+/// The child is kept in the stream state, so dropping the stream kills the process.
+fn output_lines<R>(
+    reader: R,
+    child: Option<tokio::process::Child>,
+) -> impl Stream<Item = Result<String, std::io::Error>>
+where
+    R: AsyncRead + Unpin,
+{
+    let lines = tokio::io::BufReader::new(reader).lines();
+    futures::stream::unfold(Some((lines, child)), |state| async move {
+        let (mut lines, child) = state?;
+        match lines.next_line().await {
+            Ok(Some(line)) => Some((Ok(line), Some((lines, child)))),
+            Ok(None) => None,
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+}
+
+/// Streams the logs of the service of an application.
+///
+/// Runs `docker service logs`, which gathers the logs of every task of the service across
+/// the Swarm nodes. Each line is prefixed with its task and timestamp. The process is killed
+/// when the stream is dropped, e.g. when the client disconnects.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application.
+/// * `tail` - The number of lines to return from the end of the logs, all lines if `None`.
+/// * `follow` - Whether to keep streaming new lines.
+///
+/// # Returns
+///
+/// * `Ok(Stream)` yielding the log lines of stdout and stderr.
+/// * `Err(String)` if the command could not be started.
+pub fn stream_service_logs(
+    app_name: &str,
+    tail: Option<u32>,
+    follow: bool,
+) -> Result<impl Stream<Item = Result<String, std::io::Error>>, String> {
+    let tail = tail.map_or_else(|| "all".to_string(), |tail| tail.to_string());
+    let mut command = tokio::process::Command::new("docker");
+    command
+        .args([
+            "service",
+            "logs",
+            "--timestamps",
+            "--no-trunc",
+            "--tail",
+            &tail,
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if follow {
+        command.arg("--follow");
+    }
+    command.arg(format!("nephelios_{}", app_name));
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to execute docker service logs: {}", e))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or("Failed to read docker service logs output")?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or("Failed to read docker service logs output")?;
+
+    Ok(futures::stream::select(
+        output_lines(stdout, Some(child)),
+        output_lines(stderr, None),
+    ))
+}
+
+/// An event of a command run in an app container.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExecEvent {
+    /// A chunk of the command output.
+    Output { stream: &'static str, data: String },
+    /// The outcome of the command, always the last event.
+    Exit {
+        exit_code: Option<i64>,
+        timed_out: bool,
+        error: Option<String>,
+    },
+}
+
+impl From<LogOutput> for ExecEvent {
+    fn from(output: LogOutput) -> Self {
+        let stream = match output {
+            LogOutput::StdErr { .. } => "stderr",
+            _ => "stdout",
+        };
+        ExecEvent::Output {
+            stream,
+            data: output.to_string(),
+        }
+    }
+}
+
+/// Lists the IDs and names of the running containers of the service of an application on
+/// this node.
+async fn list_running_containers(
+    docker: &Docker,
+    app_name: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let service_label = format!("com.docker.swarm.service.name=nephelios_{}", app_name);
+    let mut filters = HashMap::new();
+    filters.insert("label", vec![service_label.as_str()]);
+    filters.insert("status", vec!["running"]);
+
+    let containers = docker
+        .list_containers(Some(ListContainersOptions {
+            filters,
+            ..Default::default()
+        }))
+        .await
+        .map_err(|e| format!("Failed to list containers: {}", e))?;
+
+    Ok(containers
+        .into_iter()
+        .filter_map(|container| {
+            let id = container.id?;
+            let name = container
+                .names
+                .and_then(|names| names.into_iter().next())
+                .map(|name| name.trim_start_matches('/').to_string())
+                .unwrap_or_else(|| id.clone());
+            Some((id, name))
+        })
+        .collect())
+}
+
+/// Finds a running container of the service of an application on this node.
+async fn find_running_container(docker: &Docker, app_name: &str) -> Result<String, String> {
+    list_running_containers(docker, app_name)
+        .await?
+        .into_iter()
+        .next()
+        .map(|(id, _)| id)
+        .ok_or_else(|| format!("No running container found for {}", app_name))
+}
+
+/// A log line of an app container.
+#[derive(Debug, Serialize)]
+pub struct ContainerLogLine {
+    /// The name of the container (e.g., "nephelios_my-app.1.x8f2k").
+    pub container: String,
+    /// "stdout" or "stderr".
+    pub stream: &'static str,
+    /// The line, prefixed with its timestamp.
+    pub line: String,
+}
+
+/// Follows the logs of the running containers of an application on this node.
+///
+/// Unlike `stream_service_logs`, the logs are read through the Docker API, so only the
+/// replicas running on this node are included. The stream ends when every container stops.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application.
+/// * `tail` - The number of past lines to return for each container before following.
+///
+/// # Returns
+///
+/// * `Ok(Stream)` yielding the log lines of every container.
+/// * `Err(String)` if no running container was found.
+pub async fn follow_container_logs(
+    app_name: &str,
+    tail: u32,
+) -> Result<impl Stream<Item = Result<ContainerLogLine, String>>, String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+    let containers = list_running_containers(&docker, app_name).await?;
+    if containers.is_empty() {
+        return Err(format!("No running container found for {}", app_name));
+    }
+
+    let streams = containers.into_iter().map(|(id, name)| {
+        let options = LogsOptions::<String> {
+            follow: true,
+            stdout: true,
+            stderr: true,
+            timestamps: true,
+            tail: tail.to_string(),
+            ..Default::default()
+        };
+        docker
+            .logs(&id, Some(options))
+            .map(move |output| {
+                let output =
+                    output.map_err(|e| format!("Failed to read logs of {}: {}", name, e))?;
+                let stream = match output {
+                    LogOutput::StdErr { .. } => "stderr",
+                    _ => "stdout",
+                };
+                Ok(ContainerLogLine {
+                    container: name.clone(),
+                    stream,
+                    line: output.to_string().trim_end().to_string(),
+                })
+            })
+            .boxed()
+    });
+    Ok(futures::stream::select_all(streams))
+}
+
+/// Runs a command in a running container of an application and streams its output.
+///
+/// The command runs in the first running task of the service found on this node. The stream
+/// yields the stdout and stderr chunks as they are produced, then a final `Exit` event with
+/// the exit code. When the timeout expires, the stream ends with a timed out `Exit` event.
+/// The Docker API cannot signal an exec, so the command is wrapped in the container's
+/// `timeout` command, which kills it a second after the deadline; containers without one
+/// report that the command may still be running.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application.
+/// * `command` - The command and its arguments.
+/// * `timeout` - How long to wait for the command to complete.
+///
+/// # Returns
+///
+/// * `Ok(Stream)` yielding the output and exit events of the command.
+/// * `Err(String)` if no running container was found or the command could not be started.
+pub async fn exec_in_app(
+    app_name: &str,
+    command: Vec<String>,
+    timeout: std::time::Duration,
+) -> Result<impl Stream<Item = ExecEvent>, String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+    let container_id = find_running_container(&docker, app_name).await?;
+
+    let killable = has_timeout_command(&docker, &container_id).await;
+    let command = if killable {
+        // One second past our deadline, so the stream reports the timeout before the kill.
+        let seconds = timeout.as_secs() + 1;
+        ["timeout", "-s", "KILL", &seconds.to_string()]
+            .into_iter()
+            .map(String::from)
+            .chain(command)
+            .collect()
+    } else {
+        command
+    };
+
+    let exec = docker
+        .create_exec(
+            &container_id,
+            CreateExecOptions {
+                attach_stdout: Some(true),
+                attach_stderr: Some(true),
+                cmd: Some(command),
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|e| format!("Failed to create exec: {}", e))?;
+
+    let output = match docker
+        .start_exec(&exec.id, None)
+        .await
+        .map_err(|e| format!("Failed to start exec: {}", e))?
+    {
+        StartExecResults::Attached { output, .. } => output,
+        StartExecResults::Detached => return Err("Exec started detached".to_string()),
+    };
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    Ok(futures::stream::unfold(
+        Some((docker, exec.id, output)),
+        move |state| async move {
+            let (docker, exec_id, mut output) = state?;
+            match tokio::time::timeout_at(deadline, output.next()).await {
+                Ok(Some(Ok(chunk))) => Some((chunk.into(), Some((docker, exec_id, output)))),
+                Ok(Some(Err(e))) => Some((
+                    ExecEvent::Exit {
+                        exit_code: None,
+                        timed_out: false,
+                        error: Some(format!("Failed to read exec output: {}", e)),
+                    },
+                    None,
+                )),
+                Ok(None) => {
+                    let (exit_code, error) = match docker.inspect_exec(&exec_id).await {
+                        Ok(inspect) => (inspect.exit_code, None),
+                        Err(e) => (None, Some(format!("Failed to inspect exec: {}", e))),
+                    };
+                    Some((
+                        ExecEvent::Exit {
+                            exit_code,
+                            timed_out: false,
+                            error,
+                        },
+                        None,
+                    ))
+                }
+                Err(_) => Some((
+                    ExecEvent::Exit {
+                        exit_code: None,
+                        timed_out: true,
+                        error: (!killable).then(|| {
+                            "The container has no timeout command, the command may still be running"
+                                .to_string()
+                        }),
+                    },
+                    None,
+                )),
+            }
+        },
+    ))
+}
+
+/// Checks whether a container has a `timeout` command that can kill a command, as in coreutils
+/// and BusyBox.
+///
+/// # Arguments
+///
+/// * `docker` - The Docker client.
+/// * `container_id` - The ID of the container.
+///
+/// # Returns
+///
+/// * `true` if `timeout -s KILL` runs successfully in the container.
+async fn has_timeout_command(docker: &Docker, container_id: &str) -> bool {
+    let Ok(exec) = docker
+        .create_exec(
+            container_id,
+            CreateExecOptions {
+                attach_stdout: Some(true),
+                attach_stderr: Some(true),
+                cmd: Some(vec!["timeout", "-s", "KILL", "5", "true"]),
+                ..Default::default()
+            },
+        )
+        .await
+    else {
+        return false;
+    };
+    match docker.start_exec(&exec.id, None).await {
+        Ok(StartExecResults::Attached { mut output, .. }) => while output.next().await.is_some() {},
+        _ => return false,
+    }
+    docker
+        .inspect_exec(&exec.id)
+        .await
+        .is_ok_and(|inspect| inspect.exit_code == Some(0))
+}
+
+/// Leaves the Docker Swarm.
+///
+/// Executes the `docker swarm leave -f` command to forcefully leave the Docker Swarm.
+///
+/// # Returns
+///
+/// * `Ok(())` if the command was successful.
+/// * `Err(String)` if there was an error during execution.
+pub fn leave_swarm() -> Result<(), String> {
+    let status = Command::new("docker")
+        .arg("swarm")
+        .arg("leave")
+        .arg("-f")
+        .status()
+        .map_err(|e| format!("Failed to execute leave swarm: {}", e))?;
+
+    if !status.success() {
+        return Err("Docker Compose command failed".to_string());
+    }
+
+    Ok(())
+}
+
+/// Stops the Nephelios stack by removing the Docker stack.
+///
+/// # Returns
+///
+/// * `Ok(())` if the stack was successfully stopped.
+/// * `Err(String)` if there was an error during the process.
+pub fn stop_nephelios_stack() -> Result<(), String> {
+    let status = Command::new("docker")
+        .arg("stack")
+        .arg("rm")
+        .arg("nephelios")
+        .status()
+        .map_err(|e| format!("Failed to execute remove Nephelios: {}", e))?;
+
+    if !status.success() {
+        return Err("Docker Compose command failed".to_string());
+    }
+
+    Ok(())
+}
+
+/// Initializes Docker Swarm with the given IP address.
+///
+/// # Arguments
+///
+/// * `ip_addr` - The IP address to advertise for the Docker Swarm.
+///
+/// # Returns
 ///
-/// * `ip_addr` - The IP address to advertise for the Docker Swarm.
-///
-/// # Returns
-///
 /// * `Ok(())` if the Docker Swarm was successfully initialized.
 /// * `Err(String)` if there was an error during initialization.
 pub fn init_swarm(ip_addr: IpAddr) -> Result<(), String> {
     let addr_parameter = format!(
         "--advertise-addr={}",
-        env::var("ADVERTISE_ADDR").unwrap_or_else(|_| {
-            // Specify a default IP address if ADVERTISE_ADDR is not set
-            ip_addr.to_string()
-        })
+        config()
+            .server
+            .advertise_addr
+            .clone()
+            .unwrap_or_else(|| ip_addr.to_string())
     );
 
-    println!("Init swarm with address: {}", addr_parameter);
+    info!("Init swarm with address: {}", addr_parameter);
     let status = Command::new("docker")
         .arg("swarm")
         .arg("init")
@@ -1014,12 +2525,12 @@ pub async fn prune_images() -> Result<(), String> {
         .map_err(|e| format!("Failed to prune images: {}", e))?;
 
     match &result.images_deleted {
-        None => println!("No images deleted"),
+        None => info!("No images deleted"),
         Some(images_deleted) => {
             for image in images_deleted {
                 match &image.deleted {
                     None => {}
-                    Some(deleted) => println!("Deleted image: {}", deleted),
+                    Some(deleted) => info!("Deleted image: {}", deleted),
                 }
             }
         }
@@ -1028,150 +2539,944 @@ pub async fn prune_images() -> Result<(), String> {
     Ok(())
 }
 
-/// Parses the network I/O string from Docker stats.
+/// Computes the CPU usage of a container in percent of one CPU, as `docker stats` does.
+fn cpu_percent(stats: &Stats) -> f64 {
+    let cpu_delta = stats
+        .cpu_stats
+        .cpu_usage
+        .total_usage
+        .saturating_sub(stats.precpu_stats.cpu_usage.total_usage);
+    let system_delta = stats
+        .cpu_stats
+        .system_cpu_usage
+        .unwrap_or_default()
+        .saturating_sub(stats.precpu_stats.system_cpu_usage.unwrap_or_default());
+    if cpu_delta == 0 || system_delta == 0 {
+        return 0.0;
+    }
+
+    let online_cpus = stats.cpu_stats.online_cpus.unwrap_or_else(|| {
+        stats
+            .cpu_stats
+            .cpu_usage
+            .percpu_usage
+            .as_ref()
+            .map_or(1, |usage| usage.len() as u64)
+    });
+    cpu_delta as f64 / system_delta as f64 * online_cpus as f64 * 100.0
+}
+
+/// Computes the memory used by a container in bytes, without the page cache, as
+/// `docker stats` does.
+fn memory_bytes(stats: &Stats) -> u64 {
+    let usage = stats.memory_stats.usage.unwrap_or_default();
+    let cache = match stats.memory_stats.stats {
+        Some(MemoryStatsStats::V1(v1)) => v1.total_inactive_file,
+        Some(MemoryStatsStats::V2(v2)) => v2.inactive_file,
+        None => 0,
+    };
+    usage.saturating_sub(cache)
+}
+
+/// Sums the bytes received and sent by a container on every network.
+fn network_bytes(stats: &Stats) -> (u64, u64) {
+    stats
+        .networks
+        .iter()
+        .flat_map(|networks| networks.values())
+        .fold((0, 0), |(rx, tx), network| {
+            (rx + network.rx_bytes, tx + network.tx_bytes)
+        })
+}
+
+/// The metric labels of a container: its app, Swarm service and task.
+struct MetricLabels {
+    app: String,
+    service: String,
+    task: String,
+}
+
+impl MetricLabels {
+    /// Derives the labels from the Swarm labels of a container, falling back to its name for
+    /// containers not started by Swarm.
+    fn from_container(name: &str, labels: &HashMap<String, String>) -> Self {
+        let service = labels
+            .get("com.docker.swarm.service.name")
+            .cloned()
+            .unwrap_or_else(|| name.to_string());
+        let task = labels
+            .get("com.docker.swarm.task.name")
+            .cloned()
+            .unwrap_or_else(|| name.to_string());
+        let app = service
+            .strip_prefix("nephelios_")
+            .unwrap_or(&service)
+            .to_string();
+        MetricLabels { app, service, task }
+    }
+
+    fn values(&self) -> [&str; 3] {
+        [&self.app, &self.service, &self.task]
+    }
+}
+
+/// Updates Prometheus metrics from the Docker stats of the `nephelios` containers.
 ///
-/// This function takes a string formatted like "42kB / 252B", representing
-/// incoming and outgoing network data. It splits the string and converts
-/// each part to kilobytes.
+/// The stats of the running containers whose names start with `nephelios` are read
+/// concurrently through the Docker API, and the CPU (percent), memory (bytes) and network I/O
+/// (bytes) gauges are replaced with them, labeled with the app, service and task of each
+/// container.
 ///
-/// # Arguments
+/// # Returns
+/// * `Ok(())` if the update is successful.
+/// * `Err(String)` if the containers could not be listed.
+pub async fn update_metrics() -> Result<(), String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+
+    let mut filters = HashMap::new();
+    filters.insert("name", vec!["nephelios"]);
+    let containers = docker
+        .list_containers(Some(ListContainersOptions {
+            filters,
+            ..Default::default()
+        }))
+        .await
+        .map_err(|e| format!("Failed to list containers: {}", e))?;
+
+    let targets: Vec<(String, MetricLabels)> = containers
+        .into_iter()
+        .filter_map(|container| {
+            let name = container
+                .names?
+                .into_iter()
+                .next()?
+                .trim_start_matches('/')
+                .to_string();
+            if !name.starts_with("nephelios") {
+                return None;
+            }
+            let labels = MetricLabels::from_container(&name, &container.labels.unwrap_or_default());
+            Some((container.id?, labels))
+        })
+        .collect();
+
+    // Without `one_shot`, Docker samples twice to fill `precpu_stats`, needed for the CPU usage
+    let samples = futures::future::join_all(targets.into_iter().map(|(id, labels)| {
+        let docker = docker.clone();
+        async move {
+            let options = StatsOptions {
+                stream: false,
+                one_shot: false,
+            };
+            let stats = docker.stats(&id, Some(options)).next().await;
+            (labels, stats)
+        }
+    }))
+    .await;
+
+    CONTAINER_CPU.reset();
+    CONTAINER_MEM.reset();
+    CONTAINER_NET_IN.reset();
+    CONTAINER_NET_OUT.reset();
+
+    for (labels, stats) in samples {
+        let stats = match stats {
+            Some(Ok(stats)) => stats,
+            Some(Err(e)) => {
+                warn!("Failed to read stats of {}: {}", labels.task, e);
+                continue;
+            }
+            None => continue,
+        };
+
+        let (net_in, net_out) = network_bytes(&stats);
+        CONTAINER_CPU
+            .with_label_values(&labels.values())
+            .set(cpu_percent(&stats));
+        CONTAINER_MEM
+            .with_label_values(&labels.values())
+            .set(memory_bytes(&stats) as f64);
+        CONTAINER_NET_IN
+            .with_label_values(&labels.values())
+            .set(net_in as f64);
+        CONTAINER_NET_OUT
+            .with_label_values(&labels.values())
+            .set(net_out as f64);
+    }
+
+    Ok(())
+}
+
+/// Reads the free and total space of the filesystem holding a path, in bytes.
+fn filesystem_space(path: &str) -> Result<(u64, u64), String> {
+    let c_path =
+        std::ffi::CString::new(path).map_err(|e| format!("Invalid path {}: {}", path, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `c_path` is a valid C string and `stat` is a valid output buffer
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(format!(
+            "Failed to read the filesystem of {}: {}",
+            path,
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    let block_size = stat.f_frsize as u64;
+    Ok((
+        stat.f_bavail as u64 * block_size,
+        stat.f_blocks as u64 * block_size,
+    ))
+}
+
+/// Updates the disk usage metrics of the node.
 ///
-/// * `net_io` - A string slice representing the network I/O, e.g., "42kB / 252B".
+/// Reads the Docker disk usage (the equivalent of `docker system df -v`) to update the size
+/// of the release images of each app, the build cache and the volumes, then the free space
+/// of the filesystem holding the Docker data.
 ///
 /// # Returns
-///
-/// A tuple `(f64, f64)` representing `(net_in_kb, net_out_kb)`.
+/// * `Ok(())` if the update is successful.
+/// * `Err(String)` if the Docker disk usage could not be read.
+pub async fn update_disk_metrics() -> Result<(), String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+    let usage = docker
+        .df()
+        .await
+        .map_err(|e| format!("Failed to read Docker disk usage: {}", e))?;
+
+    // Release images are tagged `<registry>/<app>:<release>`
+    let registry_prefix = format!("{}/", config().registry.host);
+    let mut app_sizes: HashMap<String, f64> = HashMap::new();
+    for image in usage.images.unwrap_or_default() {
+        let app = image.repo_tags.iter().find_map(|tag| {
+            let (repository, _) = tag.strip_prefix(&registry_prefix)?.rsplit_once(':')?;
+            Some(repository.to_string())
+        });
+        if let Some(app) = app {
+            *app_sizes.entry(app).or_default() += image.size.max(0) as f64;
+        }
+    }
+    APP_IMAGE_SIZE.reset();
+    for (app, size) in &app_sizes {
+        APP_IMAGE_SIZE.with_label_values(&[app]).set(*size);
+    }
 
-fn parse_network_io(net_io: &str) -> (f64, f64) {
-    // Format is typically like "42kB / 252B"
-    let parts: Vec<&str> = net_io.split('/').collect();
-    if parts.len() != 2 {
-        return (0.0, 0.0);
+    let build_cache: i64 = usage
+        .build_cache
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|cache| cache.size)
+        .sum();
+    BUILD_CACHE_SIZE.set(build_cache.max(0) as f64);
+
+    VOLUME_USAGE.reset();
+    for volume in usage.volumes.unwrap_or_default() {
+        // A size of -1 means Docker could not compute it
+        if let Some(data) = volume.usage_data.filter(|data| data.size >= 0) {
+            VOLUME_USAGE
+                .with_label_values(&[&volume.name])
+                .set(data.size as f64);
+        }
     }
 
-    let in_str = parts[0].trim();
-    let out_str = parts[1].trim();
+    let docker_root = docker
+        .info()
+        .await
+        .ok()
+        .and_then(|info| info.docker_root_dir)
+        .filter(|path| Path::new(path).exists())
+        .unwrap_or_else(|| "/".to_string());
+    match filesystem_space(&docker_root) {
+        Ok((free, total)) => {
+            HOST_DISK_FREE.set(free as f64);
+            HOST_DISK_TOTAL.set(total as f64);
+        }
+        Err(e) => warn!("{}", e),
+    }
 
-    let net_in = parse_data_size(in_str);
-    let net_out = parse_data_size(out_str);
+    Ok(())
+}
 
-    // Convert to KB for consistent metrics
-    (net_in, net_out)
+/// Updates the replica metrics of every app.
+///
+/// The desired and running task counts are read from the Swarm services of the Nephelios
+/// stack, whichever node the tasks run on.
+///
+/// # Returns
+/// * `Ok(())` if the update is successful.
+/// * `Err(String)` if the services could not be listed.
+pub async fn update_replica_metrics() -> Result<(), String> {
+    let docker = Docker::connect_with_local_defaults()
+        .map_err(|e| format!("Failed to connect to Docker: {}", e))?;
+    let options = ListServicesOptions {
+        filters: HashMap::from([("label", vec!["com.docker.stack.namespace=nephelios"])]),
+        status: true,
+    };
+    let services = docker
+        .list_services(Some(options))
+        .await
+        .map_err(|e| format!("Failed to list services: {}", e))?;
+
+    APP_REPLICAS_DESIRED.reset();
+    APP_REPLICAS_RUNNING.reset();
+    APP_UP.reset();
+    for service in services {
+        let Some(app_name) = service
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.labels.as_ref())
+            .and_then(|labels| labels.get("com.myapp.name"))
+        else {
+            continue;
+        };
+
+        let status = service.service_status.clone().unwrap_or_default();
+        let running = status.running_tasks.unwrap_or_default();
+        APP_REPLICAS_DESIRED
+            .with_label_values(&[app_name])
+            .set(status.desired_tasks.unwrap_or_default() as f64);
+        APP_REPLICAS_RUNNING
+            .with_label_values(&[app_name])
+            .set(running as f64);
+        APP_UP
+            .with_label_values(&[app_name])
+            .set(if running > 0 { 1.0 } else { 0.0 });
+    }
+
+    Ok(())
 }
 
-/// Parses a human-readable data size string into kilobytes.
+/// Runs a docker command and returns its output.
 ///
-/// This function supports units such as B, KB, MB, GB, and TB,
-/// and converts them to kilobytes for consistent internal usage.
+/// Used for the Swarm nodes and tasks, which bollard does not expose.
 ///
 /// # Arguments
+/// * `args` - The arguments of the docker command.
+async fn docker_output(args: &[&str]) -> Result<Vec<u8>, String> {
+    let command = args[..2].join(" ");
+    let output = tokio::process::Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute docker {}: {}", command, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "docker {} failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Runs a docker command printing one ID per line.
+async fn docker_ids(args: &[&str]) -> Result<Vec<String>, String> {
+    let output = docker_output(args).await?;
+    let mut ids: Vec<String> = String::from_utf8_lossy(&output)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// Runs a docker command printing JSON and parses its output.
+async fn docker_json<T: DeserializeOwned>(args: &[&str]) -> Result<T, String> {
+    let output = docker_output(args).await?;
+    serde_json::from_slice(&output).map_err(|e| {
+        format!(
+            "Failed to parse docker {} output: {}",
+            args[..2].join(" "),
+            e
+        )
+    })
+}
+
+/// A node of the Swarm, with its capacity and the resources reserved by its tasks.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeInfo {
+    pub id: String,
+    pub hostname: String,
+    /// `manager` or `worker`.
+    pub role: String,
+    /// `active`, `pause` or `drain`.
+    pub availability: String,
+    /// `ready`, `down`, `disconnected` or `unknown`.
+    pub state: String,
+    pub address: Option<String>,
+    /// Whether the node is the leader of the managers.
+    pub leader: bool,
+    /// The address other nodes join the Swarm through, for managers.
+    pub manager_address: Option<String>,
+    pub engine_version: Option<String>,
+    pub labels: HashMap<String, String>,
+    pub cpus: f64,
+    pub memory_bytes: i64,
+    pub cpus_reserved: f64,
+    pub memory_reserved_bytes: i64,
+    /// The number of running tasks on the node.
+    pub tasks: usize,
+}
+
+impl NodeInfo {
+    /// Summarizes a node and the running tasks of the Swarm.
+    fn new(node: &Node, tasks: &[Task]) -> Self {
+        let id = node.id.clone().unwrap_or_default();
+        let description = node.description.clone().unwrap_or_default();
+        let spec = node.spec.clone().unwrap_or_default();
+        let status = node.status.clone().unwrap_or_default();
+        let manager_status = node.manager_status.clone().unwrap_or_default();
+        let resources = description.resources.unwrap_or_default();
+
+        let node_tasks: Vec<&Task> = tasks
+            .iter()
+            .filter(|task| task.node_id.as_deref() == Some(id.as_str()))
+            .collect();
+        let (nano_cpus, memory_bytes) = node_tasks
+            .iter()
+            .filter_map(|task| {
+                task.spec
+                    .as_ref()?
+                    .resources
+                    .as_ref()?
+                    .reservations
+                    .as_ref()
+            })
+            .fold((0, 0), |(cpus, memory), reservations| {
+                (
+                    cpus + reservations.nano_cpus.unwrap_or(0),
+                    memory + reservations.memory_bytes.unwrap_or(0),
+                )
+            });
+
+        NodeInfo {
+            hostname: description.hostname.unwrap_or_else(|| id.clone()),
+            role: spec.role.map(|role| role.to_string()).unwrap_or_default(),
+            availability: spec
+                .availability
+                .map(|availability| availability.to_string())
+                .unwrap_or_default(),
+            state: status.state.unwrap_or(NodeState::UNKNOWN).to_string(),
+            address: status.addr,
+            leader: manager_status.leader.unwrap_or(false),
+            manager_address: manager_status.addr,
+            engine_version: description.engine.and_then(|engine| engine.engine_version),
+            labels: spec.labels.unwrap_or_default(),
+            cpus: resources.nano_cpus.unwrap_or(0) as f64 / 1e9,
+            memory_bytes: resources.memory_bytes.unwrap_or(0),
+            cpus_reserved: nano_cpus as f64 / 1e9,
+            memory_reserved_bytes: memory_bytes,
+            tasks: node_tasks.len(),
+            id,
+        }
+    }
+}
+
+/// Reads nodes of the Swarm, with the running tasks of each.
+async fn inspect_swarm_nodes(node_ids: &[String]) -> Result<Vec<NodeInfo>, String> {
+    let mut args = vec!["node", "inspect"];
+    args.extend(node_ids.iter().map(String::as_str));
+    let nodes: Vec<Node> = docker_json(&args).await?;
+
+    let mut args = vec!["node", "ps", "--quiet", "--filter", "desired-state=running"];
+    args.extend(node_ids.iter().map(String::as_str));
+    let task_ids = docker_ids(&args).await?;
+    let tasks: Vec<Task> = if task_ids.is_empty() {
+        Vec::new()
+    } else {
+        let mut args = vec!["inspect", "--type", "task"];
+        args.extend(task_ids.iter().map(String::as_str));
+        docker_json(&args).await?
+    };
+
+    Ok(nodes
+        .iter()
+        .map(|node| NodeInfo::new(node, &tasks))
+        .collect())
+}
+
+/// Lists the nodes of the Swarm.
 ///
-/// * `size_str` - A string slice like "42kB", "1.2MB", etc.
+/// Reads every node with its state and capacity, and the running tasks of every node with
+/// their resource reservations, so the whole cluster is covered rather than the containers
+/// of the local node only.
 ///
 /// # Returns
+/// * `Ok(Vec<NodeInfo>)` - The nodes, managers first.
+/// * `Err(String)` - If the nodes or tasks could not be read.
+pub async fn list_swarm_nodes() -> Result<Vec<NodeInfo>, String> {
+    let node_ids = docker_ids(&["node", "ls", "--quiet"]).await?;
+    if node_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut nodes = inspect_swarm_nodes(&node_ids).await?;
+    nodes.sort_by(|a, b| {
+        (a.role != "manager", &a.hostname).cmp(&(b.role != "manager", &b.hostname))
+    });
+    Ok(nodes)
+}
+
+/// Finds a node of the Swarm by ID or hostname.
 ///
-/// The size converted to kilobytes (`f64`).
-///
-fn parse_data_size(size_str: &str) -> f64 {
-    let re = regex::Regex::new(r"([0-9.]+)\s*([a-zA-Z]+)").unwrap();
-    if let Some(caps) = re.captures(size_str) {
-        let value: f64 = caps.get(1).unwrap().as_str().parse().unwrap_or(0.0);
-        let unit = caps.get(2).unwrap().as_str().to_lowercase();
-
-        // Convert to KB
-        match unit.as_str() {
-            "b" => value / 1024.0,
-            "kb" => value,
-            "mb" => value * 1024.0,
-            "gb" => value * 1024.0 * 1024.0,
-            "tb" => value * 1024.0 * 1024.0 * 1024.0,
-            _ => value,
-        }
-    } else {
-        0.0
+/// # Arguments
+/// * `node` - The ID or hostname of the node.
+///
+/// # Returns
+/// * `Ok(Some(NodeInfo))` - The node, with its running tasks.
+/// * `Ok(None)` - If no node has this ID or hostname.
+/// * `Err(String)` - If the nodes could not be read.
+pub async fn find_swarm_node(node: &str) -> Result<Option<NodeInfo>, String> {
+    let node_ids = docker_ids(&["node", "ls", "--quiet"]).await?;
+    if node_ids.is_empty() {
+        return Ok(None);
     }
+
+    Ok(inspect_swarm_nodes(&node_ids)
+        .await?
+        .into_iter()
+        .find(|info| info.id == node || info.hostname == node))
 }
 
-/// Updates Prometheus metrics by parsing `docker stats` for `nephelios` containers.
+/// Sets the availability of a node of the Swarm.
 ///
-/// This function gathers statistics for CPU, memory, and network I/O of containers
-/// whose names start with `nephelios` and updates the corresponding Prometheus metrics.
+/// Draining a node makes Swarm stop its tasks and schedule them on the other nodes.
+///
+/// # Arguments
+/// * `node_id` - The ID of the node.
+/// * `availability` - `active`, `pause` or `drain`.
 ///
 /// # Returns
-/// * `Ok(())` if the update is successful.
-/// * `Err(String)` if the command or parsing fails.
+/// * `Ok(())` if the node was updated.
+/// * `Err(String)` if the node could not be updated.
+pub async fn set_node_availability(node_id: &str, availability: &str) -> Result<(), String> {
+    docker_output(&["node", "update", "--availability", availability, node_id]).await?;
+    Ok(())
+}
 
-pub async fn update_metrics() -> Result<(), Box<dyn std::error::Error>> {
-    let output = std::process::Command::new("docker")
-        .arg("stats")
-        .arg("--no-stream")
-        .arg("--format")
-        .arg("{{json .}}")
-        .output()?;
+/// Reads every task of the service of an application, including the stopped ones.
+async fn service_tasks(app_name: &str) -> Result<Vec<Task>, String> {
+    let service_name = format!("nephelios_{}", app_name);
+    let task_ids = docker_ids(&["service", "ps", "--quiet", "--no-trunc", &service_name]).await?;
+    if task_ids.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    let stdout = String::from_utf8(output.stdout)?;
-    let lines = stdout.lines();
+    let mut args = vec!["inspect", "--type", "task"];
+    args.extend(task_ids.iter().map(String::as_str));
+    docker_json(&args).await
+}
 
-    CONTAINER_CPU.reset();
-    CONTAINER_MEM.reset();
-    CONTAINER_NET_IN.reset();
-    CONTAINER_NET_OUT.reset();
+/// Whether a task runs an image, pinned to its digest by Swarm or not.
+fn task_runs_image(task: &Task, image: &str) -> bool {
+    let task_image = task
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.container_spec.as_ref())
+        .and_then(|container| container.image.as_deref())
+        .unwrap_or_default();
+    task_image == image
+        || task_image
+            .strip_prefix(image)
+            .is_some_and(|digest| digest.starts_with('@'))
+}
 
-    for line in lines {
-        let data: serde_json::Value = serde_json::from_str(line)?;
-        let name = data["Name"].as_str().unwrap_or("unknown");
+/// Waits until the replicas of an application run a new image.
+///
+/// Tasks are only considered running once their health check passes, if the image has one.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `image` - The image of the new release.
+/// * `replicas` - The number of replicas expected to run.
+/// * `timeout` - How long the tasks have to start.
+///
+/// # Returns
+/// * `Ok(())` once `replicas` tasks of the image are running.
+/// * `Err(String)` describing why they are not, after `MAX_ROLLOUT_FAILURES` failed tasks or
+///   the timeout.
+pub async fn wait_for_rollout(
+    app_name: &str,
+    image: &str,
+    replicas: u32,
+    timeout: Duration,
+) -> Result<(), String> {
+    let started = Instant::now();
+    loop {
+        match service_tasks(app_name).await {
+            Ok(tasks) => {
+                let new_tasks: Vec<&Task> = tasks
+                    .iter()
+                    .filter(|task| task_runs_image(task, image))
+                    .collect();
+                let state = |task: &Task| task.status.as_ref().and_then(|status| status.state);
+                let failed: Vec<&Task> = new_tasks
+                    .iter()
+                    .copied()
+                    .filter(|task| {
+                        matches!(state(task), Some(TaskState::FAILED | TaskState::REJECTED))
+                    })
+                    .collect();
+                if failed.len() >= MAX_ROLLOUT_FAILURES {
+                    let error = failed
+                        .iter()
+                        .find_map(|task| task.status.as_ref()?.err.clone())
+                        .unwrap_or_else(|| "no error reported".to_string());
+                    return Err(format!("{} tasks failed: {}", failed.len(), error));
+                }
 
-        if !name.starts_with("nephelios") {
-            continue;
+                let running = new_tasks
+                    .iter()
+                    .filter(|task| {
+                        task.desired_state == Some(TaskState::RUNNING)
+                            && state(task) == Some(TaskState::RUNNING)
+                    })
+                    .count();
+                if running >= replicas as usize {
+                    return Ok(());
+                }
+            }
+            Err(e) => warn!("Failed to read the tasks of {}: {}", app_name, e),
+        }
+
+        if started.elapsed() >= timeout {
+            return Err(format!(
+                "the tasks were not running after {} seconds",
+                timeout.as_secs()
+            ));
+        }
+        tokio::time::sleep(ROLLOUT_POLL_INTERVAL).await;
+    }
+}
+
+/// Reads the running and desired replicas of a service of the stack.
+///
+/// # Arguments
+/// * `name` - The name of the service in the stack file (e.g., "my-app-postgres").
+///
+/// # Returns
+/// * `Ok(Some((running, desired)))` if the service exists.
+/// * `Ok(None)` if the service is not deployed.
+/// * `Err(String)` if the services could not be listed.
+pub async fn service_replicas(name: &str) -> Result<Option<(u32, u32)>, String> {
+    let service_name = format!("nephelios_{}", name);
+    let output = docker_output(&[
+        "service",
+        "ls",
+        "--filter",
+        &format!("name={}", service_name),
+        "--format",
+        "{{.Name}} {{.Replicas}}",
+    ])
+    .await?;
+
+    // The name filter matches prefixes, and replicas may be followed by e.g. "(max 1 per node)"
+    Ok(String::from_utf8_lossy(&output).lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        if fields.next() != Some(service_name.as_str()) {
+            return None;
         }
+        let (running, desired) = fields.next()?.split_once('/')?;
+        Some((running.parse().ok()?, desired.parse().ok()?))
+    }))
+}
 
-        let cpu = parse_percentage(data["CPUPerc"].as_str().unwrap_or("0%"));
-        let mem = parse_memory(data["MemUsage"].as_str().unwrap_or("0MiB / 0MiB"));
-        let (net_in, net_out) = parse_network_io(data["NetIO"].as_str().unwrap_or("0kB / 0B"));
+/// Returns the IDs of the nodes running a task of a service of the stack.
+///
+/// # Arguments
+/// * `name` - The name of the service in the stack file (e.g., "my-app").
+pub async fn service_task_nodes(name: &str) -> Result<Vec<String>, String> {
+    let mut nodes: Vec<String> = service_tasks(name)
+        .await?
+        .into_iter()
+        .filter(|task| task.desired_state == Some(TaskState::RUNNING))
+        .filter_map(|task| task.node_id)
+        .collect();
+    nodes.sort();
+    nodes.dedup();
+    Ok(nodes)
+}
 
-        CONTAINER_CPU.with_label_values(&[name]).set(cpu);
-        CONTAINER_MEM.with_label_values(&[name]).set(mem);
-        CONTAINER_NET_IN.with_label_values(&[name]).set(net_in);
-        CONTAINER_NET_OUT.with_label_values(&[name]).set(net_out);
+/// Returns the ID of the Swarm node Nephelios runs on.
+pub async fn local_node_id() -> Result<String, String> {
+    let output = docker_output(&["info", "--format", "{{.Swarm.NodeID}}"]).await?;
+    let id = String::from_utf8_lossy(&output).trim().to_string();
+    if id.is_empty() {
+        return Err("This node is not part of a Swarm".to_string());
     }
+    Ok(id)
+}
 
+/// Starts a one-shot Swarm job running a shell command in the image of an app.
+///
+/// The job is a `replicated-job` service attached to the overlay network, so the command
+/// reaches the addons of the app, and its task is not restarted when the command fails.
+/// The service is not part of the stack and must be removed with `remove_service` once done.
+///
+/// # Arguments
+/// * `name` - The name of the job service, without the `nephelios_` prefix.
+/// * `app_name` - The app the job runs for, recorded in a label.
+/// * `image` - The image to run.
+/// * `environment` - The environment variables of the command.
+/// * `command` - The command, run with `sh -c`.
+///
+/// # Returns
+/// * `Ok(())` if the job service was created.
+/// * `Err(String)` if the service could not be created.
+pub async fn create_job_service(
+    name: &str,
+    app_name: &str,
+    image: &str,
+    environment: &BTreeMap<String, String>,
+    command: &str,
+) -> Result<(), String> {
+    let service_name = format!("nephelios_{}", name);
+    let label = format!("{}={}", JOB_APP_LABEL, app_name);
+    let variables: Vec<String> = environment
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+
+    let mut args = vec![
+        "service",
+        "create",
+        "--detach",
+        "--quiet",
+        "--name",
+        &service_name,
+        "--mode",
+        "replicated-job",
+        "--restart-condition",
+        "none",
+        "--network",
+        "nephelios_overlay",
+        "--label",
+        &label,
+        "--entrypoint",
+        "sh -c",
+    ];
+    for variable in &variables {
+        args.extend(["--env", variable.as_str()]);
+    }
+    args.extend([image, command]);
+    docker_output(&args).await?;
     Ok(())
 }
 
-/// Parses a percentage string like "42.5%" into a floating-point value.
+/// Waits until the task of a one-shot job exits.
 ///
 /// # Arguments
-///
-/// * `val` - A string slice representing the percentage (e.g., "42.5%").
+/// * `name` - The name of the job service, without the `nephelios_` prefix.
+/// * `timeout` - How long the command may run.
 ///
 /// # Returns
+/// * `Ok(Some(i64))` with the exit code of the command.
+/// * `Ok(None)` if the command was still running after the timeout.
+/// * `Err(String)` if the task could not be started or its state read.
+pub async fn wait_for_job(name: &str, timeout: Duration) -> Result<Option<i64>, String> {
+    let started = Instant::now();
+    loop {
+        let tasks = service_tasks(name).await?;
+        if let Some(status) = tasks.first().and_then(|task| task.status.as_ref()) {
+            let exit_code = status
+                .container_status
+                .as_ref()
+                .and_then(|container| container.exit_code);
+            match status.state {
+                Some(TaskState::COMPLETE) => return Ok(Some(exit_code.unwrap_or(0))),
+                Some(TaskState::FAILED) if exit_code.is_some() => return Ok(exit_code),
+                Some(
+                    TaskState::FAILED
+                    | TaskState::REJECTED
+                    | TaskState::ORPHANED
+                    | TaskState::SHUTDOWN,
+                ) => {
+                    return Err(status
+                        .err
+                        .clone()
+                        .unwrap_or_else(|| "the task did not start".to_string()))
+                }
+                _ => {}
+            }
+        }
+
+        if started.elapsed() >= timeout {
+            return Ok(None);
+        }
+        tokio::time::sleep(JOB_POLL_INTERVAL).await;
+    }
+}
+
+/// Runs a one-off container mounting a volume at `/volume`, its stdin or stdout connected to
+/// a file.
+async fn run_volume_helper(
+    volume: &str,
+    read_only: bool,
+    script: &str,
+    stdin: Stdio,
+    stdout: Stdio,
+) -> Result<(), String> {
+    let mount = if read_only {
+        format!("{}:/volume:ro", volume)
+    } else {
+        format!("{}:/volume", volume)
+    };
+    let output = tokio::process::Command::new("docker")
+        .args(["run", "--rm", "-i", "-v", &mount, VOLUME_HELPER_IMAGE])
+        .args(["sh", "-c", script])
+        .stdin(stdin)
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute docker run: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "docker run failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Writes the content of a Docker volume of this node to a gzipped tar archive.
+///
+/// # Arguments
+/// * `volume` - The name of the Docker volume (e.g., "nephelios_my-app-uploads").
+/// * `destination` - The archive to create.
 ///
-/// A `f64` value of the percentage, or 0.0 if parsing fails.
+/// # Returns
+/// * `Ok(())` if the archive was written.
+/// * `Err(String)` if the volume could not be read or the archive written.
+pub async fn export_volume(volume: &str, destination: &Path) -> Result<(), String> {
+    let file = File::create(destination)
+        .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+    run_volume_helper(
+        volume,
+        true,
+        "tar -czf - -C /volume .",
+        Stdio::null(),
+        Stdio::from(file),
+    )
+    .await
+}
 
-fn parse_percentage(val: &str) -> f64 {
-    val.trim_end_matches('%').parse::<f64>().unwrap_or(0.0)
+/// Replaces the content of a Docker volume of this node with a gzipped tar archive.
+///
+/// # Arguments
+/// * `volume` - The name of the Docker volume (e.g., "nephelios_my-app-uploads").
+/// * `source` - The archive to extract, written by `export_volume`.
+///
+/// # Returns
+/// * `Ok(())` if the volume was restored.
+/// * `Err(String)` if the archive could not be read or extracted.
+pub async fn import_volume(volume: &str, source: &Path) -> Result<(), String> {
+    let file =
+        File::open(source).map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+    run_volume_helper(
+        volume,
+        false,
+        "find /volume -mindepth 1 -delete && tar -xzf - -C /volume",
+        Stdio::from(file),
+        Stdio::null(),
+    )
+    .await
 }
 
-/// Parses memory usage from a Docker-formatted string.
+/// Adds, updates and removes labels of a node of the Swarm.
 ///
-/// It extracts the first part of the memory usage string (e.g., "512MiB / 2GiB")
-/// and converts it to a floating-point value, currently only stripping the unit.
+/// Labels are matched by placement constraints and preferences (e.g.,
+/// `node.labels.tier==gpu`).
 ///
 /// # Arguments
+/// * `node_id` - The ID of the node.
+/// * `set` - The labels to add or overwrite.
+/// * `remove` - The names of the labels to remove.
 ///
-/// * `val` - A string slice in the format "XMiB / YMiB".
+/// # Returns
+/// * `Ok(())` if the node was updated.
+/// * `Err(String)` if the node could not be updated.
+pub async fn update_node_labels(
+    node_id: &str,
+    set: &BTreeMap<String, String>,
+    remove: &[String],
+) -> Result<(), String> {
+    let set: Vec<String> = set
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    let mut args = vec!["node", "update"];
+    for label in &set {
+        args.extend(["--label-add", label.as_str()]);
+    }
+    for name in remove {
+        args.extend(["--label-rm", name.as_str()]);
+    }
+    args.push(node_id);
+    docker_output(&args).await?;
+    Ok(())
+}
+
+/// Returns the token and address nodes join the Swarm with.
+///
+/// # Arguments
+/// * `role` - The role of the joining nodes: `worker` or `manager`.
 ///
 /// # Returns
+/// * `Ok((String, Option<String>))` - The join token, and the address of the leader.
+/// * `Err(String)` - If the token or the nodes could not be read.
+pub async fn swarm_join_token(role: &str) -> Result<(String, Option<String>), String> {
+    let output = docker_output(&["swarm", "join-token", "--quiet", role]).await?;
+    let token = String::from_utf8_lossy(&output).trim().to_string();
+    let leader_address = list_swarm_nodes()
+        .await?
+        .into_iter()
+        .find(|node| node.leader)
+        .and_then(|node| node.manager_address);
+    Ok((token, leader_address))
+}
+
+/// Updates the Swarm node metrics.
 ///
-/// A `f64` value representing the memory usage in MiB.
+/// # Returns
+/// * `Ok(())` if the update is successful.
+/// * `Err(String)` if the nodes or tasks could not be read.
+pub async fn update_swarm_metrics() -> Result<(), String> {
+    let nodes = list_swarm_nodes().await?;
+
+    SWARM_NODES.set(nodes.len() as i64);
+    SWARM_NODE_READY.reset();
+    SWARM_NODE_CPUS.reset();
+    SWARM_NODE_MEMORY.reset();
+    SWARM_NODE_CPUS_RESERVED.reset();
+    SWARM_NODE_MEMORY_RESERVED.reset();
+    SWARM_NODE_TASKS.reset();
+
+    for node in &nodes {
+        let hostname = node.hostname.as_str();
+        SWARM_NODE_READY
+            .with_label_values(&[hostname, &node.role, &node.availability, &node.state])
+            .set(if node.state == "ready" { 1.0 } else { 0.0 });
+        SWARM_NODE_CPUS
+            .with_label_values(&[hostname])
+            .set(node.cpus);
+        SWARM_NODE_MEMORY
+            .with_label_values(&[hostname])
+            .set(node.memory_bytes as f64);
+        // Every node is reported, even without tasks
+        SWARM_NODE_TASKS
+            .with_label_values(&[hostname])
+            .set(node.tasks as f64);
+        SWARM_NODE_CPUS_RESERVED
+            .with_label_values(&[hostname])
+            .set(node.cpus_reserved);
+        SWARM_NODE_MEMORY_RESERVED
+            .with_label_values(&[hostname])
+            .set(node.memory_reserved_bytes as f64);
+    }
 
-fn parse_memory(val: &str) -> f64 {
-    val.split('/')
-        .next()
-        .unwrap_or("0")
-        .trim()
-        .replace("MiB", "")
-        .replace("GiB", "")
-        .parse::<f64>()
-        .unwrap_or(0.0)
+    Ok(())
 }
@@ -1,9 +1,19 @@
+use crate::config::config;
+use crate::services::helpers::credentials_helper::GitCredentials;
 use dirs::home_dir;
-use std::process::Command;
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{
+    Cred, CredentialType, FetchOptions, RemoteCallbacks, Repository, SubmoduleUpdateOptions,
+};
+use openssl::hash::MessageDigest;
+use openssl::memcmp;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
 use std::{
     fs,
     path::{Path, PathBuf},
 };
+use tracing::warn;
 
 /// Removes the temporary directory and its contents from the user's home folder.
 ///
@@ -41,48 +51,531 @@ pub fn create_temp_dir(app_name: &str) -> Result<PathBuf, String> {
     Ok(temp_dir)
 }
 
-/// Modifies the GitHub URL to include the specified username.
+/// Converts an HTTPS repository URL to its SSH form (`git@host:owner/repo.git`).
 ///
 /// # Arguments
 ///
-/// * `github_url` - The original GitHub URL to be modified.
+/// * `github_url` - The repository URL.
 ///
 /// # Returns
-/// * A modified GitHub URL with the username prefixed.
-pub fn modify_github_url(github_url: &str) -> String {
-    let prefix = "https://damien-mathieu1@github.com/";
-    // Remove the existing "https://github.com/" prefix if present
-    if let Some(pos) = github_url.find("https://github.com/") {
-        let modified_url = format!(
-            "{}{}",
-            prefix,
-            &github_url[pos + "https://github.com/".len()..]
-        );
-        return modified_url;
+/// * The SSH URL, or the original URL if it is not HTTPS.
+fn ssh_url(github_url: &str) -> String {
+    match github_url.strip_prefix("https://") {
+        Some(rest) => {
+            let rest = rest.split_once('@').map_or(rest, |(_, host)| host);
+            match rest.split_once('/') {
+                Some((host, path)) => {
+                    let path = path.trim_end_matches('/');
+                    let path = path.strip_suffix(".git").unwrap_or(path);
+                    format!("git@{}:{}.git", host, path)
+                }
+                None => github_url.to_string(),
+            }
+        }
+        None => github_url.to_string(),
     }
-    github_url.to_string()
+}
+
+/// Options controlling how a repository is cloned.
+#[derive(Debug, Clone, Default)]
+pub struct CloneOptions {
+    /// The branch, tag or commit SHA to check out (default branch if `None`).
+    pub git_ref: Option<String>,
+    /// History depth to fetch, `0` for a full clone.
+    pub depth: u32,
+    /// Whether submodules should be cloned as well.
+    pub recurse_submodules: bool,
+}
+
+/// Returns the default clone depth, from `build.clone_depth` (default: `1`, `0` for full clones).
+pub fn default_clone_depth() -> u32 {
+    config().build.clone_depth
+}
+
+/// Checks whether a git ref looks like a commit SHA rather than a branch or tag name.
+fn is_commit_sha(git_ref: &str) -> bool {
+    (7..=40).contains(&git_ref.len()) && git_ref.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Callback receiving clone progress as `(received_objects, total_objects)`.
+pub type CloneProgress<'a> = &'a mut dyn FnMut(usize, usize);
+
+/// Builds the remote callbacks handling authentication and transfer progress.
+///
+/// # Arguments
+///
+/// * `credentials` - The credentials used to access private repositories.
+/// * `progress` - Callback receiving the transfer progress.
+///
+/// # Returns
+/// * The remote callbacks.
+fn remote_callbacks<'a>(
+    credentials: &'a GitCredentials,
+    progress: CloneProgress<'a>,
+) -> RemoteCallbacks<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+    // libgit2 asks again as long as the credentials are rejected, so they are only given once
+    let mut attempted = false;
+    callbacks.credentials(move |_url, username_from_url, allowed_types| {
+        if allowed_types == CredentialType::USERNAME {
+            return Cred::username(username_from_url.unwrap_or("git"));
+        }
+        if attempted {
+            return Err(git2::Error::from_str("authentication failed"));
+        }
+        attempted = true;
+        match credentials {
+            GitCredentials::Anonymous => Cred::default(),
+            GitCredentials::Token { username, token } => Cred::userpass_plaintext(username, token),
+            GitCredentials::SshKey(key_path) => {
+                Cred::ssh_key(username_from_url.unwrap_or("git"), None, key_path, None)
+            }
+        }
+    });
+    callbacks.transfer_progress(move |stats| {
+        progress(stats.received_objects(), stats.total_objects());
+        true
+    });
+    callbacks
+}
+
+/// Builds the fetch options used for every network operation of a clone.
+///
+/// # Arguments
+///
+/// * `credentials` - The credentials used to access private repositories.
+/// * `depth` - History depth to fetch, `0` for the full history.
+/// * `progress` - Callback receiving the transfer progress.
+///
+/// # Returns
+/// * The fetch options, with credential and progress callbacks set.
+fn fetch_options<'a>(
+    credentials: &'a GitCredentials,
+    depth: u32,
+    progress: CloneProgress<'a>,
+) -> FetchOptions<'a> {
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(remote_callbacks(credentials, progress));
+    if depth > 0 {
+        options.depth(depth as i32);
+    }
+    options
+}
+
+/// Formats a git error for display.
+fn git_error(context: &str, e: git2::Error) -> String {
+    format!("{}: {}", context, e.message())
 }
 
 /// Clones a GitHub repository into a specified directory.
 ///
+/// Clones are shallow unless `options.depth` is `0`. When a ref is requested, only that
+/// ref is fetched (a branch, a tag, or a full commit SHA); abbreviated SHAs require the
+/// full history and disable the depth limit.
+///
 /// # Arguments
 ///
 /// * `github_url` - The URL of the GitHub repository to clone.
 /// * `target_dir` - The directory where the repository will be cloned.
+/// * `options` - The ref, depth and submodule options.
+/// * `credentials` - The credentials used to access private repositories.
+/// * `progress` - Callback receiving the transfer progress.
 ///
 /// # Returns
 /// * `Ok(())` if the repository was successfully cloned.
 /// * `Err(String)` if there was an error during the cloning process.
-pub fn clone_repo(github_url: &str, target_dir: &str) -> Result<(), String> {
-    let github_url = modify_github_url(github_url);
+pub fn clone_repo(
+    github_url: &str,
+    target_dir: &str,
+    options: &CloneOptions,
+    credentials: &GitCredentials,
+    progress: CloneProgress,
+) -> Result<(), String> {
+    let clone_url = match credentials {
+        GitCredentials::SshKey(_) => ssh_url(github_url),
+        _ => github_url.to_string(),
+    };
+
+    let repo = match options.git_ref.as_deref() {
+        None => RepoBuilder::new()
+            .fetch_options(fetch_options(credentials, options.depth, progress))
+            .clone(&clone_url, Path::new(target_dir))
+            .map_err(|e| e.message().to_string())?,
+        Some(git_ref) => {
+            let repo = Repository::init(target_dir)
+                .map_err(|e| git_error("Failed to initialize repository", e))?;
+            fetch_and_checkout(
+                &repo,
+                &clone_url,
+                git_ref,
+                options.depth,
+                credentials,
+                progress,
+            )?;
+            repo
+        }
+    };
+
+    if options.recurse_submodules {
+        update_submodules(&repo, options.depth, credentials)?;
+    }
+
+    Ok(())
+}
+
+/// Fetches a single ref from the remote and checks it out as a detached HEAD.
+///
+/// # Arguments
+///
+/// * `repo` - The freshly initialized repository.
+/// * `clone_url` - The remote URL.
+/// * `git_ref` - The branch, tag or commit SHA to check out.
+/// * `depth` - History depth to fetch, `0` for the full history.
+/// * `credentials` - The credentials used to access private repositories.
+/// * `progress` - Callback receiving the transfer progress.
+///
+/// # Returns
+/// * `Ok(())` if the ref was checked out.
+/// * `Err(String)` if the ref could not be fetched or found.
+fn fetch_and_checkout(
+    repo: &Repository,
+    clone_url: &str,
+    git_ref: &str,
+    depth: u32,
+    credentials: &GitCredentials,
+    progress: CloneProgress,
+) -> Result<(), String> {
+    let mut remote = repo
+        .remote("origin", clone_url)
+        .map_err(|e| git_error("Failed to add remote", e))?;
+
+    let is_sha = is_commit_sha(git_ref);
+    let (refspecs, depth) = if is_sha && git_ref.len() == 40 {
+        (vec![git_ref.to_string()], depth)
+    } else if is_sha || depth == 0 {
+        (
+            vec![
+                "+refs/heads/*:refs/remotes/origin/*".to_string(),
+                "+refs/tags/*:refs/tags/*".to_string(),
+            ],
+            0,
+        )
+    } else {
+        (
+            vec![
+                format!("+refs/heads/{0}:refs/remotes/origin/{0}", git_ref),
+                format!("+refs/tags/{0}:refs/tags/{0}", git_ref),
+            ],
+            depth,
+        )
+    };
+
+    remote
+        .fetch(
+            &refspecs,
+            Some(&mut fetch_options(credentials, depth, progress)),
+            None,
+        )
+        .map_err(|e| git_error(&format!("Failed to fetch git ref {}", git_ref), e))?;
+
+    let commit = [
+        format!("refs/remotes/origin/{}", git_ref),
+        format!("refs/tags/{}", git_ref),
+        git_ref.to_string(),
+    ]
+    .iter()
+    .find_map(|spec| repo.revparse_single(spec).ok())
+    .and_then(|object| object.peel_to_commit().ok())
+    .ok_or_else(|| format!("Git ref {} not found in repository", git_ref))?;
+
+    repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().force()))
+        .map_err(|e| git_error(&format!("Failed to check out git ref {}", git_ref), e))?;
+    repo.set_head_detached(commit.id())
+        .map_err(|e| git_error(&format!("Failed to check out git ref {}", git_ref), e))?;
+
+    Ok(())
+}
+
+/// Looks up the commit a remote branch or tag currently points to, without cloning.
+///
+/// # Arguments
+///
+/// * `github_url` - The URL of the repository.
+/// * `git_ref` - The tracked branch or tag (the remote default branch if `None`).
+/// * `credentials` - The credentials used to access private repositories.
+///
+/// # Returns
+/// * `Ok(String)` containing the commit SHA of the ref.
+/// * `Err(String)` if the remote cannot be reached or the ref does not exist.
+pub fn remote_head(
+    github_url: &str,
+    git_ref: Option<&str>,
+    credentials: &GitCredentials,
+) -> Result<String, String> {
+    let remote_url = match credentials {
+        GitCredentials::SshKey(_) => ssh_url(github_url),
+        _ => github_url.to_string(),
+    };
+
+    let mut remote = git2::Remote::create_detached(remote_url.as_str())
+        .map_err(|e| git_error("Failed to create remote", e))?;
+    let mut no_progress = |_: usize, _: usize| {};
+    let connection = remote
+        .connect_auth(
+            git2::Direction::Fetch,
+            Some(remote_callbacks(credentials, &mut no_progress)),
+            None,
+        )
+        .map_err(|e| git_error("Failed to connect to remote", e))?;
+
+    let wanted = match git_ref {
+        Some(git_ref) => vec![
+            format!("refs/heads/{}", git_ref),
+            format!("refs/tags/{}^{{}}", git_ref),
+            format!("refs/tags/{}", git_ref),
+        ],
+        None => vec!["HEAD".to_string()],
+    };
+
+    let heads = connection
+        .list()
+        .map_err(|e| git_error("Failed to list remote refs", e))?;
+
+    wanted
+        .iter()
+        .find_map(|name| {
+            heads
+                .iter()
+                .find(|head| head.name() == name)
+                .map(|head| head.oid().to_string())
+        })
+        .ok_or_else(|| format!("Git ref {} not found on remote", git_ref.unwrap_or("HEAD")))
+}
+
+/// Reads the commit checked out in a cloned repository.
+///
+/// # Arguments
+///
+/// * `target_dir` - The directory of the cloned repository.
+///
+/// # Returns
+/// * `Ok((String, String))` containing the HEAD commit SHA and its summary line.
+/// * `Err(String)` if the repository or its HEAD commit cannot be read.
+pub fn head_commit(target_dir: &str) -> Result<(String, String), String> {
+    let repo =
+        Repository::open(target_dir).map_err(|e| git_error("Failed to open repository", e))?;
+    let commit = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|e| git_error("Failed to read HEAD commit", e))?;
+
+    Ok((
+        commit.id().to_string(),
+        commit.summary().unwrap_or_default().to_string(),
+    ))
+}
+
+/// Initializes and updates the submodules of a repository, recursively.
+///
+/// # Arguments
+///
+/// * `repo` - The repository whose submodules are updated.
+/// * `depth` - History depth to fetch, `0` for the full history.
+/// * `credentials` - The credentials used to access private repositories.
+///
+/// # Returns
+/// * `Ok(())` if all submodules were updated.
+/// * `Err(String)` if a submodule could not be fetched.
+fn update_submodules(
+    repo: &Repository,
+    depth: u32,
+    credentials: &GitCredentials,
+) -> Result<(), String> {
+    let submodules = repo
+        .submodules()
+        .map_err(|e| git_error("Failed to list submodules", e))?;
+
+    for mut submodule in submodules {
+        let name = submodule.name().unwrap_or("unknown").to_string();
+        let mut no_progress = |_: usize, _: usize| {};
+        let mut update_options = SubmoduleUpdateOptions::new();
+        update_options.fetch(fetch_options(credentials, depth, &mut no_progress));
 
-    let status = Command::new("git")
-        .args(["clone", &github_url, target_dir])
-        .status()
-        .map_err(|e| format!("Failed to execute git: {}", e))?;
+        submodule
+            .update(true, Some(&mut update_options))
+            .map_err(|e| git_error(&format!("Failed to update submodule {}", name), e))?;
 
-    if !status.success() {
-        return Err("Failed to clone repository. Check URL and permissions.".to_string());
+        let submodule_repo = submodule
+            .open()
+            .map_err(|e| git_error(&format!("Failed to open submodule {}", name), e))?;
+        update_submodules(&submodule_repo, depth, credentials)?;
     }
+
     Ok(())
 }
+
+/// Normalizes a repository URL so HTTPS, SSH and `.git` variants can be compared.
+///
+/// # Arguments
+///
+/// * `url` - The repository URL.
+///
+/// # Returns
+/// * The lowercase `host/owner/repo` form of the URL.
+pub fn normalize_repo_url(url: &str) -> String {
+    let url = url.trim().to_lowercase();
+    let url = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .or_else(|| url.strip_prefix("ssh://"))
+        .unwrap_or(&url);
+    let url = url.split_once('@').map_or(url, |(_, host)| host);
+    let url = url.trim_end_matches('/');
+    url.strip_suffix(".git")
+        .unwrap_or(url)
+        .replacen(':', "/", 1)
+}
+
+/// State of a commit status reported to GitHub.
+#[derive(Debug, Clone, Copy)]
+pub enum CommitState {
+    Pending,
+    Success,
+    Failure,
+}
+
+impl CommitState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CommitState::Pending => "pending",
+            CommitState::Success => "success",
+            CommitState::Failure => "failure",
+        }
+    }
+}
+
+/// Tells whether deploy results should be reported on GitHub commits.
+///
+/// Enabled by default, disabled when `features.github_commit_status` is `false`.
+pub fn commit_status_enabled() -> bool {
+    config().features.github_commit_status
+}
+
+/// Extracts the owner and repository name from a GitHub repository URL.
+///
+/// # Arguments
+///
+/// * `github_url` - The repository URL.
+///
+/// # Returns
+/// * `Some((owner, repo))` if the URL points to a repository.
+/// * `None` otherwise.
+fn repo_slug(github_url: &str) -> Option<(String, String)> {
+    let normalized = normalize_repo_url(github_url);
+    let mut parts = normalized.splitn(3, '/').skip(1);
+    match (parts.next(), parts.next()) {
+        (Some(owner), Some(repo)) if !owner.is_empty() && !repo.is_empty() => {
+            Some((owner.to_string(), repo.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Reports the state of a deployment as a commit status on GitHub.
+///
+/// Errors are logged and never fail the deployment.
+///
+/// # Arguments
+///
+/// * `github_url` - The repository URL.
+/// * `token` - The token used to authenticate against the GitHub API.
+/// * `commit_sha` - The deployed commit.
+/// * `state` - The deployment state.
+/// * `description` - A short description shown on the commit.
+pub async fn report_commit_status(
+    github_url: &str,
+    token: &str,
+    commit_sha: &str,
+    state: CommitState,
+    description: &str,
+) {
+    let Some((owner, repo)) = repo_slug(github_url) else {
+        warn!("Cannot report commit status for {}", github_url);
+        return;
+    };
+
+    let api_url = &config().github.api_url;
+
+    // GitHub rejects descriptions longer than 140 characters
+    let description: String = description.chars().take(140).collect();
+
+    let result = reqwest::Client::new()
+        .post(format!(
+            "{}/repos/{}/{}/statuses/{}",
+            api_url, owner, repo, commit_sha
+        ))
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "nephelios")
+        .json(&serde_json::json!({
+            "state": state.as_str(),
+            "description": description,
+            "context": "nephelios/deploy",
+        }))
+        .send()
+        .await;
+
+    match result {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => warn!(
+            "GitHub commit status request failed with status {}",
+            response.status()
+        ),
+        Err(e) => warn!("Failed to report commit status: {}", e),
+    }
+}
+
+/// Computes the HMAC-SHA256 of a payload, as used to sign webhook deliveries.
+///
+/// # Arguments
+///
+/// * `secret` - The shared secret.
+/// * `payload` - The raw request body.
+///
+/// # Returns
+/// * `Ok(String)` - The hexadecimal digest.
+/// * `Err(String)` - If OpenSSL failed to compute it.
+pub fn hmac_sha256_hex(secret: &str, payload: &[u8]) -> Result<String, String> {
+    PKey::hmac(secret.as_bytes())
+        .and_then(|key| {
+            let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+            signer.update(payload)?;
+            signer.sign_to_vec()
+        })
+        .map(|digest| digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+        .map_err(|e| format!("Failed to compute HMAC: {}", e))
+}
+
+/// Verifies the `X-Hub-Signature-256` header of a GitHub webhook delivery.
+///
+/// # Arguments
+///
+/// * `secret` - The webhook secret configured on GitHub.
+/// * `payload` - The raw request body.
+/// * `signature` - The header value, formatted as `sha256=<hex digest>`.
+///
+/// # Returns
+/// * `true` if the signature matches the payload.
+/// * `false` otherwise.
+pub fn verify_webhook_signature(secret: &str, payload: &[u8], signature: &str) -> bool {
+    let Some(expected) = signature.strip_prefix("sha256=") else {
+        return false;
+    };
+
+    match hmac_sha256_hex(secret, payload) {
+        Ok(digest) => {
+            digest.len() == expected.len() && memcmp::eq(digest.as_bytes(), expected.as_bytes())
+        }
+        Err(_) => false,
+    }
+}
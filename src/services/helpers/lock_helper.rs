@@ -0,0 +1,112 @@
+use dirs::home_dir;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::fs::{self, File, TryLockError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::OwnedMutexGuard;
+use tracing::info;
+
+/// Delay between two attempts to take the lock file held by another process.
+const FILE_LOCK_RETRY: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    static ref APP_LOCKS: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// The exclusive deployment lock of an app, released when dropped.
+pub struct AppLock {
+    _guard: OwnedMutexGuard<()>,
+    _file: File,
+}
+
+/// Returns the in-process lock of an app, creating it on first use.
+fn app_mutex(app_name: &str) -> Arc<tokio::sync::Mutex<()>> {
+    let mut locks = APP_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+    locks.entry(app_name.to_string()).or_default().clone()
+}
+
+/// Opens the lock file of an app, shared by every Nephelios process of this host.
+fn open_lock_file(app_name: &str) -> Result<File, String> {
+    let home = home_dir().ok_or("Failed to find home directory")?;
+    let path = home.join(format!(".config/nephelios/locks/{}.lock", app_name));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create locks directory: {}", e))?;
+    }
+
+    fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open lock file: {}", e))
+}
+
+/// Takes the exclusive deployment lock of an app, waiting for the running deployment to
+/// finish if there is one.
+///
+/// Deployments of the same app share its temp directory, image tags and stack file entry, so
+/// they run one at a time. The lock is held in-process, and on a lock file so that another
+/// Nephelios process on the same host also waits.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(AppLock)` holding the lock until it is dropped.
+/// * `Err(String)` if the lock file could not be opened or locked.
+pub async fn lock_app(app_name: &str) -> Result<AppLock, String> {
+    let mutex = app_mutex(app_name);
+    let guard = match mutex.clone().try_lock_owned() {
+        Ok(guard) => guard,
+        Err(_) => {
+            info!("⏳ Waiting for the running deployment of {}", app_name);
+            mutex.lock_owned().await
+        }
+    };
+
+    let file = open_lock_file(app_name)?;
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) => tokio::time::sleep(FILE_LOCK_RETRY).await,
+            Err(TryLockError::Error(e)) => {
+                return Err(format!("Failed to lock {}: {}", app_name, e));
+            }
+        }
+    }
+
+    Ok(AppLock {
+        _guard: guard,
+        _file: file,
+    })
+}
+
+/// Takes the exclusive deployment lock of an app if it is free, without waiting.
+///
+/// Used by background tasks that skip an app while a deployment or a restore runs on it.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(Some(AppLock))` holding the lock until it is dropped.
+/// * `Ok(None)` if the lock is held, in this process or another one.
+/// * `Err(String)` if the lock file could not be opened or locked.
+pub fn try_lock_app(app_name: &str) -> Result<Option<AppLock>, String> {
+    let Ok(guard) = app_mutex(app_name).try_lock_owned() else {
+        return Ok(None);
+    };
+
+    let file = open_lock_file(app_name)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(AppLock {
+            _guard: guard,
+            _file: file,
+        })),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(format!("Failed to lock {}: {}", app_name, e)),
+    }
+}
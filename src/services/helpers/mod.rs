@@ -1,3 +1,11 @@
+pub mod acme_helper;
+pub mod credentials_helper;
+pub mod crypto_helper;
 pub mod docker_helper;
 pub mod github_helper;
+pub mod lock_helper;
+pub mod oidc_helper;
+pub mod readiness_helper;
+pub mod signing_helper;
+pub mod stack_helper;
 pub mod traefik_helper;
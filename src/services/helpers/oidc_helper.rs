@@ -0,0 +1,322 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use lazy_static::lazy_static;
+use openssl::bn::BigNum;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::rsa::Rsa;
+use openssl::sign::Verifier;
+use serde::Deserialize;
+use serde_json::Value;
+use std::env;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Clock skew tolerated on the `exp` and `nbf` claims, in seconds.
+const CLOCK_LEEWAY: i64 = 60;
+
+/// How long fetched signing keys are trusted before being refreshed.
+const JWKS_TTL: Duration = Duration::from_secs(3600);
+
+/// Minimum delay between two refreshes triggered by an unknown key ID.
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(60);
+
+/// The OIDC provider settings, read from the environment.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub audience: String,
+    pub roles_claim: String,
+}
+
+/// A signing key published by the provider.
+#[derive(Debug, Clone, Deserialize)]
+struct Jwk {
+    kid: Option<String>,
+    kty: String,
+    n: Option<String>,
+    e: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwtHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+/// The signing keys of the provider, with the time they were fetched.
+struct CachedKeys {
+    keys: Vec<Jwk>,
+    fetched_at: Instant,
+}
+
+lazy_static! {
+    static ref JWKS_CACHE: RwLock<Option<CachedKeys>> = RwLock::new(None);
+}
+
+/// Reads the OIDC settings from the environment.
+///
+/// OIDC authentication is enabled when `OIDC_ISSUER` and `OIDC_AUDIENCE` are set. Roles are
+/// read from the `OIDC_ROLES_CLAIM` claim (default: `roles`), which may be a dotted path
+/// such as `realm_access.roles`.
+///
+/// # Returns
+/// * `Some(OidcConfig)` if OIDC is configured.
+/// * `None` otherwise.
+pub fn oidc_config_from_env() -> Option<OidcConfig> {
+    let issuer = env::var("OIDC_ISSUER").ok().filter(|v| !v.is_empty())?;
+    let audience = env::var("OIDC_AUDIENCE").ok().filter(|v| !v.is_empty())?;
+    let roles_claim = env::var("OIDC_ROLES_CLAIM")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "roles".to_string());
+
+    Some(OidcConfig {
+        issuer: issuer.trim_end_matches('/').to_string(),
+        audience,
+        roles_claim,
+    })
+}
+
+/// Fetches the signing keys of the provider through its discovery document.
+async fn fetch_jwks(issuer: &str) -> Result<Vec<Jwk>, String> {
+    let client = reqwest::Client::new();
+    let discovery: Discovery = client
+        .get(format!("{}/.well-known/openid-configuration", issuer))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch OIDC discovery document: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Failed to fetch OIDC discovery document: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid OIDC discovery document: {}", e))?;
+
+    let jwks: JwkSet = client
+        .get(&discovery.jwks_uri)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch OIDC signing keys: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Failed to fetch OIDC signing keys: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid OIDC signing keys: {}", e))?;
+
+    Ok(jwks.keys)
+}
+
+/// Finds the signing key matching a key ID, refreshing the cached keys when needed.
+///
+/// Keys are refetched when the cache is older than `JWKS_TTL`, or when the key ID is unknown
+/// (the provider rotated its keys) and the last fetch is older than `JWKS_MIN_REFRESH`.
+async fn find_key(issuer: &str, kid: Option<&str>) -> Result<Jwk, String> {
+    let matches = |key: &Jwk| kid.is_none() || key.kid.as_deref() == kid;
+
+    {
+        let cache = JWKS_CACHE.read().await;
+        if let Some(cached) = cache.as_ref() {
+            if cached.fetched_at.elapsed() < JWKS_TTL {
+                if let Some(key) = cached.keys.iter().find(|key| matches(key)) {
+                    return Ok(key.clone());
+                }
+                if cached.fetched_at.elapsed() < JWKS_MIN_REFRESH {
+                    return Err("Unknown token signing key".to_string());
+                }
+            }
+        }
+    }
+
+    let keys = fetch_jwks(issuer).await?;
+    let key = keys.iter().find(|key| matches(key)).cloned();
+    *JWKS_CACHE.write().await = Some(CachedKeys {
+        keys,
+        fetched_at: Instant::now(),
+    });
+
+    key.ok_or_else(|| "Unknown token signing key".to_string())
+}
+
+/// Decodes a base64url JWT segment.
+fn decode_segment(segment: &str) -> Result<Vec<u8>, String> {
+    URL_SAFE_NO_PAD
+        .decode(segment)
+        .map_err(|e| format!("Invalid token encoding: {}", e))
+}
+
+/// Checks the RS256 signature of a token against an RSA signing key.
+fn verify_signature(key: &Jwk, signing_input: &str, signature: &[u8]) -> Result<(), String> {
+    if key.kty != "RSA" {
+        return Err(format!("Unsupported signing key type: {}", key.kty));
+    }
+    let n = decode_segment(
+        key.n
+            .as_deref()
+            .ok_or("Signing key is missing its modulus")?,
+    )?;
+    let e = decode_segment(
+        key.e
+            .as_deref()
+            .ok_or("Signing key is missing its exponent")?,
+    )?;
+
+    let rsa = BigNum::from_slice(&n)
+        .and_then(|n| Ok((n, BigNum::from_slice(&e)?)))
+        .and_then(|(n, e)| Rsa::from_public_components(n, e))
+        .map_err(|e| format!("Invalid signing key: {}", e))?;
+    let pkey = PKey::from_rsa(rsa).map_err(|e| format!("Invalid signing key: {}", e))?;
+
+    let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey)
+        .map_err(|e| format!("Failed to create token verifier: {}", e))?;
+    verifier
+        .update(signing_input.as_bytes())
+        .map_err(|e| format!("Failed to verify token: {}", e))?;
+
+    match verifier.verify(signature) {
+        Ok(true) => Ok(()),
+        _ => Err("Invalid token signature".to_string()),
+    }
+}
+
+/// Checks the issuer, audience and validity period of the token claims.
+fn verify_claims(config: &OidcConfig, claims: &Value) -> Result<(), String> {
+    let issuer = claims
+        .get("iss")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if issuer.trim_end_matches('/') != config.issuer {
+        return Err("Invalid token issuer".to_string());
+    }
+
+    let audience_matches = match claims.get("aud") {
+        Some(Value::String(aud)) => aud == &config.audience,
+        Some(Value::Array(auds)) => auds
+            .iter()
+            .any(|aud| aud.as_str() == Some(config.audience.as_str())),
+        _ => false,
+    };
+    if !audience_matches {
+        return Err("Invalid token audience".to_string());
+    }
+
+    let now = Utc::now().timestamp();
+    match claims.get("exp").and_then(Value::as_i64) {
+        Some(exp) if exp + CLOCK_LEEWAY > now => {}
+        _ => return Err("Token has expired".to_string()),
+    }
+    if let Some(nbf) = claims.get("nbf").and_then(Value::as_i64) {
+        if nbf - CLOCK_LEEWAY > now {
+            return Err("Token is not valid yet".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the roles claim of a token, following a dotted path.
+fn extract_roles(claims: &Value, roles_claim: &str) -> Vec<String> {
+    let value = roles_claim
+        .split('.')
+        .try_fold(claims, |value, key| value.get(key));
+
+    match value {
+        Some(Value::String(role)) => vec![role.clone()],
+        Some(Value::Array(roles)) => roles
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The identity carried by a verified token.
+#[derive(Debug, Clone)]
+pub struct VerifiedToken {
+    /// The `preferred_username`, `email` or `sub` claim, whichever is set first.
+    pub subject: String,
+    pub roles: Vec<String>,
+}
+
+/// Verifies a JWT issued by the OIDC provider and returns its identity and roles.
+///
+/// Only RS256 tokens are accepted. The signature is checked against the provider signing
+/// keys, then the issuer, audience and validity period.
+///
+/// # Arguments
+/// * `config` - The OIDC provider settings.
+/// * `token` - The encoded JWT.
+///
+/// # Returns
+/// * `Ok(VerifiedToken)` containing the subject and roles of the token.
+/// * `Err(String)` if the token is invalid.
+pub async fn verify_token(config: &OidcConfig, token: &str) -> Result<VerifiedToken, String> {
+    let mut segments = token.split('.');
+    let (header, payload, signature) = match (
+        segments.next(),
+        segments.next(),
+        segments.next(),
+        segments.next(),
+    ) {
+        (Some(header), Some(payload), Some(signature), None) => (header, payload, signature),
+        _ => return Err("Malformed token".to_string()),
+    };
+
+    let jwt_header: JwtHeader = serde_json::from_slice(&decode_segment(header)?)
+        .map_err(|e| format!("Invalid token header: {}", e))?;
+    if jwt_header.alg != "RS256" {
+        return Err(format!("Unsupported token algorithm: {}", jwt_header.alg));
+    }
+
+    let key = find_key(&config.issuer, jwt_header.kid.as_deref()).await?;
+    verify_signature(
+        &key,
+        &format!("{}.{}", header, payload),
+        &decode_segment(signature)?,
+    )?;
+
+    let claims: Value = serde_json::from_slice(&decode_segment(payload)?)
+        .map_err(|e| format!("Invalid token claims: {}", e))?;
+    verify_claims(config, &claims)?;
+
+    Ok(VerifiedToken {
+        subject: subject_claim(&claims),
+        roles: extract_roles(&claims, &config.roles_claim),
+    })
+}
+
+/// Reads the `preferred_username`, `email` or `sub` claim, whichever is set first.
+fn subject_claim(claims: &Value) -> String {
+    ["preferred_username", "email", "sub"]
+        .iter()
+        .find_map(|claim| claims.get(claim).and_then(Value::as_str))
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Reads the subject of a JWT without verifying it.
+///
+/// Only meant to name the caller of a request whose token was verified, or rejected, by
+/// `verify_token` (e.g., in the audit log).
+///
+/// # Arguments
+/// * `token` - The encoded JWT.
+///
+/// # Returns
+/// The subject, as in `VerifiedToken`, or `None` if the token cannot be decoded.
+pub fn token_subject(token: &str) -> Option<String> {
+    let payload = token.split('.').nth(1)?;
+    let claims: Value = serde_json::from_slice(&decode_segment(payload).ok()?).ok()?;
+    Some(subject_claim(&claims))
+}
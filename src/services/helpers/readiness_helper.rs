@@ -0,0 +1,255 @@
+use crate::config::config;
+use bollard::models::LocalNodeState;
+use bollard::service::ListServicesOptions;
+use bollard::Docker;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Maximum time given to each dependency check.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pause between two rounds of checks while waiting for the dependencies at startup.
+const WAIT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Swarm service running Traefik.
+const TRAEFIK_SERVICE: &str = "nephelios_traefik";
+
+/// Overlay network the apps, the registry and Traefik are attached to.
+const OVERLAY_NETWORK: &str = "nephelios_overlay";
+
+/// The outcome of a dependency check.
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyCheck {
+    pub ok: bool,
+    /// What was found, or why the check failed.
+    pub detail: String,
+}
+
+/// The outcome of every dependency check.
+#[derive(Debug, Clone, Serialize)]
+pub struct Readiness {
+    pub docker: DependencyCheck,
+    pub swarm: DependencyCheck,
+    pub registry: DependencyCheck,
+    pub traefik: DependencyCheck,
+    pub network: DependencyCheck,
+}
+
+impl Readiness {
+    /// Returns every check, by dependency name.
+    fn checks(&self) -> [(&'static str, &DependencyCheck); 5] {
+        [
+            ("docker", &self.docker),
+            ("swarm", &self.swarm),
+            ("registry", &self.registry),
+            ("traefik", &self.traefik),
+            ("network", &self.network),
+        ]
+    }
+
+    /// Whether every dependency is available.
+    pub fn is_ready(&self) -> bool {
+        self.checks().iter().all(|(_, check)| check.ok)
+    }
+
+    /// Describes the unavailable dependencies (e.g., `registry: Failed to reach ...`).
+    pub fn failures(&self) -> String {
+        self.checks()
+            .iter()
+            .filter(|(_, check)| !check.ok)
+            .map(|(name, check)| format!("{}: {}", name, check.detail))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Runs a check with a timeout, turning its result into a `DependencyCheck`.
+async fn run_check(check: impl Future<Output = Result<String, String>>) -> DependencyCheck {
+    let (ok, detail) = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok(detail)) => (true, detail),
+        Ok(Err(e)) => (false, e),
+        Err(_) => (
+            false,
+            format!("No answer within {} seconds", CHECK_TIMEOUT.as_secs()),
+        ),
+    };
+    DependencyCheck { ok, detail }
+}
+
+/// Pings the Docker daemon.
+async fn check_docker(docker: &Docker) -> Result<String, String> {
+    let version = docker
+        .version()
+        .await
+        .map_err(|e| format!("Failed to reach Docker: {}", e))?;
+    Ok(format!(
+        "Docker {}",
+        version.version.unwrap_or_else(|| "unknown".to_string())
+    ))
+}
+
+/// Checks that this node is an active Swarm manager.
+async fn check_swarm(docker: &Docker) -> Result<String, String> {
+    let info = docker
+        .info()
+        .await
+        .map_err(|e| format!("Failed to read Docker info: {}", e))?;
+    let swarm = info.swarm.unwrap_or_default();
+
+    match swarm.local_node_state {
+        Some(LocalNodeState::ACTIVE) if swarm.control_available == Some(true) => Ok(format!(
+            "Active manager, {} nodes",
+            swarm.nodes.unwrap_or_default()
+        )),
+        Some(LocalNodeState::ACTIVE) => Err("This node is not a Swarm manager".to_string()),
+        state => Err(format!(
+            "Swarm is not active (state: {})",
+            state.map(|state| state.to_string()).unwrap_or_default()
+        )),
+    }
+}
+
+/// Checks that the registry answers its API base endpoint.
+async fn check_registry() -> Result<String, String> {
+    let url = config().registry.api_url();
+    let endpoint = format!("{}/v2/", url.trim_end_matches('/'));
+
+    let response = reqwest::Client::new()
+        .get(&endpoint)
+        .timeout(CHECK_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach {}: {}", endpoint, e))?;
+
+    // A registry requiring authentication answers 401, which still proves it is up
+    let status = response.status();
+    if status.is_success() || status == reqwest::StatusCode::UNAUTHORIZED {
+        Ok(format!("{} answered {}", endpoint, status))
+    } else {
+        Err(format!("{} answered {}", endpoint, status))
+    }
+}
+
+/// Checks that the Traefik service has a running task.
+async fn check_traefik(docker: &Docker) -> Result<String, String> {
+    let options = ListServicesOptions {
+        filters: HashMap::from([("name", vec![TRAEFIK_SERVICE])]),
+        status: true,
+    };
+    let services = docker
+        .list_services(Some(options))
+        .await
+        .map_err(|e| format!("Failed to list services: {}", e))?;
+
+    let service = services
+        .into_iter()
+        .find(|service| {
+            service.spec.as_ref().and_then(|spec| spec.name.as_deref()) == Some(TRAEFIK_SERVICE)
+        })
+        .ok_or_else(|| format!("Service {} not found", TRAEFIK_SERVICE))?;
+
+    let status = service.service_status.unwrap_or_default();
+    let running = status.running_tasks.unwrap_or_default();
+    let desired = status.desired_tasks.unwrap_or_default();
+    if running > 0 {
+        Ok(format!("{}/{} tasks running", running, desired))
+    } else {
+        Err(format!("No running task ({} desired)", desired))
+    }
+}
+
+/// Checks that the overlay network of the stack exists.
+async fn check_network(docker: &Docker) -> Result<String, String> {
+    let network = docker
+        .inspect_network::<String>(OVERLAY_NETWORK, None)
+        .await
+        .map_err(|e| format!("Failed to inspect network {}: {}", OVERLAY_NETWORK, e))?;
+    match network.driver.as_deref() {
+        Some("overlay") => Ok(format!(
+            "Network {} ({} scope)",
+            OVERLAY_NETWORK,
+            network.scope.unwrap_or_default()
+        )),
+        driver => Err(format!(
+            "Network {} uses the {} driver, expected overlay",
+            OVERLAY_NETWORK,
+            driver.unwrap_or("unknown")
+        )),
+    }
+}
+
+/// Checks the dependencies Nephelios needs to deploy apps.
+///
+/// The Docker connection, the Swarm status, the registry, the Traefik service and the overlay
+/// network are checked concurrently, each within `CHECK_TIMEOUT`.
+///
+/// # Returns
+/// The outcome of each check.
+pub async fn check_readiness() -> Readiness {
+    let docker = match Docker::connect_with_local_defaults() {
+        Ok(docker) => docker,
+        Err(e) => {
+            let failed = |detail: String| DependencyCheck { ok: false, detail };
+            let docker_error = format!("Failed to connect to Docker: {}", e);
+            return Readiness {
+                docker: failed(docker_error.clone()),
+                swarm: failed(docker_error.clone()),
+                registry: run_check(check_registry()).await,
+                traefik: failed(docker_error.clone()),
+                network: failed(docker_error),
+            };
+        }
+    };
+
+    let (docker_check, swarm, registry, traefik, network) = tokio::join!(
+        run_check(check_docker(&docker)),
+        run_check(check_swarm(&docker)),
+        run_check(check_registry()),
+        run_check(check_traefik(&docker)),
+        run_check(check_network(&docker)),
+    );
+
+    Readiness {
+        docker: docker_check,
+        swarm,
+        registry,
+        traefik,
+        network,
+    }
+}
+
+/// Waits for the dependencies to be available, after the stack was deployed.
+///
+/// The registry and Traefik containers take a while to start after `docker stack deploy`, so
+/// images pushed right away would fail. The checks are repeated every `WAIT_INTERVAL` until
+/// they all pass, or the timeout elapses.
+///
+/// # Arguments
+/// * `timeout` - How long to wait.
+///
+/// # Returns
+/// * `Ok(())` once every dependency is available.
+/// * `Err(String)` describing the unavailable dependencies when the timeout elapsed.
+pub async fn wait_for_readiness(timeout: Duration) -> Result<(), String> {
+    let deadline = Instant::now() + timeout;
+    let mut pending = String::new();
+    loop {
+        let readiness = check_readiness().await;
+        if readiness.is_ready() {
+            return Ok(());
+        }
+
+        let failures = readiness.failures();
+        if Instant::now() + WAIT_INTERVAL > deadline {
+            return Err(failures);
+        }
+        if failures != pending {
+            info!("⏳ Waiting for {}", failures);
+            pending = failures;
+        }
+        tokio::time::sleep(WAIT_INTERVAL).await;
+    }
+}
@@ -0,0 +1,101 @@
+use crate::config::{config, SigningPolicy};
+use crate::services::helpers::docker_helper::image_digest;
+use std::process::Command;
+
+/// Returns the cosign flags needed to reach an image, when it is stored in the registry of
+/// Nephelios served over plain HTTP.
+///
+/// # Arguments
+/// * `image` - The image reference.
+fn registry_args(image: &str) -> &'static [&'static str] {
+    let registry = &config().registry;
+    if image.starts_with(&format!("{}/", registry.host))
+        && registry.api_url().starts_with("http://")
+    {
+        &["--allow-http-registry", "--allow-insecure-registry"]
+    } else {
+        &[]
+    }
+}
+
+/// Runs cosign with the given arguments.
+///
+/// # Returns
+/// * `Ok(())` if cosign succeeded.
+/// * `Err(String)` containing the error output of cosign otherwise.
+fn run_cosign(args: &[&str]) -> Result<(), String> {
+    let output = Command::new("cosign")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to execute cosign: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Signs an image pushed to the registry, unless the signing policy is `disabled`.
+///
+/// The signature is pushed next to the image in the registry, and is not uploaded to a
+/// public transparency log.
+///
+/// # Arguments
+/// * `image` - The image reference, with its tag.
+///
+/// # Returns
+/// * `Ok(())` if the image was signed, or signing is disabled.
+/// * `Err(String)` if cosign failed to sign the image.
+pub fn sign_image(image: &str) -> Result<(), String> {
+    let signing = &config().signing;
+    let Some(key) = signing
+        .key_path
+        .as_deref()
+        .filter(|_| signing.policy != SigningPolicy::Disabled)
+    else {
+        return Ok(());
+    };
+
+    let mut args = vec!["sign", "--yes", "--tlog-upload=false", "--key", key];
+    args.extend(registry_args(image));
+    args.push(image);
+    run_cosign(&args).map_err(|e| format!("Failed to sign image {}: {}", image, e))
+}
+
+/// Checks that an image is signed by one of the trusted keys, when the signing policy is
+/// `enforce`.
+///
+/// The image is resolved to its digest first, and the digest is verified and returned, so a
+/// tag pushed again after the check cannot be deployed.
+///
+/// # Arguments
+/// * `image` - The image reference.
+///
+/// # Returns
+/// * `Ok(String)` containing the reference to deploy: by digest when signatures are
+///   enforced, the image itself otherwise.
+/// * `Err(String)` if the digest could not be resolved or no trusted key signed the image.
+pub async fn verify_image(image: &str) -> Result<String, String> {
+    let signing = &config().signing;
+    if signing.policy != SigningPolicy::Enforce {
+        return Ok(image.to_string());
+    }
+
+    let image = image_digest(image).await?;
+    let image = image.as_str();
+    let mut errors = Vec::new();
+    for key in &signing.trusted_keys {
+        let mut args = vec!["verify", "--insecure-ignore-tlog=true", "--key", key];
+        args.extend(registry_args(image));
+        args.push(image);
+        match run_cosign(&args) {
+            Ok(()) => return Ok(image.to_string()),
+            Err(e) => errors.push(format!("{}: {}", key, e)),
+        }
+    }
+    Err(format!(
+        "Image {} is not signed by a trusted key ({})",
+        image,
+        errors.join("; ")
+    ))
+}
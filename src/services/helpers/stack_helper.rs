@@ -0,0 +1,225 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Path of the Nephelios stack file deployed with `docker stack deploy`.
+pub const STACK_FILE: &str = "./nephelios.yml";
+
+/// Serializes the updates of the stack file, so concurrent updates are not lost.
+static STACK_LOCK: Mutex<()> = Mutex::new(());
+
+/// The Nephelios stack file (a compose file).
+///
+/// Only the parts Nephelios edits are modeled, every other key is kept as-is in `extra`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StackFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Mapping::is_empty")]
+    pub networks: Mapping,
+    #[serde(default, skip_serializing_if = "Mapping::is_empty")]
+    pub volumes: Mapping,
+    #[serde(default)]
+    pub services: IndexMap<String, Service>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+/// A service of the stack file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Service {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deploy: Option<Deploy>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub networks: Vec<String>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+/// The `deploy` section of a service.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Deploy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replicas: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Resources>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placement: Option<Placement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_config: Option<UpdateConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+/// The `deploy.resources` section of a service.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Resources {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<ResourceSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reservations: Option<ResourceSpec>,
+}
+
+/// The `deploy.placement` section of a service.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Placement {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraints: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preferences: Vec<PlacementPreference>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+/// A placement preference, spreading the tasks of a service across the values of a label.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlacementPreference {
+    pub spread: String,
+}
+
+/// The `deploy.update_config` section of a service, how its tasks are replaced.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallelism: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_action: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+/// The hardening keys of a service, kept in its `extra` keys.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServiceSecurity {
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tmpfs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cap_drop: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cap_add: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub security_opt: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+impl ServiceSecurity {
+    /// The keys of a service the settings are written to.
+    pub const KEYS: &'static [&'static str] = &[
+        "read_only",
+        "tmpfs",
+        "cap_drop",
+        "cap_add",
+        "security_opt",
+        "user",
+    ];
+}
+
+/// CPU and memory amounts of a resource limit or reservation.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+}
+
+/// Reads and parses the stack file.
+///
+/// # Returns
+/// * `Ok(StackFile)` containing the parsed stack.
+/// * `Err(io::Error)` if the file cannot be read or is not a valid stack file.
+pub fn load_stack() -> io::Result<StackFile> {
+    let content = fs::read_to_string(PathBuf::from(STACK_FILE))?;
+    serde_yaml::from_str(&content).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid stack file: {}", e),
+        )
+    })
+}
+
+/// Serializes and writes the stack file.
+///
+/// The content is written to a temporary file first, then renamed over the stack file
+/// so a failure never leaves a truncated file behind.
+///
+/// # Arguments
+/// * `stack` - The stack to write.
+///
+/// # Returns
+/// * `Ok(())` if the file was written.
+/// * `Err(io::Error)` if serialization or writing fails.
+pub fn save_stack(stack: &StackFile) -> io::Result<()> {
+    let content = serde_yaml::to_string(stack).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Failed to serialize stack file: {}", e),
+        )
+    })?;
+
+    let path = PathBuf::from(STACK_FILE);
+    let tmp_path = path.with_extension("yml.tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, &path)
+}
+
+/// Replaces the stack file, e.g. when restoring a backup after the host was lost.
+///
+/// # Arguments
+/// * `stack` - The new stack.
+pub fn replace_stack(stack: &StackFile) -> io::Result<()> {
+    let _guard = STACK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    save_stack(stack)
+}
+
+/// Lists the names of the Docker secrets the stack file refers to.
+///
+/// Secrets are declared in the top-level `secrets` section, under their own `name` when
+/// one is set.
+pub fn stack_secret_names(stack: &StackFile) -> Vec<String> {
+    let Some(secrets) = stack.extra.get("secrets").and_then(|s| s.as_mapping()) else {
+        return Vec::new();
+    };
+    secrets
+        .iter()
+        .filter_map(|(key, secret)| {
+            secret
+                .get("name")
+                .and_then(|name| name.as_str())
+                .or_else(|| key.as_str())
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+/// Loads the stack file, applies `update` to it and writes it back.
+///
+/// # Arguments
+/// * `update` - The modification to apply.
+///
+/// # Returns
+/// * The value returned by `update`, or an I/O error.
+pub fn update_stack<T>(update: impl FnOnce(&mut StackFile) -> io::Result<T>) -> io::Result<T> {
+    let _guard = STACK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut stack = load_stack()?;
+    let result = update(&mut stack)?;
+    save_stack(&stack)?;
+    Ok(result)
+}
@@ -1,10 +1,18 @@
-use crate::services::helpers::docker_helper::AppMetadata;
-use std::fs;
-use std::fs::File;
-use std::fs::OpenOptions;
-use std::io::{self, Read, Write};
-use std::path::PathBuf;
-use regex::Regex;
+use crate::config::config;
+use crate::services::helpers::docker_helper::{sanitize_label_value, AppMetadata};
+use crate::services::helpers::stack_helper::{
+    load_stack, update_stack, Deploy, Placement, Resources, Service, ServiceSecurity, StackFile,
+    UpdateConfig,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use serde_yaml::{Mapping, Value as YamlValue};
+use std::collections::BTreeMap;
+use std::io;
+use std::net::IpAddr;
+use std::str::FromStr;
+use tracing::info;
 
 /// Verifies if the application is already deployed.
 ///
@@ -17,77 +25,916 @@ use regex::Regex;
 /// * `Ok(0)` if the application is not deployed.
 /// * `Err(String)` if there was an error during verification.
 pub fn verif_app(app: &str) -> io::Result<i32> {
-    let path = PathBuf::from("./nephelios.yml");
-    let mut file = File::open(&path)?;
-    let mut content = String::new();
-    file.read_to_string(&mut content)?;
+    let stack = load_stack()?;
 
-    if content.contains(app) {
+    if stack.services.contains_key(app) {
         Ok(1)
     } else {
         Ok(0)
     }
 }
 
+/// Routing settings of an application, rendered as Traefik labels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingConfig {
+    /// The port the application listens on inside the container.
+    #[serde(default = "default_app_port")]
+    pub port: String,
+    /// Custom domains served in addition to `<app>.localhost`.
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Source IPs or CIDR ranges allowed to reach the application, everyone if empty.
+    #[serde(default)]
+    pub ip_allowlist: Vec<String>,
+    /// The protocol spoken by the application on its port.
+    #[serde(default)]
+    pub protocol: AppProtocol,
+    /// How plain HTTP requests are handled, `HTTP_POLICY` if unset.
+    #[serde(default)]
+    pub http_policy: Option<HttpPolicy>,
+    /// Session affinity settings, disabled if `None`.
+    #[serde(default)]
+    pub sticky: Option<StickySessions>,
+    /// Additional container ports, each routed on a subdomain or path prefix.
+    #[serde(default)]
+    pub ports: Vec<ExposedPort>,
+    /// Whether requests are answered by the maintenance page instead of the application.
+    #[serde(default)]
+    pub maintenance: bool,
+    /// Extra Traefik middlewares by name, each holding a single `{ "<type>": { options } }` entry.
+    ///
+    /// They are chained after the built-in middlewares, in order.
+    #[serde(default)]
+    pub middlewares: IndexMap<String, JsonValue>,
+}
+
+/// Traefik v2 HTTP middleware types that can be attached to an application.
+const MIDDLEWARE_TYPES: &[&str] = &[
+    "addprefix",
+    "basicauth",
+    "buffering",
+    "chain",
+    "circuitbreaker",
+    "compress",
+    "contenttype",
+    "digestauth",
+    "errors",
+    "forwardauth",
+    "headers",
+    "ipwhitelist",
+    "inflightreq",
+    "passtlsclientcert",
+    "ratelimit",
+    "redirectregex",
+    "redirectscheme",
+    "replacepath",
+    "replacepathregex",
+    "retry",
+    "stripprefix",
+    "stripprefixregex",
+];
+
+/// Names of the middlewares generated by Nephelios, unavailable for custom middlewares.
+const RESERVED_MIDDLEWARES: &[&str] = &["ipallowlist", "https-redirect"];
+
+/// Checks whether a middleware name or option key only contains label-safe characters.
+fn is_label_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Flattens middleware options into `path=value` label fragments.
+///
+/// # Arguments
+///
+/// * `path` - The label path of `value`.
+/// * `value` - The options to flatten.
+/// * `labels` - The fragments, appended to.
+///
+/// # Returns
+/// * `Ok(())` if every option could be represented as a label.
+/// * `Err(String)` for invalid keys, nested lists or null values.
+fn flatten_middleware_options(
+    path: &str,
+    value: &JsonValue,
+    labels: &mut Vec<String>,
+) -> Result<(), String> {
+    // `$` starts a variable in stack files, as in basic auth password hashes
+    let scalar = |value: &JsonValue| match value {
+        JsonValue::String(value) => Some(value.replace('$', "$$")),
+        JsonValue::Bool(value) => Some(value.to_string()),
+        JsonValue::Number(value) => Some(value.to_string()),
+        _ => None,
+    };
+
+    match value {
+        JsonValue::Object(options) => {
+            for (key, option) in options {
+                if !is_label_key(key) {
+                    return Err(format!("Invalid middleware option: {}.{}", path, key));
+                }
+                flatten_middleware_options(&format!("{}.{}", path, key), option, labels)?;
+            }
+            Ok(())
+        }
+        JsonValue::Array(items) => {
+            let items = items
+                .iter()
+                .map(scalar)
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| format!("Only lists of plain values are supported: {}", path))?;
+            labels.push(format!("{}={}", path, items.join(",")));
+            Ok(())
+        }
+        JsonValue::Null => Err(format!("Missing value for middleware option: {}", path)),
+        value => {
+            let value = scalar(value).unwrap_or_default();
+            if value.contains('\n') {
+                return Err(format!("Multi-line values are not supported: {}", path));
+            }
+            labels.push(format!("{}={}", path, value));
+            Ok(())
+        }
+    }
+}
+
+/// Validates custom middleware definitions and renders them as label fragments.
+///
+/// # Arguments
+///
+/// * `middlewares` - The middleware definitions by name.
+///
+/// # Returns
+/// * `Ok(Vec<(String, Vec<String>)>)` containing each middleware name and its
+///   `<type>.<option>=value` fragments.
+/// * `Err(String)` if a definition is invalid.
+pub fn render_middlewares(
+    middlewares: &IndexMap<String, JsonValue>,
+) -> Result<Vec<(String, Vec<String>)>, String> {
+    middlewares
+        .iter()
+        .map(|(name, definition)| {
+            if !is_label_key(name)
+                || RESERVED_MIDDLEWARES.contains(&name.as_str())
+                || name.starts_with("port-")
+            {
+                return Err(format!("Invalid middleware name: {}", name));
+            }
+
+            let definition = definition
+                .as_object()
+                .filter(|definition| definition.len() == 1)
+                .ok_or_else(|| {
+                    format!(
+                        "Middleware {} must define exactly one middleware type",
+                        name
+                    )
+                })?;
+            let (kind, options) = definition.iter().next().expect("one entry");
+
+            let kind = kind.to_ascii_lowercase();
+            if !MIDDLEWARE_TYPES.contains(&kind.as_str()) {
+                return Err(format!("Unknown middleware type for {}: {}", name, kind));
+            }
+
+            let mut labels = Vec::new();
+            match options {
+                JsonValue::Object(options) if options.is_empty() => {
+                    labels.push(format!("{}=true", kind))
+                }
+                options => flatten_middleware_options(&kind, options, &mut labels)?,
+            }
+
+            Ok((name.clone(), labels))
+        })
+        .collect()
+}
+
+/// Cookie-based session affinity settings of an application.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StickySessions {
+    /// The cookie name, generated by Traefik if `None`.
+    #[serde(default)]
+    pub cookie_name: Option<String>,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    /// The SameSite attribute: `none`, `lax` or `strict`.
+    #[serde(default)]
+    pub same_site: Option<String>,
+}
+
+impl StickySessions {
+    /// Checks that the cookie name and SameSite attribute are valid.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = &self.cookie_name {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!("Invalid cookie name: {}", name));
+            }
+        }
+
+        if let Some(same_site) = &self.same_site {
+            if !["none", "lax", "strict"].contains(&same_site.as_str()) {
+                return Err(format!("Invalid SameSite value: {}", same_site));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An additional container port exposed through its own Traefik router.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExposedPort {
+    /// Short name of the port (e.g., "admin"), used in router names.
+    pub name: String,
+    pub port: u16,
+    /// Subdomain prepended to every host of the app (e.g., "admin" for `admin.<app>.localhost`).
+    #[serde(default)]
+    pub subdomain: Option<String>,
+    /// Path prefix routed to this port (e.g., "/metrics").
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    /// Whether the path prefix is removed before forwarding requests.
+    #[serde(default)]
+    pub strip_prefix: bool,
+    #[serde(default)]
+    pub protocol: AppProtocol,
+}
+
+impl ExposedPort {
+    /// Checks that the port can be rendered as a distinct router.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty()
+            || self.name == "http"
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        {
+            return Err(format!(
+                "Invalid port name: {} (lowercase letters and digits only)",
+                self.name
+            ));
+        }
+
+        if self.port == 0 {
+            return Err(format!("Invalid port for {}: 0", self.name));
+        }
+
+        if self.subdomain.is_none() && self.path_prefix.is_none() {
+            return Err(format!(
+                "Port {} needs a subdomain or a path prefix",
+                self.name
+            ));
+        }
+
+        if let Some(subdomain) = &self.subdomain {
+            normalize_domain(&format!("{}.{}", subdomain, config().domain.base))
+                .map_err(|_| format!("Invalid subdomain: {}", subdomain))?;
+        }
+
+        if let Some(prefix) = &self.path_prefix {
+            if !prefix.starts_with('/')
+                || prefix.len() < 2
+                || !prefix
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "/-_.~".contains(c))
+            {
+                return Err(format!("Invalid path prefix: {}", prefix));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that additional ports are valid and have distinct names.
+///
+/// # Arguments
+///
+/// * `ports` - The additional ports of an application.
+///
+/// # Returns
+/// * `Ok(())` if every port is valid.
+/// * `Err(String)` describing the first invalid port.
+pub fn validate_ports(ports: &[ExposedPort]) -> Result<(), String> {
+    for (i, port) in ports.iter().enumerate() {
+        port.validate()?;
+        if ports[..i].iter().any(|other| other.name == port.name) {
+            return Err(format!("Duplicate port name: {}", port.name));
+        }
+    }
+    Ok(())
+}
+
+/// Protocol spoken by an application behind Traefik.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppProtocol {
+    /// HTTP/1.1.
+    #[default]
+    Http,
+    /// HTTP/2 over cleartext.
+    H2c,
+    /// gRPC, proxied as HTTP/2 over cleartext.
+    Grpc,
+}
+
+impl AppProtocol {
+    /// Parses a protocol name (`http`, `h2c` or `grpc`).
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "http" => Ok(AppProtocol::Http),
+            "h2c" => Ok(AppProtocol::H2c),
+            "grpc" => Ok(AppProtocol::Grpc),
+            other => Err(format!("Unknown protocol: {}", other)),
+        }
+    }
+
+    /// Returns the scheme Traefik uses to reach the application.
+    fn scheme(self) -> &'static str {
+        match self {
+            AppProtocol::Http => "http",
+            AppProtocol::H2c | AppProtocol::Grpc => "h2c",
+        }
+    }
+}
+
+/// How an application handles plain HTTP requests.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpPolicy {
+    /// Permanently redirect to HTTPS.
+    Redirect,
+    /// Serve the application over HTTP as well.
+    Serve,
+    /// Only serve the application over HTTPS.
+    Block,
+}
+
+impl HttpPolicy {
+    /// Parses a policy name (`redirect`, `serve` or `block`).
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "redirect" => Ok(HttpPolicy::Redirect),
+            "serve" => Ok(HttpPolicy::Serve),
+            "block" => Ok(HttpPolicy::Block),
+            other => Err(format!("Unknown HTTP policy: {}", other)),
+        }
+    }
+
+    /// Returns the global policy, `domain.http_policy` (default: `serve`).
+    pub fn global() -> Self {
+        config().domain.http_policy
+    }
+}
+
+impl FromStr for HttpPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        HttpPolicy::parse(value)
+    }
+}
+
+impl RoutingConfig {
+    /// Returns the HTTP policy of the app, falling back to the global policy.
+    pub fn effective_http_policy(&self) -> HttpPolicy {
+        self.http_policy.unwrap_or_else(HttpPolicy::global)
+    }
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            port: default_app_port(),
+            domains: Vec::new(),
+            ip_allowlist: Vec::new(),
+            protocol: AppProtocol::Http,
+            http_policy: None,
+            sticky: None,
+            ports: Vec::new(),
+            maintenance: false,
+            middlewares: IndexMap::new(),
+        }
+    }
+}
+
+fn default_app_port() -> String {
+    "3000".to_string()
+}
+
+/// Name of the stack service serving the maintenance page.
+pub const MAINTENANCE_SERVICE: &str = "nephelios-maintenance";
+
+/// Page served, with a 503 status, to apps in maintenance mode.
+const MAINTENANCE_PAGE: &str = "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+<title>Under maintenance</title><style>body{font-family:sans-serif;text-align:center;\
+padding:15vh 1em;color:#333}</style></head><body><h1>Under maintenance</h1>\
+<p>This application is being updated and will be back shortly.</p></body></html>";
+
+/// nginx configuration answering every request with the maintenance page.
+const MAINTENANCE_NGINX_CONF: &str = "server { listen 80; root /usr/share/nginx/html; \
+error_page 503 /maintenance.html; location = /maintenance.html { internal; } \
+location / { add_header Retry-After 300 always; return 503; } }";
+
+/// Adds the maintenance page service to the stack file if it is missing.
+///
+/// The service is a small nginx answering every request with a 503 and the maintenance
+/// page. It only defines a Traefik service, app routers point to it in maintenance mode.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+pub fn ensure_maintenance_service() -> io::Result<()> {
+    update_stack(|stack| {
+        if stack.services.contains_key(MAINTENANCE_SERVICE) {
+            return Ok(());
+        }
+
+        let mut extra = Mapping::new();
+        extra.insert(
+            YamlValue::from("environment"),
+            YamlValue::Sequence(vec![
+                YamlValue::from(format!("MAINTENANCE_PAGE={}", MAINTENANCE_PAGE)),
+                YamlValue::from(format!("MAINTENANCE_CONF={}", MAINTENANCE_NGINX_CONF)),
+            ]),
+        );
+        // `$$` escapes the stack file interpolation, the shell sees `$`
+        extra.insert(
+            YamlValue::from("command"),
+            YamlValue::Sequence(vec![
+                YamlValue::from("sh"),
+                YamlValue::from("-c"),
+                YamlValue::from(
+                    "printf '%s' \"$$MAINTENANCE_PAGE\" > /usr/share/nginx/html/maintenance.html \
+                     && printf '%s' \"$$MAINTENANCE_CONF\" > /etc/nginx/conf.d/default.conf \
+                     && exec nginx -g 'daemon off;'",
+                ),
+            ]),
+        );
+
+        let service = Service {
+            image: Some("nginx:alpine".to_string()),
+            deploy: Some(Deploy {
+                mode: Some("replicated".to_string()),
+                replicas: Some(1),
+                labels: vec![
+                    "traefik.enable=true".to_string(),
+                    format!(
+                        "traefik.http.services.{}.loadbalancer.server.port=80",
+                        MAINTENANCE_SERVICE
+                    ),
+                ],
+                ..Default::default()
+            }),
+            networks: vec!["nephelios_overlay".to_string()],
+            extra,
+        };
+
+        stack
+            .services
+            .insert(MAINTENANCE_SERVICE.to_string(), service);
+        Ok(())
+    })
+}
+
+/// Validates and normalizes a custom domain name.
+///
+/// # Arguments
+///
+/// * `domain` - The domain name to validate.
+///
+/// # Returns
+/// * `Ok(String)` containing the lowercase domain.
+/// * `Err(String)` if the domain is not a valid host name.
+pub fn normalize_domain(domain: &str) -> Result<String, String> {
+    let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+    let valid = !domain.is_empty()
+        && domain.len() <= 253
+        && domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+
+    if valid {
+        Ok(domain)
+    } else {
+        Err(format!("Invalid domain: {}", domain))
+    }
+}
+
+/// Validates and normalizes an IP address or CIDR range.
+///
+/// # Arguments
+///
+/// * `cidr` - The IP address (e.g., "203.0.113.7") or CIDR range (e.g., "10.0.0.0/8").
+///
+/// # Returns
+/// * `Ok(String)` containing the normalized range.
+/// * `Err(String)` if the value is not a valid address or range.
+pub fn normalize_cidr(cidr: &str) -> Result<String, String> {
+    let cidr = cidr.trim();
+    let (addr, prefix) = match cidr.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (cidr, None),
+    };
+
+    let addr: IpAddr = addr
+        .parse()
+        .map_err(|_| format!("Invalid IP address or CIDR range: {}", cidr))?;
+    let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+
+    match prefix {
+        None => Ok(addr.to_string()),
+        Some(prefix) => match prefix.parse::<u8>() {
+            Ok(prefix) if prefix <= max_prefix => Ok(format!("{}/{}", addr, prefix)),
+            _ => Err(format!("Invalid IP address or CIDR range: {}", cidr)),
+        },
+    }
+}
+
+/// Checks whether a label is generated from the routing configuration.
+fn is_routing_label(label: &str) -> bool {
+    label.starts_with("traefik.") || label.starts_with("com.myapp.routing.")
+}
+
+/// Builds the `Host` rule matching every given host.
+fn host_rule(hosts: &[String]) -> String {
+    hosts
+        .iter()
+        .map(|host| format!("Host(`{}`)", host))
+        .collect::<Vec<_>>()
+        .join(" || ")
+}
+
+/// Adds the labels of a Traefik service forwarding to a container port.
+fn push_service(labels: &mut Vec<String>, service: &str, port: &str, protocol: AppProtocol) {
+    labels.push(format!(
+        "traefik.http.services.{}.loadbalancer.server.port={}",
+        service, port
+    ));
+    if protocol != AppProtocol::Http {
+        labels.push(format!(
+            "traefik.http.services.{}.loadbalancer.server.scheme={}",
+            service,
+            protocol.scheme()
+        ));
+    }
+}
+
+/// Adds the labels of an HTTPS router, and of its `<router>-http` counterpart on the `web`
+/// entrypoint when plain HTTP is not blocked.
+///
+/// # Arguments
+///
+/// * `labels` - The labels, appended to.
+/// * `router` - The name of the HTTPS router.
+/// * `rule` - The rule matched by both routers.
+/// * `service` - The Traefik service the routers forward to.
+/// * `middlewares` - The middlewares of the HTTPS router.
+/// * `http_middlewares` - The middlewares of the HTTP router, `None` if HTTP is blocked.
+fn push_routers(
+    labels: &mut Vec<String>,
+    router: &str,
+    rule: &str,
+    service: &str,
+    middlewares: &[String],
+    http_middlewares: Option<&[String]>,
+) {
+    labels.extend([
+        format!("traefik.http.routers.{}.rule={}", router, rule),
+        format!("traefik.http.routers.{}.entrypoints=websecure", router),
+        format!(
+            "traefik.http.routers.{}.tls.certresolver=myresolver",
+            router
+        ),
+        format!("traefik.http.routers.{}.service={}", router, service),
+    ]);
+    if !middlewares.is_empty() {
+        labels.push(format!(
+            "traefik.http.routers.{}.middlewares={}",
+            router,
+            middlewares.join(",")
+        ));
+    }
+
+    if let Some(http_middlewares) = http_middlewares {
+        let http_router = format!("{}-http", router);
+        labels.extend([
+            format!("traefik.http.routers.{}.rule={}", http_router, rule),
+            format!("traefik.http.routers.{}.entrypoints=web", http_router),
+            format!("traefik.http.routers.{}.service={}", http_router, service),
+        ]);
+        if !http_middlewares.is_empty() {
+            labels.push(format!(
+                "traefik.http.routers.{}.middlewares={}",
+                http_router,
+                http_middlewares.join(",")
+            ));
+        }
+    }
+}
+
+/// Builds the Traefik labels of an application service from its routing configuration.
+///
+/// The app gets an HTTPS router named after it, plus an `<app>-http` router on the `web`
+/// entrypoint unless plain HTTP is blocked. Each additional port gets its own
+/// `<app>-port-<name>` router and service pair.
+///
+/// # Arguments
+///
+/// * `app` - The name of the application.
+/// * `routing` - The routing configuration of the application.
+///
+/// # Returns
+/// The list of `key=value` labels.
+fn routing_labels(app: &str, routing: &RoutingConfig) -> Vec<String> {
+    let hosts: Vec<String> = std::iter::once(format!("{}.{}", app, config().domain.base))
+        .chain(routing.domains.iter().cloned())
+        .collect();
+
+    let mut labels = vec!["traefik.enable=true".to_string()];
+    push_service(&mut labels, app, &routing.port, routing.protocol);
+
+    if let Some(sticky) = &routing.sticky {
+        let cookie = format!("traefik.http.services.{}.loadbalancer.sticky.cookie", app);
+        labels.push(format!("{}=true", cookie));
+        if let Some(name) = &sticky.cookie_name {
+            labels.push(format!("{}.name={}", cookie, name));
+        }
+        labels.push(format!("{}.secure={}", cookie, sticky.secure));
+        labels.push(format!("{}.httponly={}", cookie, sticky.http_only));
+        if let Some(same_site) = &sticky.same_site {
+            labels.push(format!("{}.samesite={}", cookie, same_site));
+        }
+    }
+
+    let mut middlewares = Vec::new();
+
+    if !routing.ip_allowlist.is_empty() {
+        let name = format!("{}-ipallowlist", app);
+        labels.push(format!(
+            "traefik.http.middlewares.{}.ipwhitelist.sourcerange={}",
+            name,
+            routing.ip_allowlist.join(",")
+        ));
+        middlewares.push(name);
+    }
+
+    // Definitions are validated when they are set, invalid ones are skipped here
+    for (name, fragments) in render_middlewares(&routing.middlewares).unwrap_or_default() {
+        let name = format!("{}-{}", app, name);
+        labels.extend(
+            fragments
+                .iter()
+                .map(|fragment| format!("traefik.http.middlewares.{}.{}", name, fragment)),
+        );
+        middlewares.push(name);
+    }
+
+    let http_policy = routing.effective_http_policy();
+    let redirect = vec![format!("{}-https-redirect", app)];
+    if http_policy == HttpPolicy::Redirect {
+        labels.push(format!(
+            "traefik.http.middlewares.{}.redirectscheme.scheme=https",
+            redirect[0]
+        ));
+        labels.push(format!(
+            "traefik.http.middlewares.{}.redirectscheme.permanent=true",
+            redirect[0]
+        ));
+    }
+    let http_middlewares = |middlewares: &[String]| -> Option<Vec<String>> {
+        match http_policy {
+            HttpPolicy::Block => None,
+            HttpPolicy::Serve => Some(middlewares.to_vec()),
+            HttpPolicy::Redirect => Some(redirect.clone()),
+        }
+    };
+
+    // In maintenance mode every router is swapped to the maintenance page
+    let target = |service: &str| {
+        if routing.maintenance {
+            MAINTENANCE_SERVICE.to_string()
+        } else {
+            service.to_string()
+        }
+    };
+
+    push_routers(
+        &mut labels,
+        app,
+        &host_rule(&hosts),
+        &target(app),
+        &middlewares,
+        http_middlewares(&middlewares).as_deref(),
+    );
+
+    for port in &routing.ports {
+        let name = format!("{}-port-{}", app, port.name);
+        push_service(&mut labels, &name, &port.port.to_string(), port.protocol);
+
+        let port_hosts: Vec<String> = match &port.subdomain {
+            Some(subdomain) => hosts
+                .iter()
+                .map(|host| format!("{}.{}", subdomain, host))
+                .collect(),
+            None => hosts.clone(),
+        };
+        let mut rule = host_rule(&port_hosts);
+        let mut port_middlewares = middlewares.clone();
+
+        if let Some(prefix) = &port.path_prefix {
+            rule = format!("({}) && PathPrefix(`{}`)", rule, prefix);
+            if port.strip_prefix {
+                let strip = format!("{}-stripprefix", name);
+                labels.push(format!(
+                    "traefik.http.middlewares.{}.stripprefix.prefixes={}",
+                    strip, prefix
+                ));
+                port_middlewares.push(strip);
+            }
+        }
+
+        push_routers(
+            &mut labels,
+            &name,
+            &rule,
+            &target(&name),
+            &port_middlewares,
+            http_middlewares(&port_middlewares).as_deref(),
+        );
+    }
+
+    if !routing.domains.is_empty() {
+        labels.push(format!(
+            "com.myapp.routing.domains={}",
+            routing.domains.join(",")
+        ));
+    }
+
+    labels
+}
+
+/// Builds the Traefik and metadata labels of an application service.
+///
+/// # Arguments
+///
+/// * `app` - The name of the application.
+/// * `metadata` - The application metadata.
+/// * `routing` - The routing configuration of the application.
+///
+/// # Returns
+/// The list of `key=value` labels.
+fn app_labels(app: &str, metadata: &AppMetadata, routing: &RoutingConfig) -> Vec<String> {
+    let mut labels = routing_labels(app, routing);
+    labels.extend([
+        format!("com.myapp.name={}", app),
+        format!("com.myapp.image={}:latest", app),
+        format!("com.myapp.type={}", metadata.app_type),
+        format!("com.myapp.github_url={}", metadata.github_url),
+        format!("com.myapp.domain={}", metadata.domain),
+        format!("com.myapp.created_at={}", metadata.created_at),
+    ]);
+
+    let optional_labels = [
+        ("com.myapp.git_ref", &metadata.git_ref),
+        ("com.myapp.commit_sha", &metadata.commit_sha),
+        ("com.myapp.commit_message", &metadata.commit_message),
+    ];
+    for (key, value) in optional_labels {
+        if let Some(value) = value {
+            labels.push(format!("{}={}", key, sanitize_label_value(value)));
+        }
+    }
+
+    labels
+}
+
+/// Regenerates the Traefik labels of an application from its routing configuration.
+///
+/// Labels not generated from the routing configuration are left untouched.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application.
+/// * `routing` - The new routing configuration.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+pub fn update_routing(app_name: &str, routing: &RoutingConfig) -> io::Result<()> {
+    update_stack(|stack| {
+        let service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Application {} not found in the file nephelios.yml",
+                    app_name
+                ),
+            )
+        })?;
+
+        let deploy = service.deploy.get_or_insert_with(Deploy::default);
+        let mut labels = routing_labels(app_name, routing);
+        labels.extend(
+            deploy
+                .labels
+                .drain(..)
+                .filter(|label| !is_routing_label(label)),
+        );
+        deploy.labels = labels;
+        Ok(())
+    })
+}
+
+/// Removes the Traefik labels of an application, so it is no longer routed.
+///
+/// The routing configuration is kept in the deploy request of the app, `update_routing`
+/// restores the labels.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+pub fn disable_routing(app_name: &str) -> io::Result<()> {
+    update_stack(|stack| {
+        let service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Application {} not found in the file nephelios.yml",
+                    app_name
+                ),
+            )
+        })?;
+
+        let deploy = service.deploy.get_or_insert_with(Deploy::default);
+        deploy.labels.retain(|label| !is_routing_label(label));
+        deploy.labels.insert(0, "traefik.enable=false".to_string());
+        Ok(())
+    })
+}
+
 /// Adds the application to the Traefik configuration.
 ///
 /// # Arguments
 ///
 /// * `app_name` - The name of the application to be added.
+/// * `metadata` - The application metadata.
+/// * `routing` - The routing configuration of the application.
+/// * `resources` - The CPU and memory limits and reservations of the service.
 ///
 /// # Returns
 /// * `Ok(())` if the application was successfully added.
 /// * `Err(String)` if there was an error during the addition.
-pub fn add_to_deploy(app: &str, port: &str, metadata: &AppMetadata) -> io::Result<()> {
-    let path = PathBuf::from("./nephelios.yml");
-    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
-
-    let service = app;
-    let image = app;
-    let replicas = 1;
-    let resultat = format!(
-        r#"  {}:
-    image: registry:5000/{}:latest
-    deploy:
-        mode: replicated
-        replicas: {}
-        resources:
-            limits:
-                cpus: "1.5"      # Maximum 1.5 CPU cores
-                memory: 1G       # Maximum 1GB RAM
-            reservations:
-                cpus: "0.5"      # Reserve at least 0.5 CPU cores
-                memory: 256M     # Reserve at least 256MB RAM
-        labels:
-          - "traefik.enable=true"
-          - "traefik.http.routers.{}.rule=Host(`{}.localhost`)"
-          - "traefik.http.routers.{}.entrypoints=web,websecure"
-          - "traefik.http.routers.{}.tls.certresolver=myresolver"
-          - "traefik.http.services.{}.loadbalancer.server.port={}"
-          - "com.myapp.name={}"
-          - "com.myapp.image={}:latest"
-          - "com.myapp.type={}"
-          - "com.myapp.github_url={}"
-          - "com.myapp.domain={}"
-          - "com.myapp.created_at={}"
-    networks:
-        - nephelios_overlay
-
-"#,
-        service, image, replicas, service, app, service, service, service, port, app, image, metadata.app_type, metadata.github_url, metadata.domain, metadata.created_at
-    );
+pub fn add_to_deploy(
+    app: &str,
+    metadata: &AppMetadata,
+    routing: &RoutingConfig,
+    resources: Resources,
+) -> io::Result<()> {
+    let service = Service {
+        image: Some(format!("{}:latest", config().registry.repository(app))),
+        deploy: Some(Deploy {
+            mode: Some("replicated".to_string()),
+            replicas: Some(1),
+            resources: Some(resources),
+            labels: app_labels(app, metadata, routing),
+            ..Default::default()
+        }),
+        networks: vec!["nephelios_overlay".to_string()],
+        ..Default::default()
+    };
 
-    file.write_all(resultat.as_bytes())?;
-    println!("Contenu ajouté");
+    update_stack(|stack| {
+        stack.services.insert(app.to_string(), service);
+        Ok(())
+    })?;
+    info!("Added {} to the stack file", app);
 
     Ok(())
 }
 
-
 /// Removes the docker-compose configuration for the given application.
 ///
-/// Reads the `docker-compose.yml` file, removes the section corresponding to `app_name`,
-/// and writes the updated content back to the file.
+/// Removes the service corresponding to `app_name` from the stack file.
 ///
 /// # Arguments
 ///
@@ -97,77 +944,431 @@ pub fn add_to_deploy(app: &str, port: &str, metadata: &AppMetadata) -> io::Resul
 ///
 /// A `Result` indicating success or an I/O error.
 pub fn remove_app_compose(app_name: &str) -> io::Result<()> {
-    let path = PathBuf::from("./nephelios.yml");
-    let content = fs::read_to_string(&path)?;
+    update_stack(|stack| {
+        stack.services.shift_remove(app_name);
+        declare_secrets(stack, &[]);
+        Ok(())
+    })
+}
+
+/// Updates the number of replicas for an application in the nephelios.yml file.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application to update.
+/// * `replicas` - The new number of replicas.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+pub fn update_app_replicas(app_name: &str, replicas: u32) -> io::Result<()> {
+    update_stack(|stack| {
+        let service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Application {} not found in the file nephelios.yml",
+                    app_name
+                ),
+            )
+        })?;
+
+        service.deploy.get_or_insert_with(Deploy::default).replicas = Some(replicas);
+        Ok(())
+    })
+}
+
+/// Updates the CPU and memory limits and reservations of an application in the nephelios.yml file.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application to update.
+/// * `resources` - The new limits and reservations.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+pub fn update_app_resources(app_name: &str, resources: Resources) -> io::Result<()> {
+    update_stack(|stack| {
+        let service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Application {} not found in the file nephelios.yml",
+                    app_name
+                ),
+            )
+        })?;
+
+        service.deploy.get_or_insert_with(Deploy::default).resources = Some(resources);
+        Ok(())
+    })
+}
+
+/// Updates the placement constraints and preferences of an application in the nephelios.yml
+/// file.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application to update.
+/// * `placement` - The new placement, `None` to let the service run on any node.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+pub fn update_app_placement(app_name: &str, placement: Option<Placement>) -> io::Result<()> {
+    update_stack(|stack| {
+        let service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Application {} not found in the file nephelios.yml",
+                    app_name
+                ),
+            )
+        })?;
+
+        service.deploy.get_or_insert_with(Deploy::default).placement = placement;
+        Ok(())
+    })
+}
+
+/// Updates how the tasks of an application are replaced in the nephelios.yml file.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application to update.
+/// * `update_config` - The new `deploy.update_config` section.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+pub fn update_app_update_config(app_name: &str, update_config: UpdateConfig) -> io::Result<()> {
+    update_stack(|stack| {
+        let service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Application {} not found in the file nephelios.yml",
+                    app_name
+                ),
+            )
+        })?;
+
+        service
+            .deploy
+            .get_or_insert_with(Deploy::default)
+            .update_config = Some(update_config);
+        Ok(())
+    })
+}
 
+/// Sets the hardening settings of an application service in the nephelios.yml file.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application to update.
+/// * `security` - The new settings, the keys left unset are removed from the service.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+pub fn update_app_security(app_name: &str, security: ServiceSecurity) -> io::Result<()> {
+    let keys = serde_yaml::to_value(&security)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    update_stack(|stack| {
+        let service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Application {} not found in the file nephelios.yml",
+                    app_name
+                ),
+            )
+        })?;
 
-    let mut new_content = String::new();
-    let mut in_service = false;
+        for key in ServiceSecurity::KEYS {
+            service.extra.remove(*key);
+        }
+        if let YamlValue::Mapping(keys) = keys {
+            service.extra.extend(keys);
+        }
+        Ok(())
+    })
+}
+
+/// Sets the named volumes mounted into an application service in the nephelios.yml file.
+///
+/// The volumes are declared in the top-level `volumes` section. Declarations of volumes that
+/// are no longer mounted are kept, so their data is found again if they are mounted back.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application to update.
+/// * `mounts` - The volumes of the application, as pairs of a stack volume name and the path
+///   it is mounted at.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+pub fn update_app_volumes(app_name: &str, mounts: &[(String, String)]) -> io::Result<()> {
+    update_stack(|stack| {
+        let service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Application {} not found in the file nephelios.yml",
+                    app_name
+                ),
+            )
+        })?;
 
-    for line in content.lines() {
-        if line.starts_with("  ") && in_service {
-            continue;
+        let key = YamlValue::String("volumes".to_string());
+        if mounts.is_empty() {
+            service.extra.remove(&key);
+        } else {
+            let mounts = mounts
+                .iter()
+                .map(|(volume, path)| YamlValue::String(format!("{}:{}", volume, path)))
+                .collect();
+            service.extra.insert(key, YamlValue::Sequence(mounts));
         }
-        if line.starts_with(&format!("  {}:", app_name)) {
-            in_service = true;
-            continue;
+
+        for (volume, _) in mounts {
+            let name = YamlValue::String(volume.clone());
+            if !stack.volumes.contains_key(&name) {
+                stack
+                    .volumes
+                    .insert(name, YamlValue::Mapping(Mapping::new()));
+            }
         }
-        if !line.starts_with("  ") {
-            in_service = false;
+        Ok(())
+    })
+}
+
+/// Declares secrets as external in the top-level `secrets` section of the stack file, and
+/// removes the declarations of the secrets no service mounts anymore.
+///
+/// # Arguments
+///
+/// * `stack` - The stack file.
+/// * `mounts` - The secrets to declare, as pairs of a Docker secret name and a mount target.
+fn declare_secrets(stack: &mut StackFile, mounts: &[(String, String)]) {
+    let key = YamlValue::String("secrets".to_string());
+    let mounted: Vec<String> = stack
+        .services
+        .values()
+        .filter_map(|service| service.extra.get(&key)?.as_sequence())
+        .flatten()
+        .filter_map(|mount| {
+            mount
+                .as_str()
+                .or_else(|| mount.get("source")?.as_str())
+                .map(str::to_string)
+        })
+        .collect();
+
+    let declarations = stack
+        .extra
+        .entry(key.clone())
+        .or_insert_with(|| YamlValue::Mapping(Mapping::new()));
+    if let YamlValue::Mapping(declarations) = declarations {
+        declarations.retain(|name, _| {
+            name.as_str()
+                .is_some_and(|name| mounted.iter().any(|secret| secret == name))
+        });
+        for (secret, _) in mounts {
+            let mut declaration = Mapping::new();
+            declaration.insert("name".into(), secret.as_str().into());
+            declaration.insert("external".into(), true.into());
+            declarations.insert(secret.as_str().into(), YamlValue::Mapping(declaration));
         }
-        if !in_service {
-            new_content.push_str(line);
-            new_content.push('\n');
+        if declarations.is_empty() {
+            stack.extra.remove(&key);
         }
     }
-    
-    let mut file = fs::File::create(&path)?;
-    file.write_all(new_content.as_bytes())?;
+}
 
-    Ok(())
+/// Sets the Docker secrets mounted into an application service in the nephelios.yml file.
+///
+/// The secrets are declared as external in the top-level `secrets` section. Declarations no
+/// service mounts anymore are removed, since a secret cannot be removed while the stack
+/// references it.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application to update.
+/// * `mounts` - The secrets of the application, as pairs of a Docker secret name and the
+///   file name it is mounted as under `/run/secrets`.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+pub fn update_app_secrets(app_name: &str, mounts: &[(String, String)]) -> io::Result<()> {
+    update_stack(|stack| {
+        let service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Application {} not found in the file nephelios.yml",
+                    app_name
+                ),
+            )
+        })?;
+
+        let key = YamlValue::String("secrets".to_string());
+        if mounts.is_empty() {
+            service.extra.remove(&key);
+        } else {
+            let mounts = mounts
+                .iter()
+                .map(|(secret, target)| {
+                    let mut mount = Mapping::new();
+                    mount.insert("source".into(), secret.as_str().into());
+                    mount.insert("target".into(), target.as_str().into());
+                    YamlValue::Mapping(mount)
+                })
+                .collect();
+            service.extra.insert(key, YamlValue::Sequence(mounts));
+        }
+
+        declare_secrets(stack, mounts);
+        Ok(())
+    })
 }
 
-/// Updates the number of replicas for an application in the nephelios.yml file.
+/// Points the service of an application to another image in the nephelios.yml file.
 ///
 /// # Arguments
 ///
 /// * `app_name` - The name of the application to update.
-/// * `replicas` - The new number of replicas.
+/// * `image` - The image reference (e.g., "registry:5000/my-app:r20250101-120000").
 ///
 /// # Returns
 ///
 /// A `Result` indicating success or an I/O error.
-pub fn update_app_replicas(app_name: &str, replicas: u32) -> io::Result<()> {
-    let path = PathBuf::from("./nephelios.yml");
-    
-    if !path.exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            "The file nephelios.yml does not exist"
-        ));
-    }
-    
-    let content = fs::read_to_string(&path)?;    
-    if !content.contains(&format!("{}:", app_name)) {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            format!("Application {} not found in the file nephelios.yml", app_name)        ));
-    }
-    
-    let pattern = format!(r"(?m)^(\s*{}:\s*(?:\r?\n.*?)*?\breplicas:\s*)(\d+)", regex::escape(app_name));    
-    let re = Regex::new(&pattern).map_err(|e| {
-        io::Error::new(io::ErrorKind::InvalidInput, format!("Error while creating the regex: {}", e))    })?;
-    
-    if re.is_match(&content) {
-        let new_content = re.replace_all(&content, |caps: &regex::Captures| {
-            format!("{}{}", &caps[1], replicas)
-        });
-        
-        fs::write(&path, new_content.as_bytes())?;
+pub fn update_app_image(app_name: &str, image: &str) -> io::Result<()> {
+    update_stack(|stack| {
+        let service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Application {} not found in the file nephelios.yml",
+                    app_name
+                ),
+            )
+        })?;
+
+        service.image = Some(image.to_string());
         Ok(())
-    } else {
-        Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            format!("Pattern 'replicas:' not found for the application {}", app_name)        ))
+    })
+}
+
+/// Sets the environment variables of an application service in the nephelios.yml file.
+///
+/// The variables replace the `environment` section of the service, which is removed when
+/// there is none. Values are escaped, so they are passed to the app as they are.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application to update.
+/// * `env` - The environment variables of the application.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+pub fn update_app_environment(app_name: &str, env: &BTreeMap<String, String>) -> io::Result<()> {
+    update_stack(|stack| {
+        let service = stack.services.get_mut(app_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Application {} not found in the file nephelios.yml",
+                    app_name
+                ),
+            )
+        })?;
+
+        let key = YamlValue::String("environment".to_string());
+        if env.is_empty() {
+            service.extra.remove(&key);
+        } else {
+            // `$` would be interpolated by `docker stack deploy` from the server environment
+            let environment = env
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        YamlValue::String(name.clone()),
+                        YamlValue::String(value.replace('$', "$$")),
+                    )
+                })
+                .collect::<Mapping>();
+            service.extra.insert(key, YamlValue::Mapping(environment));
+        }
+        Ok(())
+    })
+}
+
+/// Lists the services of the stack that are not applications (e.g., "traefik", "registry").
+///
+/// App names must not collide with them.
+///
+/// # Returns
+///
+/// A `Result` containing the service names, or an I/O error.
+pub fn reserved_service_names() -> io::Result<Vec<String>> {
+    let stack = load_stack()?;
+    let mut names = stack
+        .services
+        .iter()
+        .filter(|(_, service)| {
+            !service.deploy.as_ref().is_some_and(|deploy| {
+                deploy
+                    .labels
+                    .iter()
+                    .any(|label| label.starts_with("com.myapp.name="))
+            })
+        })
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    if !names.iter().any(|name| name == MAINTENANCE_SERVICE) {
+        names.push(MAINTENANCE_SERVICE.to_string());
     }
+    Ok(names)
+}
+
+/// Returns the number of replicas of an application, from the nephelios.yml file.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+///
+/// A `Result` containing the replicas, `None` if the app is not in the stack or has no
+/// replica count, or an I/O error.
+pub fn app_replicas(app_name: &str) -> io::Result<Option<u32>> {
+    let stack = load_stack()?;
+    Ok(stack
+        .services
+        .get(app_name)
+        .and_then(|service| service.deploy.as_ref())
+        .and_then(|deploy| deploy.replicas))
+}
+
+/// Returns the image the service of an application currently runs, from the nephelios.yml file.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+///
+/// A `Result` containing the image, `None` if the app is not in the stack, or an I/O error.
+pub fn app_image(app_name: &str) -> io::Result<Option<String>> {
+    let stack = load_stack()?;
+    Ok(stack
+        .services
+        .get(app_name)
+        .and_then(|service| service.image.clone()))
 }
@@ -0,0 +1,388 @@
+use crate::services::database::{enum_column, with_connection};
+use crate::services::deployment::load_deploy_request;
+use crate::services::helpers::docker_helper::{
+    create_job_service, remove_service, stream_service_logs, wait_for_job,
+};
+use crate::services::helpers::stack_helper::load_stack;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Maximum length of the output kept for a job, in bytes; the end of the output is kept.
+pub const MAX_OUTPUT_LENGTH: usize = 64 * 1024;
+
+/// Number of one-off jobs kept in the history of each app; the oldest ones are deleted first.
+const MAX_JOBS_PER_APP: usize = 100;
+
+/// Time the last log lines of a job have to arrive once its task exited.
+const LOG_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Number of job events buffered for the client before the job waits for it.
+const EVENT_BUFFER: usize = 64;
+
+/// Error recorded on jobs that were still running when Nephelios stopped.
+const INTERRUPTED_ERROR: &str = "Interrupted by a restart of Nephelios";
+
+const JOB_COLUMNS: &str = "id, app_name, command, initiator, status, started_at, finished_at, \
+     exit_code, output, error";
+
+/// The state of a one-off job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    /// The command exited with code 0.
+    Succeeded,
+    /// The command exited with another code, or could not be started.
+    Failed,
+    /// The command was still running after the timeout and was stopped.
+    TimedOut,
+}
+
+impl JobStatus {
+    /// The name of the status, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+            JobStatus::TimedOut => "timed_out",
+        }
+    }
+}
+
+/// A command run to completion in the image of an app (e.g., a migration).
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: String,
+    pub app_name: String,
+    pub command: String,
+    /// Who started the job (e.g., "api-key:1a2b3c4d").
+    pub initiator: String,
+    pub status: JobStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The exit code of the command, once it exited.
+    pub exit_code: Option<i64>,
+    /// The end of the logs of the command, at most `MAX_OUTPUT_LENGTH` bytes.
+    pub output: Option<String>,
+    /// Why the command could not be run, if it could not.
+    pub error: Option<String>,
+}
+
+/// An event of a one-off job, streamed to the client that started it.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobEvent {
+    /// The job service was created, always the first event.
+    Started { job: Job },
+    /// A line of the logs of the command.
+    Output { data: String },
+    /// The outcome of the command, always the last event.
+    Exit {
+        exit_code: Option<i64>,
+        timed_out: bool,
+        error: Option<String>,
+    },
+}
+
+/// Returns the name of the Swarm service of a job, without the `nephelios_` prefix.
+///
+/// App names cannot contain underscores, so job services never collide with apps.
+pub fn job_service_name(id: &str) -> String {
+    format!("job_{}", id.replace('-', ""))
+}
+
+/// Returns the image an app runs and its environment, to run jobs with.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok((String, BTreeMap))` - The image of the app service and the environment of the app.
+/// * `Err(String)` - If the app is unknown or not deployed.
+pub fn job_image(app_name: &str) -> Result<(String, BTreeMap<String, String>), String> {
+    let request =
+        load_deploy_request(app_name).ok_or_else(|| format!("App {} not found", app_name))?;
+    let image = load_stack()
+        .map_err(|e| format!("Failed to read the stack file: {}", e))?
+        .services
+        .get(app_name)
+        .and_then(|service| service.image.clone())
+        .ok_or_else(|| format!("App {} is not deployed", app_name))?;
+    Ok((image, request.app_environment()))
+}
+
+/// Keeps the end of an output, so it fits in `MAX_OUTPUT_LENGTH` bytes.
+pub fn truncate_output(output: String) -> String {
+    if output.len() <= MAX_OUTPUT_LENGTH {
+        return output;
+    }
+    let mut start = output.len() - MAX_OUTPUT_LENGTH;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    output[start..].to_string()
+}
+
+/// Reads a job from a row selected with `JOB_COLUMNS`.
+fn job_from_row(row: &Row) -> rusqlite::Result<Job> {
+    Ok(Job {
+        id: row.get(0)?,
+        app_name: row.get(1)?,
+        command: row.get(2)?,
+        initiator: row.get(3)?,
+        status: enum_column(row, 4)?,
+        started_at: row.get(5)?,
+        finished_at: row.get(6)?,
+        exit_code: row.get(7)?,
+        output: row.get(8)?,
+        error: row.get(9)?,
+    })
+}
+
+/// Saves a job, replacing its previous record, and deletes the oldest jobs of its app.
+fn save_job(job: &Job) -> Result<(), String> {
+    with_connection(|connection| {
+        connection.execute(
+            &format!(
+                "INSERT OR REPLACE INTO jobs ({}) VALUES \
+                 (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                JOB_COLUMNS
+            ),
+            params![
+                job.id,
+                job.app_name,
+                job.command,
+                job.initiator,
+                job.status.as_str(),
+                job.started_at,
+                job.finished_at,
+                job.exit_code,
+                job.output,
+                job.error,
+            ],
+        )?;
+        connection.execute(
+            "DELETE FROM jobs WHERE app_name = ?1 AND id NOT IN (
+                 SELECT id FROM jobs WHERE app_name = ?1 ORDER BY started_at DESC LIMIT ?2
+             )",
+            params![job.app_name, MAX_JOBS_PER_APP],
+        )?;
+        Ok(())
+    })
+}
+
+/// Lists the one-off jobs of an app, most recent first.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(Vec<Job>)` - The last `MAX_JOBS_PER_APP` jobs of the app.
+/// * `Err(String)` - If the database could not be read.
+pub fn list_jobs(app_name: &str) -> Result<Vec<Job>, String> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM jobs WHERE app_name = ?1 ORDER BY started_at DESC",
+            JOB_COLUMNS
+        ))?;
+        let jobs = statement.query_map([app_name], job_from_row)?;
+        jobs.collect()
+    })
+}
+
+/// Finds a one-off job of an app by ID.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `id` - The ID of the job.
+///
+/// # Returns
+/// * `Ok(Some(Job))` if the app has this job, `Ok(None)` otherwise.
+/// * `Err(String)` if the database could not be read.
+pub fn find_job(app_name: &str, id: &str) -> Result<Option<Job>, String> {
+    with_connection(|connection| {
+        connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM jobs WHERE id = ?1 AND app_name = ?2",
+                    JOB_COLUMNS
+                ),
+                [id, app_name],
+                job_from_row,
+            )
+            .optional()
+    })
+}
+
+/// Deletes the one-off jobs of an app, when the app is removed.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+pub fn delete_app_jobs(app_name: &str) -> Result<(), String> {
+    with_connection(|connection| {
+        connection.execute("DELETE FROM jobs WHERE app_name = ?1", [app_name])?;
+        Ok(())
+    })
+}
+
+/// Marks the jobs left running by a previous run of Nephelios as failed.
+///
+/// Called at startup, before any job is started.
+pub fn fail_interrupted_jobs() -> Result<(), String> {
+    let interrupted = with_connection(|connection| {
+        connection.execute(
+            "UPDATE jobs SET status = ?1, error = ?2, finished_at = ?3
+             WHERE finished_at IS NULL",
+            params![JobStatus::Failed.as_str(), INTERRUPTED_ERROR, Utc::now()],
+        )
+    })?;
+    if interrupted > 0 {
+        warn!("{} job(s) were interrupted by a restart", interrupted);
+    }
+    Ok(())
+}
+
+/// Appends a log line to the output of a job, keeping its end only.
+fn push_output(output: &mut String, line: &str) {
+    output.push_str(line);
+    output.push('\n');
+    if output.len() > 2 * MAX_OUTPUT_LENGTH {
+        *output = truncate_output(std::mem::take(output));
+    }
+}
+
+/// Forwards the logs of a job until its task exits, then records its outcome.
+///
+/// The job keeps running and is recorded when the client stops reading the events.
+async fn follow_job(mut job: Job, timeout: Duration, events: mpsc::Sender<JobEvent>) {
+    let service = job_service_name(&job.id);
+    let mut output = String::new();
+
+    let result = match stream_service_logs(&service, None, true) {
+        Ok(lines) => {
+            let mut lines = Box::pin(lines.filter_map(|line| async { line.ok() }));
+            let wait = wait_for_job(&service, timeout);
+            tokio::pin!(wait);
+            let result = loop {
+                tokio::select! {
+                    result = &mut wait => break result,
+                    Some(line) = lines.next() => {
+                        push_output(&mut output, &line);
+                        let _ = events.send(JobEvent::Output { data: line }).await;
+                    }
+                }
+            };
+            // The last lines may arrive after the task exited
+            while let Ok(Some(line)) = tokio::time::timeout(LOG_DRAIN_TIMEOUT, lines.next()).await {
+                push_output(&mut output, &line);
+                let _ = events.send(JobEvent::Output { data: line }).await;
+            }
+            result
+        }
+        Err(e) => {
+            warn!("Failed to follow the logs of job {}: {}", job.id, e);
+            wait_for_job(&service, timeout).await
+        }
+    };
+    if let Err(e) = remove_service(&service).await {
+        warn!("Failed to remove job service {}: {}", service, e);
+    }
+
+    let (status, timed_out) = match &result {
+        Ok(Some(0)) => (JobStatus::Succeeded, false),
+        Ok(Some(_)) | Err(_) => (JobStatus::Failed, false),
+        Ok(None) => (JobStatus::TimedOut, true),
+    };
+    job.status = status;
+    job.finished_at = Some(Utc::now());
+    job.exit_code = result.as_ref().ok().copied().flatten();
+    job.error = result.err();
+    job.output = Some(truncate_output(output)).filter(|output| !output.is_empty());
+
+    info!(
+        "🏁 Job {} of {} {} (exit code {:?})",
+        job.id,
+        job.app_name,
+        job.status.as_str(),
+        job.exit_code
+    );
+    if let Err(e) = save_job(&job) {
+        error!("❌ Failed to record job {}: {}", job.id, e);
+    }
+    let _ = events
+        .send(JobEvent::Exit {
+            exit_code: job.exit_code,
+            timed_out,
+            error: job.error,
+        })
+        .await;
+}
+
+/// Runs a command to completion in a one-shot Swarm task of the image of an app.
+///
+/// The task runs with the environment of the app on the overlay network, and is not
+/// restarted when the command fails. It is stopped when the timeout expires. The outcome is
+/// recorded in the job history of the app, even if the returned stream is dropped.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `command` - The command, run with `sh -c`.
+/// * `timeout` - How long the command may run.
+/// * `initiator` - Who started the job.
+///
+/// # Returns
+/// * `Ok(Stream)` yielding the `Started` event, the log lines and the `Exit` event.
+/// * `Err(String)` if the app is not deployed or the job could not be started.
+pub async fn start_job(
+    app_name: &str,
+    command: &str,
+    timeout: Duration,
+    initiator: &str,
+) -> Result<impl Stream<Item = JobEvent>, String> {
+    let (image, environment) = job_image(app_name)?;
+    let mut job = Job {
+        id: Uuid::new_v4().to_string(),
+        app_name: app_name.to_string(),
+        command: command.to_string(),
+        initiator: initiator.to_string(),
+        status: JobStatus::Running,
+        started_at: Utc::now(),
+        finished_at: None,
+        exit_code: None,
+        output: None,
+        error: None,
+    };
+    save_job(&job)?;
+
+    let service = job_service_name(&job.id);
+    if let Err(e) = create_job_service(&service, app_name, &image, &environment, command).await {
+        job.status = JobStatus::Failed;
+        job.finished_at = Some(Utc::now());
+        job.error = Some(e.clone());
+        if let Err(e) = save_job(&job) {
+            error!("❌ Failed to record job {}: {}", job.id, e);
+        }
+        return Err(e);
+    }
+    info!("🏃 Started job {} of {}: {}", job.id, app_name, command);
+
+    let (sender, receiver) = mpsc::channel(EVENT_BUFFER);
+    let _ = sender.try_send(JobEvent::Started { job: job.clone() });
+    tokio::spawn(follow_job(job, timeout, sender));
+
+    Ok(futures::stream::unfold(
+        receiver,
+        |mut receiver| async move {
+            let event = receiver.recv().await?;
+            Some((event, receiver))
+        },
+    ))
+}
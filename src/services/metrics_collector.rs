@@ -0,0 +1,146 @@
+use crate::config::config;
+use crate::metrics::REGISTRY;
+use crate::services::alerting::evaluate_alerts;
+use crate::services::helpers::docker_helper::{
+    update_disk_metrics, update_metrics, update_replica_metrics, update_swarm_metrics,
+};
+use crate::services::metrics_history::record_snapshot;
+use chrono::{DateTime, Utc};
+use prometheus::proto::Metric;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+/// How often the disk usage metrics are collected; computing the Docker disk usage is slow.
+const DISK_METRICS_INTERVAL: Duration = Duration::from_secs(300);
+
+/// The latest metrics of an app container.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ContainerMetrics {
+    pub app_name: String,
+    /// The Swarm service of the container (e.g., "nephelios_my-app").
+    pub service: String,
+    /// The Swarm task of the container (e.g., "nephelios_my-app.1.x8f2k").
+    pub container: String,
+    /// CPU usage, in percent.
+    pub cpu_percent: f64,
+    /// Memory usage, in MiB.
+    pub memory_mb: f64,
+    /// Total inbound network traffic, in KB.
+    pub network_in_kb: f64,
+    /// Total outbound network traffic, in KB.
+    pub network_out_kb: f64,
+}
+
+/// The metrics of every container, sampled at `timestamp`.
+#[derive(Clone, Serialize)]
+pub struct MetricsSnapshot {
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub timestamp: DateTime<Utc>,
+    pub containers: Vec<ContainerMetrics>,
+}
+
+pub type MetricsSender = broadcast::Sender<MetricsSnapshot>;
+
+/// Returns the value of a label of a metric.
+pub fn metric_label<'a>(metric: &'a Metric, name: &str) -> Option<&'a str> {
+    metric
+        .get_label()
+        .iter()
+        .find(|label| label.get_name() == name)
+        .map(|label| label.get_value())
+}
+
+/// Reads the container metrics last collected by `update_metrics`.
+fn read_container_metrics() -> Vec<ContainerMetrics> {
+    let mut containers: BTreeMap<String, ContainerMetrics> = BTreeMap::new();
+    for family in REGISTRY.gather() {
+        for metric in family.get_metric() {
+            let Some(task) = metric_label(metric, "task") else {
+                continue;
+            };
+
+            let value = metric.get_gauge().get_value();
+            let entry = containers
+                .entry(task.to_string())
+                .or_insert_with(|| ContainerMetrics {
+                    app_name: metric_label(metric, "app").unwrap_or_default().to_string(),
+                    service: metric_label(metric, "service")
+                        .unwrap_or_default()
+                        .to_string(),
+                    container: task.to_string(),
+                    ..Default::default()
+                });
+            match family.get_name() {
+                "container_cpu_usage" => entry.cpu_percent = value,
+                "container_memory_usage_bytes" => entry.memory_mb = value / (1024.0 * 1024.0),
+                "container_network_in_bytes" => entry.network_in_kb = value / 1024.0,
+                "container_network_out_bytes" => entry.network_out_kb = value / 1024.0,
+                _ => {}
+            }
+        }
+    }
+    containers.into_values().collect()
+}
+
+/// Returns the interval between two metrics collections, from `features.metrics_interval`.
+fn metrics_interval() -> Duration {
+    Duration::from_secs(config().features.metrics_interval)
+}
+
+/// Collects the container metrics in the background.
+///
+/// Every `METRICS_INTERVAL` seconds (default 15), the Prometheus gauges are refreshed from
+/// the Docker stats, the app services and the Swarm nodes, so `/metrics` only encodes the
+/// registry, the samples are recorded in the metrics history, checked against the alert
+/// rules and broadcast to the `/ws/metrics` clients.
+///
+/// # Arguments
+///
+/// * `metrics_tx` - Broadcast channel sender of the snapshots
+pub async fn run_metrics_collector(metrics_tx: MetricsSender) {
+    let mut ticker = tokio::time::interval(metrics_interval());
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+
+        if let Err(e) = update_metrics().await {
+            warn!("Failed to update metrics: {}", e);
+            continue;
+        }
+        if let Err(e) = update_replica_metrics().await {
+            warn!("Failed to update replica metrics: {}", e);
+        }
+        if let Err(e) = update_swarm_metrics().await {
+            warn!("Failed to update Swarm metrics: {}", e);
+        }
+
+        let snapshot = MetricsSnapshot {
+            timestamp: Utc::now(),
+            containers: read_container_metrics(),
+        };
+        record_snapshot(&snapshot);
+        evaluate_alerts(&snapshot).await;
+        if metrics_tx.receiver_count() == 0 {
+            continue;
+        }
+        // Clients may disconnect between the count and the send
+        let _ = metrics_tx.send(snapshot);
+    }
+}
+
+/// Collects the disk usage metrics in the background, every `DISK_METRICS_INTERVAL`.
+pub async fn run_disk_metrics_collector() {
+    let mut ticker = tokio::time::interval(DISK_METRICS_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        if let Err(e) = update_disk_metrics().await {
+            warn!("Failed to update disk metrics: {}", e);
+        }
+    }
+}
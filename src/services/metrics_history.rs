@@ -0,0 +1,103 @@
+use crate::services::metrics_collector::MetricsSnapshot;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How long the samples of an app are kept.
+pub const HISTORY_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The resource usage of an app at a point in time, summed over its containers.
+#[derive(Clone, Debug, Serialize)]
+pub struct MetricsSample {
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub timestamp: DateTime<Utc>,
+    /// CPU usage, in percent.
+    pub cpu_percent: f64,
+    /// Memory usage, in MiB.
+    pub memory_mb: f64,
+    /// Number of running containers.
+    pub containers: usize,
+}
+
+lazy_static! {
+    /// Samples of every app, oldest first, kept for `HISTORY_RETENTION`.
+    static ref HISTORY: Mutex<HashMap<String, VecDeque<MetricsSample>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Sums the metrics of the containers of each app of a snapshot.
+///
+/// # Arguments
+///
+/// * `snapshot` - The metrics collected from every container
+///
+/// # Returns
+///
+/// The sample of every app with a running container, by app name.
+pub fn app_samples(snapshot: &MetricsSnapshot) -> HashMap<String, MetricsSample> {
+    let mut samples: HashMap<String, MetricsSample> = HashMap::new();
+    for container in &snapshot.containers {
+        let sample = samples
+            .entry(container.app_name.clone())
+            .or_insert_with(|| MetricsSample {
+                timestamp: snapshot.timestamp,
+                cpu_percent: 0.0,
+                memory_mb: 0.0,
+                containers: 0,
+            });
+        sample.cpu_percent += container.cpu_percent;
+        sample.memory_mb += container.memory_mb;
+        sample.containers += 1;
+    }
+    samples
+}
+
+/// Records a metrics snapshot in the history.
+///
+/// Samples older than `HISTORY_RETENTION` are dropped.
+///
+/// # Arguments
+///
+/// * `snapshot` - The metrics collected from every container
+pub fn record_snapshot(snapshot: &MetricsSnapshot) {
+    let samples = app_samples(snapshot);
+    let cutoff = snapshot.timestamp - HISTORY_RETENTION;
+    let mut history = HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+    for (app_name, sample) in samples {
+        history.entry(app_name).or_default().push_back(sample);
+    }
+    history.retain(|_, samples| {
+        while samples
+            .front()
+            .is_some_and(|sample| sample.timestamp < cutoff)
+        {
+            samples.pop_front();
+        }
+        !samples.is_empty()
+    });
+}
+
+/// Returns the samples of an app over the last `range`, oldest first.
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the app
+/// * `range` - How far back to look
+pub fn app_history(app_name: &str, range: Duration) -> Vec<MetricsSample> {
+    let since = Utc::now() - range;
+    HISTORY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(app_name)
+        .map(|samples| {
+            samples
+                .iter()
+                .filter(|sample| sample.timestamp >= since)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -1,2 +1,30 @@
+pub mod addons;
+pub mod alerting;
+pub mod app_registry;
+pub mod app_secrets;
+pub mod audit_log;
+pub mod auto_redeploy;
+pub mod autoscaler;
+pub mod backup;
+pub mod compose;
+pub mod cron_jobs;
+pub mod database;
+pub mod deployment;
+pub mod deployment_history;
+pub mod deployment_tracker;
+pub mod email_notifications;
+pub mod events;
+pub mod garbage_collection;
 pub mod helpers;
+pub mod jobs;
+pub mod metrics_collector;
+pub mod metrics_history;
+pub mod node_maintenance;
+pub mod quotas;
+pub mod reconciliation;
+pub mod scheduled_scaling;
+pub mod soft_delete;
+pub mod templates;
+pub mod volume_backup;
+pub mod webhooks;
 pub mod websocket;
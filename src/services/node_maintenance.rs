@@ -0,0 +1,157 @@
+use crate::services::events::{publish, Event};
+use crate::services::helpers::docker_helper::{
+    find_swarm_node, set_node_availability, update_node_labels, NodeInfo,
+};
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Time between two checks of the tasks left on a draining node.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Time after which a drain is no longer followed, e.g. when a task cannot be stopped.
+const DRAIN_WATCH_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+lazy_static! {
+    /// IDs of the nodes whose drain is being followed.
+    static ref WATCHED_DRAINS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Sets the availability of a node and publishes the change.
+///
+/// Draining a node also follows its tasks until none is left running, publishing a
+/// `NodeDrainProgress` event whenever their number changes and `NodeDrained` at the end.
+///
+/// # Arguments
+/// * `node` - The node to update.
+/// * `availability` - `active` or `drain`.
+///
+/// # Returns
+/// * `Ok(NodeInfo)` - The node after the update.
+/// * `Err(String)` - If the node could not be updated.
+pub async fn change_node_availability(
+    node: &NodeInfo,
+    availability: &str,
+) -> Result<NodeInfo, String> {
+    set_node_availability(&node.id, availability).await?;
+    info!("🖥️ Node {} is now {}", node.hostname, availability);
+    publish(Event::NodeAvailabilityChanged {
+        node_id: node.id.clone(),
+        hostname: node.hostname.clone(),
+        availability: availability.to_string(),
+    });
+
+    if availability == "drain" {
+        spawn_drain_watch(node.id.clone(), node.hostname.clone());
+    }
+
+    Ok(find_swarm_node(&node.id)
+        .await?
+        .unwrap_or_else(|| NodeInfo {
+            availability: availability.to_string(),
+            ..node.clone()
+        }))
+}
+
+/// Sets and removes labels of a node and publishes the change.
+///
+/// # Arguments
+/// * `node` - The node to update.
+/// * `set` - The labels to add or overwrite.
+/// * `remove` - The names of the labels to remove, which must exist on the node.
+///
+/// # Returns
+/// * `Ok(NodeInfo)` - The node after the update.
+/// * `Err(String)` - If the node could not be updated.
+pub async fn change_node_labels(
+    node: &NodeInfo,
+    set: &BTreeMap<String, String>,
+    remove: &[String],
+) -> Result<NodeInfo, String> {
+    update_node_labels(&node.id, set, remove).await?;
+    let updated = find_swarm_node(&node.id).await?.unwrap_or_else(|| {
+        let mut updated = node.clone();
+        for name in remove {
+            updated.labels.remove(name);
+        }
+        updated.labels.extend(set.clone());
+        updated
+    });
+
+    info!("🖥️ Updated the labels of node {}", node.hostname);
+    publish(Event::NodeLabelsChanged {
+        node_id: updated.id.clone(),
+        hostname: updated.hostname.clone(),
+        labels: updated.labels.clone(),
+    });
+    Ok(updated)
+}
+
+/// Follows the drain of a node in the background, unless it is already followed.
+fn spawn_drain_watch(node_id: String, hostname: String) {
+    {
+        let mut watched = WATCHED_DRAINS.lock().unwrap_or_else(|e| e.into_inner());
+        if !watched.insert(node_id.clone()) {
+            return;
+        }
+    }
+
+    tokio::spawn(async move {
+        watch_drain(&node_id, &hostname).await;
+        WATCHED_DRAINS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&node_id);
+    });
+}
+
+/// Publishes the progress of the drain of a node until no task runs on it.
+///
+/// Stops early when the node is made available again, removed, or still runs tasks after
+/// `DRAIN_WATCH_TIMEOUT`.
+async fn watch_drain(node_id: &str, hostname: &str) {
+    let started = Instant::now();
+    let mut last_remaining = None;
+
+    while started.elapsed() < DRAIN_WATCH_TIMEOUT {
+        let node = match find_swarm_node(node_id).await {
+            Ok(Some(node)) => node,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Failed to follow the drain of node {}: {}", hostname, e);
+                tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+                continue;
+            }
+        };
+        if node.availability != "drain" {
+            return;
+        }
+
+        if node.tasks == 0 {
+            info!("🖥️ Node {} is drained", hostname);
+            publish(Event::NodeDrained {
+                node_id: node_id.to_string(),
+                hostname: hostname.to_string(),
+            });
+            return;
+        }
+        if last_remaining != Some(node.tasks) {
+            last_remaining = Some(node.tasks);
+            publish(Event::NodeDrainProgress {
+                node_id: node_id.to_string(),
+                hostname: hostname.to_string(),
+                tasks_remaining: node.tasks,
+            });
+        }
+
+        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+    }
+
+    warn!(
+        "Node {} still runs tasks {} minutes after being drained",
+        hostname,
+        DRAIN_WATCH_TIMEOUT.as_secs() / 60
+    );
+}
@@ -0,0 +1,290 @@
+use crate::auth::{Principal, Role};
+use crate::config::{config, QuotaLimits};
+use crate::services::app_registry::MISSING_STATUS;
+use crate::services::database::with_connection;
+use crate::services::deployment::{load_deploy_request, parse_memory, DeployRequest};
+use crate::services::helpers::traefik_helper::app_replicas;
+use chrono::{Datelike, TimeZone, Utc};
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+
+/// What an account uses, counted against its quota.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QuotaUsage {
+    pub apps: u32,
+    /// Total CPU reservations of the apps, in cores.
+    pub cpus: f64,
+    /// Total memory reservations of the apps, in bytes.
+    pub memory_bytes: u64,
+    /// Minutes spent deploying the apps in the current month (UTC).
+    pub build_minutes: u64,
+}
+
+/// Why a change was refused by `enforce_quota`.
+#[derive(Debug)]
+pub enum QuotaError {
+    /// The change would exceed the quota of the account, with the reason.
+    Exceeded(String),
+    /// The usage of the account could not be read.
+    Failed(String),
+}
+
+/// A change to an app, checked against the quota of the account owning it.
+pub struct AppPlan<'a> {
+    pub app_name: &'a str,
+    /// CPU and memory reservations of one replica, in cores and bytes.
+    pub reservations: (f64, f64),
+    /// Replicas of the app once the change is applied.
+    pub replicas: u32,
+    /// Whether the change builds and deploys a release, spending build minutes.
+    pub builds: bool,
+}
+
+impl<'a> AppPlan<'a> {
+    /// Plans the deployment of a release of an app, with its current replicas.
+    ///
+    /// # Arguments
+    /// * `request` - The deploy request of the app.
+    pub fn deploy(request: &'a DeployRequest) -> Self {
+        Self {
+            app_name: &request.app_name,
+            reservations: request.resources.reservations(),
+            replicas: current_replicas(&request.app_name),
+            builds: true,
+        }
+    }
+}
+
+/// Returns the replicas of an app in the stack file, `1` for an app that is not deployed yet.
+fn current_replicas(app_name: &str) -> u32 {
+    app_replicas(app_name).ok().flatten().unwrap_or(1)
+}
+
+/// Returns the account owning an app, if the app was assigned to one.
+fn app_owner(app_name: &str) -> Result<Option<String>, String> {
+    with_connection(|connection| {
+        connection
+            .query_row(
+                "SELECT account FROM app_owners WHERE app_name = ?1",
+                [app_name],
+                |row| row.get(0),
+            )
+            .optional()
+    })
+}
+
+/// Assigns an app to the account its usage is counted against.
+fn record_app_owner(app_name: &str, account: &str) -> Result<(), String> {
+    with_connection(|connection| {
+        connection.execute(
+            "INSERT OR IGNORE INTO app_owners (app_name, account) VALUES (?1, ?2)",
+            params![app_name, account],
+        )?;
+        Ok(())
+    })
+}
+
+/// Forgets the owner of an app, when the app is removed.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+pub fn delete_app_owner(app_name: &str) -> Result<(), String> {
+    with_connection(|connection| {
+        connection.execute("DELETE FROM app_owners WHERE app_name = ?1", [app_name])?;
+        Ok(())
+    })
+}
+
+/// Lists the registered apps of an account, soft-deleted ones included since they can be
+/// restored.
+fn owned_apps(account: &str) -> Result<Vec<String>, String> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare(
+            "SELECT owners.app_name FROM app_owners owners
+             JOIN apps ON apps.app_name = owners.app_name
+             WHERE owners.account = ?1 AND apps.status != ?2",
+        )?;
+        let apps = statement.query_map(params![account, MISSING_STATUS], |row| row.get(0))?;
+        apps.collect()
+    })
+}
+
+/// Returns the minutes spent deploying the apps of an account since the start of the month.
+fn build_minutes(account: &str) -> Result<u64, String> {
+    let now = Utc::now();
+    let month_start = Utc
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now);
+    let duration_ms: i64 = with_connection(|connection| {
+        connection.query_row(
+            "SELECT COALESCE(SUM(deployments.duration_ms), 0) FROM deployments
+             JOIN app_owners owners ON owners.app_name = deployments.app_name
+             WHERE owners.account = ?1 AND deployments.created_at >= ?2",
+            params![account, month_start],
+            |row| row.get(0),
+        )
+    })?;
+    Ok(duration_ms.max(0) as u64 / 60_000)
+}
+
+/// Returns the CPU and memory reservations of an app, every replica included.
+fn app_reservations(app_name: &str) -> (f64, f64) {
+    let Some(request) = load_deploy_request(app_name) else {
+        return (0.0, 0.0);
+    };
+    let (cpus, memory) = request.resources.reservations();
+    let replicas = f64::from(current_replicas(app_name));
+    (cpus * replicas, memory * replicas)
+}
+
+/// Returns the usage of an account, leaving out one of its apps.
+///
+/// # Arguments
+/// * `account` - The account.
+/// * `excluded` - An app whose usage is replaced by a planned change, if any.
+///
+/// # Returns
+/// * `Ok((QuotaUsage, Option<(f64, f64)>))` containing the usage of the other apps, and the
+///   reservations of the excluded app if the account owns it.
+/// * `Err(String)` if the database could not be read.
+fn usage_without(
+    account: &str,
+    excluded: Option<&str>,
+) -> Result<(QuotaUsage, Option<(f64, f64)>), String> {
+    let mut usage = QuotaUsage {
+        build_minutes: build_minutes(account)?,
+        ..Default::default()
+    };
+    let mut excluded_reservations = None;
+    let mut memory = 0.0;
+    for app_name in owned_apps(account)? {
+        let reservations = app_reservations(&app_name);
+        if Some(app_name.as_str()) == excluded {
+            excluded_reservations = Some(reservations);
+            continue;
+        }
+        usage.apps += 1;
+        usage.cpus += reservations.0;
+        memory += reservations.1;
+    }
+    usage.memory_bytes = memory as u64;
+    Ok((usage, excluded_reservations))
+}
+
+/// Returns the account of a caller, its limits and its usage.
+///
+/// # Arguments
+/// * `caller` - The name of the caller, as in `Principal`.
+///
+/// # Returns
+/// * `Ok((String, QuotaLimits, QuotaUsage))` describing the quota of the caller.
+/// * `Err(String)` if the usage could not be read.
+pub fn caller_quota(caller: &str) -> Result<(String, QuotaLimits, QuotaUsage), String> {
+    let quotas = &config().quotas;
+    let account = quotas.account(caller);
+    let (usage, _) = usage_without(&account, None)?;
+    Ok((account.clone(), quotas.limits(&account).clone(), usage))
+}
+
+/// Checks a usage against limits.
+///
+/// # Returns
+/// * `Ok(())` if the usage is within the limits.
+/// * `Err(String)` naming the first limit exceeded.
+fn check_limits(account: &str, limits: &QuotaLimits, usage: &QuotaUsage) -> Result<(), String> {
+    let exceeded = |what: String| format!("Quota of {} exceeded: {}", account, what);
+    if let Some(max_apps) = limits.max_apps {
+        if usage.apps > max_apps {
+            return Err(exceeded(format!("at most {} apps are allowed", max_apps)));
+        }
+    }
+    if let Some(max_cpus) = limits.max_cpus {
+        if usage.cpus > max_cpus {
+            return Err(exceeded(format!(
+                "{:.2} CPUs would be reserved, at most {} are allowed",
+                usage.cpus, max_cpus
+            )));
+        }
+    }
+    if let Some(max_memory) = &limits.max_memory {
+        if parse_memory(max_memory).is_ok_and(|max| usage.memory_bytes as f64 > max) {
+            return Err(exceeded(format!(
+                "{} MB of memory would be reserved, at most {} is allowed",
+                usage.memory_bytes / (1024 * 1024),
+                max_memory
+            )));
+        }
+    }
+    if let Some(max_minutes) = limits.build_minutes {
+        if usage.build_minutes >= max_minutes {
+            return Err(exceeded(format!(
+                "the {} build minutes of this month are spent",
+                max_minutes
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks a change to an app against the quota of the account owning it.
+///
+/// An app is owned by the account of the caller that deployed it first; apps deployed before
+/// quotas existed are assigned to the next caller deploying them. Only the limits the change
+/// grows against are checked, so an account over its quota can still scale down. Changes
+/// made by admins are not limited, and are still counted against the owner.
+///
+/// # Arguments
+/// * `caller` - The authenticated caller, `None` for automated deploys (e.g., GitHub pushes).
+/// * `plan` - The change to the app.
+///
+/// # Returns
+/// * `Ok(())` if the change fits the quota.
+/// * `Err(QuotaError)` if it would exceed the quota, or the usage could not be read.
+pub fn enforce_quota(caller: Option<&Principal>, plan: &AppPlan) -> Result<(), QuotaError> {
+    let quotas = &config().quotas;
+    let owner = app_owner(plan.app_name).map_err(QuotaError::Failed)?;
+    let Some(account) = owner
+        .clone()
+        .or_else(|| caller.map(|caller| quotas.account(&caller.name)))
+    else {
+        return Ok(());
+    };
+
+    if caller.is_none_or(|caller| caller.role < Role::Admin) {
+        let (mut usage, current) =
+            usage_without(&account, Some(plan.app_name)).map_err(QuotaError::Failed)?;
+        let (current_cpus, current_memory) = current.unwrap_or((0.0, 0.0));
+        let replicas = f64::from(plan.replicas);
+        let (cpus, memory) = (
+            plan.reservations.0 * replicas,
+            plan.reservations.1 * replicas,
+        );
+
+        // Limits the change does not grow against are left out of the check
+        if current.is_some() {
+            usage.apps = 0;
+        } else {
+            usage.apps += 1;
+        }
+        usage.cpus = if cpus > current_cpus {
+            usage.cpus + cpus
+        } else {
+            0.0
+        };
+        usage.memory_bytes = if memory > current_memory {
+            usage.memory_bytes + memory as u64
+        } else {
+            0
+        };
+        if !plan.builds {
+            usage.build_minutes = 0;
+        }
+        check_limits(&account, quotas.limits(&account), &usage).map_err(QuotaError::Exceeded)?;
+    }
+
+    if owner.is_none() {
+        record_app_owner(plan.app_name, &account).map_err(QuotaError::Failed)?;
+    }
+    Ok(())
+}
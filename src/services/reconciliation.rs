@@ -0,0 +1,148 @@
+use crate::services::app_registry::{list_registered_apps, sync_app_registry};
+use crate::services::helpers::docker_helper::{deploy_stack_service, list_stack_services};
+use crate::services::helpers::lock_helper::lock_app;
+use crate::services::helpers::stack_helper::load_stack;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::RwLock;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Label of the app services in the stack file, holding the name of their app.
+const APP_NAME_LABEL: &str = "com.myapp.name=";
+
+lazy_static! {
+    /// The report of the last reconciliation.
+    static ref LAST_REPORT: RwLock<Option<ReconcileReport>> = RwLock::new(None);
+    /// Held while a reconciliation runs, so two runs do not create the same services.
+    static ref RECONCILING: Mutex<()> = Mutex::new(());
+}
+
+/// The outcome of a reconciliation of the stack file, the app registry and the Swarm services.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Whether nothing was missing or orphaned.
+    pub in_sync: bool,
+    /// Services of the stack file that had no Swarm service, and were created again.
+    pub recreated: Vec<String>,
+    /// Services of the stack file that could not be created again, with the error.
+    pub failed: BTreeMap<String, String>,
+    /// Swarm services of the stack that are not in the stack file anymore.
+    pub orphaned_services: Vec<String>,
+    /// Registered apps that are not in the stack file anymore.
+    pub orphaned_apps: Vec<String>,
+}
+
+/// Returns the report of the last reconciliation, if one ran.
+pub fn last_reconcile_report() -> Option<ReconcileReport> {
+    LAST_REPORT
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Compares the stack file, the app registry and the Swarm services, and repairs what can be.
+///
+/// Services of the stack file without a Swarm service (e.g., removed by hand, or lost with a
+/// manager) are created again, holding the lock of their app. Swarm services and registered
+/// apps missing from the stack file are only flagged, as they may hold data; orphaned services
+/// can be removed with `POST /system/gc`. The registry is synced afterwards, and the report is
+/// logged and kept for `last_reconcile_report`.
+///
+/// # Returns
+/// * `Ok(ReconcileReport)` describing what was found and done.
+/// * `Err(String)` if the stack file, the services or the registry could not be read.
+pub async fn reconcile() -> Result<ReconcileReport, String> {
+    let _running = RECONCILING.lock().await;
+    let started_at = Utc::now();
+
+    let stack = load_stack().map_err(|e| format!("Failed to read the stack file: {}", e))?;
+    let deployed: HashSet<String> = list_stack_services().await?.into_iter().collect();
+
+    let mut recreated = Vec::new();
+    let mut failed = BTreeMap::new();
+    for (name, service) in &stack.services {
+        if deployed.contains(name) {
+            continue;
+        }
+        let app_name = service.deploy.as_ref().and_then(|deploy| {
+            deploy
+                .labels
+                .iter()
+                .find_map(|label| label.strip_prefix(APP_NAME_LABEL))
+        });
+        let _lock = match app_name {
+            Some(app_name) => match lock_app(app_name).await {
+                Ok(lock) => Some(lock),
+                Err(e) => {
+                    failed.insert(name.clone(), e);
+                    continue;
+                }
+            },
+            None => None,
+        };
+        match deploy_stack_service(name).await {
+            Ok(()) => recreated.push(name.clone()),
+            Err(e) => {
+                failed.insert(name.clone(), e);
+            }
+        }
+    }
+
+    let mut orphaned_services: Vec<String> = deployed
+        .into_iter()
+        .filter(|name| !stack.services.contains_key(name))
+        .collect();
+    orphaned_services.sort();
+    let orphaned_apps: Vec<String> = list_registered_apps()?
+        .into_iter()
+        .map(|app| app.app_name)
+        .filter(|app_name| !stack.services.contains_key(app_name))
+        .collect();
+
+    if !recreated.is_empty() {
+        if let Err(e) = sync_app_registry().await {
+            warn!("Failed to sync app registry: {}", e);
+        }
+    }
+
+    let report = ReconcileReport {
+        started_at,
+        finished_at: Utc::now(),
+        in_sync: recreated.is_empty()
+            && failed.is_empty()
+            && orphaned_services.is_empty()
+            && orphaned_apps.is_empty(),
+        recreated,
+        failed,
+        orphaned_services,
+        orphaned_apps,
+    };
+    log_report(&report);
+    *LAST_REPORT.write().unwrap_or_else(|e| e.into_inner()) = Some(report.clone());
+    Ok(report)
+}
+
+/// Logs what a reconciliation found and did.
+fn log_report(report: &ReconcileReport) {
+    if report.in_sync {
+        info!("✅ Stack file, app registry and Swarm services are in sync");
+        return;
+    }
+    for name in &report.recreated {
+        info!("🔧 Created missing service {} from the stack file", name);
+    }
+    for (name, error) in &report.failed {
+        warn!("Failed to create missing service {}: {}", name, error);
+    }
+    for name in &report.orphaned_services {
+        warn!("Service {} is not in the stack file", name);
+    }
+    for app_name in &report.orphaned_apps {
+        warn!("Registered app {} is not in the stack file", app_name);
+    }
+}
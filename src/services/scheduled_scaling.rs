@@ -0,0 +1,125 @@
+use crate::services::app_registry::{is_deployed, list_registered_apps};
+use crate::services::autoscaler::scale_app;
+use crate::services::deployment::load_deploy_request;
+use crate::services::deployment_tracker::active_deployment_id;
+use crate::services::events::{publish, Event};
+use crate::services::helpers::lock_helper::try_lock_app;
+use crate::services::helpers::traefik_helper::app_replicas;
+use crate::services::quotas::{enforce_quota, AppPlan, QuotaError};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Interval between two evaluations of the scaling schedules.
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Scales the apps with a scaling schedule in the background, at the start and end of their
+/// windows.
+///
+/// The replicas set by the schedule are applied when they change, on startup and when a
+/// schedule is set, so an app scaled by hand keeps its replicas until the next change.
+/// Stopped apps and apps being deployed or restored are left alone, and retried on the next
+/// evaluation.
+pub async fn run_scaling_scheduler() {
+    let mut ticker = tokio::time::interval(SCHEDULER_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // Replicas last applied to each app by its schedule
+    let mut applied: HashMap<String, u32> = HashMap::new();
+
+    loop {
+        ticker.tick().await;
+        if let Err(e) = apply_schedules(&mut applied).await {
+            error!("❌ Scaling scheduler check failed: {}", e);
+        }
+    }
+}
+
+/// Scales the apps whose schedule sets other replicas than the last ones applied.
+///
+/// # Arguments
+/// * `applied` - The replicas last applied to each app by its schedule.
+///
+/// # Returns
+/// * `Ok(())` if the apps could be listed.
+/// * `Err(String)` otherwise. Failures on a single app are logged and skipped.
+async fn apply_schedules(applied: &mut HashMap<String, u32>) -> Result<(), String> {
+    let apps = list_registered_apps()?;
+    let now = Utc::now();
+    let mut scheduled = HashMap::new();
+
+    for app in apps.iter().filter(|app| is_deployed(app)) {
+        let Some(request) = load_deploy_request(&app.app_name) else {
+            continue;
+        };
+        let Some(schedule) = &request.scaling_schedule else {
+            continue;
+        };
+        let desired = schedule.replicas_at(&now);
+        let previous = applied.get(&app.app_name).copied();
+        if previous == Some(desired) {
+            scheduled.insert(app.app_name.clone(), desired);
+            continue;
+        }
+        if let Some(previous) = previous {
+            scheduled.insert(app.app_name.clone(), previous);
+        }
+        if active_deployment_id(&app.app_name).is_some() {
+            continue;
+        }
+        // Held until the replicas are written, so a deployment or a volume restore does not
+        // run in between.
+        let _lock = match try_lock_app(&app.app_name) {
+            Ok(Some(lock)) => lock,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Failed to lock {}: {}", app.app_name, e);
+                continue;
+            }
+        };
+
+        let replicas = match app_replicas(&app.app_name) {
+            Ok(replicas) => replicas.unwrap_or(1),
+            Err(e) => {
+                warn!("Failed to read replicas of {}: {}", app.app_name, e);
+                continue;
+            }
+        };
+        if replicas == 0 {
+            continue;
+        }
+        if replicas != desired {
+            let plan = AppPlan {
+                builds: false,
+                replicas: desired,
+                ..AppPlan::deploy(&request)
+            };
+            if let Err(QuotaError::Exceeded(e) | QuotaError::Failed(e)) = enforce_quota(None, &plan)
+            {
+                warn!(
+                    "Not scaling {} to {} replicas: {}",
+                    app.app_name, desired, e
+                );
+                continue;
+            }
+            if let Err(e) = scale_app(&app.app_name, desired).await {
+                error!("❌ Scheduled scaling of {} failed: {}", app.app_name, e);
+                continue;
+            }
+
+            info!(
+                "🕒 Scaled {} from {} to {} replicas on schedule",
+                app.app_name, replicas, desired
+            );
+            publish(Event::AppScaled {
+                app_name: app.app_name.clone(),
+                replicas: desired,
+            });
+        }
+        scheduled.insert(app.app_name.clone(), desired);
+    }
+
+    // Apps whose schedule was removed are forgotten, a new schedule applies right away
+    *applied = scheduled;
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use crate::config::config;
+use crate::services::addons::remove_app_addons;
+use crate::services::app_registry::{
+    refresh_registered_app, set_app_status, unregister_app, DELETED_STATUS,
+};
+use crate::services::app_secrets::remove_unused_secrets;
+use crate::services::compose::remove_app_compose_services;
+use crate::services::cron_jobs::delete_app_cron_jobs;
+use crate::services::deployment::{
+    delete_deploy_request, load_app_request, load_deploy_request, save_deploy_request,
+};
+use crate::services::email_notifications::delete_app_recipients;
+use crate::services::events::{publish, Event};
+use crate::services::helpers::docker_helper::{deploy_stack_service, remove_service};
+use crate::services::helpers::lock_helper::lock_app;
+use crate::services::helpers::traefik_helper::{
+    app_replicas, disable_routing, remove_app_compose, update_app_replicas, update_routing,
+};
+use crate::services::jobs::delete_app_jobs;
+use crate::services::quotas::delete_app_owner;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// How often expired soft-deleted apps are purged.
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// A soft-deleted application, kept until `purge_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletedApp {
+    pub app_name: String,
+    pub deleted_at: DateTime<Utc>,
+    pub purge_at: DateTime<Utc>,
+    /// The replicas of the app when it was deleted, restored with it.
+    pub replicas: u32,
+}
+
+/// Returns the directory holding the soft-deleted app records.
+fn trash_dir() -> Result<PathBuf, String> {
+    let home = home_dir().ok_or("Failed to find home directory")?;
+    Ok(home.join(".config/nephelios/trash"))
+}
+
+/// Returns the path of the record of a soft-deleted app.
+fn trash_path(app_name: &str) -> Result<PathBuf, String> {
+    Ok(trash_dir()?.join(format!("{}.json", app_name)))
+}
+
+/// Loads the record of a soft-deleted app.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Some(DeletedApp)` if the app is soft-deleted.
+/// * `None` otherwise.
+pub fn load_deleted_app(app_name: &str) -> Option<DeletedApp> {
+    let path = trash_path(app_name).ok()?;
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Lists the soft-deleted apps.
+pub fn list_deleted_apps() -> Result<Vec<DeletedApp>, String> {
+    let dir = trash_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries =
+        fs::read_dir(&dir).map_err(|e| format!("Failed to read trash directory: {}", e))?;
+    Ok(entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect())
+}
+
+/// Writes the record of a soft-deleted app.
+fn save_deleted_app(deleted: &DeletedApp) -> Result<(), String> {
+    let path = trash_path(&deleted.app_name)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create trash directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(deleted)
+        .map_err(|e| format!("Failed to serialize deleted app: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write deleted app: {}", e))
+}
+
+/// Deletes the record of a soft-deleted app.
+pub fn delete_deleted_app(app_name: &str) -> Result<(), String> {
+    fs::remove_file(trash_path(app_name)?)
+        .map_err(|e| format!("Failed to delete the record of app {}: {}", app_name, e))
+}
+
+/// Soft-deletes an application.
+///
+/// Scales the app to zero and removes its routing, keeping its image and settings until
+/// the retention period (`features.soft_delete_retention_days`) expires.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(DeletedApp)` with the restore deadline.
+/// * `Err(String)` if the app is unknown, already deleted, or the stack could not be updated.
+pub async fn soft_delete_app(app_name: &str) -> Result<DeletedApp, String> {
+    let _lock = lock_app(app_name).await?;
+
+    if load_deleted_app(app_name).is_some() {
+        return Err(format!("Application {} is already deleted", app_name));
+    }
+
+    // The settings are needed to restore the routing, store them if they were never stored
+    let request = load_app_request(app_name).await?;
+    save_deploy_request(&request)?;
+
+    let replicas = app_replicas(app_name)
+        .map_err(|e| format!("Failed to read replicas for app {}: {}", app_name, e))?
+        .unwrap_or(1);
+
+    update_app_replicas(app_name, 0)
+        .map_err(|e| format!("Failed to update replicas for app {}: {}", app_name, e))?;
+    disable_routing(app_name)
+        .map_err(|e| format!("Failed to remove routing for app {}: {}", app_name, e))?;
+    deploy_stack_service(app_name)
+        .await
+        .map_err(|e| format!("Failed to deploy app {}: {}", app_name, e))?;
+
+    let deleted_at = Utc::now();
+    let deleted = DeletedApp {
+        app_name: app_name.to_string(),
+        deleted_at,
+        purge_at: deleted_at
+            + ChronoDuration::days(config().features.soft_delete_retention_days.into()),
+        replicas,
+    };
+    save_deleted_app(&deleted)?;
+    set_app_status(app_name, DELETED_STATUS)?;
+
+    info!(
+        "🗑️ Soft-deleted {}, restorable until {}",
+        app_name, deleted.purge_at
+    );
+    publish(Event::AppRemoved {
+        app_name: app_name.to_string(),
+        soft: true,
+    });
+    Ok(deleted)
+}
+
+/// Restores a soft-deleted application with its routing and replicas.
+///
+/// # Arguments
+/// * `deleted` - The record of the soft-deleted application.
+///
+/// # Returns
+/// * `Ok(DeletedApp)` with the record of the deletion that was undone.
+/// * `Err(String)` if the stack could not be updated.
+pub async fn restore_app(deleted: DeletedApp) -> Result<DeletedApp, String> {
+    let app_name = deleted.app_name.as_str();
+    let _lock = lock_app(app_name).await?;
+
+    let request = load_app_request(app_name).await?;
+    update_routing(app_name, &request.routing)
+        .map_err(|e| format!("Failed to update routing for app {}: {}", app_name, e))?;
+    update_app_replicas(app_name, deleted.replicas)
+        .map_err(|e| format!("Failed to update replicas for app {}: {}", app_name, e))?;
+    deploy_stack_service(app_name)
+        .await
+        .map_err(|e| format!("Failed to deploy app {}: {}", app_name, e))?;
+
+    delete_deleted_app(app_name)?;
+    if let Err(e) = refresh_registered_app(app_name).await {
+        warn!("Failed to refresh app {}: {}", app_name, e);
+    }
+
+    info!("♻️ Restored {}", app_name);
+    publish(Event::AppRestored {
+        app_name: app_name.to_string(),
+    });
+    Ok(deleted)
+}
+
+/// Permanently removes an application: its addons, compose services, cron jobs, jobs,
+/// notification recipients, service and stack entry, its unused secrets, its saved deploy
+/// request, and its soft deletion record if any.
+///
+/// The saved request is removed so a new app of the same name neither inherits its settings
+/// nor is tied to its repository.
+///
+/// Holds the lock of the app, so a running deployment finishes first.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+///
+/// # Returns
+/// * `Ok(())` if the app was removed.
+/// * `Err(String)` if a resource of the app could not be removed.
+pub async fn delete_app(app_name: &str) -> Result<(), String> {
+    let _lock = lock_app(app_name).await?;
+
+    remove_app_addons(app_name).await?;
+    remove_app_compose_services(app_name).await?;
+    delete_app_cron_jobs(app_name)?;
+    delete_app_jobs(app_name)?;
+    delete_app_recipients(app_name)?;
+    remove_service(app_name)
+        .await
+        .map_err(|e| format!("Failed to remove container for app {}: {}", app_name, e))?;
+    remove_app_compose(app_name).map_err(|e| {
+        format!(
+            "Failed to remove app compose file for app {}: {}",
+            app_name, e
+        )
+    })?;
+    if let Some(request) = load_deploy_request(app_name) {
+        tokio::spawn(remove_unused_secrets(
+            request.secrets.into_values().collect(),
+        ));
+    }
+    delete_deploy_request(app_name)?;
+    if load_deleted_app(app_name).is_some() {
+        delete_deleted_app(app_name)?;
+    }
+    delete_app_owner(app_name)?;
+    unregister_app(app_name)?;
+
+    publish(Event::AppRemoved {
+        app_name: app_name.to_string(),
+        soft: false,
+    });
+    Ok(())
+}
+
+/// Periodically purges the soft-deleted apps whose retention period expired.
+pub async fn run_soft_delete_purge() {
+    let mut ticker = tokio::time::interval(PURGE_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+
+        let deleted_apps = match list_deleted_apps() {
+            Ok(deleted_apps) => deleted_apps,
+            Err(e) => {
+                error!("❌ Failed to list deleted apps: {}", e);
+                continue;
+            }
+        };
+
+        let now = Utc::now();
+        for deleted in deleted_apps
+            .iter()
+            .filter(|deleted| deleted.purge_at <= now)
+        {
+            match delete_app(&deleted.app_name).await {
+                Ok(()) => info!("🗑️ Purged {}", deleted.app_name),
+                Err(e) => error!("❌ Failed to purge {}: {}", deleted.app_name, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use crate::config::config;
+use crate::services::deployment::{
+    DeployRequest, PlacementConfig, ResourceLimits, RolloutConfig, SecurityOptions, VolumeMount,
+};
+use crate::services::deployment_tracker::DeploymentPriority;
+use crate::services::helpers::traefik_helper::RoutingConfig;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// An environment variable asked for when deploying a template.
+#[derive(Debug, Serialize)]
+pub struct TemplateVariable {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The value used when none is given, `{app_name}` and `{domain}` are replaced.
+    pub default: Option<&'static str>,
+    /// Whether a value must be given, for variables without a default.
+    pub required: bool,
+}
+
+/// A volume of a template, kept across redeploys of the app.
+#[derive(Debug, Serialize)]
+pub struct TemplateVolume {
+    pub name: &'static str,
+    pub path: &'static str,
+}
+
+/// What a template deploys.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TemplateSource {
+    /// A published image, deployed without a build.
+    Image { image: &'static str },
+    /// A repository, built like the apps created with `POST /create`.
+    Repository {
+        github_url: &'static str,
+        app_type: &'static str,
+        install_command: &'static str,
+        build_command: &'static str,
+        run_command: &'static str,
+    },
+}
+
+/// A predefined app, deployed in one call with `POST /templates/{id}/deploy`.
+#[derive(Debug, Serialize)]
+pub struct AppTemplate {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub source: TemplateSource,
+    /// The port the app listens on inside the container.
+    pub port: &'static str,
+    pub cpu_limit: &'static str,
+    pub memory_limit: &'static str,
+    pub volumes: &'static [TemplateVolume],
+    pub variables: &'static [TemplateVariable],
+}
+
+/// The templates of the catalog.
+pub const TEMPLATES: &[AppTemplate] = &[
+    AppTemplate {
+        id: "ghost",
+        name: "Ghost",
+        description: "Publishing platform for blogs and newsletters, with a SQLite database.",
+        source: TemplateSource::Image {
+            image: "ghost:5-alpine",
+        },
+        port: "2368",
+        cpu_limit: "1",
+        memory_limit: "1G",
+        volumes: &[TemplateVolume {
+            name: "content",
+            path: "/var/lib/ghost/content",
+        }],
+        variables: &[
+            TemplateVariable {
+                name: "url",
+                description: "Public URL of the blog, used in links and emails",
+                default: Some("http://{domain}"),
+                required: true,
+            },
+            TemplateVariable {
+                name: "database__client",
+                description: "Database driver",
+                default: Some("sqlite3"),
+                required: true,
+            },
+            TemplateVariable {
+                name: "database__connection__filename",
+                description: "Path of the SQLite database, inside the content volume",
+                default: Some("/var/lib/ghost/content/data/ghost.db"),
+                required: true,
+            },
+            TemplateVariable {
+                name: "mail__from",
+                description: "Sender address of the emails sent by Ghost",
+                default: None,
+                required: false,
+            },
+        ],
+    },
+    AppTemplate {
+        id: "uptime-kuma",
+        name: "Uptime Kuma",
+        description: "Self-hosted monitoring of websites and services, with status pages.",
+        source: TemplateSource::Image {
+            image: "louislam/uptime-kuma:1",
+        },
+        port: "3001",
+        cpu_limit: "0.5",
+        memory_limit: "512M",
+        volumes: &[TemplateVolume {
+            name: "data",
+            path: "/app/data",
+        }],
+        variables: &[],
+    },
+    AppTemplate {
+        id: "node-starter",
+        name: "Node.js starter",
+        description: "Minimal Express app, to fork and build on.",
+        source: TemplateSource::Repository {
+            github_url: "https://github.com/render-examples/express-hello-world",
+            app_type: "nodejs",
+            install_command: "npm install",
+            build_command: "",
+            run_command: "npm start",
+        },
+        port: "3000",
+        cpu_limit: "0.5",
+        memory_limit: "512M",
+        volumes: &[],
+        variables: &[TemplateVariable {
+            name: "PORT",
+            description: "Port the app listens on",
+            default: Some("3000"),
+            required: true,
+        }],
+    },
+    AppTemplate {
+        id: "python-starter",
+        name: "Python starter",
+        description: "Minimal Flask app served by Gunicorn, to fork and build on.",
+        source: TemplateSource::Repository {
+            github_url: "https://github.com/render-examples/flask-hello-world",
+            app_type: "python",
+            install_command: "pip install -r requirements.txt",
+            build_command: "",
+            run_command: "gunicorn --bind 0.0.0.0:8000 app:app",
+        },
+        port: "8000",
+        cpu_limit: "0.5",
+        memory_limit: "512M",
+        volumes: &[],
+        variables: &[],
+    },
+];
+
+/// Finds a template of the catalog by ID.
+pub fn find_template(id: &str) -> Option<&'static AppTemplate> {
+    TEMPLATES.iter().find(|template| template.id == id)
+}
+
+impl AppTemplate {
+    /// Returns the GitHub URL of the template, empty for image templates.
+    pub fn github_url(&self) -> &'static str {
+        match self.source {
+            TemplateSource::Image { .. } => "",
+            TemplateSource::Repository { github_url, .. } => github_url,
+        }
+    }
+
+    /// Lists the required variables that have neither a default nor a given value.
+    pub fn missing_variables(&self, env: &BTreeMap<String, String>) -> Vec<&'static str> {
+        self.variables
+            .iter()
+            .filter(|variable| variable.required && variable.default.is_none())
+            .filter(|variable| env.get(variable.name).is_none_or(|value| value.is_empty()))
+            .map(|variable| variable.name)
+            .collect()
+    }
+
+    /// Builds the deploy request of an app created from the template.
+    ///
+    /// # Arguments
+    /// * `app_name` - The name of the new application.
+    /// * `env` - The values given for the variables of the template, and any other variable.
+    pub fn deploy_request(&self, app_name: &str, env: BTreeMap<String, String>) -> DeployRequest {
+        let domain = format!("{}.{}", app_name, config().domain.base);
+        let mut variables: BTreeMap<String, String> = self
+            .variables
+            .iter()
+            .filter_map(|variable| {
+                let value = variable
+                    .default?
+                    .replace("{app_name}", app_name)
+                    .replace("{domain}", &domain);
+                Some((variable.name.to_string(), value))
+            })
+            .collect();
+        variables.extend(env);
+
+        let (app_type, github_url, install_command, build_command, run_command, image) =
+            match self.source {
+                TemplateSource::Image { image } => ("image", "", "", "", "", Some(image)),
+                TemplateSource::Repository {
+                    github_url,
+                    app_type,
+                    install_command,
+                    build_command,
+                    run_command,
+                } => (
+                    app_type,
+                    github_url,
+                    install_command,
+                    build_command,
+                    run_command,
+                    None,
+                ),
+            };
+
+        // Published images expect to run as their image defines them
+        let security = match image {
+            Some(_) => SecurityOptions::unrestricted(),
+            None => SecurityOptions::default(),
+        };
+
+        DeployRequest {
+            app_name: app_name.to_string(),
+            app_type: app_type.to_string(),
+            github_url: github_url.to_string(),
+            git_ref: None,
+            git_token: None,
+            deploy_key: None,
+            clone_depth: None,
+            recurse_submodules: false,
+            auto_redeploy: false,
+            install_command: install_command.to_string(),
+            run_command: run_command.to_string(),
+            build_command: build_command.to_string(),
+            app_workdir: "/app".to_string(),
+            additional_inputs: HashMap::new(),
+            routing: RoutingConfig {
+                port: self.port.to_string(),
+                ..Default::default()
+            },
+            resources: ResourceLimits {
+                cpu_limit: self.cpu_limit.to_string(),
+                memory_limit: self.memory_limit.to_string(),
+                ..Default::default()
+            },
+            placement: PlacementConfig::default(),
+            update_config: RolloutConfig::default(),
+            security,
+            autoscaling: None,
+            scaling_schedule: None,
+            priority: DeploymentPriority::default(),
+            volumes: self
+                .volumes
+                .iter()
+                .map(|volume| VolumeMount {
+                    name: volume.name.to_string(),
+                    path: volume.path.to_string(),
+                })
+                .collect(),
+            env: variables,
+            secrets: BTreeMap::new(),
+            pending_secrets: BTreeMap::new(),
+            needs_rebuild: false,
+            compose: None,
+            image: image.map(str::to_string),
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use crate::config::config;
+use crate::services::addons::{list_addons, AddonType};
+use crate::services::deployment::DeployRequest;
+use crate::services::helpers::docker_helper::{
+    export_volume, import_volume, local_node_id, scale_service, service_replicas,
+    service_task_nodes,
+};
+use crate::services::helpers::lock_helper::lock_app;
+use chrono::{DateTime, Utc};
+use dirs::home_dir;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Extension of the volume backup archives.
+const BACKUP_EXTENSION: &str = "tar.gz";
+
+/// Time the tasks of a service have to stop before its volume is restored.
+const STOP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Time between two checks of the tasks of a service being stopped.
+const STOP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A volume of an app or of one of its addons.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppVolume {
+    /// The name of the volume for the app, the addon type for addon volumes.
+    pub name: String,
+    /// The path the volume is mounted at in the app containers, `None` for addon volumes.
+    pub path: Option<String>,
+    /// The Docker volume holding the data (e.g., "nephelios_my-app-uploads").
+    pub docker_volume: String,
+    /// The stack service the volume is mounted into.
+    pub service: String,
+    /// The addon the volume belongs to, `None` for app volumes.
+    pub addon: Option<AddonType>,
+}
+
+/// A backup of a volume.
+#[derive(Debug, Clone, Serialize)]
+pub struct VolumeBackup {
+    pub id: String,
+    pub app_name: String,
+    pub volume: String,
+    /// The size of the compressed archive, in bytes.
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Lists the volumes of an app and of its addons.
+///
+/// # Arguments
+/// * `request` - The deploy request of the app.
+///
+/// # Returns
+/// * `Ok(Vec<AppVolume>)` - The app volumes, then the addon volumes.
+/// * `Err(String)` - If the addons could not be read from the stack file.
+pub fn app_volumes(request: &DeployRequest) -> Result<Vec<AppVolume>, String> {
+    let mut volumes: Vec<AppVolume> = request
+        .volumes
+        .iter()
+        .map(|volume| AppVolume {
+            name: volume.name.clone(),
+            path: Some(volume.path.clone()),
+            docker_volume: format!("nephelios_{}", volume.stack_volume(&request.app_name)),
+            service: request.app_name.clone(),
+            addon: None,
+        })
+        .collect();
+
+    for addon in list_addons(&request.app_name)? {
+        if let Some(volume) = addon.volume {
+            volumes.push(AppVolume {
+                name: addon.addon_type.as_str().to_string(),
+                path: None,
+                docker_volume: format!("nephelios_{}", volume),
+                service: addon.service,
+                addon: Some(addon.addon_type),
+            });
+        }
+    }
+    Ok(volumes)
+}
+
+/// Finds a volume of an app or of one of its addons by name.
+///
+/// # Arguments
+/// * `request` - The deploy request of the app.
+/// * `name` - The name of an app volume, or the type of an addon.
+pub fn find_app_volume(request: &DeployRequest, name: &str) -> Result<Option<AppVolume>, String> {
+    Ok(app_volumes(request)?
+        .into_iter()
+        .find(|volume| volume.name == name))
+}
+
+/// Returns the directory holding the backups of a volume.
+fn backup_dir(app_name: &str, volume: &str) -> Result<PathBuf, String> {
+    let root = match &config().backup.volume_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => home_dir()
+            .ok_or("Failed to find home directory")?
+            .join(".config/nephelios/volume-backups"),
+    };
+    Ok(root.join(app_name).join(volume))
+}
+
+/// Whether a backup ID is one generated by `backup_volume`, so it cannot escape the backup
+/// directory.
+fn is_backup_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_digit() || c == '-')
+}
+
+/// Returns the path of a backup archive of a volume, if it exists.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `volume` - The name of the volume.
+/// * `id` - The ID of the backup.
+pub fn volume_backup_path(
+    app_name: &str,
+    volume: &str,
+    id: &str,
+) -> Result<Option<PathBuf>, String> {
+    if !is_backup_id(id) {
+        return Ok(None);
+    }
+    let path = backup_dir(app_name, volume)?.join(format!("{}.{}", id, BACKUP_EXTENSION));
+    Ok(path.is_file().then_some(path))
+}
+
+/// Reads the description of a backup archive.
+fn read_backup(app_name: &str, volume: &str, path: &Path) -> Option<VolumeBackup> {
+    let id = path
+        .file_name()?
+        .to_str()?
+        .strip_suffix(&format!(".{}", BACKUP_EXTENSION))?;
+    if !is_backup_id(id) {
+        return None;
+    }
+    let metadata = fs::metadata(path).ok()?;
+    Some(VolumeBackup {
+        id: id.to_string(),
+        app_name: app_name.to_string(),
+        volume: volume.to_string(),
+        size: metadata.len(),
+        created_at: metadata.modified().ok()?.into(),
+    })
+}
+
+/// Lists the backups of a volume, most recent first.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `volume` - The name of the volume.
+///
+/// # Returns
+/// * `Ok(Vec<VolumeBackup>)` - The backups of the volume.
+/// * `Err(String)` - If the backup directory could not be read.
+pub fn list_volume_backups(app_name: &str, volume: &str) -> Result<Vec<VolumeBackup>, String> {
+    let dir = backup_dir(app_name, volume)?;
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+
+    let mut backups: Vec<VolumeBackup> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| read_backup(app_name, volume, &entry.path()))
+        .collect();
+    // IDs are timestamps, so they sort chronologically
+    backups.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(backups)
+}
+
+/// Checks that a volume can be read from this node.
+///
+/// Volumes are local to the node running the task that mounts them, and backups are taken
+/// on the node running Nephelios.
+///
+/// # Arguments
+/// * `volume` - The volume to check.
+///
+/// # Returns
+/// * `Ok(None)` if the service of the volume runs on this node, or does not run.
+/// * `Ok(Some(String))` describing why the volume cannot be reached.
+/// * `Err(String)` if the tasks of the service could not be read.
+pub async fn volume_location_error(volume: &AppVolume) -> Result<Option<String>, String> {
+    let nodes = service_task_nodes(&volume.service).await?;
+    if nodes.is_empty() {
+        return Ok(None);
+    }
+    let local = local_node_id().await?;
+    if nodes.iter().any(|node| node == &local) {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "Volume {} is on node {}, backups can only be taken on the node running Nephelios; \
+         pin {} to this node with a placement constraint",
+        volume.name,
+        nodes.join(", "),
+        volume.service
+    )))
+}
+
+/// Writes a backup of a volume to the backup directory.
+///
+/// The volume is archived while the app runs, so files being written may be inconsistent
+/// (e.g., database files, restored as after a crash).
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `volume` - The volume to back up.
+///
+/// # Returns
+/// * `Ok(VolumeBackup)` - The new backup.
+/// * `Err(String)` - If the volume could not be archived.
+pub async fn backup_volume(app_name: &str, volume: &AppVolume) -> Result<VolumeBackup, String> {
+    let dir = backup_dir(app_name, &volume.name)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let id = Utc::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+    let path = dir.join(format!("{}.{}", id, BACKUP_EXTENSION));
+    let tmp_path = dir.join(format!("{}.tmp", id));
+    if let Err(e) = export_volume(&volume.docker_volume, &tmp_path).await {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(format!("Failed to back up volume {}: {}", volume.name, e));
+    }
+    fs::rename(&tmp_path, &path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    info!(
+        "💾 Backed up volume {} of {} as {}",
+        volume.name, app_name, id
+    );
+    read_backup(app_name, &volume.name, &path)
+        .ok_or_else(|| format!("Failed to read backup {}", path.display()))
+}
+
+/// Waits until no task of a service is running.
+async fn wait_for_stop(service: &str) -> Result<(), String> {
+    let started = Instant::now();
+    loop {
+        match service_replicas(service).await? {
+            Some((0, _)) | None => return Ok(()),
+            Some(_) if started.elapsed() >= STOP_TIMEOUT => {
+                return Err(format!(
+                    "{} was still running after {} seconds",
+                    service,
+                    STOP_TIMEOUT.as_secs()
+                ))
+            }
+            Some(_) => tokio::time::sleep(STOP_POLL_INTERVAL).await,
+        }
+    }
+}
+
+/// Replaces the content of a volume with a backup.
+///
+/// The service mounting the volume is scaled to zero during the restore, then back to its
+/// replicas, even if the restore failed.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+/// * `volume` - The volume to restore.
+/// * `backup` - The path of the backup archive, from `volume_backup_path`.
+///
+/// # Returns
+/// * `Ok(())` - If the volume was restored.
+/// * `Err(String)` - If the service could not be stopped or the archive extracted.
+pub async fn restore_volume(
+    app_name: &str,
+    volume: &AppVolume,
+    backup: &Path,
+) -> Result<(), String> {
+    let _lock = lock_app(app_name).await?;
+
+    let replicas = service_replicas(&volume.service)
+        .await?
+        .map(|(_, desired)| desired)
+        .unwrap_or(0);
+    if replicas > 0 {
+        scale_service(&volume.service, 0).await?;
+    }
+
+    let result = match wait_for_stop(&volume.service).await {
+        Ok(()) => import_volume(&volume.docker_volume, backup).await,
+        Err(e) => Err(e),
+    };
+
+    if replicas > 0 {
+        if let Err(e) = scale_service(&volume.service, replicas).await {
+            warn!(
+                "Failed to scale {} back to {} replicas: {}",
+                volume.service, replicas, e
+            );
+        }
+    }
+
+    result.map_err(|e| format!("Failed to restore volume {}: {}", volume.name, e))?;
+    info!("💾 Restored volume {} of {}", volume.name, app_name);
+    Ok(())
+}
@@ -0,0 +1,335 @@
+use crate::services::database::with_connection;
+use crate::services::events::{consume_events, Event};
+use crate::services::helpers::crypto_helper::{decrypt_value, encrypt_value, needs_reencryption};
+use crate::services::helpers::github_helper::hmac_sha256_hex;
+use chrono::{DateTime, Utc};
+use openssl::rand::rand_bytes;
+use rusqlite::types::Type;
+use rusqlite::{params, Error, Row};
+use serde::Serialize;
+use serde_json::json;
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+/// How long a webhook may take to answer a delivery.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of attempts of a delivery before it is dropped.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Pause before retrying a failed delivery, multiplied by the number of attempts made.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+const WEBHOOK_COLUMNS: &str = "id, app_name, url, secret, events, created_at";
+
+/// The events delivered to webhooks, by `type`.
+pub const WEBHOOK_EVENTS: &[&str] = &[
+    "deployment_started",
+    "deployment_finished",
+    "app_started",
+    "app_stopped",
+    "app_scaled",
+    "app_restarted",
+    "app_removed",
+    "app_restored",
+    "app_crashed",
+    "alert_fired",
+    "alert_resolved",
+];
+
+/// A URL receiving the lifecycle events of an app, or of every app.
+///
+/// Each delivery is a JSON POST signed with the secret of the webhook: the
+/// `X-Nephelios-Signature` header holds `sha256=<hex HMAC-SHA256 of the body>`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Webhook {
+    pub id: String,
+    /// The app whose events are delivered, every app if `None`.
+    pub app_name: Option<String>,
+    pub url: String,
+    /// Never returned by the API once the webhook is created.
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// The types of the events delivered, every type of `WEBHOOK_EVENTS` if empty.
+    pub events: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    /// Whether the webhook receives an event.
+    fn accepts(&self, event: &Event) -> bool {
+        let kind = event.kind();
+        WEBHOOK_EVENTS.contains(&kind)
+            && (self.events.is_empty() || self.events.iter().any(|event| event == kind))
+            && self
+                .app_name
+                .as_deref()
+                .is_none_or(|app_name| event.app_name() == Some(app_name))
+    }
+}
+
+/// Reads a webhook from a row selected with `WEBHOOK_COLUMNS`, decrypting its secret.
+fn webhook_from_row(row: &Row) -> rusqlite::Result<Webhook> {
+    let secret: String = row.get(3)?;
+    let events: String = row.get(4)?;
+    Ok(Webhook {
+        id: row.get(0)?,
+        app_name: row.get(1)?,
+        url: row.get(2)?,
+        secret: decrypt_value(&secret)
+            .map_err(|e| Error::FromSqlConversionFailure(3, Type::Text, e.into()))?,
+        events: serde_json::from_str(&events).unwrap_or_default(),
+        created_at: row.get(5)?,
+    })
+}
+
+/// Generates a random hexadecimal secret.
+fn generate_secret() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    rand_bytes(&mut bytes).map_err(|e| format!("Failed to generate webhook secret: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Lists the webhooks of an app, or the global webhooks, oldest first.
+///
+/// # Arguments
+/// * `app_name` - The name of the application, the global webhooks if `None`.
+///
+/// # Returns
+/// * `Ok(Vec<Webhook>)` - The webhooks.
+/// * `Err(String)` - If the database could not be read.
+pub fn list_webhooks(app_name: Option<&str>) -> Result<Vec<Webhook>, String> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM webhooks WHERE app_name IS ?1 ORDER BY created_at",
+            WEBHOOK_COLUMNS
+        ))?;
+        let webhooks = statement.query_map([app_name], webhook_from_row)?;
+        webhooks.collect()
+    })
+}
+
+/// Lists the webhooks receiving the events of an app: its own and the global ones.
+fn subscribed_webhooks(app_name: Option<&str>) -> Result<Vec<Webhook>, String> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM webhooks WHERE app_name IS NULL OR app_name = ?1",
+            WEBHOOK_COLUMNS
+        ))?;
+        let webhooks = statement.query_map([app_name], webhook_from_row)?;
+        webhooks.collect()
+    })
+}
+
+/// Registers a webhook.
+///
+/// # Arguments
+/// * `app_name` - The app whose events are delivered, every app if `None`.
+/// * `url` - The URL the events are posted to.
+/// * `secret` - The secret signing the deliveries, generated if `None`.
+/// * `events` - The types of the events delivered, every type if empty.
+///
+/// # Returns
+/// * `Ok(Webhook)` - The stored webhook, with its generated ID and its secret.
+/// * `Err(String)` - If the database could not be updated.
+pub fn create_webhook(
+    app_name: Option<&str>,
+    url: String,
+    secret: Option<String>,
+    events: Vec<String>,
+) -> Result<Webhook, String> {
+    let secret = match secret {
+        Some(secret) => secret,
+        None => generate_secret()?,
+    };
+    let webhook = Webhook {
+        id: Uuid::new_v4().to_string(),
+        app_name: app_name.map(str::to_string),
+        url,
+        secret,
+        events,
+        created_at: Utc::now(),
+    };
+    let events = serde_json::to_string(&webhook.events)
+        .map_err(|e| format!("Failed to serialize webhook events: {}", e))?;
+    let secret = encrypt_value(&webhook.secret)?;
+
+    with_connection(|connection| {
+        connection.execute(
+            &format!(
+                "INSERT INTO webhooks ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                WEBHOOK_COLUMNS
+            ),
+            params![
+                webhook.id,
+                webhook.app_name,
+                webhook.url,
+                secret,
+                events,
+                webhook.created_at,
+            ],
+        )
+    })?;
+    Ok(webhook)
+}
+
+/// Encrypts the stored webhook secrets again with the current master key, when they are in
+/// clear or encrypted with a previous master key.
+///
+/// # Returns
+/// * `Ok(usize)` - The number of secrets encrypted again.
+/// * `Err(String)` - If a secret could not be decrypted, or the database could not be updated.
+pub fn reencrypt_webhook_secrets() -> Result<usize, String> {
+    let stored: Vec<(String, String)> = with_connection(|connection| {
+        let mut statement = connection.prepare("SELECT id, secret FROM webhooks")?;
+        let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    })?;
+
+    let mut reencrypted = 0;
+    for (id, secret) in stored {
+        if !needs_reencryption(&secret) {
+            continue;
+        }
+        let secret = encrypt_value(&decrypt_value(&secret)?)?;
+        with_connection(|connection| {
+            connection.execute(
+                "UPDATE webhooks SET secret = ?1 WHERE id = ?2",
+                params![secret, id],
+            )
+        })?;
+        reencrypted += 1;
+    }
+    Ok(reencrypted)
+}
+
+/// Deletes a webhook.
+///
+/// # Arguments
+/// * `app_name` - The app of the webhook, `None` for a global webhook.
+/// * `id` - The ID of the webhook.
+///
+/// # Returns
+/// * `Ok(true)` if the webhook was deleted, `Ok(false)` if it does not exist.
+/// * `Err(String)` if the database could not be updated.
+pub fn delete_webhook(app_name: Option<&str>, id: &str) -> Result<bool, String> {
+    with_connection(|connection| {
+        let deleted = connection.execute(
+            "DELETE FROM webhooks WHERE id = ?1 AND app_name IS ?2",
+            params![id, app_name],
+        )?;
+        Ok(deleted > 0)
+    })
+}
+
+/// Deletes the webhooks of an app.
+///
+/// # Arguments
+/// * `app_name` - The name of the application.
+fn delete_app_webhooks(app_name: &str) -> Result<(), String> {
+    with_connection(|connection| {
+        connection.execute("DELETE FROM webhooks WHERE app_name = ?1", [app_name])?;
+        Ok(())
+    })
+}
+
+/// Posts a signed event to a webhook, retrying failed deliveries.
+///
+/// # Arguments
+/// * `client` - The HTTP client.
+/// * `webhook` - The webhook.
+/// * `kind` - The type of the event.
+/// * `payload` - The serialized delivery.
+async fn deliver(client: reqwest::Client, webhook: Webhook, kind: &'static str, payload: String) {
+    let signature = match hmac_sha256_hex(&webhook.secret, payload.as_bytes()) {
+        Ok(digest) => format!("sha256={}", digest),
+        Err(e) => {
+            warn!("Failed to sign delivery to webhook {}: {}", webhook.url, e);
+            return;
+        }
+    };
+    let delivery_id = Uuid::new_v4().to_string();
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let result = client
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-Nephelios-Event", kind)
+            .header("X-Nephelios-Delivery", &delivery_id)
+            .header("X-Nephelios-Signature", &signature)
+            .body(payload.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return,
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                warn!(
+                    "Delivery of {} to webhook {} failed, retrying: {}",
+                    kind, webhook.url, e
+                );
+                tokio::time::sleep(RETRY_DELAY * attempt).await;
+            }
+            Err(e) => warn!(
+                "Delivery of {} to webhook {} failed after {} attempts: {}",
+                kind, webhook.url, MAX_ATTEMPTS, e
+            ),
+        }
+    }
+}
+
+/// Posts the lifecycle events published on the event bus to the webhooks of their app and to
+/// the global webhooks.
+///
+/// The webhooks of an app are deleted once its removal was delivered.
+pub async fn run_webhook_dispatcher() {
+    let client = match reqwest::Client::builder().timeout(DELIVERY_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Failed to create webhook client: {}", e);
+            return;
+        }
+    };
+
+    consume_events("webhook dispatcher", |event| {
+        let client = client.clone();
+        async move {
+            let kind = event.kind();
+            if !WEBHOOK_EVENTS.contains(&kind) {
+                return;
+            }
+
+            match subscribed_webhooks(event.app_name()) {
+                Ok(webhooks) => {
+                    let webhooks: Vec<Webhook> = webhooks
+                        .into_iter()
+                        .filter(|webhook| webhook.accepts(&event))
+                        .collect();
+                    if !webhooks.is_empty() {
+                        let payload = json!({
+                            "timestamp": Utc::now(),
+                            "event": event,
+                        })
+                        .to_string();
+                        for webhook in webhooks {
+                            tokio::spawn(deliver(client.clone(), webhook, kind, payload.clone()));
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to list webhooks for {}: {}", kind, e),
+            }
+
+            if let Event::AppRemoved {
+                app_name,
+                soft: false,
+            } = &event
+            {
+                if let Err(e) = delete_app_webhooks(app_name) {
+                    warn!("Failed to delete the webhooks of {}: {}", app_name, e);
+                }
+            }
+        }
+    })
+    .await
+}
@@ -1,72 +1,508 @@
+use crate::auth::{authenticate_token, ws_principal, AuthError, Principal, Role};
+use crate::metrics::WEBSOCKET_CLIENTS;
+use crate::requests::LogsStreamQuery;
+use crate::services::deployment::load_app_request;
+use crate::services::deployment_tracker::{active_deployment_id, record_status};
+use crate::services::events::{consume_events, publish, subscribe, Event};
+use crate::services::helpers::docker_helper::follow_container_logs;
+use crate::services::metrics_collector::{MetricsSender, MetricsSnapshot};
 use chrono::{DateTime, Utc};
 use futures::StreamExt;
 use futures_util::SinkExt;
-use serde::Serialize;
-use serde_json::Value;
-use std::sync::Arc;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::mpsc;
+use tracing::{debug, error, warn};
 use warp::ws::{Message, WebSocket};
-use warp::Filter;
+use warp::{Filter, Reply};
 
-#[derive(Clone, Serialize)]
+/// Version of the `DeploymentStatus` message schema, bumped on incompatible changes.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// What happened during a deployment, serialized with its `type` (e.g., `clone_started`).
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeploymentEvent {
+    /// A redeploy was triggered outside of the API, e.g. by a push or a new commit.
+    RedeployTriggered { reason: String },
+    CloneStarted,
+    CloneProgress {
+        received_objects: usize,
+        total_objects: usize,
+        percent: usize,
+    },
+    CloneSucceeded {
+        commit_sha: Option<String>,
+        commit_message: Option<String>,
+    },
+    BuildStarted,
+    BuildSucceeded,
+    DeployStarted,
+    DeploySucceeded,
+    RollbackStarted { image: String },
+    RollbackSucceeded { image: String, details: Value },
+    /// The new release never became healthy, the app was rolled back to `image`.
+    RolledBack { image: String, reason: String },
+    /// The app is deployed, with its details.
+    AppDeployed { app: Value },
+    /// The autoscaler changed the replicas of the app, whose replicas used `cpu_percent` of
+    /// their CPU reservation on average.
+    Autoscaled {
+        from: u32,
+        to: u32,
+        cpu_percent: f64,
+    },
+    Failed { message: String },
+}
+
+impl DeploymentEvent {
+    /// The legacy status of the event: `in_progress`, `success`, `deployed` or `error`.
+    pub fn status(&self) -> &'static str {
+        match self {
+            DeploymentEvent::RedeployTriggered { .. }
+            | DeploymentEvent::CloneStarted
+            | DeploymentEvent::CloneProgress { .. }
+            | DeploymentEvent::BuildStarted
+            | DeploymentEvent::DeployStarted
+            | DeploymentEvent::RollbackStarted { .. } => "in_progress",
+            DeploymentEvent::CloneSucceeded { .. }
+            | DeploymentEvent::BuildSucceeded
+            | DeploymentEvent::DeploySucceeded
+            | DeploymentEvent::RollbackSucceeded { .. }
+            | DeploymentEvent::Autoscaled { .. } => "success",
+            DeploymentEvent::AppDeployed { .. } => "deployed",
+            DeploymentEvent::RolledBack { .. } | DeploymentEvent::Failed { .. } => "error",
+        }
+    }
+
+    /// The legacy step of the event, or the error message for failures.
+    pub fn step(&self) -> String {
+        match self {
+            DeploymentEvent::RedeployTriggered { reason } => reason.clone(),
+            DeploymentEvent::CloneStarted
+            | DeploymentEvent::CloneProgress { .. }
+            | DeploymentEvent::CloneSucceeded { .. } => "Cloning repository".to_string(),
+            DeploymentEvent::BuildStarted | DeploymentEvent::BuildSucceeded => {
+                "Building Docker image".to_string()
+            }
+            DeploymentEvent::DeployStarted | DeploymentEvent::DeploySucceeded => {
+                "Starting deployment".to_string()
+            }
+            DeploymentEvent::RollbackStarted { image }
+            | DeploymentEvent::RollbackSucceeded { image, .. } => {
+                format!("Rolling back to {}", image)
+            }
+            DeploymentEvent::RolledBack { image, reason } => {
+                format!("Rolled back to {}: {}", image, reason)
+            }
+            DeploymentEvent::AppDeployed { .. } => "deployed_info".to_string(),
+            DeploymentEvent::Autoscaled {
+                from,
+                to,
+                cpu_percent,
+            } => format!(
+                "Autoscaled from {} to {} replicas at {:.0}% CPU",
+                from, to, cpu_percent
+            ),
+            DeploymentEvent::Failed { message } => message.clone(),
+        }
+    }
+
+    /// The legacy `app_deployed` details of the event.
+    fn details(&self) -> Option<Value> {
+        match self {
+            DeploymentEvent::CloneProgress {
+                received_objects,
+                total_objects,
+                percent,
+            } => Some(json!({
+                "received_objects": received_objects,
+                "total_objects": total_objects,
+                "percent": percent,
+            })),
+            DeploymentEvent::CloneSucceeded {
+                commit_sha,
+                commit_message,
+            } => Some(json!({
+                "commit_sha": commit_sha,
+                "commit_message": commit_message,
+            })),
+            DeploymentEvent::RollbackSucceeded { details, .. } => Some(details.clone()),
+            DeploymentEvent::AppDeployed { app } => Some(app.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// A deployment status update, as sent on `/ws`.
+///
+/// `event` is the typed event; `status`, `step` and `app_deployed` are derived from it for
+/// the clients written before it existed.
+#[derive(Clone, Debug, Serialize)]
 pub struct DeploymentStatus {
-    app_name: String,
-    status: String,
-    step: String,
+    /// The schema version of the message, `EVENT_SCHEMA_VERSION`.
+    pub version: u32,
+    pub app_name: String,
+    pub event: DeploymentEvent,
+    pub status: String,
+    pub step: String,
     #[serde(with = "chrono::serde::ts_milliseconds")]
-    timestamp: DateTime<Utc>,
-    app_deployed: Option<Value>
+    pub timestamp: DateTime<Utc>,
+    pub app_deployed: Option<Value>,
+    pub deployment_id: Option<String>,
 }
 
 pub type StatusSender = broadcast::Sender<DeploymentStatus>;
 
+/// Time given to a connection opened without credentials to authenticate.
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often connections are pinged.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time after which a connection that answered no ping is dropped.
+const PONG_TIMEOUT: Duration = Duration::from_secs(75);
+
+/// Number of log lines buffered for a `/ws/logs` connection; reading the container logs
+/// pauses while the buffer is full.
+const LOG_BUFFER_LINES: usize = 256;
+
+/// Close code sent to connections that failed to authenticate.
+const UNAUTHORIZED_CLOSE_CODE: u16 = 4401;
+
+/// Close code sent to connections whose credentials lack the required role.
+const FORBIDDEN_CLOSE_CODE: u16 = 4403;
+
+/// Number of recent status updates of each app replayed to new connections.
+const MAX_REPLAYED_EVENTS: usize = 50;
+
+lazy_static! {
+    /// The recent status updates of each app, oldest first.
+    static ref RECENT_EVENTS: Mutex<HashMap<String, VecDeque<DeploymentStatus>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Keeps a status update to replay it to the connections opened later.
+fn remember_status(status: &DeploymentStatus) {
+    let mut recent = RECENT_EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    let events = recent.entry(status.app_name.clone()).or_default();
+    if events.len() == MAX_REPLAYED_EVENTS {
+        events.pop_front();
+    }
+    events.push_back(status.clone());
+}
+
+/// Returns the recent status updates of the given apps, or of every app when `apps` is
+/// empty, oldest first.
+fn recent_events(apps: &HashSet<String>) -> Vec<DeploymentStatus> {
+    let recent = RECENT_EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut events: Vec<DeploymentStatus> = recent
+        .iter()
+        .filter(|(app_name, _)| apps.is_empty() || apps.contains(*app_name))
+        .flat_map(|(_, events)| events.iter().cloned())
+        .collect();
+    events.sort_by_key(|status| status.timestamp);
+    events
+}
+
+/// Builds the message replaying a past status update, flagged with `"replayed": true`.
+fn replay_message(status: &DeploymentStatus) -> Message {
+    let mut value = serde_json::to_value(status).unwrap_or_default();
+    value["replayed"] = Value::Bool(true);
+    Message::text(value.to_string())
+}
+
+/// Counts a connection in `WEBSOCKET_CLIENTS` while it is alive.
+struct ClientGuard;
+
+impl ClientGuard {
+    fn new() -> Self {
+        WEBSOCKET_CLIENTS.inc();
+        ClientGuard
+    }
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        WEBSOCKET_CLIENTS.dec();
+    }
+}
+
+/// The apps a connection receives updates for, every app when empty.
+type Subscriptions = Arc<RwLock<HashSet<String>>>;
+
+/// A message sent by a client to choose the apps it receives updates for.
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum ClientMessage {
+    Auth { token: String },
+    Subscribe { app: String },
+    Unsubscribe { app: String },
+}
+
+/// Returns whether a connection receives the updates of an app.
+fn is_subscribed(subscriptions: &Subscriptions, app_name: &str) -> bool {
+    let subscriptions = subscriptions.read().unwrap_or_else(|e| e.into_inner());
+    subscriptions.is_empty() || subscriptions.contains(app_name)
+}
+
+/// Applies a subscription message and returns the messages sent back to the client: the
+/// acknowledgement, followed by the recent updates of a newly subscribed app.
+fn handle_client_message(subscriptions: &Subscriptions, text: &str) -> Vec<Message> {
+    let reply = |value: Value| Message::text(value.to_string());
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(e) => return vec![reply(json!({ "error": format!("Invalid message: {}", e) }))],
+    };
+
+    let mut subscriptions = subscriptions.write().unwrap_or_else(|e| e.into_inner());
+    let subscribed = match message {
+        ClientMessage::Auth { .. } => {
+            return vec![reply(json!({ "error": "Already authenticated" }))];
+        }
+        ClientMessage::Subscribe { app } => {
+            let newly_subscribed = subscriptions.insert(app.clone());
+            newly_subscribed.then_some(app)
+        }
+        ClientMessage::Unsubscribe { app } => {
+            subscriptions.remove(&app);
+            None
+        }
+    };
+
+    let mut apps: Vec<&String> = subscriptions.iter().collect();
+    apps.sort();
+    let mut messages = vec![reply(json!({ "subscribed": apps }))];
+    if let Some(app) = subscribed {
+        messages.extend(recent_events(&HashSet::from([app])).iter().map(replay_message));
+    }
+    messages
+}
+
+/// Waits for the first message of a connection opened without credentials and checks the
+/// token it carries (`{"action": "auth", "token": "<key or JWT>"}`).
+///
+/// # Arguments
+/// * `ws_receiver` - The receiving half of the connection.
+/// * `required` - The role required by the route.
+///
+/// # Returns
+/// * `Ok(Principal)` with the authenticated caller.
+/// * `Err((u16, String))` with the close code and reason otherwise.
+async fn authenticate_first_message(
+    ws_receiver: &mut futures::stream::SplitStream<WebSocket>,
+    required: Role,
+) -> Result<Principal, (u16, String)> {
+    let unauthorized = |reason: &str| (UNAUTHORIZED_CLOSE_CODE, reason.to_string());
+
+    let message = match tokio::time::timeout(AUTH_TIMEOUT, ws_receiver.next()).await {
+        Ok(Some(Ok(message))) => message,
+        Ok(_) => return Err(unauthorized("Connection closed before authenticating")),
+        Err(_) => return Err(unauthorized("Authentication timed out")),
+    };
+
+    let token = match message
+        .to_str()
+        .ok()
+        .and_then(|text| serde_json::from_str::<ClientMessage>(text).ok())
+    {
+        Some(ClientMessage::Auth { token }) => token,
+        _ => return Err(unauthorized("The first message must authenticate")),
+    };
+
+    authenticate_token(required, &token)
+        .await
+        .map_err(|e| match e {
+            AuthError::Unauthorized => unauthorized("Invalid API key or token"),
+            AuthError::Forbidden => (FORBIDDEN_CLOSE_CODE, "Insufficient role".to_string()),
+        })
+}
+
+/// Authenticates a connection opened without credentials with its first message, closing
+/// it on failure.
+///
+/// # Returns
+/// Whether the connection is authenticated.
+async fn authenticate_connection(
+    ws_sender: &mut futures::stream::SplitSink<WebSocket, Message>,
+    ws_receiver: &mut futures::stream::SplitStream<WebSocket>,
+    required: Role,
+) -> bool {
+    match authenticate_first_message(ws_receiver, required).await {
+        Ok(principal) => {
+            let reply = json!({ "authenticated": principal.name, "role": principal.role });
+            ws_sender.send(Message::text(reply.to_string())).await.is_ok()
+        }
+        Err((code, reason)) => {
+            warn!("Closing unauthenticated WebSocket connection: {}", reason);
+            let _ = ws_sender.send(Message::close_with(code, reason)).await;
+            let _ = ws_sender.close().await;
+            false
+        }
+    }
+}
+
 /// Handles individual WebSocket connections.
 ///
 /// Splits the WebSocket connection into sender and receiver parts, sets up message
 /// forwarding, and maintains the connection until the client disconnects.
 ///
+/// The recent updates of the subscribed apps are replayed first, flagged with
+/// `"replayed": true`, so clients can render the current state immediately.
+///
+/// Only the updates of the subscribed apps are forwarded, or every update when the
+/// connection has no subscription. Clients change their subscriptions by sending
+/// `{"action": "subscribe", "app": "<name>"}` or `{"action": "unsubscribe", "app": "<name>"}`.
+///
+/// Connections are pinged every `PING_INTERVAL` and dropped when they answer no ping within
+/// `PONG_TIMEOUT`, which also stops their forwarding tasks.
+///
 /// # Arguments
 ///
 /// * `ws` - WebSocket connection
 /// * `status_rx` - Receiver for deployment status updates
-pub async fn handle_ws_connection(ws: WebSocket, status_rx: broadcast::Receiver<DeploymentStatus>) {
+/// * `apps` - Apps the connection is initially subscribed to
+/// * `principal` - The caller authenticated on the upgrade, `None` if it must authenticate
+///   with its first message
+pub async fn handle_ws_connection(
+    ws: WebSocket,
+    status_rx: broadcast::Receiver<DeploymentStatus>,
+    apps: HashSet<String>,
+    principal: Option<Principal>,
+) {
     let (mut ws_sender, mut ws_receiver) = ws.split();
+
+    if principal.is_none()
+        && !authenticate_connection(&mut ws_sender, &mut ws_receiver, Role::Viewer).await
+    {
+        return;
+    }
+
+    let _client = ClientGuard::new();
     let (tx, mut rx) = mpsc::channel(32);
     let mut status_rx = status_rx;
+    let subscriptions: Subscriptions = Arc::new(RwLock::new(apps));
+    let reply_tx = tx.clone();
+    let forwarded_subscriptions = Arc::clone(&subscriptions);
 
     // Forward deployment status updates to WebSocket
-    tokio::task::spawn(async move {
+    let sender_task = tokio::task::spawn(async move {
         while let Some(msg) = rx.recv().await {
             if let Err(e) = ws_sender.send(msg).await {
-                eprintln!("WebSocket send error: {}", e);
+                error!("WebSocket send error: {}", e);
                 break;
             }
         }
     });
 
     // Handle incoming WebSocket messages and broadcast status updates
-    tokio::task::spawn(async move {
+    let status_task = tokio::task::spawn(async move {
+        let replayed = {
+            let apps = forwarded_subscriptions
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone();
+            recent_events(&apps)
+        };
+        // Updates sent while replaying are both replayed and received, forward them once
+        let replayed_until = replayed.last().map(|status| status.timestamp);
+        for status in &replayed {
+            if tx.send(replay_message(status)).await.is_err() {
+                return;
+            }
+        }
+
         while let Ok(status) = status_rx.recv().await {
+            if replayed_until.is_some_and(|until| status.timestamp <= until) {
+                continue;
+            }
+            if !is_subscribed(&forwarded_subscriptions, &status.app_name) {
+                continue;
+            }
             let msg = serde_json::to_string(&status).unwrap();
             if let Err(e) = tx.send(Message::text(msg)).await {
-                eprintln!("Failed to forward status update: {}", e);
+                error!("Failed to forward status update: {}", e);
                 break;
             }
         }
     });
 
-    // Keep connection alive until client disconnects
-    while let Some(result) = ws_receiver.next().await {
-        if let Err(e) = result {
-            eprintln!("WebSocket error: {}", e);
-            break;
+    // Keep connection alive until client disconnects or stops answering pings
+    let mut heartbeat = tokio::time::interval(PING_INTERVAL);
+    let mut last_pong = Instant::now();
+    loop {
+        tokio::select! {
+            result = ws_receiver.next() => {
+                let message = match result {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        error!("WebSocket error: {}", e);
+                        break;
+                    }
+                    None => break,
+                };
+
+                if message.is_pong() {
+                    last_pong = Instant::now();
+                } else if let Ok(text) = message.to_str() {
+                    let replies = handle_client_message(&subscriptions, text);
+                    if !send_all(&reply_tx, replies).await {
+                        warn!("Failed to acknowledge WebSocket message");
+                        break;
+                    }
+                }
+            }
+            _ = heartbeat.tick() => {
+                if last_pong.elapsed() > PONG_TIMEOUT {
+                    warn!("Dropping WebSocket connection that answered no ping");
+                    break;
+                }
+                if reply_tx.send(Message::ping(Vec::new())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    status_task.abort();
+    sender_task.abort();
+}
+
+/// Queues messages for a connection, returning whether every message was queued.
+async fn send_all(tx: &mpsc::Sender<Message>, messages: Vec<Message>) -> bool {
+    for message in messages {
+        if tx.send(message).await.is_err() {
+            return false;
         }
     }
+    true
+}
+
+/// Reads the apps of the `app` query parameter, a comma-separated list.
+fn query_apps(query: &HashMap<String, String>) -> HashSet<String> {
+    query
+        .get("app")
+        .map(|apps| {
+            apps.split(',')
+                .map(str::trim)
+                .filter(|app| !app.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 /// Creates a WebSocket route for handling real-time deployment status updates.
 ///
+/// The `app` query parameter (e.g., `/ws?app=my-app,other-app`) subscribes the connection
+/// to the given apps only.
+///
 /// # Arguments
 ///
 /// * `status_rx` - Receiver for deployment status updates
@@ -80,41 +516,333 @@ pub fn ws_route(
     let status_rx = Arc::new(status_rx);
 
     warp::path("ws")
+        .and(warp::path::end())
+        .and(ws_principal(Role::Viewer))
+        .and(warp::query::<HashMap<String, String>>())
         .and(warp::ws())
-        .map(move |ws: warp::ws::Ws| {
+        .map(move |principal: Option<Principal>, query: HashMap<String, String>, ws: warp::ws::Ws| {
             let status_rx = Arc::clone(&status_rx);
-            ws.on_upgrade(move |socket| handle_ws_connection(socket, status_rx.resubscribe()))
+            let apps = query_apps(&query);
+            ws.on_upgrade(move |socket| {
+                handle_ws_connection(socket, status_rx.resubscribe(), apps, principal)
+            })
         })
 }
 
-/// Sends a deployment status update through the broadcast channel.
+/// Streams the logs of an application over a WebSocket connection.
+///
+/// Each log line is sent as a JSON message (`{"container", "stream", "line"}`). Lines are read
+/// from Docker only as fast as the client receives them. The connection is closed when the
+/// containers stop or the client disconnects.
 ///
 /// # Arguments
 ///
-/// * `sender` - Broadcast channel sender
-/// * `app_name` - Name of the application being deployed
-/// * `status` - Current deployment status
-/// * `step` - Current deployment step
-///
-/// # Errors
-///
-/// Prints error message to stderr if sending fails
-pub async fn send_deployment_status(
-    sender: &StatusSender,
-    app_name: &str,
-    status: &str,
-    step: &str,
-    app_deployed: Option<Value>
+/// * `ws` - WebSocket connection
+/// * `app_name` - Name of the application
+/// * `tail` - Number of past lines of each container sent before following
+/// * `principal` - The caller authenticated on the upgrade, `None` if it must authenticate
+///   with its first message
+async fn handle_logs_connection(
+    ws: WebSocket,
+    app_name: String,
+    tail: u32,
+    principal: Option<Principal>,
+) {
+    let (mut ws_sender, mut ws_receiver) = ws.split();
+
+    if principal.is_none()
+        && !authenticate_connection(&mut ws_sender, &mut ws_receiver, Role::Deployer).await
+    {
+        return;
+    }
+
+    let lines = match follow_container_logs(&app_name, tail).await {
+        Ok(lines) => lines,
+        Err(e) => {
+            let _ = ws_sender.send(Message::text(json!({ "error": e }).to_string())).await;
+            let _ = ws_sender.close().await;
+            return;
+        }
+    };
+
+    let _client = ClientGuard::new();
+    let (tx, mut rx) = mpsc::channel(LOG_BUFFER_LINES);
+
+    // The bounded channel pauses the log reader while the client is slow
+    let reader_task = tokio::task::spawn(async move {
+        futures::pin_mut!(lines);
+        while let Some(line) = lines.next().await {
+            let message = match line {
+                Ok(line) => serde_json::to_string(&line).unwrap_or_default(),
+                Err(e) => json!({ "error": e }).to_string(),
+            };
+            if tx.send(Message::text(message)).await.is_err() {
+                return;
+            }
+        }
+        let _ = tx.send(Message::close()).await;
+    });
+
+    let sender_task = tokio::task::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            let closing = message.is_close();
+            if ws_sender.send(message).await.is_err() || closing {
+                break;
+            }
+        }
+    });
+
+    // Wait for the client to disconnect
+    while let Some(Ok(message)) = ws_receiver.next().await {
+        if message.is_close() {
+            break;
+        }
+    }
+
+    reader_task.abort();
+    sender_task.abort();
+}
+
+/// Creates a WebSocket route streaming the logs of an application live.
+///
+/// This route listens at the `/ws/logs/{name}` path, requires the `deployer` role and
+/// accepts the `tail` query parameter: the number of past lines of each container sent
+/// before following (default: 100).
+///
+/// # Returns
+///
+/// A Filter that handles WebSocket upgrade requests, answering 404 for unknown apps
+pub fn ws_logs_route(
+) -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    warp::path!("ws" / "logs" / String)
+        .and(ws_principal(Role::Deployer))
+        .and(warp::query::<LogsStreamQuery>())
+        .and(warp::ws())
+        .and_then(handle_ws_logs_upgrade)
+}
+
+/// Upgrades a `/ws/logs/{name}` request, or answers 404 if the app is unknown.
+async fn handle_ws_logs_upgrade(
+    app_name: String,
+    principal: Option<Principal>,
+    query: LogsStreamQuery,
+    ws: warp::ws::Ws,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    if let Err(e) = load_app_request(&app_name).await {
+        let reply = warp::reply::json(&json!({ "error": e }));
+        let reply = warp::reply::with_status(reply, warp::http::StatusCode::NOT_FOUND);
+        return Ok(reply.into_response());
+    }
+
+    Ok(ws
+        .on_upgrade(move |socket| handle_logs_connection(socket, app_name, query.tail, principal))
+        .into_response())
+}
+
+/// Pushes the metrics snapshots over a WebSocket connection.
+///
+/// # Arguments
+///
+/// * `ws` - WebSocket connection
+/// * `metrics_rx` - Receiver for the metrics snapshots
+/// * `apps` - Apps whose containers are sent, every app when empty
+/// * `principal` - The caller authenticated on the upgrade, `None` if it must authenticate
+///   with its first message
+async fn handle_metrics_connection(
+    ws: WebSocket,
+    mut metrics_rx: broadcast::Receiver<MetricsSnapshot>,
+    apps: HashSet<String>,
+    principal: Option<Principal>,
+) {
+    let (mut ws_sender, mut ws_receiver) = ws.split();
+
+    if principal.is_none()
+        && !authenticate_connection(&mut ws_sender, &mut ws_receiver, Role::Viewer).await
+    {
+        return;
+    }
+
+    let _client = ClientGuard::new();
+    let sender_task = tokio::task::spawn(async move {
+        loop {
+            let mut snapshot = match metrics_rx.recv().await {
+                Ok(snapshot) => snapshot,
+                // A slow client skips the snapshots it missed
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            if !apps.is_empty() {
+                snapshot
+                    .containers
+                    .retain(|container| apps.contains(&container.app_name));
+            }
+
+            let message = serde_json::to_string(&snapshot).unwrap_or_default();
+            if ws_sender.send(Message::text(message)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Wait for the client to disconnect
+    while let Some(Ok(message)) = ws_receiver.next().await {
+        if message.is_close() {
+            break;
+        }
+    }
+
+    sender_task.abort();
+}
+
+/// Creates a WebSocket route pushing live container metrics on every collection.
+///
+/// This route listens at the `/ws/metrics` path and requires the `viewer` role. The `app`
+/// query parameter (e.g., `/ws/metrics?app=my-app`) restricts the metrics to given apps.
+///
+/// # Arguments
+///
+/// * `metrics_tx` - Broadcast channel sender of the snapshots
+///
+/// # Returns
+///
+/// A Filter that handles WebSocket upgrade requests and manages connections
+pub fn ws_metrics_route(
+    metrics_tx: MetricsSender,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("ws" / "metrics")
+        .and(ws_principal(Role::Viewer))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::ws())
+        .map(move |principal: Option<Principal>, query: HashMap<String, String>, ws: warp::ws::Ws| {
+            let metrics_rx = metrics_tx.subscribe();
+            let apps = query_apps(&query);
+            ws.on_upgrade(move |socket| {
+                handle_metrics_connection(socket, metrics_rx, apps, principal)
+            })
+        })
+}
+
+/// Pushes the node events of the event bus over a WebSocket connection.
+///
+/// # Arguments
+///
+/// * `ws` - WebSocket connection
+/// * `events` - Receiver of the event bus
+/// * `principal` - The caller authenticated on the upgrade, `None` if it must authenticate
+///   with its first message
+async fn handle_nodes_connection(
+    ws: WebSocket,
+    mut events: broadcast::Receiver<Event>,
+    principal: Option<Principal>,
 ) {
-    let status_update = DeploymentStatus {
+    let (mut ws_sender, mut ws_receiver) = ws.split();
+
+    if principal.is_none()
+        && !authenticate_connection(&mut ws_sender, &mut ws_receiver, Role::Viewer).await
+    {
+        return;
+    }
+
+    let _client = ClientGuard::new();
+    let sender_task = tokio::task::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            if !event.is_node_event() {
+                continue;
+            }
+
+            let message = serde_json::to_string(&event).unwrap_or_default();
+            if ws_sender.send(Message::text(message)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Wait for the client to disconnect
+    while let Some(Ok(message)) = ws_receiver.next().await {
+        if message.is_close() {
+            break;
+        }
+    }
+
+    sender_task.abort();
+}
+
+/// Creates a WebSocket route pushing the changes of the Swarm nodes.
+///
+/// This route listens at the `/ws/nodes` path and requires the `viewer` role. Node
+/// availability changes and the progress of drains are sent as events tagged by `type`.
+///
+/// # Returns
+///
+/// A Filter that handles WebSocket upgrade requests and manages connections
+pub fn ws_nodes_route() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::path!("ws" / "nodes")
+        .and(ws_principal(Role::Viewer))
+        .and(warp::ws())
+        .map(|principal: Option<Principal>, ws: warp::ws::Ws| {
+            let events = subscribe();
+            ws.on_upgrade(move |socket| handle_nodes_connection(socket, events, principal))
+        })
+}
+
+/// Publishes a deployment status update on the event bus.
+///
+/// The update is also recorded on the running deployment of the app, whose ID is included
+/// in the message. `run_status_broadcaster` forwards it to the WebSocket clients. The update
+/// is logged at the `debug` level, with the ID of the deployment.
+///
+/// # Arguments
+///
+/// * `app_name` - Name of the application being deployed
+/// * `event` - What happened
+pub fn send_deployment_status(app_name: &str, event: DeploymentEvent) {
+    let status = event.status();
+    let step = event.step();
+    record_status(app_name, status, &step);
+    let deployment_id = active_deployment_id(app_name);
+    debug!(
+        app_name,
+        status,
+        step = %step,
+        deployment_id = deployment_id.as_deref(),
+        "deployment status"
+    );
+
+    publish(Event::DeploymentStatus(DeploymentStatus {
+        version: EVENT_SCHEMA_VERSION,
         app_name: app_name.to_string(),
         status: status.to_string(),
-        step: step.to_string(),
+        step,
         timestamp: chrono::Utc::now(),
-        app_deployed
-    };
+        app_deployed: event.details(),
+        event,
+        deployment_id,
+    }));
+}
 
-    if let Err(e) = sender.send(status_update) {
-        eprintln!("Failed to send status update: {}", e);
-    }
+/// Forwards the deployment status updates of the event bus to the WebSocket clients.
+///
+/// Each update is kept to be replayed to the connections opened later, then sent through
+/// the broadcast channel read by `/ws`, the GraphQL subscriptions and the gRPC API.
+///
+/// # Arguments
+///
+/// * `sender` - Broadcast channel sender
+pub async fn run_status_broadcaster(sender: StatusSender) {
+    consume_events("status broadcaster", |event| {
+        if let Event::DeploymentStatus(status) = event {
+            remember_status(&status);
+            if let Err(e) = sender.send(status) {
+                error!("Failed to send status update: {}", e);
+            }
+        }
+        async {}
+    })
+    .await
 }